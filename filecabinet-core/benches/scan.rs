@@ -0,0 +1,75 @@
+//! What opening a cabinet takes, over a made up one of 10k documents. Opening a cabinet
+//! that big has to stay under a second, `open` is all of it but the GUI.
+use criterion::{criterion_group, criterion_main, Criterion};
+use filecabinet_core::catalog::Catalog;
+use filecabinet_core::utils::{self, OptDoc};
+use std::fs;
+use std::path::Path;
+use tempdir::TempDir;
+
+const DOCUMENTS: usize = 10_000;
+
+/// A cabinet of small documents, most of them named already, each in the catalog.
+fn cabinet() -> TempDir {
+    let dir = TempDir::new("scan").unwrap();
+    let mut catalog = Catalog::default();
+    for i in 0..DOCUMENTS {
+        let filename = match i % 10 {
+            0 => format!("scan{:05}.pdf", i),
+            _ => format!(
+                "2021-{:02}-{:02}_Bank{}_Statement_{}.pdf",
+                i % 12 + 1,
+                i % 28 + 1,
+                i % 50,
+                i
+            ),
+        };
+        let content = format!("document {}\n", i).repeat(100);
+        fs::write(dir.path().join(&filename), &content).unwrap();
+        let entry = catalog.entry(&filename);
+        entry.sha256 = Some(utils::sha256_bytes(content.as_bytes()));
+        entry.tags = vec!["bank".to_string()];
+    }
+    catalog.save(dir.path()).unwrap();
+    dir
+}
+
+/// What the app reads of each document when a cabinet opens.
+fn open(dir: &Path) -> usize {
+    let catalog = Catalog::load(dir);
+    let mut file_path = dir.to_path_buf();
+    let opened = utils::list_files(dir)
+        .iter()
+        .filter(|filename| {
+            file_path.push(filename);
+            let doc = OptDoc::new(&file_path);
+            let read = fs::metadata(&file_path).is_ok()
+                && (doc.is_normalized(&file_path) || catalog.get(filename).is_some());
+            file_path.pop();
+            read
+        })
+        .count();
+    opened + catalog.missing(dir).len()
+}
+
+fn scan(c: &mut Criterion) {
+    let dir = cabinet();
+    let dir = dir.path();
+    let mut group = c.benchmark_group("cabinet");
+    group.sample_size(10);
+    group.bench_function("list_files", |b| b.iter(|| utils::list_files(dir)));
+    group.bench_function("catalog_load", |b| b.iter(|| Catalog::load(dir)));
+    group.bench_function("sha256", |b| {
+        b.iter(|| {
+            utils::list_files(dir)
+                .iter()
+                .filter_map(|filename| utils::sha256(&dir.join(filename)))
+                .count()
+        })
+    });
+    group.bench_function("open", |b| b.iter(|| open(dir)));
+    group.finish();
+}
+
+criterion_group!(benches, scan);
+criterion_main!(benches);
@@ -0,0 +1,36 @@
+#![no_main]
+use filecabinet_core::utils::{
+    check_word, extension, is_normalized, parse_date, parse_page, to_camelcase, OptDoc,
+};
+use libfuzzer_sys::fuzz_target;
+use std::path::Path;
+
+fn normalize(filename: &Path) -> Option<String> {
+    OptDoc::new(filename).normalized(&extension(filename))
+}
+
+fuzz_target!(|data: &[u8]| {
+    #[cfg(unix)]
+    {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        let filename = Path::new(OsStr::from_bytes(data));
+        OptDoc::new(filename);
+        is_normalized(filename);
+    }
+    let text = String::from_utf8_lossy(data);
+    if let Some(normalized) = normalize(Path::new(text.as_ref())) {
+        assert_eq!(normalize(Path::new(&normalized)), Some(normalized));
+    }
+    if let Some(date) = parse_date(&text.as_ref()) {
+        assert_eq!(parse_date(&date.as_str()), Some(date.clone()));
+    }
+    if let Some(page) = parse_page(&text.as_ref()) {
+        assert_eq!(parse_page(&page.as_str()), Some(page.clone()));
+    }
+    let word = to_camelcase(&text);
+    assert_eq!(to_camelcase(&word), word);
+    if let Ok(word) = check_word(&text) {
+        assert_eq!(check_word(&word), Ok(word));
+    }
+});
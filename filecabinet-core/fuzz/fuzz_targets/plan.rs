@@ -0,0 +1,43 @@
+#![no_main]
+use filecabinet_core::renames::{plan, Rename};
+use libfuzzer_sys::fuzz_target;
+use std::collections::HashSet;
+use std::path::Path;
+use tempdir::TempDir;
+
+lazy_static::lazy_static! {
+    // A few documents to rename, the plans are only checked, never applied.
+    static ref CABINET: TempDir = {
+        let dir = TempDir::new("fuzz").unwrap();
+        for filename in &["a.pdf", "b.pdf", "c.pdf"] {
+            std::fs::write(dir.path().join(filename), filename).unwrap();
+        }
+        dir
+    };
+}
+
+// Each line is a rename, `from` and `to` separated by a tab.
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    let renames: Vec<Rename> = text
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(from, to)| Rename {
+            from: from.to_string(),
+            to: to.to_string(),
+        })
+        .collect();
+    let plan = plan(CABINET.path(), renames);
+    if plan.is_valid() {
+        let mut sources = HashSet::new();
+        let mut targets = HashSet::new();
+        for rename in &plan.renames {
+            for filename in [&rename.from, &rename.to] {
+                let name = Path::new(filename).file_name();
+                assert_eq!(name.and_then(|name| name.to_str()), Some(filename.as_str()));
+            }
+            assert!(sources.insert(&rename.from));
+            assert!(targets.insert(&rename.to));
+        }
+    }
+});
@@ -0,0 +1,125 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A monetary amount in hundredths of the currency unit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Amount {
+    pub cents: i64,
+    pub currency: String,
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Amounts under one unit have no sign in the units, e.g. -0.50.
+        let sign = if self.cents < 0 { "-" } else { "" };
+        let cents = self.cents.unsigned_abs();
+        write!(
+            f,
+            "{}{}.{:02} {}",
+            sign,
+            cents / 100,
+            cents % 100,
+            self.currency
+        )
+    }
+}
+
+lazy_static! {
+    static ref RE_AMOUNT: Regex = Regex::new(
+        r"(?P<pre>[$€£¥]|\b(?:USD|EUR|GBP|CHF|CAD|AUD|JPY)\b)?\s?(?P<num>\d{1,3}(?:[.,' ]\d{3})*[.,]\d{2}|\d+[.,]\d{2})\b\s?(?P<post>[$€£¥]|\b(?:USD|EUR|GBP|CHF|CAD|AUD|JPY)\b)?"
+    )
+    .unwrap();
+    static ref RE_TOTAL_LINE: Regex =
+        Regex::new(r"(?i)total|amount due|balance|amount paid|summe|betrag").unwrap();
+}
+
+fn currency_code(symbol: &str) -> String {
+    match symbol {
+        "$" => "USD",
+        "€" => "EUR",
+        "£" => "GBP",
+        "¥" => "JPY",
+        code => code,
+    }
+    .to_string()
+}
+
+/// Finds every amount written with a currency symbol or code and exactly two decimals.
+pub fn parse_amounts(text: &str) -> Vec<Amount> {
+    RE_AMOUNT
+        .captures_iter(text)
+        .filter_map(|c| {
+            let currency = c.name("pre").or_else(|| c.name("post"))?.as_str();
+            // The decimal part is always two digits, so the digits alone are the cents.
+            let digits: String = c["num"].chars().filter(char::is_ascii_digit).collect();
+            Some(Amount {
+                cents: digits.parse().ok()?,
+                currency: currency_code(currency),
+            })
+        })
+        .collect()
+}
+
+/// Picks the amount a document is about: the largest one on a "total"-like line,
+/// falling back to the largest amount anywhere in the text.
+pub fn document_amount(text: &str) -> Option<Amount> {
+    let largest = |amounts: Vec<Amount>| amounts.into_iter().max_by_key(|a| a.cents);
+    let on_total_lines: Vec<Amount> = text
+        .lines()
+        .filter(|line| RE_TOTAL_LINE.is_match(line))
+        .flat_map(parse_amounts)
+        .collect();
+    largest(on_total_lines).or_else(|| largest(parse_amounts(text)))
+}
+
+/// Sums amounts per currency.
+pub fn totals<'a>(amounts: impl Iterator<Item = &'a Amount>) -> Vec<Amount> {
+    let mut sums: BTreeMap<&str, i64> = BTreeMap::new();
+    for amount in amounts {
+        *sums.entry(&amount.currency).or_insert(0) += amount.cents;
+    }
+    sums.into_iter()
+        .map(|(currency, cents)| Amount {
+            cents,
+            currency: currency.to_string(),
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_amounts() {
+    let usd = |cents| Amount {
+        cents,
+        currency: "USD".to_string(),
+    };
+    let eur = |cents| Amount {
+        cents,
+        currency: "EUR".to_string(),
+    };
+    assert_eq!(parse_amounts("Total: $1,234.56"), vec![usd(123456)]);
+    assert_eq!(parse_amounts("Summe 1.234,56 €"), vec![eur(123456)]);
+    assert_eq!(parse_amounts("EUR 12,00"), vec![eur(1200)]);
+    assert_eq!(parse_amounts("Account 1234.56 on 2021-03-04"), vec![]);
+}
+
+#[test]
+fn test_document_amount() {
+    let text = "Consultation $80.00\nLab work $120.00\nAmount due $45.00\n";
+    assert_eq!(document_amount(text).unwrap().cents, 4500);
+    assert_eq!(document_amount("Fee $5.00\nFee $7.50").unwrap().cents, 750);
+    assert_eq!(document_amount("nothing here"), None);
+}
+
+#[test]
+fn test_display() {
+    let usd = |cents| Amount {
+        cents,
+        currency: "USD".to_string(),
+    };
+    assert_eq!(usd(12345).to_string(), "123.45 USD");
+    assert_eq!(usd(-12345).to_string(), "-123.45 USD");
+    assert_eq!(usd(-50).to_string(), "-0.50 USD");
+    assert_eq!(usd(7).to_string(), "0.07 USD");
+}
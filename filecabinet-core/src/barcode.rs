@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+/// A decoded QR code or barcode, e.g. an invoice reference or a tracking number.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Barcode {
+    pub symbology: String,
+    pub data: String,
+}
+
+/// Decodes the codes on a scan with `zbarimg`, empty if it isn't installed or finds nothing.
+pub fn detect(path: &Path) -> Vec<Barcode> {
+    Command::new("zbarimg")
+        .arg("--quiet")
+        .arg(path)
+        .output()
+        .map(|output| parse_zbarimg(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or_default()
+}
+
+/// Parses `zbarimg` output, one `SYMBOLOGY:data` per line.
+fn parse_zbarimg(output: &str) -> Vec<Barcode> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, ':');
+            let symbology = parts.next()?.trim();
+            let data = parts.next()?.trim();
+            if symbology.is_empty() || data.is_empty() {
+                return None;
+            }
+            Some(Barcode {
+                symbology: symbology.to_string(),
+                data: data.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_zbarimg() {
+    let codes = parse_zbarimg("QR-Code:https://pay.example/INV-42\nEAN-13:4006381333931\n\n");
+    assert_eq!(codes.len(), 2);
+    assert_eq!(codes[0].symbology, "QR-Code");
+    assert_eq!(codes[0].data, "https://pay.example/INV-42");
+    assert_eq!(codes[1].data, "4006381333931");
+}
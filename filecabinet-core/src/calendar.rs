@@ -0,0 +1,265 @@
+use crate::utils::OptDoc;
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+
+pub const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Number of documents per month, keyed by institution and year.
+pub type MonthCounts = BTreeMap<(String, i32), [usize; 12]>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Frequency {
+    #[default]
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl Frequency {
+    pub fn name(self) -> &'static str {
+        match self {
+            Frequency::Monthly => "monthly",
+            Frequency::Quarterly => "quarterly",
+            Frequency::Yearly => "yearly",
+        }
+    }
+
+    /// The frequency after this one, to cycle through them with a single button.
+    pub fn next(self) -> Frequency {
+        match self {
+            Frequency::Monthly => Frequency::Quarterly,
+            Frequency::Quarterly => Frequency::Yearly,
+            Frequency::Yearly => Frequency::Monthly,
+        }
+    }
+
+    /// The period `year` and `month` (1 to 12) fall in: `2023-07`, `2023-Q3` or `2023`.
+    fn period(self, year: i32, month: u32) -> String {
+        match self {
+            Frequency::Monthly => format!("{}-{:02}", year, month),
+            Frequency::Quarterly => format!("{}-Q{}", year, (month - 1) / 3 + 1),
+            Frequency::Yearly => year.to_string(),
+        }
+    }
+}
+
+/// A document an institution sends every period, like a monthly statement or a yearly
+/// tax form.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Expected {
+    /// Found in the title of the documents, ignoring case.
+    pub title: String,
+    pub frequency: Frequency,
+    /// The first month it is expected for, `YYYY-MM`.
+    pub since: String,
+}
+
+/// A period an expected document wasn't filed for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Missing {
+    pub institution: String,
+    pub title: String,
+    pub period: String,
+}
+
+impl fmt::Display for Missing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} missing for {}",
+            self.institution, self.title, self.period
+        )
+    }
+}
+
+/// Periods since they are expected that none of `paths` covers, up to the one before
+/// `today`'s since the current one may still arrive. Keyed by institution.
+pub fn missing<P: AsRef<Path>>(
+    paths: &[P],
+    expected: &BTreeMap<String, Vec<Expected>>,
+    today: NaiveDate,
+) -> Vec<Missing> {
+    let docs: Vec<OptDoc> = paths.iter().map(OptDoc::new).collect();
+    let mut missing = Vec::new();
+    for (institution, expected) in expected {
+        for expected in expected {
+            let title = expected.title.trim().to_lowercase();
+            let since =
+                NaiveDate::parse_from_str(&format!("{}-01", expected.since.trim()), "%Y-%m-%d");
+            let (Ok(since), false) = (since, title.is_empty()) else {
+                continue;
+            };
+            let filed: BTreeSet<String> = docs
+                .iter()
+                .filter(|doc| doc.institution.as_deref() == Some(institution.as_str()))
+                .filter(|doc| {
+                    doc.name
+                        .as_deref()
+                        .is_some_and(|name| name.to_lowercase().contains(&title))
+                })
+                .filter_map(OptDoc::day)
+                .map(|date| expected.frequency.period(date.year(), date.month()))
+                .collect();
+            let mut periods: Vec<String> = Vec::new();
+            let (mut year, mut month) = (since.year(), since.month());
+            while (year, month) <= (today.year(), today.month()) {
+                let period = expected.frequency.period(year, month);
+                if periods.last() != Some(&period) {
+                    periods.push(period);
+                }
+                (year, month) = if month == 12 {
+                    (year + 1, 1)
+                } else {
+                    (year, month + 1)
+                };
+            }
+            // The current period isn't over yet.
+            periods.pop();
+            missing.extend(
+                periods
+                    .into_iter()
+                    .filter(|period| !filed.contains(period))
+                    .map(|period| Missing {
+                        institution: institution.clone(),
+                        title: expected.title.trim().to_string(),
+                        period,
+                    }),
+            );
+        }
+    }
+    missing
+}
+
+/// Buckets documents by the date in their filename. Files without a parseable
+/// filename are left out rather than plotted on a made-up date.
+pub fn month_counts<P: AsRef<Path>>(paths: &[P]) -> MonthCounts {
+    let mut counts = MonthCounts::new();
+    for path in paths {
+        let doc = OptDoc::new(path);
+        if let (Some(date), Some(institution)) = (doc.day(), doc.institution) {
+            let months = counts.entry((institution, date.year())).or_insert([0; 12]);
+            months[date.month0() as usize] += 1;
+        }
+    }
+    counts
+}
+
+/// The first day of the month `months` after the month of `date`, or before it when
+/// negative.
+pub fn add_months(date: NaiveDate, months: i32) -> NaiveDate {
+    let month0 = date.year() * 12 + date.month0() as i32 + months;
+    NaiveDate::from_ymd_opt(month0.div_euclid(12), month0.rem_euclid(12) as u32 + 1, 1)
+        .unwrap_or(date)
+}
+
+/// The days of the month of `date` laid out as weeks from Monday to Sunday, for a
+/// calendar. Days of the months before and after are `None`.
+pub fn weeks(date: NaiveDate) -> Vec<[Option<NaiveDate>; 7]> {
+    let next = add_months(date, 1);
+    let mut weeks = Vec::new();
+    let mut week = [None; 7];
+    let mut day = date.with_day(1);
+    while let Some(today) = day.filter(|day| *day < next) {
+        let weekday = today.weekday().num_days_from_monday() as usize;
+        week[weekday] = Some(today);
+        if weekday == 6 {
+            weeks.push(week);
+            week = [None; 7];
+        }
+        day = today.succ_opt();
+    }
+    if week.iter().any(Option::is_some) {
+        weeks.push(week);
+    }
+    weeks
+}
+
+#[test]
+fn test_month_counts() {
+    let counts = month_counts(&[
+        "/docs/2021-01-05_Chase_Statement_1.pdf",
+        "/docs/2021-03-05_Chase_Statement_1.pdf",
+        "/docs/2021-03-05_Chase_Statement_2.pdf",
+        "/docs/2020-12-01_Pge_Bill_1.pdf",
+        "/docs/scan.pdf",
+    ]);
+    assert_eq!(counts.len(), 2);
+    let chase = counts[&("Chase".to_string(), 2021)];
+    assert_eq!(chase[0], 1);
+    assert_eq!(chase[1], 0);
+    assert_eq!(chase[2], 2);
+    assert_eq!(counts[&("Pge".to_string(), 2020)][11], 1);
+}
+
+#[test]
+fn test_missing() {
+    let paths = [
+        "/docs/2023-05-03_Chase_Statement_1.pdf",
+        "/docs/2023-06-03_Chase_MonthlyStatement_1.pdf",
+        "/docs/2023-08-03_Chase_Statement_1.pdf",
+        "/docs/2023-07-15_Chase_Letter_1.pdf",
+        "/docs/2022-02-01_Irs_W2_1.pdf",
+    ];
+    let expected = BTreeMap::from([
+        (
+            "Chase".to_string(),
+            vec![Expected {
+                title: "statement".to_string(),
+                frequency: Frequency::Monthly,
+                since: "2023-05".to_string(),
+            }],
+        ),
+        (
+            "Irs".to_string(),
+            vec![Expected {
+                title: "W2".to_string(),
+                frequency: Frequency::Yearly,
+                since: "2021-01".to_string(),
+            }],
+        ),
+    ]);
+    let today = NaiveDate::from_ymd_opt(2023, 10, 2).unwrap();
+    let missing: Vec<String> = missing(&paths, &expected, today)
+        .iter()
+        .map(|m| m.to_string())
+        .collect();
+    assert_eq!(
+        missing,
+        vec![
+            "Chase statement missing for 2023-07",
+            "Chase statement missing for 2023-09",
+            "Irs W2 missing for 2021",
+        ]
+    );
+    assert_eq!(Frequency::Quarterly.period(2023, 7), "2023-Q3");
+}
+
+#[test]
+fn test_weeks() {
+    let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    assert_eq!(add_months(day(2023, 1, 31), 1), day(2023, 2, 1));
+    assert_eq!(add_months(day(2023, 1, 15), -1), day(2022, 12, 1));
+    assert_eq!(add_months(day(2023, 11, 2), 14), day(2025, 1, 1));
+
+    // February 2021 starts on a Monday and fills four weeks exactly.
+    let february = weeks(day(2021, 2, 20));
+    assert_eq!(february.len(), 4);
+    assert_eq!(february[0][0], Some(day(2021, 2, 1)));
+    assert_eq!(february[3][6], Some(day(2021, 2, 28)));
+
+    // October 2023 starts on a Sunday.
+    let october = weeks(day(2023, 10, 1));
+    assert_eq!(october.len(), 6);
+    assert_eq!(
+        october[0],
+        [None, None, None, None, None, None, Some(day(2023, 10, 1))]
+    );
+    assert_eq!(october[5][1], Some(day(2023, 10, 31)));
+    assert_eq!(october[5][2], None);
+}
@@ -0,0 +1,618 @@
+use crate::amount::Amount;
+use crate::barcode::Barcode;
+use crate::calendar::Expected;
+use crate::events::{self, Event};
+use crate::institutions::Contact;
+use crate::metadata::Metadata;
+use crate::{checklists, locks, search, similarity, utils, versions};
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+pub const VIEWED_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// How many searches are remembered besides the pinned ones.
+const RECENT_SEARCHES: usize = 20;
+
+/// Metadata about the documents of a cabinet that can't be stored in their filenames.
+/// Lives in `<cabinet>/.filecabinet/catalog.json`, keyed by filename.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Catalog {
+    #[serde(default)]
+    pub entries: BTreeMap<String, Entry>,
+    /// Queries of the search box, most recently used first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub searches: Vec<SavedSearch>,
+    /// Contacts keyed by the institution name of the filenames.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub institutions: BTreeMap<String, Contact>,
+    /// Documents the institutions send every period, keyed like `institutions`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub expected: BTreeMap<String, Vec<Expected>>,
+    /// The people documents can belong to, for cabinets a household shares.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub owners: Vec<Owner>,
+}
+
+/// Someone documents of the cabinet belong to, e.g. a spouse, see `Entry::owner`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Owner {
+    pub name: String,
+    pub color: Label,
+}
+
+/// A query that was searched for, offered again as a suggestion.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub query: String,
+    /// How often it was searched for.
+    pub uses: u32,
+    /// Pinned searches are suggested first and never forgotten.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Whether the document went through `index::run` already.
+    #[serde(default)]
+    pub indexed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Amount>,
+    /// Detected language of the text, as the name of its Tesseract model (`eng`, `deu`...).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub codes: Vec<Barcode>,
+    /// Hex encoded SHA-256 of the file, to find it again after it was moved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Perceptual hash of the first page, see `similarity::dhash`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dhash: Option<u64>,
+    /// Starred by the user, listed by the Favorites filter.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub favorite: bool,
+    /// When the document was last previewed, `%Y-%m-%d %H:%M:%S` in UTC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub viewed: Option<String>,
+    /// When the document was last exported in a bundle or packet, same format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exported: Option<String>,
+    /// Title, author and creation date embedded in a PDF.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+    /// Sealed with a passphrase of its own instead of the vault's, see `vault::seal`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub own_passphrase: bool,
+    /// Where the document was moved to in cold storage, see `archive::archive_document`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived: Option<String>,
+    /// Sorted, see `tags::edit`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<Label>,
+    /// Values of the custom fields of the cabinet by field name, see `fields::FieldDef`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+    /// Name of the `Owner` the document belongs to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+}
+
+/// Color a document can be marked with, shown as a stripe on its row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Label {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+    Gray,
+}
+
+impl Label {
+    pub const ALL: [Label; 7] = [
+        Label::Red,
+        Label::Orange,
+        Label::Yellow,
+        Label::Green,
+        Label::Blue,
+        Label::Purple,
+        Label::Gray,
+    ];
+
+    pub fn color(self) -> [f32; 3] {
+        match self {
+            Label::Red => [0.93, 0.3, 0.3],
+            Label::Orange => [0.96, 0.6, 0.2],
+            Label::Yellow => [0.95, 0.82, 0.25],
+            Label::Green => [0.35, 0.75, 0.4],
+            Label::Blue => [0.3, 0.55, 0.9],
+            Label::Purple => [0.65, 0.4, 0.85],
+            Label::Gray => [0.6, 0.6, 0.6],
+        }
+    }
+
+    /// The color after this one, to cycle through them with a single button.
+    pub fn next(self) -> Label {
+        let i = Label::ALL
+            .iter()
+            .position(|l| *l == self)
+            .unwrap_or_default();
+        Label::ALL[(i + 1) % Label::ALL.len()]
+    }
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum CatalogError {
+    DirectoryError,
+    FormatError,
+    WriteError,
+    /// Someone else sharing the cabinet kept writing the catalog.
+    LockError,
+}
+
+impl Catalog {
+    pub fn path(dir: &Path) -> PathBuf {
+        dir.join(".filecabinet").join("catalog.json")
+    }
+
+    /// Loads the catalog of the cabinet at `dir`, empty if there is none yet.
+    pub fn load(dir: &Path) -> Catalog {
+        fs::read_to_string(Self::path(dir))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), CatalogError> {
+        let json = serde_json::to_string_pretty(self).map_err(|_| CatalogError::FormatError)?;
+        let path = Self::path(dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|_| CatalogError::DirectoryError)?;
+        }
+        // Write to a temp file and move it in place so a crash never leaves half a catalog.
+        AtomicFile::new(path, OverwriteBehavior::AllowOverwrite)
+            .write(|f| f.write_all(json.as_bytes()))
+            .map_err(|_| CatalogError::WriteError)
+    }
+
+    /// Applies `change` to the catalog of the cabinet at `dir` and saves it, holding the
+    /// catalog lock throughout so changes made meanwhile from another computer sharing the
+    /// cabinet aren't overwritten.
+    pub fn update<T>(
+        dir: &Path,
+        change: impl FnOnce(&mut Catalog) -> T,
+    ) -> Result<T, CatalogError> {
+        let _lock = locks::lock_catalog(dir).map_err(|_| CatalogError::LockError)?;
+        let mut catalog = Catalog::load(dir);
+        let changed = change(&mut catalog);
+        catalog.save(dir)?;
+        Ok(changed)
+    }
+
+    pub fn get(&self, filename: &str) -> Option<&Entry> {
+        self.entries.get(filename)
+    }
+
+    pub fn entry(&mut self, filename: &str) -> &mut Entry {
+        self.entries.entry(filename.to_string()).or_default()
+    }
+
+    /// Carries the metadata of a renamed file over to its new name.
+    pub fn rename(&mut self, old: &str, new: &str) -> bool {
+        match self.entries.remove(old) {
+            Some(entry) => {
+                self.entries.insert(new.to_string(), entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove(&mut self, filename: &str) -> bool {
+        self.entries.remove(filename).is_some()
+    }
+
+    /// Pairs of documents that look alike but aren't the same file, e.g. a page scanned
+    /// twice, with the distance of their perceptual hashes. Closest pairs first.
+    pub fn duplicate_candidates(&self) -> Vec<(String, String, u32)> {
+        let hashed: Vec<(&String, &Entry, u64)> = self
+            .entries
+            .iter()
+            .filter_map(|(filename, entry)| Some((filename, entry, entry.dhash?)))
+            .collect();
+        let mut pairs = Vec::new();
+        for (i, (a, entry_a, hash_a)) in hashed.iter().enumerate() {
+            for (b, entry_b, hash_b) in hashed.iter().skip(i + 1) {
+                let distance = similarity::distance(*hash_a, *hash_b);
+                let same_file = entry_a.sha256.is_some() && entry_a.sha256 == entry_b.sha256;
+                if distance <= similarity::DUPLICATE_DISTANCE && !same_file {
+                    pairs.push((a.to_string(), b.to_string(), distance));
+                }
+            }
+        }
+        pairs.sort_by_key(|(_, _, distance)| *distance);
+        pairs
+    }
+
+    /// Moves `query` to the front of the searches, counting its use. Only the
+    /// `RECENT_SEARCHES` most recent unpinned ones are kept.
+    pub fn add_search(&mut self, query: &str) {
+        let query = query.trim();
+        let mut search = match self.searches.iter().position(|s| s.query == query) {
+            Some(i) => self.searches.remove(i),
+            None => SavedSearch {
+                query: query.to_string(),
+                ..Default::default()
+            },
+        };
+        search.uses += 1;
+        self.searches.insert(0, search);
+        let mut unpinned = 0;
+        self.searches.retain(|search| {
+            unpinned += usize::from(!search.pinned);
+            search.pinned || unpinned <= RECENT_SEARCHES
+        });
+    }
+
+    /// Filenames with an entry that are no longer in the cabinet at `dir`, archived ones
+    /// aside.
+    pub fn missing(&self, dir: &Path) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|(filename, entry)| entry.archived.is_none() && !dir.join(filename).exists())
+            .map(|(filename, _)| filename.clone())
+            .collect()
+    }
+
+    /// Filenames of the documents moved to cold storage.
+    pub fn archived(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| entry.archived.is_some())
+            .map(|(filename, _)| filename.clone())
+            .collect()
+    }
+
+    /// Moves the entry of the missing `filename` to a file of the cabinet with the same
+    /// contents that has no metadata of its own yet. Returns the filename it moved to.
+    pub fn relink(&mut self, dir: &Path, filename: &str) -> Option<String> {
+        let sha256 = self.get(filename)?.sha256.clone()?;
+        let found = utils::list_files(dir).into_iter().find(|candidate| {
+            self.get(candidate).is_none_or(|e| e.sha256.is_none())
+                && utils::sha256(&dir.join(candidate)).as_ref() == Some(&sha256)
+        })?;
+        self.rename(filename, &found);
+        Some(found)
+    }
+
+    /// Relinks every missing entry that has a moved copy in the cabinet at `dir`, hashing
+    /// each candidate file only once. Returns the `(old, new)` filenames.
+    pub fn relink_missing(&mut self, dir: &Path) -> Vec<(String, String)> {
+        let missing: Vec<(String, String)> = self
+            .missing(dir)
+            .into_iter()
+            .filter_map(|filename| {
+                let sha256 = self.get(&filename)?.sha256.clone()?;
+                Some((filename, sha256))
+            })
+            .collect();
+        if missing.is_empty() {
+            return Vec::new();
+        }
+        let mut candidates: Vec<(String, String)> = utils::list_files(dir)
+            .into_iter()
+            .filter(|candidate| self.get(candidate).is_none_or(|e| e.sha256.is_none()))
+            .filter_map(|candidate| {
+                let sha256 = utils::sha256(&dir.join(&candidate))?;
+                Some((candidate, sha256))
+            })
+            .collect();
+        let mut relinked = Vec::new();
+        for (filename, sha256) in missing {
+            if let Some(i) = candidates.iter().position(|(_, c)| *c == sha256) {
+                let (found, _) = candidates.remove(i);
+                self.rename(&filename, &found);
+                relinked.push((filename, found));
+            }
+        }
+        relinked
+    }
+}
+
+fn split(path: &Path) -> Option<(&Path, String)> {
+    Some((path.parent()?, path.file_name()?.to_str()?.to_string()))
+}
+
+/// Keeps the catalog in step with a file renamed within its cabinet.
+pub fn record_rename(old: &Path, new: &Path) {
+    if let (Some((dir, old)), Some((_, new))) = (split(old), split(new)) {
+        if let Err(error) = Catalog::update(dir, |catalog| catalog.rename(&old, &new)) {
+            warn!(event = "catalog_save_failed", ?error);
+        }
+        checklists::rename(dir, &[(old.clone(), new.clone())]);
+        events::emit(dir, &[Event::Renamed { from: old, to: new }]);
+    }
+    search::rename_text(old, new);
+    versions::rename_versions(old, new);
+}
+
+/// Re-links the entry of the missing file at `path` to its moved copy, see `Catalog::relink`.
+pub fn record_relink(path: &Path) -> Option<String> {
+    let (dir, filename) = split(path)?;
+    let found = match Catalog::update(dir, |catalog| catalog.relink(dir, &filename)) {
+        Ok(found) => found?,
+        Err(error) => {
+            warn!(event = "catalog_save_failed", ?error);
+            return None;
+        }
+    };
+    info!(event = "Relink", old = %filename, new = %found);
+    Some(found)
+}
+
+/// Stars or unstars the file at `path`.
+pub fn record_favorite(path: &Path, favorite: bool) {
+    if let Some((dir, filename)) = split(path) {
+        if let Err(error) =
+            Catalog::update(dir, |catalog| catalog.entry(&filename).favorite = favorite)
+        {
+            warn!(event = "catalog_save_failed", ?error);
+        }
+    }
+}
+
+/// Marks the file at `path` with `label`, or clears its label.
+pub fn record_label(path: &Path, label: Option<Label>) {
+    if let Some((dir, filename)) = split(path) {
+        if let Err(error) = Catalog::update(dir, |catalog| catalog.entry(&filename).label = label) {
+            warn!(event = "catalog_save_failed", ?error);
+        }
+    }
+}
+
+/// Gives the file at `path` to `owner`, or to nobody.
+pub fn record_owner(path: &Path, owner: Option<String>) {
+    if let Some((dir, filename)) = split(path) {
+        if let Err(error) = Catalog::update(dir, |catalog| catalog.entry(&filename).owner = owner) {
+            warn!(event = "catalog_save_failed", ?error);
+        }
+    }
+}
+
+/// Replaces the owners of the cabinet at `dir`. The documents of the owner `moved` from
+/// go to the one it names, or to nobody, e.g. when someone is renamed or removed.
+pub fn record_owners(
+    dir: &Path,
+    owners: Vec<Owner>,
+    moved: Option<(String, Option<String>)>,
+) -> Result<(), CatalogError> {
+    Catalog::update(dir, |catalog| {
+        catalog.owners = owners;
+        if let Some((from, to)) = moved {
+            for entry in catalog.entries.values_mut() {
+                if entry.owner.as_ref() == Some(&from) {
+                    entry.owner = to.clone();
+                }
+            }
+        }
+    })
+}
+
+/// Sets the custom fields of the file at `path`, the ones missing from `fields` are cleared.
+pub fn record_fields(path: &Path, fields: BTreeMap<String, String>) {
+    if let Some((dir, filename)) = split(path) {
+        if let Err(error) = Catalog::update(dir, |catalog| catalog.entry(&filename).fields = fields)
+        {
+            warn!(event = "catalog_save_failed", ?error);
+        }
+    }
+}
+
+/// Records that the file at `path` was just previewed. Returns the time it was recorded.
+pub fn record_view(path: &Path) -> Option<String> {
+    let (dir, filename) = split(path)?;
+    let now = Utc::now().format(VIEWED_FORMAT).to_string();
+    if let Err(error) = Catalog::update(dir, |catalog| {
+        catalog.entry(&filename).viewed = Some(now.clone())
+    }) {
+        warn!(event = "catalog_save_failed", ?error);
+        return None;
+    }
+    Some(now)
+}
+
+/// Records that the files at `paths`, in the cabinet at `dir`, were just exported.
+pub fn record_exports(dir: &Path, paths: &[String]) {
+    let now = Utc::now().format(VIEWED_FORMAT).to_string();
+    if let Err(error) = Catalog::update(dir, |catalog| {
+        for path in paths {
+            if let Some((_, filename)) = split(Path::new(path)) {
+                catalog.entry(&filename).exported = Some(now.clone());
+            }
+        }
+    }) {
+        warn!(event = "catalog_save_failed", ?error);
+    }
+}
+
+/// Updates the content hash of a file the app rewrote, so it can still be relinked, and
+/// drops its perceptual hash for the next index to recompute.
+pub fn record_rewrite(path: &Path) {
+    if let Some((dir, filename)) = split(path) {
+        let sha256 = utils::sha256(path);
+        if let Err(error) = Catalog::update(dir, |catalog| {
+            let entry = catalog.entry(&filename);
+            entry.sha256 = sha256;
+            entry.dhash = None;
+        }) {
+            warn!(event = "catalog_save_failed", ?error);
+        }
+    }
+}
+
+/// Searches to suggest for what was typed so far: the pinned ones, most used first,
+/// then the recent ones. All of them for an empty search box.
+pub fn suggestions<'a>(searches: &'a [SavedSearch], typed: &str) -> Vec<&'a SavedSearch> {
+    let typed = typed.trim().to_lowercase();
+    let mut suggestions: Vec<&SavedSearch> = searches
+        .iter()
+        .filter(|search| search.query.to_lowercase().contains(&typed))
+        .filter(|search| search.query.to_lowercase() != typed)
+        .collect();
+    // Stable, so the recent ones keep their order.
+    suggestions.sort_by_key(|search| {
+        (
+            !search.pinned,
+            Reverse(if search.pinned { search.uses } else { 0 }),
+        )
+    });
+    suggestions
+}
+
+/// Remembers that `query` was searched for in the cabinet at `dir`. Returns the searches.
+pub fn record_search(dir: &Path, query: &str) -> Vec<SavedSearch> {
+    match Catalog::update(dir, |catalog| {
+        catalog.add_search(query);
+        catalog.searches.clone()
+    }) {
+        Ok(searches) => searches,
+        Err(error) => {
+            warn!(event = "catalog_save_failed", ?error);
+            Catalog::load(dir).searches
+        }
+    }
+}
+
+/// Pins or unpins the search for `query`. Returns the searches.
+pub fn record_pin(dir: &Path, query: &str, pinned: bool) -> Vec<SavedSearch> {
+    match Catalog::update(dir, |catalog| {
+        if let Some(search) = catalog.searches.iter_mut().find(|s| s.query == query) {
+            search.pinned = pinned;
+        }
+        catalog.searches.clone()
+    }) {
+        Ok(searches) => searches,
+        Err(error) => {
+            warn!(event = "catalog_save_failed", ?error);
+            Catalog::load(dir).searches
+        }
+    }
+}
+
+/// Drops the catalog entry of a deleted file.
+pub fn record_delete(path: &Path) {
+    if let Some((dir, filename)) = split(path) {
+        if let Err(error) = Catalog::update(dir, |catalog| catalog.remove(&filename)) {
+            warn!(event = "catalog_save_failed", ?error);
+        }
+        events::emit(dir, &[Event::Deleted { filename }]);
+    }
+    search::forget_text(path);
+}
+
+#[test]
+fn test_relink() {
+    let dir = tempdir::TempDir::new("catalog").unwrap();
+    let old = "2021-01-01_Bank_Statement_1.pdf";
+    fs::write(dir.path().join("moved.pdf"), b"statement").unwrap();
+    fs::write(dir.path().join("other.pdf"), b"other").unwrap();
+    let mut catalog = Catalog::default();
+    catalog.entry(old).sha256 = utils::sha256(&dir.path().join("moved.pdf"));
+    assert_eq!(catalog.missing(dir.path()), vec![old.to_string()]);
+    assert_eq!(
+        catalog.relink(dir.path(), old).as_deref(),
+        Some("moved.pdf")
+    );
+    assert!(catalog.missing(dir.path()).is_empty());
+    assert!(catalog.get("moved.pdf").is_some());
+}
+
+#[test]
+fn test_relink_missing() {
+    let dir = tempdir::TempDir::new("catalog").unwrap();
+    fs::write(dir.path().join("a2.pdf"), b"a").unwrap();
+    fs::write(dir.path().join("b2.pdf"), b"b").unwrap();
+    let mut catalog = Catalog::default();
+    catalog.entry("a.pdf").sha256 = utils::sha256(&dir.path().join("a2.pdf"));
+    catalog.entry("b.pdf").sha256 = utils::sha256(&dir.path().join("b2.pdf"));
+    catalog.entry("gone.pdf").sha256 = Some("0".repeat(64));
+    let relinked = catalog.relink_missing(dir.path());
+    assert_eq!(relinked.len(), 2);
+    assert_eq!(catalog.missing(dir.path()), vec!["gone.pdf".to_string()]);
+}
+
+#[test]
+fn test_searches() {
+    let mut catalog = Catalog::default();
+    catalog.add_search("tax");
+    catalog.add_search("institution:Chase");
+    catalog.add_search(" tax ");
+    assert_eq!(catalog.searches[0].query, "tax");
+    assert_eq!(catalog.searches[0].uses, 2);
+    catalog.searches[1].pinned = true;
+    for i in 0..RECENT_SEARCHES {
+        catalog.add_search(&format!("query {}", i));
+    }
+    assert_eq!(catalog.searches.len(), RECENT_SEARCHES + 1);
+    assert!(catalog.searches.iter().all(|s| s.query != "tax"));
+    let all = suggestions(&catalog.searches, "");
+    assert_eq!(all[0].query, "institution:Chase");
+    assert_eq!(all[1].query, format!("query {}", RECENT_SEARCHES - 1));
+    assert_eq!(suggestions(&catalog.searches, "CHASE").len(), 1);
+    assert!(suggestions(&catalog.searches, "institution:chase").is_empty());
+}
+
+#[test]
+fn test_label() {
+    let dir = tempdir::TempDir::new("catalog").unwrap();
+    let path = dir.path().join("a.pdf");
+    record_label(&path, Some(Label::Green));
+    assert_eq!(
+        Catalog::load(dir.path()).get("a.pdf").unwrap().label,
+        Some(Label::Green)
+    );
+    let json = fs::read_to_string(dir.path().join(".filecabinet/catalog.json")).unwrap();
+    assert!(json.contains("\"label\": \"green\""), "{}", json);
+    record_label(&path, None);
+    assert_eq!(Catalog::load(dir.path()).get("a.pdf").unwrap().label, None);
+}
+
+#[test]
+fn test_owners() {
+    let dir = tempdir::TempDir::new("catalog").unwrap();
+    let owner = |name: &str, color: Label| Owner {
+        name: name.to_string(),
+        color,
+    };
+    record_owner(&dir.path().join("a.pdf"), Some("Kid1".to_string()));
+    record_owner(&dir.path().join("b.pdf"), Some("Me".to_string()));
+    record_owners(
+        dir.path(),
+        vec![owner("Me", Label::Blue), owner("Alice", Label::Red.next())],
+        Some(("Kid1".to_string(), Some("Alice".to_string()))),
+    )
+    .unwrap();
+    let catalog = Catalog::load(dir.path());
+    assert_eq!(catalog.owners[1], owner("Alice", Label::Orange));
+    assert_eq!(
+        catalog.get("a.pdf").unwrap().owner.as_deref(),
+        Some("Alice")
+    );
+    assert_eq!(catalog.get("b.pdf").unwrap().owner.as_deref(), Some("Me"));
+    record_owners(dir.path(), vec![], Some(("Me".to_string(), None))).unwrap();
+    assert_eq!(Catalog::load(dir.path()).get("b.pdf").unwrap().owner, None);
+    assert_eq!(Label::Gray.next(), Label::ALL[0]);
+}
@@ -0,0 +1,188 @@
+use crate::catalog::{Catalog, CatalogError};
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Documents to gather for a purpose, e.g. a mortgage application.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Checklist {
+    pub name: String,
+    pub items: Vec<Item>,
+}
+
+/// What a checklist asks for, e.g. 3 bank statements, and the documents given for it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Item {
+    pub description: String,
+    /// How many documents it takes.
+    pub count: usize,
+    /// Filenames within the cabinet.
+    #[serde(default)]
+    pub documents: Vec<String>,
+}
+
+impl Item {
+    pub fn is_complete(&self) -> bool {
+        self.documents.len() >= self.count
+    }
+}
+
+impl Checklist {
+    /// Reads a checklist typed as `Name: 2 payslips, 3 bank statements, ID`, items without
+    /// a number take one document. `None` without a name or items.
+    pub fn parse(text: &str) -> Option<Checklist> {
+        let (name, items) = text.split_once(':')?;
+        let items: Vec<Item> = items
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| {
+                let (count, description) = match item.split_once(char::is_whitespace) {
+                    Some((count, rest)) => match count.parse::<usize>() {
+                        Ok(count) => (count.max(1), rest.trim()),
+                        Err(_) => (1, item),
+                    },
+                    None => (1, item),
+                };
+                Item {
+                    description: description.to_string(),
+                    count,
+                    documents: Vec::new(),
+                }
+            })
+            .collect();
+        let name = name.trim();
+        (!name.is_empty() && !items.is_empty()).then(|| Checklist {
+            name: name.to_string(),
+            items,
+        })
+    }
+
+    /// Documents given and documents asked for.
+    pub fn progress(&self) -> (usize, usize) {
+        self.items.iter().fold((0, 0), |(done, total), item| {
+            (
+                done + item.documents.len().min(item.count),
+                total + item.count,
+            )
+        })
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.items.iter().all(Item::is_complete)
+    }
+
+    /// Every document given, each once.
+    pub fn documents(&self) -> Vec<String> {
+        let mut documents: Vec<String> = Vec::new();
+        for filename in self.items.iter().flat_map(|item| &item.documents) {
+            if !documents.contains(filename) {
+                documents.push(filename.clone());
+            }
+        }
+        documents
+    }
+
+    /// The checklist as text, to go with its documents.
+    pub fn summary(&self) -> String {
+        let (done, total) = self.progress();
+        let mut text = format!("{} ({} of {} documents)\n", self.name, done, total);
+        for item in &self.items {
+            text.push_str(&format!(
+                "\n[{}] {} {}\n",
+                if item.is_complete() { "x" } else { " " },
+                item.count,
+                item.description
+            ));
+            for filename in &item.documents {
+                text.push_str(&format!("    {}\n", filename));
+            }
+        }
+        text
+    }
+}
+
+/// The checklists of a cabinet, in `<cabinet>/.filecabinet/checklists.json`.
+fn checklists_path(dir: &Path) -> PathBuf {
+    Catalog::path(dir).with_file_name("checklists.json")
+}
+
+pub fn load(dir: &Path) -> Vec<Checklist> {
+    fs::read_to_string(checklists_path(dir))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(dir: &Path, checklists: &[Checklist]) -> Result<(), CatalogError> {
+    let path = checklists_path(dir);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|_| CatalogError::DirectoryError)?;
+    }
+    let json = serde_json::to_string_pretty(checklists).map_err(|_| CatalogError::FormatError)?;
+    AtomicFile::new(&path, OverwriteBehavior::AllowOverwrite)
+        .write(|f| f.write_all(json.as_bytes()))
+        .map_err(|_| CatalogError::WriteError)
+}
+
+/// Keeps the checklists of the cabinet at `dir` pointing at documents renamed, given as
+/// `(from, to)` pairs applied together.
+pub fn rename(dir: &Path, renames: &[(String, String)]) {
+    let mut checklists = load(dir);
+    let mut renamed = false;
+    for filename in checklists
+        .iter_mut()
+        .flat_map(|checklist| &mut checklist.items)
+        .flat_map(|item| &mut item.documents)
+    {
+        if let Some((_, to)) = renames.iter().find(|(from, _)| from == filename) {
+            *filename = to.clone();
+            renamed = true;
+        }
+    }
+    if renamed {
+        if let Err(error) = save(dir, &checklists) {
+            warn!(event = "checklists_save_failed", ?error);
+        }
+    }
+}
+
+#[test]
+fn test_checklists() {
+    let mut checklist =
+        Checklist::parse("Mortgage application: 2 payslips, 3 bank statements, ID,").unwrap();
+    assert_eq!(checklist.name, "Mortgage application");
+    assert_eq!(
+        checklist
+            .items
+            .iter()
+            .map(|item| (item.count, item.description.as_str()))
+            .collect::<Vec<_>>(),
+        [(2, "payslips"), (3, "bank statements"), (1, "ID")]
+    );
+    assert!(Checklist::parse("No items:").is_none());
+    assert!(Checklist::parse("no colon").is_none());
+
+    checklist.items[0].documents = vec!["a.pdf".to_string(), "b.pdf".to_string()];
+    checklist.items[2].documents = vec!["a.pdf".to_string()];
+    assert_eq!(checklist.progress(), (3, 6));
+    assert!(checklist.items[0].is_complete() && !checklist.is_complete());
+    assert_eq!(checklist.documents(), ["a.pdf", "b.pdf"]);
+    assert!(checklist
+        .summary()
+        .contains("[x] 2 payslips\n    a.pdf\n    b.pdf\n"));
+
+    let dir = tempdir::TempDir::new("checklists").unwrap();
+    save(dir.path(), &[checklist]).unwrap();
+    // Swapped names stay with their documents.
+    let swap = |from: &str, to: &str| (from.to_string(), to.to_string());
+    rename(
+        dir.path(),
+        &[swap("a.pdf", "b.pdf"), swap("b.pdf", "a.pdf")],
+    );
+    let checklist = &load(dir.path())[0];
+    assert_eq!(checklist.documents(), ["b.pdf", "a.pdf"]);
+}
@@ -0,0 +1,674 @@
+use aes::block_cipher_trait::generic_array::GenericArray;
+use aes::block_cipher_trait::BlockCipher;
+use aes::{Aes128, Aes256};
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use ring::digest::{digest, SHA256, SHA384, SHA512};
+
+/// Pads or truncates passwords of the RC4 based revisions to 32 bytes.
+const PADDING: [u8; 32] = [
+    0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA, 0x01, 0x08,
+    0x2E, 0x2E, 0x00, 0xB6, 0xD0, 0x68, 0x3E, 0x80, 0x2F, 0x0C, 0xA9, 0xFE, 0x64, 0x53, 0x69, 0x7A,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::enum_variant_names)]
+pub enum DecryptError {
+    /// Not encrypted with the standard password based security handler, or with a
+    /// revision of it that isn't supported.
+    UnsupportedError,
+    PasswordError,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cipher {
+    Identity,
+    Rc4,
+    Aes128,
+    Aes256,
+}
+
+/// What is needed to decrypt the objects of a document, from its `Encrypt` dictionary.
+struct Handler {
+    key: Vec<u8>,
+    strings: Cipher,
+    streams: Cipher,
+    encrypt_metadata: bool,
+}
+
+pub fn is_encrypted(document: &Document) -> bool {
+    document.trailer.has(b"Encrypt")
+}
+
+/// Decrypts every string and stream of a document encrypted with the standard security
+/// handler (PDF 1.7 section 7.6.3 and ISO 32000-2 for AES-256), using either its user or
+/// its owner `password`, and drops the encryption from the document.
+pub fn decrypt(document: &mut Document, password: &str) -> Result<(), DecryptError> {
+    let encrypt_id = document
+        .trailer
+        .get(b"Encrypt")
+        .ok()
+        .and_then(|e| e.as_reference().ok());
+    let encrypt = match encrypt_id {
+        Some(id) => document.get_dictionary(id).ok().cloned(),
+        None => document
+            .trailer
+            .get(b"Encrypt")
+            .and_then(Object::as_dict)
+            .ok()
+            .cloned(),
+    }
+    .ok_or(DecryptError::UnsupportedError)?;
+    let id = document
+        .trailer
+        .get(b"ID")
+        .and_then(Object::as_array)
+        .ok()
+        .and_then(|ids| ids.first())
+        .and_then(|id| id.as_str().ok())
+        .map(|id| id.to_vec())
+        .unwrap_or_default();
+    let handler = Handler::new(&encrypt, &id, password.as_bytes())?;
+    for (&object_id, object) in document.objects.iter_mut() {
+        if Some(object_id) == encrypt_id {
+            continue;
+        }
+        handler.decrypt_object(object_id, object);
+    }
+    if let Some(id) = encrypt_id {
+        document.objects.remove(&id);
+    }
+    document.trailer.remove(b"Encrypt");
+    Ok(())
+}
+
+impl Handler {
+    fn new(encrypt: &Dictionary, id: &[u8], password: &[u8]) -> Result<Handler, DecryptError> {
+        let name = |key: &[u8]| encrypt.get(key).and_then(Object::as_name).ok();
+        let int = |key: &[u8]| encrypt.get(key).and_then(Object::as_i64).ok();
+        let bytes = |key: &[u8]| {
+            encrypt
+                .get(key)
+                .and_then(Object::as_str)
+                .map(|s| s.to_vec())
+                .map_err(|_| DecryptError::UnsupportedError)
+        };
+        if name(b"Filter") != Some(b"Standard") {
+            return Err(DecryptError::UnsupportedError);
+        }
+        let version = int(b"V").unwrap_or(0);
+        let revision = int(b"R").ok_or(DecryptError::UnsupportedError)?;
+        let encrypt_metadata = encrypt
+            .get(b"EncryptMetadata")
+            .and_then(|m| match m {
+                Object::Boolean(b) => Ok(*b),
+                _ => Err(lopdf::Error::Type),
+            })
+            .unwrap_or(true);
+        let (strings, streams) = match version {
+            1 | 2 => (Cipher::Rc4, Cipher::Rc4),
+            4 | 5 => {
+                let filter = |key: &[u8]| match name(key) {
+                    None | Some(b"Identity") => Ok(Cipher::Identity),
+                    Some(filter) => crypt_filter(encrypt, filter),
+                };
+                (filter(b"StrF")?, filter(b"StmF")?)
+            }
+            _ => return Err(DecryptError::UnsupportedError),
+        };
+        let (owner, user) = (bytes(b"O")?, bytes(b"U")?);
+        let key = match revision {
+            2..=4 => {
+                let length = match revision {
+                    2 => 5,
+                    // 40 to 128 bits in steps of 8, longer than the MD5 it is cut from
+                    // can't be.
+                    _ => match int(b"Length").unwrap_or(40) {
+                        bits @ 40..=128 if bits % 8 == 0 => bits as usize / 8,
+                        _ => return Err(DecryptError::UnsupportedError),
+                    },
+                };
+                // AESV2 takes a 128 bit file key, AESV3 the 256 bit one of revisions 5 and 6.
+                let ciphers = [strings, streams];
+                if ciphers.contains(&Cipher::Aes256)
+                    || ciphers.contains(&Cipher::Aes128) && length != 16
+                {
+                    return Err(DecryptError::UnsupportedError);
+                }
+                let permissions = int(b"P").ok_or(DecryptError::UnsupportedError)? as i32;
+                let key = |password: &[u8]| {
+                    let key = file_key(
+                        password,
+                        &owner,
+                        permissions,
+                        id,
+                        revision,
+                        length,
+                        encrypt_metadata,
+                    );
+                    Some(key)
+                        .filter(|key| user_hash(key, id, revision) == user[..16.min(user.len())])
+                };
+                key(password)
+                    .or_else(|| key(&owner_to_user(password, &owner, revision, length)))
+                    .ok_or(DecryptError::PasswordError)?
+            }
+            5 | 6 => aes256_file_key(password, &owner, &user, encrypt, revision)?,
+            _ => return Err(DecryptError::UnsupportedError),
+        };
+        Ok(Handler {
+            key,
+            strings,
+            streams,
+            encrypt_metadata,
+        })
+    }
+
+    fn decrypt_object(&self, id: ObjectId, object: &mut Object) {
+        match object {
+            Object::String(bytes, _) => *bytes = self.decrypt_bytes(id, self.strings, bytes),
+            Object::Array(items) => {
+                for item in items {
+                    self.decrypt_object(id, item);
+                }
+            }
+            Object::Dictionary(dict) => self.decrypt_dictionary(id, dict),
+            Object::Stream(stream) => {
+                self.decrypt_dictionary(id, &mut stream.dict);
+                let metadata = stream.dict.type_is(b"Metadata");
+                if !stream.dict.type_is(b"XRef") && (self.encrypt_metadata || !metadata) {
+                    let content = self.decrypt_bytes(id, self.streams, &stream.content);
+                    stream.set_content(content);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn decrypt_dictionary(&self, id: ObjectId, dict: &mut Dictionary) {
+        for (_, value) in dict.iter_mut() {
+            self.decrypt_object(id, value);
+        }
+    }
+
+    fn decrypt_bytes(
+        &self,
+        (number, generation): ObjectId,
+        cipher: Cipher,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let object_key = || {
+            let mut input = self.key.clone();
+            input.extend_from_slice(&number.to_le_bytes()[..3]);
+            input.extend_from_slice(&generation.to_le_bytes()[..2]);
+            if cipher == Cipher::Aes128 {
+                input.extend_from_slice(b"sAlT");
+            }
+            md5::compute(&input)[..(self.key.len() + 5).min(16)].to_vec()
+        };
+        match cipher {
+            Cipher::Identity => data.to_vec(),
+            Cipher::Rc4 => rc4(&object_key(), data),
+            Cipher::Aes128 | Cipher::Aes256 if data.len() < 16 => Vec::new(),
+            Cipher::Aes128 => unpad(aes_cbc_decrypt(&object_key(), &data[..16], &data[16..])),
+            Cipher::Aes256 => unpad(aes_cbc_decrypt(&self.key, &data[..16], &data[16..])),
+        }
+    }
+}
+
+fn crypt_filter(encrypt: &Dictionary, filter: &[u8]) -> Result<Cipher, DecryptError> {
+    let method = encrypt
+        .get(b"CF")
+        .and_then(Object::as_dict)
+        .and_then(|filters| filters.get(filter))
+        .and_then(Object::as_dict)
+        .and_then(|filter| filter.get(b"CFM"))
+        .and_then(Object::as_name)
+        .map_err(|_| DecryptError::UnsupportedError)?;
+    match method {
+        b"None" => Ok(Cipher::Identity),
+        b"V2" => Ok(Cipher::Rc4),
+        b"AESV2" => Ok(Cipher::Aes128),
+        b"AESV3" => Ok(Cipher::Aes256),
+        _ => Err(DecryptError::UnsupportedError),
+    }
+}
+
+fn padded(password: &[u8]) -> Vec<u8> {
+    password
+        .iter()
+        .chain(PADDING.iter())
+        .take(32)
+        .copied()
+        .collect()
+}
+
+/// Algorithm 2: the file key of revisions 2 to 4.
+fn file_key(
+    password: &[u8],
+    owner: &[u8],
+    permissions: i32,
+    id: &[u8],
+    revision: i64,
+    length: usize,
+    encrypt_metadata: bool,
+) -> Vec<u8> {
+    let mut input = padded(password);
+    input.extend_from_slice(owner);
+    input.extend_from_slice(&permissions.to_le_bytes());
+    input.extend_from_slice(id);
+    if revision >= 4 && !encrypt_metadata {
+        input.extend_from_slice(&[0xFF; 4]);
+    }
+    let mut hash = md5::compute(&input).0.to_vec();
+    if revision >= 3 {
+        for _ in 0..50 {
+            hash = md5::compute(&hash[..length]).0.to_vec();
+        }
+    }
+    hash.truncate(length);
+    hash
+}
+
+/// Algorithms 4 and 5: the first 16 bytes the `U` entry has for the file key `key`.
+fn user_hash(key: &[u8], id: &[u8], revision: i64) -> Vec<u8> {
+    if revision == 2 {
+        return rc4(key, &PADDING)[..16].to_vec();
+    }
+    let mut input = PADDING.to_vec();
+    input.extend_from_slice(id);
+    let mut hash = rc4(key, &md5::compute(&input).0);
+    for i in 1..=19 {
+        let key: Vec<u8> = key.iter().map(|b| b ^ i).collect();
+        hash = rc4(&key, &hash);
+    }
+    hash
+}
+
+/// Algorithm 7: recovers the user password from the owner password.
+fn owner_to_user(password: &[u8], owner: &[u8], revision: i64, length: usize) -> Vec<u8> {
+    let mut hash = md5::compute(padded(password)).0.to_vec();
+    if revision >= 3 {
+        for _ in 0..50 {
+            hash = md5::compute(&hash).0.to_vec();
+        }
+    }
+    let key = &hash[..length];
+    if revision == 2 {
+        return rc4(key, owner);
+    }
+    let mut user = owner.to_vec();
+    for i in (0..=19).rev() {
+        let key: Vec<u8> = key.iter().map(|b| b ^ i).collect();
+        user = rc4(&key, &user);
+    }
+    user
+}
+
+/// The file key of revisions 5 and 6, from the user or the owner password.
+fn aes256_file_key(
+    password: &[u8],
+    owner: &[u8],
+    user: &[u8],
+    encrypt: &Dictionary,
+    revision: i64,
+) -> Result<Vec<u8>, DecryptError> {
+    if owner.len() < 48 || user.len() < 48 {
+        return Err(DecryptError::UnsupportedError);
+    }
+    let password = &password[..password.len().min(127)];
+    let encrypted_key = |key: &[u8]| {
+        encrypt
+            .get(key)
+            .and_then(Object::as_str)
+            .map(|s| s.to_vec())
+            .map_err(|_| DecryptError::UnsupportedError)
+    };
+    // Each hash is followed by a validation salt and a key salt.
+    let (key_salt, extra, encrypted) =
+        if hash_2b(password, &owner[32..40], &user[..48], revision) == owner[..32] {
+            (&owner[40..48], &user[..48], encrypted_key(b"OE")?)
+        } else if hash_2b(password, &user[32..40], &[], revision) == user[..32] {
+            (&user[40..48], &[][..], encrypted_key(b"UE")?)
+        } else {
+            return Err(DecryptError::PasswordError);
+        };
+    if encrypted.len() != 32 {
+        return Err(DecryptError::UnsupportedError);
+    }
+    let key = hash_2b(password, key_salt, extra, revision);
+    Ok(aes_cbc_decrypt(&key, &[0; 16], &encrypted))
+}
+
+/// Algorithm 2.B (plain SHA-256 for revision 5).
+fn hash_2b(password: &[u8], salt: &[u8], extra: &[u8], revision: i64) -> Vec<u8> {
+    let input = [password, salt, extra].concat();
+    let mut hash = digest(&SHA256, &input).as_ref().to_vec();
+    if revision == 5 {
+        return hash;
+    }
+    let mut round = 0;
+    loop {
+        let block = [password, &hash, extra].concat().repeat(64);
+        let encrypted = aes_cbc_encrypt(&hash[..16], &hash[16..32], &block);
+        let algorithm = match encrypted[..16].iter().map(|&b| b as u32).sum::<u32>() % 3 {
+            0 => &SHA256,
+            1 => &SHA384,
+            _ => &SHA512,
+        };
+        hash = digest(algorithm, &encrypted).as_ref().to_vec();
+        round += 1;
+        if round >= 64 && *encrypted.last().unwrap_or(&0) as usize <= round - 32 {
+            break;
+        }
+    }
+    hash.truncate(32);
+    hash
+}
+
+fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    if key.is_empty() {
+        return data.to_vec();
+    }
+    let mut state: Vec<u8> = (0..=255).collect();
+    let mut j: u8 = 0;
+    for i in 0..256 {
+        j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+        state.swap(i, j as usize);
+    }
+    let (mut i, mut j) = (0u8, 0u8);
+    data.iter()
+        .map(|byte| {
+            i = i.wrapping_add(1);
+            j = j.wrapping_add(state[i as usize]);
+            state.swap(i as usize, j as usize);
+            byte ^ state[state[i as usize].wrapping_add(state[j as usize]) as usize]
+        })
+        .collect()
+}
+
+/// AES with a 128 or a 256 bit key.
+enum Aes {
+    Aes128(Box<Aes128>),
+    Aes256(Box<Aes256>),
+}
+
+impl Aes {
+    /// `None` for keys of other sizes.
+    fn new(key: &[u8]) -> Option<Aes> {
+        match key.len() {
+            16 => Some(Aes::Aes128(Box::new(Aes128::new(
+                GenericArray::from_slice(key),
+            )))),
+            32 => Some(Aes::Aes256(Box::new(Aes256::new(
+                GenericArray::from_slice(key),
+            )))),
+            _ => None,
+        }
+    }
+
+    fn decrypt(&self, block: &mut [u8]) {
+        let block = GenericArray::from_mut_slice(block);
+        match self {
+            Aes::Aes128(cipher) => cipher.decrypt_block(block),
+            Aes::Aes256(cipher) => cipher.decrypt_block(block),
+        }
+    }
+
+    fn encrypt(&self, block: &mut [u8]) {
+        let block = GenericArray::from_mut_slice(block);
+        match self {
+            Aes::Aes128(cipher) => cipher.encrypt_block(block),
+            Aes::Aes256(cipher) => cipher.encrypt_block(block),
+        }
+    }
+}
+
+/// Nothing for keys that are neither 128 nor 256 bits long.
+fn aes_cbc_decrypt(key: &[u8], iv: &[u8], data: &[u8]) -> Vec<u8> {
+    let aes = match Aes::new(key) {
+        Some(aes) => aes,
+        None => return Vec::new(),
+    };
+    let mut previous = iv.to_vec();
+    let mut plain = Vec::with_capacity(data.len());
+    for chunk in data.chunks_exact(16) {
+        let mut block = chunk.to_vec();
+        aes.decrypt(&mut block);
+        plain.extend(block.iter().zip(&previous).map(|(a, b)| a ^ b));
+        previous = chunk.to_vec();
+    }
+    plain
+}
+
+/// Nothing for keys that are neither 128 nor 256 bits long.
+fn aes_cbc_encrypt(key: &[u8], iv: &[u8], data: &[u8]) -> Vec<u8> {
+    let aes = match Aes::new(key) {
+        Some(aes) => aes,
+        None => return Vec::new(),
+    };
+    let mut previous = iv.to_vec();
+    let mut encrypted = Vec::with_capacity(data.len());
+    for chunk in data.chunks_exact(16) {
+        let mut block: Vec<u8> = chunk.iter().zip(&previous).map(|(a, b)| a ^ b).collect();
+        aes.encrypt(&mut block);
+        encrypted.extend_from_slice(&block);
+        previous = block;
+    }
+    encrypted
+}
+
+/// Strips the PKCS#5 padding of AES encrypted strings and streams.
+fn unpad(mut data: Vec<u8>) -> Vec<u8> {
+    let padding = data.last().copied().unwrap_or(0) as usize;
+    if (1..=16).contains(&padding) && padding <= data.len() {
+        data.truncate(data.len() - padding);
+    }
+    data
+}
+
+#[test]
+fn test_rc4() {
+    assert_eq!(
+        rc4(b"Key", b"Plaintext"),
+        [0xBB, 0xF3, 0x16, 0xE8, 0xD9, 0x40, 0xAF, 0x0A, 0xD3]
+    );
+}
+
+/// A document with the `Encrypt` dictionary `encrypt` and a string holding `secret`
+/// encrypted by `encrypt_string`, given the id of its object. Returns the id.
+#[cfg(test)]
+fn encrypted_document(
+    encrypt: Dictionary,
+    id: &[u8],
+    encrypt_string: impl Fn(ObjectId) -> Vec<u8>,
+) -> (Document, ObjectId) {
+    use lopdf::StringFormat;
+    let mut document = Document::with_version("1.5");
+    let encrypt = document.add_object(encrypt);
+    let text = document.add_object(Object::String(
+        encrypt_string((2, 0)),
+        StringFormat::Literal,
+    ));
+    assert_eq!(text, (2, 0));
+    document.trailer.set("Encrypt", encrypt);
+    document.trailer.set(
+        "ID",
+        vec![Object::String(id.to_vec(), StringFormat::Hexadecimal); 2],
+    );
+    (document, text)
+}
+
+/// Checks that `document` opens with either password, and not with a wrong one.
+#[cfg(test)]
+fn assert_decrypts(document: &Document, text: ObjectId) {
+    assert_eq!(
+        decrypt(&mut document.clone(), "wrong"),
+        Err(DecryptError::PasswordError)
+    );
+    for password in &["secret", "owner"] {
+        let mut document = document.clone();
+        assert_eq!(decrypt(&mut document, password), Ok(()));
+        assert!(!is_encrypted(&document));
+        assert_eq!(
+            document.get_object(text).and_then(Object::as_str).ok(),
+            Some(&b"Account 1234"[..])
+        );
+    }
+}
+
+/// The `O` and `U` entries of revisions 3 and 4 for the passwords `secret` and `owner`,
+/// with the file key.
+#[cfg(test)]
+fn rc4_entries(id: &[u8], revision: i64, permissions: i32) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+    let length = 16;
+    // Algorithm 3: the owner entry is the padded user password encrypted with the owner key.
+    let mut hash = md5::compute(padded(b"owner")).0.to_vec();
+    for _ in 0..50 {
+        hash = md5::compute(&hash).0.to_vec();
+    }
+    let mut owner = padded(b"secret");
+    for i in 0..=19 {
+        let key: Vec<u8> = hash[..length].iter().map(|b| b ^ i).collect();
+        owner = rc4(&key, &owner);
+    }
+    let key = file_key(b"secret", &owner, permissions, id, revision, length, true);
+    let mut user = user_hash(&key, id, revision);
+    user.extend_from_slice(&[0; 16]);
+    (owner, user, key)
+}
+
+/// `data` with PKCS#5 padding encrypted with `key`, after the IV it was encrypted with.
+#[cfg(test)]
+fn aes_encrypt_padded(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let iv = *b"initialvector..!";
+    let padding = 16 - data.len() % 16;
+    let mut padded = data.to_vec();
+    padded.resize(data.len() + padding, padding as u8);
+    [&iv[..], &aes_cbc_encrypt(key, &iv, &padded)].concat()
+}
+
+#[test]
+fn test_decrypt() {
+    use lopdf::{dictionary, StringFormat};
+    let id = b"0123456789abcdef".to_vec();
+    let permissions = -4;
+    let (owner, user, key) = rc4_entries(&id, 3, permissions);
+    let encrypt = dictionary! {
+        "Filter" => "Standard",
+        "V" => 2,
+        "R" => 3,
+        "Length" => 128,
+        "P" => permissions,
+        "O" => Object::String(owner, StringFormat::Hexadecimal),
+        "U" => Object::String(user, StringFormat::Hexadecimal),
+    };
+    let handler = Handler {
+        key,
+        strings: Cipher::Rc4,
+        streams: Cipher::Rc4,
+        encrypt_metadata: true,
+    };
+    let (document, text) = encrypted_document(encrypt.clone(), &id, |object| {
+        handler.decrypt_bytes(object, Cipher::Rc4, b"Account 1234")
+    });
+    assert_decrypts(&document, text);
+
+    // Lengths no key can have are turned down rather than cut out of the hash.
+    for &bits in &[0, 44, 256] {
+        let mut encrypt = encrypt.clone();
+        encrypt.set("Length", bits);
+        let (mut document, _) = encrypted_document(encrypt, &id, |_| Vec::new());
+        assert_eq!(
+            decrypt(&mut document, "secret"),
+            Err(DecryptError::UnsupportedError)
+        );
+    }
+}
+
+#[test]
+fn test_decrypt_aesv2() {
+    use lopdf::{dictionary, StringFormat};
+    let id = b"0123456789abcdef".to_vec();
+    let permissions = -4;
+    let (owner, user, key) = rc4_entries(&id, 4, permissions);
+    let encrypt = dictionary! {
+        "Filter" => "Standard",
+        "V" => 4,
+        "R" => 4,
+        "Length" => 128,
+        "P" => permissions,
+        "O" => Object::String(owner, StringFormat::Hexadecimal),
+        "U" => Object::String(user, StringFormat::Hexadecimal),
+        "CF" => dictionary! {
+            "StdCF" => dictionary! { "CFM" => "AESV2", "Length" => 16 },
+        },
+        "StrF" => "StdCF",
+        "StmF" => "StdCF",
+    };
+    let (document, text) = encrypted_document(encrypt.clone(), &id, |(number, generation)| {
+        // Algorithm 1: the object key salted for AES.
+        let mut input = key.clone();
+        input.extend_from_slice(&number.to_le_bytes()[..3]);
+        input.extend_from_slice(&generation.to_le_bytes()[..2]);
+        input.extend_from_slice(b"sAlT");
+        aes_encrypt_padded(&md5::compute(&input)[..], b"Account 1234")
+    });
+    assert_decrypts(&document, text);
+
+    // AESV2 needs a 128 bit key.
+    let mut short = encrypt;
+    short.set("Length", 40);
+    let (mut document, _) = encrypted_document(short, &id, |_| Vec::new());
+    assert_eq!(
+        decrypt(&mut document, "secret"),
+        Err(DecryptError::UnsupportedError)
+    );
+}
+
+#[test]
+fn test_decrypt_aesv3() {
+    use lopdf::{dictionary, StringFormat};
+    let id = b"0123456789abcdef".to_vec();
+    let key = b"a file key of exactly 32 bytes!!".to_vec();
+    // Algorithms 8 and 9: a hash of each password with a validation salt, followed by
+    // the salts, and the file key encrypted with a hash of the password and key salt.
+    let mut user = hash_2b(b"secret", b"uvalsalt", &[], 6);
+    user.extend_from_slice(b"uvalsaltukeysalt");
+    let user_key = hash_2b(b"secret", b"ukeysalt", &[], 6);
+    let user_encrypted = aes_cbc_encrypt(&user_key, &[0; 16], &key);
+    let mut owner = hash_2b(b"owner", b"ovalsalt", &user, 6);
+    owner.extend_from_slice(b"ovalsaltokeysalt");
+    let owner_key = hash_2b(b"owner", b"okeysalt", &user, 6);
+    let owner_encrypted = aes_cbc_encrypt(&owner_key, &[0; 16], &key);
+    let encrypt = dictionary! {
+        "Filter" => "Standard",
+        "V" => 5,
+        "R" => 6,
+        "Length" => 256,
+        "P" => -4,
+        "O" => Object::String(owner, StringFormat::Hexadecimal),
+        "U" => Object::String(user, StringFormat::Hexadecimal),
+        "OE" => Object::String(owner_encrypted, StringFormat::Hexadecimal),
+        "UE" => Object::String(user_encrypted, StringFormat::Hexadecimal),
+        "CF" => dictionary! {
+            "StdCF" => dictionary! { "CFM" => "AESV3", "Length" => 32 },
+        },
+        "StrF" => "StdCF",
+        "StmF" => "StdCF",
+    };
+    let (document, text) = encrypted_document(encrypt.clone(), &id, |_| {
+        aes_encrypt_padded(&key, b"Account 1234")
+    });
+    assert_decrypts(&document, text);
+
+    // An encrypted file key of the wrong size is turned down.
+    let mut truncated = encrypt;
+    truncated.set("UE", Object::String(vec![0; 16], StringFormat::Hexadecimal));
+    let (mut document, _) = encrypted_document(truncated, &id, |_| Vec::new());
+    assert_eq!(
+        decrypt(&mut document, "secret"),
+        Err(DecryptError::UnsupportedError)
+    );
+    assert!(aes_cbc_decrypt(&[0; 5], &[0; 16], &[0; 32]).is_empty());
+    assert_eq!(rc4(&[], b"text"), b"text");
+}
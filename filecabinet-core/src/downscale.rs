@@ -0,0 +1,109 @@
+use image::codecs::jpeg::JpegDecoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageBuffer, ImageDecoder};
+use png::{ColorType, Decoder, Transformations};
+use std::io::Cursor;
+
+/// Scales `image` down to fit in `max_side` pixels, if it is larger.
+pub fn fit(image: DynamicImage, max_side: u32) -> DynamicImage {
+    if image.width().max(image.height()) > max_side {
+        image.resize(max_side, max_side, FilterType::Triangle)
+    } else {
+        image
+    }
+}
+
+/// Decodes the JPEG `content` to fit in `max_side` pixels. The decoder itself scales it
+/// down by up to 8 on the way, so a 600 DPI scan never takes the memory of its full
+/// resolution.
+pub fn jpeg(content: &[u8], max_side: u32) -> Option<DynamicImage> {
+    let mut decoder = JpegDecoder::new(Cursor::new(content)).ok()?;
+    let (width, height) = decoder.dimensions();
+    let longest = width.max(height).max(1);
+    if longest > max_side {
+        let scaled = |side: u32| (side as u64 * max_side as u64 / longest as u64).max(1) as u16;
+        decoder.scale(scaled(width), scaled(height)).ok()?;
+    }
+    Some(fit(DynamicImage::from_decoder(decoder).ok()?, max_side))
+}
+
+/// Decodes the PNG `content` to fit in `max_side` pixels. Rows are averaged in blocks as
+/// they are read so only the scaled down image is held, interlaced ones aside.
+pub fn png(content: &[u8], max_side: u32) -> Option<DynamicImage> {
+    let mut decoder = Decoder::new(content);
+    decoder.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+    let (info, mut reader) = decoder.read_info().ok()?;
+    if reader.info().interlaced {
+        return image::load_from_memory(content)
+            .ok()
+            .map(|image| fit(image, max_side));
+    }
+    let (color, _) = reader.output_color_type();
+    let channels = color.samples();
+    // Blocks of step by step pixels become one, the rest is left to `fit`.
+    let step = (info.width.max(info.height) / max_side).max(1) as usize;
+    let (width, height) = (info.width as usize, info.height as usize);
+    let (out_width, out_height) = (width.div_ceil(step), height.div_ceil(step));
+    let mut pixels = Vec::with_capacity(out_width * out_height * channels);
+    let mut sums = vec![0u32; out_width * channels];
+    let mut counts = vec![0u32; out_width];
+    let mut y = 0;
+    while let Some(row) = reader.next_row().ok()? {
+        for (x, pixel) in row.chunks_exact(channels).take(width).enumerate() {
+            let block = x / step;
+            for (sum, value) in sums[block * channels..].iter_mut().zip(pixel) {
+                *sum += *value as u32;
+            }
+            counts[block] += 1;
+        }
+        y += 1;
+        if y % step == 0 || y == height {
+            for (block, count) in counts.iter_mut().enumerate() {
+                for sum in &mut sums[block * channels..(block + 1) * channels] {
+                    pixels.push((*sum / (*count).max(1)) as u8);
+                    *sum = 0;
+                }
+                *count = 0;
+            }
+        }
+    }
+    let (out_width, out_height) = (out_width as u32, out_height as u32);
+    let image = match color {
+        ColorType::Grayscale => {
+            DynamicImage::ImageLuma8(ImageBuffer::from_raw(out_width, out_height, pixels)?)
+        }
+        ColorType::GrayscaleAlpha => {
+            DynamicImage::ImageLumaA8(ImageBuffer::from_raw(out_width, out_height, pixels)?)
+        }
+        ColorType::RGB => {
+            DynamicImage::ImageRgb8(ImageBuffer::from_raw(out_width, out_height, pixels)?)
+        }
+        ColorType::RGBA => {
+            DynamicImage::ImageRgba8(ImageBuffer::from_raw(out_width, out_height, pixels)?)
+        }
+        // Expanded to RGB by the decoder.
+        ColorType::Indexed => return None,
+    };
+    Some(fit(image, max_side))
+}
+
+#[test]
+fn test_downscale() {
+    let image = DynamicImage::ImageRgb8(ImageBuffer::from_fn(3000, 1000, |x, _| {
+        image::Rgb([(x % 256) as u8, 128, 0])
+    }));
+    let mut png = Vec::new();
+    image
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .unwrap();
+    let small = self::png(&png, 1000).unwrap();
+    assert_eq!(small.dimensions(), (1000, 334));
+    assert_eq!(self::png(&png, 4000).unwrap().dimensions(), (3000, 1000));
+
+    let mut jpeg = Vec::new();
+    image
+        .write_to(&mut jpeg, image::ImageOutputFormat::Jpeg(80))
+        .unwrap();
+    assert_eq!(self::jpeg(&jpeg, 1000).unwrap().dimensions(), (1000, 333));
+    assert!(self::jpeg(b"not a jpeg", 1000).is_none());
+}
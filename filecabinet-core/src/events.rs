@@ -0,0 +1,106 @@
+use crate::catalog::Catalog;
+use chrono::Utc;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::warn;
+
+/// Whether events are written, see `Preferences::event_stream`.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// A change to the documents of a cabinet, by filename.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    Added {
+        filename: String,
+    },
+    Renamed {
+        from: String,
+        to: String,
+    },
+    /// The tags the document has now.
+    Tagged {
+        filename: String,
+        tags: Vec<String>,
+    },
+    Deleted {
+        filename: String,
+    },
+}
+
+/// A line of the stream.
+#[derive(Serialize)]
+struct Line<'a> {
+    /// UTC, RFC 3339.
+    time: &'a str,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+pub fn enable(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// The events of the cabinet at `dir`, in `<cabinet>/.filecabinet/events.jsonl` for other
+/// programs to tail.
+pub fn path(dir: &Path) -> PathBuf {
+    Catalog::path(dir).with_file_name("events.jsonl")
+}
+
+/// Appends `events` to the stream of the cabinet at `dir`, if it is enabled.
+pub fn emit(dir: &Path, events: &[Event]) {
+    if !ENABLED.load(Ordering::Relaxed) || events.is_empty() {
+        return;
+    }
+    if let Err(error) = append(dir, events) {
+        warn!(event = "events_write_failed", ?error);
+    }
+}
+
+fn append(dir: &Path, events: &[Event]) -> std::io::Result<()> {
+    let path = path(dir);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let time = Utc::now().to_rfc3339();
+    let mut lines = String::new();
+    for event in events {
+        lines.push_str(&serde_json::to_string(&Line { time: &time, event })?);
+        lines.push('\n');
+    }
+    // One write so readers never see half a batch.
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(lines.as_bytes())
+}
+
+#[test]
+fn test_events() {
+    let dir = tempdir::TempDir::new("events").unwrap();
+    let dir = dir.path();
+    let added = Event::Added {
+        filename: "scan.pdf".to_string(),
+    };
+    let renamed = Event::Renamed {
+        from: "scan.pdf".to_string(),
+        to: "2021-03-04_Bank_Statement_1.pdf".to_string(),
+    };
+    append(dir, &[added]).unwrap();
+    append(dir, &[renamed]).unwrap();
+    let lines: Vec<serde_json::Value> = fs::read_to_string(path(dir))
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["event"], "added");
+    assert_eq!(lines[0]["filename"], "scan.pdf");
+    assert_eq!(lines[1]["event"], "renamed");
+    assert_eq!(lines[1]["to"], "2021-03-04_Bank_Statement_1.pdf");
+    assert!(lines[1]["time"].as_str().unwrap().contains('T'));
+}
@@ -0,0 +1,244 @@
+use crate::catalog::{Catalog, CatalogError, Entry};
+use crate::utils;
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// What a custom field holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldKind {
+    #[default]
+    Text,
+    Number,
+    /// Stored as `%Y-%m-%d`.
+    Date,
+    /// One of the options of the field.
+    Choice,
+}
+
+impl FieldKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            FieldKind::Text => "Text",
+            FieldKind::Number => "Number",
+            FieldKind::Date => "Date",
+            FieldKind::Choice => "Choice",
+        }
+    }
+
+    /// The kind after this one, to cycle through them with a single button.
+    pub fn next(self) -> FieldKind {
+        match self {
+            FieldKind::Text => FieldKind::Number,
+            FieldKind::Number => FieldKind::Date,
+            FieldKind::Date => FieldKind::Choice,
+            FieldKind::Choice => FieldKind::Text,
+        }
+    }
+}
+
+/// A field the documents of a cabinet can have besides the ones of their filename, e.g.
+/// "Policy number".
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FieldDef {
+    pub name: String,
+    pub kind: FieldKind,
+    /// Comma separated values a choice can take.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub options: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::enum_variant_names)]
+pub enum FieldError {
+    NumberError(String),
+    DateError(String),
+    ChoiceError(String),
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldError::NumberError(name) => write!(f, "{} needs a number.", name),
+            FieldError::DateError(name) => write!(f, "{} needs a date, e.g. 2021-03-01.", name),
+            FieldError::ChoiceError(name) => write!(f, "{} needs one of its options.", name),
+        }
+    }
+}
+
+impl FieldDef {
+    pub fn choices(&self) -> Vec<&str> {
+        self.options
+            .split(',')
+            .map(str::trim)
+            .filter(|option| !option.is_empty())
+            .collect()
+    }
+
+    /// What the input of the field shows when it is empty.
+    pub fn placeholder(&self) -> String {
+        match self.kind {
+            FieldKind::Text => self.name.clone(),
+            FieldKind::Number => format!("{} (number)", self.name),
+            FieldKind::Date => format!("{} (YYYY-MM-DD)", self.name),
+            FieldKind::Choice => format!("{}: {}", self.name, self.choices().join(", ")),
+        }
+    }
+
+    /// `value` as it is stored, empty to clear the field.
+    pub fn check(&self, value: &str) -> Result<String, FieldError> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(String::new());
+        }
+        match self.kind {
+            FieldKind::Text => Ok(value.to_string()),
+            FieldKind::Number => value
+                .parse::<f64>()
+                .map(|_| value.to_string())
+                .map_err(|_| FieldError::NumberError(self.name.clone())),
+            FieldKind::Date => utils::parse_date(&value)
+                .filter(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok())
+                .ok_or_else(|| FieldError::DateError(self.name.clone())),
+            FieldKind::Choice => self
+                .choices()
+                .into_iter()
+                .find(|option| option.eq_ignore_ascii_case(value))
+                .map(str::to_string)
+                .ok_or_else(|| FieldError::ChoiceError(self.name.clone())),
+        }
+    }
+}
+
+/// Checks the `values` typed for the fields of `defs`, by field name. The fields left
+/// empty aren't kept.
+pub fn check_all(
+    defs: &[FieldDef],
+    values: &BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>, FieldError> {
+    let mut checked = BTreeMap::new();
+    for def in defs.iter().filter(|def| !def.name.trim().is_empty()) {
+        let value = def.check(values.get(&def.name).map_or("", String::as_str))?;
+        if !value.is_empty() {
+            checked.insert(def.name.clone(), value);
+        }
+    }
+    Ok(checked)
+}
+
+/// The custom fields of a cabinet, in `<cabinet>/.filecabinet/fields.json`.
+fn fields_path(dir: &Path) -> PathBuf {
+    Catalog::path(dir).with_file_name("fields.json")
+}
+
+pub fn load(dir: &Path) -> Vec<FieldDef> {
+    fs::read_to_string(fields_path(dir))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(dir: &Path, defs: &[FieldDef]) -> Result<(), CatalogError> {
+    let path = fields_path(dir);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|_| CatalogError::DirectoryError)?;
+    }
+    let json = serde_json::to_string_pretty(defs).map_err(|_| CatalogError::FormatError)?;
+    AtomicFile::new(&path, OverwriteBehavior::AllowOverwrite)
+        .write(|f| f.write_all(json.as_bytes()))
+        .map_err(|_| CatalogError::WriteError)
+}
+
+/// The names of the fields, as extra columns of CSV exports.
+pub fn csv_header(defs: &[FieldDef]) -> Vec<String> {
+    defs.iter().map(|def| def.name.clone()).collect()
+}
+
+/// The values of the fields for a document with `entry`, in the order of `csv_header`.
+pub fn csv_row(defs: &[FieldDef], entry: Option<&Entry>) -> Vec<String> {
+    defs.iter()
+        .map(|def| {
+            entry
+                .and_then(|entry| entry.fields.get(&def.name))
+                .cloned()
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+#[test]
+fn test_fields() {
+    let def = |name: &str, kind: FieldKind, options: &str| FieldDef {
+        name: name.to_string(),
+        kind,
+        options: options.to_string(),
+    };
+    let defs = vec![
+        def("Case number", FieldKind::Text, ""),
+        def("Premium", FieldKind::Number, ""),
+        def("Renewal", FieldKind::Date, ""),
+        def("Status", FieldKind::Choice, "Open, Closed"),
+    ];
+    let values = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    };
+
+    let checked = check_all(
+        &defs,
+        &values(&[
+            ("Case number", " A-12 "),
+            ("Premium", "120.50"),
+            ("Renewal", "20220301"),
+            ("Status", "closed"),
+        ]),
+    )
+    .unwrap();
+    assert_eq!(
+        checked,
+        values(&[
+            ("Case number", "A-12"),
+            ("Premium", "120.50"),
+            ("Renewal", "2022-03-01"),
+            ("Status", "Closed"),
+        ])
+    );
+    assert!(check_all(&defs, &values(&[("Premium", "")]))
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        check_all(&defs, &values(&[("Premium", "a lot")])),
+        Err(FieldError::NumberError("Premium".to_string()))
+    );
+    assert_eq!(
+        check_all(&defs, &values(&[("Renewal", "2022-13-45")])),
+        Err(FieldError::DateError("Renewal".to_string()))
+    );
+    assert_eq!(
+        check_all(&defs, &values(&[("Status", "Pending")])),
+        Err(FieldError::ChoiceError("Status".to_string()))
+    );
+
+    let dir = tempdir::TempDir::new("fields").unwrap();
+    assert!(load(dir.path()).is_empty());
+    save(dir.path(), &defs).unwrap();
+    assert_eq!(load(dir.path()), defs);
+
+    let entry = Entry {
+        fields: checked,
+        ..Default::default()
+    };
+    assert_eq!(
+        csv_row(&defs, Some(&entry)),
+        ["A-12", "120.50", "2022-03-01", "Closed"]
+    );
+    assert_eq!(csv_row(&defs, None), ["", "", "", ""]);
+}
@@ -0,0 +1,333 @@
+use crate::calendar::Expected;
+use crate::catalog::{Catalog, CatalogError, Label};
+use crate::renames::{self, Rename, RenameError};
+use crate::utils::{self, OptDoc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// How to reach an institution, kept in the catalog under the name its documents are
+/// filed with. Only a reference to the account is meant to be kept, like its last digits.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Contact {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub website: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub account: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub phone: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+    /// Marks the documents of the institution in the document list, unless it has an icon.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<Label>,
+    /// The file name of its icon in the assets folder, see `icon`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub icon: String,
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum IconError {
+    /// Only PNG and JPEG images can be icons.
+    FormatError,
+    DirectoryError,
+    CopyError,
+}
+
+impl Contact {
+    pub fn is_empty(&self) -> bool {
+        *self == Contact::default()
+    }
+
+    /// The color after this one, to cycle through `Label::ALL` and back to none with a
+    /// single button.
+    pub fn next_color(&self) -> Option<Label> {
+        match self.color {
+            None => Some(Label::ALL[0]),
+            Some(color) if color.next() == Label::ALL[0] => None,
+            Some(color) => Some(color.next()),
+        }
+    }
+
+    /// The contact on one line, for the list of institutions.
+    pub fn summary(&self) -> String {
+        [&self.website, &self.phone, &self.account]
+            .iter()
+            .filter(|part| !part.is_empty())
+            .map(|part| part.trim())
+            .collect::<Vec<_>>()
+            .join("  ·  ")
+    }
+}
+
+/// Extensions of the images that can be icons.
+const ICON_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// Where the icons of the institutions of the cabinet at `dir` are kept.
+pub fn assets(dir: &Path) -> PathBuf {
+    dir.join(".filecabinet").join("assets")
+}
+
+/// The icon of `contact` in the cabinet at `dir`, if it has one that is still there. The
+/// catalog may come from anyone sharing the cabinet, so only a file right in the assets
+/// folder is taken, not a path out of it.
+pub fn icon(dir: &Path, contact: &Contact) -> Option<PathBuf> {
+    let is_filename = Path::new(&contact.icon)
+        .file_name()
+        .is_some_and(|name| name == contact.icon.as_str());
+    let path = assets(dir).join(&contact.icon);
+    (is_filename && path.is_file()).then_some(path)
+}
+
+/// Copies the image at `from` into the assets of the cabinet at `dir` as the icon of
+/// `institution`, replacing the one it had. Returns its file name for `Contact::icon`.
+pub fn import_icon(dir: &Path, institution: &str, from: &Path) -> Result<String, IconError> {
+    let extension = from
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .filter(|extension| ICON_EXTENSIONS.contains(&extension.as_str()))
+        .ok_or(IconError::FormatError)?;
+    let assets = assets(dir);
+    fs::create_dir_all(&assets).map_err(|_| IconError::DirectoryError)?;
+    let name = format!("{}.{}", institution, extension);
+    // An icon of another type would be left behind, nothing refers to it any more.
+    for other in ICON_EXTENSIONS.iter().filter(|other| **other != extension) {
+        let old = assets.join(format!("{}.{}", institution, other));
+        if old.is_file() {
+            fs::remove_file(&old).map_err(|_| IconError::CopyError)?;
+        }
+    }
+    fs::copy(from, assets.join(&name)).map_err(|_| IconError::CopyError)?;
+    info!(event = "InstitutionIcon", institution = %institution, icon = %name);
+    Ok(name)
+}
+
+/// The institutions of the cabinet at `dir` with how many documents they filed, sorted
+/// by name. Institutions with a contact or expected documents but no documents are
+/// listed too.
+pub fn list(dir: &Path) -> Vec<(String, usize)> {
+    let catalog = Catalog::load(dir);
+    let mut counts: BTreeMap<String, usize> = catalog
+        .institutions
+        .into_keys()
+        .chain(catalog.expected.into_keys())
+        .map(|institution| (institution, 0))
+        .collect();
+    for filename in utils::list_files(dir) {
+        if let Some(institution) = OptDoc::new(&filename).institution {
+            *counts.entry(institution).or_default() += 1;
+        }
+    }
+    let mut list: Vec<(String, usize)> = counts.into_iter().collect();
+    list.sort_by_key(|(institution, _)| institution.to_lowercase());
+    list
+}
+
+/// The documents `institution` filed in the cabinet at `dir`, newest first.
+pub fn documents(dir: &Path, institution: &str) -> Vec<String> {
+    let mut documents: Vec<(Option<String>, String)> = utils::list_files(dir)
+        .into_iter()
+        .filter_map(|filename| {
+            let doc = OptDoc::new(&filename);
+            (doc.institution.as_deref() == Some(institution)).then_some((doc.date, filename))
+        })
+        .collect();
+    documents.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    documents
+        .into_iter()
+        .map(|(_, filename)| filename)
+        .collect()
+}
+
+/// The contact of `institution` and the documents it is expected to send.
+pub fn details(dir: &Path, institution: &str) -> (Contact, Vec<Expected>) {
+    let catalog = Catalog::load(dir);
+    (
+        catalog
+            .institutions
+            .get(institution)
+            .cloned()
+            .unwrap_or_default(),
+        catalog
+            .expected
+            .get(institution)
+            .cloned()
+            .unwrap_or_default(),
+    )
+}
+
+/// Saves the contact of `institution` and the documents it is expected to send in the
+/// catalog of the cabinet at `dir`. Empty ones are forgotten.
+pub fn record_details(
+    dir: &Path,
+    institution: &str,
+    contact: &Contact,
+    expected: &[Expected],
+) -> Result<(), CatalogError> {
+    let mut catalog = Catalog::load(dir);
+    if contact.is_empty() {
+        catalog.institutions.remove(institution);
+    } else {
+        catalog
+            .institutions
+            .insert(institution.to_string(), contact.clone());
+    }
+    if expected.is_empty() {
+        catalog.expected.remove(institution);
+    } else {
+        catalog
+            .expected
+            .insert(institution.to_string(), expected.to_vec());
+    }
+    catalog.save(dir)?;
+    info!(event = "InstitutionDetails", institution = %institution, expected = expected.len());
+    Ok(())
+}
+
+/// The renames filing the documents of `institution` in the cabinet at `dir` under `to`,
+/// the rest of their names unchanged.
+pub fn renames(dir: &Path, institution: &str, to: &str) -> Vec<Rename> {
+    documents(dir, institution)
+        .into_iter()
+        .map(|from| {
+            let path = Path::new(&from);
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let mut parts: Vec<&str> = stem.splitn(3, '_').collect();
+            parts[1] = to;
+            let mut to = parts.join("_");
+            if let Some(extension) = path.extension() {
+                to = format!("{}.{}", to, extension.to_string_lossy());
+            }
+            Rename { from, to }
+        })
+        .collect()
+}
+
+/// Files the documents of `from` in the cabinet at `dir` under `to` in one batch, see
+/// `renames::apply`, and moves its contact and expected documents along. Returns how many
+/// documents were renamed.
+pub fn rename(dir: &Path, from: &str, to: &str) -> Result<usize, RenameError> {
+    let plan = renames::plan(dir, renames(dir, from, to));
+    let renamed = renames::apply(dir, &plan, "Rename institution")?;
+    let mut catalog = Catalog::load(dir);
+    if let Some(contact) = catalog.institutions.remove(from) {
+        catalog
+            .institutions
+            .entry(to.to_string())
+            .or_insert(contact);
+    }
+    if let Some(expected) = catalog.expected.remove(from) {
+        catalog
+            .expected
+            .entry(to.to_string())
+            .or_default()
+            .extend(expected);
+    }
+    if let Err(error) = catalog.save(dir) {
+        warn!(event = "catalog_save_failed", ?error);
+    }
+    info!(event = "RenameInstitution", from = %from, to = %to, documents = renamed);
+    Ok(renamed)
+}
+
+pub async fn rename_all(dir: String, from: String, to: String) -> Result<usize, RenameError> {
+    rename(Path::new(&dir), &from, &to)
+}
+
+#[test]
+fn test_institutions() {
+    let dir = tempdir::TempDir::new("institutions").unwrap();
+    let dir = dir.path();
+    for filename in [
+        "2021-01-05_Chase_Statement_1.pdf",
+        "2021-02-05_Chase_Statement_1.pdf",
+        "2020-06-01_amazon_Invoice_1.pdf",
+        "scan.jpg",
+    ] {
+        std::fs::write(dir.join(filename), b"document").unwrap();
+    }
+    let contact = Contact {
+        phone: "+1 800 935 9935".to_string(),
+        account: "ends in 1234".to_string(),
+        ..Default::default()
+    };
+    record_details(dir, "CityWater", &contact, &[]).unwrap();
+    assert_eq!(
+        list(dir),
+        vec![
+            ("amazon".to_string(), 1),
+            ("Chase".to_string(), 2),
+            ("CityWater".to_string(), 0),
+        ]
+    );
+    assert_eq!(
+        documents(dir, "Chase"),
+        vec![
+            "2021-02-05_Chase_Statement_1.pdf",
+            "2021-01-05_Chase_Statement_1.pdf"
+        ]
+    );
+    assert_eq!(contact.summary(), "+1 800 935 9935  ·  ends in 1234");
+    assert_eq!(details(dir, "CityWater"), (contact.clone(), Vec::new()));
+
+    record_details(dir, "CityWater", &Contact::default(), &[]).unwrap();
+    assert!(Catalog::load(dir).institutions.is_empty());
+
+    record_details(dir, "Chase", &contact, &[]).unwrap();
+    std::fs::write(dir.join("2021-03-05_Chase.pdf"), b"document").unwrap();
+    assert_eq!(rename(dir, "Chase", "JPMorgan").unwrap(), 3);
+    assert!(documents(dir, "Chase").is_empty());
+    assert_eq!(
+        documents(dir, "JPMorgan"),
+        vec![
+            "2021-03-05_JPMorgan.pdf",
+            "2021-02-05_JPMorgan_Statement_1.pdf",
+            "2021-01-05_JPMorgan_Statement_1.pdf"
+        ]
+    );
+    assert_eq!(details(dir, "JPMorgan").0, contact);
+
+    let logo = dir.join("logo.PNG");
+    std::fs::write(&logo, b"image").unwrap();
+    assert!(matches!(
+        import_icon(dir, "JPMorgan", &dir.join("notes.txt")),
+        Err(IconError::FormatError)
+    ));
+    let with_icon = Contact {
+        icon: import_icon(dir, "JPMorgan", &logo).unwrap(),
+        color: Some(Label::Blue),
+        ..Default::default()
+    };
+    assert_eq!(with_icon.icon, "JPMorgan.png");
+    assert_eq!(
+        icon(dir, &with_icon),
+        Some(dir.join(".filecabinet/assets/JPMorgan.png"))
+    );
+    assert_eq!(icon(dir, &Contact::default()), None);
+    std::fs::write(dir.join("secret.png"), b"image").unwrap();
+    let outside = Contact {
+        icon: "../../secret.png".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(icon(dir, &outside), None);
+    let photo = dir.join("logo.jpg");
+    std::fs::write(&photo, b"image").unwrap();
+    assert_eq!(
+        import_icon(dir, "JPMorgan", &photo).unwrap(),
+        "JPMorgan.jpg"
+    );
+    assert!(!assets(dir).join("JPMorgan.png").exists());
+    assert_eq!(import_icon(dir, "JPMorgan", &logo).unwrap(), "JPMorgan.png");
+    assert!(!assets(dir).join("JPMorgan.jpg").exists());
+    record_details(dir, "JPMorgan", &with_icon, &[]).unwrap();
+    assert_eq!(details(dir, "JPMorgan").0, with_icon);
+    assert_eq!(with_icon.next_color(), Some(Label::Purple));
+    let last = Contact {
+        color: Label::ALL.last().copied(),
+        ..Default::default()
+    };
+    assert_eq!(last.next_color(), None);
+}
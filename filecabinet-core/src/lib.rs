@@ -0,0 +1,50 @@
+//! The document handling of filecabinet without the GUI: what the app, its command line
+//! and other tools share about a cabinet, a folder of documents named
+//! `date_institution_name_page.ext`.
+//!
+//! - [`utils::OptDoc`] parses a filename into its parts, [`utils::normalized_filename`]
+//!   builds one and [`utils::list_files`] lists the documents of a cabinet.
+//! - [`catalog::Catalog`] is what is known about the documents of a cabinet, kept in
+//!   `<cabinet>/.filecabinet/catalog.json`.
+//! - [`fields`] is the schema of the custom fields of a cabinet.
+//! - [`renames::plan`] checks a batch of renames for conflicts and [`renames::apply`]
+//!   carries it out, all or nothing.
+//!
+//! ```
+//! use filecabinet_core::utils::{normalized_filename, OptDoc};
+//!
+//! let doc = OptDoc::new("2021-03-04_Chase_Statement_2.pdf");
+//! assert_eq!(doc.institution.as_deref(), Some("Chase"));
+//! assert_eq!(doc.page_number(), Some(2));
+//! assert_eq!(
+//!     normalized_filename("2021-03-04", "Chase", "Statement", "2", "pdf"),
+//!     "2021-03-04_Chase_Statement_2.pdf"
+//! );
+//! ```
+#[macro_use]
+extern crate lazy_static;
+
+pub mod amount;
+pub mod barcode;
+pub mod calendar;
+pub mod catalog;
+pub mod checklists;
+pub mod decrypt;
+pub mod downscale;
+pub mod events;
+pub mod fields;
+pub mod institutions;
+pub mod locks;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod mapped;
+pub mod metadata;
+pub mod office;
+pub mod orientation;
+pub mod pdf;
+pub mod renames;
+pub mod report;
+pub mod search;
+pub mod similarity;
+pub mod storage;
+pub mod utils;
+pub mod versions;
@@ -0,0 +1,210 @@
+use crate::catalog::{self, Catalog};
+use chrono::{Duration, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use tracing::{info, warn};
+
+/// After how long a document lock is taken over, e.g. when the app holding it crashed.
+const DOCUMENT_STALE_HOURS: i64 = 12;
+
+/// After how long a catalog lock is taken over, it is only held while the catalog is
+/// written.
+const CATALOG_STALE_SECONDS: i64 = 30;
+
+/// How long a catalog write waits for another one to finish.
+const CATALOG_WAIT_MS: u64 = 2000;
+
+const CATALOG_RETRY_MS: u64 = 50;
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum LockError {
+    /// Someone else holds the lock.
+    HeldError(Holder),
+    /// The lock can't be written, e.g. the cabinet is on a read-only share.
+    WriteError,
+}
+
+/// Who holds a lock, written in the lock file so others can tell who to ask.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Holder {
+    pub user: String,
+    pub host: String,
+    pub pid: u32,
+    /// When the lock was taken, in UTC as `catalog::VIEWED_FORMAT`.
+    pub since: String,
+}
+
+impl Holder {
+    /// This app.
+    fn me() -> Holder {
+        let user = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_else(|_| "someone".to_string());
+        let host = std::env::var("HOSTNAME")
+            .ok()
+            .or_else(|| fs::read_to_string("/etc/hostname").ok())
+            .map(|host| host.trim().to_string())
+            .filter(|host| !host.is_empty())
+            .unwrap_or_else(|| "another computer".to_string());
+        Holder {
+            user,
+            host,
+            pid: std::process::id(),
+            since: Utc::now().format(catalog::VIEWED_FORMAT).to_string(),
+        }
+    }
+
+    fn is_me(&self) -> bool {
+        let me = Holder::me();
+        self.host == me.host && self.pid == me.pid
+    }
+}
+
+impl fmt::Display for Holder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} on {}", self.user, self.host)
+    }
+}
+
+/// The lock files of a cabinet, in `<cabinet>/.filecabinet/locks`.
+fn locks_dir(dir: &Path) -> PathBuf {
+    Catalog::path(dir).with_file_name("locks")
+}
+
+fn document_lock(path: &Path) -> Option<PathBuf> {
+    let dir = path.parent()?;
+    let filename = path.file_name()?.to_string_lossy();
+    Some(locks_dir(dir).join(format!("{}.lock", filename)))
+}
+
+/// The holder of the lock file at `lock`, unless it is missing or older than `stale`.
+fn holder(lock: &Path, stale: Duration) -> Option<Holder> {
+    let holder: Holder = serde_json::from_str(&fs::read_to_string(lock).ok()?).ok()?;
+    let since = NaiveDateTime::parse_from_str(&holder.since, catalog::VIEWED_FORMAT).ok()?;
+    (Utc::now().naive_utc() - since < stale).then_some(holder)
+}
+
+/// Creates the lock file at `lock` for this app. A lock it holds already is kept if
+/// `reenter`, a stale one is taken over.
+fn take(lock: &Path, stale: Duration, reenter: bool) -> Result<(), LockError> {
+    if let Some(parent) = lock.parent() {
+        fs::create_dir_all(parent).map_err(|_| LockError::WriteError)?;
+    }
+    let json = serde_json::to_vec(&Holder::me()).map_err(|_| LockError::WriteError)?;
+    // Twice at most, the second time after removing a stale lock.
+    for _ in 0..2 {
+        match OpenOptions::new().write(true).create_new(true).open(lock) {
+            Ok(mut file) => return file.write_all(&json).map_err(|_| LockError::WriteError),
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => match holder(lock, stale) {
+                Some(holder) if reenter && holder.is_me() => return Ok(()),
+                Some(holder) => return Err(LockError::HeldError(holder)),
+                None => {
+                    info!(event = "StaleLock", lock = %lock.display());
+                    fs::remove_file(lock).map_err(|_| LockError::WriteError)?;
+                }
+            },
+            Err(_) => return Err(LockError::WriteError),
+        }
+    }
+    Err(LockError::WriteError)
+}
+
+/// Locks the document at `path` while its metadata is edited, so someone else sharing
+/// the cabinet can't edit it at the same time.
+pub fn lock_document(path: &Path) -> Result<(), LockError> {
+    let lock = document_lock(path).ok_or(LockError::WriteError)?;
+    take(&lock, Duration::hours(DOCUMENT_STALE_HOURS), true)
+}
+
+/// Releases the lock of the document at `path`, if this app holds it.
+pub fn unlock_document(path: &Path) {
+    if let Some(lock) = document_lock(path) {
+        if holder(&lock, Duration::hours(DOCUMENT_STALE_HOURS)).is_some_and(|h| h.is_me()) {
+            if let Err(error) = fs::remove_file(&lock) {
+                warn!(event = "unlock_failed", lock = %lock.display(), ?error);
+            }
+        }
+    }
+}
+
+/// Who else is editing the document at `path`, if anyone.
+pub fn edited_by(path: &Path) -> Option<Holder> {
+    let lock = document_lock(path)?;
+    holder(&lock, Duration::hours(DOCUMENT_STALE_HOURS)).filter(|holder| !holder.is_me())
+}
+
+/// Holds the catalog of a cabinet while it is read, changed and written back, released
+/// when dropped.
+pub struct CatalogLock {
+    lock: PathBuf,
+}
+
+impl Drop for CatalogLock {
+    fn drop(&mut self) {
+        if let Err(error) = fs::remove_file(&self.lock) {
+            warn!(event = "unlock_failed", lock = %self.lock.display(), ?error);
+        }
+    }
+}
+
+/// Locks the catalog of the cabinet at `dir`, waiting a little for someone else writing
+/// it to finish.
+pub fn lock_catalog(dir: &Path) -> Result<CatalogLock, LockError> {
+    let lock = locks_dir(dir).join("catalog.lock");
+    let stale = Duration::seconds(CATALOG_STALE_SECONDS);
+    let mut waited = 0;
+    loop {
+        // Not reentered, so threads of this app wait for each other too.
+        match take(&lock, stale, false) {
+            Err(LockError::HeldError(_)) if waited < CATALOG_WAIT_MS => {
+                thread::sleep(std::time::Duration::from_millis(CATALOG_RETRY_MS));
+                waited += CATALOG_RETRY_MS;
+            }
+            result => return result.map(|()| CatalogLock { lock }),
+        }
+    }
+}
+
+#[test]
+fn test_locks() {
+    let dir = tempdir::TempDir::new("locks").unwrap();
+    let path = dir.path().join("a.pdf");
+    assert!(lock_document(&path).is_ok());
+    // This app holds it already, so it can edit it again.
+    assert!(lock_document(&path).is_ok());
+    assert!(edited_by(&path).is_none());
+
+    let lock = document_lock(&path).unwrap();
+    let someone = |since: chrono::DateTime<Utc>| Holder {
+        user: "alice".to_string(),
+        host: "laptop".to_string(),
+        pid: 1,
+        since: since.format(catalog::VIEWED_FORMAT).to_string(),
+    };
+    fs::write(&lock, serde_json::to_vec(&someone(Utc::now())).unwrap()).unwrap();
+    assert!(matches!(
+        lock_document(&path),
+        Err(LockError::HeldError(holder)) if holder.user == "alice"
+    ));
+    assert_eq!(edited_by(&path).unwrap().host, "laptop");
+    // Not theirs to release.
+    unlock_document(&path);
+    assert!(lock.exists());
+
+    let crashed = Utc::now() - Duration::hours(DOCUMENT_STALE_HOURS + 1);
+    fs::write(&lock, serde_json::to_vec(&someone(crashed)).unwrap()).unwrap();
+    assert!(lock_document(&path).is_ok());
+    unlock_document(&path);
+    assert!(!lock.exists());
+
+    let catalog_lock = lock_catalog(dir.path()).unwrap();
+    let lock = locks_dir(dir.path()).join("catalog.lock");
+    assert!(lock.exists());
+    drop(catalog_lock);
+    assert!(!lock.exists());
+}
@@ -0,0 +1,802 @@
+use crate::pdf::{self, PdfError};
+use flate2::read::ZlibDecoder;
+use image::DynamicImage;
+use lopdf::{Dictionary, Object, ObjectId, Stream, StringFormat};
+use memmap2::Mmap;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Deepest page tree walked, deeper ones are taken for a loop.
+const MAX_DEPTH: usize = 32;
+
+/// Deepest nesting of arrays and dictionaries parsed, so crafted files can't overflow the
+/// stack.
+const MAX_NESTING: usize = 64;
+
+/// A PDF read from a memory-mapped file, for paging through long scans. Only where its
+/// objects start is read up front, a page's objects are parsed when it is shown, so a
+/// 300 page scan takes the memory of the page on screen rather than of the whole file.
+pub struct MappedPdf {
+    map: Mmap,
+    /// Where each object starts, by number. Later definitions win, as incremental
+    /// updates append the new version of an object.
+    offsets: HashMap<u32, usize>,
+    /// Objects kept in object streams, which are small and only hold dictionaries.
+    compressed: HashMap<u32, Object>,
+    /// The dictionary of the last cross-reference stream, the trailer of PDFs without one.
+    xref: Option<Dictionary>,
+    /// The object numbers of the pages, in order.
+    pages: Vec<u32>,
+}
+
+impl MappedPdf {
+    /// Maps the PDF at `path`. Encrypted PDFs and ones whose page tree can't be walked are
+    /// a `ReadError`, those have to be loaded whole.
+    ///
+    /// The file must not be truncated in place while mapped: reading the part that is gone
+    /// kills the process with SIGBUS. This app replaces files rather than writing them over,
+    /// see `pdf::save`, other programs editing the cabinet's folder might not.
+    pub fn open(path: &Path) -> Result<MappedPdf, PdfError> {
+        let file = File::open(path).map_err(|_| PdfError::ReadError)?;
+        // Safety: only sound as long as nobody truncates the file, see above.
+        let map = unsafe { Mmap::map(&file) }.map_err(|_| PdfError::ReadError)?;
+        let mut pdf = MappedPdf {
+            map,
+            offsets: HashMap::new(),
+            compressed: HashMap::new(),
+            xref: None,
+            pages: Vec::new(),
+        };
+        let object_streams = pdf.index();
+        for number in object_streams {
+            pdf.unpack(number);
+        }
+        let trailer = pdf.trailer().ok_or(PdfError::ReadError)?;
+        if trailer.has(b"Encrypt") {
+            return Err(PdfError::ReadError);
+        }
+        let root = pdf
+            .resolve(trailer.get(b"Root").map_err(|_| PdfError::ReadError)?)
+            .and_then(|root| root.as_dict().ok().cloned())
+            .ok_or(PdfError::ReadError)?;
+        let tree = root.get(b"Pages").map_err(|_| PdfError::ReadError)?;
+        let mut pages = Vec::new();
+        pdf.collect_pages(tree, &mut HashSet::new(), 0, &mut pages);
+        if pages.is_empty() {
+            return Err(PdfError::ReadError);
+        }
+        pdf.pages = pages;
+        Ok(pdf)
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// The image of page `number`, counted from 1, of a scanned PDF, decoded to fit in
+    /// `max_side` pixels, see `pdf::page_within`.
+    pub fn page_within(&self, number: u32, max_side: u32) -> Option<DynamicImage> {
+        let page = *self.pages.get((number as usize).checked_sub(1)?)?;
+        let image = self.page_image(page)?;
+        pdf::image_within(&image, max_side)
+    }
+
+    /// Records where every object starts, skipping over the content of streams so their
+    /// bytes aren't taken for objects. Returns the numbers of the object streams.
+    fn index(&mut self) -> Vec<u32> {
+        let bytes: &[u8] = &self.map;
+        let mut offsets = HashMap::new();
+        let mut object_streams = Vec::new();
+        let mut xref = None;
+        let mut pos = 0;
+        while let Some(found) = find(bytes, b"obj", pos) {
+            pos = found + 3;
+            let start = match object_start(bytes, found) {
+                Some(start) => start,
+                None => continue,
+            };
+            let mut parser = Parser::new(bytes, start);
+            let (number, _) = match parser.header() {
+                Some(id) => id,
+                None => continue,
+            };
+            offsets.insert(number, start);
+            if let Some((dict, content)) = parser.stream_extent(&offsets) {
+                match dict.get(b"Type").and_then(Object::as_name_str).ok() {
+                    Some("ObjStm") => object_streams.push(number),
+                    Some("XRef") => xref = Some(dict),
+                    _ => {}
+                }
+                pos = content.end;
+            }
+        }
+        self.offsets = offsets;
+        self.xref = xref;
+        object_streams
+    }
+
+    /// Parses the objects of the object stream `number` into `compressed`.
+    fn unpack(&mut self, number: u32) {
+        let stream = match self.object(number) {
+            Some(Object::Stream(stream)) => stream,
+            _ => return,
+        };
+        let content = match inflated(&stream) {
+            Some(content) => content,
+            None => return,
+        };
+        let get = |key: &[u8]| stream.dict.get(key).and_then(Object::as_i64).ok();
+        let (count, first) = match (get(b"N"), get(b"First")) {
+            (Some(count), Some(first)) if first >= 0 => (count, first as usize),
+            _ => return,
+        };
+        let mut header = Parser::new(&content, 0);
+        for _ in 0..count {
+            let (number, offset) = match (header.integer(), header.integer()) {
+                (Some(number), Some(offset)) if number >= 0 && offset >= 0 => {
+                    (number as u32, offset as usize)
+                }
+                _ => return,
+            };
+            // Objects written out plainly after the stream take precedence.
+            if self.offsets.contains_key(&number) {
+                continue;
+            }
+            if let Some(object) = Parser::new(&content, first + offset).object() {
+                self.compressed.insert(number, object);
+            }
+        }
+    }
+
+    /// The last trailer dictionary, or the dictionary of the last cross-reference stream.
+    fn trailer(&self) -> Option<Dictionary> {
+        let bytes: &[u8] = &self.map;
+        if let Some(found) = rfind(bytes, b"trailer") {
+            let mut parser = Parser::new(bytes, found + b"trailer".len());
+            if let Some(Object::Dictionary(trailer)) = parser.object() {
+                return Some(trailer);
+            }
+        }
+        self.xref.clone()
+    }
+
+    /// Parses object `number`, streams with their content.
+    fn object(&self, number: u32) -> Option<Object> {
+        if let Some(object) = self.compressed.get(&number) {
+            return Some(object.clone());
+        }
+        let mut parser = Parser::new(&self.map, *self.offsets.get(&number)?);
+        parser.header()?;
+        let mut body = parser.clone();
+        match parser.object()? {
+            Object::Dictionary(dict) if !parser.keyword(b"stream") => {
+                Some(Object::Dictionary(dict))
+            }
+            Object::Dictionary(_) => {
+                let (dict, content) = body.stream_extent(&self.offsets)?;
+                Some(Object::Stream(Stream::new(
+                    dict,
+                    self.map[content].to_vec(),
+                )))
+            }
+            object => Some(object),
+        }
+    }
+
+    /// `object`, or the one it refers to.
+    fn resolve(&self, object: &Object) -> Option<Object> {
+        match object {
+            Object::Reference((number, _)) => self.object(*number),
+            object => Some(object.clone()),
+        }
+    }
+
+    fn collect_pages(
+        &self,
+        node: &Object,
+        seen: &mut HashSet<u32>,
+        depth: usize,
+        pages: &mut Vec<u32>,
+    ) {
+        let number = match node {
+            Object::Reference((number, _)) => *number,
+            _ => return,
+        };
+        if depth > MAX_DEPTH || !seen.insert(number) {
+            return;
+        }
+        let dict = match self.object(number) {
+            Some(Object::Dictionary(dict)) => dict,
+            _ => return,
+        };
+        match dict.get(b"Kids").ok().and_then(|kids| self.resolve(kids)) {
+            Some(Object::Array(kids)) => {
+                for kid in &kids {
+                    self.collect_pages(kid, seen, depth + 1, pages);
+                }
+            }
+            _ => pages.push(number),
+        }
+    }
+
+    /// The resources of page `number`, inherited from its parents if it has none.
+    fn resources(&self, number: u32) -> Option<Dictionary> {
+        let mut node = self.object(number)?.as_dict().ok()?.clone();
+        for _ in 0..MAX_DEPTH {
+            if let Ok(resources) = node.get(b"Resources") {
+                return self.resolve(resources)?.as_dict().ok().cloned();
+            }
+            node = self
+                .resolve(node.get(b"Parent").ok()?)?
+                .as_dict()
+                .ok()?
+                .clone();
+        }
+        None
+    }
+
+    /// The one image a scanned page draws, with the references of its dictionary
+    /// resolved, see `pdf::page_image`.
+    fn page_image(&self, page: u32) -> Option<Stream> {
+        let resources = self.resources(page)?;
+        let xobjects = self.resolve(resources.get(b"XObject").ok()?)?;
+        let mut images: Vec<Stream> = xobjects
+            .as_dict()
+            .ok()?
+            .iter()
+            .filter_map(|(_, value)| match self.resolve(value)? {
+                Object::Stream(stream) => Some(stream),
+                _ => None,
+            })
+            .filter(|stream| {
+                stream
+                    .dict
+                    .get(b"Subtype")
+                    .and_then(Object::as_name_str)
+                    .ok()
+                    == Some("Image")
+            })
+            .collect();
+        if images.len() != 1 {
+            return None;
+        }
+        let mut image = images.pop()?;
+        let references: Vec<(Vec<u8>, u32)> = image
+            .dict
+            .iter()
+            .filter_map(|(key, value)| match value {
+                Object::Reference((number, _)) => Some((key.clone(), *number)),
+                _ => None,
+            })
+            .collect();
+        for (key, number) in references {
+            if let Some(value) = self.object(number) {
+                image.dict.set(key, value);
+            }
+        }
+        Some(image)
+    }
+}
+
+/// The content of a Flate compressed stream, e.g. an object stream.
+fn inflated(stream: &Stream) -> Option<Vec<u8>> {
+    match stream.filters().unwrap_or_default().as_slice() {
+        [] => Some(stream.content.clone()),
+        [filter] if filter == "FlateDecode" => {
+            let mut content = Vec::new();
+            ZlibDecoder::new(stream.content.as_slice())
+                .read_to_end(&mut content)
+                .ok()?;
+            Some(content)
+        }
+        _ => None,
+    }
+}
+
+fn find(bytes: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    bytes
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|i| from + i)
+}
+
+fn rfind(bytes: &[u8], needle: &[u8]) -> Option<usize> {
+    bytes
+        .windows(needle.len())
+        .rposition(|window| window == needle)
+}
+
+fn is_space(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\r' | b'\x0c' | b'\0')
+}
+
+fn is_delimiter(byte: u8) -> bool {
+    is_space(byte) || b"()<>[]{}/%".contains(&byte)
+}
+
+/// Where the `<number> <generation> obj` header ending in the `obj` at `found` starts.
+fn object_start(bytes: &[u8], found: usize) -> Option<usize> {
+    if bytes
+        .get(found + 3)
+        .is_some_and(|byte| !is_delimiter(*byte))
+    {
+        return None;
+    }
+    let mut i = found;
+    for _ in 0..2 {
+        let digits_end = i.checked_sub(1).filter(|&end| is_space(bytes[end]))?;
+        let mut start = digits_end;
+        while start > 0 && is_space(bytes[start - 1]) {
+            start -= 1;
+        }
+        let end = start;
+        while start > 0 && bytes[start - 1].is_ascii_digit() {
+            start -= 1;
+        }
+        if start == end {
+            return None;
+        }
+        i = start;
+    }
+    if i > 0 && !is_delimiter(bytes[i - 1]) {
+        return None;
+    }
+    Some(i)
+}
+
+/// Reads PDF objects from `bytes`, starting at `pos`.
+#[derive(Clone)]
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// How many arrays and dictionaries the object being parsed is in.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(bytes: &'a [u8], pos: usize) -> Self {
+        Parser {
+            bytes,
+            pos,
+            depth: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_space(&mut self) {
+        while let Some(byte) = self.peek() {
+            if is_space(byte) {
+                self.pos += 1;
+            } else if byte == b'%' {
+                while !matches!(self.peek(), None | Some(b'\n') | Some(b'\r')) {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Consumes `keyword` if it comes next.
+    fn keyword(&mut self, keyword: &[u8]) -> bool {
+        self.skip_space();
+        let end = self.pos + keyword.len();
+        let matches = self.bytes.get(self.pos..end) == Some(keyword)
+            && self.bytes.get(end).is_none_or(|byte| is_delimiter(*byte));
+        if matches {
+            self.pos = end;
+        }
+        matches
+    }
+
+    fn integer(&mut self) -> Option<i64> {
+        match self.object()? {
+            Object::Integer(integer) => Some(integer),
+            _ => None,
+        }
+    }
+
+    /// The `<number> <generation> obj` an indirect object starts with.
+    fn header(&mut self) -> Option<(u32, u16)> {
+        let number = self.integer()?;
+        let generation = self.integer()?;
+        if !self.keyword(b"obj") {
+            return None;
+        }
+        Some((u32::try_from(number).ok()?, u16::try_from(generation).ok()?))
+    }
+
+    /// After the header, the dictionary of a stream and where its content lies. The
+    /// length is looked up in `offsets` when it is a reference, the content runs up to
+    /// `endstream` when it can't be.
+    fn stream_extent(
+        &mut self,
+        offsets: &HashMap<u32, usize>,
+    ) -> Option<(Dictionary, std::ops::Range<usize>)> {
+        let dict = match self.object()? {
+            Object::Dictionary(dict) => dict,
+            _ => return None,
+        };
+        if !self.keyword(b"stream") {
+            return None;
+        }
+        match self.bytes.get(self.pos..self.pos + 2) {
+            Some(b"\r\n") => self.pos += 2,
+            Some([b'\n', _]) | Some([b'\r', _]) => self.pos += 1,
+            _ => {}
+        }
+        let start = self.pos;
+        let length = match dict.get(b"Length") {
+            Ok(Object::Integer(length)) => Some(*length),
+            Ok(Object::Reference((number, _))) => offsets.get(number).and_then(|offset| {
+                let mut parser = Parser::new(self.bytes, *offset);
+                parser.header()?;
+                parser.integer()
+            }),
+            _ => None,
+        };
+        let end = length
+            .and_then(|length| usize::try_from(length).ok())
+            .and_then(|length| start.checked_add(length))
+            .filter(|&end| {
+                let mut after = Parser::new(self.bytes, end);
+                after.keyword(b"endstream")
+            })
+            .or_else(|| find(self.bytes, b"endstream", start))?;
+        self.pos = end;
+        Some((dict, start..end.min(self.bytes.len())))
+    }
+
+    fn object(&mut self) -> Option<Object> {
+        self.skip_space();
+        let byte = self.peek()?;
+        match byte {
+            b'<' if self.bytes.get(self.pos + 1) == Some(&b'<') => self.nested(Parser::dictionary),
+            b'<' => {
+                self.pos += 1;
+                let end = find(self.bytes, b">", self.pos)?;
+                let digits: Vec<u8> = self.bytes[self.pos..end]
+                    .iter()
+                    .copied()
+                    .filter(u8::is_ascii_hexdigit)
+                    .collect();
+                self.pos = end + 1;
+                let string = digits
+                    .chunks(2)
+                    .map(|pair| {
+                        let hex = |d: u8| (d as char).to_digit(16).unwrap_or(0) as u8;
+                        hex(pair[0]) << 4 | pair.get(1).map_or(0, |d| hex(*d))
+                    })
+                    .collect();
+                Some(Object::String(string, StringFormat::Hexadecimal))
+            }
+            b'[' => self.nested(Parser::array),
+            b'(' => self.literal_string(),
+            b'/' => {
+                self.pos += 1;
+                let start = self.pos;
+                while self.peek().is_some_and(|byte| !is_delimiter(byte)) {
+                    self.pos += 1;
+                }
+                Some(Object::Name(unescape_name(&self.bytes[start..self.pos])))
+            }
+            b'0'..=b'9' | b'+' | b'-' | b'.' => self.number(),
+            _ if self.keyword(b"true") => Some(Object::Boolean(true)),
+            _ if self.keyword(b"false") => Some(Object::Boolean(false)),
+            _ if self.keyword(b"null") => Some(Object::Null),
+            _ => None,
+        }
+    }
+
+    /// Parses a container with `parse`, nothing when nested deeper than `MAX_NESTING`.
+    fn nested(&mut self, parse: fn(&mut Self) -> Option<Object>) -> Option<Object> {
+        if self.depth >= MAX_NESTING {
+            return None;
+        }
+        self.depth += 1;
+        let object = parse(self);
+        self.depth -= 1;
+        object
+    }
+
+    fn dictionary(&mut self) -> Option<Object> {
+        self.pos += 2;
+        let mut dict = Dictionary::new();
+        loop {
+            self.skip_space();
+            if self.bytes.get(self.pos..self.pos + 2) == Some(b">>") {
+                self.pos += 2;
+                return Some(Object::Dictionary(dict));
+            }
+            let key = match self.object()? {
+                Object::Name(key) => key,
+                _ => return None,
+            };
+            let value = self.object()?;
+            dict.set(key, value);
+        }
+    }
+
+    fn array(&mut self) -> Option<Object> {
+        self.pos += 1;
+        let mut array = Vec::new();
+        loop {
+            self.skip_space();
+            if self.peek()? == b']' {
+                self.pos += 1;
+                return Some(Object::Array(array));
+            }
+            array.push(self.object()?);
+        }
+    }
+
+    /// A number, or a reference when followed by a generation and `R`.
+    fn number(&mut self) -> Option<Object> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|byte| byte.is_ascii_digit() || b"+-.".contains(&byte))
+        {
+            self.pos += 1;
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.pos]).ok()?;
+        let integer = match text.parse::<i64>() {
+            Ok(integer) => integer,
+            Err(_) => return text.parse::<f64>().ok().map(Object::Real),
+        };
+        let mut ahead = self.clone();
+        ahead.skip_space();
+        let generation_start = ahead.pos;
+        while ahead.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+            ahead.pos += 1;
+        }
+        let generation = std::str::from_utf8(&self.bytes[generation_start..ahead.pos])
+            .ok()
+            .and_then(|generation| generation.parse::<u16>().ok());
+        if let (Ok(number), Some(generation)) = (u32::try_from(integer), generation) {
+            if ahead.keyword(b"R") {
+                *self = ahead;
+                let id: ObjectId = (number, generation);
+                return Some(Object::Reference(id));
+            }
+        }
+        Some(Object::Integer(integer))
+    }
+
+    fn literal_string(&mut self) -> Option<Object> {
+        self.pos += 1;
+        let mut string = Vec::new();
+        let mut depth = 0;
+        loop {
+            let byte = self.peek()?;
+            self.pos += 1;
+            match byte {
+                b'\\' => {
+                    let escaped = self.peek()?;
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => string.push(b'\n'),
+                        b'r' => string.push(b'\r'),
+                        b't' => string.push(b'\t'),
+                        b'b' => string.push(b'\x08'),
+                        b'f' => string.push(b'\x0c'),
+                        b'0'..=b'7' => {
+                            let mut value = u32::from(escaped - b'0');
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(digit @ b'0'..=b'7') => {
+                                        value = value * 8 + u32::from(digit - b'0');
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            string.push(value as u8);
+                        }
+                        // A line break after a backslash continues the string.
+                        b'\r' => {
+                            if self.peek() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        other => string.push(other),
+                    }
+                }
+                b'(' => {
+                    depth += 1;
+                    string.push(byte);
+                }
+                b')' if depth == 0 => return Some(Object::String(string, StringFormat::Literal)),
+                b')' => {
+                    depth -= 1;
+                    string.push(byte);
+                }
+                _ => string.push(byte),
+            }
+        }
+    }
+}
+
+/// A name with its `#xx` escapes decoded.
+fn unescape_name(name: &[u8]) -> Vec<u8> {
+    let mut unescaped = Vec::with_capacity(name.len());
+    let mut i = 0;
+    while i < name.len() {
+        let hex = name
+            .get(i + 1..i + 3)
+            .filter(|_| name[i] == b'#')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(byte) => {
+                unescaped.push(byte);
+                i += 3;
+            }
+            None => {
+                unescaped.push(name[i]);
+                i += 1;
+            }
+        }
+    }
+    unescaped
+}
+
+#[test]
+fn test_mapped_pdf() {
+    let dir = tempdir::TempDir::new("mapped").unwrap();
+    let paths: Vec<std::path::PathBuf> = (0..3)
+        .map(|i| {
+            let path = dir.path().join(format!("{}.png", i));
+            image::RgbImage::from_pixel(10 + i, 10, image::Rgb([0, 0, 0]))
+                .save(&path)
+                .unwrap();
+            path
+        })
+        .collect();
+    let path = dir.path().join("scan.pdf");
+    pdf::save(&mut pdf::concat(&paths).unwrap(), &path).unwrap();
+
+    let mapped = MappedPdf::open(&path).unwrap();
+    assert_eq!(mapped.page_count(), 3);
+    let width = |number| mapped.page_within(number, 2000).unwrap().to_rgb8().width();
+    assert_eq!((width(1), width(2), width(3)), (10, 11, 12));
+    assert!(mapped.page_within(4, 2000).is_none());
+
+    let mut parser = Parser::new(b"<< /A#20B [1 2.5 (a\\)b) <414> 3 0 R] /C true >>", 0);
+    let dict = match parser.object() {
+        Some(Object::Dictionary(dict)) => dict,
+        other => panic!("{:?}", other),
+    };
+    let array = dict.get(b"A B").unwrap().as_array().unwrap();
+    assert_eq!(array[0].as_i64().unwrap(), 1);
+    assert_eq!(array[2].as_str().unwrap(), b"a)b");
+    assert_eq!(array[3].as_str().unwrap(), b"A@");
+    assert_eq!(array[4].as_reference().unwrap(), (3, 0));
+    assert!(matches!(dict.get(b"C"), Ok(Object::Boolean(true))));
+}
+
+/// A one page PDF with its catalog and page tree in an object stream, and a
+/// cross-reference stream in place of the trailer.
+#[cfg(test)]
+fn xref_stream_pdf() -> Vec<u8> {
+    let catalog = "<< /Type /Catalog /Pages 3 0 R >>";
+    let pages = "<< /Type /Pages /Kids [4 0 R] /Count 1 >>";
+    let header = format!("2 0 3 {} ", catalog.len() + 1);
+    let objects = format!("{}{} {}", header, catalog, pages);
+    let mut pdf = format!(
+        "%PDF-1.5\n1 0 obj\n<< /Type /ObjStm /N 2 /First {} /Length {} >>\nstream\n{}\nendstream\nendobj\n",
+        header.len(),
+        objects.len(),
+        objects
+    )
+    .into_bytes();
+    pdf.extend_from_slice(
+        b"4 0 obj\n<< /Type /Page /Parent 3 0 R /Resources << /XObject << /Im 5 0 R >> >> >>\nendobj\n\
+        6 0 obj\n2\nendobj\n\
+        5 0 obj\n<< /Type /XObject /Subtype /Image /Width 2 /Height 1 /ColorSpace /DeviceGray \
+        /BitsPerComponent 8 /Length 6 0 R >>\nstream\n\x00\xff\nendstream\nendobj\n\
+        7 0 obj\n<< /Type /XRef /Size 8 /Root 2 0 R /W [1 1 1] /Length 0 >>\nstream\n\nendstream\nendobj\n\
+        startxref\n0\n%%EOF\n",
+    );
+    pdf
+}
+
+#[test]
+fn test_xref_stream() {
+    let dir = tempdir::TempDir::new("mapped").unwrap();
+    let path = dir.path().join("scan.pdf");
+    std::fs::write(&path, xref_stream_pdf()).unwrap();
+    let mapped = MappedPdf::open(&path).unwrap();
+    assert_eq!(mapped.page_count(), 1);
+    assert_eq!(mapped.page_within(1, 2000).unwrap().to_luma8().width(), 2);
+}
+
+#[test]
+fn test_incremental_update() {
+    let dir = tempdir::TempDir::new("mapped").unwrap();
+    let path = dir.path().join("scan.pdf");
+
+    // A second page appended to the page tree kept in the object stream.
+    let mut pdf = xref_stream_pdf();
+    pdf.extend_from_slice(
+        b"3 0 obj\n<< /Type /Pages /Kids [4 0 R 8 0 R] /Count 2 >>\nendobj\n\
+        8 0 obj\n<< /Type /Page /Parent 3 0 R /Resources << /XObject << /Im 9 0 R >> >> >>\nendobj\n\
+        9 0 obj\n<< /Type /XObject /Subtype /Image /Width 1 /Height 2 /ColorSpace /DeviceGray \
+        /BitsPerComponent 8 /Length 2 >>\nstream\n\x00\xff\nendstream\nendobj\n\
+        10 0 obj\n<< /Type /XRef /Size 11 /Root 2 0 R /Prev 0 /W [1 1 1] /Length 0 >>\nstream\n\nendstream\nendobj\n\
+        startxref\n0\n%%EOF\n",
+    );
+    std::fs::write(&path, pdf).unwrap();
+    let mapped = MappedPdf::open(&path).unwrap();
+    assert_eq!(mapped.page_count(), 2);
+    let width = |number| mapped.page_within(number, 2000).unwrap().to_luma8().width();
+    assert_eq!((width(1), width(2)), (2, 1));
+
+    // A new catalog with only the last page, after a trailer.
+    let paths: Vec<std::path::PathBuf> = (0..2)
+        .map(|i| {
+            let path = dir.path().join(format!("{}.png", i));
+            image::RgbImage::from_pixel(10 + i, 10, image::Rgb([0, 0, 0]))
+                .save(&path)
+                .unwrap();
+            path
+        })
+        .collect();
+    pdf::save(&mut pdf::concat(&paths).unwrap(), &path).unwrap();
+    let document = lopdf::Document::load(&path).unwrap();
+    let last = document.get_pages()[&2].0;
+    let next = document.max_id + 1;
+    let update = format!(
+        "\n{pages} 0 obj\n<< /Type /Pages /Kids [{last} 0 R] /Count 1 >>\nendobj\n\
+        {catalog} 0 obj\n<< /Type /Catalog /Pages {pages} 0 R >>\nendobj\n\
+        trailer\n<< /Size {size} /Root {catalog} 0 R >>\nstartxref\n0\n%%EOF\n",
+        pages = next,
+        catalog = next + 1,
+        size = next + 2,
+        last = last
+    );
+    let mut pdf = std::fs::read(&path).unwrap();
+    pdf.extend_from_slice(update.as_bytes());
+    std::fs::write(&path, pdf).unwrap();
+    let mapped = MappedPdf::open(&path).unwrap();
+    assert_eq!(mapped.page_count(), 1);
+    assert_eq!(mapped.page_within(1, 2000).unwrap().to_rgb8().width(), 11);
+}
+
+#[test]
+fn test_malformed() {
+    for bytes in &[&b"<< /A [1 2"[..], b"(abc", b"<41", b"<< 1 2 >>", b"]"] {
+        assert!(Parser::new(bytes, 0).object().is_none());
+    }
+    assert!(Parser::new(b"[[[1]]]", 0).object().is_some());
+    for open in &["[", "<< /A "] {
+        let deep = open.repeat(100_000);
+        assert!(Parser::new(deep.as_bytes(), 0).object().is_none());
+    }
+
+    // A length past the end of the file is taken for a wrong one.
+    let stream = b"<< /Length 9223372036854775807 >>\nstream\nab\nendstream";
+    let (_, content) = Parser::new(stream, 0)
+        .stream_extent(&HashMap::new())
+        .unwrap();
+    assert_eq!(&stream[content], b"ab\n");
+
+    // Cut anywhere, files are read as far as they go or turned down.
+    let dir = tempdir::TempDir::new("mapped").unwrap();
+    let path = dir.path().join("scan.pdf");
+    let pdf = xref_stream_pdf();
+    for end in 0..pdf.len() {
+        std::fs::write(&path, &pdf[..end]).unwrap();
+        if let Ok(mapped) = MappedPdf::open(&path) {
+            mapped.page_within(1, 2000);
+        }
+    }
+}
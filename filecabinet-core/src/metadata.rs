@@ -0,0 +1,296 @@
+use crate::decrypt;
+use crate::pdf::{self, PdfError};
+use crate::utils;
+use lopdf::{dictionary, Document, Object, Stream};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// What a PDF says about itself in its document information dictionary and XMP packet,
+/// or a photo in its EXIF data.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Creation date, `%Y-%m-%d`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+}
+
+lazy_static! {
+    static ref RE_XMP_TITLE: Regex =
+        Regex::new(r"(?s)<dc:title>.*?<rdf:li[^>]*>(?P<value>.*?)</rdf:li>").unwrap();
+    static ref RE_XMP_CREATOR: Regex =
+        Regex::new(r"(?s)<dc:creator>.*?<rdf:li[^>]*>(?P<value>.*?)</rdf:li>").unwrap();
+    static ref RE_XMP_CREATE_DATE: Regex =
+        Regex::new(r#"xmp:CreateDate(?:>|=")(?P<value>[^<"]+)"#).unwrap();
+    static ref RE_XMP_FILECABINET: Regex =
+        Regex::new(r"(?s)\s*<rdf:Description[^>]*xmlns:filecabinet=[^>]*>.*?</rdf:Description>")
+            .unwrap();
+}
+
+/// Namespace of the properties written by `write`.
+const XMP_NAMESPACE: &str = "https://d6e.io/ns/filecabinet/1.0/";
+
+/// Reads the metadata of the PDF or photo at `path`, `None` for other files or if the
+/// PDF can't be read.
+pub fn read(path: &Path) -> Option<Metadata> {
+    match utils::extension(path).as_str() {
+        "pdf" => Document::load(path)
+            .ok()
+            .map(|document| from_document(&document)),
+        "jpg" | "jpeg" | "heic" => Some(Metadata {
+            created: photo_date(path),
+            ..Default::default()
+        }),
+        _ => None,
+    }
+}
+
+/// When a photo was taken, from its EXIF `DateTimeOriginal`, as `%Y-%m-%d`.
+fn photo_date(path: &Path) -> Option<String> {
+    let mut file = BufReader::new(File::open(path).ok()?);
+    let exif = exif::Reader::new().read_from_container(&mut file).ok()?;
+    let field = exif.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)?;
+    match &field.value {
+        exif::Value::Ascii(values) => {
+            let taken = exif::DateTime::from_ascii(values.first()?).ok()?;
+            Some(format!(
+                "{:04}-{:02}-{:02}",
+                taken.year, taken.month, taken.day
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// The document information dictionary wins over the XMP packet, which fills the gaps.
+pub fn from_document(document: &Document) -> Metadata {
+    let info = document
+        .trailer
+        .get(b"Info")
+        .and_then(|info| match info {
+            Object::Reference(id) => document.get_dictionary(*id),
+            _ => info.as_dict(),
+        })
+        .ok();
+    let info_text = |key: &[u8]| {
+        info.and_then(|info| info.get(key).ok())
+            .and_then(|value| value.as_str().ok())
+            .map(decode_text_string)
+            .filter(|text| !text.trim().is_empty())
+    };
+    let xmp = xmp(document).unwrap_or_default();
+    let xmp_text = |re: &Regex| {
+        re.captures(&xmp)
+            .and_then(|c| c.name("value"))
+            .map(|m| unescape(m.as_str().trim()))
+            .filter(|text| !text.is_empty())
+    };
+    Metadata {
+        title: info_text(b"Title").or_else(|| xmp_text(&RE_XMP_TITLE)),
+        author: info_text(b"Author").or_else(|| xmp_text(&RE_XMP_CREATOR)),
+        created: info_text(b"CreationDate")
+            .or_else(|| xmp_text(&RE_XMP_CREATE_DATE))
+            .and_then(|date| utils::parse_date(&date.trim_start_matches("D:"))),
+    }
+}
+
+/// Stores the normalized fields of the PDF at `path` in its XMP packet, next to the
+/// properties already there.
+pub fn write(path: &Path, date: &str, institution: &str, name: &str) -> Result<(), PdfError> {
+    let mut document = Document::load(path).map_err(|_| PdfError::ReadError)?;
+    if decrypt::is_encrypted(&document) {
+        return Err(PdfError::EncryptionError);
+    }
+    let packet = with_properties(xmp(&document), date, institution, name);
+    // Left uncompressed so other tools can find it, as PDF/A requires.
+    let stream = Stream::new(
+        dictionary! { "Type" => "Metadata", "Subtype" => "XML" },
+        packet.into_bytes(),
+    )
+    .with_compression(false);
+    let existing = document
+        .catalog()
+        .and_then(|catalog| catalog.get(b"Metadata"))
+        .and_then(Object::as_reference);
+    match existing {
+        Ok(id) => {
+            document.objects.insert(id, Object::Stream(stream));
+        }
+        Err(_) => {
+            let id = document.add_object(stream);
+            let root = document
+                .trailer
+                .get(b"Root")
+                .and_then(Object::as_reference)
+                .map_err(|_| PdfError::ReadError)?;
+            document
+                .get_object_mut(root)
+                .and_then(Object::as_dict_mut)
+                .map_err(|_| PdfError::ReadError)?
+                .set("Metadata", id);
+        }
+    }
+    pdf::save(&mut document, path)
+}
+
+/// Adds the normalized fields to an XMP packet, replacing the ones written before, or
+/// builds a packet with just them.
+fn with_properties(xmp: Option<String>, date: &str, institution: &str, name: &str) -> String {
+    let description = format!(
+        r#"<rdf:Description rdf:about="" xmlns:filecabinet="{}">
+   <filecabinet:date>{}</filecabinet:date>
+   <filecabinet:institution>{}</filecabinet:institution>
+   <filecabinet:name>{}</filecabinet:name>
+  </rdf:Description>"#,
+        XMP_NAMESPACE,
+        escape(date),
+        escape(institution),
+        escape(name)
+    );
+    match xmp.filter(|xmp| xmp.contains("</rdf:RDF>")) {
+        Some(xmp) => RE_XMP_FILECABINET.replace_all(&xmp, "").replacen(
+            "</rdf:RDF>",
+            &format!("  {}\n </rdf:RDF>", description),
+            1,
+        ),
+        None => format!(
+            r#"{}
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  {}
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#,
+            pdf::XPACKET_BEGIN,
+            description
+        ),
+    }
+}
+
+/// The XMP packet of the document catalog, if any.
+fn xmp(document: &Document) -> Option<String> {
+    let catalog = document.catalog().ok()?;
+    let id = catalog.get(b"Metadata").ok()?.as_reference().ok()?;
+    let stream = document.get_object(id).ok()?.as_stream().ok()?;
+    let content = stream
+        .decompressed_content()
+        .unwrap_or_else(|_| stream.content.clone());
+    Some(String::from_utf8_lossy(&content).to_string())
+}
+
+/// Decodes a PDF text string: UTF-16BE with a byte order mark, or PDFDocEncoding, which
+/// matches Latin-1 for the characters that matter here.
+fn decode_text_string(bytes: &[u8]) -> String {
+    match bytes {
+        [0xfe, 0xff, rest @ ..] => {
+            let units: Vec<u16> = rest
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[test]
+fn test_from_document() {
+    use lopdf::{dictionary, Stream, StringFormat};
+    let mut document = Document::with_version("1.5");
+    let xmp = r#"<x:xmpmeta><rdf:RDF><rdf:Description xmp:CreateDate="2020-11-02T10:00:00Z">
+        <dc:title><rdf:Alt><rdf:li xml:lang="x-default">Ignored</rdf:li></rdf:Alt></dc:title>
+        <dc:creator><rdf:Seq><rdf:li>Bank &amp; Co</rdf:li></rdf:Seq></dc:creator>
+        </rdf:Description></rdf:RDF></x:xmpmeta>"#;
+    let metadata = document.add_object(Stream::new(dictionary! {}, xmp.as_bytes().to_vec()));
+    let catalog = document.add_object(dictionary! { "Type" => "Catalog", "Metadata" => metadata });
+    let mut title = vec![0xfe, 0xff];
+    title.extend(
+        "Statement €"
+            .encode_utf16()
+            .flat_map(|u| u.to_be_bytes().to_vec()),
+    );
+    let info = document.add_object(dictionary! {
+        "Title" => Object::String(title, StringFormat::Hexadecimal),
+        "CreationDate" => Object::string_literal("D:20210315120000+01'00'"),
+    });
+    document.trailer.set("Root", catalog);
+    document.trailer.set("Info", info);
+    assert_eq!(
+        from_document(&document),
+        Metadata {
+            title: Some("Statement €".to_string()),
+            author: Some("Bank & Co".to_string()),
+            created: Some("2021-03-15".to_string()),
+        }
+    );
+}
+
+#[test]
+fn test_with_properties() {
+    let packet = with_properties(None, "2021-03-15", "Bank", "Statement");
+    assert!(packet.contains("<filecabinet:institution>Bank</filecabinet:institution>"));
+    let packet = with_properties(
+        Some(packet.replace(
+            "</rdf:RDF>",
+            "  <rdf:Description><dc:title/></rdf:Description>\n </rdf:RDF>",
+        )),
+        "2021-03-15",
+        "Bank & Co",
+        "Statement",
+    );
+    assert_eq!(packet.matches("xmlns:filecabinet").count(), 1);
+    assert!(packet.contains("<filecabinet:institution>Bank &amp; Co</filecabinet:institution>"));
+    assert!(packet.contains("<dc:title/>"));
+}
+
+#[test]
+fn test_photo_date() {
+    let field = exif::Field {
+        tag: exif::Tag::DateTimeOriginal,
+        ifd_num: exif::In::PRIMARY,
+        value: exif::Value::Ascii(vec![b"2021:03:15 10:20:30".to_vec()]),
+    };
+    let mut writer = exif::experimental::Writer::new();
+    writer.push_field(&field);
+    let mut tiff = std::io::Cursor::new(Vec::new());
+    writer.write(&mut tiff, false).unwrap();
+    let tiff = tiff.into_inner();
+
+    let mut jpeg = Vec::new();
+    image::DynamicImage::new_rgb8(1, 1)
+        .write_to(&mut jpeg, image::ImageOutputFormat::Jpeg(90))
+        .unwrap();
+    // An APP1 segment with the EXIF data right after the start of image marker.
+    let mut photo = vec![0xff, 0xd8, 0xff, 0xe1];
+    photo.extend_from_slice(&(2 + 6 + tiff.len() as u16).to_be_bytes());
+    photo.extend_from_slice(b"Exif\0\0");
+    photo.extend_from_slice(&tiff);
+    photo.extend_from_slice(&jpeg[2..]);
+    let dir = tempdir::TempDir::new("metadata").unwrap();
+    let path = dir.path().join("IMG_0001.jpg");
+    std::fs::write(&path, photo).unwrap();
+    assert_eq!(
+        read(&path).and_then(|m| m.created).as_deref(),
+        Some("2021-03-15")
+    );
+}
@@ -0,0 +1,221 @@
+use flate2::read::DeflateDecoder;
+use std::convert::TryFrom;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// Word processor documents, which have no preview but whose text can be shown.
+pub const EXTENSIONS: [&str; 2] = ["docx", "odt"];
+
+/// Most bytes of text XML read, so a zip bomb can't take the memory.
+const MAX_XML: u64 = 32 * 1024 * 1024;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATE: u16 = 8;
+
+pub fn is_office(path: &str) -> bool {
+    EXTENSIONS.contains(&crate::utils::extension(path).as_str())
+}
+
+fn u16_at(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        <[u8; 2]>::try_from(data.get(at..at + 2)?).ok()?,
+    ))
+}
+
+fn u32_at(data: &[u8], at: usize) -> Option<usize> {
+    let value = u32::from_le_bytes(<[u8; 4]>::try_from(data.get(at..at + 4)?).ok()?);
+    usize::try_from(value).ok()
+}
+
+/// The file `name` of the zip `data`, decompressed. Only stored and deflated files are
+/// read, which is what word processors write.
+fn unzip(data: &[u8], name: &str) -> Option<Vec<u8>> {
+    // The end of central directory record, a comment may follow it.
+    let end = (0..data.len().saturating_sub(21))
+        .rev()
+        .find(|&at| data[at..].starts_with(b"PK\x05\x06"))?;
+    let entries = u16_at(data, end + 10)?;
+    let mut at = u32_at(data, end + 16)?;
+    for _ in 0..entries {
+        if !data.get(at..)?.starts_with(b"PK\x01\x02") {
+            return None;
+        }
+        let method = u16_at(data, at + 10)?;
+        let compressed = u32_at(data, at + 20)?;
+        let name_len = usize::from(u16_at(data, at + 28)?);
+        let extra_len = usize::from(u16_at(data, at + 30)?);
+        let comment_len = usize::from(u16_at(data, at + 32)?);
+        let offset = u32_at(data, at + 42)?;
+        if data.get(at + 46..at + 46 + name_len)? == name.as_bytes() {
+            // The local header has its own name and extra field lengths.
+            let start = offset
+                + 30
+                + usize::from(u16_at(data, offset + 26)?)
+                + usize::from(u16_at(data, offset + 28)?);
+            let content = data.get(start..start + compressed)?;
+            let mut xml = Vec::new();
+            match method {
+                METHOD_STORED => xml.extend_from_slice(content),
+                METHOD_DEFLATE => {
+                    DeflateDecoder::new(content)
+                        .take(MAX_XML)
+                        .read_to_end(&mut xml)
+                        .ok()?;
+                }
+                _ => return None,
+            }
+            return Some(xml);
+        }
+        at += 46 + name_len + extra_len + comment_len;
+    }
+    None
+}
+
+/// Replaces the entities of XML text with their characters.
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                unescaped.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// The text of the body XML of a document, a line per paragraph.
+fn xml_text(xml: &str) -> String {
+    let mut text = String::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&unescape(&rest[..start]));
+        let end = match rest[start..].find('>') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let tag = &rest[start + 1..end];
+        let name = tag
+            .trim_end_matches('/')
+            .split_whitespace()
+            .next()
+            .unwrap_or_default();
+        match name {
+            "/w:p" | "/text:p" | "/text:h" | "w:br" | "text:line-break" => text.push('\n'),
+            "w:tab" | "text:tab" => text.push('\t'),
+            _ => {}
+        }
+        rest = &rest[end + 1..];
+    }
+    text.trim().to_string()
+}
+
+/// The text of the `.docx` or `.odt` document at `path`, `None` if it can't be read.
+pub fn text(path: &Path) -> Option<String> {
+    let data = fs::read(path).ok()?;
+    let name = match crate::utils::extension(path).as_str() {
+        "docx" => "word/document.xml",
+        "odt" => "content.xml",
+        _ => return None,
+    };
+    let xml = unzip(&data, name)?;
+    Some(xml_text(&String::from_utf8_lossy(&xml)))
+}
+
+pub async fn extract(path: String) -> (String, Option<String>) {
+    let text = text(Path::new(&path));
+    (path, text)
+}
+
+#[test]
+fn test_text() {
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    // A zip with a stored file and a deflated one, as word processors write them.
+    let files: [(&str, u16, &[u8]); 2] = [
+        (
+            "mimetype",
+            METHOD_STORED,
+            b"application/vnd.oasis.opendocument.text",
+        ),
+        (
+            "content.xml",
+            METHOD_DEFLATE,
+            b"<?xml version=\"1.0\"?><office:text><text:h>Lease</text:h>\
+              <text:p>Rent:<text:tab/>1&#x2019;200 &amp; more</text:p><text:p/></office:text>",
+        ),
+    ];
+    let mut zip = Vec::new();
+    let mut directory = Vec::new();
+    for (name, method, content) in &files {
+        let content = match *method {
+            METHOD_DEFLATE => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(content).unwrap();
+                encoder.finish().unwrap()
+            }
+            _ => content.to_vec(),
+        };
+        let mut header = vec![0u8; 30];
+        header[..4].copy_from_slice(b"PK\x03\x04");
+        header[26..28].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        let mut entry = vec![0u8; 46];
+        entry[..4].copy_from_slice(b"PK\x01\x02");
+        entry[10..12].copy_from_slice(&method.to_le_bytes());
+        entry[20..24].copy_from_slice(&(content.len() as u32).to_le_bytes());
+        entry[28..30].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        entry[42..46].copy_from_slice(&(zip.len() as u32).to_le_bytes());
+        entry.extend_from_slice(name.as_bytes());
+        directory.extend(entry);
+        zip.extend(header);
+        zip.extend_from_slice(name.as_bytes());
+        zip.extend(content);
+    }
+    let mut end = vec![0u8; 22];
+    end[..4].copy_from_slice(b"PK\x05\x06");
+    end[10..12].copy_from_slice(&(files.len() as u16).to_le_bytes());
+    end[16..20].copy_from_slice(&(zip.len() as u32).to_le_bytes());
+    zip.extend(directory);
+    zip.extend(end);
+
+    let dir = tempdir::TempDir::new("office").unwrap();
+    let path = dir.path().join("lease.odt");
+    fs::write(&path, &zip).unwrap();
+    assert_eq!(text(&path).unwrap(), "Lease\nRent:\t1\u{2019}200 & more");
+    assert_eq!(unzip(&zip, "word/document.xml"), None);
+    assert_eq!(
+        xml_text("<w:p><w:r><w:t>A &lt;b&gt;</w:t></w:r><w:br/><w:t>c &bogus</w:t></w:p>"),
+        "A <b>\nc &bogus"
+    );
+    assert!(text(&dir.path().join("missing.docx")).is_none());
+}
@@ -0,0 +1,225 @@
+use crate::utils;
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use exif::experimental::Writer;
+use exif::{Exif, Field, In, Tag, Value};
+use image::{DynamicImage, ImageOutputFormat};
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+/// How an image has to be turned to be shown upright: mirrored horizontally first, then
+/// rotated clockwise by `turns` quarter turns. Covers the eight EXIF orientations.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Orientation {
+    pub mirrored: bool,
+    pub turns: u8,
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum RotateError {
+    ReadError,
+    ExifError,
+    WriteError,
+}
+
+impl Orientation {
+    pub fn turned(turns: u8) -> Self {
+        Orientation {
+            mirrored: false,
+            turns: turns % 4,
+        }
+    }
+
+    fn from_exif(value: u32) -> Self {
+        let (mirrored, turns) = match value {
+            2 => (true, 0),
+            3 => (false, 2),
+            4 => (true, 2),
+            5 => (true, 3),
+            6 => (false, 1),
+            7 => (true, 1),
+            8 => (false, 3),
+            _ => (false, 0),
+        };
+        Orientation { mirrored, turns }
+    }
+
+    fn exif(self) -> u16 {
+        match (self.mirrored, self.turns % 4) {
+            (false, 0) => 1,
+            (true, 0) => 2,
+            (false, 2) => 3,
+            (true, 2) => 4,
+            (true, 3) => 5,
+            (false, 1) => 6,
+            (true, 1) => 7,
+            _ => 8,
+        }
+    }
+
+    /// The orientation after `turns` more clockwise quarter turns.
+    pub fn rotated(self, turns: u8) -> Self {
+        Orientation {
+            turns: (self.turns + turns) % 4,
+            ..self
+        }
+    }
+
+    pub fn apply(self, image: DynamicImage) -> DynamicImage {
+        let image = if self.mirrored { image.fliph() } else { image };
+        match self.turns % 4 {
+            1 => image.rotate90(),
+            2 => image.rotate180(),
+            3 => image.rotate270(),
+            _ => image,
+        }
+    }
+}
+
+/// The EXIF orientation of a JPEG file's content, upright without one.
+pub fn parse(jpeg: &[u8]) -> Orientation {
+    exif::Reader::new()
+        .read_from_container(&mut Cursor::new(jpeg))
+        .map(|exif| of(&exif))
+        .unwrap_or_default()
+}
+
+fn of(exif: &Exif) -> Orientation {
+    exif.get_field(Tag::Orientation, In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .map(Orientation::from_exif)
+        .unwrap_or_default()
+}
+
+/// Turns the image at `path` by `turns` clockwise quarter turns for every viewer. JPEGs
+/// get a new EXIF orientation so they aren't recompressed, PNGs are re-encoded.
+pub async fn save(path: String, turns: u8) -> (String, Result<(), RotateError>) {
+    let file = Path::new(&path);
+    let rotated = match utils::extension(file).as_str() {
+        "jpg" | "jpeg" => fs::read(file)
+            .map_err(|_| RotateError::ReadError)
+            .and_then(|jpeg| with_orientation(&jpeg, turns)),
+        "png" => image::open(file)
+            .map_err(|_| RotateError::ReadError)
+            .and_then(|image| {
+                let mut png = Vec::new();
+                Orientation::turned(turns)
+                    .apply(image)
+                    .write_to(&mut png, ImageOutputFormat::Png)
+                    .map_err(|_| RotateError::WriteError)?;
+                Ok(png)
+            }),
+        _ => Err(RotateError::ReadError),
+    };
+    let saved = rotated.and_then(|data| {
+        AtomicFile::new(file, OverwriteBehavior::AllowOverwrite)
+            .write(|f| f.write_all(&data))
+            .map_err(|_| RotateError::WriteError)
+    });
+    (path, saved)
+}
+
+/// The JPEG with its EXIF orientation turned by `turns` quarter turns, keeping the other
+/// EXIF fields and the thumbnail.
+fn with_orientation(jpeg: &[u8], turns: u8) -> Result<Vec<u8>, RotateError> {
+    let exif = exif::Reader::new()
+        .read_from_container(&mut Cursor::new(jpeg))
+        .ok();
+    let orientation = Field {
+        tag: Tag::Orientation,
+        ifd_num: In::PRIMARY,
+        value: Value::Short(vec![exif
+            .as_ref()
+            .map(of)
+            .unwrap_or_default()
+            .rotated(turns)
+            .exif()]),
+    };
+    let mut writer = Writer::new();
+    writer.push_field(&orientation);
+    let mut little_endian = false;
+    if let Some(exif) = &exif {
+        little_endian = exif.little_endian();
+        for field in exif.fields() {
+            let replaced = field.tag == Tag::Orientation && field.ifd_num == In::PRIMARY;
+            // Fields of unknown types can't be written back.
+            if !replaced && !matches!(field.value, Value::Unknown(..)) {
+                writer.push_field(field);
+            }
+        }
+        let thumbnail = |tag| {
+            exif.get_field(tag, In::THUMBNAIL)
+                .and_then(|field| field.value.get_uint(0))
+        };
+        if let (Some(offset), Some(length)) = (
+            thumbnail(Tag::JPEGInterchangeFormat),
+            thumbnail(Tag::JPEGInterchangeFormatLength),
+        ) {
+            if let Some(data) = exif.buf().get(offset as usize..(offset + length) as usize) {
+                writer.set_jpeg(data, In::THUMBNAIL);
+            }
+        }
+    }
+    let mut tiff = Cursor::new(Vec::new());
+    writer
+        .write(&mut tiff, little_endian)
+        .map_err(|_| RotateError::ExifError)?;
+    replace_exif(jpeg, &tiff.into_inner())
+}
+
+/// Swaps the EXIF segment of a JPEG for one holding `tiff`, or adds it after the start
+/// of image and JFIF segments.
+fn replace_exif(jpeg: &[u8], tiff: &[u8]) -> Result<Vec<u8>, RotateError> {
+    if !jpeg.starts_with(&[0xff, 0xd8]) || tiff.len() + 8 > u16::MAX as usize {
+        return Err(RotateError::ExifError);
+    }
+    let mut segment = vec![0xff, 0xe1];
+    segment.extend_from_slice(&((2 + 6 + tiff.len()) as u16).to_be_bytes());
+    segment.extend_from_slice(b"Exif\0\0");
+    segment.extend_from_slice(tiff);
+
+    let mut position = 2;
+    let mut insert_at = 2;
+    // Walks the segments before the image data, which all start with a marker and length.
+    while position + 4 <= jpeg.len() && jpeg[position] == 0xff {
+        let marker = jpeg[position + 1];
+        let length = u16::from_be_bytes([jpeg[position + 2], jpeg[position + 3]]) as usize;
+        let end = position + 2 + length;
+        if marker == 0xda || end > jpeg.len() {
+            break;
+        }
+        if marker == 0xe1 && jpeg[position + 4..end].starts_with(b"Exif\0\0") {
+            return Ok([&jpeg[..position], &segment[..], &jpeg[end..]].concat());
+        }
+        if marker == 0xe0 {
+            insert_at = end;
+        }
+        position = end;
+    }
+    Ok([&jpeg[..insert_at], &segment[..], &jpeg[insert_at..]].concat())
+}
+
+#[test]
+fn test_with_orientation() {
+    let mut jpeg = Vec::new();
+    DynamicImage::new_rgb8(4, 2)
+        .write_to(&mut jpeg, ImageOutputFormat::Jpeg(90))
+        .unwrap();
+    let orientation = |jpeg: &[u8]| {
+        of(&exif::Reader::new()
+            .read_from_container(&mut Cursor::new(jpeg))
+            .unwrap())
+    };
+    let right = with_orientation(&jpeg, 1).unwrap();
+    assert_eq!(orientation(&right), Orientation::turned(1));
+    // Turning again replaces the segment instead of adding another one.
+    let upside_down = with_orientation(&right, 1).unwrap();
+    assert_eq!(orientation(&upside_down), Orientation::turned(2));
+    assert_eq!(upside_down.len(), right.len());
+    let image = image::load_from_memory(&upside_down).unwrap();
+    assert_eq!(
+        Orientation::turned(1).apply(image).to_rgb8().dimensions(),
+        (2, 4)
+    );
+}
@@ -0,0 +1,920 @@
+use crate::decrypt::{self, DecryptError};
+use crate::{downscale, similarity, versions};
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use flate2::read::ZlibDecoder;
+use image::DynamicImage;
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use std::convert::TryFrom;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+// A4 in points, images are scaled to fit on it.
+const PAGE_WIDTH: f64 = 595.0;
+const PAGE_HEIGHT: f64 = 842.0;
+
+/// Header of XMP packets, the byte order mark tells readers the encoding.
+pub const XPACKET_BEGIN: &str = "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>";
+
+/// Attributes a page may inherit from its parent `Pages` node.
+/// Size of the stamp text in points, and its distance from the corner of the page.
+const STAMP_SIZE: f64 = 8.0;
+const STAMP_MARGIN: f64 = 12.0;
+
+/// Name of the stamp font in the resources of the pages, unlikely to be taken.
+const STAMP_FONT: &str = "FStamp";
+
+const INHERITABLE: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum PdfError {
+    ReadError,
+    ImageError,
+    WriteError,
+    PasswordError,
+    EncryptionError,
+}
+
+/// A page of a scanned PDF written out as an image file.
+#[derive(Debug, Clone)]
+pub struct PageImage {
+    pub path: PathBuf,
+    /// Resolution that gives the image the size of the page it came from.
+    pub dpi: u32,
+}
+
+/// Concatenates PDFs and images into a single PDF with one bookmark per file.
+/// Returns the document and the page each file starts on, or `None` for files
+/// that can't be embedded (e.g. encrypted ones).
+pub fn merge<P: AsRef<Path>>(
+    parts: &[(P, String)],
+) -> Result<(Document, Vec<Option<u32>>), PdfError> {
+    let mut merged = Document::with_version("1.5");
+    let pages_id = merged.new_object_id();
+    let mut kids: Vec<ObjectId> = Vec::new();
+    let mut bookmarks: Vec<(String, ObjectId)> = Vec::new();
+    let mut starts = Vec::new();
+
+    for (path, title) in parts {
+        let pages = append(&mut merged, pages_id, path.as_ref())?;
+        match pages.first() {
+            Some(first) => {
+                starts.push(Some(kids.len() as u32 + 1));
+                bookmarks.push((title.clone(), *first));
+            }
+            None => starts.push(None),
+        }
+        kids.extend(pages);
+    }
+
+    let outlines_id = merged.new_object_id();
+    let item_ids: Vec<ObjectId> = bookmarks.iter().map(|_| merged.new_object_id()).collect();
+    for (i, (title, page_id)) in bookmarks.iter().enumerate() {
+        let mut item = dictionary! {
+            "Title" => text_string(title),
+            "Parent" => outlines_id,
+            "Dest" => vec![Object::Reference(*page_id), Object::Name(b"Fit".to_vec())],
+        };
+        if i > 0 {
+            item.set("Prev", item_ids[i - 1]);
+        }
+        if i + 1 < item_ids.len() {
+            item.set("Next", item_ids[i + 1]);
+        }
+        merged.objects.insert(item_ids[i], Object::Dictionary(item));
+    }
+    let mut outlines = dictionary! {
+        "Type" => "Outlines",
+        "Count" => item_ids.len() as i64,
+    };
+    if let (Some(first), Some(last)) = (item_ids.first(), item_ids.last()) {
+        outlines.set("First", *first);
+        outlines.set("Last", *last);
+    }
+    merged
+        .objects
+        .insert(outlines_id, Object::Dictionary(outlines));
+
+    insert_page_tree(&mut merged, pages_id, kids);
+    let catalog_id = merged.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+        "Outlines" => outlines_id,
+        "PageMode" => "UseOutlines",
+    });
+    merged.trailer.set("Root", catalog_id);
+
+    Ok((merged, starts))
+}
+
+/// Concatenates PDFs and images into a single PDF, without bookmarks.
+pub fn concat<P: AsRef<Path>>(paths: &[P]) -> Result<Document, PdfError> {
+    let mut merged = Document::with_version("1.5");
+    let pages_id = merged.new_object_id();
+    let mut kids: Vec<ObjectId> = Vec::new();
+    for path in paths {
+        kids.extend(append(&mut merged, pages_id, path.as_ref())?);
+    }
+    insert_page_tree(&mut merged, pages_id, kids);
+    let catalog_id = merged.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    merged.trailer.set("Root", catalog_id);
+    Ok(merged)
+}
+
+/// A PDF of `images`, one page each, e.g. pages flattened after they were edited.
+pub fn from_images(images: Vec<DynamicImage>) -> Result<Document, PdfError> {
+    let mut document = Document::with_version("1.5");
+    let pages_id = document.new_object_id();
+    let mut kids = Vec::with_capacity(images.len());
+    for image in images {
+        kids.push(append_pixels(&mut document, pages_id, image.into_rgb8())?);
+    }
+    insert_page_tree(&mut document, pages_id, kids);
+    let catalog_id = document.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    document.trailer.set("Root", catalog_id);
+    Ok(document)
+}
+
+pub fn save<P: AsRef<Path>>(document: &mut Document, path: P) -> Result<(), PdfError> {
+    document.compress();
+    // Documents may be saved over the file they were made from, so never leave half of one.
+    AtomicFile::new(path, OverwriteBehavior::AllowOverwrite)
+        .write(|f| document.save_to(f))
+        .map_err(|_| PdfError::WriteError)
+}
+
+/// Writes `text` small along the bottom of every page, e.g. who a copy was provided to.
+/// Characters outside of Latin-1 show as `?` in the standard font it is set in.
+pub fn stamp(document: &mut Document, text: &str) -> Result<(), PdfError> {
+    let font_id = document.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+        "Encoding" => "WinAnsiEncoding",
+    });
+    let latin1: Vec<u8> = text
+        .chars()
+        .map(|c| u8::try_from(c as u32).unwrap_or(b'?'))
+        .collect();
+    let resolved = |document: &Document, object: Option<Object>| match object {
+        Some(Object::Reference(id)) => document.get_dictionary(id).ok().cloned(),
+        Some(Object::Dictionary(dictionary)) => Some(dictionary),
+        _ => None,
+    };
+    let page_ids: Vec<ObjectId> = document.get_pages().values().copied().collect();
+    for page_id in page_ids {
+        let mut page = document
+            .get_dictionary(page_id)
+            .map_err(|_| PdfError::ReadError)?
+            .clone();
+        let (left, bottom) = page
+            .get(b"MediaBox")
+            .ok()
+            .cloned()
+            .or_else(|| inherited(document, &page, b"MediaBox"))
+            .as_ref()
+            .and_then(|b| b.as_array().ok())
+            .and_then(|b| Some((number(b.first()?)?, number(b.get(1)?)?)))
+            .unwrap_or((0.0, 0.0));
+
+        // The resources may be shared with other pages, the page gets a copy of its own.
+        let resources = page
+            .get(b"Resources")
+            .ok()
+            .cloned()
+            .or_else(|| inherited(document, &page, b"Resources"));
+        let mut resources = resolved(document, resources).unwrap_or_default();
+        let fonts = resources.get(b"Font").ok().cloned();
+        let mut fonts = resolved(document, fonts).unwrap_or_default();
+        fonts.set(STAMP_FONT, font_id);
+        resources.set("Font", fonts);
+        page.set("Resources", resources);
+
+        // The page's own drawing is wrapped in a saved graphics state, so whatever it
+        // leaves set doesn't move or hide the stamp.
+        let mut contents = vec![Object::Reference(
+            document.add_object(Stream::new(dictionary! {}, b"q\n".to_vec())),
+        )];
+        match page.get(b"Contents") {
+            Ok(Object::Array(parts)) => contents.extend(parts.iter().cloned()),
+            Ok(part) => contents.push(part.clone()),
+            Err(_) => {}
+        }
+        let stamp = Content {
+            operations: vec![
+                Operation::new("Q", vec![]),
+                Operation::new("BT", vec![]),
+                Operation::new(
+                    "Tf",
+                    vec![
+                        Object::Name(STAMP_FONT.as_bytes().to_vec()),
+                        STAMP_SIZE.into(),
+                    ],
+                ),
+                Operation::new("rg", vec![0.6.into(), 0.1.into(), 0.1.into()]),
+                Operation::new(
+                    "Td",
+                    vec![(left + STAMP_MARGIN).into(), (bottom + STAMP_MARGIN).into()],
+                ),
+                Operation::new(
+                    "Tj",
+                    vec![Object::String(latin1.clone(), StringFormat::Literal)],
+                ),
+                Operation::new("ET", vec![]),
+            ],
+        };
+        let stamp_id = document.add_object(Stream::new(
+            dictionary! {},
+            stamp.encode().map_err(|_| PdfError::WriteError)?,
+        ));
+        contents.push(Object::Reference(stamp_id));
+        page.set("Contents", contents);
+        document.objects.insert(page_id, Object::Dictionary(page));
+    }
+    Ok(())
+}
+
+/// The document at `path` as a PDF with `text` stamped on every page, see `stamp`. An
+/// image is put on a page of its own first.
+pub fn stamped(path: &Path, text: &str) -> Result<Vec<u8>, PdfError> {
+    if is_encrypted(path) {
+        return Err(PdfError::PasswordError);
+    }
+    let mut document = match crate::utils::extension(path).as_str() {
+        "pdf" => Document::load(path).map_err(|_| PdfError::ReadError)?,
+        _ => concat(&[path])?,
+    };
+    if document.get_pages().is_empty() {
+        return Err(PdfError::ReadError);
+    }
+    stamp(&mut document, text)?;
+    document.compress();
+    let mut stamped = Vec::new();
+    document
+        .save_to(&mut stamped)
+        .map_err(|_| PdfError::WriteError)?;
+    Ok(stamped)
+}
+
+/// Tags a document as PDF/A-2B: an sRGB output intent, the XMP identification and
+/// a file identifier.
+pub fn mark_pdfa(document: &mut Document, title: &str) -> Result<(), PdfError> {
+    let icc_id = document.add_object(Stream::new(dictionary! { "N" => 3 }, srgb_icc_profile()));
+    let intent_id = document.add_object(dictionary! {
+        "Type" => "OutputIntent",
+        "S" => "GTS_PDFA1",
+        "OutputConditionIdentifier" => Object::string_literal("sRGB"),
+        "DestOutputProfile" => icc_id,
+    });
+    // PDF/A readers must be able to find the metadata without decoding it.
+    let metadata_id = document.add_object(
+        Stream::new(
+            dictionary! { "Type" => "Metadata", "Subtype" => "XML" },
+            xmp_packet(title).into_bytes(),
+        )
+        .with_compression(false),
+    );
+    let catalog_id = document
+        .trailer
+        .get(b"Root")
+        .and_then(Object::as_reference)
+        .map_err(|_| PdfError::ReadError)?;
+    let catalog = document
+        .get_object_mut(catalog_id)
+        .and_then(Object::as_dict_mut)
+        .map_err(|_| PdfError::ReadError)?;
+    catalog.set("OutputIntents", vec![Object::Reference(intent_id)]);
+    catalog.set("Metadata", metadata_id);
+
+    let id = Object::String(
+        rand::random::<[u8; 16]>().to_vec(),
+        StringFormat::Hexadecimal,
+    );
+    document.trailer.set("ID", vec![id.clone(), id]);
+    document.trailer.remove(b"Info");
+    Ok(())
+}
+
+/// Whether the PDF at `path` needs a password to be read.
+pub fn is_encrypted(path: &Path) -> bool {
+    Document::load(path)
+        .map(|document| decrypt::is_encrypted(&document))
+        .unwrap_or(false)
+}
+
+/// Opens the password protected PDF at `path` with its user or owner `password`.
+pub fn unlock(path: &Path, password: &str) -> Result<Document, PdfError> {
+    let mut document = Document::load(path).map_err(|_| PdfError::ReadError)?;
+    decrypt::decrypt(&mut document, password).map_err(|error| match error {
+        DecryptError::PasswordError => PdfError::PasswordError,
+        DecryptError::UnsupportedError => PdfError::EncryptionError,
+    })?;
+    // Objects packed in encrypted object streams can't be loaded.
+    if document.get_pages().is_empty() {
+        return Err(PdfError::EncryptionError);
+    }
+    Ok(document)
+}
+
+/// Rewrites the PDF at `path` with its pages rearranged, see `rearrange`. The document
+/// as it was is kept as an earlier version.
+pub async fn edit_pages(path: String, pages: Vec<(u32, u8)>) -> (String, Result<(), PdfError>) {
+    let edited = Document::load(&path)
+        .map_err(|_| PdfError::ReadError)
+        .and_then(|mut document| {
+            if decrypt::is_encrypted(&document) {
+                return Err(PdfError::PasswordError);
+            }
+            rearrange(&mut document, &pages)?;
+            versions::keep(Path::new(&path)).map_err(|_| PdfError::WriteError)?;
+            save(&mut document, &path)
+        });
+    (path, edited)
+}
+
+/// Rewrites the PDF at `path` without its blank pages, see `blank_pages`, keeping the
+/// document as it was as an earlier version. Returns the numbers of the dropped pages.
+/// Documents that are blank throughout are left alone, there would be nothing left.
+pub fn drop_blank_pages(path: &Path, max_ink: f32) -> Result<Vec<u32>, PdfError> {
+    let mut document = Document::load(path).map_err(|_| PdfError::ReadError)?;
+    if decrypt::is_encrypted(&document) {
+        return Err(PdfError::PasswordError);
+    }
+    let blank = blank_pages(&document, max_ink);
+    let count = document.get_pages().len() as u32;
+    if blank.is_empty() || blank.len() as u32 == count {
+        return Ok(Vec::new());
+    }
+    let kept: Vec<(u32, u8)> = (1..=count)
+        .filter(|number| !blank.contains(number))
+        .map(|number| (number, 0))
+        .collect();
+    rearrange(&mut document, &kept)?;
+    versions::keep(path).map_err(|_| PdfError::WriteError)?;
+    save(&mut document, path)?;
+    Ok(blank)
+}
+
+/// Writes the image of each page of a scanned PDF into `dir`, in page order. Fails
+/// with `ImageError` unless every page is a single JPEG or 8 bit RGB or gray image.
+pub fn extract_page_images(path: &Path, dir: &Path) -> Result<Vec<PageImage>, PdfError> {
+    let document = Document::load(path).map_err(|_| PdfError::ReadError)?;
+    page_images(&document, dir)
+}
+
+/// Same as `extract_page_images`, for a document already loaded.
+pub fn page_images(document: &Document, dir: &Path) -> Result<Vec<PageImage>, PdfError> {
+    document
+        .get_pages()
+        .into_values()
+        .enumerate()
+        .map(|(i, page_id)| {
+            let image = page_image(document, page_id).ok_or(PdfError::ImageError)?;
+            let path = write_image(image, &dir.join(format!("page{:04}", i + 1)))?;
+            let width = image
+                .dict
+                .get(b"Width")
+                .and_then(Object::as_i64)
+                .map_err(|_| PdfError::ImageError)?;
+            let page = document
+                .get_dictionary(page_id)
+                .map_err(|_| PdfError::ReadError)?;
+            let media_box = page
+                .get(b"MediaBox")
+                .ok()
+                .cloned()
+                .or_else(|| inherited(document, page, b"MediaBox"));
+            let page_width = media_box
+                .as_ref()
+                .and_then(|b| b.as_array().ok())
+                .and_then(|b| Some(number(b.get(2)?)? - number(b.first()?)?))
+                .filter(|w| *w > 0.0)
+                .unwrap_or(PAGE_WIDTH);
+            Ok(PageImage {
+                path,
+                dpi: (width as f64 * 72.0 / page_width).round() as u32,
+            })
+        })
+        .collect()
+}
+
+fn number(object: &Object) -> Option<f64> {
+    match object {
+        Object::Integer(i) => Some(*i as f64),
+        Object::Real(r) => Some(*r),
+        _ => None,
+    }
+}
+
+/// The only image a page shows, `None` if it shows none or several.
+fn page_image(document: &Document, page_id: ObjectId) -> Option<&Stream> {
+    let (resources, resource_ids) = document.get_page_resources(page_id);
+    let xobjects = resources
+        .into_iter()
+        .chain(
+            resource_ids
+                .iter()
+                .filter_map(|id| document.get_dictionary(*id).ok()),
+        )
+        .find_map(|resources| {
+            let (_, xobjects) = document.dereference(resources.get(b"XObject").ok()?).ok()?;
+            xobjects.as_dict().ok()
+        })?;
+    let images: Vec<&Stream> = xobjects
+        .iter()
+        .filter_map(|(_, value)| match document.dereference(value).ok()? {
+            (_, Object::Stream(stream)) => Some(stream),
+            _ => None,
+        })
+        .filter(|stream| {
+            stream
+                .dict
+                .get(b"Subtype")
+                .and_then(Object::as_name_str)
+                .ok()
+                == Some("Image")
+        })
+        .collect();
+    match images.as_slice() {
+        [image] => Some(image),
+        _ => None,
+    }
+}
+
+/// The image of the first page of a scanned PDF, decoded in memory so the pages of a
+/// decrypted document never reach the disk.
+pub fn first_page(document: &Document) -> Option<DynamicImage> {
+    let page_id = *document.get_pages().values().next()?;
+    decode_image(page_image(document, page_id)?).ok()
+}
+
+/// The image of page `number`, counted from 1, of a scanned PDF.
+pub fn page(document: &Document, number: u32) -> Option<DynamicImage> {
+    let page_id = *document.get_pages().get(&number)?;
+    decode_image(page_image(document, page_id)?).ok()
+}
+
+/// Same as `page`, decoded to fit in `max_side` pixels, see `downscale`.
+pub fn page_within(document: &Document, number: u32, max_side: u32) -> Option<DynamicImage> {
+    let page_id = *document.get_pages().get(&number)?;
+    image_within(page_image(document, page_id)?, max_side)
+}
+
+/// Decodes the image of a scanned page to fit in `max_side` pixels.
+pub fn image_within(image: &Stream, max_side: u32) -> Option<DynamicImage> {
+    if image.filters().unwrap_or_default() == ["DCTDecode"] {
+        return downscale::jpeg(&image.content, max_side);
+    }
+    decode_image(image)
+        .ok()
+        .map(|image| downscale::fit(image, max_side))
+}
+
+/// Operators that put ink on a page: text, images, painted paths and shadings.
+const PAINTING: [&str; 17] = [
+    "Tj", "TJ", "'", "\"", "Do", "BI", "sh", "f", "F", "f*", "B", "B*", "b", "b*", "S", "s", "EI",
+];
+
+/// Whether page `number`, counted from 1, draws nothing at all. Scanned pages draw their
+/// image, see `similarity::ink` for those.
+pub fn is_empty_page(document: &Document, number: u32) -> bool {
+    document
+        .get_pages()
+        .get(&number)
+        .and_then(|id| document.get_and_decode_page_content(*id).ok())
+        .is_some_and(|content| {
+            !content
+                .operations
+                .iter()
+                .any(|operation| PAINTING.contains(&operation.operator.as_str()))
+        })
+}
+
+/// The numbers of the pages of `document` that draw nothing, or are scans with at most
+/// `max_ink` of them inked.
+pub fn blank_pages(document: &Document, max_ink: f32) -> Vec<u32> {
+    (1..=document.get_pages().len() as u32)
+        .filter(|number| {
+            is_empty_page(document, *number)
+                || page(document, *number).is_some_and(|image| similarity::ink(&image) <= max_ink)
+        })
+        .collect()
+}
+
+/// Rebuilds the pages of `document` from `pages`: the numbers of the pages to keep,
+/// counted from 1, in their new order, with the clockwise quarter turns to add to each.
+pub fn rearrange(document: &mut Document, pages: &[(u32, u8)]) -> Result<(), PdfError> {
+    let page_ids = document.get_pages();
+    let pages_id = document
+        .catalog()
+        .and_then(|catalog| catalog.get(b"Pages"))
+        .and_then(Object::as_reference)
+        .map_err(|_| PdfError::ReadError)?;
+    let mut kids = Vec::with_capacity(pages.len());
+    for (number, turns) in pages {
+        let id = *page_ids.get(number).ok_or(PdfError::ReadError)?;
+        let mut page = document
+            .get_dictionary(id)
+            .map_err(|_| PdfError::ReadError)?
+            .clone();
+        // Pages may move out of the node they inherit from, so pull everything down.
+        for key in INHERITABLE.iter() {
+            if page.get(key).is_err() {
+                if let Some(value) = inherited(document, &page, key) {
+                    page.set(key.to_vec(), value);
+                }
+            }
+        }
+        let rotate = page.get(b"Rotate").and_then(Object::as_i64).unwrap_or(0);
+        page.set("Rotate", (rotate + 90 * *turns as i64).rem_euclid(360));
+        page.set("Parent", pages_id);
+        document.objects.insert(id, Object::Dictionary(page));
+        kids.push(id);
+    }
+    let root = document
+        .get_object_mut(pages_id)
+        .and_then(Object::as_dict_mut)
+        .map_err(|_| PdfError::ReadError)?;
+    root.set("Count", kids.len() as i64);
+    root.set(
+        "Kids",
+        kids.into_iter().map(Object::Reference).collect::<Vec<_>>(),
+    );
+    document.prune_objects();
+    Ok(())
+}
+
+/// Writes an image XObject next to `base`, as is for JPEGs and as PNG otherwise.
+fn write_image(image: &Stream, base: &Path) -> Result<PathBuf, PdfError> {
+    if image.filters().unwrap_or_default() == ["DCTDecode"] {
+        let path = base.with_extension("jpg");
+        fs::write(&path, &image.content).map_err(|_| PdfError::WriteError)?;
+        return Ok(path);
+    }
+    let path = base.with_extension("png");
+    decode_image(image)?
+        .save(&path)
+        .map_err(|_| PdfError::WriteError)?;
+    Ok(path)
+}
+
+/// Decodes a JPEG or an 8 bit RGB or gray image XObject.
+fn decode_image(image: &Stream) -> Result<DynamicImage, PdfError> {
+    let filters = image.filters().unwrap_or_default();
+    if filters == ["DCTDecode"] {
+        return image::load_from_memory(&image.content).map_err(|_| PdfError::ImageError);
+    }
+
+    let samples = match filters.as_slice() {
+        [] => image.content.clone(),
+        [filter] if filter == "FlateDecode" && image.dict.get(b"DecodeParms").is_err() => {
+            let mut samples = Vec::new();
+            ZlibDecoder::new(image.content.as_slice())
+                .read_to_end(&mut samples)
+                .map_err(|_| PdfError::ImageError)?;
+            samples
+        }
+        _ => return Err(PdfError::ImageError),
+    };
+    let get = |key: &[u8]| image.dict.get(key).and_then(Object::as_i64).ok();
+    let (width, height) = match (get(b"Width"), get(b"Height"), get(b"BitsPerComponent")) {
+        (Some(width), Some(height), Some(8)) => (width as u32, height as u32),
+        _ => return Err(PdfError::ImageError),
+    };
+    let color_space = image.dict.get(b"ColorSpace").and_then(Object::as_name_str);
+    let decoded = match color_space {
+        Ok("DeviceRGB") => {
+            image::RgbImage::from_raw(width, height, samples).map(DynamicImage::ImageRgb8)
+        }
+        Ok("DeviceGray") => {
+            image::GrayImage::from_raw(width, height, samples).map(DynamicImage::ImageLuma8)
+        }
+        _ => None,
+    };
+    decoded.ok_or(PdfError::ImageError)
+}
+
+fn append(
+    merged: &mut Document,
+    pages_id: ObjectId,
+    path: &Path,
+) -> Result<Vec<ObjectId>, PdfError> {
+    match crate::utils::extension(path).as_str() {
+        "pdf" => append_pdf(merged, pages_id, path),
+        "jpg" | "jpeg" | "png" => Ok(vec![append_image(merged, pages_id, path)?]),
+        _ => Ok(Vec::new()),
+    }
+}
+
+fn insert_page_tree(merged: &mut Document, pages_id: ObjectId, kids: Vec<ObjectId>) {
+    let pages = dictionary! {
+        "Type" => "Pages",
+        "Count" => kids.len() as i64,
+        "Kids" => kids.into_iter().map(Object::Reference).collect::<Vec<_>>(),
+    };
+    merged.objects.insert(pages_id, Object::Dictionary(pages));
+}
+
+/// Moves every page of the PDF at `path` into `merged`, returning the page ids in order.
+fn append_pdf(
+    merged: &mut Document,
+    pages_id: ObjectId,
+    path: &Path,
+) -> Result<Vec<ObjectId>, PdfError> {
+    let mut document = Document::load(path).map_err(|_| PdfError::ReadError)?;
+    document.renumber_objects_with(merged.max_id + 1);
+    merged.max_id = document.max_id;
+
+    let page_ids: Vec<ObjectId> = document.get_pages().into_values().collect();
+    // The page tree is rebuilt, so pull down whatever the pages inherited from it.
+    let pages: Vec<(ObjectId, Dictionary)> = page_ids
+        .iter()
+        .filter_map(|id| {
+            let mut page = document.get_dictionary(*id).ok()?.clone();
+            for key in INHERITABLE.iter() {
+                if page.get(key).is_err() {
+                    if let Some(value) = inherited(&document, &page, key) {
+                        page.set(key.to_vec(), value);
+                    }
+                }
+            }
+            page.set("Parent", pages_id);
+            Some((*id, page))
+        })
+        .collect();
+
+    for (id, object) in document.objects {
+        match object.type_name().unwrap_or("") {
+            "Catalog" | "Pages" | "Outlines" | "Page" => {}
+            _ => {
+                merged.objects.insert(id, object);
+            }
+        }
+    }
+    for (id, page) in pages.iter() {
+        merged.objects.insert(*id, Object::Dictionary(page.clone()));
+    }
+    Ok(pages.into_iter().map(|(id, _)| id).collect())
+}
+
+fn inherited(document: &Document, page: &Dictionary, key: &[u8]) -> Option<Object> {
+    let mut parent = page.get(b"Parent").and_then(Object::as_reference).ok();
+    while let Some(id) = parent {
+        let node = document.get_dictionary(id).ok()?;
+        if let Ok(value) = node.get(key) {
+            return Some(value.clone());
+        }
+        parent = node.get(b"Parent").and_then(Object::as_reference).ok();
+    }
+    None
+}
+
+/// Adds a page showing the image at `path` scaled to fit on A4.
+fn append_image(
+    merged: &mut Document,
+    pages_id: ObjectId,
+    path: &Path,
+) -> Result<ObjectId, PdfError> {
+    let image = image::open(path).map_err(|_| PdfError::ImageError)?;
+    append_pixels(merged, pages_id, image.into_rgb8())
+}
+
+/// Adds a page showing `image` scaled to fit on A4.
+fn append_pixels(
+    merged: &mut Document,
+    pages_id: ObjectId,
+    image: image::RgbImage,
+) -> Result<ObjectId, PdfError> {
+    let (width, height) = image.dimensions();
+    let mut stream = Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => width as i64,
+            "Height" => height as i64,
+            "ColorSpace" => "DeviceRGB",
+            "BitsPerComponent" => 8,
+        },
+        image.into_raw(),
+    );
+    let _ = stream.compress();
+    let image_id = merged.add_object(stream);
+
+    let scale = (PAGE_WIDTH / width as f64).min(PAGE_HEIGHT / height as f64);
+    let (page_width, page_height) = (width as f64 * scale, height as f64 * scale);
+    let content = Content {
+        operations: vec![
+            Operation::new("q", vec![]),
+            Operation::new(
+                "cm",
+                vec![
+                    page_width.into(),
+                    0.into(),
+                    0.into(),
+                    page_height.into(),
+                    0.into(),
+                    0.into(),
+                ],
+            ),
+            Operation::new("Do", vec![Object::Name(b"Im0".to_vec())]),
+            Operation::new("Q", vec![]),
+        ],
+    };
+    let content_id = merged.add_object(Stream::new(
+        dictionary! {},
+        content.encode().map_err(|_| PdfError::WriteError)?,
+    ));
+    Ok(merged.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "Contents" => content_id,
+        "MediaBox" => vec![0.into(), 0.into(), page_width.into(), page_height.into()],
+        "Resources" => dictionary! {
+            "XObject" => dictionary! {
+                "Im0" => image_id,
+            },
+        },
+    }))
+}
+
+/// Encodes text as a UTF-16BE PDF string so bookmark titles survive any characters.
+fn text_string(text: &str) -> Object {
+    let mut bytes = vec![0xfe, 0xff];
+    for unit in text.encode_utf16() {
+        bytes.extend_from_slice(&unit.to_be_bytes());
+    }
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
+
+/// The XMP packet identifying a PDF/A-2B document.
+fn xmp_packet(title: &str) -> String {
+    let title = title
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    format!(
+        r#"{}
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:pdfaid="http://www.aiim.org/pdfa/ns/id/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/">
+   <pdfaid:part>2</pdfaid:part>
+   <pdfaid:conformance>B</pdfaid:conformance>
+   <dc:title><rdf:Alt><rdf:li xml:lang="x-default">{}</rdf:li></rdf:Alt></dc:title>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#,
+        XPACKET_BEGIN, title
+    )
+}
+
+/// Builds a minimal ICC v2 display profile for sRGB: D50 adapted primaries and a
+/// 2.2 gamma curve, which is enough for an output intent.
+fn srgb_icc_profile() -> Vec<u8> {
+    fn s15f16(value: f64) -> [u8; 4] {
+        ((value * 65536.0).round() as i32).to_be_bytes()
+    }
+    fn xyz(x: f64, y: f64, z: f64) -> Vec<u8> {
+        let mut tag = b"XYZ \0\0\0\0".to_vec();
+        for value in [x, y, z].iter() {
+            tag.extend_from_slice(&s15f16(*value));
+        }
+        tag
+    }
+    let description = b"sRGB";
+    let mut desc = b"desc\0\0\0\0".to_vec();
+    desc.extend_from_slice(&(description.len() as u32 + 1).to_be_bytes());
+    desc.extend_from_slice(description);
+    // Terminating null, then empty Unicode and ScriptCode descriptions.
+    desc.extend_from_slice(&[0; 1 + 4 + 4 + 2 + 1 + 67]);
+    let cprt = b"text\0\0\0\0No copyright, use freely\0".to_vec();
+    let mut curve = b"curv\0\0\0\0".to_vec();
+    curve.extend_from_slice(&1u32.to_be_bytes());
+    curve.extend_from_slice(&((2.2 * 256.0) as u16).to_be_bytes());
+
+    let tags: [(&[u8; 4], Vec<u8>); 9] = [
+        (b"desc", desc),
+        (b"cprt", cprt),
+        (b"wtpt", xyz(0.9642, 1.0, 0.8249)),
+        (b"rXYZ", xyz(0.4361, 0.2225, 0.0139)),
+        (b"gXYZ", xyz(0.3851, 0.7169, 0.0971)),
+        (b"bXYZ", xyz(0.1431, 0.0606, 0.7141)),
+        (b"rTRC", curve.clone()),
+        (b"gTRC", curve.clone()),
+        (b"bTRC", curve),
+    ];
+
+    let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+    let mut data = Vec::new();
+    let data_start = 128 + 4 + 12 * tags.len();
+    for (signature, tag) in tags.iter() {
+        table.extend_from_slice(*signature);
+        table.extend_from_slice(&((data_start + data.len()) as u32).to_be_bytes());
+        table.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+        data.extend_from_slice(tag);
+        // Tags start on 4 byte boundaries.
+        data.resize(data.len().div_ceil(4) * 4, 0);
+    }
+
+    let size = data_start + data.len();
+    let mut profile = Vec::with_capacity(size);
+    profile.extend_from_slice(&(size as u32).to_be_bytes());
+    profile.extend_from_slice(&[0; 4]);
+    profile.extend_from_slice(&0x0210_0000u32.to_be_bytes());
+    profile.extend_from_slice(b"mntrRGB XYZ ");
+    for part in [2021u16, 1, 1, 0, 0, 0].iter() {
+        profile.extend_from_slice(&part.to_be_bytes());
+    }
+    profile.extend_from_slice(b"acsp");
+    // Platform, flags, manufacturer, model, attributes and rendering intent.
+    profile.extend_from_slice(&[0; 4 + 4 + 4 + 4 + 8 + 4]);
+    profile.extend_from_slice(&xyz(0.9642, 1.0, 0.8249)[8..]);
+    // Creator, profile id and reserved bytes.
+    profile.extend_from_slice(&[0; 4 + 16 + 28]);
+    profile.extend_from_slice(&table);
+    profile.extend_from_slice(&data);
+    profile
+}
+
+#[test]
+fn test_srgb_icc_profile() {
+    let profile = srgb_icc_profile();
+    let size = u32::from_be_bytes([profile[0], profile[1], profile[2], profile[3]]);
+    assert_eq!(size as usize, profile.len());
+    assert_eq!(&profile[36..40], b"acsp");
+    assert_eq!(&profile[128..132], &9u32.to_be_bytes());
+}
+
+#[test]
+fn test_rearrange() {
+    let dir = tempdir::TempDir::new("pdf").unwrap();
+    let paths: Vec<PathBuf> = (0..3)
+        .map(|i| {
+            let path = dir.path().join(format!("{}.png", i));
+            // The second page is inked, the others blank.
+            let shade = if i == 1 { 0 } else { 255 };
+            image::RgbImage::from_pixel(10 + i, 10, image::Rgb([shade, shade, shade]))
+                .save(&path)
+                .unwrap();
+            path
+        })
+        .collect();
+    let mut document = concat(&paths).unwrap();
+    assert!(!is_empty_page(&document, 1));
+
+    rearrange(&mut document, &[(3, 1), (1, 0)]).unwrap();
+    let pages = document.get_pages();
+    assert_eq!(pages.len(), 2);
+    let width = |number: u32| page(&document, number).unwrap().to_rgb8().width();
+    assert_eq!((width(1), width(2)), (12, 10));
+    let rotate = |number: u32| {
+        document
+            .get_dictionary(pages[&number])
+            .unwrap()
+            .get(b"Rotate")
+            .and_then(Object::as_i64)
+            .unwrap()
+    };
+    assert_eq!((rotate(1), rotate(2)), (90, 0));
+
+    let path = dir.path().join("scan.pdf");
+    save(&mut concat(&paths).unwrap(), &path).unwrap();
+    assert_eq!(
+        drop_blank_pages(&path, similarity::BLANK_INK).unwrap(),
+        [1, 3]
+    );
+    let document = Document::load(&path).unwrap();
+    assert_eq!(document.get_pages().len(), 1);
+    assert!(blank_pages(&document, similarity::BLANK_INK).is_empty());
+    assert_eq!(versions::list(&path).len(), 1);
+}
+
+#[test]
+fn test_stamp() {
+    let dir = tempdir::TempDir::new("pdf").unwrap();
+    let path = dir.path().join("2021-03-04_Bank_Statement_1.png");
+    image::RgbImage::from_pixel(20, 30, image::Rgb([255, 255, 255]))
+        .save(&path)
+        .unwrap();
+    let stamped = stamped(&path, "Copy provided to Café (Acme) on 2021-03-04").unwrap();
+    let document = Document::load_mem(&stamped).unwrap();
+    let page_id = document.get_pages()[&1];
+    let content = String::from_utf8_lossy(&document.get_page_content(page_id).unwrap()).to_string();
+    assert!(content.starts_with("q\n"));
+    assert!(content.contains("/FStamp 8.00 Tf"));
+    assert!(content.contains("Copy provided to Caf"));
+    let (resources, _) = document.get_page_resources(page_id);
+    assert!(resources.unwrap().get(b"Font").is_ok());
+    // Still taken for a scan, e.g. by the preview.
+    assert_eq!(page(&document, 1).unwrap().to_rgb8().dimensions(), (20, 30));
+}
@@ -0,0 +1,423 @@
+use crate::catalog::Catalog;
+use crate::events::{self, Event};
+use crate::storage;
+use crate::{checklists, locks, report, search, versions};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::{info, warn};
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum RenameError {
+    /// The plan has conflicts, nothing was renamed.
+    ConflictError,
+    /// A rename failed, the ones done before it were undone.
+    WriteError,
+    /// A rename failed and undoing the others did too, see the audit log.
+    RollbackError,
+}
+
+/// One document of a batch, by filename within the cabinet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rename {
+    pub from: String,
+    pub to: String,
+}
+
+/// Why a batch can't be applied as planned.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Conflict {
+    /// The document to rename is gone.
+    Missing(String),
+    /// Several documents would get this name.
+    SameTarget(String),
+    /// A file that isn't renamed away has this name already.
+    Taken(String),
+    /// Someone sharing the cabinet is editing the document, the holder of its lock follows.
+    Locked(String, String),
+    /// The name isn't the one of a file in the cabinet, e.g. it has a slash.
+    Outside(String),
+    /// The document is renamed more than once.
+    SameSource(String),
+}
+
+impl std::fmt::Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Conflict::Missing(filename) => write!(f, "{} is gone", filename),
+            Conflict::SameTarget(filename) => {
+                write!(f, "several documents would be named {}", filename)
+            }
+            Conflict::Taken(filename) => write!(f, "{} exists already", filename),
+            Conflict::Locked(filename, holder) => {
+                write!(f, "{} is being edited by {}", filename, holder)
+            }
+            Conflict::Outside(filename) => write!(f, "{} isn't a filename", filename),
+            Conflict::SameSource(filename) => {
+                write!(f, "{} would be renamed more than once", filename)
+            }
+        }
+    }
+}
+
+/// The renames of a batch, all checked against the cabinet before any is done.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    pub renames: Vec<Rename>,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl Plan {
+    pub fn is_valid(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// What the audit log keeps of a batch.
+#[derive(Debug, Serialize)]
+struct Record<'a> {
+    /// What the batch was for, e.g. "Rename institution".
+    source: &'a str,
+    finished: String,
+    plan: &'a Plan,
+    /// "applied", "refused", "rolled back" or "rollback failed".
+    result: &'a str,
+}
+
+/// Whether `filename` is the name of a file right in the cabinet, not a path out of it.
+fn is_filename(filename: &str) -> bool {
+    Path::new(filename)
+        .file_name()
+        .is_some_and(|name| name == filename)
+}
+
+/// Plans renaming the documents of the cabinet at `dir`. Documents keeping their name are
+/// left out, names may be swapped or passed along within the batch.
+pub fn plan(dir: &Path, renames: Vec<Rename>) -> Plan {
+    let renames: Vec<Rename> = renames.into_iter().filter(|r| r.from != r.to).collect();
+    let mut sources: HashMap<&str, usize> = HashMap::new();
+    let mut targets: HashMap<&str, usize> = HashMap::new();
+    for rename in &renames {
+        *sources.entry(rename.from.as_str()).or_default() += 1;
+        *targets.entry(rename.to.as_str()).or_default() += 1;
+    }
+    let mut conflicts = Vec::new();
+    for rename in &renames {
+        if !is_filename(&rename.from) {
+            conflicts.push(Conflict::Outside(rename.from.clone()));
+        } else if !dir.join(&rename.from).is_file() {
+            conflicts.push(Conflict::Missing(rename.from.clone()));
+        } else if let Some(holder) = locks::edited_by(&dir.join(&rename.from)) {
+            conflicts.push(Conflict::Locked(rename.from.clone(), holder.to_string()));
+        }
+    }
+    let mut twice: Vec<&str> = sources
+        .iter()
+        .filter(|(_, count)| **count > 1)
+        .map(|(source, _)| *source)
+        .collect();
+    twice.sort_unstable();
+    for source in twice {
+        conflicts.push(Conflict::SameSource(source.to_string()));
+    }
+    let mut targets: Vec<(&str, usize)> = targets.into_iter().collect();
+    targets.sort();
+    for (target, count) in targets {
+        if !is_filename(target) {
+            conflicts.push(Conflict::Outside(target.to_string()));
+        } else if count > 1 {
+            conflicts.push(Conflict::SameTarget(target.to_string()));
+        } else if !sources.contains_key(target)
+            && dir.join(target).exists()
+            && !renames.iter().any(|r| {
+                r.to == target && storage::same_file(&dir.join(&r.from), &dir.join(target))
+            })
+        {
+            conflicts.push(Conflict::Taken(target.to_string()));
+        }
+    }
+    Plan { renames, conflicts }
+}
+
+/// Where a document waits between its old and new name, out of the list of documents.
+fn staging(rename: &Rename) -> String {
+    format!(".{}.renaming", rename.from)
+}
+
+/// Renames `(from, to)` pairs in order, the ones done are pushed to `done`.
+fn move_all(dir: &Path, moves: &[(String, String)], done: &mut Vec<(String, String)>) -> bool {
+    for (from, to) in moves {
+        if let Err(error) = storage::backend().rename(&dir.join(from), &dir.join(to)) {
+            warn!(event = "batch_rename_failed", from = %from, to = %to, ?error);
+            return false;
+        }
+        done.push((from.clone(), to.clone()));
+    }
+    true
+}
+
+/// Undoes the renames in `done`, last first.
+fn undo(dir: &Path, done: &[(String, String)]) -> bool {
+    let mut undone = true;
+    for (from, to) in done.iter().rev() {
+        if let Err(error) = storage::backend().rename(&dir.join(to), &dir.join(from)) {
+            warn!(event = "batch_rollback_failed", from = %to, to = %from, ?error);
+            undone = false;
+        }
+    }
+    undone
+}
+
+/// Applies the renames of `plan` to the cabinet at `dir` as one: every document moves to
+/// a staging name first, then to its new one, so swaps work, and if any step fails the
+/// ones done are undone. The catalog, texts and versions follow. The plan and its outcome
+/// go to the audit log under `source`. Returns how many documents were renamed.
+pub fn apply(dir: &Path, plan: &Plan, source: &str) -> Result<usize, RenameError> {
+    let result = transact(dir, plan);
+    let outcome = match &result {
+        Ok(_) => "applied",
+        Err(RenameError::ConflictError) => "refused",
+        Err(RenameError::WriteError) => "rolled back",
+        Err(RenameError::RollbackError) => "rollback failed",
+    };
+    let record = Record {
+        source,
+        finished: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        plan,
+        result: outcome,
+    };
+    if let Err(error) = report::audit(dir, &record) {
+        warn!(event = "audit_failed", ?error);
+    }
+    info!(
+        event = "BatchRename",
+        source = %source,
+        renames = plan.renames.len(),
+        result = %outcome
+    );
+    result
+}
+
+fn transact(dir: &Path, plan: &Plan) -> Result<usize, RenameError> {
+    if !plan.is_valid() {
+        return Err(RenameError::ConflictError);
+    }
+    let staged: Vec<(String, String)> = plan
+        .renames
+        .iter()
+        .map(|r| (r.from.clone(), staging(r)))
+        .collect();
+    let finished: Vec<(String, String)> = plan
+        .renames
+        .iter()
+        .map(|r| (staging(r), r.to.clone()))
+        .collect();
+    let mut done = Vec::new();
+    if !move_all(dir, &staged, &mut done) || !move_all(dir, &finished, &mut done) {
+        return Err(if undo(dir, &done) {
+            RenameError::WriteError
+        } else {
+            RenameError::RollbackError
+        });
+    }
+
+    let mut catalog = Catalog::load(dir);
+    let entries: Vec<_> = plan
+        .renames
+        .iter()
+        .map(|r| (r.to.clone(), catalog.entries.remove(&r.from)))
+        .collect();
+    for (to, entry) in entries {
+        if let Some(entry) = entry {
+            catalog.entries.insert(to, entry);
+        }
+    }
+    if let Err(error) = catalog.save(dir) {
+        warn!(event = "catalog_save_failed", ?error);
+        return Err(if undo(dir, &done) {
+            RenameError::WriteError
+        } else {
+            RenameError::RollbackError
+        });
+    }
+    let moved: Vec<(String, String)> = plan
+        .renames
+        .iter()
+        .map(|r| (r.from.clone(), r.to.clone()))
+        .collect();
+    checklists::rename(dir, &moved);
+    let renamed: Vec<Event> = moved
+        .into_iter()
+        .map(|(from, to)| Event::Renamed { from, to })
+        .collect();
+    events::emit(dir, &renamed);
+    for (from, to) in staged.iter().chain(&finished) {
+        search::rename_text(&dir.join(from), &dir.join(to));
+        versions::rename_versions(&dir.join(from), &dir.join(to));
+    }
+    Ok(plan.renames.len())
+}
+
+#[test]
+fn test_batch_rename() {
+    let dir = tempdir::TempDir::new("renames").unwrap();
+    let dir = dir.path();
+    let rename = |from: &str, to: &str| Rename {
+        from: from.to_string(),
+        to: to.to_string(),
+    };
+    for filename in ["a.pdf", "b.pdf", "c.pdf"] {
+        std::fs::write(dir.join(filename), filename).unwrap();
+    }
+    let mut catalog = Catalog::default();
+    catalog.entry("a.pdf").favorite = true;
+    catalog.save(dir).unwrap();
+    search::save_text(&dir.join("a.pdf"), &[]).unwrap();
+
+    let conflicting = plan(
+        dir,
+        vec![
+            rename("a.pdf", "d.pdf"),
+            rename("b.pdf", "d.pdf"),
+            rename("c.pdf", "c.pdf"),
+            rename("gone.pdf", "e.pdf"),
+            rename("e.pdf", "c.pdf"),
+        ],
+    );
+    assert_eq!(conflicting.renames.len(), 4);
+    assert_eq!(
+        conflicting.conflicts,
+        [
+            Conflict::Missing("gone.pdf".to_string()),
+            Conflict::Missing("e.pdf".to_string()),
+            Conflict::Taken("c.pdf".to_string()),
+            Conflict::SameTarget("d.pdf".to_string()),
+        ]
+    );
+    assert!(apply(dir, &conflicting, "Test").is_err());
+    assert!(dir.join("a.pdf").exists());
+
+    // Swapping two names and passing a third along.
+    let swap = plan(
+        dir,
+        vec![
+            rename("a.pdf", "b.pdf"),
+            rename("b.pdf", "a.pdf"),
+            rename("c.pdf", "d.pdf"),
+        ],
+    );
+    assert!(swap.is_valid());
+    assert_eq!(apply(dir, &swap, "Test").unwrap(), 3);
+    assert_eq!(std::fs::read(dir.join("b.pdf")).unwrap(), b"a.pdf");
+    assert_eq!(std::fs::read(dir.join("a.pdf")).unwrap(), b"b.pdf");
+    assert!(dir.join("d.pdf").exists() && !dir.join("c.pdf").exists());
+    assert!(Catalog::load(dir).get("b.pdf").unwrap().favorite);
+    assert!(search::has_text(&dir.join("b.pdf")));
+    let audit = std::fs::read_to_string(report::audit_path(dir)).unwrap();
+    assert_eq!(audit.lines().count(), 2);
+
+    // The staging name of b.pdf is taken, so its rename fails and the one of a.pdf is undone.
+    std::fs::create_dir(dir.join(".b.pdf.renaming")).unwrap();
+    std::fs::write(dir.join(".b.pdf.renaming/x"), b"x").unwrap();
+    let failing = plan(
+        dir,
+        vec![rename("a.pdf", "e.pdf"), rename("b.pdf", "f.pdf")],
+    );
+    assert!(matches!(
+        apply(dir, &failing, "Test"),
+        Err(RenameError::WriteError)
+    ));
+    assert!(dir.join("a.pdf").exists() && dir.join("b.pdf").exists());
+    assert!(!dir.join("e.pdf").exists());
+}
+
+#[cfg(test)]
+fn contents(dir: &Path) -> std::collections::BTreeMap<String, Vec<u8>> {
+    crate::utils::list_files(dir)
+        .into_iter()
+        .map(|filename| (filename.clone(), std::fs::read(dir.join(filename)).unwrap()))
+        .collect()
+}
+
+/// A name for a batch, mostly of documents that may or may not exist, at times not a
+/// filename at all.
+#[cfg(test)]
+fn name() -> impl proptest::strategy::Strategy<Value = String> {
+    use proptest::strategy::Strategy;
+    proptest::prop_oneof![
+        8 => "[a-f]".prop_map(|name| format!("{}.pdf", name)),
+        1 => "\\.\\./a|/f|",
+    ]
+}
+
+#[cfg(test)]
+proptest::proptest! {
+    #![proptest_config(proptest::test_runner::Config::with_cases(256))]
+
+    // Any batch among a few documents either moves each to its new name or, with
+    // conflicts, leaves the cabinet as it was.
+    #[test]
+    fn test_plan_any_batch(
+        existing in proptest::collection::btree_set("[a-e]", 3..6),
+        pairs in proptest::collection::vec((name(), name()), 2..4),
+    ) {
+        // Names out of the cabinet land in the temporary folder, if they get renamed at all.
+        let root = tempdir::TempDir::new("renames").unwrap();
+        let dir = &root.path().join("cabinet");
+        std::fs::create_dir(dir).unwrap();
+        let outside = |name: String| match name.as_str() {
+            "/f" => root.path().join("f").to_string_lossy().to_string(),
+            _ => name,
+        };
+        for name in &existing {
+            std::fs::write(dir.join(format!("{}.pdf", name)), name).unwrap();
+        }
+        let renames = pairs
+            .into_iter()
+            .map(|(from, to)| Rename {
+                from: outside(from),
+                to: outside(to),
+            })
+            .collect();
+        let before = contents(dir);
+        let plan = plan(dir, renames);
+        let result = apply(dir, &plan, "Test");
+        let mut expected = before.clone();
+        if plan.is_valid() {
+            proptest::prop_assert_eq!(result.unwrap(), plan.renames.len());
+            for rename in &plan.renames {
+                expected.remove(&rename.from);
+            }
+            for rename in &plan.renames {
+                expected.insert(rename.to.clone(), before[&rename.from].clone());
+            }
+        } else {
+            proptest::prop_assert!(result.is_err());
+        }
+        proptest::prop_assert_eq!(contents(dir), expected);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_case_rename() {
+    let dir = tempdir::TempDir::new("renames").unwrap();
+    let dir = dir.path();
+    std::fs::write(dir.join("2020-01-01_chase_statement.pdf"), "a").unwrap();
+    // Linux tells casings apart, a second link stands in for the other casing.
+    std::fs::hard_link(
+        dir.join("2020-01-01_chase_statement.pdf"),
+        dir.join("2020-01-01_Chase_statement.pdf"),
+    )
+    .unwrap();
+    let planned = plan(
+        dir,
+        vec![Rename {
+            from: "2020-01-01_chase_statement.pdf".to_string(),
+            to: "2020-01-01_Chase_statement.pdf".to_string(),
+        }],
+    );
+    assert!(planned.is_valid());
+}
@@ -0,0 +1,172 @@
+use crate::catalog::Catalog;
+use crate::events::{self, Event};
+use crate::utils;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum ReportError {
+    DirectoryError,
+    FormatError,
+    WriteError,
+}
+
+/// What an import or a pull from the scanner brought into the cabinet. Documents are
+/// listed by filename.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportReport {
+    /// Where the documents came from, e.g. "Import rules".
+    pub source: String,
+    pub finished: String,
+    pub imported: Vec<String>,
+    /// Imported documents that were given a normalized name.
+    pub normalized: Vec<String>,
+    /// Imported documents that still have to be named by hand.
+    pub needs_attention: Vec<String>,
+    /// Files left out because the cabinet has the same content already.
+    pub duplicates: Vec<String>,
+    /// Imported PDFs blank pages were dropped from, with how many.
+    pub blank_pages: Vec<(String, usize)>,
+    pub errors: Vec<String>,
+}
+
+impl ImportReport {
+    pub fn new(source: &str) -> Self {
+        ImportReport {
+            source: source.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether nothing came in and nothing went wrong.
+    pub fn is_empty(&self) -> bool {
+        self.imported.is_empty() && self.duplicates.is_empty() && self.errors.is_empty()
+    }
+
+    /// Lists the document imported at `path`, with the number of blank pages dropped from it.
+    pub fn add(&mut self, path: &Path, blank_pages: usize) {
+        let filename = filename(path);
+        if utils::is_normalized(path) {
+            self.normalized.push(filename.clone());
+        } else {
+            self.needs_attention.push(filename.clone());
+        }
+        if blank_pages > 0 {
+            self.blank_pages.push((filename.clone(), blank_pages));
+        }
+        self.imported.push(filename);
+    }
+
+    pub fn duplicate(&mut self, path: &Path) {
+        self.duplicates.push(filename(path));
+    }
+
+    pub fn error(&mut self, path: &Path, error: &str) {
+        self.errors.push(format!("{}: {}", filename(path), error));
+    }
+
+    /// Stamps the report, logs it and appends it to the audit log of the cabinet at `dir`.
+    pub fn finish(&mut self, dir: &Path) {
+        self.finished = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        info!(
+            event = "ImportReport",
+            source = %self.source,
+            imported = self.imported.len(),
+            needs_attention = self.needs_attention.len(),
+            duplicates = self.duplicates.len(),
+            errors = self.errors.len()
+        );
+        if let Err(error) = audit(dir, self) {
+            warn!(event = "audit_failed", ?error);
+        }
+        let added: Vec<Event> = self
+            .imported
+            .iter()
+            .map(|filename| Event::Added {
+                filename: filename.clone(),
+            })
+            .collect();
+        events::emit(dir, &added);
+    }
+}
+
+fn filename(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// The log of the imports into the cabinet at `dir` and of its batch renames, one JSON
+/// record per line.
+pub fn audit_path(dir: &Path) -> PathBuf {
+    Catalog::path(dir).with_file_name("audit.jsonl")
+}
+
+/// Appends `record` as a line of JSON to the audit log of the cabinet at `dir`.
+pub fn audit<T: Serialize>(dir: &Path, record: &T) -> Result<(), ReportError> {
+    let path = audit_path(dir);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|_| ReportError::DirectoryError)?;
+    }
+    let line = serde_json::to_string(record).map_err(|_| ReportError::FormatError)?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut f| writeln!(f, "{}", line))
+        .map_err(|_| ReportError::WriteError)
+}
+
+/// The content hashes of the documents of the cabinet at `dir` that were indexed.
+pub fn known_hashes(dir: &Path) -> HashSet<String> {
+    Catalog::load(dir)
+        .entries
+        .into_values()
+        .filter_map(|entry| entry.sha256)
+        .collect()
+}
+
+/// Saves `report` as JSON in `<dir>/reports`. Returns the path of the file.
+pub async fn export(dir: String, report: ImportReport) -> Result<String, ReportError> {
+    let json = serde_json::to_string_pretty(&report).map_err(|_| ReportError::FormatError)?;
+    let out_dir = Path::new(&dir).join("reports");
+    fs::create_dir_all(&out_dir).map_err(|_| ReportError::DirectoryError)?;
+    let stamp = Local::now().format("%Y-%m-%d_%H%M%S");
+    let path = utils::free_path(&out_dir, &format!("Import_{}.json", stamp));
+    fs::write(&path, json).map_err(|_| ReportError::WriteError)?;
+    info!(event = "ExportImportReport", file = %path.display());
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[test]
+fn test_import_report() {
+    let dir = tempdir::TempDir::new("report").unwrap();
+    let dir = dir.path();
+    let mut report = ImportReport::new("Import rules");
+    assert!(report.is_empty());
+    report.add(&dir.join("2021-03-04_Chase_Statement_1.pdf"), 2);
+    report.add(&dir.join("scan0001.pdf"), 0);
+    report.duplicate(Path::new("/downloads/eStmt_2021-03-04.pdf"));
+    report.error(Path::new("/downloads/locked.pdf"), "couldn't move it");
+    report.finish(dir);
+    report.finish(dir);
+
+    assert_eq!(report.normalized, ["2021-03-04_Chase_Statement_1.pdf"]);
+    assert_eq!(report.needs_attention, ["scan0001.pdf"]);
+    assert_eq!(
+        report.blank_pages,
+        [("2021-03-04_Chase_Statement_1.pdf".to_string(), 2)]
+    );
+    assert_eq!(report.errors, ["locked.pdf: couldn't move it"]);
+    let audit = fs::read_to_string(audit_path(dir)).unwrap();
+    assert_eq!(audit.lines().count(), 2);
+    let logged: ImportReport = serde_json::from_str(audit.lines().last().unwrap()).unwrap();
+    assert_eq!(logged, report);
+}
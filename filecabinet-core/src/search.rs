@@ -0,0 +1,745 @@
+use crate::catalog::Catalog;
+use crate::utils::{self, OptDoc};
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use rust_stemmers::{Algorithm, Stemmer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::str::Chars;
+use tracing::{info, warn};
+
+/// Characters of context shown on each side of a hit.
+const CONTEXT: usize = 40;
+
+/// Where a query was found in a document: the page, counted from 1, and the words around
+/// the match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub page: usize,
+    pub before: String,
+    pub matched: String,
+    pub after: String,
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum SearchError {
+    DirectoryError,
+    WriteError,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::enum_variant_names)]
+pub enum QueryError {
+    EmptyError,
+    OperatorError,
+    ParenthesisError,
+    ValueError,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            QueryError::EmptyError => "Type something to search for.",
+            QueryError::OperatorError => {
+                "AND, OR and NOT need something to search for next to them."
+            }
+            QueryError::ParenthesisError => "A parenthesis isn't closed or opened.",
+            QueryError::ValueError => "A field needs a value, e.g. date:2021.",
+        })
+    }
+}
+
+/// Parts of a document a query can be limited to, e.g. `institution:Chase`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    Institution,
+    Date,
+    Title,
+    Extension,
+    Language,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Field> {
+        match name.to_lowercase().as_str() {
+            "institution" => Some(Field::Institution),
+            "date" => Some(Field::Date),
+            "title" => Some(Field::Title),
+            "ext" | "type" => Some(Field::Extension),
+            "lang" | "language" => Some(Field::Language),
+            _ => None,
+        }
+    }
+}
+
+/// A parsed search, e.g. `institution:Chase AND date:2021 AND (statement OR invoice)`.
+///
+/// Words and `"quoted phrases"` are looked for in the text and the filename of documents,
+/// `field:value` in a part of the filename or the detected language. Other `name:value`
+/// words, or `"name with spaces:value"` phrases, also match the custom field of that name,
+/// see `fields`. Terms next to each other must all match, as with `AND`. `OR` binds looser
+/// than `AND`, and `NOT` or a leading `-` excludes. Operators are only recognized in upper
+/// case. In documents of a known language, words also find other forms of themselves, e.g.
+/// `statements` finds `statement`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    Term(String),
+    Field(Field, String),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Term(String),
+    Field(Field, String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+/// The rest of a quoted phrase, up to the closing quote or the end of the query.
+fn quoted(chars: &mut Peekable<Chars<'_>>) -> String {
+    chars.by_ref().take_while(|&c| c != '"').collect()
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | '-' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    _ => Token::Not,
+                });
+            }
+            '"' => {
+                chars.next();
+                tokens.push(Token::Term(quoted(&mut chars)));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                let field = word
+                    .split_once(':')
+                    .and_then(|(name, value)| Some((Field::parse(name)?, value)));
+                tokens.push(match (word.as_str(), field) {
+                    ("AND", _) => Token::And,
+                    ("OR", _) => Token::Or,
+                    ("NOT", _) => Token::Not,
+                    (_, Some((field, value))) => {
+                        let value = if value.is_empty() && chars.peek() == Some(&'"') {
+                            chars.next();
+                            quoted(&mut chars)
+                        } else {
+                            value.to_string()
+                        };
+                        if value.trim().is_empty() {
+                            return Err(QueryError::ValueError);
+                        }
+                        Token::Field(field, value)
+                    }
+                    // Words like `Ref:123` that don't name a field are searched as they are.
+                    _ => Token::Term(word),
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over the tokens of a query, one function per precedence level.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let matched = self.peek() == Some(token);
+        if matched {
+            self.position += 1;
+        }
+        matched
+    }
+
+    fn or(&mut self) -> Result<Query, QueryError> {
+        let mut any = vec![self.and()?];
+        while self.eat(&Token::Or) {
+            any.push(self.and()?);
+        }
+        Ok(if any.len() == 1 {
+            any.remove(0)
+        } else {
+            Query::Or(any)
+        })
+    }
+
+    fn and(&mut self) -> Result<Query, QueryError> {
+        let mut all = vec![self.unary()?];
+        // Terms next to each other are joined as if there was an `AND` between them.
+        while self.eat(&Token::And)
+            || matches!(
+                self.peek(),
+                Some(Token::Term(_) | Token::Field(_, _) | Token::Not | Token::Open)
+            )
+        {
+            all.push(self.unary()?);
+        }
+        Ok(if all.len() == 1 {
+            all.remove(0)
+        } else {
+            Query::And(all)
+        })
+    }
+
+    fn unary(&mut self) -> Result<Query, QueryError> {
+        if self.eat(&Token::Not) {
+            return Ok(Query::Not(Box::new(self.unary()?)));
+        }
+        let token = self.peek().cloned();
+        self.position += 1;
+        match token {
+            Some(Token::Open) => {
+                let query = self.or()?;
+                if !self.eat(&Token::Close) {
+                    return Err(QueryError::ParenthesisError);
+                }
+                Ok(query)
+            }
+            Some(Token::Term(term)) => Ok(Query::Term(term)),
+            Some(Token::Field(field, value)) => Ok(Query::Field(field, value)),
+            Some(Token::Close) => Err(QueryError::ParenthesisError),
+            _ => Err(QueryError::OperatorError),
+        }
+    }
+}
+
+/// A word of a page as searches compare it, with the characters of the collapsed page it
+/// was read from.
+struct Word {
+    stem: String,
+    start: usize,
+    end: usize,
+}
+
+/// Splits text into words for the language of a document: its stop words are left out and
+/// the rest is stemmed, so `statements` finds `statement` and `the` finds nothing.
+struct Tokenizer {
+    stemmer: Stemmer,
+    stop_words: &'static [&'static str],
+}
+
+impl Tokenizer {
+    /// The tokenizer of a language by the name of its Tesseract model, as the catalog keeps
+    /// it. Languages without a stemmer are only searched as typed.
+    fn new(language: &str) -> Option<Tokenizer> {
+        let (algorithm, stop_words) = match language {
+            "ara" => (Algorithm::Arabic, Some("ar")),
+            "dan" => (Algorithm::Danish, Some("da")),
+            "nld" => (Algorithm::Dutch, Some("nl")),
+            "eng" => (Algorithm::English, Some("en")),
+            "fin" => (Algorithm::Finnish, Some("fi")),
+            "fra" => (Algorithm::French, Some("fr")),
+            "deu" => (Algorithm::German, Some("de")),
+            "ell" => (Algorithm::Greek, Some("el")),
+            "hun" => (Algorithm::Hungarian, Some("hu")),
+            "ita" => (Algorithm::Italian, Some("it")),
+            "nob" | "nor" => (Algorithm::Norwegian, Some("no")),
+            "por" => (Algorithm::Portuguese, Some("pt")),
+            "ron" => (Algorithm::Romanian, Some("ro")),
+            "rus" => (Algorithm::Russian, Some("ru")),
+            "spa" => (Algorithm::Spanish, Some("es")),
+            "swe" => (Algorithm::Swedish, Some("sv")),
+            "tam" => (Algorithm::Tamil, None),
+            "tur" => (Algorithm::Turkish, Some("tr")),
+            _ => return None,
+        };
+        Some(Tokenizer {
+            stemmer: Stemmer::create(algorithm),
+            stop_words: stop_words.map(stop_words::get).unwrap_or_default(),
+        })
+    }
+
+    fn words(&self, text: &[char]) -> Vec<Word> {
+        let mut words = Vec::new();
+        let mut start = 0;
+        while start < text.len() {
+            if !text[start].is_alphanumeric() {
+                start += 1;
+                continue;
+            }
+            let end = text[start..]
+                .iter()
+                .position(|c| !c.is_alphanumeric())
+                .map_or(text.len(), |length| start + length);
+            let word: String = text[start..end]
+                .iter()
+                .flat_map(|c| c.to_lowercase())
+                .collect();
+            if !self.stop_words.contains(&word.as_str()) {
+                words.push(Word {
+                    stem: self.stemmer.stem(&word).into_owned(),
+                    start,
+                    end,
+                });
+            }
+            start = end;
+        }
+        words
+    }
+}
+
+/// A document as queries see it.
+pub struct Candidate {
+    filename: String,
+    doc: OptDoc,
+    language: Option<String>,
+    /// Custom fields by name.
+    fields: BTreeMap<String, String>,
+    pages: Vec<String>,
+    tokenizer: Option<Tokenizer>,
+    /// The words of each page, when the language of the document is known.
+    words: Vec<Vec<Word>>,
+}
+
+impl Candidate {
+    pub fn load(dir: &Path, filename: &str, catalog: &Catalog) -> Candidate {
+        let language = catalog.get(filename).and_then(|e| e.language.clone());
+        let tokenizer = language.as_deref().and_then(Tokenizer::new);
+        let pages = load_text(&dir.join(filename));
+        let words = match &tokenizer {
+            Some(tokenizer) => pages
+                .iter()
+                .map(|page| tokenizer.words(&collapse(page).chars().collect::<Vec<_>>()))
+                .collect(),
+            None => Vec::new(),
+        };
+        Candidate {
+            filename: filename.to_string(),
+            doc: OptDoc::new(filename),
+            language,
+            fields: catalog
+                .get(filename)
+                .map(|e| e.fields.clone())
+                .unwrap_or_default(),
+            pages,
+            tokenizer,
+            words,
+        }
+    }
+
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    pub fn pages(&self) -> &[String] {
+        &self.pages
+    }
+
+    /// The first match of `query` in the text, as typed or else word by word in the
+    /// language of the document.
+    pub fn find(&self, query: &str) -> Option<Hit> {
+        find(&self.pages, query).or_else(|| self.find_words(query))
+    }
+
+    /// The first place the words of `query` follow each other on a page, stop words left
+    /// out and stemmed as the words of the document.
+    fn find_words(&self, query: &str) -> Option<Hit> {
+        let query: Vec<String> = self
+            .tokenizer
+            .as_ref()?
+            .words(&collapse(query).chars().collect::<Vec<_>>())
+            .into_iter()
+            .map(|word| word.stem)
+            .collect();
+        if query.is_empty() {
+            return None;
+        }
+        self.words.iter().enumerate().find_map(|(i, words)| {
+            let run = words.windows(query.len()).find(|run| {
+                run.iter()
+                    .zip(&query)
+                    .all(|(word, stem)| &word.stem == stem)
+            })?;
+            let page: Vec<char> = collapse(&self.pages[i]).chars().collect();
+            Some(snippet(i, &page, run[0].start, run[run.len() - 1].end))
+        })
+    }
+}
+
+/// `text` in lower case without spaces or punctuation, so `Bank of America` matches the
+/// `BankOfAmerica` of a filename.
+fn squashed(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Query, QueryError> {
+        let tokens = tokenize(query)?;
+        if tokens.is_empty() {
+            return Err(QueryError::EmptyError);
+        }
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+        let query = parser.or()?;
+        if parser.position < parser.tokens.len() {
+            return Err(QueryError::ParenthesisError);
+        }
+        Ok(query)
+    }
+
+    pub fn matches(&self, candidate: &Candidate) -> bool {
+        match self {
+            Query::Term(term) => {
+                candidate
+                    .filename
+                    .to_lowercase()
+                    .contains(&term.to_lowercase())
+                    || candidate.find(term).is_some()
+                    || term.split_once(':').is_some_and(|(name, value)| {
+                        candidate.fields.iter().any(|(field, part)| {
+                            squashed(field) == squashed(name)
+                                && squashed(part).contains(&squashed(value))
+                        })
+                    })
+            }
+            Query::Field(field, value) => {
+                let part = match field {
+                    Field::Institution => candidate.doc.institution.as_deref(),
+                    Field::Title => candidate.doc.name.as_deref(),
+                    // `2021` and `2021-03` match the dates they begin.
+                    Field::Date => {
+                        return candidate
+                            .doc
+                            .date
+                            .as_deref()
+                            .is_some_and(|date| date.starts_with(value.trim()))
+                    }
+                    Field::Extension => {
+                        return utils::extension(&candidate.filename)
+                            == value.trim_start_matches('.').to_lowercase()
+                    }
+                    Field::Language => candidate.language.as_deref(),
+                };
+                part.is_some_and(|part| squashed(part).contains(&squashed(value)))
+            }
+            Query::And(all) => all.iter().all(|query| query.matches(candidate)),
+            Query::Or(any) => any.iter().any(|query| query.matches(candidate)),
+            Query::Not(query) => !query.matches(candidate),
+        }
+    }
+
+    /// The first place in the text of `candidate` a term that isn't excluded was found.
+    pub fn hit(&self, candidate: &Candidate) -> Option<Hit> {
+        match self {
+            Query::Term(term) => candidate.find(term),
+            Query::And(queries) | Query::Or(queries) => {
+                queries.iter().find_map(|query| query.hit(candidate))
+            }
+            Query::Field(_, _) | Query::Not(_) => None,
+        }
+    }
+}
+
+/// The text of the pages of a document, read while indexing. Lives in
+/// `<cabinet>/.filecabinet/text/<filename>.json`, out of the catalog so that stays small.
+fn text_path(path: &Path) -> Option<PathBuf> {
+    let dir = path.parent()?;
+    let filename = path.file_name()?.to_string_lossy();
+    Some(text_dir(dir).join(format!("{}.json", filename)))
+}
+
+/// Where the texts of the documents of the cabinet at `dir` are kept.
+pub fn text_dir(dir: &Path) -> PathBuf {
+    Catalog::path(dir).with_file_name("text")
+}
+
+/// Whether the text of the document at `path` was read already, even if it had none.
+pub fn has_text(path: &Path) -> bool {
+    text_path(path).is_some_and(|text| text.is_file())
+}
+
+pub fn load_text(path: &Path) -> Vec<String> {
+    text_path(path)
+        .and_then(|text| fs::read_to_string(text).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Saves the text of the `pages` of the document at `path`, no pages for documents
+/// nothing could be read from.
+pub fn save_text(path: &Path, pages: &[String]) -> Result<(), SearchError> {
+    let text = text_path(path).ok_or(SearchError::DirectoryError)?;
+    if let Some(parent) = text.parent() {
+        fs::create_dir_all(parent).map_err(|_| SearchError::DirectoryError)?;
+    }
+    let json = serde_json::to_string(pages).map_err(|_| SearchError::WriteError)?;
+    AtomicFile::new(text, OverwriteBehavior::AllowOverwrite)
+        .write(|f| f.write_all(json.as_bytes()))
+        .map_err(|_| SearchError::WriteError)
+}
+
+/// Drops the text of the document at `path`, e.g. once it was deleted or rewritten.
+pub fn forget_text(path: &Path) {
+    if let Some(text) = text_path(path).filter(|text| text.is_file()) {
+        if let Err(error) = fs::remove_file(&text) {
+            warn!(event = "text_remove_failed", file = %text.display(), ?error);
+        }
+    }
+}
+
+/// Keeps the text of a document renamed within its cabinet.
+pub fn rename_text(old: &Path, new: &Path) {
+    if let (Some(old), Some(new)) = (text_path(old), text_path(new)) {
+        if old.is_file() {
+            if let Err(error) = fs::rename(&old, &new) {
+                warn!(event = "text_rename_failed", file = %old.display(), ?error);
+            }
+        }
+    }
+}
+
+/// Collapses runs of whitespace, so line breaks of the text layer don't show in snippets.
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The first match of `query` in `pages`, ignoring case.
+pub fn find(pages: &[String], query: &str) -> Option<Hit> {
+    // Lower casing may change the length of a character, so compare char by char.
+    let query: Vec<char> = collapse(query).chars().collect();
+    if query.is_empty() {
+        return None;
+    }
+    pages.iter().enumerate().find_map(|(i, page)| {
+        let page: Vec<char> = collapse(page).chars().collect();
+        let start = (0..page.len().saturating_sub(query.len() - 1)).find(|&start| {
+            page[start..start + query.len()]
+                .iter()
+                .zip(&query)
+                .all(|(c, q)| c.to_lowercase().eq(q.to_lowercase()))
+        })?;
+        Some(snippet(i, &page, start, start + query.len()))
+    })
+}
+
+/// A hit on the page `i` spanning the characters from `start` to `end` of the collapsed
+/// `page`.
+fn snippet(i: usize, page: &[char], start: usize, end: usize) -> Hit {
+    let mut before: String = page[start.saturating_sub(CONTEXT)..start].iter().collect();
+    let mut after: String = page[end..(end + CONTEXT).min(page.len())].iter().collect();
+    if start > CONTEXT {
+        before.insert(0, '…');
+    }
+    if end + CONTEXT < page.len() {
+        after.push('…');
+    }
+    Hit {
+        page: i + 1,
+        before,
+        matched: page[start..end].iter().collect(),
+        after,
+    }
+}
+
+/// The documents of the cabinet at `dir` that match `query`, by filename, with the first
+/// hit in their text if they have one.
+pub fn run(dir: &Path, query: &Query) -> BTreeMap<String, Option<Hit>> {
+    let catalog = Catalog::load(dir);
+    utils::list_files(dir)
+        .into_iter()
+        .filter_map(|filename| {
+            let candidate = Candidate::load(dir, &filename, &catalog);
+            query
+                .matches(&candidate)
+                .then(|| (filename, query.hit(&candidate)))
+        })
+        .collect()
+}
+
+/// Parses `query` and searches the cabinet at `dir` with it. Returns the query too, so
+/// results of a query that was typed over can be told apart.
+#[allow(clippy::type_complexity)]
+pub async fn search(
+    dir: String,
+    query: String,
+) -> (String, Result<BTreeMap<String, Option<Hit>>, QueryError>) {
+    let found = Query::parse(&query).map(|parsed| run(Path::new(&dir), &parsed));
+    if let Ok(found) = &found {
+        info!(event = "Search", found = found.len());
+    }
+    (query, found)
+}
+
+#[test]
+fn test_find() {
+    let pages = vec![
+        "Account statement\nfor March".to_string(),
+        "Interest   paid:\n12.40 EUR. Please keep this statement for your tax records.".to_string(),
+    ];
+    assert_eq!(
+        find(&pages, "interest PAID"),
+        Some(Hit {
+            page: 2,
+            before: String::new(),
+            matched: "Interest paid".to_string(),
+            after: ": 12.40 EUR. Please keep this statement …".to_string(),
+        })
+    );
+    assert_eq!(find(&pages, "statement").unwrap().page, 1);
+    assert_eq!(find(&pages, "invoice"), None);
+    assert_eq!(find(&pages, " "), None);
+
+    let dir = tempdir::TempDir::new("search").unwrap();
+    let path = dir.path().join("scan.pdf");
+    assert!(!has_text(&path));
+    save_text(&path, &pages).unwrap();
+    assert!(has_text(&path));
+    rename_text(&path, &dir.path().join("statement.pdf"));
+    assert_eq!(load_text(&dir.path().join("statement.pdf")), pages);
+    assert!(!has_text(&path));
+}
+
+#[test]
+fn test_query() {
+    use Query::*;
+    let term = |term: &str| Term(term.to_string());
+    assert_eq!(
+        Query::parse("institution:Chase AND date:2021 AND (statement OR invoice)"),
+        Ok(And(vec![
+            Field(self::Field::Institution, "Chase".to_string()),
+            Field(self::Field::Date, "2021".to_string()),
+            Or(vec![term("statement"), term("invoice")]),
+        ]))
+    );
+    assert_eq!(
+        Query::parse("tax OR \"interest paid\" -draft Ref:12"),
+        Ok(Or(vec![
+            term("tax"),
+            And(vec![
+                term("interest paid"),
+                Not(Box::new(term("draft"))),
+                term("Ref:12"),
+            ]),
+        ]))
+    );
+    assert_eq!(Query::parse("  "), Err(QueryError::EmptyError));
+    assert_eq!(Query::parse("tax AND"), Err(QueryError::OperatorError));
+    assert_eq!(Query::parse("(tax"), Err(QueryError::ParenthesisError));
+    assert_eq!(Query::parse("tax)"), Err(QueryError::ParenthesisError));
+    assert_eq!(Query::parse("date:"), Err(QueryError::ValueError));
+
+    let dir = tempdir::TempDir::new("query").unwrap();
+    let statement = "2021-03-04_BankOfAmerica_Statement_1.pdf";
+    for filename in [statement, "2020-01-02_Chase_Invoice_1.pdf", "scan.jpg"] {
+        fs::write(dir.path().join(filename), b"").unwrap();
+    }
+    save_text(
+        &dir.path().join(statement),
+        &["Checking account".to_string(), "Interest paid".to_string()],
+    )
+    .unwrap();
+    let found = |query: &str| {
+        run(dir.path(), &Query::parse(query).unwrap())
+            .into_iter()
+            .map(|(filename, hit)| (filename, hit.map(|hit| hit.page)))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        found("institution:\"bank of america\" date:2021-03 interest"),
+        vec![(statement.to_string(), Some(2))]
+    );
+    let mut catalog = Catalog::default();
+    catalog
+        .entry("scan.jpg")
+        .fields
+        .insert("Policy number".to_string(), "PN-4471".to_string());
+    catalog.save(dir.path()).unwrap();
+    assert_eq!(
+        found("policy_number:pn4471 OR \"policy number:PN-4472\""),
+        vec![("scan.jpg".to_string(), None)]
+    );
+    assert_eq!(
+        found("NOT ext:pdf OR (chase -statement)"),
+        vec![
+            ("2020-01-02_Chase_Invoice_1.pdf".to_string(), None),
+            ("scan.jpg".to_string(), None),
+        ]
+    );
+}
+
+#[test]
+fn test_language() {
+    let dir = tempdir::TempDir::new("language").unwrap();
+    for filename in ["letter.pdf", "brief.pdf", "unknown.pdf"] {
+        fs::write(dir.path().join(filename), b"").unwrap();
+    }
+    let pages = |text: &str| vec!["Cover".to_string(), text.to_string()];
+    save_text(
+        &dir.path().join("letter.pdf"),
+        &pages("The interest of your savings accounts was paid."),
+    )
+    .unwrap();
+    save_text(
+        &dir.path().join("brief.pdf"),
+        &pages("Die Zinsen der Sparkonten wurden gezahlt."),
+    )
+    .unwrap();
+    save_text(
+        &dir.path().join("unknown.pdf"),
+        &pages("The interest of your savings accounts was paid."),
+    )
+    .unwrap();
+    let mut catalog = Catalog::default();
+    catalog.entry("letter.pdf").language = Some("eng".to_string());
+    catalog.entry("brief.pdf").language = Some("deu".to_string());
+    catalog.save(dir.path()).unwrap();
+
+    let candidate = Candidate::load(dir.path(), "letter.pdf", &catalog);
+    assert_eq!(
+        candidate.find("saving account interests"),
+        None,
+        "words are found in their order"
+    );
+    let hit = candidate.find("interests on saving account").unwrap();
+    assert_eq!(hit.page, 2);
+    assert_eq!(hit.matched, "interest of your savings accounts");
+    assert_eq!(candidate.find("the was"), None);
+    let found = |query: &str| {
+        run(dir.path(), &Query::parse(query).unwrap())
+            .into_keys()
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(found("\"saving account\""), vec!["letter.pdf"]);
+    assert_eq!(found("Zinses"), vec!["brief.pdf"]);
+    assert_eq!(found("the"), vec!["letter.pdf", "unknown.pdf"]);
+}
@@ -0,0 +1,130 @@
+use crate::{downscale, orientation, pdf, storage, utils};
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use std::path::Path;
+
+/// Hashes at most this many bits apart are likely the same page scanned twice.
+pub const DUPLICATE_DISTANCE: u32 = 10;
+
+/// Scans with less of their surface inked are likely blank, e.g. the back of a page
+/// scanned in duplex.
+pub const BLANK_INK: f32 = 0.002;
+
+/// Difference hash: one bit per pair of horizontally adjacent pixels of a 9x8 grayscale
+/// thumbnail, set when the left one is brighter. Survives rescans, resizing and
+/// recompression, unlike a content hash.
+pub fn dhash(image: &DynamicImage) -> u64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).into_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// The first page of a document as an image: the image itself, upright, or the scan a PDF
+/// is made of.
+pub fn first_page(path: &Path) -> Option<DynamicImage> {
+    decode(
+        &utils::extension(path),
+        &storage::backend().read(path).ok()?,
+    )
+}
+
+/// Same as `first_page`, for the `content` of a file with the `extension`, e.g. one that
+/// was just decrypted.
+pub fn decode(extension: &str, content: &[u8]) -> Option<DynamicImage> {
+    match extension {
+        "jpg" | "jpeg" => {
+            let image = image::load_from_memory(content).ok()?;
+            Some(orientation::parse(content).apply(image))
+        }
+        "png" => image::load_from_memory(content).ok(),
+        "pdf" => pdf::first_page(&lopdf::Document::load_mem(content).ok()?),
+        _ => None,
+    }
+}
+
+/// Same as `first_page`, decoded to fit in `max_side` pixels so huge scans never take the
+/// memory of their full resolution.
+pub fn first_page_within(path: &Path, max_side: u32) -> Option<DynamicImage> {
+    decode_within(
+        &utils::extension(path),
+        &storage::backend().read(path).ok()?,
+        max_side,
+    )
+}
+
+/// Same as `decode`, to fit in `max_side` pixels.
+pub fn decode_within(extension: &str, content: &[u8], max_side: u32) -> Option<DynamicImage> {
+    match extension {
+        "jpg" | "jpeg" => {
+            let image = downscale::jpeg(content, max_side)?;
+            Some(orientation::parse(content).apply(image))
+        }
+        "png" => downscale::png(content, max_side),
+        "pdf" => pdf::page_within(&lopdf::Document::load_mem(content).ok()?, 1, max_side),
+        _ => None,
+    }
+}
+
+/// The share of a scan that is inked, leaving out the margins where scanners leave
+/// shadows of the paper's edges.
+pub fn ink(image: &DynamicImage) -> f32 {
+    let small = image.resize(400, 400, FilterType::Triangle).into_luma8();
+    let (width, height) = small.dimensions();
+    let (margin_x, margin_y) = (width / 20, height / 20);
+    let mut inked = 0;
+    let mut total = 0;
+    for y in margin_y..height - margin_y {
+        for x in margin_x..width - margin_x {
+            total += 1;
+            if small.get_pixel(x, y)[0] < 160 {
+                inked += 1;
+            }
+        }
+    }
+    if total == 0 {
+        return 0.0;
+    }
+    inked as f32 / total as f32
+}
+
+/// Overlays two scans of a page: the first one faded to gray, with the pixels where the
+/// second one differs in red.
+pub fn diff(a: &DynamicImage, b: &DynamicImage) -> RgbaImage {
+    let (width, height) = a.dimensions();
+    let a = a.to_luma8();
+    let b = b
+        .resize_exact(width, height, FilterType::Triangle)
+        .into_luma8();
+    RgbaImage::from_fn(width, height, |x, y| {
+        let (pa, pb) = (a.get_pixel(x, y)[0], b.get_pixel(x, y)[0]);
+        if (pa as i16 - pb as i16).abs() > 48 {
+            Rgba([220, 30, 30, 255])
+        } else {
+            let faded = 160 + pa / 3;
+            Rgba([faded, faded, faded, 255])
+        }
+    })
+}
+
+#[test]
+fn test_dhash() {
+    let gradient = DynamicImage::ImageLuma8(image::GrayImage::from_fn(90, 80, |x, y| {
+        image::Luma([((x * 2 + y) % 256) as u8])
+    }));
+    let rescanned = gradient.resize_exact(180, 160, FilterType::Nearest);
+    let flipped = gradient.fliph();
+    assert!(distance(dhash(&gradient), dhash(&rescanned)) <= DUPLICATE_DISTANCE);
+    assert!(distance(dhash(&gradient), dhash(&flipped)) > DUPLICATE_DISTANCE);
+}
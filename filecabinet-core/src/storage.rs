@@ -0,0 +1,216 @@
+use std::fs;
+use std::path::Path;
+
+/// Where the cabinet's documents are kept: the filesystem natively, the files the user
+/// picked in the browser for the web build.
+pub trait Storage: Sync {
+    /// Names of the files directly in `dir`.
+    fn list(&self, dir: &Path) -> Vec<String>;
+    fn read(&self, path: &Path) -> Result<Vec<u8>, StorageError>;
+    /// Fails rather than overwrite a file at `to`.
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), StorageError>;
+    fn remove(&self, path: &Path) -> Result<(), StorageError>;
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum StorageError {
+    ReadError,
+    WriteError,
+    ExistsError,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn backend() -> &'static dyn Storage {
+    &Disk
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn backend() -> &'static dyn Storage {
+    &browser::Browser
+}
+
+pub struct Disk;
+
+/// Whether `a` and `b` are two casings of the name of one file, as on filesystems that
+/// ignore case. Renaming between them only changes the case.
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    let lowercase = |path: &Path| path.to_string_lossy().to_lowercase();
+    lowercase(a) == lowercase(b) && same_inode(a, b)
+}
+
+#[cfg(unix)]
+fn same_inode(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_inode(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+impl Storage for Disk {
+    fn list(&self, dir: &Path) -> Vec<String> {
+        fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            // The type comes with the listing on most systems, only links need a look at
+            // what they point to.
+            .filter(|entry| match entry.file_type() {
+                Ok(kind) if kind.is_symlink() => entry.path().is_file(),
+                Ok(kind) => kind.is_file(),
+                Err(_) => false,
+            })
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect()
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>, StorageError> {
+        fs::read(path).map_err(|_| StorageError::ReadError)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), StorageError> {
+        if from != to && to.exists() && !same_file(from, to) {
+            return Err(StorageError::ExistsError);
+        }
+        fs::rename(from, to).map_err(|_| StorageError::WriteError)
+    }
+
+    fn remove(&self, path: &Path) -> Result<(), StorageError> {
+        fs::remove_file(path).map_err(|_| StorageError::WriteError)
+    }
+}
+
+/// Browsers don't let pages list folders, so the user picks the documents and they are
+/// kept in memory under the cabinet path, renames and deletions included.
+#[cfg(target_arch = "wasm32")]
+pub mod browser {
+    use super::{Storage, StorageError};
+    use std::collections::BTreeMap;
+    use std::path::{Path, PathBuf};
+    use std::sync::Mutex;
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_futures::JsFuture;
+
+    lazy_static! {
+        static ref FILES: Mutex<BTreeMap<PathBuf, Vec<u8>>> = Mutex::new(BTreeMap::new());
+    }
+
+    pub struct Browser;
+
+    impl Storage for Browser {
+        fn list(&self, dir: &Path) -> Vec<String> {
+            let files = FILES.lock().unwrap();
+            files
+                .keys()
+                .filter(|path| path.parent() == Some(dir))
+                .filter_map(|path| path.file_name()?.to_str().map(str::to_string))
+                .collect()
+        }
+
+        fn read(&self, path: &Path) -> Result<Vec<u8>, StorageError> {
+            let files = FILES.lock().unwrap();
+            files.get(path).cloned().ok_or(StorageError::ReadError)
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> Result<(), StorageError> {
+            let mut files = FILES.lock().unwrap();
+            if from != to && files.contains_key(to) {
+                return Err(StorageError::ExistsError);
+            }
+            let content = files.remove(from).ok_or(StorageError::WriteError)?;
+            files.insert(to.to_path_buf(), content);
+            Ok(())
+        }
+
+        fn remove(&self, path: &Path) -> Result<(), StorageError> {
+            let mut files = FILES.lock().unwrap();
+            files
+                .remove(path)
+                .map(|_| ())
+                .ok_or(StorageError::WriteError)
+        }
+    }
+
+    /// Asks the user for documents with the browser's file picker and adds them to the
+    /// cabinet `dir`. Returns how many were added.
+    pub async fn pick(dir: String) -> Result<usize, StorageError> {
+        let document = web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or(StorageError::ReadError)?;
+        let input: web_sys::HtmlInputElement = document
+            .create_element("input")
+            .map_err(|_| StorageError::ReadError)?
+            .dyn_into()
+            .map_err(|_| StorageError::ReadError)?;
+        input.set_type("file");
+        input.set_multiple(true);
+        input.set_accept(".pdf,.jpg,.jpeg,.png,.heic,.cocoon");
+        let changed = js_sys::Promise::new(&mut |resolve, _reject| {
+            input.set_onchange(Some(&resolve));
+        });
+        input.click();
+        JsFuture::from(changed)
+            .await
+            .map_err(|_| StorageError::ReadError)?;
+        let picked = input.files().ok_or(StorageError::ReadError)?;
+        let mut added = 0;
+        for i in 0..picked.length() {
+            let file = match picked.item(i) {
+                Some(file) => file,
+                None => continue,
+            };
+            let buffer = JsFuture::from(file.array_buffer())
+                .await
+                .map_err(|_| StorageError::ReadError)?;
+            let content = js_sys::Uint8Array::new(&buffer).to_vec();
+            FILES
+                .lock()
+                .unwrap()
+                .insert(Path::new(&dir).join(file.name()), content);
+            added += 1;
+        }
+        Ok(added)
+    }
+}
+
+#[test]
+fn test_disk_rename() {
+    let dir = tempdir::TempDir::new("filecabinet-storage").unwrap();
+    let (a, b) = (dir.path().join("a.pdf"), dir.path().join("b.pdf"));
+    fs::write(&a, b"a").unwrap();
+    fs::write(&b, b"b").unwrap();
+    assert!(matches!(
+        Disk.rename(&a, &b),
+        Err(StorageError::ExistsError)
+    ));
+    Disk.remove(&b).unwrap();
+    Disk.rename(&a, &b).unwrap();
+    assert_eq!(Disk.list(dir.path()), vec!["b.pdf".to_string()]);
+    assert_eq!(Disk.read(&b).unwrap(), b"a".to_vec());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_same_file() {
+    let dir = tempdir::TempDir::new("filecabinet-storage").unwrap();
+    let (a, b) = (dir.path().join("chase.pdf"), dir.path().join("b.pdf"));
+    fs::write(&a, b"a").unwrap();
+    fs::write(&b, b"b").unwrap();
+    // Linux tells casings apart, a second link stands in for the other casing.
+    let other_case = dir.path().join("Chase.pdf");
+    fs::hard_link(&a, &other_case).unwrap();
+    assert!(same_file(&a, &other_case));
+    assert!(!same_file(&a, &b));
+    fs::hard_link(&b, dir.path().join("B2.pdf")).unwrap();
+    assert!(!same_file(&b, &dir.path().join("B2.pdf")));
+    assert!(!same_file(&a, &dir.path().join("CHASE.pdf")));
+}
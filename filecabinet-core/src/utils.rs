@@ -0,0 +1,503 @@
+use crate::storage;
+use chrono::NaiveDate;
+use data_encoding::HEXLOWER;
+use regex::Regex;
+use ring::digest::{Context, SHA256};
+
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+pub struct OptDoc {
+    pub date: Option<String>,
+    pub institution: Option<String>,
+    pub name: Option<String>,
+    pub page: Option<String>,
+}
+
+/// Represents a Document with fields that were maybe parseable
+impl OptDoc {
+    pub fn new<T: AsRef<Path>>(filename: T) -> OptDoc {
+        let filename = filename.as_ref();
+        // Scanners write names that aren't UTF-8 at times, those parse as far as they can.
+        let filestem = filename
+            .file_stem()
+            .unwrap_or(filename.as_os_str())
+            .to_string_lossy();
+        let v: Vec<&str> = filestem.split('_').collect();
+        OptDoc {
+            date: v.first().and_then(parse_date),
+            institution: v.get(1).map(|x| x.to_string()),
+            name: v.get(2).map(|x| x.to_string()),
+            page: v.get(3).and_then(parse_page),
+        }
+    }
+    /// The date as a real date, when the filename has one.
+    pub fn day(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(self.date.as_deref()?, "%Y-%m-%d").ok()
+    }
+
+    pub fn page_number(&self) -> Option<u32> {
+        self.page.as_deref()?.parse().ok()
+    }
+
+    pub fn is_parseable(&self) -> bool {
+        self.date.is_some()
+            && self.institution.is_some()
+            && self.name.is_some()
+            && self.page.is_some()
+    }
+
+    /// The normalized filename of the parts with `extension`, when they all parsed.
+    pub fn normalized(&self, extension: &str) -> Option<String> {
+        Some(normalized_filename(
+            self.date.as_deref()?,
+            self.institution.as_deref()?,
+            self.name.as_deref()?,
+            self.page.as_deref()?,
+            extension,
+        ))
+    }
+
+    /// Whether `source`, which this was parsed from, is named as normalized already.
+    pub fn is_normalized<P: AsRef<Path>>(&self, source: P) -> bool {
+        let source = source.as_ref();
+        match (source.file_name(), self.normalized(&extension(source))) {
+            (Some(filename), Some(normalized)) => filename == normalized.as_str(),
+            _ => false,
+        }
+    }
+}
+
+pub fn is_normalized<P: AsRef<Path>>(source: P) -> bool {
+    OptDoc::new(&source).is_normalized(&source)
+}
+
+/// Builds the canonical `date_institution_name_page.ext` filename.
+pub fn normalized_filename(
+    date: &str,
+    institution: &str,
+    name: &str,
+    page: &str,
+    extension: &str,
+) -> String {
+    format!("{}_{}_{}_{}.{}", date, institution, name, page, extension)
+}
+
+/// Why what was typed for a part of the filename wouldn't make a normalized one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PartError {
+    Missing,
+    DateError,
+    /// Underscores separate the parts.
+    UnderscoreError,
+    SlashError,
+    PageError,
+}
+
+impl fmt::Display for PartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartError::Missing => write!(f, "missing"),
+            PartError::DateError => write!(f, "needs a date, e.g. 2021-03-01"),
+            PartError::UnderscoreError => write!(f, "can't have an underscore"),
+            PartError::SlashError => write!(f, "can't have a slash"),
+            PartError::PageError => write!(f, "needs a number, e.g. 1"),
+        }
+    }
+}
+
+/// The date part of the filename as typed, which has to be a `%Y-%m-%d` date.
+pub fn check_date(text: &str) -> Result<String, PartError> {
+    if text.trim().is_empty() {
+        return Err(PartError::Missing);
+    }
+    // chrono takes `2021-3-1` too, the filename wouldn't parse back.
+    match NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        Ok(date) if date.format("%Y-%m-%d").to_string() == text => Ok(text.to_string()),
+        _ => Err(PartError::DateError),
+    }
+}
+
+/// The institution or name part of the filename for what was typed, camel cased.
+pub fn check_word(text: &str) -> Result<String, PartError> {
+    let word = to_camelcase(text);
+    if word.is_empty() {
+        Err(PartError::Missing)
+    } else if word.contains('_') {
+        Err(PartError::UnderscoreError)
+    } else if word.contains(['/', '\\']) {
+        Err(PartError::SlashError)
+    } else {
+        Ok(word)
+    }
+}
+
+/// The page part of the filename as typed, which has to be a number.
+pub fn check_page(text: &str) -> Result<String, PartError> {
+    if text.is_empty() {
+        Err(PartError::Missing)
+    } else if text.chars().all(|c| c.is_ascii_digit()) {
+        Ok(text.to_string())
+    } else {
+        Err(PartError::PageError)
+    }
+}
+
+/// The page a new document of the group `date_institution_name` gets among `filenames`,
+/// the one after the last page filed, e.g. 3 after `_1` and `_2`, or 1 for a new group.
+pub fn next_page<'a>(
+    filenames: impl IntoIterator<Item = &'a str>,
+    date: &str,
+    institution: &str,
+    name: &str,
+) -> u32 {
+    filenames
+        .into_iter()
+        .filter(|filename| is_normalized(filename))
+        .map(OptDoc::new)
+        .filter(|doc| {
+            doc.date.as_deref() == Some(date)
+                && doc.institution.as_deref() == Some(institution)
+                && doc.name.as_deref() == Some(name)
+        })
+        .filter_map(|doc| doc.page_number())
+        .max()
+        .map_or(1, |page| page + 1)
+}
+
+/// Hex encoded SHA-256 of the contents of a file.
+pub fn sha256(path: &Path) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    let mut context = Context::new(&SHA256);
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).ok()?;
+        if read == 0 {
+            break;
+        }
+        context.update(&buffer[..read]);
+    }
+    Some(HEXLOWER.encode(context.finish().as_ref()))
+}
+
+/// Hex encoded SHA-256 of `content`.
+pub fn sha256_bytes(content: &[u8]) -> String {
+    HEXLOWER.encode(ring::digest::digest(&SHA256, content).as_ref())
+}
+
+pub fn extension<P: AsRef<Path>>(source: P) -> String {
+    source
+        .as_ref()
+        .extension()
+        .and_then(std::ffi::OsStr::to_str)
+        .map(|s| s.to_ascii_lowercase())
+        .unwrap_or(String::new())
+}
+
+/// Extensions of the documents a cabinet holds, besides the word processor ones.
+const DOCUMENT_EXTENSIONS: [&str; 6] = ["pdf", "jpg", "jpeg", "heic", "png", "cocoon"];
+
+/// Whether `filename` is a kind of document a cabinet holds.
+pub fn is_document(filename: &str) -> bool {
+    // Without lowercasing into a new string, this runs for every file of the cabinet.
+    let ext = match Path::new(filename)
+        .extension()
+        .and_then(std::ffi::OsStr::to_str)
+    {
+        Some(ext) => ext,
+        None => return false,
+    };
+    DOCUMENT_EXTENSIONS
+        .iter()
+        .chain(&crate::office::EXTENSIONS)
+        .any(|known| ext.eq_ignore_ascii_case(known))
+}
+
+/// `filename`, or `<stem>_2.<extension>` and so on if a document of the cabinet has it.
+/// Scanners and phones reuse names like `scan0001.pdf` or `image.jpg`.
+pub fn free_path(dir: &Path, filename: &str) -> PathBuf {
+    let path = Path::new(filename);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = extension(path);
+    let mut candidate = dir.join(filename);
+    let mut n = 2;
+    while candidate.exists() {
+        candidate = dir.join(format!("{}_{}.{}", stem, n, extension));
+        n += 1;
+    }
+    candidate
+}
+
+/// Escapes what can't be left as is in the path of a URL as `%XX`.
+pub fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Undoes the `%XX` escapes of URLs.
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = text.get(i + 1..i + 3).filter(|_| bytes[i] == b'%');
+        match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+// TODO: use async paths
+pub fn list_files(path: &Path) -> Vec<String> {
+    storage::backend()
+        .list(path)
+        .into_iter()
+        .filter(|filename| is_document(filename))
+        .collect()
+}
+
+pub fn to_camelcase(text: &str) -> String {
+    let text = text.trim();
+    let mut result = String::with_capacity(text.len());
+    let mut start_of_word = true;
+    for c in text.chars() {
+        if c == ' ' {
+            start_of_word = true;
+        } else if start_of_word {
+            result.push(c.to_ascii_uppercase());
+            start_of_word = false;
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+#[test]
+fn test_free_path() {
+    let dir = tempdir::TempDir::new("cabinet").unwrap();
+    std::fs::write(dir.path().join("scan0001.pdf"), b"scan").unwrap();
+    assert_eq!(
+        free_path(dir.path(), "scan0001.pdf"),
+        dir.path().join("scan0001_2.pdf")
+    );
+    assert_eq!(
+        free_path(dir.path(), "image.jpg"),
+        dir.path().join("image.jpg")
+    );
+}
+
+#[test]
+fn test_next_page() {
+    let filenames = [
+        "2021-03-04_Chase_Statement_1.pdf",
+        "2021-03-04_Chase_Statement_2.jpg",
+        "2021-03-04_Chase_Receipt_1.pdf",
+        "2021-03-04_Chase_Statement_x.pdf",
+    ];
+    assert_eq!(next_page(filenames, "2021-03-04", "Chase", "Statement"), 3);
+    assert_eq!(next_page(filenames, "2021-03-04", "Chase", "Receipt"), 2);
+    assert_eq!(next_page(filenames, "2021-03-05", "Chase", "Statement"), 1);
+}
+
+#[test]
+fn test_check_parts() {
+    assert_eq!(check_date("2021-03-04"), Ok("2021-03-04".to_string()));
+    assert_eq!(check_date(""), Err(PartError::Missing));
+    assert_eq!(check_date("2021-3-4"), Err(PartError::DateError));
+    assert_eq!(check_date("2021-02-30"), Err(PartError::DateError));
+    assert_eq!(check_date(" 2021-03-04"), Err(PartError::DateError));
+    assert_eq!(
+        check_word("bank of america"),
+        Ok("BankOfAmerica".to_string())
+    );
+    assert_eq!(check_word("  "), Err(PartError::Missing));
+    assert_eq!(check_word("my_bank"), Err(PartError::UnderscoreError));
+    assert_eq!(check_word("a/b"), Err(PartError::SlashError));
+    assert_eq!(check_page("02"), Ok("02".to_string()));
+    assert_eq!(check_page("two"), Err(PartError::PageError));
+    let filename = normalized_filename("2021-03-04", "Chase", "Statement", "02", "pdf");
+    assert!(is_normalized(filename));
+}
+
+#[test]
+fn test_to_camelcase() {
+    assert_eq!(to_camelcase("hello this is a test"), "HelloThisIsATest");
+    assert_eq!(to_camelcase("_a"), "_a");
+    assert_eq!(to_camelcase("boopLoop"), "BoopLoop");
+}
+
+lazy_static! {
+    static ref RE_PARSE_PAGE: Regex = Regex::new(r"(\d+)").unwrap();
+}
+
+/// The page number in the page part of a filename, e.g. `20` of `pg20`.
+pub fn parse_page(text: &&str) -> Option<String> {
+    RE_PARSE_PAGE
+        .captures(text)
+        .and_then(|c| c.get(1))
+        .map(|m| m.as_str().to_owned())
+}
+
+#[test]
+fn test_parse_page() {
+    assert_eq!(parse_page(&""), None);
+    assert_eq!(parse_page(&"pg"), None);
+    assert_eq!(parse_page(&"01"), Some("01".to_owned()));
+    assert_eq!(parse_page(&"20"), Some("20".to_owned()));
+    assert_eq!(parse_page(&"pg20"), Some("20".to_owned()));
+}
+
+lazy_static! {
+    static ref RE_WITH_HYPHENS: Regex =
+        Regex::new(r"^(?P<year>\d{4})-(?P<month>\d{2})-(?P<day>\d{2})").unwrap();
+    static ref RE_NO_HYPHENS: Regex =
+        Regex::new(r"^(?P<year>\d{4})(?P<month>\d{2})(?P<day>\d{2})").unwrap();
+    static ref RE_YEAR_ONLY: Regex = Regex::new(r"^(?P<year>\d{4})").unwrap();
+}
+
+pub fn parse_date(text: &&str) -> Option<String> {
+    // Returns the parsed date in ISO8601 format
+    RE_WITH_HYPHENS
+        .captures(text)
+        .map(|x| {
+            format!(
+                "{}-{}-{}",
+                x.name("year").unwrap().as_str(),
+                x.name("month").unwrap().as_str(),
+                x.name("day").unwrap().as_str(),
+            )
+        })
+        .or(RE_NO_HYPHENS.captures(text).map(|x| {
+            format!(
+                "{}-{}-{}",
+                x.name("year").unwrap().as_str(),
+                x.name("month").unwrap().as_str(),
+                x.name("day").unwrap().as_str(),
+            )
+        }))
+        .or(RE_YEAR_ONLY.captures(text).map(|x| {
+            format!(
+                "{}-{}-{}",
+                x.name("year").unwrap().as_str(),
+                x.name("month").map(|m| m.as_str()).unwrap_or("01"),
+                x.name("day").map(|m| m.as_str()).unwrap_or("01"),
+            )
+        }))
+}
+
+#[test]
+fn test_parse_date_hyphens() {
+    assert_eq!(
+        parse_date(&"2020-04-03_boop_loop"),
+        Some("2020-04-03".to_string())
+    )
+}
+
+#[test]
+fn test_parse_date_no_hyphens() {
+    assert_eq!(
+        parse_date(&"20180530_boop_loop"),
+        Some("2018-05-30".to_string())
+    )
+}
+#[test]
+fn test_parse_date_year_only() {
+    assert_eq!(
+        parse_date(&"2018_boop_loop"),
+        Some("2018-01-01".to_string())
+    )
+}
+#[test]
+fn test_opt_doc_fields() {
+    let doc = OptDoc::new("/docs/2021-03-04_Chase_Statement_pg02.pdf");
+    assert_eq!(doc.day(), NaiveDate::from_ymd_opt(2021, 3, 4));
+    assert_eq!(doc.page_number(), Some(2));
+    let doc = OptDoc::new("/docs/2021-13-40_Chase_Statement_1.pdf");
+    assert_eq!(doc.day(), None);
+    assert_eq!(OptDoc::new("scan0001.pdf").page_number(), None);
+}
+
+#[cfg(test)]
+proptest::proptest! {
+    #[test]
+    fn test_parse_any_filename(filename in proptest::prelude::any::<String>()) {
+        let doc = OptDoc::new(&filename);
+        is_normalized(&filename);
+        if let Some(date) = &doc.date {
+            proptest::prop_assert_eq!(parse_date(&date.as_str()), Some(date.clone()));
+        }
+        if let Some(page) = &doc.page {
+            proptest::prop_assert_eq!(parse_page(&page.as_str()), Some(page.clone()));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_parse_any_bytes(bytes in proptest::collection::vec(proptest::prelude::any::<u8>(), 0..64)) {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        let filename = Path::new(OsStr::from_bytes(&bytes));
+        OptDoc::new(filename);
+        is_normalized(filename);
+    }
+
+    // Normalizing a normalized filename gives it back.
+    #[test]
+    fn test_normalize_twice(filename in proptest::prelude::any::<String>()) {
+        let normalize = |filename: &str| OptDoc::new(filename).normalized(&extension(filename));
+        if let Some(normalized) = normalize(&filename) {
+            proptest::prop_assert_eq!(normalize(&normalized), Some(normalized));
+        }
+        let word = to_camelcase(&filename);
+        proptest::prop_assert_eq!(to_camelcase(&word), word.clone());
+        if let Ok(word) = check_word(&filename) {
+            proptest::prop_assert_eq!(check_word(&word), Ok(word));
+        }
+    }
+
+    // The parts checked in the rename form parse back from the filename they make.
+    #[test]
+    fn test_parts_round_trip(
+        days in 365..365 * 9000i32,
+        institution in proptest::prelude::any::<String>(),
+        name in proptest::prelude::any::<String>(),
+        page in "[0-9]{1,4}",
+        extension in "[a-z0-9]{1,4}",
+    ) {
+        let date = NaiveDate::from_num_days_from_ce_opt(days).unwrap().format("%Y-%m-%d").to_string();
+        let (institution, name) = match (check_word(&institution), check_word(&name)) {
+            (Ok(institution), Ok(name)) => (institution, name),
+            _ => return Ok(()),
+        };
+        let filename = normalized_filename(
+            &check_date(&date).unwrap(),
+            &institution,
+            &name,
+            &check_page(&page).unwrap(),
+            &extension,
+        );
+        let doc = OptDoc::new(&filename);
+        proptest::prop_assert_eq!(doc.date, Some(date));
+        proptest::prop_assert_eq!(doc.institution, Some(institution));
+        proptest::prop_assert_eq!(doc.name, Some(name));
+        proptest::prop_assert_eq!(doc.page, Some(page));
+        proptest::prop_assert!(is_normalized(&filename));
+    }
+}
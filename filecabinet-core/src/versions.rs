@@ -0,0 +1,76 @@
+use crate::catalog::Catalog;
+use crate::utils;
+use chrono::Local;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum VersionError {
+    DirectoryError,
+    WriteError,
+}
+
+/// Where the earlier versions of the document at `path` are kept, one folder per
+/// document in the cabinet's metadata.
+fn versions_dir(path: &Path) -> Option<PathBuf> {
+    let dir = path.parent()?;
+    let filename = path.file_name()?;
+    Some(root(dir).join(filename))
+}
+
+/// The folder holding the versions of the documents of the cabinet at `dir`.
+pub fn root(dir: &Path) -> PathBuf {
+    Catalog::path(dir).with_file_name("versions")
+}
+
+/// Copies the document at `path` aside before the app rewrites it. Returns the copy.
+pub fn keep(path: &Path) -> Result<PathBuf, VersionError> {
+    let dir = versions_dir(path).ok_or(VersionError::DirectoryError)?;
+    fs::create_dir_all(&dir).map_err(|_| VersionError::DirectoryError)?;
+    let stamp = Local::now().format("%Y-%m-%d_%H%M%S");
+    let copy = utils::free_path(&dir, &format!("{}.{}", stamp, utils::extension(path)));
+    fs::copy(path, &copy).map_err(|_| VersionError::WriteError)?;
+    info!(event = "KeepVersion", file = %path.display(), version = %copy.display());
+    Ok(copy)
+}
+
+/// The earlier versions of the document at `path`, oldest first.
+pub fn list(path: &Path) -> Vec<PathBuf> {
+    let mut versions: Vec<PathBuf> = versions_dir(path)
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    versions.sort();
+    versions
+}
+
+/// Keeps the versions of a document renamed within its cabinet.
+pub fn rename_versions(old: &Path, new: &Path) {
+    if let (Some(old), Some(new)) = (versions_dir(old), versions_dir(new)) {
+        if old.is_dir() {
+            if let Err(error) = fs::rename(&old, &new) {
+                warn!(event = "versions_rename_failed", file = %old.display(), ?error);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_versions() {
+    let dir = tempdir::TempDir::new("versions").unwrap();
+    let path = dir.path().join("2021-01-05_Chase_Statement_1.pdf");
+    fs::write(&path, b"first").unwrap();
+    let first = keep(&path).unwrap();
+    fs::write(&path, b"second").unwrap();
+    keep(&path).unwrap();
+    assert_eq!(list(&path).len(), 2);
+    assert_eq!(list(&path)[0], first);
+    assert_eq!(fs::read(&first).unwrap(), b"first");
+
+    let renamed = dir.path().join("2021-01-05_Chase_Statement_2.pdf");
+    rename_versions(&path, &renamed);
+    assert!(list(&path).is_empty());
+    assert_eq!(list(&renamed).len(), 2);
+}
@@ -0,0 +1,140 @@
+use crate::catalog::Catalog;
+use crate::utils;
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum ArchiveError {
+    DirectoryError,
+    ReadError,
+    WriteError,
+    ExistsError,
+    VerifyError,
+}
+
+fn write(path: &Path, content: &[u8]) -> Result<(), ArchiveError> {
+    AtomicFile::new(path, OverwriteBehavior::DisallowOverwrite)
+        .write(|f| f.write_all(content))
+        .map_err(|_| ArchiveError::WriteError)
+}
+
+/// Whether the file at `path` holds the content hashed as `sha256`.
+fn holds(path: &Path, sha256: &str) -> bool {
+    utils::sha256(path).as_deref() == Some(sha256)
+}
+
+/// Moves `filename` of the cabinet at `dir` into `<archive_dir>/<cabinet name>/`, e.g. on
+/// an external drive or a mounted remote. The copy is read back before the original is
+/// removed, and the catalog keeps the entry so the document can be restored later.
+/// Returns where it was archived to.
+pub fn archive_document(
+    dir: &Path,
+    filename: &str,
+    archive_dir: &Path,
+) -> Result<PathBuf, ArchiveError> {
+    if archive_dir.starts_with(dir) {
+        return Err(ArchiveError::DirectoryError);
+    }
+    let source = dir.join(filename);
+    let content = fs::read(&source).map_err(|_| ArchiveError::ReadError)?;
+    let sha256 = utils::sha256_bytes(&content);
+    let folder = archive_dir.join(dir.file_name().ok_or(ArchiveError::DirectoryError)?);
+    fs::create_dir_all(&folder).map_err(|_| ArchiveError::DirectoryError)?;
+    let target = folder.join(filename);
+    if target.exists() {
+        // Left over from an archival that didn't get to remove the original.
+        if !holds(&target, &sha256) {
+            return Err(ArchiveError::ExistsError);
+        }
+    } else {
+        write(&target, &content)?;
+        if !holds(&target, &sha256) {
+            return Err(ArchiveError::VerifyError);
+        }
+    }
+    let mut catalog = Catalog::load(dir);
+    let entry = catalog.entry(filename);
+    entry.sha256 = Some(sha256);
+    entry.archived = Some(target.to_string_lossy().to_string());
+    catalog.save(dir).map_err(|_| ArchiveError::WriteError)?;
+    fs::remove_file(&source).map_err(|_| ArchiveError::WriteError)?;
+    Ok(target)
+}
+
+/// Brings the archived `filename` back into the cabinet at `dir` and removes its copy
+/// from the archive.
+pub fn restore_document(dir: &Path, filename: &str) -> Result<(), ArchiveError> {
+    let mut catalog = Catalog::load(dir);
+    let entry = catalog.get(filename).ok_or(ArchiveError::ReadError)?;
+    let archived = PathBuf::from(entry.archived.as_ref().ok_or(ArchiveError::ReadError)?);
+    let content = fs::read(&archived).map_err(|_| ArchiveError::ReadError)?;
+    // The drive may have been damaged while it sat on a shelf.
+    if entry.sha256.as_deref() != Some(utils::sha256_bytes(&content).as_str()) {
+        return Err(ArchiveError::VerifyError);
+    }
+    let path = dir.join(filename);
+    if path.exists() {
+        return Err(ArchiveError::ExistsError);
+    }
+    write(&path, &content)?;
+    catalog.entry(filename).archived = None;
+    catalog.save(dir).map_err(|_| ArchiveError::WriteError)?;
+    if let Err(error) = fs::remove_file(&archived) {
+        warn!(event = "unarchive_cleanup_failed", file = %archived.display(), ?error);
+    }
+    Ok(())
+}
+
+/// Archives the documents at `paths`, all in the same cabinet. Returns how many were
+/// archived.
+pub async fn archive(paths: Vec<String>, archive_dir: String) -> Result<usize, ArchiveError> {
+    for path in &paths {
+        let path = Path::new(path);
+        let (dir, filename) = match (path.parent(), path.file_name()) {
+            (Some(dir), Some(filename)) => (dir, filename.to_string_lossy()),
+            _ => return Err(ArchiveError::ReadError),
+        };
+        let target = archive_document(dir, &filename, Path::new(&archive_dir))?;
+        info!(event = "Archive", file = %path.display(), to = %target.display());
+    }
+    Ok(paths.len())
+}
+
+/// Restores the archived document at `path`, returned once it is back.
+pub async fn restore(path: String) -> Result<String, ArchiveError> {
+    let (dir, filename) = match (Path::new(&path).parent(), Path::new(&path).file_name()) {
+        (Some(dir), Some(filename)) => (dir, filename.to_string_lossy()),
+        _ => return Err(ArchiveError::ReadError),
+    };
+    restore_document(dir, &filename)?;
+    info!(event = "Unarchive", file = %path);
+    Ok(path)
+}
+
+#[test]
+fn test_archive_and_restore() {
+    let cabinet = tempdir::TempDir::new("cabinet").unwrap();
+    let archive_dir = tempdir::TempDir::new("archive").unwrap();
+    let dir = cabinet.path();
+    let filename = "2012-03-04_Bank_Statement_1.pdf";
+    fs::write(dir.join(filename), b"statement").unwrap();
+    let target = archive_document(dir, filename, archive_dir.path()).unwrap();
+    assert!(!dir.join(filename).exists());
+    assert_eq!(fs::read(&target).unwrap(), b"statement");
+    let catalog = Catalog::load(dir);
+    assert_eq!(catalog.archived(), vec![filename]);
+    assert!(catalog.missing(dir).is_empty());
+    assert!(archive_document(dir, "scan.pdf", archive_dir.path()).is_err());
+
+    fs::write(&target, b"damaged").unwrap();
+    assert!(restore_document(dir, filename).is_err());
+    fs::write(&target, b"statement").unwrap();
+    restore_document(dir, filename).unwrap();
+    assert_eq!(fs::read(dir.join(filename)).unwrap(), b"statement");
+    assert!(!target.exists());
+    assert!(Catalog::load(dir).archived().is_empty());
+}
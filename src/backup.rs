@@ -0,0 +1,250 @@
+use crate::catalog::Catalog;
+use crate::utils;
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use chrono::Local;
+use cocoon::Cocoon;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// What a snapshot records, the content itself is stored once per hash.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    /// Filenames and the SHA-256 of their content.
+    files: BTreeMap<String, String>,
+    /// Hash of the cabinet's catalog, if it had one.
+    catalog: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum BackupError {
+    DirectoryError,
+    ReadError,
+    WriteError,
+    EncryptError,
+    DecryptError,
+    FormatError,
+}
+
+/// A snapshot that was just written, and how many files weren't in the backup yet.
+#[derive(Debug, Clone)]
+pub struct Created {
+    pub snapshot: String,
+    pub added: usize,
+}
+
+/// Known plaintext sealed with the passphrase of the backup's first encrypted snapshot.
+const MARKER: &[u8] = b"filecabinet backup";
+
+/// Sealed objects are kept apart, so plain snapshots never share content with them.
+fn object_path(target: &Path, sha256: &str, sealed: bool) -> PathBuf {
+    let objects = if sealed { "sealed" } else { "objects" };
+    target.join(objects).join(&sha256[..2]).join(sha256)
+}
+
+/// Encrypted snapshots share their content, so they all have to use the same passphrase.
+fn check_passphrase(target: &Path, passphrase: &str) -> Result<(), BackupError> {
+    let path = target.join("passphrase.cocoon");
+    match fs::read(&path) {
+        Ok(marker) => Cocoon::new(passphrase.as_bytes())
+            .unwrap(&marker)
+            .map(|_| ())
+            .map_err(|_| BackupError::DecryptError),
+        Err(_) => write(&path, &seal(MARKER, Some(passphrase))?),
+    }
+}
+
+fn write(path: &Path, content: &[u8]) -> Result<(), BackupError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|_| BackupError::DirectoryError)?;
+    }
+    AtomicFile::new(path, OverwriteBehavior::AllowOverwrite)
+        .write(|f| f.write_all(content))
+        .map_err(|_| BackupError::WriteError)
+}
+
+fn seal(content: &[u8], passphrase: Option<&str>) -> Result<Vec<u8>, BackupError> {
+    match passphrase {
+        Some(passphrase) => Cocoon::new(passphrase.as_bytes())
+            .wrap(content)
+            .map_err(|_| BackupError::EncryptError),
+        None => Ok(content.to_vec()),
+    }
+}
+
+fn open(sealed: Vec<u8>, passphrase: Option<&str>) -> Result<Vec<u8>, BackupError> {
+    match passphrase {
+        Some(passphrase) => Cocoon::new(passphrase.as_bytes())
+            .unwrap(&sealed)
+            .map_err(|_| BackupError::DecryptError),
+        None => Ok(sealed),
+    }
+}
+
+/// Stores `content` under its hash unless it is there already, returns the hash and
+/// whether it was added.
+fn store(
+    target: &Path,
+    content: &[u8],
+    passphrase: Option<&str>,
+) -> Result<(String, bool), BackupError> {
+    let sha256 = utils::sha256_bytes(content);
+    let path = object_path(target, &sha256, passphrase.is_some());
+    if path.exists() {
+        return Ok((sha256, false));
+    }
+    write(&path, &seal(content, passphrase)?)?;
+    Ok((sha256, true))
+}
+
+/// Snapshots of the backup at `target`, newest first. Encrypted ones end in `.cocoon`.
+pub fn snapshots(target: &Path) -> Vec<String> {
+    let mut snapshots: Vec<String> = fs::read_dir(target.join("snapshots"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.ends_with(".json") || name.ends_with(".cocoon"))
+        .collect();
+    snapshots.sort_by(|a, b| b.cmp(a));
+    snapshots
+}
+
+/// Writes a snapshot of the cabinet at `dir` into `target`, sealing everything with
+/// `passphrase` if there is one. Only content the backup doesn't have yet is copied.
+pub fn create(dir: &Path, target: &Path, passphrase: Option<&str>) -> Result<Created, BackupError> {
+    if !dir.is_dir() || target.starts_with(dir) {
+        return Err(BackupError::DirectoryError);
+    }
+    if let Some(passphrase) = passphrase {
+        check_passphrase(target, passphrase)?;
+    }
+    let mut manifest = Manifest::default();
+    let mut added = 0;
+    for filename in utils::list_files(dir) {
+        let content = fs::read(dir.join(&filename)).map_err(|_| BackupError::ReadError)?;
+        let (sha256, new) = store(target, &content, passphrase)?;
+        added += new as usize;
+        manifest.files.insert(filename, sha256);
+    }
+    if let Ok(catalog) = fs::read(Catalog::path(dir)) {
+        manifest.catalog = Some(store(target, &catalog, passphrase)?.0);
+    }
+    let json = serde_json::to_vec_pretty(&manifest).map_err(|_| BackupError::FormatError)?;
+    let snapshot = format!(
+        "{}.{}",
+        Local::now().format("%Y-%m-%d_%H-%M-%S"),
+        if passphrase.is_some() {
+            "cocoon"
+        } else {
+            "json"
+        }
+    );
+    write(
+        &target.join("snapshots").join(&snapshot),
+        &seal(&json, passphrase)?,
+    )?;
+    Ok(Created { snapshot, added })
+}
+
+/// Writes the files of `snapshot` into `dir`, leaving the ones already there alone.
+/// The catalog is restored too when `dir` has none. Returns how many files were written.
+pub fn restore_snapshot(
+    target: &Path,
+    snapshot: &str,
+    passphrase: Option<&str>,
+    dir: &Path,
+) -> Result<usize, BackupError> {
+    let passphrase = if snapshot.ends_with(".cocoon") {
+        Some(passphrase.ok_or(BackupError::DecryptError)?)
+    } else {
+        None
+    };
+    let sealed =
+        fs::read(target.join("snapshots").join(snapshot)).map_err(|_| BackupError::ReadError)?;
+    let manifest: Manifest =
+        serde_json::from_slice(&open(sealed, passphrase)?).map_err(|_| BackupError::FormatError)?;
+    let read = |sha256: &str| {
+        let sealed = fs::read(object_path(target, sha256, passphrase.is_some()))
+            .map_err(|_| BackupError::ReadError)?;
+        let content = open(sealed, passphrase)?;
+        // A damaged backup must not pass for the original document.
+        if utils::sha256_bytes(&content) != sha256 {
+            return Err(BackupError::FormatError);
+        }
+        Ok(content)
+    };
+    fs::create_dir_all(dir).map_err(|_| BackupError::DirectoryError)?;
+    let mut restored = 0;
+    for (filename, sha256) in &manifest.files {
+        let path = dir.join(filename);
+        if path.exists() {
+            continue;
+        }
+        write(&path, &read(sha256)?)?;
+        restored += 1;
+    }
+    if let (Some(sha256), false) = (&manifest.catalog, Catalog::path(dir).exists()) {
+        write(&Catalog::path(dir), &read(sha256)?)?;
+    }
+    Ok(restored)
+}
+
+pub async fn backup(
+    dir: String,
+    target: String,
+    passphrase: Option<String>,
+) -> Result<Created, BackupError> {
+    let created = create(Path::new(&dir), Path::new(&target), passphrase.as_deref())?;
+    info!(event = "Backup", dir = %dir, target = %target, snapshot = %created.snapshot, added = created.added);
+    Ok(created)
+}
+
+pub async fn restore(
+    target: String,
+    snapshot: String,
+    passphrase: Option<String>,
+    dir: String,
+) -> Result<usize, BackupError> {
+    let restored = restore_snapshot(
+        Path::new(&target),
+        &snapshot,
+        passphrase.as_deref(),
+        Path::new(&dir),
+    )?;
+    info!(event = "Restore", target = %target, snapshot = %snapshot, dir = %dir, restored);
+    Ok(restored)
+}
+
+#[test]
+fn test_backup_and_restore() {
+    let cabinet = tempdir::TempDir::new("cabinet").unwrap();
+    let target = tempdir::TempDir::new("backup").unwrap();
+    let (dir, target) = (cabinet.path(), target.path());
+    fs::write(dir.join("2021-03-04_Bank_Statement_1.pdf"), b"statement").unwrap();
+    fs::write(dir.join("2021-03-04_Bank_Statement_2.pdf"), b"statement").unwrap();
+    let created = create(dir, target, None).unwrap();
+    assert_eq!(created.added, 1);
+    fs::write(dir.join("2021-04-04_Bank_Statement_1.pdf"), b"april").unwrap();
+    assert_eq!(create(dir, target, Some("secret")).unwrap().added, 2);
+    assert!(create(dir, target, Some("other secret")).is_err());
+    let encrypted = snapshots(target)
+        .into_iter()
+        .find(|s| s.ends_with(".cocoon"))
+        .unwrap();
+
+    let restored = tempdir::TempDir::new("restored").unwrap();
+    assert!(restore_snapshot(target, &encrypted, Some("wrong"), restored.path()).is_err());
+    assert_eq!(
+        restore_snapshot(target, &created.snapshot, None, restored.path()).unwrap(),
+        2
+    );
+    assert_eq!(
+        fs::read(restored.path().join("2021-03-04_Bank_Statement_2.pdf")).unwrap(),
+        b"statement"
+    );
+}
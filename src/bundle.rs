@@ -0,0 +1,449 @@
+use crate::catalog::{self, Catalog};
+use crate::fields::{self, FieldDef};
+use crate::packet::csv_field;
+use crate::pdf;
+use crate::utils::OptDoc;
+use aes::block_cipher_trait::generic_array::GenericArray;
+use aes::block_cipher_trait::BlockCipher;
+use aes::Aes256;
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, Timelike};
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use ring::{hmac, pbkdf2};
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fs;
+use std::io::Write;
+use std::num::NonZeroU32;
+use std::path::Path;
+use tracing::info;
+
+/// Shorter passwords are refused, the bundle is meant to travel by email.
+pub const MIN_PASSWORD: usize = 8;
+
+/// The listing of what the bundle holds, no document may take its name.
+const MANIFEST: &str = "manifest.csv";
+
+/// Iterations of PBKDF2 the WinZip AES format prescribes.
+const ITERATIONS: u32 = 1000;
+
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const VERIFIER_LEN: usize = 2;
+const MAC_LEN: usize = 10;
+
+/// Compression method announcing WinZip AES encryption, the real one is in the extra field.
+const METHOD_AES: u16 = 99;
+const METHOD_DEFLATE: u16 = 8;
+/// Version 5.1 of the zip specification, the first with AES.
+const VERSION: u16 = 51;
+/// Encrypted, names in UTF-8.
+const FLAGS: u16 = 0x0001 | 0x0800;
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum BundleError {
+    PasswordError,
+    ReadError,
+    DirectoryError,
+    WriteError,
+    /// A document can't be stamped, e.g. an encrypted PDF.
+    StampError,
+    /// Over 4 GiB or 65535 files, which needs zip64.
+    SizeError,
+}
+
+/// A file of the zip, encrypted and compressed.
+struct Entry {
+    name: String,
+    /// Salt, password verifier, encrypted data and authentication code.
+    data: Vec<u8>,
+    size: u32,
+    offset: u32,
+}
+
+/// The encryption key, the authentication key and the password verifier of an entry,
+/// one after the other.
+fn derive_keys(password: &str, salt: &[u8]) -> [u8; 2 * KEY_LEN + VERIFIER_LEN] {
+    let mut keys = [0; 2 * KEY_LEN + VERIFIER_LEN];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA1,
+        NonZeroU32::new(ITERATIONS).unwrap(),
+        salt,
+        password.as_bytes(),
+        &mut keys,
+    );
+    keys
+}
+
+/// Encrypts or decrypts `data` in place with AES-256 in the counter mode of WinZip: a
+/// little endian counter starting at 1.
+fn apply_keystream(key: &[u8], data: &mut [u8]) {
+    let cipher = Aes256::new(GenericArray::from_slice(key));
+    for (counter, chunk) in data.chunks_mut(16).enumerate() {
+        let mut block = [0; 16];
+        block[..8].copy_from_slice(&(counter as u64 + 1).to_le_bytes());
+        cipher.encrypt_block(GenericArray::from_mut_slice(&mut block));
+        for (byte, key) in chunk.iter_mut().zip(block.iter()) {
+            *byte ^= key;
+        }
+    }
+}
+
+/// The HMAC-SHA1 of the encrypted data, of which AE-2 keeps the first bytes.
+fn mac(key: &[u8], encrypted: &[u8]) -> hmac::Tag {
+    hmac::sign(
+        &hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, key),
+        encrypted,
+    )
+}
+
+/// Encrypts `plain` the way of WinZip AE-2, authenticated with the first bytes of an
+/// HMAC-SHA1.
+fn encrypt(password: &str, plain: &[u8]) -> Vec<u8> {
+    let salt = rand::random::<[u8; SALT_LEN]>();
+    let keys = derive_keys(password, &salt);
+    let mut encrypted = plain.to_vec();
+    apply_keystream(&keys[..KEY_LEN], &mut encrypted);
+    let mac = mac(&keys[KEY_LEN..2 * KEY_LEN], &encrypted);
+    let mut data = Vec::with_capacity(SALT_LEN + VERIFIER_LEN + encrypted.len() + MAC_LEN);
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&keys[2 * KEY_LEN..]);
+    data.extend_from_slice(&encrypted);
+    data.extend_from_slice(&mac.as_ref()[..MAC_LEN]);
+    data
+}
+
+/// Time and date in the MS-DOS format of zip headers.
+fn dos_time(time: NaiveDateTime) -> (u16, u16) {
+    (
+        ((time.hour() << 11) | (time.minute() << 5) | (time.second() / 2)) as u16,
+        (((time.year().max(1980) - 1980) << 9) as u32 | (time.month() << 5) | time.day()) as u16,
+    )
+}
+
+/// The extra field telling the real compression method of an AES encrypted entry.
+fn aes_extra() -> Vec<u8> {
+    let mut extra = Vec::with_capacity(11);
+    extra.extend_from_slice(&0x9901u16.to_le_bytes());
+    extra.extend_from_slice(&7u16.to_le_bytes());
+    // AE-2, leaving out the CRC that would give away small files.
+    extra.extend_from_slice(&2u16.to_le_bytes());
+    extra.extend_from_slice(b"AE");
+    // AES-256.
+    extra.push(3);
+    extra.extend_from_slice(&METHOD_DEFLATE.to_le_bytes());
+    extra
+}
+
+/// A zip of `files`, name and contents, encrypted with `password` the way 7-Zip, WinZip
+/// and the archive tools of macOS and most Linux desktops can open.
+pub fn zip(files: &[(String, Vec<u8>)], password: &str) -> Result<Vec<u8>, BundleError> {
+    if files.len() >= u16::MAX as usize {
+        return Err(BundleError::SizeError);
+    }
+    let (time, date) = dos_time(Local::now().naive_local());
+    let extra = aes_extra();
+    let header = |signature: u32, entry: &Entry| {
+        let mut header = Vec::new();
+        header.extend_from_slice(&signature.to_le_bytes());
+        if signature == 0x0201_4b50 {
+            // Made by: the version, on Unix.
+            header.extend_from_slice(&(VERSION | 3 << 8).to_le_bytes());
+        }
+        for field in [VERSION, FLAGS, METHOD_AES, time, date] {
+            header.extend_from_slice(&field.to_le_bytes());
+        }
+        // No CRC with AE-2.
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&(entry.data.len() as u32).to_le_bytes());
+        header.extend_from_slice(&entry.size.to_le_bytes());
+        header.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+        if signature == 0x0201_4b50 {
+            // No comment, first disk, binary.
+            header.extend_from_slice(&[0; 6]);
+            // A regular file readable by everyone.
+            header.extend_from_slice(&(0o100644u32 << 16).to_le_bytes());
+            header.extend_from_slice(&entry.offset.to_le_bytes());
+        }
+        header.extend_from_slice(entry.name.as_bytes());
+        header.extend_from_slice(&extra);
+        header
+    };
+
+    let mut zip = Vec::new();
+    let mut entries = Vec::with_capacity(files.len());
+    for (name, contents) in files {
+        let mut deflater = DeflateEncoder::new(Vec::new(), Compression::default());
+        deflater
+            .write_all(contents)
+            .map_err(|_| BundleError::WriteError)?;
+        let compressed = deflater.finish().map_err(|_| BundleError::WriteError)?;
+        let entry = Entry {
+            name: name.clone(),
+            data: encrypt(password, &compressed),
+            size: u32::try_from(contents.len()).map_err(|_| BundleError::SizeError)?,
+            offset: u32::try_from(zip.len()).map_err(|_| BundleError::SizeError)?,
+        };
+        u32::try_from(entry.data.len()).map_err(|_| BundleError::SizeError)?;
+        zip.extend(header(0x0403_4b50, &entry));
+        zip.extend_from_slice(&entry.data);
+        entries.push(entry);
+    }
+    let directory_offset = u32::try_from(zip.len()).map_err(|_| BundleError::SizeError)?;
+    for entry in &entries {
+        zip.extend(header(0x0201_4b50, entry));
+    }
+    let directory_len = zip.len() as u32 - directory_offset;
+    zip.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    zip.extend_from_slice(&[0; 4]);
+    zip.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    zip.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    zip.extend_from_slice(&directory_len.to_le_bytes());
+    zip.extend_from_slice(&directory_offset.to_le_bytes());
+    zip.extend_from_slice(&0u16.to_le_bytes());
+    u32::try_from(zip.len()).map_err(|_| BundleError::SizeError)?;
+    Ok(zip)
+}
+
+/// `name`, or `name` with ` (2)`, ` (3)`... before its extension if a file of the zip is
+/// named so already. Names are told apart without case, as they are once extracted on
+/// Windows or macOS.
+fn unique_name(name: &str, taken: &mut HashSet<String>) -> String {
+    let path = Path::new(name);
+    let stem = path
+        .file_stem()
+        .map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+    let extension = path.extension().map_or(String::new(), |extension| {
+        format!(".{}", extension.to_string_lossy())
+    });
+    let mut candidate = name.to_string();
+    let mut n = 2;
+    while !taken.insert(candidate.to_lowercase()) {
+        candidate = format!("{} ({}){}", stem, n, extension);
+        n += 1;
+    }
+    candidate
+}
+
+/// A CSV listing what the bundle holds, for whoever opens it, with the custom fields
+/// `defs` of the cabinet. `sources` are the filenames the `files` have in the cabinet,
+/// which their fields are read from.
+fn manifest(
+    files: &[(String, Vec<u8>)],
+    sources: &[String],
+    defs: &[FieldDef],
+    catalog: &Catalog,
+) -> String {
+    let header: Vec<String> = [
+        "File",
+        "Date",
+        "Institution",
+        "Title",
+        "Page",
+        "Bytes",
+        "SHA256",
+    ]
+    .iter()
+    .map(|f| f.to_string())
+    .chain(fields::csv_header(defs))
+    .map(|f| csv_field(&f))
+    .collect();
+    let mut csv = format!("{}\n", header.join(","));
+    for ((name, contents), source) in files.iter().zip(sources) {
+        let doc = OptDoc::new(source);
+        let digest = ring::digest::digest(&ring::digest::SHA256, contents);
+        let row = [
+            name.clone(),
+            doc.date.unwrap_or_default(),
+            doc.institution.unwrap_or_default(),
+            doc.name.unwrap_or_default(),
+            doc.page.unwrap_or_default(),
+            contents.len().to_string(),
+            data_encoding::HEXLOWER.encode(digest.as_ref()),
+        ];
+        let custom = fields::csv_row(defs, catalog.get(source));
+        let row: Vec<String> = row.iter().chain(&custom).map(|f| csv_field(f)).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// The stamp `template` gives for copies provided to `recipient` on `date`, with
+/// `{recipient}` and `{date}` filled in.
+pub fn stamp_text(template: &str, recipient: &str, date: NaiveDate) -> String {
+    template
+        .replace("{recipient}", recipient.trim())
+        .replace("{date}", &date.format("%Y-%m-%d").to_string())
+}
+
+/// Writes the documents at `paths`, a `manifest.csv` of them and the `extra` files, e.g. a
+/// checklist, into a zip encrypted with `password`, in `<dir>/bundles`. With a `stamp`,
+/// every page of the copies shows it, and images go in as PDFs. Files that would have
+/// the name of another get a number, see `unique_name`. Returns the path of the zip.
+pub async fn export(
+    dir: String,
+    paths: Vec<String>,
+    extra: Vec<(String, Vec<u8>)>,
+    password: String,
+    stamp: Option<String>,
+) -> Result<String, BundleError> {
+    if password.chars().count() < MIN_PASSWORD {
+        return Err(BundleError::PasswordError);
+    }
+    let mut taken: HashSet<String> = std::iter::once(MANIFEST.to_string()).collect();
+    let mut files = Vec::with_capacity(paths.len() + 1 + extra.len());
+    let mut sources = Vec::with_capacity(paths.len());
+    for path in &paths {
+        let path = Path::new(path);
+        let filename = path
+            .file_name()
+            .ok_or(BundleError::ReadError)?
+            .to_string_lossy()
+            .to_string();
+        let (name, contents) = match &stamp {
+            Some(text) => (
+                Path::new(&filename)
+                    .with_extension("pdf")
+                    .to_string_lossy()
+                    .to_string(),
+                pdf::stamped(path, text).map_err(|_| BundleError::StampError)?,
+            ),
+            None => (
+                filename.clone(),
+                fs::read(path).map_err(|_| BundleError::ReadError)?,
+            ),
+        };
+        files.push((unique_name(&name, &mut taken), contents));
+        sources.push(filename);
+    }
+    let manifest = manifest(
+        &files,
+        &sources,
+        &fields::load(Path::new(&dir)),
+        &Catalog::load(Path::new(&dir)),
+    );
+    files.push((MANIFEST.to_string(), manifest.into_bytes()));
+    for (name, contents) in extra {
+        files.push((unique_name(&name, &mut taken), contents));
+    }
+    let zip = zip(&files, &password)?;
+
+    let out_dir = Path::new(&dir).join("bundles");
+    fs::create_dir_all(&out_dir).map_err(|_| BundleError::DirectoryError)?;
+    let time = Local::now().format("%Y-%m-%d_%H%M%S");
+    let zip_path = out_dir.join(format!("Bundle_{}.zip", time));
+    fs::write(&zip_path, zip).map_err(|_| BundleError::WriteError)?;
+    catalog::record_exports(Path::new(&dir), &paths);
+    info!(
+        event = "ExportBundle",
+        file = %zip_path.display(),
+        documents = paths.len(),
+        stamped = stamp.is_some()
+    );
+    Ok(zip_path.to_string_lossy().to_string())
+}
+
+#[test]
+fn test_zip() {
+    let files = vec![
+        (
+            "2021-03-04_Chase_Statement_1.pdf".to_string(),
+            b"%PDF statement ".repeat(100),
+        ),
+        ("notes, draft.txt".to_string(), b"short".to_vec()),
+    ];
+    let zip = zip(&files, "correct horse").unwrap();
+    assert_eq!(&zip[..4], b"PK\x03\x04");
+    assert_eq!(&zip[zip.len() - 22..zip.len() - 18], b"PK\x05\x06");
+    // Two entries, and nothing of the contents in the clear.
+    assert_eq!(zip[zip.len() - 12], 2);
+    assert!(!zip.windows(9).any(|w| w == b"statement"));
+    // The central directory starts where the end record says, names 46 bytes in.
+    let end = &zip[zip.len() - 22..];
+    let directory = u32::from_le_bytes([end[16], end[17], end[18], end[19]]) as usize;
+    assert_eq!(&zip[directory..directory + 4], b"PK\x01\x02");
+    assert_eq!(&zip[directory + 46..directory + 78], files[0].0.as_bytes());
+
+    let defs = vec![FieldDef {
+        name: "Case number".to_string(),
+        ..Default::default()
+    }];
+    let mut catalog = Catalog::default();
+    catalog
+        .entry("2021-03-04_Chase_Statement_1.pdf")
+        .fields
+        .insert("Case number".to_string(), "A-12".to_string());
+    let sources: Vec<String> = files.iter().map(|(name, _)| name.clone()).collect();
+    let csv = manifest(&files, &sources, &defs, &catalog);
+    assert!(csv.starts_with("File,Date,Institution,Title,Page,Bytes,SHA256,Case number\n"));
+    assert!(csv.contains(",A-12\n"));
+    assert!(csv.contains("2021-03-04_Chase_Statement_1.pdf,2021-03-04,Chase,Statement,1,1500,"));
+    assert!(csv.contains("\"notes, draft.txt\""));
+
+    assert_eq!(
+        stamp_text(
+            "Copy provided to {recipient} on {date}",
+            " Acme Mortgage ",
+            NaiveDate::from_ymd_opt(2021, 3, 4).unwrap()
+        ),
+        "Copy provided to Acme Mortgage on 2021-03-04"
+    );
+}
+
+#[test]
+fn test_unique_name() {
+    let mut taken: HashSet<String> = std::iter::once(MANIFEST.to_string()).collect();
+    assert_eq!(unique_name("a.pdf", &mut taken), "a.pdf");
+    assert_eq!(unique_name("a.pdf", &mut taken), "a (2).pdf");
+    assert_eq!(unique_name("A.PDF", &mut taken), "A (3).PDF");
+    assert_eq!(unique_name("Manifest.csv", &mut taken), "Manifest (2).csv");
+    assert_eq!(unique_name("notes", &mut taken), "notes");
+    assert_eq!(unique_name("notes", &mut taken), "notes (2)");
+}
+
+/// The name and contents of the first entry of `zip`, once its code is checked and it is
+/// decrypted with `password`. `None` if the code doesn't match.
+#[cfg(test)]
+fn unzip_first(zip: &[u8], password: &str) -> Option<(String, Vec<u8>)> {
+    use std::io::Read;
+    let u16_at = |at: usize| u16::from_le_bytes([zip[at], zip[at + 1]]) as usize;
+    let compressed = u32::from_le_bytes([zip[18], zip[19], zip[20], zip[21]]) as usize;
+    let (name_len, extra_len) = (u16_at(26), u16_at(28));
+    let name = String::from_utf8(zip[30..30 + name_len].to_vec()).ok()?;
+    let start = 30 + name_len + extra_len;
+    let data = &zip[start..start + compressed];
+
+    let (salt, rest) = data.split_at(SALT_LEN);
+    let (verifier, rest) = rest.split_at(VERIFIER_LEN);
+    let (encrypted, code) = rest.split_at(rest.len() - MAC_LEN);
+    let keys = derive_keys(password, salt);
+    if mac(&keys[KEY_LEN..2 * KEY_LEN], encrypted).as_ref()[..MAC_LEN] != *code {
+        return None;
+    }
+    assert_eq!(verifier, &keys[2 * KEY_LEN..]);
+    let mut deflated = encrypted.to_vec();
+    apply_keystream(&keys[..KEY_LEN], &mut deflated);
+    let mut contents = Vec::new();
+    flate2::read::DeflateDecoder::new(&deflated[..])
+        .read_to_end(&mut contents)
+        .ok()?;
+    Some((name, contents))
+}
+
+#[test]
+fn test_zip_round_trip() {
+    let statement = b"%PDF statement ".repeat(100);
+    let files = vec![(
+        "2021-03-04_Chase_Statement_1.pdf".to_string(),
+        statement.clone(),
+    )];
+    let zip = zip(&files, "correct horse").unwrap();
+    assert_eq!(
+        unzip_first(&zip, "correct horse"),
+        Some(("2021-03-04_Chase_Statement_1.pdf".to_string(), statement))
+    );
+    assert_eq!(unzip_first(&zip, "battery staple"), None);
+}
@@ -0,0 +1,186 @@
+use crate::utils;
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Name of the manifest, the one `sha256sum` users expect.
+pub const FILENAME: &str = "SHA256SUMS";
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum ChecksumError {
+    ReadError,
+    WriteError,
+    FormatError,
+}
+
+/// What verifying a manifest found, filenames in manifest order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    pub ok: usize,
+    /// Content differs from the one the manifest recorded.
+    pub changed: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.changed.is_empty() && self.missing.is_empty()
+    }
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} OK, {} changed, {} missing",
+            self.ok,
+            self.changed.len(),
+            self.missing.len()
+        )
+    }
+}
+
+pub fn path(dir: &Path) -> PathBuf {
+    dir.join(FILENAME)
+}
+
+/// A manifest line. Like `sha256sum`, names with a backslash or a newline are escaped and
+/// the line starts with a backslash then.
+fn line(sha256: &str, filename: &str) -> String {
+    if filename.contains(['\\', '\n']) {
+        let escaped = filename.replace('\\', "\\\\").replace('\n', "\\n");
+        format!("\\{}  {}\n", sha256, escaped)
+    } else {
+        format!("{}  {}\n", sha256, filename)
+    }
+}
+
+fn unescape(filename: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = filename.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Reads the lines `sha256sum` writes, in text (`  `) or binary (` *`) mode.
+pub fn parse(manifest: &str) -> Result<Vec<(String, String)>, ChecksumError> {
+    manifest
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (escaped, line) = match line.strip_prefix('\\') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            let (sha256, filename) = line
+                .split_at_checked(64)
+                .ok_or(ChecksumError::FormatError)?;
+            let filename = filename
+                .strip_prefix("  ")
+                .or_else(|| filename.strip_prefix(" *"))
+                .ok_or(ChecksumError::FormatError)?;
+            if !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(ChecksumError::FormatError);
+            }
+            let filename = if escaped {
+                unescape(filename)
+            } else {
+                filename.to_string()
+            };
+            Ok((sha256.to_ascii_lowercase(), filename))
+        })
+        .collect()
+}
+
+/// Writes the hashes of every document of the cabinet at `dir` into its `SHA256SUMS`,
+/// which `sha256sum -c SHA256SUMS` checks from within the cabinet. Returns how many
+/// documents were hashed.
+pub fn write(dir: &Path) -> Result<usize, ChecksumError> {
+    let hashes: BTreeMap<String, String> = utils::list_files(dir)
+        .into_iter()
+        .map(|filename| {
+            let sha256 = utils::sha256(&dir.join(&filename)).ok_or(ChecksumError::ReadError)?;
+            Ok((filename, sha256))
+        })
+        .collect::<Result<_, _>>()?;
+    let manifest: String = hashes
+        .iter()
+        .map(|(filename, sha256)| line(sha256, filename))
+        .collect();
+    AtomicFile::new(path(dir), OverwriteBehavior::AllowOverwrite)
+        .write(|f| f.write_all(manifest.as_bytes()))
+        .map_err(|_| ChecksumError::WriteError)?;
+    Ok(hashes.len())
+}
+
+/// Checks the documents of the cabinet at `dir` against the `manifest`, which may come
+/// from `sha256sum` as well.
+pub fn verify(dir: &Path, manifest: &Path) -> Result<Report, ChecksumError> {
+    let manifest = fs::read_to_string(manifest).map_err(|_| ChecksumError::ReadError)?;
+    let mut report = Report::default();
+    for (sha256, filename) in parse(&manifest)? {
+        match utils::sha256(&dir.join(&filename)) {
+            Some(actual) if actual == sha256 => report.ok += 1,
+            Some(_) => report.changed.push(filename),
+            None => report.missing.push(filename),
+        }
+    }
+    Ok(report)
+}
+
+pub async fn export(dir: String) -> Result<usize, ChecksumError> {
+    let hashed = write(Path::new(&dir))?;
+    info!(event = "WriteChecksums", dir = %dir, hashed);
+    Ok(hashed)
+}
+
+pub async fn check(dir: String) -> Result<Report, ChecksumError> {
+    let report = verify(Path::new(&dir), &path(Path::new(&dir)))?;
+    info!(event = "VerifyChecksums", dir = %dir, ?report);
+    Ok(report)
+}
+
+#[test]
+fn test_write_and_verify() {
+    let dir = tempdir::TempDir::new("checksums").unwrap();
+    let dir = dir.path();
+    fs::write(dir.join("2021-03-04_Bank_Statement_1.pdf"), b"statement").unwrap();
+    fs::write(dir.join("2021-04-04_Bank_Statement_1.pdf"), b"april").unwrap();
+    assert_eq!(write(dir).unwrap(), 2);
+    let manifest = fs::read_to_string(path(dir)).unwrap();
+    assert!(manifest.starts_with(&format!(
+        "{}  2021-03-04_Bank_Statement_1.pdf\n",
+        utils::sha256_bytes(b"statement")
+    )));
+
+    fs::write(dir.join("2021-04-04_Bank_Statement_1.pdf"), b"edited").unwrap();
+    fs::remove_file(dir.join("2021-03-04_Bank_Statement_1.pdf")).unwrap();
+    let report = verify(dir, &path(dir)).unwrap();
+    assert_eq!(report.changed, vec!["2021-04-04_Bank_Statement_1.pdf"]);
+    assert_eq!(report.missing, vec!["2021-03-04_Bank_Statement_1.pdf"]);
+    assert!(!report.passed());
+
+    assert_eq!(
+        parse(&line(&"a".repeat(64), "back\\nslash\nnewline.pdf")).unwrap(),
+        vec![("a".repeat(64), "back\\nslash\nnewline.pdf".to_string())]
+    );
+    assert_eq!(
+        parse(&format!("{} *binary.pdf\n", "B".repeat(64))).unwrap(),
+        vec![("b".repeat(64), "binary.pdf".to_string())]
+    );
+    assert!(parse("not a manifest").is_err());
+}
@@ -0,0 +1,153 @@
+use crate::catalog::{Catalog, CatalogError, Entry};
+use crate::orphans::{self, Orphans};
+use crate::search;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Downloads from the drop folder and temp files of interrupted writes this old are
+/// left over from a crash, not in use.
+const LEFTOVER_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// What compacting the metadata of a cabinet did.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Compaction {
+    /// Bytes of `<cabinet>/.filecabinet` before and after.
+    pub before: u64,
+    pub after: u64,
+    pub orphans: Orphans,
+    /// Catalog entries that held nothing.
+    pub empty_entries: usize,
+    /// Interrupted downloads and writes.
+    pub leftovers: Vec<PathBuf>,
+}
+
+impl std::fmt::Display for Compaction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "metadata {} → {}, {} empty entries, {} leftover files, {} previews",
+            crate::stats::size(self.before),
+            crate::stats::size(self.after),
+            self.empty_entries,
+            self.leftovers.len(),
+            self.orphans.previews.len()
+        )
+    }
+}
+
+fn metadata_dir(dir: &Path) -> PathBuf {
+    Catalog::path(dir).parent().unwrap_or(dir).to_path_buf()
+}
+
+fn is_old(path: &Path) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age > LEFTOVER_AGE)
+}
+
+/// Old partial downloads of the drop folder and the temp folders of atomic writes that
+/// never got moved in place.
+fn leftovers(dir: &Path) -> Vec<PathBuf> {
+    let metadata = metadata_dir(dir);
+    let mut leftovers = Vec::new();
+    for folder in [
+        metadata.clone(),
+        metadata.join("incoming"),
+        search::text_dir(dir),
+    ] {
+        let incoming = folder.ends_with("incoming");
+        for entry in fs::read_dir(&folder).into_iter().flatten().flatten() {
+            let path = entry.path();
+            let temp = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(".atomicwrite"));
+            if (incoming || temp) && is_old(&path) {
+                leftovers.push(path);
+            }
+        }
+    }
+    leftovers.sort();
+    leftovers
+}
+
+/// Drops the orphaned metadata of the cabinet at `dir` and cached previews over
+/// `preview_max_age` (see `orphans::clean`), catalog entries holding nothing and
+/// leftovers of interrupted downloads and writes.
+pub fn compact(dir: &Path, preview_max_age: Option<Duration>) -> Result<Compaction, CatalogError> {
+    let before = orphans::size(&metadata_dir(dir));
+    let orphans = orphans::clean(dir, preview_max_age, false)?;
+
+    let mut catalog = Catalog::load(dir);
+    let count = catalog.entries.len();
+    catalog
+        .entries
+        .retain(|_, entry| *entry != Entry::default());
+    let empty_entries = count - catalog.entries.len();
+    if empty_entries > 0 {
+        catalog.save(dir)?;
+    }
+
+    let leftovers = leftovers(dir);
+    for path in &leftovers {
+        let removed = if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        };
+        if let Err(error) = removed {
+            warn!(event = "leftover_remove_failed", file = %path.display(), ?error);
+        }
+    }
+
+    let compaction = Compaction {
+        before,
+        after: orphans::size(&metadata_dir(dir)),
+        orphans,
+        empty_entries,
+        leftovers,
+    };
+    info!(
+        event = "Compact",
+        dir = %dir.display(),
+        before = compaction.before,
+        after = compaction.after,
+        empty_entries = compaction.empty_entries,
+        leftovers = compaction.leftovers.len()
+    );
+    Ok(compaction)
+}
+
+pub async fn run(dir: String) -> Result<Compaction, CatalogError> {
+    compact(Path::new(&dir), Some(orphans::PREVIEW_MAX_AGE))
+}
+
+#[test]
+fn test_compact() {
+    let dir = tempdir::TempDir::new("compact").unwrap();
+    let dir = dir.path();
+    fs::write(dir.join("kept.pdf"), b"kept").unwrap();
+    let mut catalog = Catalog::default();
+    catalog.entry("kept.pdf").favorite = true;
+    catalog.entry("scan.pdf");
+    catalog.entry("gone.pdf").indexed = true;
+    catalog.save(dir).unwrap();
+    fs::write(dir.join("scan.pdf"), b"scan").unwrap();
+    let incoming = metadata_dir(dir).join("incoming");
+    fs::create_dir_all(&incoming).unwrap();
+    fs::write(incoming.join("fresh.pdf"), b"downloading").unwrap();
+
+    let compaction = compact(dir, None).unwrap();
+    assert_eq!(compaction.orphans.entries, ["gone.pdf"]);
+    assert_eq!(compaction.empty_entries, 1);
+    // Only old leftovers go, this download may still be running.
+    assert!(compaction.leftovers.is_empty());
+    assert!(incoming.join("fresh.pdf").exists());
+    assert!(compaction.after < compaction.before);
+    let catalog = Catalog::load(dir);
+    assert_eq!(catalog.entries.len(), 1);
+    assert!(catalog.get("kept.pdf").unwrap().favorite);
+}
@@ -0,0 +1,311 @@
+use crate::utils;
+#[cfg(unix)]
+use iced_native::futures::channel::mpsc;
+#[cfg(unix)]
+use iced_native::futures::stream::{BoxStream, StreamExt};
+#[cfg(unix)]
+use iced_native::subscription::Recipe;
+use std::fs;
+#[cfg(unix)]
+use std::io::{self, BufRead, BufReader, Write};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::thread;
+#[cfg(unix)]
+use std::time::Duration;
+use tracing::info;
+#[cfg(unix)]
+use tracing::warn;
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum ControlError {
+    NotFoundError,
+    WriteError,
+}
+
+/// What a script asked the running app for, one per line on the control socket.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Request {
+    /// `add <path>`: copies the document into the intake folder.
+    Add(String),
+    /// `open <path>`: copies the document into the open cabinet unless it is in there, and
+    /// opens it for naming. Documents the app is launched with are opened.
+    Open(String),
+    /// `search <query>`: searches the document list, an empty query clears it.
+    Search(String),
+    /// `focus <filename>`: previews the document of the open cabinet.
+    Focus(String),
+    /// `raise`: brings the window to the front, e.g. when the app is launched again.
+    Raise,
+}
+
+impl Request {
+    pub fn parse(line: &str) -> Result<Request, String> {
+        let line = line.trim();
+        let (verb, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let argument = argument.trim().to_string();
+        match verb {
+            "add" if argument.is_empty() => Err("add needs a path".to_string()),
+            "add" if !Path::new(&argument).is_file() => Err(format!("no file at {}", argument)),
+            "add" => Ok(Request::Add(argument)),
+            "open" if argument.is_empty() => Err("open needs a path".to_string()),
+            "open" if !Path::new(&argument).is_file() => Err(format!("no file at {}", argument)),
+            "open" => Ok(Request::Open(argument)),
+            "search" => Ok(Request::Search(argument)),
+            "focus" if argument.is_empty() => Err("focus needs a filename".to_string()),
+            "focus" => Ok(Request::Focus(argument)),
+            "raise" => Ok(Request::Raise),
+            _ => Err(format!(
+                "unknown command {:?}, try add, open, search, focus or raise",
+                verb
+            )),
+        }
+    }
+}
+
+/// Where the running app listens, in the runtime folder of the user. Without one the
+/// socket is in the shared temporary folder, so only the user may connect to it.
+#[cfg(unix)]
+pub fn socket_path() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("filecabinet.sock"),
+        None => {
+            let user = std::env::var("USER").unwrap_or_default();
+            std::env::temp_dir().join(format!("filecabinet-{}.sock", user))
+        }
+    }
+}
+
+/// Answers every line of `stream` with `ok` or `error: <why>`, passing the requests on.
+#[cfg(unix)]
+fn handle(stream: UnixStream, sender: &mpsc::UnboundedSender<Request>) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match Request::parse(&line) {
+            Ok(request) => {
+                info!(event = "ControlRequest", request = ?request);
+                let _ = sender.unbounded_send(request);
+                writer.write_all(b"ok\n")?;
+            }
+            Err(error) => writer.write_all(format!("error: {}\n", error).as_bytes())?,
+        }
+    }
+    Ok(())
+}
+
+/// Accepts connections until the subscription is dropped, which closes `sender`. Each
+/// is answered on a thread of its own, so a client that stays silent holds up no other.
+#[cfg(unix)]
+fn serve(listener: UnixListener, path: PathBuf, sender: mpsc::UnboundedSender<Request>) {
+    while !sender.is_closed() {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    if let Err(error) = handle(stream, &sender) {
+                        warn!(event = "control_request_failed", ?error);
+                    }
+                });
+            }
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(250))
+            }
+            Err(error) => warn!(event = "control_accept_failed", ?error),
+        }
+    }
+    let _ = fs::remove_file(path);
+}
+
+/// Listens on the control socket so scripts and file managers can drive the app, e.g.
+/// `echo "add $FILE" | socat - UNIX-CONNECT:$XDG_RUNTIME_DIR/filecabinet.sock`, or
+/// `filecabinet control add $FILE`. Produces every request understood.
+#[cfg(unix)]
+pub struct Control;
+
+#[cfg(unix)]
+impl<H, E> Recipe<H, E> for Control
+where
+    H: std::hash::Hasher,
+{
+    type Output = Request;
+
+    fn hash(&self, state: &mut H) {
+        use std::hash::Hash;
+        struct Marker;
+        std::any::TypeId::of::<Marker>().hash(state);
+    }
+
+    fn stream(self: Box<Self>, _input: BoxStream<'static, E>) -> BoxStream<'static, Request> {
+        let (sender, receiver) = mpsc::unbounded();
+        let path = socket_path();
+        // A socket nobody answers on is left over from an app that crashed.
+        if path.exists() && UnixStream::connect(&path).is_err() {
+            let _ = fs::remove_file(&path);
+        }
+        // Polling for connections lets the thread notice the subscription ended. Other
+        // users mustn't connect, the socket may be in the shared temporary folder.
+        let listener = UnixListener::bind(&path).and_then(|listener| {
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+            listener.set_nonblocking(true)?;
+            Ok(listener)
+        });
+        match listener {
+            Ok(listener) => {
+                info!(event = "ControlStart", socket = %path.display());
+                thread::spawn(move || serve(listener, path, sender));
+            }
+            Err(error) => warn!(event = "control_start_failed", socket = %path.display(), ?error),
+        }
+        receiver.boxed()
+    }
+}
+
+/// Sends `lines` to the running app. Returns its answer to each, fails when no app runs.
+#[cfg(unix)]
+pub fn send(lines: &[String]) -> io::Result<Vec<String>> {
+    let stream = UnixStream::connect(socket_path())?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let mut answers = Vec::with_capacity(lines.len());
+    for line in lines {
+        writer.write_all(format!("{}\n", line.trim()).as_bytes())?;
+        let mut answer = String::new();
+        reader.read_line(&mut answer)?;
+        answers.push(answer.trim().to_string());
+    }
+    Ok(answers)
+}
+
+/// Hands `documents` to the app running already and brings its window up, so a second
+/// one doesn't fight it over the catalog. Returns whether one was running.
+#[cfg(unix)]
+pub fn forward(documents: &[String]) -> bool {
+    let mut lines: Vec<String> = documents
+        .iter()
+        .map(|path| format!("open {}", path))
+        .collect();
+    lines.push("raise".to_string());
+    match send(&lines) {
+        Ok(answers) => {
+            for (line, answer) in lines.iter().zip(&answers).filter(|(_, a)| *a != "ok") {
+                eprintln!("{}: {}", line, answer);
+            }
+            info!(event = "ForwardLaunch", documents = documents.len());
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Copies the document at `path` into `dir`, keeping its name unless taken.
+fn copy_into(path: &Path, dir: &Path) -> Result<PathBuf, ControlError> {
+    let filename = path.file_name().ok_or(ControlError::NotFoundError)?;
+    let destination = utils::free_path(dir, &filename.to_string_lossy());
+    fs::copy(path, &destination).map_err(|_| ControlError::WriteError)?;
+    Ok(destination)
+}
+
+/// Copies the document at `path` into `dir`, the intake folder. Returns where it went.
+pub async fn add(path: String, dir: String) -> Result<String, ControlError> {
+    let destination = copy_into(Path::new(&path), Path::new(&dir))?;
+    info!(event = "ControlAdd", from = %path, to = %destination.display());
+    Ok(destination.to_string_lossy().to_string())
+}
+
+/// Copies the document at `path` into `dir`, the open cabinet, unless it is in there
+/// already. Returns where it is and whether it was copied.
+pub async fn open(path: String, dir: String) -> Result<(String, bool), ControlError> {
+    if Path::new(&path).parent() == Some(Path::new(&dir)) {
+        return Ok((path, false));
+    }
+    let destination = copy_into(Path::new(&path), Path::new(&dir))?;
+    info!(event = "ControlOpen", from = %path, to = %destination.display());
+    Ok((destination.to_string_lossy().to_string(), true))
+}
+
+#[test]
+fn test_control() {
+    let dir = tempdir::TempDir::new("control").unwrap();
+    let scan = dir.path().join("scan.pdf");
+    fs::write(&scan, b"scan").unwrap();
+    let scan = scan.to_string_lossy().to_string();
+    assert_eq!(
+        Request::parse(&format!("add {}\n", scan)),
+        Ok(Request::Add(scan.clone()))
+    );
+    assert!(Request::parse("add /nowhere/scan.pdf").is_err());
+    assert!(Request::parse("add").is_err());
+    assert_eq!(
+        Request::parse(&format!("open {}", scan)),
+        Ok(Request::Open(scan.clone()))
+    );
+    assert_eq!(
+        Request::parse("search institution:Chase AND 2021"),
+        Ok(Request::Search("institution:Chase AND 2021".to_string()))
+    );
+    assert_eq!(Request::parse("search"), Ok(Request::Search(String::new())));
+    assert!(Request::parse("focus").is_err());
+    assert_eq!(Request::parse("raise"), Ok(Request::Raise));
+    assert!(Request::parse("delete a.pdf").is_err());
+
+    let inbox = dir.path().join("inbox");
+    fs::create_dir(&inbox).unwrap();
+    let first = copy_into(Path::new(&scan), &inbox).unwrap();
+    let second = copy_into(Path::new(&scan), &inbox).unwrap();
+    assert_eq!(first, inbox.join("scan.pdf"));
+    assert_ne!(first, second);
+    assert!(second.is_file());
+
+    #[cfg(unix)]
+    {
+        let (client, server) = UnixStream::pair().unwrap();
+        let (sender, mut receiver) = mpsc::unbounded();
+        let handler = thread::spawn(move || handle(server, &sender));
+        let mut writer = client.try_clone().unwrap();
+        writer.write_all(b"focus a.pdf\n\nfly\n").unwrap();
+        drop(writer);
+        client.shutdown(std::net::Shutdown::Write).unwrap();
+        let answers: Vec<String> = BufReader::new(client).lines().map(Result::unwrap).collect();
+        assert_eq!(answers[0], "ok");
+        assert!(answers[1].starts_with("error: unknown command"));
+        handler.join().unwrap().unwrap();
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            Request::Focus("a.pdf".to_string())
+        );
+
+        // A client that says nothing doesn't keep the next one waiting.
+        let path = dir.path().join("control.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let (sender, receiver) = mpsc::unbounded();
+        let server = {
+            let path = path.clone();
+            thread::spawn(move || serve(listener, path, sender))
+        };
+        let _silent = UnixStream::connect(&path).unwrap();
+        let client = UnixStream::connect(&path).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        (&client).write_all(b"raise\n").unwrap();
+        let mut answer = String::new();
+        BufReader::new(&client).read_line(&mut answer).unwrap();
+        assert_eq!(answer, "ok\n");
+        drop(receiver);
+        server.join().unwrap();
+        assert!(!path.exists());
+    }
+}
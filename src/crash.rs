@@ -0,0 +1,91 @@
+use crate::logging;
+use chrono::Local;
+use std::backtrace::Backtrace;
+use std::fs;
+use std::panic::{self, PanicHookInfo};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing::error;
+
+lazy_static! {
+    /// The latest application state, flushed by the panic hook if it wasn't saved yet.
+    static ref UNSAVED: Mutex<Option<String>> = Mutex::new(None);
+}
+
+/// Writes the app state somewhere it is loaded from on the next launch.
+pub type Flush = fn(&str) -> Result<(), String>;
+
+/// Where crash reports are written.
+pub fn crash_dir() -> Option<PathBuf> {
+    directories_next::ProjectDirs::from("rs", "d6e", "filecabinet")
+        .map(|project_dirs| project_dirs.data_local_dir().join("crashes"))
+}
+
+/// Remembers the state to flush if the app panics before it is saved.
+pub fn unsaved(state: String) {
+    if let Ok(mut unsaved) = UNSAVED.lock() {
+        *unsaved = Some(state);
+    }
+}
+
+/// Forgets the state once it is on disk.
+pub fn saved() {
+    if let Ok(mut unsaved) = UNSAVED.lock() {
+        *unsaved = None;
+    }
+}
+
+/// On a panic, flushes the unsaved state with `flush` and writes a crash report with the
+/// recent log events, before the default hook prints the panic.
+pub fn install(flush: Flush) {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        // The lock may be held by the panicking thread, better lose the state than hang.
+        let state = match UNSAVED.try_lock().map(|mut unsaved| unsaved.take()) {
+            Ok(Some(state)) => match flush(&state) {
+                Ok(()) => "flushed".to_string(),
+                Err(error) => format!("lost ({})", error),
+            },
+            Ok(None) => "already saved".to_string(),
+            Err(_) => "lost (locked)".to_string(),
+        };
+        let report = report(info, &state, &logging::recent_events());
+        let written = crash_dir().and_then(|dir| {
+            fs::create_dir_all(&dir).ok()?;
+            let path = dir.join(format!(
+                "crash-{}.txt",
+                Local::now().format("%Y%m%d-%H%M%S")
+            ));
+            fs::write(&path, report).ok()?;
+            Some(path)
+        });
+        error!(event = "panic", state = %state, report = ?written);
+        default_hook(info);
+    }));
+}
+
+fn report(info: &PanicHookInfo, state: &str, recent: &[String]) -> String {
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+    let location = info
+        .location()
+        .map(|l| format!("{}:{}", l.file(), l.line()))
+        .unwrap_or_default();
+    format!(
+        "filecabinet {} crashed on {}\n\n\
+         Panic: {}\nLocation: {}\nThread: {}\nUnsaved state: {}\n\n\
+         Recent events:\n{}\n\nBacktrace:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        message,
+        location,
+        std::thread::current().name().unwrap_or("unnamed"),
+        state,
+        recent.join("\n"),
+        Backtrace::force_capture()
+    )
+}
@@ -0,0 +1,259 @@
+use crate::catalog::Catalog;
+use crate::fields::{self, FieldDef};
+use crate::packet::csv_field;
+use crate::utils::{self, OptDoc};
+use chrono::Local;
+#[cfg(not(target_arch = "wasm32"))]
+use iced_native::futures::channel::mpsc;
+#[cfg(not(target_arch = "wasm32"))]
+use iced_native::futures::stream::{BoxStream, StreamExt};
+#[cfg(not(target_arch = "wasm32"))]
+use iced_native::subscription::Recipe;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+use tracing::info;
+
+/// Rows written between two progress reports.
+const PROGRESS_EVERY: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum ExportError {
+    DirectoryError,
+    WriteError,
+    /// The export was cancelled, nothing was left behind.
+    CancelledError,
+}
+
+/// What a running export reports.
+#[derive(Debug, Clone)]
+pub enum Progress {
+    /// Rows written so far, out of how many.
+    Wrote(usize, usize),
+    /// The path of the export.
+    Finished(Result<String, ExportError>),
+}
+
+fn header(defs: &[FieldDef]) -> Vec<String> {
+    [
+        "File",
+        "Date",
+        "Institution",
+        "Title",
+        "Page",
+        "Tags",
+        "Owner",
+    ]
+    .iter()
+    .map(|f| f.to_string())
+    .chain(fields::csv_header(defs))
+    .collect()
+}
+
+fn row(filename: &str, defs: &[FieldDef], catalog: &Catalog) -> Vec<String> {
+    let doc = OptDoc::new(filename);
+    let entry = catalog.get(filename);
+    let row = [
+        filename.to_string(),
+        doc.date.unwrap_or_default(),
+        doc.institution.unwrap_or_default(),
+        doc.name.unwrap_or_default(),
+        doc.page.unwrap_or_default(),
+        entry.map(|e| e.tags.join(" ")).unwrap_or_default(),
+        entry.and_then(|e| e.owner.clone()).unwrap_or_default(),
+    ];
+    row.iter()
+        .cloned()
+        .chain(fields::csv_row(defs, entry))
+        .collect()
+}
+
+/// Writes a row for each of `filenames` to `writer` as they are built, so only one is
+/// held in memory at a time. JSON exports are an array of objects keyed by the CSV
+/// header. `keep_going` is told the rows written so far every `PROGRESS_EVERY` rows and
+/// stops the export by returning false.
+pub fn write_rows<W: Write>(
+    writer: &mut W,
+    format: Format,
+    filenames: &[String],
+    defs: &[FieldDef],
+    catalog: &Catalog,
+    mut keep_going: impl FnMut(usize) -> bool,
+) -> Result<(), ExportError> {
+    let header = header(defs);
+    match format {
+        Format::Csv => {
+            let line: Vec<String> = header.iter().map(|f| csv_field(f)).collect();
+            writeln!(writer, "{}", line.join(",")).map_err(|_| ExportError::WriteError)?;
+        }
+        Format::Json => writer
+            .write_all(b"[")
+            .map_err(|_| ExportError::WriteError)?,
+    }
+    for (i, filename) in filenames.iter().enumerate() {
+        if i % PROGRESS_EVERY == 0 && !keep_going(i) {
+            return Err(ExportError::CancelledError);
+        }
+        let row = row(filename, defs, catalog);
+        let written = match format {
+            Format::Csv => {
+                let line: Vec<String> = row.iter().map(|f| csv_field(f)).collect();
+                writeln!(writer, "{}", line.join(","))
+            }
+            Format::Json => {
+                let object: serde_json::Map<String, serde_json::Value> = header
+                    .iter()
+                    .cloned()
+                    .zip(row.into_iter().map(serde_json::Value::String))
+                    .collect();
+                let separator: &[u8] = if i == 0 { b"\n" } else { b",\n" };
+                writer
+                    .write_all(separator)
+                    .and_then(|()| Ok(serde_json::to_writer(&mut *writer, &object)?))
+            }
+        };
+        written.map_err(|_| ExportError::WriteError)?;
+    }
+    if format == Format::Json {
+        writer
+            .write_all(b"\n]\n")
+            .map_err(|_| ExportError::WriteError)?;
+    }
+    keep_going(filenames.len());
+    Ok(())
+}
+
+/// Exports the documents of the cabinet at `dir` into `<dir>/exports`, reporting progress
+/// with `keep_going` as `write_rows` does. The rows go to a `.part` file renamed once
+/// complete, a cancelled or failed export leaves nothing behind. Returns the path of the
+/// export.
+pub fn write(
+    dir: &Path,
+    format: Format,
+    mut keep_going: impl FnMut(usize, usize) -> bool,
+) -> Result<PathBuf, ExportError> {
+    let filenames = utils::list_files(dir);
+    let defs = fields::load(dir);
+    let catalog = Catalog::load(dir);
+    let out_dir = dir.join("exports");
+    fs::create_dir_all(&out_dir).map_err(|_| ExportError::DirectoryError)?;
+    let time = Local::now().format("%Y-%m-%d_%H%M%S");
+    let path = out_dir.join(format!("Cabinet_{}.{}", time, format.extension()));
+    let part = path.with_extension(format!("{}.part", format.extension()));
+
+    let file = File::create(&part).map_err(|_| ExportError::WriteError)?;
+    let mut writer = BufWriter::new(file);
+    let total = filenames.len();
+    let written = write_rows(&mut writer, format, &filenames, &defs, &catalog, |done| {
+        keep_going(done, total)
+    })
+    .and_then(|()| writer.flush().map_err(|_| ExportError::WriteError));
+    drop(writer);
+    if let Err(error) = written {
+        let _ = fs::remove_file(&part);
+        return Err(error);
+    }
+    fs::rename(&part, &path).map_err(|_| ExportError::WriteError)?;
+    info!(event = "ExportCabinet", file = %path.display(), documents = total);
+    Ok(path)
+}
+
+/// Exports the cabinet at `dir` on a thread of its own. Dropping the subscription cancels
+/// the export.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Export {
+    pub dir: String,
+    pub format: Format,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<H, E> Recipe<H, E> for Export
+where
+    H: std::hash::Hasher,
+{
+    type Output = Progress;
+
+    fn hash(&self, state: &mut H) {
+        use std::hash::Hash;
+        struct Marker;
+        std::any::TypeId::of::<Marker>().hash(state);
+        self.dir.hash(state);
+        self.format.hash(state);
+    }
+
+    fn stream(self: Box<Self>, _input: BoxStream<'static, E>) -> BoxStream<'static, Progress> {
+        let (sender, receiver) = mpsc::unbounded();
+        let Export { dir, format } = *self;
+        thread::spawn(move || {
+            // Sending fails once the subscription was dropped.
+            let result = write(Path::new(&dir), format, |done, total| {
+                sender.unbounded_send(Progress::Wrote(done, total)).is_ok()
+            })
+            .map(|path| path.to_string_lossy().to_string());
+            let _ = sender.unbounded_send(Progress::Finished(result));
+        });
+        receiver.boxed()
+    }
+}
+
+#[test]
+fn test_write_rows() {
+    let defs = vec![FieldDef {
+        name: "Case number".to_string(),
+        ..Default::default()
+    }];
+    let filenames = vec![
+        "2021-03-04_Chase_Statement_1.pdf".to_string(),
+        "scan, \"new\".pdf".to_string(),
+    ];
+    let catalog = Catalog::default();
+    let mut csv = Vec::new();
+    write_rows(&mut csv, Format::Csv, &filenames, &defs, &catalog, |_| true).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "File,Date,Institution,Title,Page,Tags,Owner,Case number\n\
+         2021-03-04_Chase_Statement_1.pdf,2021-03-04,Chase,Statement,1,,,\n\
+         \"scan, \"\"new\"\".pdf\",,,,,,,\n"
+    );
+
+    let mut json = Vec::new();
+    write_rows(&mut json, Format::Json, &filenames, &defs, &catalog, |_| {
+        true
+    })
+    .unwrap();
+    let rows: Vec<serde_json::Value> = serde_json::from_slice(&json).unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["Institution"], "Chase");
+    assert_eq!(rows[1]["File"], "scan, \"new\".pdf");
+
+    let mut cancelled = Vec::new();
+    assert!(matches!(
+        write_rows(
+            &mut cancelled,
+            Format::Csv,
+            &filenames,
+            &defs,
+            &catalog,
+            |_| false
+        ),
+        Err(ExportError::CancelledError)
+    ));
+}
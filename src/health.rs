@@ -0,0 +1,158 @@
+use crate::catalog::{Catalog, CatalogError};
+use crate::{search, utils};
+use rand::seq::SliceRandom;
+use std::path::Path;
+use tracing::info;
+
+/// How many documents have their contents checked against the catalog on each open, a
+/// different sample each time.
+pub const SAMPLE: usize = 20;
+
+/// What the consistency check of a cabinet found.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Health {
+    /// Documents of the catalog that are gone from the folder, archived ones aside.
+    pub missing: Vec<String>,
+    /// Files of the folder the catalog knows nothing about yet.
+    pub uncatalogued: Vec<String>,
+    /// Sampled documents whose contents differ from when they were indexed.
+    pub changed: Vec<String>,
+    /// How many documents were sampled.
+    pub sampled: usize,
+}
+
+impl Health {
+    pub fn is_healthy(&self) -> bool {
+        self.missing.is_empty() && self.uncatalogued.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl std::fmt::Display for Health {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} missing, {} not catalogued, {} of {} checked changed",
+            self.missing.len(),
+            self.uncatalogued.len(),
+            self.changed.len(),
+            self.sampled
+        )
+    }
+}
+
+/// Compares the catalog of the cabinet at `dir` with its files, and the contents of up to
+/// `sample` documents picked at random with their recorded checksums.
+pub fn check(dir: &Path, sample: usize) -> Health {
+    let catalog = Catalog::load(dir);
+    let files = utils::list_files(dir);
+    let uncatalogued = files
+        .iter()
+        .filter(|filename| catalog.get(filename).is_none())
+        .cloned()
+        .collect();
+    let hashed: Vec<(&String, &String)> = files
+        .iter()
+        .filter_map(|filename| Some((filename, catalog.get(filename)?.sha256.as_ref()?)))
+        .collect();
+    let sampled: Vec<&(&String, &String)> = hashed
+        .choose_multiple(&mut rand::thread_rng(), sample)
+        .collect();
+    let mut changed: Vec<String> = sampled
+        .iter()
+        .filter(|(filename, sha256)| utils::sha256(&dir.join(filename)).as_ref() != Some(sha256))
+        .map(|(filename, _)| filename.to_string())
+        .collect();
+    changed.sort();
+    Health {
+        missing: catalog.missing(dir),
+        uncatalogued,
+        changed,
+        sampled: sampled.len(),
+    }
+}
+
+pub async fn run(dir: String) -> Health {
+    let health = check(Path::new(&dir), SAMPLE);
+    info!(
+        event = "HealthCheck",
+        dir = %dir,
+        missing = health.missing.len(),
+        uncatalogued = health.uncatalogued.len(),
+        changed = health.changed.len(),
+        sampled = health.sampled
+    );
+    health
+}
+
+/// Relinks the missing documents of the cabinet at `dir` that were moved within it and
+/// forgets the others. Returns how many were forgotten.
+pub fn forget_missing(dir: &Path) -> Result<usize, CatalogError> {
+    let mut catalog = Catalog::load(dir);
+    for (old, new) in catalog.relink_missing(dir) {
+        info!(event = "Relink", old = %old, new = %new);
+    }
+    let missing = catalog.missing(dir);
+    for filename in &missing {
+        catalog.remove(filename);
+        search::forget_text(&dir.join(filename));
+    }
+    catalog.save(dir)?;
+    info!(event = "ForgetMissing", documents = missing.len());
+    Ok(missing.len())
+}
+
+/// Forgets what was found in the changed `filenames` of the cabinet at `dir`, so the next
+/// index reads them again. Tags, labels and the like are kept. Returns how many there were.
+pub fn reset(dir: &Path, filenames: &[String]) -> Result<usize, CatalogError> {
+    let mut catalog = Catalog::load(dir);
+    for filename in filenames {
+        let entry = catalog.entry(filename);
+        entry.indexed = false;
+        entry.sha256 = None;
+        entry.dhash = None;
+        search::forget_text(&dir.join(filename));
+    }
+    catalog.save(dir)?;
+    info!(event = "ResetChanged", documents = filenames.len());
+    Ok(filenames.len())
+}
+
+pub async fn fix_missing(dir: String) -> Result<usize, CatalogError> {
+    forget_missing(Path::new(&dir))
+}
+
+/// Resets the changed `filenames`, see `reset`. Returns them, to be indexed again.
+pub async fn fix_changed(dir: String, filenames: Vec<String>) -> Result<Vec<String>, CatalogError> {
+    reset(Path::new(&dir), &filenames)?;
+    Ok(filenames)
+}
+
+#[test]
+fn test_health() {
+    let dir = tempdir::TempDir::new("health").unwrap();
+    let dir = dir.path();
+    for filename in ["kept.pdf", "edited.pdf", "new.pdf"] {
+        std::fs::write(dir.join(filename), filename).unwrap();
+    }
+    let mut catalog = Catalog::default();
+    for filename in ["kept.pdf", "edited.pdf", "gone.pdf"] {
+        catalog.entry(filename).sha256 = utils::sha256(&dir.join(filename));
+        catalog.entry(filename).indexed = true;
+    }
+    catalog.save(dir).unwrap();
+    std::fs::write(dir.join("edited.pdf"), b"edited since").unwrap();
+
+    let health = check(dir, SAMPLE);
+    assert_eq!(health.missing, ["gone.pdf"]);
+    assert_eq!(health.uncatalogued, ["new.pdf"]);
+    assert_eq!(health.changed, ["edited.pdf"]);
+    assert_eq!(health.sampled, 2);
+    assert_eq!(check(dir, 0).sampled, 0);
+
+    assert_eq!(forget_missing(dir).unwrap(), 1);
+    assert_eq!(reset(dir, &health.changed).unwrap(), 1);
+    let catalog = Catalog::load(dir);
+    assert!(catalog.get("gone.pdf").is_none());
+    assert!(!catalog.get("edited.pdf").unwrap().indexed);
+    assert!(check(dir, SAMPLE).changed.is_empty());
+}
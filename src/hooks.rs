@@ -0,0 +1,203 @@
+use crate::catalog::{Catalog, Entry};
+use crate::tools;
+use crate::utils::OptDoc;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tracing::{info, warn};
+
+/// When a hook runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookEvent {
+    /// Before a file is moved into the cabinet by the import rules, which leave it out if
+    /// the hook fails.
+    #[default]
+    PreImport,
+    /// After a document got its normalized name.
+    PostNormalize,
+    /// After a document was deleted, the file is gone but its metadata is passed along.
+    PostDelete,
+}
+
+impl HookEvent {
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::PreImport => "pre-import",
+            HookEvent::PostNormalize => "post-normalize",
+            HookEvent::PostDelete => "post-delete",
+        }
+    }
+
+    /// The event after this one, to cycle through them with a single button.
+    pub fn next(self) -> HookEvent {
+        match self {
+            HookEvent::PreImport => HookEvent::PostNormalize,
+            HookEvent::PostNormalize => HookEvent::PostDelete,
+            HookEvent::PostDelete => HookEvent::PreImport,
+        }
+    }
+}
+
+/// A command run on documents around a file operation, e.g. to OCR or upload them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Hook {
+    pub event: HookEvent,
+    /// Program and arguments, with `{}` standing for the file as for external tools. The
+    /// payload comes on stdin.
+    pub command: String,
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum HookError {
+    ParseError,
+    LaunchError,
+    /// The command exited with a failure.
+    ExitError,
+}
+
+/// What a hook gets as JSON on stdin.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Payload {
+    pub event: HookEvent,
+    /// The file as it is now, or was before it was deleted.
+    pub path: String,
+    /// Its name in the cabinet, which is still to come for pre-import hooks.
+    pub filename: String,
+    pub date: Option<String>,
+    pub institution: Option<String>,
+    pub title: Option<String>,
+    pub page: Option<u32>,
+    /// Its catalog entry, if it has one.
+    pub entry: Option<Entry>,
+}
+
+impl Payload {
+    /// The payload for the file at `path`, to be filed in the cabinet at `dir` as
+    /// `filename`.
+    pub fn new(event: HookEvent, path: &Path, dir: &Path, filename: &str) -> Payload {
+        let fields = OptDoc::new(filename);
+        let page = fields.page_number();
+        Payload {
+            event,
+            path: path.display().to_string(),
+            filename: filename.to_string(),
+            date: fields.date,
+            institution: fields.institution,
+            title: fields.name,
+            page,
+            entry: Catalog::load(dir).get(filename).cloned(),
+        }
+    }
+
+    /// The payload for the document at `path` in its cabinet.
+    pub fn of(event: HookEvent, path: &Path) -> Payload {
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let filename = path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Payload::new(event, path, dir, &filename)
+    }
+}
+
+/// Runs `command` on the file of `payload` and waits for it to exit.
+fn run_one(command: &str, payload: &Payload) -> Result<(), HookError> {
+    let args = tools::command_args(command, &payload.path).ok_or(HookError::ParseError)?;
+    let json = serde_json::to_vec(payload).map_err(|_| HookError::ParseError)?;
+    let mut child = Command::new(&args[0])
+        .args(&args[1..])
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|_| HookError::LaunchError)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Hooks that only look at their arguments may exit without reading it.
+        let _ = stdin.write_all(&json);
+    }
+    let status = child.wait().map_err(|_| HookError::LaunchError)?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(HookError::ExitError)
+    }
+}
+
+/// Runs the hooks of the payload's event one after the other, stopping at the first that
+/// fails.
+pub fn run(hooks: &[Hook], payload: &Payload) -> Result<(), HookError> {
+    for hook in hooks.iter().filter(|hook| hook.event == payload.event) {
+        if hook.command.trim().is_empty() {
+            continue;
+        }
+        match run_one(&hook.command, payload) {
+            Ok(()) => info!(event = "Hook", hook = payload.event.name(), file = %payload.path),
+            Err(error) => {
+                let hook_name = payload.event.name();
+                warn!(event = "hook_failed", hook = hook_name, command = %hook.command, ?error);
+                return Err(error);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs the hooks of the payload's event in the background, for the ones that follow an
+/// operation and can't undo it anyway.
+pub fn spawn(hooks: &[Hook], payload: Payload) {
+    if !hooks
+        .iter()
+        .any(|hook| hook.event == payload.event && !hook.command.trim().is_empty())
+    {
+        return;
+    }
+    let hooks = hooks.to_vec();
+    std::thread::spawn(move || run(&hooks, &payload));
+}
+
+#[test]
+fn test_hooks() {
+    let dir = tempdir::TempDir::new("hooks").unwrap();
+    let dir = dir.path();
+    let path = dir.join("2021-03-01_Bank_Statement_2.pdf");
+    std::fs::write(&path, b"%PDF").unwrap();
+    let mut catalog = Catalog::default();
+    catalog.entry("2021-03-01_Bank_Statement_2.pdf").favorite = true;
+    catalog.save(dir).unwrap();
+
+    let payload = Payload::of(HookEvent::PostNormalize, &path);
+    assert_eq!(payload.date.as_deref(), Some("2021-03-01"));
+    assert_eq!(payload.institution.as_deref(), Some("Bank"));
+    assert_eq!(payload.title.as_deref(), Some("Statement"));
+    assert_eq!(payload.page, Some(2));
+    assert!(payload.entry.as_ref().unwrap().favorite);
+    let json = serde_json::to_value(&payload).unwrap();
+    assert_eq!(json["event"], "post-normalize");
+
+    let hook = |event: HookEvent, command: &str| Hook {
+        event,
+        command: command.to_string(),
+    };
+    let copy = dir.join("payload.json");
+    let hooks = vec![
+        hook(
+            HookEvent::PostNormalize,
+            &format!("sh -c 'test -f \"$0\" && cat > {}' {{}}", copy.display()),
+        ),
+        // Not for this event.
+        hook(HookEvent::PreImport, "false"),
+    ];
+    assert!(run(&hooks, &payload).is_ok());
+    let written: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&copy).unwrap()).unwrap();
+    assert_eq!(written, json);
+
+    let failing = vec![hook(HookEvent::PostNormalize, "false")];
+    assert!(matches!(run(&failing, &payload), Err(HookError::ExitError)));
+    let unparsable = vec![hook(HookEvent::PostNormalize, "sh 'unterminated")];
+    assert!(matches!(
+        run(&unparsable, &payload),
+        Err(HookError::ParseError)
+    ));
+}
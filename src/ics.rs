@@ -0,0 +1,240 @@
+use crate::catalog::Catalog;
+use crate::fields::{self, FieldKind};
+use crate::utils::{self, OptDoc};
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Name of the calendar, in the cabinet so calendar apps can subscribe to it.
+pub const FILENAME: &str = "filecabinet.ics";
+
+/// How many days before a date field, e.g. an expiry, the calendar app reminds of it.
+const REMINDER_DAYS: u32 = 7;
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum IcsError {
+    WriteError,
+}
+
+pub fn path(dir: &Path) -> PathBuf {
+    dir.join(FILENAME)
+}
+
+/// A day of the calendar.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Event {
+    date: NaiveDate,
+    summary: String,
+    description: String,
+    /// Whether the calendar app reminds of it beforehand.
+    reminder: bool,
+}
+
+/// Escapes text values as RFC 5545 asks.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Splits `line` in lines of 75 bytes at most, the next ones starting with a space.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+/// Turns the words of a normalized filename apart, e.g. `BankStatement`.
+fn words(text: &str) -> String {
+    let mut words = String::new();
+    for c in text.chars() {
+        if c.is_uppercase() && !words.is_empty() {
+            words.push(' ');
+        }
+        words.push(c);
+    }
+    words
+}
+
+/// The events of the cabinet at `dir`: the date of every document, pages of one counted
+/// once, and the dates of its date fields, which come with a reminder.
+fn events(dir: &Path) -> Vec<Event> {
+    let catalog = Catalog::load(dir);
+    let defs: Vec<String> = fields::load(dir)
+        .into_iter()
+        .filter(|def| def.kind == FieldKind::Date)
+        .map(|def| def.name)
+        .collect();
+    let mut events = BTreeSet::new();
+    for filename in utils::list_files(dir) {
+        let doc = OptDoc::new(&filename);
+        let normalized = utils::is_normalized(&filename);
+        let name = match (&doc.institution, &doc.name) {
+            (Some(institution), Some(title)) if normalized => {
+                format!("{} {}", words(institution), words(title))
+            }
+            _ => filename.clone(),
+        };
+        // Later pages of a document would only add duplicates.
+        let first_page = doc.page_number().is_none_or(|page| page <= 1);
+        if let (Some(date), true) = (doc.day(), normalized && first_page) {
+            events.insert(Event {
+                date,
+                summary: name.clone(),
+                description: filename.clone(),
+                reminder: false,
+            });
+        }
+        let entry = match catalog.get(&filename) {
+            Some(entry) => entry,
+            None => continue,
+        };
+        for def in &defs {
+            let date = entry
+                .fields
+                .get(def)
+                .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+            if let Some(date) = date {
+                events.insert(Event {
+                    date,
+                    summary: format!("{}: {}", name, def),
+                    description: filename.clone(),
+                    reminder: true,
+                });
+            }
+        }
+    }
+    events.into_iter().collect()
+}
+
+/// The calendar of the cabinet at `dir` as an iCalendar file, stamped with `now`.
+/// Returns it with how many events it has.
+pub fn render(dir: &Path, now: NaiveDateTime) -> (String, usize) {
+    let events = events(dir);
+    let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let mut ics = String::new();
+    for line in [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//filecabinet//documents//EN",
+        "CALSCALE:GREGORIAN",
+    ] {
+        ics.push_str(&fold(line));
+    }
+    for event in &events {
+        let day = event.date.format("%Y%m%d");
+        // Stable across exports so calendar apps update events rather than duplicate them.
+        let uid = utils::sha256_bytes(format!("{}{}", day, event.summary).as_bytes());
+        let mut lines = vec![
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}@filecabinet", &uid[..32]),
+            format!("DTSTAMP:{}", stamp),
+            format!("DTSTART;VALUE=DATE:{}", day),
+            format!(
+                "DTEND;VALUE=DATE:{}",
+                (event.date + Duration::days(1)).format("%Y%m%d")
+            ),
+            format!("SUMMARY:{}", escape(&event.summary)),
+            format!("DESCRIPTION:{}", escape(&event.description)),
+        ];
+        if event.reminder {
+            lines.extend([
+                "BEGIN:VALARM".to_string(),
+                "ACTION:DISPLAY".to_string(),
+                format!("DESCRIPTION:{}", escape(&event.summary)),
+                format!("TRIGGER:-P{}D", REMINDER_DAYS),
+                "END:VALARM".to_string(),
+            ]);
+        }
+        lines.push("END:VEVENT".to_string());
+        for line in lines {
+            ics.push_str(&fold(&line));
+        }
+    }
+    ics.push_str(&fold("END:VCALENDAR"));
+    (ics, events.len())
+}
+
+/// Writes the calendar of the cabinet at `dir` to its `filecabinet.ics`. Returns how many
+/// events it has.
+pub fn write(dir: &Path) -> Result<usize, IcsError> {
+    let (ics, events) = render(dir, Utc::now().naive_utc());
+    AtomicFile::new(path(dir), OverwriteBehavior::AllowOverwrite)
+        .write(|f| f.write_all(ics.as_bytes()))
+        .map_err(|_| IcsError::WriteError)?;
+    Ok(events)
+}
+
+pub async fn export(dir: String) -> Result<usize, IcsError> {
+    let events = write(Path::new(&dir))?;
+    info!(event = "WriteCalendar", dir = %dir, events);
+    Ok(events)
+}
+
+#[test]
+fn test_ics() {
+    let dir = tempdir::TempDir::new("ics").unwrap();
+    let dir = dir.path();
+    for filename in [
+        "2021-03-04_Bank_Statement_1.pdf",
+        "2021-03-04_Bank_Statement_2.pdf",
+        "2021-05-01_Acme_Policy_1.pdf",
+        "scan.pdf",
+    ] {
+        std::fs::write(dir.join(filename), b"document").unwrap();
+    }
+    fields::save(
+        dir,
+        &[fields::FieldDef {
+            name: "Expires".to_string(),
+            kind: FieldKind::Date,
+            options: String::new(),
+        }],
+    )
+    .unwrap();
+    let mut catalog = Catalog::default();
+    catalog
+        .entry("2021-05-01_Acme_Policy_1.pdf")
+        .fields
+        .insert("Expires".to_string(), "2022-04-30".to_string());
+    catalog.save(dir).unwrap();
+
+    let now = NaiveDate::from_ymd_opt(2021, 6, 1)
+        .unwrap()
+        .and_hms_opt(12, 0, 0)
+        .unwrap();
+    let (ics, events) = render(dir, now);
+    assert_eq!(events, 3);
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    assert!(ics.contains("DTSTAMP:20210601T120000Z\r\n"));
+    assert!(ics.contains(
+        "DTSTART;VALUE=DATE:20210304\r\nDTEND;VALUE=DATE:20210305\r\nSUMMARY:Bank Statement\r\n"
+    ));
+    assert!(ics.contains(
+        "DTSTART;VALUE=DATE:20220430\r\nDTEND;VALUE=DATE:20220501\r\n\
+         SUMMARY:Acme Policy: Expires\r\n"
+    ));
+    assert_eq!(ics.matches("BEGIN:VALARM").count(), 1);
+    assert!(ics.lines().all(|line| line.len() <= 75));
+
+    assert_eq!(escape("a;b,c\nd"), "a\\;b\\,c\\nd");
+    assert_eq!(
+        fold(&"x".repeat(80)),
+        format!("{}\r\n {}\r\n", "x".repeat(75), "x".repeat(5))
+    );
+}
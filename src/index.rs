@@ -0,0 +1,247 @@
+use crate::catalog::{Catalog, CatalogError, Entry};
+use crate::events::{self, Event};
+use crate::{amount, barcode, metadata, ocr, plugins, rules, search, similarity, tags, utils};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+use tracing::{info, warn};
+
+/// Whether the indexing queue of the open cabinet runs. It is saved with the app state,
+/// so a queue the app was closed on picks up where it was after a restart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Status {
+    #[default]
+    Idle,
+    Running,
+    Paused,
+}
+
+/// Documents waiting to be indexed. They are indexed one at a time, so the queue can be
+/// paused and reordered in between.
+#[derive(Debug, Default)]
+pub struct Queue {
+    pub status: Status,
+    pending: VecDeque<String>,
+    /// Documents indexed since the queue started.
+    pub done: usize,
+    /// Set while a document is being indexed.
+    busy: bool,
+}
+
+impl Queue {
+    pub fn start(&mut self, pending: Vec<String>) {
+        self.pending = pending.into();
+        self.done = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Moves the queued ones of `filenames` to the front, in their order.
+    pub fn prioritize(&mut self, filenames: &[String]) {
+        let queued: Vec<&String> = filenames
+            .iter()
+            .filter(|filename| self.pending.contains(filename))
+            .collect();
+        self.pending.retain(|filename| !queued.contains(&filename));
+        for filename in queued.into_iter().rev() {
+            self.pending.push_front(filename.clone());
+        }
+    }
+
+    /// Queues documents that were just added ahead of the others, unless the queue is idle.
+    pub fn add(&mut self, filenames: &[String]) {
+        if self.status == Status::Idle {
+            return;
+        }
+        self.pending
+            .retain(|filename| !filenames.contains(filename));
+        for filename in filenames.iter().rev() {
+            self.pending.push_front(filename.clone());
+        }
+    }
+
+    /// The next document to index, `None` while paused or a document is being indexed.
+    pub fn next(&mut self) -> Option<String> {
+        if self.status != Status::Running || self.busy {
+            return None;
+        }
+        let filename = self.pending.pop_front()?;
+        self.busy = true;
+        Some(filename)
+    }
+
+    /// Records that the document from `next` was indexed.
+    pub fn finished(&mut self) {
+        self.busy = false;
+        self.done += 1;
+    }
+
+    /// Makes the queue idle if nothing is left, returns whether it did.
+    pub fn is_done(&mut self) -> bool {
+        let done = self.status != Status::Idle && self.pending.is_empty() && !self.busy;
+        if done {
+            self.status = Status::Idle;
+        }
+        done
+    }
+}
+
+fn modified(path: &Path) -> SystemTime {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+/// Documents of `dir` that weren't indexed or searchable yet, most recently added first since those
+/// are the ones looked for. Entries of files that were moved within the cabinet are
+/// relinked first.
+pub async fn queue(dir: String) -> Result<Vec<String>, CatalogError> {
+    let dir = Path::new(&dir);
+    let mut catalog = Catalog::load(dir);
+    // Files moved or renamed outside the app keep their metadata.
+    let relinked = catalog.relink_missing(dir);
+    for (old, new) in &relinked {
+        info!(event = "Relink", old = %old, new = %new);
+    }
+    if !relinked.is_empty() {
+        catalog.save(dir)?;
+    }
+    let mut pending: Vec<(SystemTime, String)> = utils::list_files(dir)
+        .into_iter()
+        .filter(|filename| {
+            catalog
+                .get(filename)
+                .is_none_or(|e| !e.indexed || e.sha256.is_none())
+                || !search::has_text(&dir.join(filename))
+        })
+        .map(|filename| (modified(&dir.join(&filename)), filename))
+        .collect();
+    pending.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    Ok(pending.into_iter().map(|(_, filename)| filename).collect())
+}
+
+/// Analyzes `filename` of the cabinet at `dir` and records what was found (amount,
+/// language, barcodes, PDF metadata, photo dates) in the catalog, and its text for the
+/// search. Scans are read with the installed OCR `languages`, then the post-processing
+/// plugins run on them. Documents new to the cabinet get the tags of the matching
+/// auto-tag rules. Returns the filename.
+pub async fn index(
+    dir: String,
+    filename: String,
+    languages: Vec<String>,
+) -> Result<String, CatalogError> {
+    let dir = Path::new(&dir);
+    let path = dir.join(&filename);
+    // Renamed or deleted while it waited.
+    if !path.is_file() {
+        return Ok(filename);
+    }
+    let mut entry = Catalog::load(dir)
+        .get(&filename)
+        .cloned()
+        .unwrap_or_default();
+    let entering = !entry.indexed;
+    analyze(&path, &mut entry, &languages);
+    // Reading a scan takes a while, the document may have been starred or viewed since.
+    let mut catalog = Catalog::load(dir);
+    let current = catalog.entry(&filename);
+    current.indexed = entry.indexed;
+    current.amount = entry.amount;
+    current.language = entry.language;
+    current.codes = entry.codes;
+    current.sha256 = entry.sha256;
+    current.dhash = entry.dhash;
+    current.metadata = entry.metadata;
+    // With its text read, the auto-tag rules can look at it.
+    let mut tagged = None;
+    if entering {
+        let tags = rules::rule_tags(&rules::load_tag_rules(dir), dir, &filename, &catalog);
+        let entry = catalog.entry(&filename);
+        if tags::merge(&mut entry.tags, &tags) {
+            info!(event = "AutoTag", file = %filename, tags = ?tags);
+            tagged = Some(Event::Tagged {
+                filename: filename.clone(),
+                tags: entry.tags.clone(),
+            });
+        }
+    }
+    catalog.save(dir)?;
+    events::emit(dir, tagged.as_slice());
+    Ok(filename)
+}
+
+fn analyze(path: &Path, entry: &mut Entry, languages: &[String]) {
+    if entry.sha256.is_none() {
+        entry.sha256 = utils::sha256(path);
+    }
+    if entry.dhash.is_none() {
+        entry.dhash = similarity::first_page(path).map(|page| similarity::dhash(&page));
+    }
+    if entry.metadata.is_none() {
+        entry.metadata = metadata::read(path);
+    }
+    // Documents indexed before their text was kept are read once more for the search.
+    let missing_text = !search::has_text(path);
+    if entry.indexed && !missing_text {
+        return;
+    }
+    if missing_text || entry.amount.is_none() || entry.language.is_none() {
+        let recognized = ocr::document_text(path, languages);
+        let pages = recognized
+            .as_ref()
+            .map(|r| r.pages.as_slice())
+            .unwrap_or_default();
+        if let Err(error) = search::save_text(path, pages) {
+            warn!(event = "text_save_failed", file = %path.display(), ?error);
+        }
+        if let Some(recognized) = recognized.filter(|_| !entry.indexed) {
+            if entry.amount.is_none() {
+                entry.amount = amount::document_amount(&recognized.text);
+            }
+            entry.language = recognized.language;
+        }
+    }
+    if entry.indexed {
+        return;
+    }
+    entry.codes = barcode::detect(path);
+    plugins::registry().post_process(path, entry);
+    entry.indexed = true;
+    info!(
+        event = "Index",
+        file = %path.display(),
+        amount = %entry.amount.as_ref().map(|a| a.to_string()).unwrap_or_default(),
+        language = entry.language.as_deref().unwrap_or_default(),
+        codes = entry.codes.len()
+    );
+}
+
+#[test]
+fn test_queue() {
+    let mut queue = Queue {
+        status: Status::Running,
+        ..Default::default()
+    };
+    queue.start(vec!["a.pdf".to_string(), "b.pdf".to_string()]);
+    queue.prioritize(&["b.pdf".to_string(), "indexed.pdf".to_string()]);
+    assert_eq!(queue.next().as_deref(), Some("b.pdf"));
+    assert_eq!(queue.next(), None);
+    queue.finished();
+    queue.add(&["new.pdf".to_string()]);
+    queue.status = Status::Paused;
+    assert_eq!(queue.next(), None);
+    queue.status = Status::Running;
+    assert_eq!(queue.next().as_deref(), Some("new.pdf"));
+    queue.finished();
+    assert_eq!(queue.next().as_deref(), Some("a.pdf"));
+    assert!(!queue.is_done());
+    queue.finished();
+    assert_eq!(queue.next(), None);
+    assert!(queue.is_done());
+    assert_eq!(queue.status, Status::Idle);
+    assert_eq!(queue.done, 3);
+}
@@ -0,0 +1,111 @@
+use crate::catalog::Catalog;
+use crate::utils::list_files;
+use crate::{DocState, Document};
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::debug;
+
+/// Lists the documents of the cabinet at `path` again. The ones of `docs` whose file has
+/// the same name, size and modification time are kept as they are, with their selection
+/// and open forms, only new and changed files are read.
+pub fn rescan(path: &str, docs: Vec<Document>) -> Vec<Document> {
+    let dir_path = Path::new(&path).to_path_buf();
+    let catalog = Catalog::load(&dir_path);
+    let mut known: HashMap<String, Document> = docs
+        .into_iter()
+        .map(|doc| (doc.path.clone(), doc))
+        .collect();
+    let mut read = 0;
+    // One buffer for the paths of all the files, they are many in a big cabinet.
+    let mut file_path = dir_path.clone();
+    let mut docs: Vec<Document> = list_files(&dir_path)
+        .iter()
+        .map(|path| {
+            file_path.push(path);
+            let full_path = file_path.to_string_lossy().into_owned();
+            file_path.pop();
+            let mut doc = match known.remove(&full_path) {
+                Some(doc) if doc.is_current() => doc,
+                _ => {
+                    read += 1;
+                    Document::new(full_path)
+                }
+            };
+            if let Some(entry) = catalog.get(path) {
+                doc.amount = entry.amount.clone();
+                doc.language = entry.language.clone();
+                doc.codes = entry.codes.clone();
+                doc.favorite = entry.favorite;
+                doc.viewed = entry.viewed.clone();
+                doc.metadata = entry.metadata.clone();
+                doc.tags = entry.tags.clone();
+                doc.label = entry.label;
+                doc.fields = entry.fields.clone();
+                doc.owner = entry.owner.clone();
+            }
+            doc
+        })
+        .collect();
+    // Files the catalog knows about but that were moved or deleted outside the app.
+    for filename in catalog.missing(&dir_path) {
+        let path = dir_path.join(filename).to_string_lossy().to_string();
+        let doc = match known.remove(&path) {
+            Some(doc) if matches!(doc.state, DocState::Missing { .. }) => doc,
+            _ => {
+                let mut doc = Document::new(path);
+                doc.state = DocState::Missing {
+                    locate_button: Default::default(),
+                    remove_button: Default::default(),
+                    not_found: false,
+                };
+                doc
+            }
+        };
+        docs.push(doc);
+    }
+    // Stubs of the documents in cold storage.
+    for filename in catalog.archived() {
+        let path = dir_path.join(&filename).to_string_lossy().to_string();
+        let mut doc = match known.remove(&path) {
+            Some(doc) if doc.archived => doc,
+            _ => {
+                let mut doc = Document::new(path);
+                doc.archived = true;
+                doc.state = DocState::Archived {
+                    restore_button: Default::default(),
+                };
+                doc
+            }
+        };
+        if let Some(entry) = catalog.get(&filename) {
+            doc.favorite = entry.favorite;
+            doc.label = entry.label;
+        }
+        docs.push(doc);
+    }
+    debug!(event = "Rescan", dir = %path, documents = docs.len(), read);
+    docs
+}
+
+#[test]
+fn test_rescan() {
+    let dir = tempdir::TempDir::new("rescan").unwrap();
+    let dir_path = dir.path().to_string_lossy().to_string();
+    std::fs::write(dir.path().join("kept.pdf"), b"kept").unwrap();
+    std::fs::write(dir.path().join("edited.pdf"), b"edited").unwrap();
+    let mut docs = rescan(&dir_path, Vec::new());
+    assert_eq!(docs.len(), 2);
+    for doc in &mut docs {
+        doc.selected = true;
+    }
+
+    std::fs::write(dir.path().join("edited.pdf"), b"edited since").unwrap();
+    std::fs::write(dir.path().join("new.pdf"), b"new").unwrap();
+    let docs = rescan(&dir_path, docs);
+    let selected = |filename: &str| docs.iter().find(|d| d.filename == filename).unwrap();
+    assert_eq!(docs.len(), 3);
+    assert!(selected("kept.pdf").selected);
+    assert!(!selected("edited.pdf").selected);
+    assert_eq!(selected("edited.pdf").size, 12);
+    assert!(!selected("new.pdf").selected);
+}
@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Prefix of the log files, a new one is started every day.
+const LOG_FILE: &str = "filecabinet.log";
+/// Log files older than this many days are deleted on launch.
+const KEEP_DAYS: i64 = 14;
+/// Events kept in memory for crash reports.
+const RECENT_EVENTS: usize = 100;
+
+lazy_static! {
+    static ref RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+}
+
+/// Keeps the events written to it in `RECENT`, one line at a time.
+#[derive(Default)]
+struct RecentWriter {
+    line: String,
+}
+
+impl RecentWriter {
+    fn push(line: String) {
+        // Skipped rather than waited for, a panic may happen while the lock is held.
+        if let Ok(mut recent) = RECENT.try_lock() {
+            recent.push_back(line);
+            if recent.len() > RECENT_EVENTS {
+                recent.pop_front();
+            }
+        }
+    }
+}
+
+impl io::Write for RecentWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.push_str(&String::from_utf8_lossy(buf));
+        while let Some(end) = self.line.find('\n') {
+            let rest = self.line.split_off(end + 1);
+            let line = std::mem::replace(&mut self.line, rest);
+            RecentWriter::push(line.trim_end().to_string());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for RecentWriter {
+    fn drop(&mut self) {
+        if !self.line.is_empty() {
+            RecentWriter::push(std::mem::take(&mut self.line));
+        }
+    }
+}
+
+/// Where the log files are kept.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn log_dir() -> Option<PathBuf> {
+    directories_next::ProjectDirs::from("rs", "d6e", "filecabinet")
+        .map(|project_dirs| project_dirs.data_local_dir().join("logs"))
+}
+
+/// Browsers have no folder to keep log files in.
+#[cfg(target_arch = "wasm32")]
+pub fn log_dir() -> Option<PathBuf> {
+    None
+}
+
+/// Logs to stdout, to memory for crash reports and to a daily log file, at debug level
+/// if `verbose`. The returned guard flushes the file when dropped, so it has to live as
+/// long as the app.
+pub fn init(verbose: bool) -> Option<WorkerGuard> {
+    let level = if verbose {
+        LevelFilter::DEBUG
+    } else {
+        LevelFilter::INFO
+    };
+    let registry = tracing_subscriber::registry()
+        .with(level)
+        .with(fmt::layer().with_writer(std::io::stdout))
+        .with(fmt::layer().with_writer(RecentWriter::default));
+    match log_dir().filter(|dir| fs::create_dir_all(dir).is_ok()) {
+        Some(dir) => {
+            prune(&dir);
+            let (writer, guard) =
+                tracing_appender::non_blocking(tracing_appender::rolling::daily(&dir, LOG_FILE));
+            registry.with(fmt::layer().with_writer(writer)).init();
+            Some(guard)
+        }
+        None => {
+            registry.init();
+            None
+        }
+    }
+}
+
+/// Deletes the log files older than `KEEP_DAYS`, which are dated in their extension.
+fn prune(dir: &std::path::Path) {
+    let oldest = (chrono::Local::today() - chrono::Duration::days(KEEP_DAYS)).naive_local();
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let filename = entry.file_name().to_string_lossy().to_string();
+        let date = filename
+            .strip_prefix(LOG_FILE)
+            .and_then(|date| chrono::NaiveDate::parse_from_str(date, ".%Y-%m-%d").ok());
+        if date.is_some_and(|date| date < oldest) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// The last `count` lines of the most recent log file, oldest first.
+pub fn recent_lines(count: usize) -> Vec<String> {
+    let latest = log_dir()
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(LOG_FILE))
+        .max_by_key(|entry| entry.file_name());
+    let content = latest
+        .and_then(|entry| fs::read_to_string(entry.path()).ok())
+        .unwrap_or_default();
+    let lines: Vec<_> = content.lines().map(str::to_string).collect();
+    lines[lines.len().saturating_sub(count)..].to_vec()
+}
+
+/// The latest events logged by this run, oldest first.
+pub fn recent_events() -> Vec<String> {
+    RECENT
+        .try_lock()
+        .map(|recent| recent.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_recent_writer() {
+    use std::io::Write;
+    let mut writer = RecentWriter::default();
+    for i in 0..RECENT_EVENTS + 5 {
+        writeln!(writer, "event={}", i).unwrap();
+    }
+    let recent = recent_events();
+    assert_eq!(recent.len(), RECENT_EVENTS);
+    assert_eq!(recent[0], "event=5");
+    assert_eq!(
+        recent.last().unwrap(),
+        &format!("event={}", RECENT_EVENTS + 4)
+    );
+}
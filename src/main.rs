@@ -1,184 +1,7616 @@
 #[macro_use]
 extern crate lazy_static;
-use crate::utils::OptDoc;
-use chrono::{DateTime, Utc};
+use crate::amount::Amount;
+use crate::archive::ArchiveError;
+use crate::backup::BackupError;
+use crate::barcode::Barcode;
+use crate::bundle::BundleError;
+use crate::calendar::{Expected, Missing, MonthCounts};
+use crate::catalog::{Catalog, Label, Owner, SavedSearch};
+use crate::checklists::Checklist;
+use crate::checksums::ChecksumError;
+use crate::dropfolder::DropFolderError;
+use crate::fields::{FieldDef, FieldKind};
+use crate::health::Health;
+use crate::hooks::{Hook, HookEvent, Payload};
+use crate::ics::IcsError;
+use crate::institutions::{Contact, IconError};
+use crate::locks::LockError;
+use crate::metadata::Metadata;
+use crate::orientation::RotateError;
+use crate::packet::{PacketCriteria, PacketItem, PacketMessage};
+use crate::pdf::PdfError;
+use crate::preferences::{
+    BlankSensitivity, Confirmation, Preferences, PreferencesMessage, PreviewLayout,
+};
+use crate::preview::PreviewCache;
+use crate::recipients::Recipient;
+use crate::redact::{RedactError, Region};
+use crate::renames::{Plan, RenameError};
+use crate::report::{ImportReport, ReportError};
+use crate::rules::{ImportProfile, RuleMessage, TagRule};
+use crate::search::{Hit, QueryError};
+use crate::site::SiteError;
+use crate::stats::Stats;
+use crate::store::Docs;
+use crate::sync::{Change, Side, SyncError};
+use crate::tags::TagEdit;
+use crate::tools::ExternalTool;
+use crate::utils::{OptDoc, PartError};
+use crate::vault::VaultError;
+use chrono::{Datelike, NaiveDate, Utc};
+use clap::{Arg, ArgMatches, SubCommand};
+use filecabinet_core::{
+    amount, barcode, calendar, catalog, checklists, events, fields, institutions, locks, mapped,
+    metadata, office, orientation, pdf, renames, report, search, similarity, storage, utils,
+    versions,
+};
 use iced::futures::{AsyncReadExt, AsyncWriteExt};
 use iced::widget::pane_grid::Pane;
 use iced::{
-    button, pane_grid, scrollable, text_input, Align, Application, Button, Checkbox, Column,
-    Command, Container, Element, Font, HorizontalAlignment, Image, Length, PaneGrid, Row,
-    Scrollable, Settings, Text, TextInput,
+    button, image, pane_grid, scrollable, text_input, Align, Application, Button, Checkbox, Column,
+    Command, Container, Element, Font, HorizontalAlignment, Image, Length, PaneGrid, Radio,
+    Rectangle, Row, Scrollable, Settings, Space, Subscription, Text, TextInput,
 };
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::fs;
-use std::path::Path;
-mod utils;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{self, AtomicU64};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, info, warn};
+mod archive;
+mod backup;
+mod bundle;
+mod checksums;
+mod compact;
+mod control;
+#[cfg(not(target_arch = "wasm32"))]
+mod crash;
+mod dropfolder;
+mod export;
+mod health;
+mod hooks;
+mod ics;
+mod index;
+mod listing;
+mod logging;
+mod ocr;
+mod orphans;
+mod packet;
+mod passwords;
+mod plugins;
+mod power;
+mod preferences;
+mod preview;
+mod recipients;
+mod redact;
+mod rules;
+mod scratch;
+#[cfg(not(target_arch = "wasm32"))]
+mod share;
+mod site;
+mod stats;
+mod store;
+mod sync;
+mod tags;
+mod tools;
+mod trash;
+#[cfg(target_os = "linux")]
+mod tray;
+mod vault;
 
-const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Lines shown by the log pane.
+const LOG_LINES: usize = 500;
+/// Windows narrower than this show one pane at a time.
+const COMPACT_WIDTH: u32 = 720;
+/// Unused documents listed by the stats pane, the others are only counted.
+const IDLE_LISTED: usize = 50;
 
 pub fn main() -> iced::Result {
-    println!("VERSION: {}", VERSION);
-    FileCabinet::run(Settings::default())
+    let matches = clap::App::new("filecabinet")
+        .version(VERSION)
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .help("Logs debug messages too"),
+        )
+        .arg(
+            Arg::with_name("document")
+                .multiple(true)
+                .help("Documents to copy into the cabinet and name, e.g. from \"Open with\", handed to the app if it runs already"),
+        )
+        .subcommand(
+            SubCommand::with_name("sums")
+                .about("Writes the SHA256SUMS of a cabinet")
+                .arg(Arg::with_name("cabinet").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Checks a cabinet against a SHA256SUMS manifest")
+                .arg(Arg::with_name("cabinet").required(true))
+                .arg(
+                    Arg::with_name("manifest")
+                        .help("Defaults to the SHA256SUMS in the cabinet, sha256sum's work too"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("clean")
+                .about("Removes what is kept about documents that are gone from a cabinet")
+                .arg(Arg::with_name("cabinet").required(true))
+                .arg(
+                    Arg::with_name("dry-run")
+                        .long("dry-run")
+                        .help("Only lists what would be removed"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("compact")
+                .about("Cleans up and compacts the metadata of a cabinet, see clean")
+                .arg(Arg::with_name("cabinet").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("search")
+                .about("Lists the documents of a cabinet that match a query")
+                .arg(Arg::with_name("cabinet").required(true))
+                .arg(
+                    Arg::with_name("query")
+                        .required(true)
+                        .multiple(true)
+                        .help("e.g. 'institution:Chase AND date:2021 AND (statement OR invoice)'"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("control")
+                .about("Asks the running app to add, open or show a document, search, or raise its window")
+                .arg(
+                    Arg::with_name("command")
+                        .required(true)
+                        .possible_values(&["add", "open", "search", "focus", "raise"]),
+                )
+                .arg(
+                    Arg::with_name("argument")
+                        .multiple(true)
+                        .help("The path to add or open, the query or the filename to show"),
+                ),
+        )
+        .get_matches();
+    let _guard = logging::init(matches.is_present("verbose"));
+    if let Some(code) = run_subcommand(&matches) {
+        std::process::exit(code);
+    }
+    // The running app is elsewhere, relative paths wouldn't mean the same to it.
+    let documents: Vec<String> = matches
+        .values_of("document")
+        .unwrap_or_default()
+        .map(|path| {
+            fs::canonicalize(path)
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_else(|_| path.to_string())
+        })
+        .collect();
+    #[cfg(unix)]
+    if control::forward(&documents) {
+        return Ok(());
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    crash::install(SavedState::flush);
+    info!(version = VERSION, "Starting");
+    // Browsers have no temp folder to clean up.
+    #[cfg(not(target_arch = "wasm32"))]
+    scratch::clean_up_stale();
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(threads) = SavedState::preferences().and_then(|p| p.worker_threads()) {
+        power::limit_threads(threads);
+    }
+    FileCabinet::run(Settings::with_flags(documents))
+}
+
+/// Runs a subcommand without opening the window, returns the exit code.
+fn run_subcommand(matches: &ArgMatches) -> Option<i32> {
+    let code = match matches.subcommand() {
+        ("sums", Some(args)) => {
+            let cabinet = Path::new(args.value_of("cabinet")?);
+            match checksums::write(cabinet) {
+                Ok(hashed) => {
+                    println!(
+                        "Wrote {} hashes to {}",
+                        hashed,
+                        checksums::path(cabinet).display()
+                    );
+                    0
+                }
+                Err(error) => {
+                    eprintln!("Couldn't write the checksums: {:?}", error);
+                    2
+                }
+            }
+        }
+        ("verify", Some(args)) => {
+            let cabinet = Path::new(args.value_of("cabinet")?);
+            let manifest = args
+                .value_of("manifest")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| checksums::path(cabinet));
+            match checksums::verify(cabinet, &manifest) {
+                Ok(report) => {
+                    for filename in &report.changed {
+                        println!("{}: CHANGED", filename);
+                    }
+                    for filename in &report.missing {
+                        println!("{}: MISSING", filename);
+                    }
+                    println!("{}", report);
+                    if report.passed() {
+                        0
+                    } else {
+                        1
+                    }
+                }
+                Err(error) => {
+                    eprintln!("Couldn't check {}: {:?}", manifest.display(), error);
+                    2
+                }
+            }
+        }
+        ("clean", Some(args)) => {
+            let cabinet = Path::new(args.value_of("cabinet")?);
+            let dry_run = args.is_present("dry-run");
+            match orphans::clean(cabinet, Some(orphans::PREVIEW_MAX_AGE), dry_run) {
+                Ok(orphans) => {
+                    for (old, new) in &orphans.relinked {
+                        println!("{}: RELINKED to {}", old, new);
+                    }
+                    for filename in &orphans.entries {
+                        println!("{}: FORGOTTEN", filename);
+                    }
+                    for path in orphans
+                        .texts
+                        .iter()
+                        .chain(&orphans.versions)
+                        .chain(&orphans.previews)
+                    {
+                        println!("{}: REMOVED", path.display());
+                    }
+                    if orphans.is_empty() {
+                        println!("Nothing to clean");
+                    } else if dry_run {
+                        println!("Would clean {}", orphans);
+                    } else {
+                        println!("Cleaned {}", orphans);
+                    }
+                    0
+                }
+                Err(error) => {
+                    eprintln!("Couldn't clean {}: {:?}", cabinet.display(), error);
+                    2
+                }
+            }
+        }
+        ("compact", Some(args)) => {
+            let cabinet = Path::new(args.value_of("cabinet")?);
+            match compact::compact(cabinet, Some(orphans::PREVIEW_MAX_AGE)) {
+                Ok(compaction) => {
+                    println!("Compacted {}", compaction);
+                    0
+                }
+                Err(error) => {
+                    eprintln!("Couldn't compact {}: {:?}", cabinet.display(), error);
+                    2
+                }
+            }
+        }
+        ("search", Some(args)) => {
+            let cabinet = Path::new(args.value_of("cabinet")?);
+            let query = args.values_of("query")?.join(" ");
+            match search::Query::parse(&query) {
+                Ok(query) => {
+                    let found = search::run(cabinet, &query);
+                    for (filename, hit) in &found {
+                        println!("{}", filename);
+                        if let Some(hit) = hit {
+                            println!(
+                                "    p. {}: {}[{}]{}",
+                                hit.page, hit.before, hit.matched, hit.after
+                            );
+                        }
+                    }
+                    if found.is_empty() {
+                        1
+                    } else {
+                        0
+                    }
+                }
+                Err(error) => {
+                    eprintln!("{}", error);
+                    2
+                }
+            }
+        }
+        #[cfg(unix)]
+        ("control", Some(args)) => {
+            let command = args.value_of("command")?;
+            let mut argument = args.values_of("argument").unwrap_or_default().join(" ");
+            // The app runs elsewhere, relative paths wouldn't mean the same to it.
+            if command == "add" || command == "open" {
+                if let Ok(path) = fs::canonicalize(&argument) {
+                    argument = path.to_string_lossy().to_string();
+                }
+            }
+            match control::send(&[format!("{} {}", command, argument)]) {
+                Ok(answers) if answers.iter().all(|answer| answer == "ok") => 0,
+                Ok(answers) => {
+                    eprintln!("{}", answers.join("\n"));
+                    2
+                }
+                Err(error) => {
+                    eprintln!("Couldn't reach a running filecabinet: {}", error);
+                    1
+                }
+            }
+        }
+        _ => return None,
+    };
+    Some(code)
 }
 
+#[allow(clippy::large_enum_variant)]
 enum FileCabinet {
-    Loading,
+    /// With the documents given on the command line, added once loaded.
+    Loading(Vec<String>),
+    Onboarding(Onboarding),
     Loaded(State),
 }
 
 struct State {
     refresh_state: button::State,
+    rules_state: button::State,
+    calendar_state: button::State,
+    duplicates_state: button::State,
+    packet_state: button::State,
+    sync_state: button::State,
+    backup_state: button::State,
+    stats_state: button::State,
+    tag_manager_state: button::State,
+    tag_rules_state: button::State,
+    fields_state: button::State,
+    people_state: button::State,
+    checklists_state: button::State,
+    institutions_state: button::State,
+    settings_state: button::State,
+    log_state: button::State,
     target_dir_state: text_input::State,
     target_dir: String,
-    panes: pane_grid::State<Box<dyn PaneContent>>,
+    panes: pane_grid::State<Panel>,
+    /// Pane shown alone instead of the grid.
+    maximized: Option<Pane>,
+    /// Width of the window once it was resized, for the compact layout.
+    window_width: Option<u32>,
     doc_pane: Option<Pane>,
     preview_pane: Option<Pane>,
     preview_image: String,
-    dirty: bool,
-    saving: bool,
+    preview_cache: PreviewCache,
+    compare_pane: Option<Pane>,
+    tags_pane: Option<Pane>,
+    tag_manager_pane: Option<Pane>,
+    tag_rules_pane: Option<Pane>,
+    fields_pane: Option<Pane>,
+    people_pane: Option<Pane>,
+    checklists_pane: Option<Pane>,
+    institutions_pane: Option<Pane>,
+    bundle_pane: Option<Pane>,
+    import_report_pane: Option<Pane>,
+    health_pane: Option<Pane>,
+    /// Sessions of the cabinets other than the open one, whose is kept by its panes.
+    sessions: BTreeMap<String, Session>,
+    duplicates_pane: Option<Pane>,
+    rules_pane: Option<Pane>,
+    calendar_pane: Option<Pane>,
+    packet_pane: Option<Pane>,
+    sync_pane: Option<Pane>,
+    backup_pane: Option<Pane>,
+    stats_pane: Option<Pane>,
+    settings_pane: Option<Pane>,
+    log_pane: Option<Pane>,
+    import_profile: ImportProfile,
+    packet_criteria: PacketCriteria,
+    preferences: Preferences,
+    /// Last mouse or keyboard input, `None` until the first check of the auto-lock.
+    last_activity: Option<Instant>,
+    /// Set once the cabinet locked itself, only the lock screen is shown then.
+    locked: bool,
+    /// Whether it was locked with Lock now rather than for being left alone.
+    locked_by_hand: bool,
+    lock_button: button::State,
+    unlock_passphrase: String,
+    unlock_error: String,
+    unlock_input: text_input::State,
+    unlock_button: button::State,
+    /// Shown instead of the cabinet until it is answered.
+    pending: Option<Pending>,
+    /// Set while scans are pulled from the drop folder, so polls don't overlap.
+    pulling: bool,
+    /// The downloads the import rules matched when last looked for, so the ones left behind
+    /// by a failed import aren't imported again until others arrive.
+    downloads: Vec<String>,
+    index_queue: index::Queue,
+    index_queue_button: button::State,
+    /// Set while indexing waits for the machine to be plugged in, see
+    /// `Preferences::low_power`.
+    on_battery: bool,
+    /// The export running, with the rows written so far and how many there are.
+    export: Option<(export::Format, usize, usize)>,
+    export_button: button::State,
+    /// Shreds what is left in the scratch workspace when the app exits.
+    _scratch: scratch::Cleanup,
+    /// The saved state as last handed to `SavedState::save`, so only changes are saved.
+    /// Cleared when that save fails, for the next one to write it again.
+    saved_json: String,
+    /// Saves started and the newest one written, counted from launch.
+    saves: u64,
+    saves_written: u64,
+}
+
+/// A destructive action waiting for the user to confirm it, see `Confirmation`.
+struct Pending {
+    confirmation: Confirmation,
+    question: String,
+    /// Done once confirmed.
+    message: Message,
+    dont_ask: bool,
+    yes_button: button::State,
+    no_button: button::State,
+}
+
+impl Pending {
+    fn new(confirmation: Confirmation, question: String, message: Message) -> Self {
+        Pending {
+            confirmation,
+            question,
+            message,
+            dont_ask: false,
+            yes_button: Default::default(),
+            no_button: Default::default(),
+        }
+    }
 }
 
 impl Default for State {
     fn default() -> Self {
-        let (pane_state, pane) =
-            pane_grid::State::new(Box::new(DocPane::default()) as Box<dyn PaneContent>);
+        let (pane_state, pane) = pane_grid::State::new(Panel::new(DocPane::default()));
         State {
             refresh_state: Default::default(),
+            rules_state: Default::default(),
+            calendar_state: Default::default(),
+            duplicates_state: Default::default(),
+            packet_state: Default::default(),
+            sync_state: Default::default(),
+            backup_state: Default::default(),
+            stats_state: Default::default(),
+            tag_manager_state: Default::default(),
+            tag_rules_state: Default::default(),
+            fields_state: Default::default(),
+            people_state: Default::default(),
+            checklists_state: Default::default(),
+            institutions_state: Default::default(),
+            settings_state: Default::default(),
+            log_state: Default::default(),
             target_dir_state: Default::default(),
             target_dir: "".to_string(),
             panes: pane_state,
+            maximized: None,
+            window_width: None,
             doc_pane: Some(pane),
             preview_pane: None,
             preview_image: "".to_string(),
-            dirty: false,
-            saving: false,
+            preview_cache: Default::default(),
+            compare_pane: None,
+            tags_pane: None,
+            tag_manager_pane: None,
+            tag_rules_pane: None,
+            fields_pane: None,
+            people_pane: None,
+            checklists_pane: None,
+            institutions_pane: None,
+            bundle_pane: None,
+            import_report_pane: None,
+            health_pane: None,
+            sessions: BTreeMap::new(),
+            duplicates_pane: None,
+            rules_pane: None,
+            calendar_pane: None,
+            packet_pane: None,
+            sync_pane: None,
+            backup_pane: None,
+            stats_pane: None,
+            settings_pane: None,
+            log_pane: None,
+            import_profile: Default::default(),
+            packet_criteria: Default::default(),
+            preferences: Default::default(),
+            last_activity: None,
+            locked: false,
+            locked_by_hand: false,
+            lock_button: Default::default(),
+            unlock_passphrase: String::new(),
+            unlock_error: String::new(),
+            unlock_input: Default::default(),
+            unlock_button: Default::default(),
+            pending: None,
+            pulling: false,
+            downloads: Vec::new(),
+            index_queue: Default::default(),
+            index_queue_button: Default::default(),
+            on_battery: false,
+            export: None,
+            export_button: Default::default(),
+            _scratch: Default::default(),
+            saved_json: String::new(),
+            saves: 0,
+            saves_written: 0,
+        }
+    }
+}
+
+impl State {
+    fn from_saved(saved_state: SavedState) -> Self {
+        // Create the panes so that the documents are loaded on launch.
+        let (mut pane_state, pane) = pane_grid::State::new(Panel::new(DocPane::default()));
+        // Pass the path to each doc_pane doc so it can render.
+        for (_pane, boxed_content) in pane_state.iter_mut() {
+            boxed_content.update(PaneMessage::Event(Event::PathChanged(
+                saved_state.target_dir.clone(),
+            )));
+            boxed_content.update(PaneMessage::Event(Event::PreferencesChanged(
+                saved_state.preferences.clone(),
+            )));
+        }
+        let mut state = State {
+            target_dir: saved_state.target_dir,
+            import_profile: saved_state.import_profile,
+            packet_criteria: saved_state.packet_criteria,
+            preferences: saved_state.preferences,
+            sessions: saved_state.sessions,
+            panes: pane_state,
+            doc_pane: Some(pane),
+            ..Default::default()
+        };
+        state.index_queue.status = saved_state.index_status;
+        events::enable(state.preferences.event_stream);
+        state
+    }
+
+    /// Filenames of the documents on screen, the previewed one first.
+    fn visible(&self) -> Vec<String> {
+        let listed = self
+            .doc_pane
+            .and_then(|pane| self.panes.get(&pane))
+            .map(|panel| panel.content.listed())
+            .unwrap_or_default();
+        std::iter::once(self.preview_image.clone())
+            .chain(listed.into_iter().take(VISIBLE))
+            .filter_map(|path| Some(Path::new(&path).file_name()?.to_string_lossy().to_string()))
+            .unique()
+            .collect()
+    }
+
+    /// Queues the documents at `paths` ahead of the others if they are in the cabinet.
+    fn queue_added(&mut self, paths: &[String]) {
+        let added: Vec<String> = paths
+            .iter()
+            .map(Path::new)
+            .filter(|path| path.parent() == Some(Path::new(&self.target_dir)))
+            .filter_map(|path| Some(path.file_name()?.to_string_lossy().to_string()))
+            .collect();
+        let events: Vec<events::Event> = added
+            .iter()
+            .map(|filename| events::Event::Added {
+                filename: filename.clone(),
+            })
+            .collect();
+        events::emit(Path::new(&self.target_dir), &events);
+        self.index_queue.add(&added);
+    }
+
+    /// Shows `report` in the import report pane, opening it below the documents if needed.
+    fn show_import_report(&mut self, report: ImportReport) {
+        let report_pane = ImportReportPane {
+            report,
+            ..Default::default()
+        };
+        match self
+            .import_report_pane
+            .and_then(|pane| self.panes.get_mut(&pane))
+        {
+            Some(panel) => panel.content = Box::new(report_pane),
+            None => {
+                if let Some(doc_pane) = self.doc_pane {
+                    self.import_report_pane = self
+                        .panes
+                        .split(
+                            pane_grid::Axis::Horizontal,
+                            &doc_pane,
+                            Panel::new(report_pane),
+                        )
+                        .map(|(pane, _)| pane);
+                }
+            }
+        }
+    }
+
+    /// Shows what the health check found, opening a pane below the documents if there is
+    /// anything to fix.
+    fn show_health(&mut self, health: Health) {
+        match self.health_pane.and_then(|pane| self.panes.get_mut(&pane)) {
+            Some(panel) => {
+                panel.content = Box::new(HealthPane {
+                    health,
+                    ..Default::default()
+                })
+            }
+            None if health.is_healthy() => {}
+            None => {
+                if let Some(doc_pane) = self.doc_pane {
+                    self.health_pane = self
+                        .panes
+                        .split(
+                            pane_grid::Axis::Horizontal,
+                            &doc_pane,
+                            Panel::new(HealthPane {
+                                health,
+                                ..Default::default()
+                            }),
+                        )
+                        .map(|(pane, _)| pane);
+                }
+            }
+        }
+    }
+
+    fn check_health(&self) -> Command<Message> {
+        Command::perform(health::run(self.target_dir.clone()), Message::HealthChecked)
+    }
+
+    /// Removes the documents that stayed in the trash longer than the preferences keep them.
+    fn purge_trash(&self) -> Command<Message> {
+        Command::perform(
+            trash::purge_expired(
+                self.target_dir.clone(),
+                self.preferences.trash_retention_days,
+            ),
+            Message::TrashPurged,
+        )
+    }
+
+    /// Indexes `filenames` ahead of the others, starting the queue if it is idle.
+    fn index_first(&mut self, filenames: &[String]) -> Command<Message> {
+        if self.index_queue.status == index::Status::Idle {
+            self.index_queue.status = index::Status::Running;
+            Command::perform(index::queue(self.target_dir.clone()), Message::IndexQueued)
+        } else {
+            self.index_queue.add(filenames);
+            Command::none()
+        }
+    }
+
+    /// Indexes the next document of the queue, if it runs and isn't busy. In low power
+    /// mode, the queue waits while the machine runs on its battery.
+    fn index_next(&mut self) -> Command<Message> {
+        if self.preferences.low_power
+            && self.index_queue.status == index::Status::Running
+            && power::on_battery()
+        {
+            self.on_battery = true;
+            return Command::none();
+        }
+        match self.index_queue.next() {
+            Some(filename) => Command::perform(
+                index::index(
+                    self.target_dir.clone(),
+                    filename,
+                    self.preferences.ocr_languages(),
+                ),
+                Message::IndexedDocument,
+            ),
+            None => {
+                if self.index_queue.is_done() {
+                    self.broadcast(Event::RefreshTargetDir(self.target_dir.clone()));
+                }
+                Command::none()
+            }
+        }
+    }
+
+    /// Where documents from outside land: the folder the import rules file from, or else
+    /// the cabinet itself.
+    /// Runs the import rules over the import folder.
+    fn import(&self) -> Command<Message> {
+        Command::perform(
+            rules::import(
+                self.import_profile.clone(),
+                self.target_dir.clone(),
+                self.preferences.blank_ink(),
+                self.preferences.hooks.clone(),
+            ),
+            Message::Imported,
+        )
+    }
+
+    fn intake_dir(&self) -> String {
+        let source_dir = self.import_profile.source_dir.trim();
+        if !source_dir.is_empty() && Path::new(source_dir).is_dir() {
+            source_dir.to_string()
+        } else {
+            self.target_dir.clone()
+        }
+    }
+
+    fn compact(&self) -> bool {
+        self.window_width.is_some_and(|width| width < COMPACT_WIDTH)
+    }
+
+    /// The pane `message` opens or brings up, for the compact layout to show it.
+    fn opened_by(&self, message: &Message) -> Option<Pane> {
+        match message {
+            Message::DocPane(DocPaneMessage::Doc(_, DocMessage::OpenPreviewPane(_, _)))
+            | Message::ShowPreview(_)
+            | Message::ShowHit(_, _) => self.preview_pane,
+            Message::Compare(_, _) => self.compare_pane,
+            Message::OpenTagsPane(_) => self.tags_pane,
+            Message::OpenBundlePane(_, _) => self.bundle_pane,
+            Message::OpenChecklistsPane => self.checklists_pane,
+            Message::Imported(Ok(_)) => self.import_report_pane,
+            Message::HealthChecked(_) => self.health_pane,
+            Message::OpenTagManagerPane => self.tag_manager_pane,
+            Message::OpenTagRulesPane => self.tag_rules_pane,
+            Message::OpenFieldsPane => self.fields_pane,
+            Message::OpenPeoplePane => self.people_pane,
+            Message::OpenInstitutionsPane => self.institutions_pane,
+            Message::OpenRulesPane => self.rules_pane,
+            Message::OpenCalendarPane => self.calendar_pane,
+            Message::OpenDuplicatesPane => self.duplicates_pane,
+            Message::OpenPacketPane => self.packet_pane,
+            Message::OpenSyncPane => self.sync_pane,
+            Message::OpenBackupPane => self.backup_pane,
+            Message::OpenStatsPane => self.stats_pane,
+            Message::OpenSettingsPane => self.settings_pane,
+            Message::OpenLogPane => self.log_pane,
+            _ => None,
+        }
+    }
+
+    /// Sends `message` to the pane it is addressed to, or to every pane for events.
+    fn send(&mut self, message: PaneMessage) {
+        let pane = match &message {
+            PaneMessage::Doc(_) => self.doc_pane,
+            PaneMessage::Preview(_) => self.preview_pane,
+            PaneMessage::Compare(_) => self.compare_pane,
+            PaneMessage::Tags(_) => self.tags_pane,
+            PaneMessage::TagManager(_) => self.tag_manager_pane,
+            PaneMessage::TagRules(_) => self.tag_rules_pane,
+            PaneMessage::Fields(_) => self.fields_pane,
+            PaneMessage::People(_) => self.people_pane,
+            PaneMessage::Checklists(_) => self.checklists_pane,
+            PaneMessage::Institutions(_) => self.institutions_pane,
+            PaneMessage::Bundle(_) => self.bundle_pane,
+            PaneMessage::ImportReport(_) => self.import_report_pane,
+            PaneMessage::Health(_) => self.health_pane,
+            PaneMessage::Packet(_) => self.packet_pane,
+            PaneMessage::Sync(_) => self.sync_pane,
+            PaneMessage::Backup(_) => self.backup_pane,
+            PaneMessage::Stats(_) => self.stats_pane,
+            PaneMessage::Calendar(_) => self.calendar_pane,
+            PaneMessage::Settings(_) => self.settings_pane,
+            PaneMessage::Log(_) => self.log_pane,
+            PaneMessage::Event(_) => {
+                for (_pane, panel) in self.panes.iter_mut() {
+                    panel.update(message.clone());
+                }
+                return;
+            }
+        };
+        if let Some(panel) = pane.and_then(|pane| self.panes.get_mut(&pane)) {
+            panel.update(message);
         }
     }
+
+    fn broadcast(&mut self, event: Event) {
+        self.send(PaneMessage::Event(event));
+    }
+
+    /// Drops decrypted previews and typed passphrases and shows the lock screen, `by_hand`
+    /// when asked to rather than after a while without use.
+    fn lock(&mut self, by_hand: bool) {
+        self.locked = true;
+        self.locked_by_hand = by_hand;
+        self.preview_cache.forget_unlocked();
+        self.broadcast(Event::Locked);
+        if by_hand {
+            info!(event = "LockNow", dir = %self.target_dir);
+        } else {
+            info!(
+                event = "AutoLock",
+                minutes = self.preferences.auto_lock_minutes
+            );
+        }
+    }
+
+    /// Shows `path` in the preview pane, opening one next to the documents if needed.
+    fn show_preview(&mut self, path: String) -> Command<Message> {
+        if let Some(doc_pane) = self.doc_pane {
+            let neighbours = self
+                .panes
+                .get(&doc_pane)
+                .map(|panel| panel.content.neighbours(&path))
+                .unwrap_or_default();
+            let preview = PreviewPane::new(
+                path.clone(),
+                neighbours.clone(),
+                self.preview_cache.get(&path),
+            );
+            // Swap the content of an open preview pane so the layout stays put.
+            match self.preview_pane.and_then(|pane| self.panes.get_mut(&pane)) {
+                Some(panel) => panel.content = Box::new(preview),
+                None => {
+                    if let Some((preview_pane, _split)) = self.panes.split(
+                        self.preferences.preview_axis(),
+                        &doc_pane,
+                        Panel::new(preview),
+                    ) {
+                        self.preview_pane = Some(preview_pane);
+                    }
+                }
+            }
+            self.preview_image = path.clone();
+            self.index_queue.prioritize(&self.visible());
+            if let Some(viewed) = catalog::record_view(Path::new(&path)) {
+                self.send(PaneMessage::Doc(DocPaneMessage::Viewed(
+                    path.clone(),
+                    viewed,
+                )));
+            }
+            // Decode the neighbours too, so stepping through the list is instant.
+            let (previous, next) = neighbours;
+            let count = if utils::extension(&path) == "pdf" {
+                Command::perform(preview::page_count(path.clone()), |(path, pages)| {
+                    Message::PreviewPane(PreviewMessage::PagesCounted(path, pages))
+                })
+            } else {
+                Command::none()
+            };
+            let previews =
+                self.load_previews(std::iter::once(path).chain(previous).chain(next).collect());
+            return Command::batch(vec![previews, count]);
+        }
+        Command::none()
+    }
+
+    /// Shows page `page` of the previewed document, from the cache if it was shown lately.
+    fn show_page(&mut self, path: String, page: usize) -> Command<Message> {
+        self.send(PaneMessage::Preview(PreviewMessage::ShowPage(
+            path.clone(),
+            page,
+        )));
+        let cached = if page == 1 {
+            self.preview_cache.get(&path)
+        } else {
+            self.preview_cache.get_page(&path, page)
+        };
+        match cached {
+            Some(handle) => {
+                self.send(PaneMessage::Preview(PreviewMessage::PageLoaded(
+                    path,
+                    page,
+                    Some(handle),
+                )));
+                Command::none()
+            }
+            None => Command::perform(preview::page(path, page), |(path, page, handle)| {
+                Message::PreviewPane(PreviewMessage::PageLoaded(path, page, handle))
+            }),
+        }
+    }
+
+    /// Decodes the previews of `paths` that aren't cached or being decoded yet.
+    fn load_previews(&mut self, paths: Vec<String>) -> Command<Message> {
+        let commands: Vec<_> = paths
+            .into_iter()
+            .filter(|path| self.preview_cache.start_loading(path))
+            .map(|path| {
+                Command::perform(preview::load(path), |(path, handle)| {
+                    Message::PreviewLoaded(path, handle)
+                })
+            })
+            .collect();
+        Command::batch(commands)
+    }
+
+    /// Saves the state if it changed since the last save, keeping it for the panic hook
+    /// until it is written.
+    fn save(&mut self) -> Command<Message> {
+        let json = match serde_json::to_string_pretty(&self.saved_state()) {
+            Ok(json) if json != self.saved_json => json,
+            _ => return Command::none(),
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        crash::unsaved(json.clone());
+        self.saved_json = json.clone();
+        self.saves += 1;
+        let save = self.saves;
+        Command::perform(SavedState::save(json, save), move |result| {
+            Message::Saved(save, result)
+        })
+    }
+
+    fn saved_state(&self) -> SavedState {
+        SavedState {
+            target_dir: self.target_dir.clone(),
+            import_profile: self.import_profile.clone(),
+            packet_criteria: self.packet_criteria.clone(),
+            preferences: self.preferences.clone(),
+            index_status: self.index_queue.status,
+            sessions: self.sessions(),
+        }
+    }
+
+    /// The sessions of every cabinet, the open one's as it is now.
+    fn sessions(&self) -> BTreeMap<String, Session> {
+        let mut sessions = self.sessions.clone();
+        let session = self
+            .doc_pane
+            .and_then(|pane| self.panes.get(&pane))
+            .and_then(|panel| panel.content.session());
+        if let Some(mut session) = session {
+            session.previewed = self
+                .preview_pane
+                .and_then(|_| Path::new(&self.preview_image).file_name())
+                .map(|filename| filename.to_string_lossy().to_string());
+            sessions.insert(self.target_dir.clone(), session);
+        }
+        sessions
+    }
+
+    /// Puts the document list of the open cabinet back the way it was left, searching and
+    /// previewing again.
+    fn restore_session(&mut self) -> Command<Message> {
+        let session = match self.sessions.get(&self.target_dir) {
+            Some(session) => session.clone(),
+            None => return Command::none(),
+        };
+        self.send(PaneMessage::Doc(DocPaneMessage::Restore(session.clone())));
+        let mut commands = Vec::new();
+        if !session.query.trim().is_empty() {
+            commands.push(Command::perform(
+                search::search(self.target_dir.clone(), session.query),
+                |(query, found)| Message::DocPane(DocPaneMessage::Searched(query, found)),
+            ));
+        }
+        let previewed = session
+            .previewed
+            .map(|filename| Path::new(&self.target_dir).join(filename))
+            .filter(|path| path.exists());
+        if let Some(path) = previewed {
+            commands.push(self.show_preview(path.to_string_lossy().to_string()));
+        }
+        Command::batch(commands)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum OnboardingStep {
+    #[default]
+    Cabinet,
+    Schema,
+    Vault,
+    Import,
+}
+
+/// Why a new vault passphrase can't be used, if it can't.
+fn check_passphrase(passphrase: &str, confirmation: &str) -> Result<(), String> {
+    if passphrase.chars().count() < 8 {
+        Err("Use at least 8 characters.".to_string())
+    } else if passphrase != confirmation {
+        Err("The passphrases don't match.".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+/// First-run wizard, shown instead of the main UI until a cabinet is set up.
+#[derive(Debug, Default)]
+struct Onboarding {
+    step: OnboardingStep,
+    cabinet_dir: String,
+    passphrase: String,
+    passphrase_confirmation: String,
+    import_dir: String,
+    error: String,
+    cabinet_dir_input: text_input::State,
+    passphrase_input: text_input::State,
+    passphrase_confirmation_input: text_input::State,
+    import_dir_input: text_input::State,
+    back_button: button::State,
+    next_button: button::State,
+}
+
+#[derive(Debug, Clone)]
+enum OnboardingMessage {
+    CabinetDirEdited(String),
+    PassphraseEdited(String),
+    PassphraseConfirmationEdited(String),
+    ImportDirEdited(String),
+    Back,
+    Next,
+}
+
+impl Onboarding {
+    fn update(&mut self, message: OnboardingMessage) {
+        match message {
+            OnboardingMessage::CabinetDirEdited(s) => self.cabinet_dir = s,
+            OnboardingMessage::PassphraseEdited(s) => self.passphrase = s,
+            OnboardingMessage::PassphraseConfirmationEdited(s) => self.passphrase_confirmation = s,
+            OnboardingMessage::ImportDirEdited(s) => self.import_dir = s,
+            OnboardingMessage::Back => {
+                self.error.clear();
+                self.step = match self.step {
+                    OnboardingStep::Cabinet | OnboardingStep::Schema => OnboardingStep::Cabinet,
+                    OnboardingStep::Vault => OnboardingStep::Schema,
+                    OnboardingStep::Import => OnboardingStep::Vault,
+                };
+            }
+            OnboardingMessage::Next => match self.validate() {
+                Err(error) => self.error = error,
+                Ok(()) => {
+                    self.error.clear();
+                    self.step = match self.step {
+                        OnboardingStep::Cabinet => OnboardingStep::Schema,
+                        OnboardingStep::Schema => OnboardingStep::Vault,
+                        OnboardingStep::Vault | OnboardingStep::Import => OnboardingStep::Import,
+                    };
+                }
+            },
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match self.step {
+            OnboardingStep::Cabinet => {
+                let dir = Path::new(self.cabinet_dir.trim());
+                if self.cabinet_dir.trim().is_empty() {
+                    Err("Choose a folder for the cabinet.".to_string())
+                } else if dir.exists() && !dir.is_dir() {
+                    Err("That path is a file, not a folder.".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+            OnboardingStep::Schema => Ok(()),
+            OnboardingStep::Vault => {
+                if self.passphrase.is_empty() {
+                    Ok(())
+                } else {
+                    check_passphrase(&self.passphrase, &self.passphrase_confirmation)
+                }
+            }
+            OnboardingStep::Import => {
+                let dir = self.import_dir.trim();
+                if dir.is_empty() || Path::new(dir).is_dir() {
+                    Ok(())
+                } else {
+                    Err("That folder doesn't exist.".to_string())
+                }
+            }
+        }
+    }
+
+    /// Creates the cabinet folder and its vault.
+    fn finish(&self) -> Result<(), String> {
+        self.validate()?;
+        let dir = Path::new(self.cabinet_dir.trim());
+        fs::create_dir_all(dir).map_err(|_| "The cabinet folder can't be created.".to_string())?;
+        if !self.passphrase.is_empty() {
+            vault::create(dir, &self.passphrase)
+                .map_err(|_| "The vault can't be set up.".to_string())?;
+        }
+        info!(event = "Onboarding", cabinet = %dir.display());
+        Ok(())
+    }
+
+    fn view(&mut self) -> Element<'_, Message> {
+        let (number, title) = match self.step {
+            OnboardingStep::Cabinet => (1, "Where should documents be kept?"),
+            OnboardingStep::Schema => (2, "How documents are named"),
+            OnboardingStep::Vault => (3, "Protect the cabinet"),
+            OnboardingStep::Import => (4, "Bring in existing documents"),
+        };
+        let hint = |text: &str| Text::new(text).size(16).color([0.5, 0.5, 0.5]);
+        let content: Element<_> = match self.step {
+            OnboardingStep::Cabinet => Column::new()
+                .spacing(10)
+                .push(hint(
+                    "Pick a folder, it is created if it doesn't exist yet.",
+                ))
+                .push(
+                    TextInput::new(
+                        &mut self.cabinet_dir_input,
+                        "Path to the cabinet folder",
+                        &self.cabinet_dir,
+                        |s| Message::OnboardingMessage(OnboardingMessage::CabinetDirEdited(s)),
+                    )
+                    .padding(10),
+                )
+                .into(),
+            OnboardingStep::Schema => Column::new()
+                .spacing(10)
+                .push(hint("Every document is renamed to"))
+                .push(Text::new("date_Institution_Title_page.ext"))
+                .push(hint("for example"))
+                .push(Text::new(utils::normalized_filename(
+                    "2021-03-04",
+                    "Chase",
+                    "Statement",
+                    "1",
+                    "pdf",
+                )))
+                .push(hint(
+                    "so they sort by date and can be found without this app.",
+                ))
+                .into(),
+            OnboardingStep::Vault => Column::new()
+                .spacing(10)
+                .push(hint(
+                    "Optionally set a passphrase for the vault. Leave it empty to skip.",
+                ))
+                .push(
+                    TextInput::new(
+                        &mut self.passphrase_input,
+                        "Passphrase",
+                        &self.passphrase,
+                        |s| Message::OnboardingMessage(OnboardingMessage::PassphraseEdited(s)),
+                    )
+                    .password()
+                    .padding(10),
+                )
+                .push(
+                    TextInput::new(
+                        &mut self.passphrase_confirmation_input,
+                        "Repeat the passphrase",
+                        &self.passphrase_confirmation,
+                        |s| {
+                            Message::OnboardingMessage(
+                                OnboardingMessage::PassphraseConfirmationEdited(s),
+                            )
+                        },
+                    )
+                    .password()
+                    .padding(10),
+                )
+                .into(),
+            OnboardingStep::Import => Column::new()
+                .spacing(10)
+                .push(hint(
+                    "Optionally copy the documents of a folder into the cabinet.",
+                ))
+                .push(
+                    TextInput::new(
+                        &mut self.import_dir_input,
+                        "Folder to import",
+                        &self.import_dir,
+                        |s| Message::OnboardingMessage(OnboardingMessage::ImportDirEdited(s)),
+                    )
+                    .padding(10),
+                )
+                .into(),
+        };
+        let mut back = Button::new(&mut self.back_button, Text::new("Back"))
+            .padding(10)
+            .style(style::Button::Cancel);
+        if self.step != OnboardingStep::Cabinet {
+            back = back.on_press(Message::OnboardingMessage(OnboardingMessage::Back));
+        }
+        let next = Button::new(
+            &mut self.next_button,
+            Text::new(if self.step == OnboardingStep::Import {
+                "Finish"
+            } else {
+                "Next"
+            }),
+        )
+        .padding(10)
+        .style(style::Button::Update)
+        .on_press(Message::OnboardingMessage(OnboardingMessage::Next));
+
+        Container::new(
+            Column::new()
+                .max_width(600)
+                .spacing(20)
+                .push(
+                    Text::new("Welcome to filecabinet")
+                        .size(40)
+                        .color([0.5, 0.5, 0.5]),
+                )
+                .push(Text::new(format!("Step {} of 4", number)).size(16))
+                .push(Text::new(title).size(24))
+                .push(content)
+                .push(Text::new(&self.error).size(16).color([0.8, 0.2, 0.2]))
+                .push(Row::new().spacing(10).push(back).push(next)),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .padding(40)
+        .center_x()
+        .center_y()
+        .into()
+    }
 }
 
 #[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names, clippy::large_enum_variant)]
 enum Message {
     RefreshTargetDir(String),
     Loaded(Result<SavedState, LoadError>),
-    Saved(Result<(), SaveError>),
+    /// The save of that number was written.
+    Saved(u64, Result<(), SaveError>),
     PathChanged(String),
-    FilterChanged(Filter),
-    DocMessage(usize, DocMessage),
+    DocPane(DocPaneMessage),
     ClosePreviewPane(Pane),
     Dragged(pane_grid::DragEvent),
     Resized(pane_grid::ResizeEvent),
+    OpenRulesPane,
+    CloseRulesPane(Pane),
+    OpenCalendarPane,
+    CloseCalendarPane(Pane),
+    CalendarPane(CalendarMessage),
+    ExportCalendar,
+    OpenPacketPane,
+    ClosePacketPane(Pane),
+    PacketMessage(PacketMessage),
+    PacketPane(PacketPaneMessage),
+    SyncPane(SyncPaneMessage),
+    BackupPane(BackupPaneMessage),
+    SettingsPane(SettingsPaneMessage),
+    ExportPacket(String, Vec<String>, Vec<Recipient>),
+    OpenSyncPane,
+    CloseSyncPane(Pane),
+    PlanSync(String),
+    ApplySync(String, Vec<Change>),
+    OpenBackupPane,
+    CloseBackupPane(Pane),
+    Backup(String, Option<String>),
+    Restore(String, String, Option<String>, String),
+    WriteChecksums,
+    WriteSite,
+    VerifyChecksums,
+    OpenStatsPane,
+    CloseStatsPane(Pane),
+    ComputeStats,
+    StatsPane(StatsPaneMessage),
+    /// Drops orphaned and stale metadata of the cabinet, see `compact::compact`.
+    CompactMetadata,
+    /// Writes every document of the cabinet to a file in `<cabinet>/exports`.
+    ExportCabinet(export::Format),
+    ExportProgress(export::Progress),
+    CancelExport,
+    EmptyTrash,
+    /// What was purged from the trash for being kept longer than the preferences say.
+    TrashPurged(Result<stats::Totals, trash::TrashError>),
+    Index,
+    IndexQueued(Result<Vec<String>, catalog::CatalogError>),
+    IndexedDocument(Result<String, catalog::CatalogError>),
+    PauseIndex,
+    ResumeIndex,
+    /// Checks whether the machine was plugged in, to resume indexing.
+    CheckPower,
+    /// Page of the previewed document to show, counted from 1.
+    ShowPage(String, usize),
+    /// A phone shared a document, saved at this path.
+    #[cfg(not(target_arch = "wasm32"))]
+    Shared(String),
+    #[cfg(target_os = "linux")]
+    Tray(tray::TrayAction),
+    /// Documents added from the tray, copied into the intake folder.
+    #[cfg(target_os = "linux")]
+    QuickAdded(Result<Vec<String>, tray::TrayError>),
+    /// A script asked through the control socket, or the app was launched again.
+    Control(control::Request),
+    /// A document added through the control socket or the command line, copied into the
+    /// intake folder.
+    ControlAdded(Result<String, control::ControlError>),
+    /// A document to name, in the open cabinet, and whether it was copied in.
+    ControlOpened(Result<(String, bool), control::ControlError>),
+    PullScans,
+    PulledScans(Result<ImportReport, DropFolderError>),
+    Archive(Vec<String>),
+    Archived(Result<usize, ArchiveError>),
+    Unarchived(Result<String, ArchiveError>),
+    OpenSettingsPane,
+    CloseSettingsPane(Pane),
+    ChangePassphrase(String, String),
+    OpenLogPane,
+    CloseLogPane(Pane),
+    LogPane(LogMessage),
+    PreferencesMessage(PreferencesMessage),
+    MadeSearchable(Result<String, ocr::OcrError>),
+    ShowPreview(String),
+    /// Shows a document at the page a search hit is on.
+    ShowHit(String, usize),
+    /// Opens a document with the program the desktop opens its kind of file with.
+    OpenExternally(String),
+    WindowResized(u32),
+    Activity,
+    Tick(Instant),
+    /// Locks the cabinet right away, e.g. before stepping away from a shared computer.
+    LockNow,
+    UnlockPassphraseEdited(String),
+    UnlockCabinet,
+    Compare(String, String),
+    CloseComparePane(Pane),
+    /// Edits the tags of the documents at these paths.
+    OpenTagsPane(Vec<String>),
+    CloseTagsPane(Pane),
+    TagsPane(TagsPaneMessage),
+    OpenTagManagerPane,
+    CloseTagManagerPane(Pane),
+    TagManagerPane(TagManagerMessage),
+    OpenTagRulesPane,
+    CloseTagRulesPane(Pane),
+    TagRulesPane(TagRulesMessage),
+    OpenFieldsPane,
+    CloseFieldsPane(Pane),
+    FieldsPane(FieldsMessage),
+    OpenPeoplePane,
+    ClosePeoplePane(Pane),
+    PeoplePane(PeopleMessage),
+    OpenChecklistsPane,
+    CloseChecklistsPane(Pane),
+    ChecklistsPane(ChecklistsMessage),
+    OpenInstitutionsPane,
+    CloseInstitutionsPane(Pane),
+    InstitutionsPane(InstitutionsMessage),
+    /// Documents and files going with them, see `ExportBundle`.
+    OpenBundlePane(Vec<String>, Vec<(String, Vec<u8>)>),
+    CloseBundlePane(Pane),
+    BundlePane(BundleMessage),
+    /// Documents, files going with them, the password and whom the copies are stamped as
+    /// provided to, if they are.
+    ExportBundle(Vec<String>, Vec<(String, Vec<u8>)>, String, Option<String>),
+    CloseImportReportPane(Pane),
+    ImportReportPane(ImportReportMessage),
+    ExportImportReport(ImportReport),
+    CheckHealth,
+    HealthChecked(Health),
+    CloseHealthPane(Pane),
+    /// Relinks the missing documents that were moved and forgets the others.
+    FixMissing,
+    /// Indexes the changed documents again.
+    FixChanged(Vec<String>),
+    IndexUncatalogued(Vec<String>),
+    /// The documents to index again, once fixed.
+    HealthFixed(Result<Vec<String>, catalog::CatalogError>),
+    ComparePane(CompareMessage),
+    OpenDuplicatesPane,
+    CloseDuplicatesPane(Pane),
+    MaximizePane(Pane),
+    RestorePanes,
+    OnboardingMessage(OnboardingMessage),
+    PreviewLoaded(String, Option<image::Handle>),
+    PreviewPane(PreviewMessage),
+    Unlock(String, String, bool, bool),
+    OpenSealed(String, String),
+    Seal(String, String),
+    SaveRotation(String, u8),
+    /// The pages to keep, in order, with the quarter turns to add to each.
+    SavePages(String, Vec<(u32, u8)>),
+    /// Writes a copy of the document with the boxes blacked out, by page number.
+    ExportRedacted(String, BTreeMap<usize, Vec<Region>>),
+    RuleMessage(RuleMessage),
+    Import,
+    /// Looks for downloads the import rules match, to import them as they arrive.
+    WatchDownloads,
+    Imported(Result<ImportReport, rules::ImportError>),
+    #[cfg(target_arch = "wasm32")]
+    PickFiles,
+    #[cfg(target_arch = "wasm32")]
+    Picked(Result<usize, storage::StorageError>),
+    /// Whether the pending destructive action is done, see `Pending`.
+    ConfirmationAnswered(bool),
+    DontAskAgainToggled(bool),
 }
 
-#[derive(Debug, Default)]
-struct DocPane {
-    scroll: scrollable::State,
-    filter: Filter,
-    controls: Controls,
-    docs: Vec<Document>,
+impl Message {
+    /// What to ask before the message is handled, for actions that can't be undone.
+    fn confirmation(&self) -> Option<(Confirmation, String)> {
+        let filename = |path: &str| {
+            Path::new(path)
+                .file_name()
+                .map_or(String::new(), |name| name.to_string_lossy().to_string())
+        };
+        match self {
+            Message::DocPane(DocPaneMessage::Doc(_, DocMessage::ConfirmDelete)) => Some((
+                Confirmation::Delete,
+                "Move the document to the trash?".to_string(),
+            )),
+            Message::EmptyTrash => Some((
+                Confirmation::Delete,
+                "Empty the trash? Its documents can't be brought back.".to_string(),
+            )),
+            Message::ApplySync(_, changes) => {
+                let renames = changes
+                    .iter()
+                    .filter(|change| matches!(change, Change::Rename { .. }))
+                    .count();
+                (renames > 1).then(|| {
+                    let question = format!("Rename {} documents to match the sync?", renames);
+                    (Confirmation::BulkRename, question)
+                })
+            }
+            Message::InstitutionsPane(InstitutionsMessage::Rename(from, to)) => Some((
+                Confirmation::BulkRename,
+                format!("Rename every document of {} to {}?", from, to),
+            )),
+            Message::SaveRotation(path, _) | Message::SavePages(path, _) => Some((
+                Confirmation::Overwrite,
+                format!("Overwrite {} with the changed pages?", filename(path)),
+            )),
+            Message::Unlock(path, _, _, true) => Some((
+                Confirmation::Decrypt,
+                format!(
+                    "Keep a copy of {} without its password? Anyone with the file can read it.",
+                    filename(path)
+                ),
+            )),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, Default)]
-struct PreviewPane {
-    preview_image_path: String,
-    close_button: button::State,
-    scroll_state: scrollable::State,
+/// What panes are told. A message addressed to a kind of pane only reaches that pane,
+/// events reach every pane.
+#[derive(Debug, Clone)]
+enum PaneMessage {
+    Doc(DocPaneMessage),
+    Preview(PreviewMessage),
+    Compare(CompareMessage),
+    Tags(TagsPaneMessage),
+    TagManager(TagManagerMessage),
+    TagRules(TagRulesMessage),
+    Fields(FieldsMessage),
+    People(PeopleMessage),
+    Checklists(ChecklistsMessage),
+    Institutions(InstitutionsMessage),
+    Bundle(BundleMessage),
+    ImportReport(ImportReportMessage),
+    Health(HealthMessage),
+    Packet(PacketPaneMessage),
+    Sync(SyncPaneMessage),
+    Backup(BackupPaneMessage),
+    Stats(StatsPaneMessage),
+    Calendar(CalendarMessage),
+    Settings(SettingsPaneMessage),
+    Log(LogMessage),
+    Event(Event),
 }
 
-trait PaneContent {
-    fn update(&mut self, message: Message);
-    fn view(&mut self, pane: Pane) -> Element<Message>;
+/// Changes of the app state panes may depend on.
+#[derive(Debug, Clone)]
+enum Event {
+    RefreshTargetDir(String),
+    PathChanged(String),
+    PreferencesChanged(Preferences),
+    ImportProfileChanged(ImportProfile),
+    PacketCriteriaChanged(PacketCriteria),
+    PreviewLoaded(String, Option<image::Handle>),
+    Imported(Result<ImportReport, rules::ImportError>),
+    /// The cabinet was locked, secrets have to be forgotten.
+    Locked,
 }
 
-impl PaneContent for PreviewPane {
-    fn update(&mut self, _message: Message) {}
-    fn view(&mut self, pane: Pane) -> Element<'_, Message> {
-        println!(
-            "event=preview_pane_opened image=\"{}\"",
-            &self.preview_image_path
-        );
-        Column::new()
-            .push(
-                Button::new(&mut self.close_button, Text::new("X").size(10))
-                    .padding(10)
-                    .style(style::Button::Destructive)
-                    .on_press(Message::ClosePreviewPane(pane)),
+#[derive(Debug, Clone)]
+enum DocPaneMessage {
+    FilterChanged(Filter),
+    OwnerFilterChanged(Option<String>),
+    Doc(DocumentId, DocMessage),
+    Viewed(String, String),
+    QueryEdited(String),
+    SearchSubmitted(String),
+    SuggestionPicked(String),
+    PinSearch(String, bool),
+    Searched(String, Result<BTreeMap<String, Option<Hit>>, QueryError>),
+    Restore(Session),
+    /// Lists the document by this filename and opens it for naming, e.g. when the app was
+    /// asked to open it.
+    EditDocument(String),
+    /// Closes the rename forms open, dropping what was typed in them, e.g. on Escape.
+    CancelEditing,
+}
+
+#[derive(Debug, Clone)]
+enum PreviewMessage {
+    PasswordRequired(String),
+    /// A `.cocoon` document, and whether it has a passphrase of its own.
+    PassphraseRequired(String, bool),
+    PasswordEdited(String),
+    RememberPasswordToggled(bool),
+    KeepDecryptedCopyToggled(bool),
+    Unlocked(String, Result<preview::Unlocked, PdfError>),
+    Rotate(String, u8),
+    Rotated(String, u8, Option<image::Handle>),
+    RotationSaved(String, Result<(), RotateError>),
+    SealPassphraseEdited(String),
+    SealConfirmationEdited(String),
+    Sealed(String, Result<String, VaultError>),
+    ShowPage(String, usize),
+    PageLoaded(String, usize, Option<image::Handle>),
+    PagesCounted(String, Option<usize>),
+    EditPages(String),
+    PagesLoaded(String, Result<Vec<preview::Thumbnail>, PdfError>),
+    TurnPage(usize),
+    RemovePage(usize),
+    /// Picks up a page, or puts the one picked up before this one.
+    MovePage(usize),
+    RemoveBlankPages,
+    CancelPageEdit,
+    PagesSaved(String, Result<(), PdfError>),
+    Redact(String),
+    /// Page of the document to draw boxes on, counted from 1.
+    RedactPage(String, usize),
+    RedactPageLoaded(String, Result<redact::Page, RedactError>),
+    RegionDrawn(Region),
+    UndoRegion,
+    ClearRegions,
+    CancelRedaction,
+    RedactedExported(String, Result<String, RedactError>),
+    /// Turns the spinner shown while a preview is decoded.
+    Spin,
+    /// The text of a document with no preview, see `office`.
+    TextLoaded(String, Option<String>),
+}
+
+#[derive(Debug, Clone)]
+enum CompareMessage {
+    ZoomChanged(u16),
+    DiffToggled(bool),
+    DiffLoaded(String, String, Option<image::Handle>),
+}
+
+#[derive(Debug, Clone)]
+enum TagsPaneMessage {
+    AddingEdited(String),
+    RemoveToggled(String),
+    Apply,
+    Undo,
+}
+
+#[derive(Debug, Clone)]
+enum TagManagerMessage {
+    Select(String),
+    NewNameEdited(String),
+    Rename,
+    Delete,
+    ConfirmDelete,
+}
+
+#[derive(Debug, Clone)]
+enum TagRulesMessage {
+    AddRule,
+    RemoveRule(usize),
+    /// Moves the rule on to the next field it can look at.
+    FieldToggled(usize),
+    PatternEdited(usize, String),
+    TagsEdited(usize, String),
+    Rerun,
+    Reran(Result<usize, catalog::CatalogError>),
+}
+
+#[derive(Debug, Clone)]
+enum FieldsMessage {
+    AddField,
+    RemoveField(usize),
+    NameEdited(usize, String),
+    /// Moves the field on to the next kind of value.
+    KindToggled(usize),
+    OptionsEdited(usize, String),
+}
+
+#[derive(Debug, Clone)]
+enum PeopleMessage {
+    AddPerson,
+    RemovePerson(usize),
+    NameEdited(usize, String),
+    /// Moves the person on to the next color.
+    ColorToggled(usize),
+}
+
+#[derive(Debug, Clone)]
+enum ChecklistsMessage {
+    NewEdited(String),
+    Add,
+    /// Shows the items of a checklist, or hides them if they are shown.
+    Open(usize),
+    Remove(usize),
+    /// Gives the documents ticked in the list to an item of the open checklist.
+    LinkSelected(usize),
+    Link(usize, Vec<String>),
+    Unlink(usize, String),
+}
+
+#[derive(Debug, Clone)]
+enum InstitutionsMessage {
+    Open(String),
+    Back,
+    WebsiteEdited(String),
+    AccountEdited(String),
+    PhoneEdited(String),
+    NotesEdited(String),
+    /// Moves the institution on to the next color, see `Contact::next_color`.
+    ColorToggled,
+    IconPathEdited(String),
+    /// Copies the image at the path typed into the assets, see `institutions::import_icon`.
+    SetIcon,
+    RemoveIcon,
+    AddExpected,
+    RemoveExpected(usize),
+    ExpectedTitleEdited(usize, String),
+    /// Moves the expected document on to the next frequency.
+    FrequencyToggled(usize),
+    SinceEdited(usize, String),
+    Save,
+    RenameEdited(String),
+    /// Files the documents of an institution under another name, `(from, to)`.
+    Rename(String, String),
+    Renamed(String, Result<usize, RenameError>),
+}
+
+#[derive(Debug, Clone)]
+enum BundleMessage {
+    PasswordEdited(String),
+    ConfirmationEdited(String),
+    StampToggled(bool),
+    StampRecipientEdited(String),
+    Export,
+    Exported(Result<String, BundleError>),
+}
+
+#[derive(Debug, Clone)]
+enum ImportReportMessage {
+    Export,
+    Exported(Result<String, ReportError>),
+}
+
+#[derive(Debug, Clone)]
+enum HealthMessage {
+    Fixing,
+    Indexing,
+    Fixed(Result<Vec<String>, catalog::CatalogError>),
+}
+
+#[derive(Debug, Clone)]
+enum PacketPaneMessage {
+    ItemToggled(usize, bool),
+    EncryptToggled(bool),
+    Exported(Result<String, packet::PacketError>),
+}
+
+#[derive(Debug, Clone)]
+enum SyncPaneMessage {
+    RemoteEdited(String),
+    Planned(Result<Vec<Change>, SyncError>),
+    Toggled(usize, bool),
+    Keep(usize, Side),
+    Synced(Result<usize, SyncError>),
+}
+
+#[derive(Debug, Clone)]
+enum BackupPaneMessage {
+    PassphraseEdited(String),
+    SnapshotSelected(usize),
+    RestoreDirEdited(String),
+    BackedUp(Result<backup::Created, BackupError>),
+    Restored(Result<usize, BackupError>),
+    ChecksumsWritten(Result<usize, ChecksumError>),
+    ChecksumsVerified(Result<checksums::Report, ChecksumError>),
+    SiteWritten(Result<usize, SiteError>),
+}
+
+#[derive(Debug, Clone)]
+enum CalendarMessage {
+    Exported(Result<usize, IcsError>),
+}
+
+#[derive(Debug, Clone)]
+enum StatsPaneMessage {
+    Computed(String, Stats),
+    Compacting,
+    Compacted(Result<compact::Compaction, catalog::CatalogError>),
+    EmptyingTrash,
+    TrashEmptied(Result<stats::Totals, trash::TrashError>),
+    Exported(Result<String, export::ExportError>),
+}
+
+#[derive(Debug, Clone)]
+enum SettingsPaneMessage {
+    OldPassphraseEdited(String),
+    NewPassphraseEdited(String),
+    ConfirmationEdited(String),
+    ChangingPassphrase,
+    PassphraseChanged(Result<usize, VaultError>),
+}
+
+#[derive(Debug, Clone)]
+enum LogMessage {
+    Refresh,
+}
+
+#[derive(Debug, Default)]
+struct DocPane {
+    scroll: scrollable::State,
+    filter: Filter,
+    /// Lists only the documents of this owner, on top of `filter`.
+    owner: Option<String>,
+    /// The people of the household, from the catalog.
+    owners: Vec<Owner>,
+    controls: Controls,
+    docs: Docs,
+    write_pdf_metadata: bool,
+    external_tools: Vec<ExternalTool>,
+    hooks: Vec<Hook>,
+    /// The custom fields of the cabinet, edited in the form of documents.
+    field_defs: Vec<FieldDef>,
+    /// Whether an archive folder is configured.
+    can_archive: bool,
+    query: String,
+    /// Results of the last search, `None` until there are some for `query`.
+    results: Option<SearchResults>,
+    /// Why `query` couldn't be parsed, the results of the last query that could are kept.
+    query_error: Option<QueryError>,
+    /// The cabinet the documents are in, its catalog remembers the searches.
+    dir: String,
+    searches: Vec<SavedSearch>,
+    search_input: text_input::State,
+    suggestion_buttons: Vec<(button::State, button::State)>,
+    hit_buttons: Vec<button::State>,
+    /// The document last picked, kept in view when the list is filtered or searched.
+    anchor: Option<DocumentId>,
+    /// How the documents of each institution are marked, by institution.
+    badges: BTreeMap<String, Badge>,
+}
+
+/// Documents found by the search box, see `search::Query`.
+#[derive(Debug, Default)]
+struct SearchResults {
+    query: String,
+    /// Found documents by filename, with the first hit in their text if there is one.
+    found: BTreeMap<String, Option<Hit>>,
+}
+
+impl SearchResults {
+    fn hit(&self, doc: &Document) -> Option<Hit> {
+        self.found.get(&doc.filename).cloned().flatten()
+    }
+}
+
+/// How the documents of an institution are marked at the start of their rows, see
+/// `Contact::color` and `Contact::icon`.
+#[derive(Debug, Clone, Default)]
+struct Badge {
+    color: Option<[f32; 3]>,
+    icon: Option<image::Handle>,
+}
+
+impl Badge {
+    fn of(dir: &Path, contact: &Contact) -> Self {
+        Badge {
+            color: contact.color.map(Label::color),
+            icon: institutions::icon(dir, contact).map(image::Handle::from_path),
+        }
+    }
+
+    /// The badges of the institutions in `contacts` that have a color or an icon.
+    fn load(dir: &Path, contacts: &BTreeMap<String, Contact>) -> BTreeMap<String, Badge> {
+        contacts
+            .iter()
+            .map(|(institution, contact)| (institution.clone(), Badge::of(dir, contact)))
+            .filter(|(_, badge)| badge.color.is_some() || badge.icon.is_some())
+            .collect()
+    }
+
+    /// The icon, or else a dot in the color. Without a badge, a blank as wide keeps the
+    /// rows lined up.
+    fn view<'a, M: 'a>(badge: Option<&Badge>) -> Element<'a, M> {
+        let side = Length::Units(24);
+        match badge {
+            Some(Badge {
+                icon: Some(icon), ..
+            }) => Image::new(icon.clone()).width(side).height(side).into(),
+            Some(Badge {
+                color: Some(color), ..
+            }) => Text::new("●")
+                .size(20)
+                .width(side)
+                .horizontal_alignment(HorizontalAlignment::Center)
+                .color(*color)
+                .into(),
+            _ => Space::with_width(side).into(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct PreviewPane {
+    preview_image_path: String,
+    /// Page shown for a search hit or paged to, the first one otherwise.
+    page: usize,
+    /// How many pages the PDF has, once counted.
+    pages: Option<usize>,
+    previous_page_button: button::State,
+    next_page_button: button::State,
+    /// Decoded image, `None` while it is being decoded or if it can't be.
+    handle: Option<image::Handle>,
+    failed: bool,
+    previous: Option<String>,
+    next: Option<String>,
+    previous_button: button::State,
+    next_button: button::State,
+    scroll_state: scrollable::State,
+    /// Set for password protected PDFs until they are unlocked.
+    locked: bool,
+    /// Locked `.cocoon` documents are opened with a passphrase rather than a PDF password,
+    /// the vault's unless they have one of their own.
+    sealed: bool,
+    own_passphrase: bool,
+    /// Whether the shown preview was decrypted, it is hidden again when the app locks.
+    unlocked: bool,
+    password: String,
+    remember_password: bool,
+    keep_decrypted_copy: bool,
+    unlock_status: String,
+    password_input: text_input::State,
+    unlock_button: button::State,
+    /// Clockwise quarter turns shown but not saved into the file yet.
+    turns: u8,
+    rotation_status: String,
+    rotate_left_button: button::State,
+    rotate_right_button: button::State,
+    save_rotation_button: button::State,
+    /// For sealing the document with a passphrase of its own.
+    seal_passphrase: String,
+    seal_confirmation: String,
+    seal_status: String,
+    seal_passphrase_input: text_input::State,
+    seal_confirmation_input: text_input::State,
+    seal_button: button::State,
+    page_editor: Option<PageEditor>,
+    edit_pages_button: button::State,
+    redactor: Option<Redactor>,
+    redact_button: button::State,
+    /// Shown instead of a preview for documents that have none but text, e.g. a `.docx`.
+    text: Option<String>,
+    open_externally_button: button::State,
+    /// Frame of the spinner shown while the preview is decoded.
+    spinner: usize,
+}
+
+/// Frames of the spinner of a preview being decoded.
+const SPINNER: [&str; 4] = ["|", "/", "-", "\\"];
+
+/// A page of the PDF in the `PageEditor`.
+#[derive(Debug)]
+struct EditedPage {
+    /// Number of the page in the document as saved, counted from 1.
+    number: u32,
+    thumbnail: preview::Thumbnail,
+    /// Clockwise quarter turns to add.
+    turns: u8,
+    removed: bool,
+    turn_button: button::State,
+    remove_button: button::State,
+    move_button: button::State,
+}
+
+/// The pages of a PDF being reordered, rotated and removed, before the document is saved
+/// again. Pages are moved by picking one up and clicking the one to put it before.
+#[derive(Debug, Default)]
+struct PageEditor {
+    /// `None` until the pages are read.
+    pages: Option<Vec<EditedPage>>,
+    moving: Option<usize>,
+    status: String,
+    remove_blank_button: button::State,
+    save_button: button::State,
+    cancel_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+impl PageEditor {
+    fn update(&mut self, message: PreviewMessage) {
+        let pages = match &mut self.pages {
+            Some(pages) => pages,
+            None => return,
+        };
+        match message {
+            PreviewMessage::TurnPage(index) => {
+                if let Some(page) = pages.get_mut(index) {
+                    page.turns = (page.turns + 1) % 4;
+                }
+            }
+            PreviewMessage::RemovePage(index) => {
+                if let Some(page) = pages.get_mut(index) {
+                    page.removed = !page.removed;
+                }
+            }
+            PreviewMessage::MovePage(index) => match self.moving.take() {
+                None => self.moving = Some(index),
+                Some(from) if from != index && from < pages.len() && index <= pages.len() => {
+                    let page = pages.remove(from);
+                    pages.insert(if from < index { index - 1 } else { index }, page);
+                }
+                Some(_) => {}
+            },
+            PreviewMessage::RemoveBlankPages => {
+                for page in pages.iter_mut().filter(|page| page.thumbnail.blank) {
+                    page.removed = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn view(&mut self, path: &str) -> Element<'_, Message> {
+        let pages = match &mut self.pages {
+            Some(pages) => pages,
+            None if self.status.is_empty() => return Text::new("Reading the pages...").into(),
+            None => return Text::new(&self.status).into(),
+        };
+        let kept: Vec<(u32, u8)> = pages
+            .iter()
+            .filter(|page| !page.removed)
+            .map(|page| (page.number, page.turns))
+            .collect();
+        let blank = pages.iter().filter(|page| page.thumbnail.blank).count();
+        let mut save = Button::new(&mut self.save_button, Text::new("Save").size(10))
+            .padding(10)
+            .style(style::Button::Update);
+        if !kept.is_empty() {
+            save = save.on_press(Message::SavePages(path.to_string(), kept));
+        }
+        let mut remove_blank = Button::new(
+            &mut self.remove_blank_button,
+            Text::new(format!("Remove {} blank pages", blank)).size(10),
+        )
+        .padding(10)
+        .style(style::Button::Destructive);
+        if blank > 0 {
+            remove_blank =
+                remove_blank.on_press(Message::PreviewPane(PreviewMessage::RemoveBlankPages));
+        }
+        let controls = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(save)
+            .push(
+                Button::new(&mut self.cancel_button, Text::new("Cancel").size(10))
+                    .padding(10)
+                    .style(style::Button::Cancel)
+                    .on_press(Message::PreviewPane(PreviewMessage::CancelPageEdit)),
+            )
+            .push(remove_blank)
+            .push(Text::new(&self.status).size(14));
+
+        let moving = self.moving;
+        let mut grid = Column::new().spacing(10);
+        let mut row = Row::new().spacing(10);
+        for (index, page) in pages.iter_mut().enumerate() {
+            let preview: Element<_> = match &page.thumbnail.handle {
+                Some(handle) if !page.removed => {
+                    Image::new(handle.clone()).width(Length::Units(120)).into()
+                }
+                _ => Text::new(format!("Page {}", page.number)).size(14).into(),
+            };
+            let mut caption = format!("Page {}", page.number);
+            if page.turns != 0 {
+                caption.push_str(&format!(", turned {}°", 90 * page.turns as u32));
+            }
+            if page.thumbnail.blank {
+                caption.push_str(", blank");
+            }
+            let move_label = match moving {
+                Some(from) if from == index => "Moving",
+                Some(_) => "Put before",
+                None => "Move",
+            };
+            let card = Column::new()
+                .spacing(5)
+                .width(Length::Units(140))
+                .push(
+                    Container::new(preview)
+                        .width(Length::Units(120))
+                        .height(Length::Units(160))
+                        .center_x()
+                        .center_y(),
+                )
+                .push(Text::new(caption).size(12).color(if page.removed {
+                    [0.8, 0.2, 0.2]
+                } else {
+                    [0.5, 0.5, 0.5]
+                }))
+                .push(
+                    Row::new()
+                        .spacing(5)
+                        .push(
+                            Button::new(&mut page.turn_button, Text::new("Rotate").size(10))
+                                .padding(5)
+                                .style(style::Button::Refresh)
+                                .on_press(Message::PreviewPane(PreviewMessage::TurnPage(index))),
+                        )
+                        .push(
+                            Button::new(
+                                &mut page.remove_button,
+                                Text::new(if page.removed { "Keep" } else { "Remove" }).size(10),
+                            )
+                            .padding(5)
+                            .style(style::Button::Destructive)
+                            .on_press(Message::PreviewPane(PreviewMessage::RemovePage(index))),
+                        )
+                        .push(
+                            Button::new(&mut page.move_button, Text::new(move_label).size(10))
+                                .padding(5)
+                                .style(style::Button::Filter {
+                                    selected: moving == Some(index),
+                                })
+                                .on_press(Message::PreviewPane(PreviewMessage::MovePage(index))),
+                        ),
+                );
+            row = row.push(card);
+            if index % 4 == 3 {
+                grid = grid.push(row);
+                row = Row::new().spacing(10);
+            }
+        }
+        grid = grid.push(row);
+        Column::new()
+            .spacing(10)
+            .push(controls)
+            .push(
+                Scrollable::new(&mut self.scroll_state)
+                    .push(grid)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .into()
+    }
+}
+
+/// Boxes drawn over the pages of a document, blacked out in a copy exported for sharing,
+/// e.g. with the account numbers hidden. The document itself is never changed.
+#[derive(Debug, Default)]
+struct Redactor {
+    /// `None` until the page is read.
+    page: Option<redact::Page>,
+    /// The page with its boxes blacked out.
+    handle: Option<image::Handle>,
+    /// Boxes by page number, counted from 1.
+    regions: BTreeMap<usize, Vec<Region>>,
+    status: String,
+    picker: redact::PickerState,
+    previous_page_button: button::State,
+    next_page_button: button::State,
+    undo_button: button::State,
+    clear_button: button::State,
+    export_button: button::State,
+    cancel_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+impl Redactor {
+    fn boxes(&self) -> &[Region] {
+        match &self.page {
+            Some(page) => self.regions.get(&page.number).map_or(&[], Vec::as_slice),
+            None => &[],
+        }
+    }
+
+    /// Shows the page again with its boxes.
+    fn redraw(&mut self) {
+        self.handle = self.page.as_ref().map(|page| page.handle(self.boxes()));
+    }
+
+    fn update(&mut self, message: PreviewMessage) {
+        match message {
+            PreviewMessage::RedactPageLoaded(_, Ok(page)) => {
+                self.page = Some(page);
+                self.status.clear();
+                self.redraw();
+            }
+            PreviewMessage::RedactPageLoaded(_, Err(RedactError::UnsupportedError)) => {
+                self.status = "Only scanned pages can be redacted.".to_string()
+            }
+            PreviewMessage::RedactPageLoaded(_, Err(_)) => {
+                self.status = "Couldn't read the page.".to_string()
+            }
+            PreviewMessage::RegionDrawn(region) => {
+                if let Some(page) = &self.page {
+                    self.regions.entry(page.number).or_default().push(region);
+                    self.redraw();
+                }
+            }
+            PreviewMessage::UndoRegion => {
+                if let Some(page) = &self.page {
+                    if let Some(boxes) = self.regions.get_mut(&page.number) {
+                        boxes.pop();
+                    }
+                    self.redraw();
+                }
+            }
+            PreviewMessage::ClearRegions => {
+                self.regions.clear();
+                self.redraw();
+            }
+            PreviewMessage::RedactedExported(_, exported) => {
+                self.status = match exported {
+                    Ok(copy) => format!("Exported a redacted copy to {}", copy),
+                    Err(RedactError::UnsupportedError) => {
+                        "Only documents of scanned pages can be redacted.".to_string()
+                    }
+                    Err(error) => format!("Couldn't export a redacted copy: {:?}", error),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn view(&mut self, path: &str) -> Element<'_, Message> {
+        let boxes: usize = self.regions.values().map(Vec::len).sum();
+        let undoable = !self.boxes().is_empty();
+        let mut export = Button::new(
+            &mut self.export_button,
+            Text::new("Export redacted copy").size(10),
+        )
+        .padding(10)
+        .style(style::Button::Update);
+        if boxes > 0 {
+            export = export.on_press(Message::ExportRedacted(
+                path.to_string(),
+                self.regions.clone(),
+            ));
+        }
+        let mut undo = Button::new(&mut self.undo_button, Text::new("Undo box").size(10))
+            .padding(10)
+            .style(style::Button::Refresh);
+        let mut clear = Button::new(&mut self.clear_button, Text::new("Clear").size(10))
+            .padding(10)
+            .style(style::Button::Destructive);
+        if undoable {
+            undo = undo.on_press(Message::PreviewPane(PreviewMessage::UndoRegion));
+        }
+        if boxes > 0 {
+            clear = clear.on_press(Message::PreviewPane(PreviewMessage::ClearRegions));
+        }
+        let mut controls = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(export)
+            .push(
+                Button::new(&mut self.cancel_button, Text::new("Cancel").size(10))
+                    .padding(10)
+                    .style(style::Button::Cancel)
+                    .on_press(Message::PreviewPane(PreviewMessage::CancelRedaction)),
+            )
+            .push(undo)
+            .push(clear);
+        if let Some(page) = &self.page {
+            if page.count > 1 {
+                let mut previous =
+                    Button::new(&mut self.previous_page_button, Text::new("<").size(10))
+                        .padding(10)
+                        .style(style::Button::Refresh);
+                if page.number > 1 {
+                    previous = previous.on_press(Message::PreviewPane(PreviewMessage::RedactPage(
+                        path.to_string(),
+                        page.number - 1,
+                    )));
+                }
+                let mut next = Button::new(&mut self.next_page_button, Text::new(">").size(10))
+                    .padding(10)
+                    .style(style::Button::Refresh);
+                if page.number < page.count {
+                    next = next.on_press(Message::PreviewPane(PreviewMessage::RedactPage(
+                        path.to_string(),
+                        page.number + 1,
+                    )));
+                }
+                controls = controls
+                    .push(previous)
+                    .push(Text::new(format!("Page {} of {}", page.number, page.count)).size(14))
+                    .push(next);
+            }
+        }
+        let status = match (&self.handle, self.status.is_empty()) {
+            (Some(_), true) => "Drag over what to black out.".to_string(),
+            (None, true) => "Reading the page...".to_string(),
+            _ => self.status.clone(),
+        };
+        controls = controls.push(Text::new(status).size(14));
+        let page: Element<_> = match &self.handle {
+            #[cfg(not(target_arch = "wasm32"))]
+            Some(handle) => redact::RegionPicker::new(&mut self.picker, handle.clone(), |region| {
+                Message::PreviewPane(PreviewMessage::RegionDrawn(region))
+            })
+            .into(),
+            #[cfg(target_arch = "wasm32")]
+            Some(handle) => Image::new(handle.clone()).into(),
+            None => Space::with_height(Length::Shrink).into(),
+        };
+        Column::new()
+            .spacing(10)
+            .push(controls)
+            .push(
+                Scrollable::new(&mut self.scroll_state)
+                    .push(
+                        Row::new()
+                            .push(page)
+                            .align_items(Align::Center)
+                            .width(Length::Fill),
+                    )
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .into()
+    }
+}
+
+/// Two documents side by side in one scrollable, so they scroll and zoom together.
+#[derive(Debug, Default)]
+struct ComparePane {
+    paths: [String; 2],
+    handles: [Option<image::Handle>; 2],
+    failed: [bool; 2],
+    /// Distance of the perceptual hashes, if both documents were indexed.
+    distance: Option<u32>,
+    /// Overlay of both documents, shown instead of them when `show_diff` is set.
+    diff: Option<image::Handle>,
+    show_diff: bool,
+    /// Percent of the default width.
+    zoom: u16,
+    zoom_in_button: button::State,
+    zoom_out_button: button::State,
+    diff_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+/// Zips the selected documents with a password, for people without the app.
+#[derive(Debug, Default)]
+struct BundlePane {
+    paths: Vec<String>,
+    /// Files going with the documents, by name.
+    extra: Vec<(String, Vec<u8>)>,
+    password: String,
+    confirmation: String,
+    /// Stamp every page of the copies with `Preferences::stamp_template`.
+    stamp: bool,
+    stamp_recipient: String,
+    exporting: bool,
+    status: String,
+    password_input: text_input::State,
+    confirmation_input: text_input::State,
+    stamp_recipient_input: text_input::State,
+    export_button: button::State,
+}
+
+/// What the last import or pull from the scanner brought in, see `report::ImportReport`.
+#[derive(Debug, Default)]
+struct ImportReportPane {
+    report: ImportReport,
+    status: String,
+    export_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+/// What the consistency check of the cabinet found on open, with ways to fix it, see
+/// `health::check`.
+#[derive(Debug, Default)]
+struct HealthPane {
+    health: Health,
+    status: String,
+    fix_missing_button: button::State,
+    index_button: button::State,
+    fix_changed_button: button::State,
+    check_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+/// Adds and removes tags on several documents at once.
+#[derive(Debug, Default)]
+struct TagsPane {
+    dir: String,
+    filenames: Vec<String>,
+    /// Tags all of the documents have.
+    common: Vec<String>,
+    /// Common tags to remove on apply.
+    removing: Vec<String>,
+    /// Comma separated tags to add on apply.
+    adding: String,
+    status: String,
+    /// The last applied edit, until it is undone.
+    last_edit: Option<TagEdit>,
+    tag_buttons: Vec<button::State>,
+    adding_input: text_input::State,
+    apply_button: button::State,
+    undo_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+/// Renames, merges and deletes tags across the cabinet.
+#[derive(Debug, Default)]
+struct TagManagerPane {
+    dir: String,
+    /// Tags in tree order with how many documents are tagged with them or below them.
+    tree: Vec<(String, usize)>,
+    selected: Option<String>,
+    new_name: String,
+    confirm_delete: bool,
+    status: String,
+    tag_buttons: Vec<button::State>,
+    new_name_input: text_input::State,
+    rename_button: button::State,
+    delete_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+#[derive(Debug, Default)]
+struct DuplicatesPane {
+    dir: String,
+    candidates: Vec<(String, String, u32)>,
+    compare_buttons: Vec<button::State>,
+    scroll_state: scrollable::State,
+}
+
+#[derive(Debug, Default)]
+struct RulesPane {
+    profile: ImportProfile,
+    source_dir_input: text_input::State,
+    add_button: button::State,
+    import_button: button::State,
+    rows: Vec<RuleRow>,
+    scroll_state: scrollable::State,
+    /// What the last import did.
+    status: String,
+}
+
+/// Edits the rules tagging the documents entering the cabinet, see `rules::TagRule`.
+#[derive(Debug, Default)]
+struct TagRulesPane {
+    dir: String,
+    rules: Vec<TagRule>,
+    status: String,
+    rows: Vec<TagRuleRow>,
+    add_button: button::State,
+    rerun_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+/// Edits the custom fields of the cabinet, see `fields::FieldDef`.
+#[derive(Debug, Default)]
+struct FieldsPane {
+    dir: String,
+    defs: Vec<FieldDef>,
+    status: String,
+    rows: Vec<FieldRow>,
+    add_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+/// Edits the people of the household documents can belong to, see `catalog::Owner`.
+#[derive(Debug, Default)]
+struct PeoplePane {
+    dir: String,
+    owners: Vec<Owner>,
+    status: String,
+    rows: Vec<PersonRow>,
+    add_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+/// Checklists of documents to gather, e.g. for a mortgage application, see `checklists`.
+#[derive(Debug, Default)]
+struct ChecklistsPane {
+    dir: String,
+    checklists: Vec<Checklist>,
+    /// The checklist whose items are shown.
+    open: Option<usize>,
+    new_checklist: String,
+    status: String,
+    new_input: text_input::State,
+    add_button: button::State,
+    /// Open and delete buttons, one pair per checklist.
+    checklist_buttons: Vec<(button::State, button::State)>,
+    item_rows: Vec<ChecklistItemRow>,
+    export_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+#[derive(Debug, Default)]
+struct ChecklistItemRow {
+    link_button: button::State,
+    /// One per document given.
+    unlink_buttons: Vec<button::State>,
+}
+
+/// The institutions documents are filed under, and a page per institution with how to
+/// reach it and everything it sent.
+#[derive(Debug, Default)]
+struct InstitutionsPane {
+    dir: String,
+    list: Vec<(String, usize)>,
+    contacts: BTreeMap<String, Contact>,
+    badges: BTreeMap<String, Badge>,
+    institution_buttons: Vec<button::State>,
+    /// The institution whose page is shown.
+    open: Option<String>,
+    contact: Contact,
+    expected: Vec<Expected>,
+    /// Periods the saved expected documents weren't filed for.
+    missing: Vec<Missing>,
+    documents: Vec<String>,
+    status: String,
+    expected_rows: Vec<ExpectedRow>,
+    add_expected_button: button::State,
+    document_buttons: Vec<button::State>,
+    back_button: button::State,
+    website_input: text_input::State,
+    account_input: text_input::State,
+    phone_input: text_input::State,
+    notes_input: text_input::State,
+    color_button: button::State,
+    /// Path of an image to make the icon of the open institution, as typed.
+    icon_path: String,
+    icon_input: text_input::State,
+    icon_button: button::State,
+    remove_icon_button: button::State,
+    save_button: button::State,
+    /// New name for the open institution, with what stands in the way of it.
+    rename_to: String,
+    rename_plan: Plan,
+    rename_input: text_input::State,
+    rename_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+/// The end of the log file, to find out why a file operation failed.
+#[derive(Debug, Default)]
+struct LogPane {
+    lines: Vec<String>,
+    refresh_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+#[derive(Debug, Default)]
+struct CalendarPane {
+    counts: MonthCounts,
+    /// Expected documents that weren't filed, see `InstitutionsPane`.
+    missing: Vec<Missing>,
+    /// What the last export did.
+    status: String,
+    export_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+#[derive(Debug, Default)]
+struct PacketPane {
+    dir: String,
+    criteria: PacketCriteria,
+    items: Vec<PacketItem>,
+    recipients: Vec<Recipient>,
+    /// Encrypt the packet to the recipients.
+    encrypt: bool,
+    status: String,
+    year_input: text_input::State,
+    start_month_input: text_input::State,
+    keywords_input: text_input::State,
+    export_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+#[derive(Debug, Default)]
+struct SyncPane {
+    /// The cabinet to sync the open one with.
+    remote: String,
+    rows: Vec<SyncRow>,
+    status: String,
+    remote_input: text_input::State,
+    compare_button: button::State,
+    apply_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+/// A change found by comparing the cabinets, applied if still checked.
+#[derive(Debug)]
+struct SyncRow {
+    change: Change,
+    apply: bool,
+}
+
+#[derive(Debug, Default)]
+struct BackupPane {
+    backup_dir: String,
+    /// Seals the snapshot if not empty, never saved.
+    passphrase: String,
+    /// Snapshots in the backup dir, newest first.
+    snapshots: Vec<String>,
+    selected: Option<usize>,
+    restore_dir: String,
+    status: String,
+    backup_dir_input: text_input::State,
+    passphrase_input: text_input::State,
+    backup_button: button::State,
+    restore_dir_input: text_input::State,
+    restore_button: button::State,
+    write_checksums_button: button::State,
+    verify_checksums_button: button::State,
+    write_site_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+/// Where the space of the cabinet goes.
+#[derive(Debug, Default)]
+struct StatsPane {
+    dir: String,
+    /// `None` until computed for `dir`.
+    stats: Option<Stats>,
+    /// What the last compaction did.
+    status: String,
+    refresh_button: button::State,
+    compact_button: button::State,
+    export_csv_button: button::State,
+    export_json_button: button::State,
+    empty_trash_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+#[derive(Debug, Default)]
+struct SettingsPane {
+    preferences: Preferences,
+    ocr_languages_input: text_input::State,
+    archive_dir_input: text_input::State,
+    /// Link phones open to share documents, computed with the preferences.
+    share_link: String,
+    renew_share_token_button: button::State,
+    drop_folder_input: text_input::State,
+    tool_rows: Vec<ToolRow>,
+    add_tool_button: button::State,
+    hook_rows: Vec<HookRow>,
+    add_hook_button: button::State,
+    recipient_rows: Vec<RecipientRow>,
+    add_recipient_button: button::State,
+    stamp_template_input: text_input::State,
+    /// Whether the open cabinet has a vault whose passphrase can be changed.
+    vault: bool,
+    old_passphrase: String,
+    new_passphrase: String,
+    confirmation: String,
+    /// Set while the containers are re-encrypted.
+    changing: bool,
+    passphrase_status: String,
+    old_passphrase_input: text_input::State,
+    new_passphrase_input: text_input::State,
+    confirmation_input: text_input::State,
+    change_passphrase_button: button::State,
+    scroll_state: scrollable::State,
+}
+
+#[derive(Debug, Default)]
+struct ToolRow {
+    name_input: text_input::State,
+    extensions_input: text_input::State,
+    command_input: text_input::State,
+    delete_button: button::State,
+}
+
+#[derive(Debug, Default)]
+struct HookRow {
+    event_button: button::State,
+    command_input: text_input::State,
+    delete_button: button::State,
+}
+
+#[derive(Debug, Default)]
+struct RecipientRow {
+    name_input: text_input::State,
+    key_input: text_input::State,
+    delete_button: button::State,
+}
+
+#[derive(Debug, Default)]
+struct RuleRow {
+    pattern_input: text_input::State,
+    institution_input: text_input::State,
+    name_input: text_input::State,
+    delete_button: button::State,
+}
+
+#[derive(Debug, Default)]
+struct ExpectedRow {
+    title_input: text_input::State,
+    frequency_button: button::State,
+    since_input: text_input::State,
+    delete_button: button::State,
+}
+
+#[derive(Debug, Default)]
+struct FieldRow {
+    name_input: text_input::State,
+    kind_button: button::State,
+    options_input: text_input::State,
+    delete_button: button::State,
+}
+
+#[derive(Debug, Default)]
+struct PersonRow {
+    name_input: text_input::State,
+    color_button: button::State,
+    delete_button: button::State,
+}
+
+#[derive(Debug, Default)]
+struct TagRuleRow {
+    field_button: button::State,
+    pattern_input: text_input::State,
+    tags_input: text_input::State,
+    delete_button: button::State,
+}
+
+trait PaneContent {
+    fn title(&self) -> String;
+    fn update(&mut self, message: PaneMessage);
+    fn view(&mut self, pane: Pane) -> Element<'_, Message>;
+
+    /// The documents shown by a compare pane.
+    fn compared(&self) -> Option<[String; 2]> {
+        None
+    }
+
+    /// The message closing this pane, `None` for panes that stay open.
+    fn close_message(&self, _pane: Pane) -> Option<Message> {
+        None
+    }
+
+    /// The documents listed before and after `path`, for panes that list documents.
+    fn neighbours(&self, _path: &str) -> (Option<String>, Option<String>) {
+        (None, None)
+    }
+
+    /// Paths of the documents listed, in order, for panes that list documents.
+    fn listed(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Where the list was left, for the pane listing the documents of the cabinet.
+    fn session(&self) -> Option<Session> {
+        None
+    }
+}
+
+/// A pane's content along with the widget state of its title bar.
+struct Panel {
+    content: Box<dyn PaneContent>,
+    close_button: button::State,
+    maximize_button: button::State,
+}
+
+impl Panel {
+    fn new(content: impl PaneContent + 'static) -> Self {
+        Panel {
+            content: Box::new(content),
+            close_button: Default::default(),
+            maximize_button: Default::default(),
+        }
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        self.content.update(message);
+    }
+
+    /// The pane with a title bar to drag it by, and buttons to maximize and close it.
+    fn view(&mut self, pane: Pane) -> pane_grid::Content<'_, Message> {
+        let Panel {
+            content,
+            close_button,
+            maximize_button,
+        } = self;
+        let title_bar = pane_grid::TitleBar::new(content.title())
+            .title_size(16)
+            .padding(10)
+            .controls(Self::controls(
+                content.close_message(pane),
+                close_button,
+                maximize_button,
+                Some((Message::MaximizePane(pane), "Maximize")),
+            ))
+            .style(style::TitleBar {});
+        pane_grid::Content::new(content.view(pane))
+            .title_bar(title_bar)
+            .style(style::Pane {})
+    }
+
+    /// The pane alone, in place of the whole grid, with a button sending `restore` if any.
+    fn view_maximized(
+        &mut self,
+        pane: Pane,
+        restore: Option<(Message, &str)>,
+    ) -> Element<'_, Message> {
+        let Panel {
+            content,
+            close_button,
+            maximize_button,
+        } = self;
+        let title_bar = Container::new(
+            Row::new()
+                .align_items(Align::Center)
+                .push(Text::new(content.title()).size(16).width(Length::Fill))
+                .push(Self::controls(
+                    content.close_message(pane),
+                    close_button,
+                    maximize_button,
+                    restore,
+                )),
+        )
+        .width(Length::Fill)
+        .padding(10)
+        .style(style::TitleBar {});
+        Container::new(Column::new().push(title_bar).push(content.view(pane)))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(style::Pane {})
+            .into()
+    }
+
+    fn controls<'a>(
+        close: Option<Message>,
+        close_button: &'a mut button::State,
+        maximize_button: &'a mut button::State,
+        maximize: Option<(Message, &str)>,
+    ) -> Element<'a, Message> {
+        let mut controls = Row::new().spacing(5);
+        if let Some((maximize, label)) = maximize {
+            controls = controls.push(
+                Button::new(maximize_button, Text::new(label).size(12))
+                    .padding(5)
+                    .style(style::Button::Refresh)
+                    .on_press(maximize),
+            );
+        }
+        if let Some(close) = close {
+            controls = controls.push(
+                Button::new(close_button, Text::new("X").size(12))
+                    .padding(5)
+                    .style(style::Button::Destructive)
+                    .on_press(close),
+            );
+        }
+        controls.into()
+    }
+}
+
+impl PreviewPane {
+    fn new(
+        path: String,
+        (previous, next): (Option<String>, Option<String>),
+        handle: Option<image::Handle>,
+    ) -> Self {
+        PreviewPane {
+            preview_image_path: path,
+            page: 1,
+            handle,
+            previous,
+            next,
+            ..Default::default()
+        }
+    }
+}
+
+impl PaneContent for PreviewPane {
+    fn title(&self) -> String {
+        "Preview".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::ClosePreviewPane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        match message {
+            PaneMessage::Event(Event::PreviewLoaded(path, handle))
+                if path == self.preview_image_path && self.handle.is_none() =>
+            {
+                self.failed = handle.is_none();
+                self.handle = handle;
+            }
+            PaneMessage::Preview(PreviewMessage::PasswordRequired(path))
+                if path == self.preview_image_path =>
+            {
+                self.locked = true
+            }
+            PaneMessage::Preview(PreviewMessage::PassphraseRequired(path, own_passphrase))
+                if path == self.preview_image_path =>
+            {
+                self.locked = true;
+                self.sealed = true;
+                self.own_passphrase = own_passphrase;
+            }
+            PaneMessage::Preview(PreviewMessage::PasswordEdited(password)) => {
+                self.password = password
+            }
+            PaneMessage::Preview(PreviewMessage::RememberPasswordToggled(remember)) => {
+                self.remember_password = remember
+            }
+            PaneMessage::Preview(PreviewMessage::KeepDecryptedCopyToggled(keep)) => {
+                self.keep_decrypted_copy = keep
+            }
+            PaneMessage::Preview(PreviewMessage::Unlocked(path, unlocked))
+                if path == self.preview_image_path =>
+            {
+                match unlocked {
+                    Ok(unlocked) => {
+                        self.locked = false;
+                        self.unlocked = true;
+                        self.password.clear();
+                        self.unlock_status = match (&unlocked.handle, &unlocked.copy) {
+                            (_, Some(copy)) => format!("Saved a decrypted copy as {}", copy),
+                            (None, None) => {
+                                "Unlocked. Only scanned PDFs can be previewed.".to_string()
+                            }
+                            (Some(_), None) => String::new(),
+                        };
+                        self.handle = unlocked.handle;
+                    }
+                    Err(PdfError::PasswordError) if self.sealed => {
+                        self.unlock_status = "Wrong passphrase.".to_string()
+                    }
+                    Err(PdfError::PasswordError) => {
+                        self.unlock_status = "Wrong password.".to_string()
+                    }
+                    Err(_) => {
+                        self.unlock_status = "This PDF's encryption isn't supported.".to_string()
+                    }
+                }
+            }
+            PaneMessage::Preview(PreviewMessage::Rotate(path, turns))
+                if path == self.preview_image_path =>
+            {
+                self.turns = turns;
+                self.rotation_status.clear();
+            }
+            PaneMessage::Preview(PreviewMessage::Rotated(path, turns, Some(handle)))
+                if path == self.preview_image_path && turns == self.turns =>
+            {
+                // Rotating shows the first page.
+                self.page = 1;
+                self.handle = Some(handle)
+            }
+            PaneMessage::Preview(PreviewMessage::TextLoaded(path, text))
+                if path == self.preview_image_path =>
+            {
+                self.text = text
+            }
+            PaneMessage::Preview(PreviewMessage::Spin) => {
+                self.spinner = (self.spinner + 1) % SPINNER.len()
+            }
+            PaneMessage::Preview(PreviewMessage::ShowPage(path, page))
+                if path == self.preview_image_path =>
+            {
+                self.page = page
+            }
+            PaneMessage::Preview(PreviewMessage::PageLoaded(path, page, Some(handle)))
+                if path == self.preview_image_path && page == self.page =>
+            {
+                self.failed = false;
+                self.handle = Some(handle);
+            }
+            PaneMessage::Preview(PreviewMessage::PagesCounted(path, pages))
+                if path == self.preview_image_path =>
+            {
+                self.pages = pages
+            }
+            PaneMessage::Preview(PreviewMessage::EditPages(path))
+                if path == self.preview_image_path =>
+            {
+                self.page_editor = Some(PageEditor::default())
+            }
+            PaneMessage::Preview(PreviewMessage::PagesLoaded(path, loaded))
+                if path == self.preview_image_path =>
+            {
+                if let Some(editor) = &mut self.page_editor {
+                    match loaded {
+                        Ok(thumbnails) => {
+                            editor.pages = Some(
+                                thumbnails
+                                    .into_iter()
+                                    .zip(1..)
+                                    .map(|(thumbnail, number)| EditedPage {
+                                        number,
+                                        thumbnail,
+                                        turns: 0,
+                                        removed: false,
+                                        turn_button: button::State::new(),
+                                        remove_button: button::State::new(),
+                                        move_button: button::State::new(),
+                                    })
+                                    .collect(),
+                            )
+                        }
+                        Err(_) => editor.status = "Couldn't read the pages.".to_string(),
+                    }
+                }
+            }
+            PaneMessage::Preview(PreviewMessage::CancelPageEdit) => self.page_editor = None,
+            PaneMessage::Preview(PreviewMessage::Redact(path))
+                if path == self.preview_image_path =>
+            {
+                self.redactor = Some(Redactor::default())
+            }
+            PaneMessage::Preview(PreviewMessage::CancelRedaction) => self.redactor = None,
+            PaneMessage::Preview(PreviewMessage::RedactPageLoaded(path, page))
+                if path == self.preview_image_path =>
+            {
+                if let Some(redactor) = &mut self.redactor {
+                    redactor.update(PreviewMessage::RedactPageLoaded(path, page))
+                }
+            }
+            PaneMessage::Preview(PreviewMessage::RedactedExported(path, exported))
+                if path == self.preview_image_path =>
+            {
+                if let Some(redactor) = &mut self.redactor {
+                    redactor.update(PreviewMessage::RedactedExported(path, exported))
+                }
+            }
+            PaneMessage::Preview(
+                message @ (PreviewMessage::RegionDrawn(_)
+                | PreviewMessage::UndoRegion
+                | PreviewMessage::ClearRegions),
+            ) => {
+                if let Some(redactor) = &mut self.redactor {
+                    redactor.update(message)
+                }
+            }
+            PaneMessage::Preview(PreviewMessage::PagesSaved(path, saved))
+                if path == self.preview_image_path =>
+            {
+                match saved {
+                    Ok(()) => {
+                        self.page_editor = None;
+                        self.page = 1;
+                        self.handle = None;
+                        self.failed = false;
+                        let versions = versions::list(Path::new(&self.preview_image_path)).len();
+                        self.rotation_status = format!(
+                            "Saved the pages, {} earlier versions are kept in .filecabinet.",
+                            versions
+                        );
+                    }
+                    Err(_) => {
+                        if let Some(editor) = &mut self.page_editor {
+                            editor.status = "Couldn't save the pages.".to_string()
+                        }
+                    }
+                }
+            }
+            PaneMessage::Preview(
+                message @ (PreviewMessage::TurnPage(_)
+                | PreviewMessage::RemovePage(_)
+                | PreviewMessage::MovePage(_)
+                | PreviewMessage::RemoveBlankPages),
+            ) => {
+                if let Some(editor) = &mut self.page_editor {
+                    editor.update(message)
+                }
+            }
+            PaneMessage::Preview(PreviewMessage::RotationSaved(path, saved))
+                if path == self.preview_image_path =>
+            {
+                match saved {
+                    Ok(()) => {
+                        self.turns = 0;
+                        self.rotation_status = "Saved the rotation.".to_string();
+                    }
+                    Err(_) => self.rotation_status = "Couldn't save the rotation.".to_string(),
+                }
+            }
+            PaneMessage::Event(Event::Locked) => {
+                self.password.clear();
+                self.seal_passphrase.clear();
+                self.seal_confirmation.clear();
+                if self.unlocked {
+                    self.unlocked = false;
+                    self.locked = true;
+                    self.handle = None;
+                    self.failed = true;
+                    self.unlock_status.clear();
+                }
+            }
+            PaneMessage::Preview(PreviewMessage::SealPassphraseEdited(s)) => {
+                self.seal_passphrase = s
+            }
+            PaneMessage::Preview(PreviewMessage::SealConfirmationEdited(s)) => {
+                self.seal_confirmation = s
+            }
+            PaneMessage::Preview(PreviewMessage::Sealed(path, sealed))
+                if path == self.preview_image_path =>
+            {
+                self.seal_passphrase.clear();
+                self.seal_confirmation.clear();
+                self.seal_status = match sealed {
+                    Ok(sealed) => format!("Sealed as {}", sealed),
+                    Err(error) => format!("Couldn't seal the document: {:?}", error),
+                };
+            }
+            _ => {}
+        }
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        debug!(event = "preview_pane_opened", image = %self.preview_image_path);
+        if let Some(editor) = &mut self.page_editor {
+            return Column::new()
+                .spacing(10)
+                .padding(10)
+                .push(Text::new(&self.preview_image_path))
+                .push(editor.view(&self.preview_image_path))
+                .into();
+        }
+        if let Some(redactor) = &mut self.redactor {
+            return Column::new()
+                .spacing(10)
+                .padding(10)
+                .push(Text::new(&self.preview_image_path))
+                .push(redactor.view(&self.preview_image_path))
+                .into();
+        }
+        let mut previous = Button::new(&mut self.previous_button, Text::new("<").size(10))
+            .padding(10)
+            .style(style::Button::Refresh);
+        if let Some(path) = &self.previous {
+            previous = previous.on_press(Message::ShowPreview(path.clone()));
+        }
+        let mut next = Button::new(&mut self.next_button, Text::new(">").size(10))
+            .padding(10)
+            .style(style::Button::Refresh);
+        if let Some(path) = &self.next {
+            next = next.on_press(Message::ShowPreview(path.clone()));
+        }
+        let mut controls = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(previous)
+            .push(next);
+        match self.pages {
+            Some(pages) if pages > 1 && !self.locked => {
+                let path = self.preview_image_path.clone();
+                let mut previous_page =
+                    Button::new(&mut self.previous_page_button, Text::new("Page -").size(10))
+                        .padding(10)
+                        .style(style::Button::Refresh);
+                if self.page > 1 {
+                    previous_page =
+                        previous_page.on_press(Message::ShowPage(path.clone(), self.page - 1));
+                }
+                let mut next_page =
+                    Button::new(&mut self.next_page_button, Text::new("Page +").size(10))
+                        .padding(10)
+                        .style(style::Button::Refresh);
+                if self.page < pages {
+                    next_page = next_page.on_press(Message::ShowPage(path, self.page + 1));
+                }
+                controls = controls
+                    .push(previous_page)
+                    .push(Text::new(format!("Page {} of {}", self.page, pages)).size(14))
+                    .push(next_page);
+            }
+            _ if self.page > 1 => {
+                controls = controls.push(Text::new(format!("Hit on page {}", self.page)).size(14));
+            }
+            _ => {}
+        }
+        if self.handle.is_some() {
+            let path = self.preview_image_path.clone();
+            let rotate = |state, label, turns| {
+                Button::new(state, Text::new(label).size(10))
+                    .padding(10)
+                    .style(style::Button::Refresh)
+                    .on_press(Message::PreviewPane(PreviewMessage::Rotate(
+                        path.clone(),
+                        turns,
+                    )))
+            };
+            controls = controls
+                .push(rotate(
+                    &mut self.rotate_left_button,
+                    "Rotate left",
+                    (self.turns + 3) % 4,
+                ))
+                .push(rotate(
+                    &mut self.rotate_right_button,
+                    "Rotate right",
+                    (self.turns + 1) % 4,
+                ));
+            // PDFs have their pages rotated in the page editor.
+            let savable = matches!(
+                utils::extension(&self.preview_image_path).as_str(),
+                "jpg" | "jpeg" | "png"
+            );
+            if self.turns != 0 && savable {
+                controls = controls.push(
+                    Button::new(
+                        &mut self.save_rotation_button,
+                        Text::new("Save rotation").size(10),
+                    )
+                    .padding(10)
+                    .style(style::Button::Refresh)
+                    .on_press(Message::SaveRotation(
+                        self.preview_image_path.clone(),
+                        self.turns,
+                    )),
+                );
+            }
+        }
+        // Encrypted PDFs would have to be saved decrypted.
+        if utils::extension(&self.preview_image_path) == "pdf" && !self.locked && !self.unlocked {
+            controls = controls.push(
+                Button::new(
+                    &mut self.edit_pages_button,
+                    Text::new("Edit pages").size(10),
+                )
+                .padding(10)
+                .style(style::Button::Refresh)
+                .on_press(Message::PreviewPane(PreviewMessage::EditPages(
+                    self.preview_image_path.clone(),
+                ))),
+            );
+        }
+        // Decrypted documents would leave the vault in the copy.
+        if self.handle.is_some() && !self.locked && !self.unlocked {
+            controls = controls.push(
+                Button::new(&mut self.redact_button, Text::new("Redact").size(10))
+                    .padding(10)
+                    .style(style::Button::Refresh)
+                    .on_press(Message::PreviewPane(PreviewMessage::Redact(
+                        self.preview_image_path.clone(),
+                    ))),
+            );
+        }
+        controls = controls.push(Text::new(&self.rotation_status).size(14));
+        let institution = OptDoc::new(&self.preview_image_path).institution;
+        let image: Element<_> = match (&self.handle, self.failed) {
+            (Some(handle), _) => Image::new(handle.clone()).into(),
+            (None, false) => Text::new(format!("{} Loading...", SPINNER[self.spinner]))
+                .size(20)
+                .color([0.5, 0.5, 0.5])
+                .into(),
+            (None, true) if self.locked && self.sealed => {
+                let mut unlock = Button::new(&mut self.unlock_button, Text::new("Open"))
+                    .padding(10)
+                    .style(style::Button::Refresh);
+                if !self.password.is_empty() {
+                    unlock = unlock.on_press(Message::OpenSealed(
+                        self.preview_image_path.clone(),
+                        self.password.clone(),
+                    ));
+                }
+                let (message, placeholder) = if self.own_passphrase {
+                    (
+                        "This document is sealed with a passphrase of its own.",
+                        "Its passphrase",
+                    )
+                } else {
+                    ("This document is in the vault.", "Vault passphrase")
+                };
+                Column::new()
+                    .spacing(10)
+                    .max_width(400)
+                    .push(Text::new(message))
+                    .push(
+                        TextInput::new(
+                            &mut self.password_input,
+                            placeholder,
+                            &self.password,
+                            |s| Message::PreviewPane(PreviewMessage::PasswordEdited(s)),
+                        )
+                        .password()
+                        .padding(10),
+                    )
+                    .push(unlock)
+                    .push(
+                        Text::new(&self.unlock_status)
+                            .size(14)
+                            .color([0.8, 0.2, 0.2]),
+                    )
+                    .into()
+            }
+            (None, true) if self.locked => {
+                let mut unlock = Button::new(&mut self.unlock_button, Text::new("Unlock"))
+                    .padding(10)
+                    .style(style::Button::Refresh);
+                if !self.password.is_empty() {
+                    unlock = unlock.on_press(Message::Unlock(
+                        self.preview_image_path.clone(),
+                        self.password.clone(),
+                        self.remember_password && institution.is_some(),
+                        self.keep_decrypted_copy,
+                    ));
+                }
+                let mut form = Column::new()
+                    .spacing(10)
+                    .max_width(400)
+                    .push(Text::new("This PDF is password protected."))
+                    .push(
+                        TextInput::new(&mut self.password_input, "Password", &self.password, |s| {
+                            Message::PreviewPane(PreviewMessage::PasswordEdited(s))
+                        })
+                        .password()
+                        .padding(10),
+                    );
+                if let Some(institution) = &institution {
+                    form = form.push(Checkbox::new(
+                        self.remember_password,
+                        format!("Remember the password for {}", institution),
+                        |remember| {
+                            Message::PreviewPane(PreviewMessage::RememberPasswordToggled(remember))
+                        },
+                    ));
+                }
+                form.push(Checkbox::new(
+                    self.keep_decrypted_copy,
+                    "Save a decrypted copy into the cabinet",
+                    |keep| Message::PreviewPane(PreviewMessage::KeepDecryptedCopyToggled(keep)),
+                ))
+                .push(unlock)
+                .push(
+                    Text::new(&self.unlock_status)
+                        .size(14)
+                        .color([0.8, 0.2, 0.2]),
+                )
+                .into()
+            }
+            (None, true) if !self.unlock_status.is_empty() => Text::new(&self.unlock_status).into(),
+            (None, true) => {
+                let open = Button::new(
+                    &mut self.open_externally_button,
+                    Text::new("Open externally"),
+                )
+                .on_press(Message::OpenExternally(self.preview_image_path.clone()))
+                .padding(10)
+                .style(style::Button::Refresh);
+                match &self.text {
+                    Some(text) => Column::new()
+                        .spacing(10)
+                        .push(open)
+                        .push(Text::new(text.as_str()).size(16))
+                        .into(),
+                    None => Column::new()
+                        .spacing(10)
+                        .push(Text::new("This file can't be previewed."))
+                        .push(open)
+                        .into(),
+                }
+            }
+        };
+        let mut column = Column::new()
+            .push(controls)
+            .push(Text::new(&self.preview_image_path))
+            .push(
+                Scrollable::new(&mut self.scroll_state)
+                    .push(
+                        Row::new()
+                            .push(image)
+                            .align_items(Align::Center)
+                            .width(Length::Fill),
+                    )
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            );
+        // Sensitive documents, e.g. a will, can be kept from opening with the vault
+        // passphrase.
+        if self.handle.is_some() && utils::extension(&self.preview_image_path) != "cocoon" {
+            let checked = check_passphrase(&self.seal_passphrase, &self.seal_confirmation);
+            let mut seal = Button::new(&mut self.seal_button, Text::new("Seal").size(10))
+                .padding(10)
+                .style(style::Button::Refresh);
+            if checked.is_ok() {
+                seal = seal.on_press(Message::Seal(
+                    self.preview_image_path.clone(),
+                    self.seal_passphrase.clone(),
+                ));
+            }
+            let status = match checked {
+                Err(error) if !self.seal_passphrase.is_empty() => error,
+                _ => self.seal_status.clone(),
+            };
+            column = column.push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(
+                        TextInput::new(
+                            &mut self.seal_passphrase_input,
+                            "Own passphrase",
+                            &self.seal_passphrase,
+                            |s| Message::PreviewPane(PreviewMessage::SealPassphraseEdited(s)),
+                        )
+                        .password()
+                        .padding(10),
+                    )
+                    .push(
+                        TextInput::new(
+                            &mut self.seal_confirmation_input,
+                            "Repeat it",
+                            &self.seal_confirmation,
+                            |s| Message::PreviewPane(PreviewMessage::SealConfirmationEdited(s)),
+                        )
+                        .password()
+                        .padding(10),
+                    )
+                    .push(seal)
+                    .push(Text::new(status).size(14)),
+            );
+        }
+        column.padding(10).into()
+    }
+}
+
+impl ComparePane {
+    fn new(paths: [String; 2], handles: [Option<image::Handle>; 2], distance: Option<u32>) -> Self {
+        ComparePane {
+            paths,
+            handles,
+            distance,
+            zoom: 100,
+            ..Default::default()
+        }
+    }
+}
+
+impl TagsPane {
+    fn new(dir: &str, paths: &[String]) -> Self {
+        let filenames: Vec<String> = paths
+            .iter()
+            .filter_map(|path| Some(Path::new(path).file_name()?.to_string_lossy().to_string()))
+            .collect();
+        TagsPane {
+            dir: dir.to_string(),
+            common: tags::common(&Catalog::load(Path::new(dir)), &filenames),
+            filenames,
+            ..Default::default()
+        }
+    }
+}
+
+impl PaneContent for TagsPane {
+    fn title(&self) -> String {
+        "Tags".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::CloseTagsPane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        match message {
+            PaneMessage::Tags(TagsPaneMessage::AddingEdited(adding)) => self.adding = adding,
+            PaneMessage::Tags(TagsPaneMessage::RemoveToggled(tag)) => {
+                match self.removing.iter().position(|t| *t == tag) {
+                    Some(i) => {
+                        self.removing.remove(i);
+                    }
+                    None => self.removing.push(tag),
+                }
+            }
+            PaneMessage::Tags(TagsPaneMessage::Apply) => {
+                let dir = Path::new(&self.dir);
+                let adding = tags::parse(&self.adding);
+                match tags::edit(dir, &self.filenames, &adding, &self.removing) {
+                    Ok(edit) => {
+                        self.status = format!(
+                            "Added {} and removed {} tags on {} documents.",
+                            edit.added.len(),
+                            edit.removed.len(),
+                            edit.len()
+                        );
+                        self.last_edit = Some(edit);
+                        self.adding.clear();
+                        self.removing.clear();
+                    }
+                    Err(error) => self.status = format!("Couldn't save the tags: {:?}", error),
+                }
+                self.common = tags::common(&Catalog::load(dir), &self.filenames);
+            }
+            PaneMessage::Tags(TagsPaneMessage::Undo) => {
+                let dir = Path::new(&self.dir);
+                if let Some(edit) = self.last_edit.take() {
+                    self.status = match tags::undo(dir, &edit) {
+                        Ok(()) => "Undone.".to_string(),
+                        Err(error) => {
+                            self.last_edit = Some(edit);
+                            format!("Couldn't undo: {:?}", error)
+                        }
+                    };
+                }
+                self.common = tags::common(&Catalog::load(dir), &self.filenames);
+            }
+            // The documents may no longer be there.
+            PaneMessage::Event(Event::PathChanged(dir)) => {
+                *self = TagsPane {
+                    dir,
+                    ..Default::default()
+                }
+            }
+            // Tags may have been renamed in the tag manager.
+            PaneMessage::Event(Event::RefreshTargetDir(_)) => {
+                self.common = tags::common(&Catalog::load(Path::new(&self.dir)), &self.filenames)
+            }
+            _ => {}
+        }
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        if self.filenames.is_empty() {
+            return Container::new(
+                Text::new("Tick documents in the list to tag them.")
+                    .size(14)
+                    .color([0.5, 0.5, 0.5]),
+            )
+            .padding(10)
+            .into();
+        }
+        if self.tag_buttons.len() < self.common.len() {
+            self.tag_buttons
+                .resize_with(self.common.len(), Default::default);
+        }
+        let removing = &self.removing;
+        let common = self.common.iter().zip(self.tag_buttons.iter_mut()).fold(
+            Row::new().spacing(10),
+            |row, (tag, state)| {
+                let removed = removing.contains(tag);
+                row.push(
+                    Button::new(
+                        state,
+                        Text::new(if removed {
+                            format!("{} (remove)", tag)
+                        } else {
+                            format!("{} ×", tag)
+                        })
+                        .size(14),
+                    )
+                    .padding(6)
+                    .style(style::Button::Filter { selected: removed })
+                    .on_press(Message::TagsPane(
+                        TagsPaneMessage::RemoveToggled(tag.clone()),
+                    )),
+                )
+            },
+        );
+        let common: Element<_> = if self.common.is_empty() {
+            Text::new("The documents have no tags in common.")
+                .size(14)
+                .color([0.5, 0.5, 0.5])
+                .into()
+        } else {
+            common.into()
+        };
+        let mut apply = Button::new(&mut self.apply_button, Text::new("Apply"))
+            .padding(10)
+            .style(style::Button::Update);
+        if !tags::parse(&self.adding).is_empty() || !self.removing.is_empty() {
+            apply = apply.on_press(Message::TagsPane(TagsPaneMessage::Apply));
+        }
+        let mut actions = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(apply);
+        if self.last_edit.is_some() {
+            actions = actions.push(
+                Button::new(&mut self.undo_button, Text::new("Undo"))
+                    .padding(10)
+                    .style(style::Button::Cancel)
+                    .on_press(Message::TagsPane(TagsPaneMessage::Undo)),
+            );
+        }
+        actions = actions.push(Text::new(&self.status).size(14));
+        Scrollable::new(&mut self.scroll_state)
+            .padding(10)
+            .spacing(10)
+            .push(Text::new(format!("{} documents", self.filenames.len())).size(16))
+            .push(common)
+            .push(
+                TextInput::new(
+                    &mut self.adding_input,
+                    "Tags to add, separated by commas",
+                    &self.adding,
+                    |adding| Message::TagsPane(TagsPaneMessage::AddingEdited(adding)),
+                )
+                .on_submit(Message::TagsPane(TagsPaneMessage::Apply))
+                .padding(10),
+            )
+            .push(actions)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+}
+
+impl TagManagerPane {
+    fn new(dir: &str) -> Self {
+        TagManagerPane {
+            dir: dir.to_string(),
+            tree: tags::tree(&Catalog::load(Path::new(dir))),
+            ..Default::default()
+        }
+    }
+}
+
+impl PaneContent for TagManagerPane {
+    fn title(&self) -> String {
+        "Manage tags".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::CloseTagManagerPane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        match message {
+            PaneMessage::Event(Event::RefreshTargetDir(dir) | Event::PathChanged(dir)) => {
+                let status = std::mem::take(&mut self.status);
+                *self = TagManagerPane::new(&dir);
+                // Keeps what the last change did.
+                self.status = status;
+            }
+            PaneMessage::TagManager(TagManagerMessage::Select(tag)) => {
+                self.new_name = tag.clone();
+                self.selected = Some(tag);
+                self.confirm_delete = false;
+                self.status.clear();
+            }
+            PaneMessage::TagManager(TagManagerMessage::NewNameEdited(name)) => self.new_name = name,
+            PaneMessage::TagManager(TagManagerMessage::Rename) => {
+                if let Some(tag) = &self.selected {
+                    let to = tags::normalize(&self.new_name);
+                    self.status = match tags::rename(Path::new(&self.dir), tag, &to) {
+                        Ok(changed) => {
+                            format!("Renamed {} to {} on {} documents.", tag, to, changed)
+                        }
+                        Err(error) => format!("Couldn't rename {}: {:?}", tag, error),
+                    };
+                }
+            }
+            PaneMessage::TagManager(TagManagerMessage::Delete) => self.confirm_delete = true,
+            PaneMessage::TagManager(TagManagerMessage::ConfirmDelete) => {
+                if let Some(tag) = &self.selected {
+                    self.status = match tags::delete(Path::new(&self.dir), tag) {
+                        Ok(changed) => format!("Deleted {} from {} documents.", tag, changed),
+                        Err(error) => format!("Couldn't delete {}: {:?}", tag, error),
+                    };
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let mut column = Column::new().spacing(10);
+        if self.tree.is_empty() {
+            column = column.push(
+                Text::new("No tags yet. Tick documents and press tags to add some.")
+                    .size(14)
+                    .color([0.5, 0.5, 0.5]),
+            );
+        }
+        let selected = self.selected.clone();
+        let selected_count = self
+            .tree
+            .iter()
+            .find(|(tag, _)| Some(tag) == selected.as_ref())
+            .map(|(_, count)| *count);
+        if let (Some(tag), Some(count)) = (&selected, selected_count) {
+            let to = tags::normalize(&self.new_name);
+            let merge = to != *tag && self.tree.iter().any(|(t, _)| *t == to);
+            let mut rename = Button::new(
+                &mut self.rename_button,
+                Text::new(if merge { "Merge" } else { "Rename" }),
+            )
+            .padding(10)
+            .style(style::Button::Update);
+            if !to.is_empty() && to != *tag {
+                rename = rename.on_press(Message::TagManagerPane(TagManagerMessage::Rename));
+            }
+            let delete = if self.confirm_delete {
+                Button::new(
+                    &mut self.delete_button,
+                    Text::new(format!("Delete from {} documents?", count)),
+                )
+                .on_press(Message::TagManagerPane(TagManagerMessage::ConfirmDelete))
+            } else {
+                Button::new(&mut self.delete_button, Text::new("Delete"))
+                    .on_press(Message::TagManagerPane(TagManagerMessage::Delete))
+            }
+            .padding(10)
+            .style(style::Button::Destructive);
+            column = column.push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(
+                        TextInput::new(&mut self.new_name_input, tag, &self.new_name, |name| {
+                            Message::TagManagerPane(TagManagerMessage::NewNameEdited(name))
+                        })
+                        .on_submit(Message::TagManagerPane(TagManagerMessage::Rename))
+                        .padding(10),
+                    )
+                    .push(rename)
+                    .push(delete),
+            );
+        }
+        column = column.push(Text::new(&self.status).size(14));
+        if self.tag_buttons.len() < self.tree.len() {
+            self.tag_buttons
+                .resize_with(self.tree.len(), Default::default);
+        }
+        for ((tag, count), state) in self.tree.iter().zip(self.tag_buttons.iter_mut()) {
+            let depth = tag.matches('/').count() as u16;
+            let name = tag.rsplit('/').next().unwrap_or(tag);
+            column = column.push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(Space::with_width(Length::Units(depth * 20)))
+                    .push(
+                        Button::new(state, Text::new(name).size(16))
+                            .padding(4)
+                            .style(style::Button::Filter {
+                                selected: Some(tag) == selected.as_ref(),
+                            })
+                            .on_press(Message::TagManagerPane(TagManagerMessage::Select(
+                                tag.clone(),
+                            ))),
+                    )
+                    .push(Text::new(count.to_string()).size(14).color([0.5, 0.5, 0.5])),
+            );
+        }
+        Scrollable::new(&mut self.scroll_state)
+            .padding(10)
+            .push(column)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+}
+
+impl DuplicatesPane {
+    fn new(dir: &str) -> Self {
+        let mut pane = DuplicatesPane::default();
+        pane.load(dir);
+        pane
+    }
+
+    fn load(&mut self, dir: &str) {
+        self.dir = dir.to_string();
+        self.candidates = Catalog::load(Path::new(dir)).duplicate_candidates();
+        self.compare_buttons
+            .resize_with(self.candidates.len(), Default::default);
+    }
+}
+
+impl PaneContent for DuplicatesPane {
+    fn title(&self) -> String {
+        "Duplicates".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::CloseDuplicatesPane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        if let PaneMessage::Event(Event::RefreshTargetDir(path) | Event::PathChanged(path)) =
+            message
+        {
+            self.load(&path)
+        }
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let dir = Path::new(&self.dir);
+        let rows = self
+            .candidates
+            .iter()
+            .zip(self.compare_buttons.iter_mut())
+            .fold(
+                Column::new().spacing(10),
+                |column, ((a, b, distance), button)| {
+                    let compare = Message::Compare(
+                        dir.join(a).to_string_lossy().to_string(),
+                        dir.join(b).to_string_lossy().to_string(),
+                    );
+                    column.push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Align::Center)
+                            .push(
+                                Column::new()
+                                    .width(Length::Fill)
+                                    .push(Text::new(a).size(16))
+                                    .push(Text::new(b).size(16)),
+                            )
+                            .push(Text::new(format!("{} bits apart", distance)).size(14))
+                            .push(
+                                Button::new(button, Text::new("Compare"))
+                                    .on_press(compare)
+                                    .padding(10)
+                                    .style(style::Button::Refresh),
+                            ),
+                    )
+                },
+            );
+        let content: Element<_> = if self.candidates.is_empty() {
+            Text::new("No look-alike documents. Run \"index\" to check new ones.")
+                .size(16)
+                .color([0.5, 0.5, 0.5])
+                .into()
+        } else {
+            rows.into()
+        };
+        Scrollable::new(&mut self.scroll_state)
+            .push(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding(10)
+            .into()
+    }
+}
+
+impl PaneContent for ComparePane {
+    fn title(&self) -> String {
+        "Compare".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::CloseComparePane(pane))
+    }
+
+    fn compared(&self) -> Option<[String; 2]> {
+        Some(self.paths.clone())
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        match message {
+            PaneMessage::Event(Event::PreviewLoaded(path, handle)) => {
+                for i in 0..2 {
+                    if self.paths[i] == path && self.handles[i].is_none() {
+                        self.failed[i] = handle.is_none();
+                        self.handles[i] = handle.clone();
+                    }
+                }
+            }
+            PaneMessage::Compare(CompareMessage::ZoomChanged(zoom)) => self.zoom = zoom,
+            PaneMessage::Compare(CompareMessage::DiffToggled(show_diff)) => {
+                self.show_diff = show_diff
+            }
+            PaneMessage::Compare(CompareMessage::DiffLoaded(a, b, handle))
+                if [&a, &b] == [&self.paths[0], &self.paths[1]] =>
+            {
+                self.diff = handle
+            }
+            _ => {}
+        }
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let width = 400 * self.zoom / 100;
+        let side = |path: &str, handle: &Option<image::Handle>, failed: bool| {
+            let image: Element<_> = match (handle, failed) {
+                (Some(handle), _) => Image::new(handle.clone())
+                    .width(Length::Units(width))
+                    .into(),
+                (None, false) => Text::new("Loading...").into(),
+                (None, true) => Text::new("This file can't be previewed.").into(),
+            };
+            Column::new()
+                .spacing(10)
+                .width(Length::Units(width))
+                .push(Text::new(path).size(14))
+                .push(image)
+        };
+        let mut zoom_out = Button::new(&mut self.zoom_out_button, Text::new("-").size(12))
+            .padding(10)
+            .style(style::Button::Refresh);
+        if self.zoom > 25 {
+            zoom_out = zoom_out.on_press(Message::ComparePane(CompareMessage::ZoomChanged(
+                self.zoom - 25,
+            )));
+        }
+        let mut zoom_in = Button::new(&mut self.zoom_in_button, Text::new("+").size(12))
+            .padding(10)
+            .style(style::Button::Refresh);
+        if self.zoom < 400 {
+            zoom_in = zoom_in.on_press(Message::ComparePane(CompareMessage::ZoomChanged(
+                self.zoom + 25,
+            )));
+        }
+
+        let diff_button = Button::new(
+            &mut self.diff_button,
+            Text::new(if self.show_diff {
+                "Side by side"
+            } else {
+                "Show differences"
+            })
+            .size(12),
+        )
+        .padding(10)
+        .style(style::Button::Refresh)
+        .on_press(Message::ComparePane(CompareMessage::DiffToggled(
+            !self.show_diff,
+        )));
+        let similarity = Text::new(match self.distance {
+            Some(distance) if distance <= similarity::DUPLICATE_DISTANCE => {
+                format!("Look alike ({} bits apart)", distance)
+            }
+            Some(distance) => format!("Look different ({} bits apart)", distance),
+            None => String::new(),
+        })
+        .size(14)
+        .color([0.3, 0.3, 0.3]);
+        let content: Element<_> = if self.show_diff {
+            match &self.diff {
+                Some(diff) => Image::new(diff.clone())
+                    .width(Length::Units(width * 2))
+                    .into(),
+                None => Text::new("Comparing...").into(),
+            }
+        } else {
+            Row::new()
+                .spacing(10)
+                .push(side(&self.paths[0], &self.handles[0], self.failed[0]))
+                .push(side(&self.paths[1], &self.handles[1], self.failed[1]))
+                .into()
+        };
+
+        Column::new()
+            .spacing(10)
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(zoom_out)
+                    .push(Text::new(format!("{}%", self.zoom)).size(16))
+                    .push(zoom_in)
+                    .push(diff_button)
+                    .push(similarity),
+            )
+            .push(
+                Scrollable::new(&mut self.scroll_state)
+                    .push(content)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .padding(10)
+            .into()
+    }
+}
+
+impl RulesPane {
+    fn new(profile: ImportProfile) -> Self {
+        let mut pane = RulesPane::default();
+        pane.set_profile(profile);
+        pane
+    }
+
+    fn set_profile(&mut self, profile: ImportProfile) {
+        self.rows.resize_with(profile.rules.len(), Default::default);
+        self.profile = profile;
+    }
+}
+
+impl PaneContent for RulesPane {
+    fn title(&self) -> String {
+        "Import rules".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::CloseRulesPane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        match message {
+            PaneMessage::Event(Event::ImportProfileChanged(profile)) => self.set_profile(profile),
+            PaneMessage::Event(Event::Imported(Ok(imported))) => {
+                self.status = format!("Imported {} documents.", imported.imported.len());
+                let dropped: usize = imported.blank_pages.iter().map(|(_, pages)| pages).sum();
+                if dropped > 0 {
+                    self.status.push_str(&format!(
+                        " Dropped {} blank pages from {} of them.",
+                        dropped,
+                        imported.blank_pages.len()
+                    ));
+                }
+            }
+            PaneMessage::Event(Event::Imported(Err(rules::ImportError::SourceError))) => {
+                self.status = "There is no folder to import from.".to_string()
+            }
+            _ => {}
+        }
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let RulesPane {
+            profile,
+            source_dir_input,
+            add_button,
+            import_button,
+            rows,
+            scroll_state,
+            status,
+        } = self;
+
+        let rules = profile.rules.iter().zip(rows.iter_mut()).enumerate().fold(
+            Column::new().spacing(10),
+            |column, (i, (rule, row))| {
+                column.push(
+                    Row::new()
+                        .spacing(10)
+                        .align_items(Align::Center)
+                        .push(
+                            TextInput::new(
+                                &mut row.pattern_input,
+                                "eStmt_*.pdf",
+                                &rule.pattern,
+                                move |s| Message::RuleMessage(RuleMessage::PatternEdited(i, s)),
+                            )
+                            .padding(10),
+                        )
+                        .push(
+                            TextInput::new(
+                                &mut row.institution_input,
+                                "Institution",
+                                &rule.institution,
+                                move |s| Message::RuleMessage(RuleMessage::InstitutionEdited(i, s)),
+                            )
+                            .padding(10),
+                        )
+                        .push(
+                            TextInput::new(&mut row.name_input, "Title", &rule.name, move |s| {
+                                Message::RuleMessage(RuleMessage::NameEdited(i, s))
+                            })
+                            .padding(10),
+                        )
+                        .push(
+                            Button::new(&mut row.delete_button, delete_icon())
+                                .on_press(Message::RuleMessage(RuleMessage::RemoveRule(i)))
+                                .padding(10)
+                                .style(style::Button::Icon),
+                        ),
+                )
+            },
+        );
+
+        Column::new()
+            .spacing(10)
+            .push(
+                TextInput::new(
+                    source_dir_input,
+                    "Folder to import from",
+                    &profile.source_dir,
+                    |s| Message::RuleMessage(RuleMessage::SourceDirChanged(s)),
+                )
+                .padding(10),
+            )
+            .push(
+                Scrollable::new(scroll_state)
+                    .push(rules)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(
+                        Button::new(add_button, Text::new("Add rule"))
+                            .on_press(Message::RuleMessage(RuleMessage::AddRule))
+                            .padding(10)
+                            .style(style::Button::Update),
+                    )
+                    .push(
+                        Button::new(import_button, Text::new("Import now"))
+                            .on_press(Message::Import)
+                            .padding(10)
+                            .style(style::Button::Refresh),
+                    )
+                    .push(Text::new(status.as_str()).size(14)),
+            )
+            .padding(10)
+            .into()
+    }
+}
+
+impl TagRulesPane {
+    fn new(dir: &str) -> Self {
+        let rules = rules::load_tag_rules(Path::new(dir));
+        TagRulesPane {
+            dir: dir.to_string(),
+            rows: rules.iter().map(|_| Default::default()).collect(),
+            rules,
+            ..Default::default()
+        }
+    }
+
+    fn save(&mut self) {
+        self.rows.resize_with(self.rules.len(), Default::default);
+        if let Err(error) = rules::save_tag_rules(Path::new(&self.dir), &self.rules) {
+            warn!(event = "tag_rules_save_failed", ?error);
+            self.status = "Couldn't save the rules.".to_string();
+        }
+    }
+}
+
+impl PaneContent for TagRulesPane {
+    fn title(&self) -> String {
+        "Auto-tag rules".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::CloseTagRulesPane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        let message = match message {
+            PaneMessage::Event(Event::PathChanged(dir)) => {
+                *self = TagRulesPane::new(&dir);
+                return;
+            }
+            PaneMessage::TagRules(message) => message,
+            _ => return,
+        };
+        match message {
+            TagRulesMessage::AddRule => self.rules.push(TagRule::default()),
+            TagRulesMessage::RemoveRule(i) if i < self.rules.len() => {
+                self.rules.remove(i);
+            }
+            TagRulesMessage::FieldToggled(i) => {
+                if let Some(rule) = self.rules.get_mut(i) {
+                    rule.field = rule.field.next();
+                }
+            }
+            TagRulesMessage::PatternEdited(i, s) => {
+                if let Some(rule) = self.rules.get_mut(i) {
+                    rule.pattern = s;
+                }
+            }
+            TagRulesMessage::TagsEdited(i, s) => {
+                if let Some(rule) = self.rules.get_mut(i) {
+                    rule.tags = s;
+                }
+            }
+            TagRulesMessage::Rerun => {
+                self.status = "Running the rules...".to_string();
+                return;
+            }
+            TagRulesMessage::Reran(Ok(changed)) => {
+                self.status = format!("Tagged {} documents.", changed);
+                return;
+            }
+            TagRulesMessage::Reran(Err(error)) => {
+                self.status = format!("Couldn't run the rules: {:?}", error);
+                return;
+            }
+            _ => return,
+        }
+        self.save();
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let TagRulesPane {
+            rules,
+            status,
+            rows,
+            add_button,
+            rerun_button,
+            scroll_state,
+            ..
+        } = self;
+
+        let mut column = Column::new().spacing(10).push(
+            Text::new(
+                "New documents matching a rule get its tags. Click the field to change \
+                 what a rule looks at.",
+            )
+            .size(14)
+            .color([0.5, 0.5, 0.5]),
+        );
+        for (i, (rule, row)) in rules.iter().zip(rows.iter_mut()).enumerate() {
+            column = column.push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(
+                        Button::new(&mut row.field_button, Text::new(rule.field.name()).size(16))
+                            .on_press(Message::TagRulesPane(TagRulesMessage::FieldToggled(i)))
+                            .width(Length::Units(110))
+                            .padding(10)
+                            .style(style::Button::Filter { selected: false }),
+                    )
+                    .push(
+                        TextInput::new(
+                            &mut row.pattern_input,
+                            rule.field.placeholder(),
+                            &rule.pattern,
+                            move |s| Message::TagRulesPane(TagRulesMessage::PatternEdited(i, s)),
+                        )
+                        .padding(10),
+                    )
+                    .push(
+                        TextInput::new(
+                            &mut row.tags_input,
+                            "finance/tax, urgent",
+                            &rule.tags,
+                            move |s| Message::TagRulesPane(TagRulesMessage::TagsEdited(i, s)),
+                        )
+                        .padding(10),
+                    )
+                    .push(
+                        Button::new(&mut row.delete_button, delete_icon())
+                            .on_press(Message::TagRulesPane(TagRulesMessage::RemoveRule(i)))
+                            .padding(10)
+                            .style(style::Button::Icon),
+                    ),
+            );
+        }
+
+        let mut rerun = Button::new(rerun_button, Text::new("Re-run rules on all documents"))
+            .padding(10)
+            .style(style::Button::Refresh);
+        if !rules.is_empty() {
+            rerun = rerun.on_press(Message::TagRulesPane(TagRulesMessage::Rerun));
+        }
+        Column::new()
+            .spacing(10)
+            .push(
+                Scrollable::new(scroll_state)
+                    .push(column)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .push(Text::new(status.as_str()).size(14))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(
+                        Button::new(add_button, Text::new("Add rule"))
+                            .on_press(Message::TagRulesPane(TagRulesMessage::AddRule))
+                            .padding(10)
+                            .style(style::Button::Update),
+                    )
+                    .push(rerun),
+            )
+            .padding(10)
+            .into()
+    }
+}
+
+impl FieldsPane {
+    fn new(dir: &str) -> Self {
+        let defs = fields::load(Path::new(dir));
+        FieldsPane {
+            dir: dir.to_string(),
+            rows: defs.iter().map(|_| Default::default()).collect(),
+            defs,
+            ..Default::default()
+        }
+    }
+
+    fn save(&mut self) {
+        self.rows.resize_with(self.defs.len(), Default::default);
+        self.status = match fields::save(Path::new(&self.dir), &self.defs) {
+            Ok(()) => String::new(),
+            Err(error) => {
+                warn!(event = "fields_save_failed", ?error);
+                "Couldn't save the fields.".to_string()
+            }
+        };
+    }
+}
+
+impl PaneContent for FieldsPane {
+    fn title(&self) -> String {
+        "Custom fields".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::CloseFieldsPane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        let message = match message {
+            PaneMessage::Event(Event::PathChanged(dir)) => {
+                *self = FieldsPane::new(&dir);
+                return;
+            }
+            PaneMessage::Fields(message) => message,
+            _ => return,
+        };
+        match message {
+            FieldsMessage::AddField => self.defs.push(FieldDef::default()),
+            FieldsMessage::RemoveField(i) if i < self.defs.len() => {
+                self.defs.remove(i);
+            }
+            FieldsMessage::NameEdited(i, s) => {
+                if let Some(def) = self.defs.get_mut(i) {
+                    def.name = s;
+                }
+            }
+            FieldsMessage::KindToggled(i) => {
+                if let Some(def) = self.defs.get_mut(i) {
+                    def.kind = def.kind.next();
+                }
+            }
+            FieldsMessage::OptionsEdited(i, s) => {
+                if let Some(def) = self.defs.get_mut(i) {
+                    def.options = s;
+                }
+            }
+            _ => return,
+        }
+        self.save();
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let FieldsPane {
+            defs,
+            status,
+            rows,
+            add_button,
+            scroll_state,
+            ..
+        } = self;
+
+        let mut column = Column::new().spacing(10).push(
+            Text::new(
+                "Every document of the cabinet can have these, filled in its form. Search \
+                 them by name, e.g. \"policy number:123\". Click the kind to change it.",
+            )
+            .size(14)
+            .color([0.5, 0.5, 0.5]),
+        );
+        for (i, (def, row)) in defs.iter().zip(rows.iter_mut()).enumerate() {
+            let mut field = Row::new()
+                .spacing(10)
+                .align_items(Align::Center)
+                .push(
+                    TextInput::new(&mut row.name_input, "Policy number", &def.name, move |s| {
+                        Message::FieldsPane(FieldsMessage::NameEdited(i, s))
+                    })
+                    .padding(10),
+                )
+                .push(
+                    Button::new(&mut row.kind_button, Text::new(def.kind.name()).size(16))
+                        .on_press(Message::FieldsPane(FieldsMessage::KindToggled(i)))
+                        .width(Length::Units(110))
+                        .padding(10)
+                        .style(style::Button::Filter { selected: false }),
+                );
+            if def.kind == FieldKind::Choice {
+                field = field.push(
+                    TextInput::new(
+                        &mut row.options_input,
+                        "Open, Closed",
+                        &def.options,
+                        move |s| Message::FieldsPane(FieldsMessage::OptionsEdited(i, s)),
+                    )
+                    .padding(10),
+                );
+            }
+            column = column.push(
+                field.push(
+                    Button::new(&mut row.delete_button, delete_icon())
+                        .on_press(Message::FieldsPane(FieldsMessage::RemoveField(i)))
+                        .padding(10)
+                        .style(style::Button::Icon),
+                ),
+            );
+        }
+
+        Column::new()
+            .spacing(10)
+            .push(
+                Scrollable::new(scroll_state)
+                    .push(column)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .push(Text::new(status.as_str()).size(14))
+            .push(
+                Button::new(add_button, Text::new("Add field"))
+                    .on_press(Message::FieldsPane(FieldsMessage::AddField))
+                    .padding(10)
+                    .style(style::Button::Update),
+            )
+            .padding(10)
+            .into()
+    }
+}
+
+impl PeoplePane {
+    fn new(dir: &str) -> Self {
+        let owners = Catalog::load(Path::new(dir)).owners;
+        PeoplePane {
+            dir: dir.to_string(),
+            rows: owners.iter().map(|_| Default::default()).collect(),
+            owners,
+            ..Default::default()
+        }
+    }
+
+    /// Saves the owners, moving the documents of `moved` along, see `catalog::record_owners`.
+    fn save(&mut self, moved: Option<(String, Option<String>)>) {
+        self.rows.resize_with(self.owners.len(), Default::default);
+        self.status = match catalog::record_owners(Path::new(&self.dir), self.owners.clone(), moved)
+        {
+            Ok(()) => String::new(),
+            Err(error) => {
+                warn!(event = "owners_save_failed", ?error);
+                "Couldn't save the people.".to_string()
+            }
+        };
+    }
+}
+
+impl PaneContent for PeoplePane {
+    fn title(&self) -> String {
+        "People".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::ClosePeoplePane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        let message = match message {
+            PaneMessage::Event(Event::PathChanged(dir)) => {
+                *self = PeoplePane::new(&dir);
+                return;
+            }
+            PaneMessage::People(message) => message,
+            _ => return,
+        };
+        let moved = match message {
+            PeopleMessage::AddPerson => {
+                let color = Label::ALL[self.owners.len() % Label::ALL.len()];
+                self.owners.push(Owner {
+                    name: String::new(),
+                    color,
+                });
+                None
+            }
+            PeopleMessage::RemovePerson(i) if i < self.owners.len() => {
+                Some((self.owners.remove(i).name, None))
+            }
+            PeopleMessage::NameEdited(i, s) => {
+                // Documents of two people with the same name couldn't be told apart again.
+                if self.owners.iter().any(|owner| owner.name == s) {
+                    self.status = format!("Someone is named {} already.", s);
+                    return;
+                }
+                match self.owners.get_mut(i) {
+                    Some(owner) => Some((std::mem::replace(&mut owner.name, s.clone()), Some(s))),
+                    None => return,
+                }
+            }
+            PeopleMessage::ColorToggled(i) => match self.owners.get_mut(i) {
+                Some(owner) => {
+                    owner.color = owner.color.next();
+                    None
+                }
+                None => return,
+            },
+            _ => return,
+        };
+        self.save(moved);
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let PeoplePane {
+            owners,
+            status,
+            rows,
+            add_button,
+            scroll_state,
+            ..
+        } = self;
+
+        let mut column = Column::new().spacing(10).push(
+            Text::new(
+                "Documents can belong to one of these people, click the name on a row to \
+                 pass it on. Renaming someone keeps their documents, click the color to \
+                 change it.",
+            )
+            .size(14)
+            .color([0.5, 0.5, 0.5]),
+        );
+        for (i, (owner, row)) in owners.iter().zip(rows.iter_mut()).enumerate() {
+            column = column.push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(
+                        Button::new(&mut row.color_button, Text::new(""))
+                            .on_press(Message::PeoplePane(PeopleMessage::ColorToggled(i)))
+                            .width(Length::Units(24))
+                            .height(Length::Units(24))
+                            .style(style::Button::Label {
+                                color: Some(owner.color.color()),
+                                selected: false,
+                            }),
+                    )
+                    .push(
+                        TextInput::new(&mut row.name_input, "Name", &owner.name, move |s| {
+                            Message::PeoplePane(PeopleMessage::NameEdited(i, s))
+                        })
+                        .padding(10),
+                    )
+                    .push(
+                        Button::new(&mut row.delete_button, delete_icon())
+                            .on_press(Message::PeoplePane(PeopleMessage::RemovePerson(i)))
+                            .padding(10)
+                            .style(style::Button::Icon),
+                    ),
+            );
+        }
+
+        Column::new()
+            .spacing(10)
+            .push(
+                Scrollable::new(scroll_state)
+                    .push(column)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .push(Text::new(status.as_str()).size(14))
+            .push(
+                Button::new(add_button, Text::new("Add person"))
+                    .on_press(Message::PeoplePane(PeopleMessage::AddPerson))
+                    .padding(10)
+                    .style(style::Button::Update),
+            )
+            .padding(10)
+            .into()
+    }
+}
+
+impl ChecklistsPane {
+    fn new(dir: &str) -> Self {
+        ChecklistsPane {
+            dir: dir.to_string(),
+            checklists: checklists::load(Path::new(dir)),
+            ..Default::default()
+        }
+    }
+
+    fn save(&mut self) {
+        self.status = match checklists::save(Path::new(&self.dir), &self.checklists) {
+            Ok(()) => String::new(),
+            Err(error) => {
+                warn!(event = "checklists_save_failed", ?error);
+                "Couldn't save the checklists.".to_string()
+            }
+        };
+    }
+}
+
+impl PaneContent for ChecklistsPane {
+    fn title(&self) -> String {
+        "Checklists".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::CloseChecklistsPane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        let message = match message {
+            PaneMessage::Event(Event::PathChanged(dir)) => {
+                *self = ChecklistsPane::new(&dir);
+                return;
+            }
+            // Documents given may have been renamed or deleted.
+            PaneMessage::Event(Event::RefreshTargetDir(_)) => {
+                self.checklists = checklists::load(Path::new(&self.dir));
+                return;
+            }
+            PaneMessage::Checklists(message) => message,
+            _ => return,
+        };
+        match message {
+            ChecklistsMessage::NewEdited(s) => {
+                self.new_checklist = s;
+                return;
+            }
+            ChecklistsMessage::Add => match Checklist::parse(&self.new_checklist) {
+                Some(checklist) => {
+                    self.checklists.push(checklist);
+                    self.open = Some(self.checklists.len() - 1);
+                    self.new_checklist.clear();
+                }
+                None => {
+                    self.status =
+                        "Type a name, a colon and the documents, separated by commas.".to_string();
+                    return;
+                }
+            },
+            ChecklistsMessage::Open(i) => {
+                self.open = if self.open == Some(i) { None } else { Some(i) };
+                return;
+            }
+            ChecklistsMessage::Remove(i) if i < self.checklists.len() => {
+                self.checklists.remove(i);
+                self.open = None;
+            }
+            ChecklistsMessage::Link(item, filenames) => {
+                let item = self
+                    .open
+                    .and_then(|open| self.checklists.get_mut(open))
+                    .and_then(|checklist| checklist.items.get_mut(item));
+                match item {
+                    Some(item) if !filenames.is_empty() => {
+                        for filename in filenames {
+                            if !item.documents.contains(&filename) {
+                                item.documents.push(filename);
+                            }
+                        }
+                    }
+                    _ => {
+                        self.status = "Tick documents in the list first.".to_string();
+                        return;
+                    }
+                }
+            }
+            ChecklistsMessage::Unlink(item, filename) => {
+                if let Some(item) = self
+                    .open
+                    .and_then(|open| self.checklists.get_mut(open))
+                    .and_then(|checklist| checklist.items.get_mut(item))
+                {
+                    item.documents.retain(|f| *f != filename);
+                }
+            }
+            _ => return,
+        }
+        self.save();
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let ChecklistsPane {
+            dir,
+            checklists,
+            open,
+            new_checklist,
+            status,
+            new_input,
+            add_button,
+            checklist_buttons,
+            item_rows,
+            export_button,
+            scroll_state,
+        } = self;
+
+        let mut column = Column::new().spacing(10);
+        checklist_buttons.resize_with(checklists.len(), Default::default);
+        for (i, (checklist, (open_button, delete_button))) in checklists
+            .iter()
+            .zip(checklist_buttons.iter_mut())
+            .enumerate()
+        {
+            let (done, total) = checklist.progress();
+            let label = if checklist.is_complete() {
+                format!("{}  complete", checklist.name)
+            } else {
+                format!("{}  {}/{}", checklist.name, done, total)
+            };
+            column = column.push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(
+                        Button::new(open_button, Text::new(label).size(16))
+                            .on_press(Message::ChecklistsPane(ChecklistsMessage::Open(i)))
+                            .width(Length::Fill)
+                            .padding(10)
+                            .style(style::Button::Filter {
+                                selected: *open == Some(i),
+                            }),
+                    )
+                    .push(
+                        Button::new(delete_button, delete_icon())
+                            .on_press(Message::ChecklistsPane(ChecklistsMessage::Remove(i)))
+                            .padding(10)
+                            .style(style::Button::Icon),
+                    ),
+            );
+        }
+
+        let checklist = open.and_then(|open| checklists.get(open));
+        let mut items = Column::new().spacing(10);
+        let mut export = Button::new(export_button, Text::new("Bundle with its documents"))
+            .padding(10)
+            .style(style::Button::Update);
+        if let Some(checklist) = checklist {
+            item_rows.resize_with(checklist.items.len(), Default::default);
+            for (i, (item, row)) in checklist.items.iter().zip(item_rows.iter_mut()).enumerate() {
+                let color = if item.is_complete() {
+                    [0.2, 0.6, 0.2]
+                } else {
+                    [0.5, 0.5, 0.5]
+                };
+                let mut documents = Column::new().spacing(5);
+                row.unlink_buttons
+                    .resize_with(item.documents.len(), Default::default);
+                for (filename, unlink_button) in item.documents.iter().zip(&mut row.unlink_buttons)
+                {
+                    let gone = !Path::new(dir.as_str()).join(filename).exists();
+                    documents = documents.push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Align::Center)
+                            .push(
+                                Text::new(if gone {
+                                    format!("{} (gone)", filename)
+                                } else {
+                                    filename.clone()
+                                })
+                                .size(14)
+                                .width(Length::Fill),
+                            )
+                            .push(
+                                Button::new(unlink_button, delete_icon())
+                                    .on_press(Message::ChecklistsPane(ChecklistsMessage::Unlink(
+                                        i,
+                                        filename.clone(),
+                                    )))
+                                    .padding(4)
+                                    .style(style::Button::Icon),
+                            ),
+                    );
+                }
+                items = items.push(
+                    Row::new()
+                        .spacing(10)
+                        .align_items(Align::Center)
+                        .push(
+                            Text::new(format!(
+                                "{}/{} {}",
+                                item.documents.len().min(item.count),
+                                item.count,
+                                item.description
+                            ))
+                            .size(16)
+                            .color(color)
+                            .width(Length::Fill),
+                        )
+                        .push(
+                            Button::new(&mut row.link_button, Text::new("Add ticked").size(14))
+                                .on_press(Message::ChecklistsPane(ChecklistsMessage::LinkSelected(
+                                    i,
+                                )))
+                                .padding(6)
+                                .style(style::Button::Refresh),
+                        ),
+                );
+                items = items.push(documents);
+            }
+            let paths: Vec<String> = checklist
+                .documents()
+                .iter()
+                .map(|filename| Path::new(dir.as_str()).join(filename))
+                .filter(|path| path.exists())
+                .map(|path| path.to_string_lossy().to_string())
+                .collect();
+            if !paths.is_empty() {
+                let summary = (
+                    "checklist.txt".to_string(),
+                    checklist.summary().into_bytes(),
+                );
+                export = export.on_press(Message::OpenBundlePane(paths, vec![summary]));
+            }
+        }
+
+        let mut add = Button::new(add_button, Text::new("Add checklist"))
+            .padding(10)
+            .style(style::Button::Update);
+        if !new_checklist.trim().is_empty() {
+            add = add.on_press(Message::ChecklistsPane(ChecklistsMessage::Add));
+        }
+        let mut content = Column::new().spacing(20).push(column).push(items);
+        if checklist.is_some() {
+            content = content.push(export);
+        }
+        Column::new()
+            .spacing(10)
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(
+                        TextInput::new(
+                            new_input,
+                            "Mortgage application: 2 payslips, 3 bank statements, ID",
+                            new_checklist,
+                            |s| Message::ChecklistsPane(ChecklistsMessage::NewEdited(s)),
+                        )
+                        .on_submit(Message::ChecklistsPane(ChecklistsMessage::Add))
+                        .padding(10),
+                    )
+                    .push(add),
+            )
+            .push(Text::new(status.as_str()).size(14))
+            .push(
+                Scrollable::new(scroll_state)
+                    .push(content)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .padding(10)
+            .into()
+    }
+}
+
+impl InstitutionsPane {
+    fn new(dir: &str) -> Self {
+        let mut pane = InstitutionsPane {
+            dir: dir.to_string(),
+            ..Default::default()
+        };
+        pane.refresh();
+        pane
+    }
+
+    /// The name documents would be filed under for `name` as typed, if it can be one and
+    /// isn't the current one.
+    fn rename_target(&self, name: &str) -> Option<String> {
+        let to = utils::to_camelcase(name);
+        let valid = !to.is_empty() && !to.contains(['_', '/', '\\', '.']);
+        (valid && self.open.as_deref() != Some(to.as_str())).then_some(to)
+    }
+
+    /// Lists the institutions again, and the documents of the open one.
+    fn refresh(&mut self) {
+        let dir = Path::new(&self.dir);
+        self.list = institutions::list(dir);
+        self.contacts = Catalog::load(dir).institutions;
+        self.badges = Badge::load(dir, &self.contacts);
+        if let Some(institution) = &self.open {
+            self.documents = institutions::documents(dir, institution);
+            let (_, expected) = institutions::details(dir, institution);
+            self.missing = calendar::missing(
+                &self.documents,
+                &BTreeMap::from([(institution.clone(), expected)]),
+                Utc::now().naive_utc().date(),
+            );
+        }
+    }
+}
+
+impl PaneContent for InstitutionsPane {
+    fn title(&self) -> String {
+        match &self.open {
+            Some(institution) => institution.clone(),
+            None => "Institutions".to_string(),
+        }
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::CloseInstitutionsPane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        match message {
+            PaneMessage::Event(Event::PathChanged(dir)) => *self = InstitutionsPane::new(&dir),
+            PaneMessage::Event(Event::RefreshTargetDir(_)) => self.refresh(),
+            PaneMessage::Institutions(InstitutionsMessage::Open(institution)) => {
+                (self.contact, self.expected) =
+                    institutions::details(Path::new(&self.dir), &institution);
+                self.expected_rows
+                    .resize_with(self.expected.len(), Default::default);
+                self.open = Some(institution);
+                self.refresh();
+                self.status.clear();
+                self.scroll_state = Default::default();
+            }
+            PaneMessage::Institutions(InstitutionsMessage::Back) => {
+                self.open = None;
+                self.rename_to.clear();
+                self.rename_plan = Plan::default();
+                self.refresh();
+            }
+            PaneMessage::Institutions(InstitutionsMessage::RenameEdited(s)) => {
+                self.rename_plan = match (&self.open, self.rename_target(&s)) {
+                    (Some(institution), Some(to)) => {
+                        let dir = Path::new(&self.dir);
+                        renames::plan(dir, institutions::renames(dir, institution, &to))
+                    }
+                    _ => Plan::default(),
+                };
+                self.rename_to = s;
+            }
+            PaneMessage::Institutions(InstitutionsMessage::Renamed(to, renamed)) => match renamed {
+                Ok(count) => {
+                    self.rename_to.clear();
+                    self.rename_plan = Plan::default();
+                    self.update(PaneMessage::Institutions(InstitutionsMessage::Open(to)));
+                    self.status = format!("Renamed {} documents.", count);
+                }
+                Err(error) => self.status = format!("Couldn't rename: {:?}", error),
+            },
+            PaneMessage::Institutions(InstitutionsMessage::WebsiteEdited(s)) => {
+                self.contact.website = s
+            }
+            PaneMessage::Institutions(InstitutionsMessage::AccountEdited(s)) => {
+                self.contact.account = s
+            }
+            PaneMessage::Institutions(InstitutionsMessage::PhoneEdited(s)) => {
+                self.contact.phone = s
+            }
+            PaneMessage::Institutions(InstitutionsMessage::NotesEdited(s)) => {
+                self.contact.notes = s
+            }
+            PaneMessage::Institutions(InstitutionsMessage::ColorToggled) => {
+                self.contact.color = self.contact.next_color()
+            }
+            PaneMessage::Institutions(InstitutionsMessage::IconPathEdited(s)) => self.icon_path = s,
+            PaneMessage::Institutions(InstitutionsMessage::SetIcon) => {
+                if let Some(institution) = &self.open {
+                    let from = Path::new(self.icon_path.trim());
+                    self.status =
+                        match institutions::import_icon(Path::new(&self.dir), institution, from) {
+                            Ok(icon) => {
+                                self.contact.icon = icon;
+                                self.icon_path.clear();
+                                "Save to keep the icon.".to_string()
+                            }
+                            Err(IconError::FormatError) => {
+                                "Icons are PNG or JPEG images.".to_string()
+                            }
+                            Err(error) => format!("Couldn't copy the icon: {:?}", error),
+                        };
+                }
+            }
+            PaneMessage::Institutions(InstitutionsMessage::RemoveIcon) => self.contact.icon.clear(),
+            PaneMessage::Institutions(InstitutionsMessage::AddExpected) => {
+                self.expected.push(Expected {
+                    since: Utc::now().format("%Y-01").to_string(),
+                    ..Default::default()
+                });
+                self.expected_rows
+                    .resize_with(self.expected.len(), Default::default);
+            }
+            PaneMessage::Institutions(InstitutionsMessage::RemoveExpected(i))
+                if i < self.expected.len() =>
+            {
+                self.expected.remove(i);
+                self.expected_rows.remove(i);
+            }
+            PaneMessage::Institutions(InstitutionsMessage::ExpectedTitleEdited(i, s)) => {
+                if let Some(expected) = self.expected.get_mut(i) {
+                    expected.title = s;
+                }
+            }
+            PaneMessage::Institutions(InstitutionsMessage::FrequencyToggled(i)) => {
+                if let Some(expected) = self.expected.get_mut(i) {
+                    expected.frequency = expected.frequency.next();
+                }
+            }
+            PaneMessage::Institutions(InstitutionsMessage::SinceEdited(i, s)) => {
+                if let Some(expected) = self.expected.get_mut(i) {
+                    expected.since = s;
+                }
+            }
+            PaneMessage::Institutions(InstitutionsMessage::Save) => {
+                if let Some(institution) = &self.open {
+                    self.status = match institutions::record_details(
+                        Path::new(&self.dir),
+                        institution,
+                        &self.contact,
+                        &self.expected,
+                    ) {
+                        Ok(()) => "Saved.".to_string(),
+                        Err(error) => format!("Couldn't save: {:?}", error),
+                    };
+                    self.refresh();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let mut column = Column::new().spacing(10);
+        let target = self.rename_target(&self.rename_to);
+        match &self.open {
+            None => {
+                if self.list.is_empty() {
+                    column = column.push(
+                        Text::new(
+                            "No institutions yet. Name documents \
+                             date_Institution_Title_page.ext to list them here.",
+                        )
+                        .size(14)
+                        .color([0.5, 0.5, 0.5]),
+                    );
+                }
+                self.institution_buttons
+                    .resize_with(self.list.len(), Default::default);
+                for ((institution, count), state) in
+                    self.list.iter().zip(self.institution_buttons.iter_mut())
+                {
+                    let summary = self
+                        .contacts
+                        .get(institution)
+                        .map(Contact::summary)
+                        .unwrap_or_default();
+                    let mut row = Row::new().spacing(10).align_items(Align::Center);
+                    if !self.badges.is_empty() {
+                        row = row.push(Badge::view(self.badges.get(institution)));
+                    }
+                    column = column.push(
+                        Button::new(
+                            state,
+                            row.push(Text::new(institution.as_str()).size(16))
+                                .push(
+                                    Text::new(format!("{} documents", count))
+                                        .size(14)
+                                        .color([0.5, 0.5, 0.5]),
+                                )
+                                .push(Text::new(summary).size(14).color([0.3, 0.3, 0.3])),
+                        )
+                        .on_press(Message::InstitutionsPane(InstitutionsMessage::Open(
+                            institution.clone(),
+                        )))
+                        .width(Length::Fill)
+                        .padding(8)
+                        .style(style::Button::Filter { selected: false }),
+                    );
+                }
+            }
+            Some(_) => {
+                let field =
+                    |state,
+                     placeholder,
+                     value: &str,
+                     on_change: fn(String) -> InstitutionsMessage| {
+                        TextInput::new(state, placeholder, value, move |s| {
+                            Message::InstitutionsPane(on_change(s))
+                        })
+                        .on_submit(Message::InstitutionsPane(InstitutionsMessage::Save))
+                        .padding(10)
+                    };
+                column = column
+                    .push(
+                        Button::new(
+                            &mut self.back_button,
+                            Text::new("< All institutions").size(16),
+                        )
+                        .on_press(Message::InstitutionsPane(InstitutionsMessage::Back))
+                        .padding(8)
+                        .style(style::Button::Filter { selected: false }),
+                    )
+                    .push(field(
+                        &mut self.website_input,
+                        "Website",
+                        &self.contact.website,
+                        InstitutionsMessage::WebsiteEdited,
+                    ))
+                    .push(field(
+                        &mut self.phone_input,
+                        "Phone",
+                        &self.contact.phone,
+                        InstitutionsMessage::PhoneEdited,
+                    ))
+                    .push(field(
+                        &mut self.account_input,
+                        "Account reference, e.g. ends in 1234",
+                        &self.contact.account,
+                        InstitutionsMessage::AccountEdited,
+                    ))
+                    .push(field(
+                        &mut self.notes_input,
+                        "Notes",
+                        &self.contact.notes,
+                        InstitutionsMessage::NotesEdited,
+                    ));
+                let mut badge = Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(Badge::view(Some(&Badge::of(
+                        Path::new(&self.dir),
+                        &self.contact,
+                    ))))
+                    .push(
+                        Button::new(
+                            &mut self.color_button,
+                            Text::new(match self.contact.color {
+                                Some(_) => "Color",
+                                None => "No color",
+                            })
+                            .size(14),
+                        )
+                        .on_press(Message::InstitutionsPane(InstitutionsMessage::ColorToggled))
+                        .padding(10)
+                        .style(style::Button::Label {
+                            color: self.contact.color.map(Label::color),
+                            selected: false,
+                        }),
+                    )
+                    .push(
+                        TextInput::new(
+                            &mut self.icon_input,
+                            "Icon, the path of a PNG or JPEG image",
+                            &self.icon_path,
+                            |s| Message::InstitutionsPane(InstitutionsMessage::IconPathEdited(s)),
+                        )
+                        .on_submit(Message::InstitutionsPane(InstitutionsMessage::SetIcon))
+                        .padding(10),
+                    )
+                    .push(
+                        Button::new(&mut self.icon_button, Text::new("Set icon"))
+                            .on_press(Message::InstitutionsPane(InstitutionsMessage::SetIcon))
+                            .padding(10)
+                            .style(style::Button::Refresh),
+                    );
+                if !self.contact.icon.is_empty() {
+                    badge = badge.push(
+                        Button::new(&mut self.remove_icon_button, Text::new("Remove icon"))
+                            .on_press(Message::InstitutionsPane(InstitutionsMessage::RemoveIcon))
+                            .padding(10)
+                            .style(style::Button::Cancel),
+                    );
+                }
+                column = column
+                    .push(Text::new("In the document list").size(16))
+                    .push(badge)
+                    .push(Text::new("Expected documents").size(16))
+                    .push(
+                        Text::new(
+                            "Documents whose title contains this are expected every period \
+                             since the month given as YYYY-MM.",
+                        )
+                        .size(14)
+                        .color([0.5, 0.5, 0.5]),
+                    );
+                for (i, (expected, row)) in self
+                    .expected
+                    .iter()
+                    .zip(self.expected_rows.iter_mut())
+                    .enumerate()
+                {
+                    column = column.push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Align::Center)
+                            .push(
+                                TextInput::new(
+                                    &mut row.title_input,
+                                    "Statement",
+                                    &expected.title,
+                                    move |s| {
+                                        Message::InstitutionsPane(
+                                            InstitutionsMessage::ExpectedTitleEdited(i, s),
+                                        )
+                                    },
+                                )
+                                .padding(10),
+                            )
+                            .push(
+                                Button::new(
+                                    &mut row.frequency_button,
+                                    Text::new(expected.frequency.name()).size(16),
+                                )
+                                .on_press(Message::InstitutionsPane(
+                                    InstitutionsMessage::FrequencyToggled(i),
+                                ))
+                                .width(Length::Units(100))
+                                .padding(10)
+                                .style(style::Button::Filter { selected: false }),
+                            )
+                            .push(
+                                TextInput::new(
+                                    &mut row.since_input,
+                                    "Since YYYY-MM",
+                                    &expected.since,
+                                    move |s| {
+                                        Message::InstitutionsPane(InstitutionsMessage::SinceEdited(
+                                            i, s,
+                                        ))
+                                    },
+                                )
+                                .width(Length::Units(120))
+                                .padding(10),
+                            )
+                            .push(
+                                Button::new(&mut row.delete_button, delete_icon())
+                                    .on_press(Message::InstitutionsPane(
+                                        InstitutionsMessage::RemoveExpected(i),
+                                    ))
+                                    .padding(10)
+                                    .style(style::Button::Icon),
+                            ),
+                    );
+                }
+                column = column.push(
+                    Button::new(
+                        &mut self.add_expected_button,
+                        Text::new("Expect a document").size(16),
+                    )
+                    .on_press(Message::InstitutionsPane(InstitutionsMessage::AddExpected))
+                    .padding(8)
+                    .style(style::Button::Filter { selected: false }),
+                );
+                for missing in &self.missing {
+                    column = column.push(
+                        Text::new(missing.to_string())
+                            .size(14)
+                            .color([0.8, 0.2, 0.2]),
+                    );
+                }
+                column = column.push(
+                    Row::new()
+                        .spacing(10)
+                        .align_items(Align::Center)
+                        .push(
+                            Button::new(&mut self.save_button, Text::new("Save"))
+                                .on_press(Message::InstitutionsPane(InstitutionsMessage::Save))
+                                .padding(10)
+                                .style(style::Button::Update),
+                        )
+                        .push(Text::new(&self.status).size(14)),
+                );
+                let mut rename = Button::new(&mut self.rename_button, Text::new("Rename"))
+                    .padding(10)
+                    .style(style::Button::Update);
+                if let (Some(from), Some(to), true) =
+                    (&self.open, &target, self.rename_plan.is_valid())
+                {
+                    rename = rename.on_press(Message::InstitutionsPane(
+                        InstitutionsMessage::Rename(from.clone(), to.clone()),
+                    ));
+                }
+                column = column.push(
+                    Row::new()
+                        .spacing(10)
+                        .align_items(Align::Center)
+                        .push(
+                            TextInput::new(
+                                &mut self.rename_input,
+                                "Rename to",
+                                &self.rename_to,
+                                |s| Message::InstitutionsPane(InstitutionsMessage::RenameEdited(s)),
+                            )
+                            .padding(10),
+                        )
+                        .push(rename),
+                );
+                if let Some(to) = &target {
+                    let count = self.rename_plan.renames.len();
+                    column = column.push(
+                        Text::new(format!("Files {} documents under {}.", count, to))
+                            .size(14)
+                            .color([0.5, 0.5, 0.5]),
+                    );
+                }
+                for conflict in &self.rename_plan.conflicts {
+                    column = column.push(
+                        Text::new(format!("Can't rename, {}.", conflict))
+                            .size(14)
+                            .color([0.8, 0.2, 0.2]),
+                    );
+                }
+                column =
+                    column.push(Text::new(format!("{} documents", self.documents.len())).size(16));
+                self.document_buttons
+                    .resize_with(self.documents.len(), Default::default);
+                for (filename, state) in self.documents.iter().zip(self.document_buttons.iter_mut())
+                {
+                    column = column.push(
+                        Button::new(state, Text::new(filename.as_str()).size(14))
+                            .on_press(Message::ShowPreview(
+                                Path::new(&self.dir)
+                                    .join(filename)
+                                    .to_string_lossy()
+                                    .to_string(),
+                            ))
+                            .width(Length::Fill)
+                            .style(style::Button::Doc),
+                    );
+                }
+            }
+        }
+        Scrollable::new(&mut self.scroll_state)
+            .padding(10)
+            .push(column)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+}
+
+impl BundlePane {
+    fn new(paths: Vec<String>, extra: Vec<(String, Vec<u8>)>) -> Self {
+        BundlePane {
+            paths,
+            extra,
+            ..Default::default()
+        }
+    }
+
+    /// Why the password can't be used yet, if it can't.
+    fn password_problem(&self) -> Option<String> {
+        if self.password.chars().count() < bundle::MIN_PASSWORD {
+            Some(format!(
+                "The password needs at least {} characters.",
+                bundle::MIN_PASSWORD
+            ))
+        } else if self.password != self.confirmation {
+            Some("The passwords don't match.".to_string())
+        } else {
+            None
+        }
+    }
+}
+
+impl PaneContent for BundlePane {
+    fn title(&self) -> String {
+        "Encrypted bundle".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::CloseBundlePane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        match message {
+            // The selected documents belong to the previous cabinet.
+            PaneMessage::Event(Event::PathChanged(_)) => *self = BundlePane::default(),
+            PaneMessage::Bundle(BundleMessage::PasswordEdited(password)) => {
+                self.password = password
+            }
+            PaneMessage::Bundle(BundleMessage::ConfirmationEdited(confirmation)) => {
+                self.confirmation = confirmation
+            }
+            PaneMessage::Bundle(BundleMessage::StampToggled(stamp)) => self.stamp = stamp,
+            PaneMessage::Bundle(BundleMessage::StampRecipientEdited(recipient)) => {
+                self.stamp_recipient = recipient
+            }
+            PaneMessage::Bundle(BundleMessage::Export) => {
+                self.exporting = true;
+                self.status = "Encrypting...".to_string();
+            }
+            PaneMessage::Bundle(BundleMessage::Exported(Ok(path))) => {
+                self.exporting = false;
+                self.password.clear();
+                self.confirmation.clear();
+                self.status = format!(
+                    "Saved {}. Send the password another way than the bundle.",
+                    path
+                );
+            }
+            PaneMessage::Bundle(BundleMessage::Exported(Err(error))) => {
+                self.exporting = false;
+                self.status = format!("Couldn't export the bundle: {:?}", error);
+            }
+            _ => {}
+        }
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let problem = self.password_problem();
+        let stamp = Some(self.stamp_recipient.clone()).filter(|_| self.stamp);
+        let ready = problem.is_none()
+            && !self.exporting
+            && !self.paths.is_empty()
+            && stamp.as_ref().is_none_or(|to| !to.trim().is_empty());
+        let submit = Message::ExportBundle(
+            self.paths.clone(),
+            self.extra.clone(),
+            self.password.clone(),
+            stamp,
+        );
+        let mut export = Button::new(&mut self.export_button, Text::new("Export"))
+            .padding(10)
+            .style(style::Button::Update);
+        if ready {
+            export = export.on_press(submit.clone());
+        }
+        let hint = if self.password.is_empty() {
+            String::new()
+        } else {
+            problem.unwrap_or_default()
+        };
+        let mut confirmation = TextInput::new(
+            &mut self.confirmation_input,
+            "Password again",
+            &self.confirmation,
+            |confirmation| Message::BundlePane(BundleMessage::ConfirmationEdited(confirmation)),
+        )
+        .password()
+        .padding(10);
+        if ready {
+            confirmation = confirmation.on_submit(submit);
+        }
+        let column = Column::new()
+            .spacing(10)
+            .push(Text::new(format!(
+                "{} documents and a manifest.csv listing them{} go into a zip encrypted \
+                 with AES-256, which 7-Zip, WinZip and macOS can open.",
+                self.paths.len(),
+                self.extra
+                    .iter()
+                    .map(|(name, _)| format!(", {}", name))
+                    .collect::<String>()
+            )))
+            .push(
+                TextInput::new(
+                    &mut self.password_input,
+                    "Password",
+                    &self.password,
+                    |password| Message::BundlePane(BundleMessage::PasswordEdited(password)),
+                )
+                .password()
+                .padding(10),
+            )
+            .push(confirmation)
+            .push(Text::new(hint).size(14).color([0.8, 0.2, 0.2]))
+            .push(Checkbox::new(
+                self.stamp,
+                "Stamp every page with whom the copies are provided to",
+                |stamp| Message::BundlePane(BundleMessage::StampToggled(stamp)),
+            ));
+        let column = if self.stamp {
+            column
+                .push(
+                    TextInput::new(
+                        &mut self.stamp_recipient_input,
+                        "Provided to, e.g. Acme Mortgage",
+                        &self.stamp_recipient,
+                        |recipient| {
+                            Message::BundlePane(BundleMessage::StampRecipientEdited(recipient))
+                        },
+                    )
+                    .padding(10),
+                )
+                .push(
+                    Text::new("Stamped copies are PDFs, the text is set in the settings.")
+                        .size(14)
+                        .color([0.5, 0.5, 0.5]),
+                )
+        } else {
+            column
+        };
+        let column = column.push(export).push(Text::new(&self.status).size(14));
+        Container::new(column)
+            .padding(10)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+}
+
+impl PaneContent for ImportReportPane {
+    fn title(&self) -> String {
+        "Import report".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::CloseImportReportPane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        match message {
+            PaneMessage::ImportReport(ImportReportMessage::Export) => {
+                self.status = "Exporting...".to_string()
+            }
+            PaneMessage::ImportReport(ImportReportMessage::Exported(Ok(path))) => {
+                self.status = format!("Saved {}", path)
+            }
+            PaneMessage::ImportReport(ImportReportMessage::Exported(Err(error))) => {
+                self.status = format!("Couldn't export the report: {:?}", error)
+            }
+            _ => {}
+        }
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let report = &self.report;
+        let section = |column: Column<'static, Message>, title: &str, lines: Vec<String>, color| {
+            if lines.is_empty() {
+                return column;
+            }
+            let heading = Text::new(format!("{} ({})", title, lines.len())).size(16);
+            lines
+                .into_iter()
+                .fold(column.push(heading), |column, line| {
+                    column.push(Text::new(line).size(14).color(color))
+                })
+        };
+        let gray = [0.5, 0.5, 0.5];
+        let mut details = Column::new().spacing(5);
+        details = section(details, "Imported", report.imported.clone(), gray);
+        details = section(
+            details,
+            "Normalized automatically",
+            report.normalized.clone(),
+            gray,
+        );
+        details = section(
+            details,
+            "Need a name",
+            report.needs_attention.clone(),
+            [0.8, 0.5, 0.1],
+        );
+        details = section(
+            details,
+            "Duplicates skipped",
+            report.duplicates.clone(),
+            gray,
+        );
+        details = section(
+            details,
+            "Blank pages dropped",
+            report
+                .blank_pages
+                .iter()
+                .map(|(filename, pages)| format!("{}: {} pages", filename, pages))
+                .collect(),
+            gray,
+        );
+        details = section(details, "Errors", report.errors.clone(), [0.8, 0.2, 0.2]);
+
+        let summary = format!(
+            "{}, {}: {} imported, {} to name by hand, {} duplicates skipped, {} errors.",
+            report.source,
+            report.finished,
+            report.imported.len(),
+            report.needs_attention.len(),
+            report.duplicates.len(),
+            report.errors.len()
+        );
+        Column::new()
+            .spacing(10)
+            .padding(10)
+            .push(Text::new(summary))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(
+                        Button::new(&mut self.export_button, Text::new("Export JSON").size(10))
+                            .padding(10)
+                            .style(style::Button::Refresh)
+                            .on_press(Message::ExportImportReport(self.report.clone())),
+                    )
+                    .push(Text::new(&self.status).size(14)),
+            )
+            .push(
+                Scrollable::new(&mut self.scroll_state)
+                    .push(details)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .into()
+    }
+}
+
+impl PaneContent for HealthPane {
+    fn title(&self) -> String {
+        "Cabinet health".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::CloseHealthPane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        match message {
+            PaneMessage::Health(HealthMessage::Fixing) => self.status = "Fixing...".to_string(),
+            PaneMessage::Health(HealthMessage::Indexing) => {
+                self.status = "Indexing, check again once it's done.".to_string()
+            }
+            PaneMessage::Health(HealthMessage::Fixed(Ok(_))) => {
+                self.status = "Checking again...".to_string()
+            }
+            PaneMessage::Health(HealthMessage::Fixed(Err(error))) => {
+                self.status = format!("Couldn't fix it: {:?}", error)
+            }
+            _ => {}
+        }
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let health = &self.health;
+        let section = |column: Column<'static, Message>, title: &str, lines: &[String]| {
+            if lines.is_empty() {
+                return column;
+            }
+            let heading = Text::new(format!("{} ({})", title, lines.len())).size(16);
+            lines.iter().fold(column.push(heading), |column, line| {
+                column.push(Text::new(line).size(14).color([0.5, 0.5, 0.5]))
+            })
+        };
+        let mut details = Column::new().spacing(5);
+        details = section(details, "Missing from the folder", &health.missing);
+        details = section(details, "Not catalogued", &health.uncatalogued);
+        details = section(details, "Changed since indexed", &health.changed);
+
+        let fix = |state, label, message, needed: bool| {
+            let button = Button::new(state, Text::new(label).size(10))
+                .padding(10)
+                .style(style::Button::Refresh);
+            if needed {
+                button.on_press(message)
+            } else {
+                button
+            }
+        };
+        let summary = if health.is_healthy() {
+            format!(
+                "Everything is in order, {} documents checked.",
+                health.sampled
+            )
+        } else {
+            format!("{}.", health)
+        };
+        Column::new()
+            .spacing(10)
+            .padding(10)
+            .push(Text::new(summary))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(fix(
+                        &mut self.fix_missing_button,
+                        "Relink or forget missing",
+                        Message::FixMissing,
+                        !health.missing.is_empty(),
+                    ))
+                    .push(fix(
+                        &mut self.index_button,
+                        "Index new files",
+                        Message::IndexUncatalogued(health.uncatalogued.clone()),
+                        !health.uncatalogued.is_empty(),
+                    ))
+                    .push(fix(
+                        &mut self.fix_changed_button,
+                        "Index changed again",
+                        Message::FixChanged(health.changed.clone()),
+                        !health.changed.is_empty(),
+                    ))
+                    .push(fix(
+                        &mut self.check_button,
+                        "Check again",
+                        Message::CheckHealth,
+                        true,
+                    ))
+                    .push(Text::new(&self.status).size(14)),
+            )
+            .push(
+                Scrollable::new(&mut self.scroll_state)
+                    .push(details)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .into()
+    }
+}
+
+impl LogPane {
+    fn new() -> Self {
+        LogPane {
+            lines: logging::recent_lines(LOG_LINES),
+            ..Default::default()
+        }
+    }
+}
+
+impl PaneContent for LogPane {
+    fn title(&self) -> String {
+        "Log".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::CloseLogPane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        if let PaneMessage::Log(LogMessage::Refresh) = message {
+            self.lines = logging::recent_lines(LOG_LINES);
+        }
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let lines: Element<_> = if self.lines.is_empty() {
+            empty_message("Nothing logged yet.")
+        } else {
+            self.lines
+                .iter()
+                .fold(Column::new().spacing(2), |column, line| {
+                    let color = if line.contains(" ERROR ") {
+                        [0.8, 0.2, 0.2]
+                    } else if line.contains(" WARN ") {
+                        [0.8, 0.4, 0.1]
+                    } else {
+                        [0.3, 0.3, 0.3]
+                    };
+                    column.push(Text::new(line).size(12).color(color))
+                })
+                .into()
+        };
+        Column::new()
+            .spacing(10)
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(
+                        Text::new(
+                            logging::log_dir()
+                                .map(|dir| dir.display().to_string())
+                                .unwrap_or_default(),
+                        )
+                        .size(14)
+                        .color([0.5, 0.5, 0.5])
+                        .width(Length::Fill),
+                    )
+                    .push(
+                        Button::new(&mut self.refresh_button, Text::new("refresh").size(16))
+                            .on_press(Message::LogPane(LogMessage::Refresh))
+                            .padding(10)
+                            .style(style::Button::Refresh),
+                    ),
+            )
+            .push(
+                Scrollable::new(&mut self.scroll_state)
+                    .push(lines)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .padding(10)
+            .into()
+    }
+}
+
+impl CalendarPane {
+    fn new(path: &str) -> Self {
+        let mut pane = CalendarPane::default();
+        pane.load(path);
+        pane
+    }
+
+    fn load(&mut self, path: &str) {
+        let dir = Path::new(path);
+        let paths: Vec<_> = utils::list_files(dir)
+            .iter()
+            .map(|filename| dir.join(filename))
+            .collect();
+        self.counts = calendar::month_counts(&paths);
+        self.missing = calendar::missing(
+            &paths,
+            &Catalog::load(dir).expected,
+            Utc::now().naive_utc().date(),
+        );
+    }
+}
+
+impl PaneContent for CalendarPane {
+    fn title(&self) -> String {
+        "Calendar".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::CloseCalendarPane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        match message {
+            PaneMessage::Event(Event::RefreshTargetDir(path)) => self.load(&path),
+            PaneMessage::Event(Event::PathChanged(path)) => self.load(&path),
+            PaneMessage::Calendar(CalendarMessage::Exported(exported)) => {
+                self.status = match exported {
+                    Ok(events) => format!(
+                        "Wrote {} events to {}, subscribe to it in your calendar app",
+                        events,
+                        ics::FILENAME
+                    ),
+                    Err(error) => format!("Couldn't write {}: {:?}", ics::FILENAME, error),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let cell = |label: String, color: [f32; 3]| {
+            Text::new(label)
+                .size(16)
+                .width(Length::Units(40))
+                .horizontal_alignment(HorizontalAlignment::Center)
+                .color(color)
+        };
+        let header = calendar::MONTHS.iter().fold(
+            Row::new()
+                .push(Text::new("Institution").size(16).width(Length::Units(160)))
+                .push(Text::new("Year").size(16).width(Length::Units(60))),
+            |row, month| row.push(cell(month.to_string(), [0.3, 0.3, 0.3])),
+        );
+        let missing = &self.missing;
+        let flagged = |institution: &str, year: i32, month: usize| {
+            let period = format!("{}-{:02}", year, month + 1);
+            missing
+                .iter()
+                .any(|m| m.institution == institution && m.period == period)
+        };
+        let missing_list = missing.iter().fold(Column::new().spacing(5), |column, m| {
+            column.push(Text::new(m.to_string()).size(14).color([0.8, 0.2, 0.2]))
+        });
+        let rows = self.counts.iter().fold(
+            Column::new().spacing(5).push(missing_list).push(header),
+            |column, ((institution, year), months)| {
+                // Empty months are the point of this view, so they stay visible as dashes.
+                column.push(
+                    months.iter().enumerate().fold(
+                        Row::new()
+                            .push(Text::new(institution).size(16).width(Length::Units(160)))
+                            .push(
+                                Text::new(year.to_string())
+                                    .size(16)
+                                    .width(Length::Units(60)),
+                            ),
+                        |row, (month, count)| match count {
+                            0 if flagged(institution, *year, month) => {
+                                row.push(cell("!".to_string(), [0.8, 0.2, 0.2]))
+                            }
+                            0 => row.push(cell("-".to_string(), [0.7, 0.7, 0.7])),
+                            n => row.push(cell(n.to_string(), [0.1, 0.5, 0.3])),
+                        },
+                    ),
+                )
+            },
+        );
+
+        Column::new()
+            .spacing(10)
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(
+                        Button::new(&mut self.export_button, Text::new("Export .ics"))
+                            .on_press(Message::ExportCalendar)
+                            .padding(10)
+                            .style(style::Button::Refresh),
+                    )
+                    .push(Text::new(&self.status).size(14)),
+            )
+            .push(
+                Scrollable::new(&mut self.scroll_state)
+                    .push(rows)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .padding(10)
+            .into()
+    }
+}
+
+impl PaneContent for StatsPane {
+    fn title(&self) -> String {
+        "Statistics".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::CloseStatsPane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        match message {
+            PaneMessage::Event(Event::PathChanged(dir)) => {
+                self.dir = dir;
+                self.stats = None;
+            }
+            // Results for a cabinet that is no longer open are dropped.
+            PaneMessage::Stats(StatsPaneMessage::Computed(dir, stats)) if dir == self.dir => {
+                self.stats = Some(stats)
+            }
+            PaneMessage::Stats(StatsPaneMessage::Compacting) => {
+                self.status = "Compacting...".to_string()
+            }
+            PaneMessage::Stats(StatsPaneMessage::Compacted(Ok(compaction))) => {
+                self.status = format!("Compacted {}", compaction)
+            }
+            PaneMessage::Stats(StatsPaneMessage::Compacted(Err(error))) => {
+                self.status = format!("Couldn't compact the metadata: {:?}", error)
+            }
+            PaneMessage::Stats(StatsPaneMessage::EmptyingTrash) => {
+                self.status = "Emptying the trash...".to_string()
+            }
+            PaneMessage::Stats(StatsPaneMessage::TrashEmptied(Ok(emptied))) => {
+                self.status = format!(
+                    "Emptied the trash of {} documents, {}",
+                    emptied.files,
+                    stats::size(emptied.bytes)
+                )
+            }
+            PaneMessage::Stats(StatsPaneMessage::TrashEmptied(Err(error))) => {
+                self.status = format!("Couldn't empty the trash: {:?}", error)
+            }
+            PaneMessage::Stats(StatsPaneMessage::Exported(Ok(path))) => {
+                self.status = format!("Exported {}", path)
+            }
+            PaneMessage::Stats(StatsPaneMessage::Exported(Err(error))) => {
+                self.status = format!("Couldn't export the cabinet: {:?}", error)
+            }
+            _ => {}
+        }
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let refresh = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(
+                Button::new(&mut self.refresh_button, Text::new("Refresh"))
+                    .on_press(Message::ComputeStats)
+                    .padding(10)
+                    .style(style::Button::Refresh),
+            )
+            .push(
+                Button::new(&mut self.compact_button, Text::new("Compact metadata"))
+                    .on_press(Message::CompactMetadata)
+                    .padding(10)
+                    .style(style::Button::Refresh),
+            )
+            .push(
+                Button::new(&mut self.export_csv_button, Text::new("Export CSV"))
+                    .on_press(Message::ExportCabinet(export::Format::Csv))
+                    .padding(10)
+                    .style(style::Button::Refresh),
+            )
+            .push(
+                Button::new(&mut self.export_json_button, Text::new("Export JSON"))
+                    .on_press(Message::ExportCabinet(export::Format::Json))
+                    .padding(10)
+                    .style(style::Button::Refresh),
+            )
+            .push(Text::new(&self.status).size(14));
+        let stats = match &self.stats {
+            Some(stats) => stats,
+            None => {
+                return Column::new()
+                    .spacing(10)
+                    .push(refresh)
+                    .push(Text::new("Computing...").size(16))
+                    .padding(10)
+                    .into()
+            }
+        };
+        let row = |label: String, files: Option<usize>, bytes: u64| {
+            Row::new()
+                .push(Text::new(label).size(16).width(Length::FillPortion(4)))
+                .push(
+                    Text::new(files.map(|f| f.to_string()).unwrap_or_default())
+                        .size(16)
+                        .width(Length::FillPortion(1))
+                        .horizontal_alignment(HorizontalAlignment::Right),
+                )
+                .push(
+                    Text::new(stats::size(bytes))
+                        .size(16)
+                        .width(Length::FillPortion(1))
+                        .horizontal_alignment(HorizontalAlignment::Right),
+                )
+        };
+        let heading = |title: &str| {
+            Row::new()
+                .push(Text::new(title).size(16).width(Length::FillPortion(4)))
+                .push(
+                    Text::new("Files")
+                        .size(14)
+                        .color([0.5, 0.5, 0.5])
+                        .width(Length::FillPortion(1))
+                        .horizontal_alignment(HorizontalAlignment::Right),
+                )
+                .push(
+                    Text::new("Size")
+                        .size(14)
+                        .color([0.5, 0.5, 0.5])
+                        .width(Length::FillPortion(1))
+                        .horizontal_alignment(HorizontalAlignment::Right),
+                )
+        };
+        let folders = stats.folders.iter().fold(
+            Column::new().spacing(5).push(heading("Folders")),
+            |column, (folder, totals)| {
+                column.push(row(folder.clone(), Some(totals.files), totals.bytes))
+            },
+        );
+        // Old years are what goes to cold storage first.
+        let years = stats.years.iter().fold(
+            Column::new().spacing(5).push(heading("Documents by year")),
+            |column, (year, totals)| {
+                let label = year.map_or("Undated".to_string(), |year| year.to_string());
+                column.push(row(label, Some(totals.files), totals.bytes))
+            },
+        );
+        let largest = stats.largest.iter().fold(
+            Column::new().spacing(5).push(heading("Largest files")),
+            |column, (path, bytes)| column.push(row(path.clone(), None, *bytes)),
+        );
+        let idle_bytes = stats.idle.iter().map(|(_, _, bytes)| bytes).sum();
+        let idle = stats.idle.iter().take(IDLE_LISTED).fold(
+            Column::new()
+                .spacing(5)
+                .push(heading(&format!(
+                    "Not opened in {} years",
+                    stats::IDLE_YEARS
+                )))
+                .push(row("All".to_string(), Some(stats.idle.len()), idle_bytes)),
+            |column, (filename, last_used, bytes)| {
+                let label = format!("{}, last {}", filename, last_used.format("%Y-%m-%d"));
+                column.push(row(label, None, *bytes))
+            },
+        );
+        let mut empty_trash = Button::new(&mut self.empty_trash_button, Text::new("Empty trash"))
+            .padding(10)
+            .style(style::Button::Destructive);
+        if stats.trash.files > 0 {
+            empty_trash = empty_trash.on_press(Message::EmptyTrash);
+        }
+        let trash = Column::new()
+            .spacing(5)
+            .push(heading("Trash"))
+            .push(row(
+                "Deleted documents".to_string(),
+                Some(stats.trash.files),
+                stats.trash.bytes,
+            ))
+            .push(empty_trash);
+        Column::new()
+            .spacing(10)
+            .push(refresh)
+            .push(
+                Scrollable::new(&mut self.scroll_state)
+                    .spacing(20)
+                    .push(trash)
+                    .push(folders)
+                    .push(years)
+                    .push(largest)
+                    .push(idle)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .padding(10)
+            .into()
+    }
+}
+
+impl SettingsPane {
+    fn new(preferences: Preferences, dir: &str) -> Self {
+        let mut pane = SettingsPane {
+            vault: vault::exists(Path::new(dir)),
+            ..Default::default()
+        };
+        pane.set_preferences(preferences);
+        pane
+    }
+
+    fn set_preferences(&mut self, preferences: Preferences) {
+        self.tool_rows
+            .resize_with(preferences.external_tools.len(), Default::default);
+        self.hook_rows
+            .resize_with(preferences.hooks.len(), Default::default);
+        self.recipient_rows
+            .resize_with(preferences.recipients.len(), Default::default);
+        // Browsers can't take uploads from phones, the web build has no link to show.
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.share_link = if preferences.share_enabled {
+                share::link(preferences.share_port, &preferences.share_token)
+            } else {
+                String::new()
+            };
+        }
+        self.preferences = preferences;
+    }
+}
+
+impl PaneContent for SettingsPane {
+    fn title(&self) -> String {
+        "Settings".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::CloseSettingsPane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        match message {
+            PaneMessage::Event(Event::PreferencesChanged(preferences)) => {
+                self.set_preferences(preferences)
+            }
+            PaneMessage::Event(Event::PathChanged(dir)) => {
+                self.vault = vault::exists(Path::new(&dir))
+            }
+            PaneMessage::Event(Event::Locked) => {
+                self.old_passphrase.clear();
+                self.new_passphrase.clear();
+                self.confirmation.clear();
+            }
+            PaneMessage::Settings(SettingsPaneMessage::OldPassphraseEdited(s)) => {
+                self.old_passphrase = s
+            }
+            PaneMessage::Settings(SettingsPaneMessage::NewPassphraseEdited(s)) => {
+                self.new_passphrase = s
+            }
+            PaneMessage::Settings(SettingsPaneMessage::ConfirmationEdited(s)) => {
+                self.confirmation = s
+            }
+            PaneMessage::Settings(SettingsPaneMessage::ChangingPassphrase) => {
+                self.changing = true;
+                self.passphrase_status = "Re-encrypting documents...".to_string();
+            }
+            PaneMessage::Settings(SettingsPaneMessage::PassphraseChanged(result)) => {
+                self.changing = false;
+                self.passphrase_status = match result {
+                    Ok(changed) => {
+                        self.old_passphrase.clear();
+                        self.new_passphrase.clear();
+                        self.confirmation.clear();
+                        format!("Passphrase changed, {} documents re-encrypted", changed)
+                    }
+                    Err(VaultError::PassphraseError) => {
+                        "The old passphrase is wrong, or an unfinished change used another new one."
+                            .to_string()
+                    }
+                    // Containers done so far are kept, trying again picks up from there.
+                    Err(error) => format!("Failed: {:?}, try again to resume", error),
+                };
+            }
+            _ => {}
+        }
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let SettingsPane {
+            preferences,
+            ocr_languages_input,
+            archive_dir_input,
+            share_link,
+            renew_share_token_button,
+            drop_folder_input,
+            tool_rows,
+            add_tool_button,
+            hook_rows,
+            add_hook_button,
+            recipient_rows,
+            add_recipient_button,
+            stamp_template_input,
+            vault,
+            old_passphrase,
+            new_passphrase,
+            confirmation,
+            changing,
+            passphrase_status,
+            old_passphrase_input,
+            new_passphrase_input,
+            confirmation_input,
+            change_passphrase_button,
+            scroll_state,
+        } = self;
+        let preview_layout = preferences.preview_layout;
+        let tools = preferences
+            .external_tools
+            .iter()
+            .zip(tool_rows.iter_mut())
+            .enumerate()
+            .fold(Column::new().spacing(10), |column, (i, (tool, row))| {
+                column.push(
+                    Row::new()
+                        .spacing(10)
+                        .align_items(Align::Center)
+                        .push(
+                            TextInput::new(&mut row.name_input, "GIMP", &tool.name, move |s| {
+                                Message::PreferencesMessage(PreferencesMessage::ToolNameEdited(
+                                    i, s,
+                                ))
+                            })
+                            .padding(10)
+                            .width(Length::FillPortion(2)),
+                        )
+                        .push(
+                            TextInput::new(
+                                &mut row.extensions_input,
+                                "png, jpg",
+                                &tool.extensions,
+                                move |s| {
+                                    Message::PreferencesMessage(
+                                        PreferencesMessage::ToolExtensionsEdited(i, s),
+                                    )
+                                },
+                            )
+                            .padding(10)
+                            .width(Length::FillPortion(2)),
+                        )
+                        .push(
+                            TextInput::new(
+                                &mut row.command_input,
+                                "gimp {}",
+                                &tool.command,
+                                move |s| {
+                                    Message::PreferencesMessage(
+                                        PreferencesMessage::ToolCommandEdited(i, s),
+                                    )
+                                },
+                            )
+                            .padding(10)
+                            .width(Length::FillPortion(4)),
+                        )
+                        .push(
+                            Button::new(&mut row.delete_button, delete_icon())
+                                .on_press(Message::PreferencesMessage(
+                                    PreferencesMessage::RemoveTool(i),
+                                ))
+                                .padding(10)
+                                .style(style::Button::Icon),
+                        ),
+                )
+            });
+        let hooks = preferences
+            .hooks
+            .iter()
+            .zip(hook_rows.iter_mut())
+            .enumerate()
+            .fold(Column::new().spacing(10), |column, (i, (hook, row))| {
+                column.push(
+                    Row::new()
+                        .spacing(10)
+                        .align_items(Align::Center)
+                        .push(
+                            Button::new(
+                                &mut row.event_button,
+                                Text::new(hook.event.name()).size(16),
+                            )
+                            .on_press(Message::PreferencesMessage(
+                                PreferencesMessage::HookEventToggled(i),
+                            ))
+                            .width(Length::Units(140))
+                            .padding(10)
+                            .style(style::Button::Filter { selected: false }),
+                        )
+                        .push(
+                            TextInput::new(
+                                &mut row.command_input,
+                                "upload-document {}",
+                                &hook.command,
+                                move |s| {
+                                    Message::PreferencesMessage(
+                                        PreferencesMessage::HookCommandEdited(i, s),
+                                    )
+                                },
+                            )
+                            .padding(10)
+                            .width(Length::Fill),
+                        )
+                        .push(
+                            Button::new(&mut row.delete_button, delete_icon())
+                                .on_press(Message::PreferencesMessage(
+                                    PreferencesMessage::RemoveHook(i),
+                                ))
+                                .padding(10)
+                                .style(style::Button::Icon),
+                        ),
+                )
+            });
+        let recipients = preferences
+            .recipients
+            .iter()
+            .zip(recipient_rows.iter_mut())
+            .enumerate()
+            .fold(
+                Column::new().spacing(10),
+                |column, (i, (recipient, row))| {
+                    column.push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Align::Center)
+                            .push(
+                                TextInput::new(
+                                    &mut row.name_input,
+                                    "Accountant",
+                                    &recipient.name,
+                                    move |s| {
+                                        Message::PreferencesMessage(
+                                            PreferencesMessage::RecipientNameEdited(i, s),
+                                        )
+                                    },
+                                )
+                                .padding(10)
+                                .width(Length::FillPortion(2)),
+                            )
+                            .push(
+                                TextInput::new(
+                                    &mut row.key_input,
+                                    "age1... or accountant@example.com",
+                                    &recipient.key,
+                                    move |s| {
+                                        Message::PreferencesMessage(
+                                            PreferencesMessage::RecipientKeyEdited(i, s),
+                                        )
+                                    },
+                                )
+                                .padding(10)
+                                .width(Length::FillPortion(6)),
+                            )
+                            .push(
+                                Button::new(&mut row.delete_button, delete_icon())
+                                    .on_press(Message::PreferencesMessage(
+                                        PreferencesMessage::RemoveRecipient(i),
+                                    ))
+                                    .padding(10)
+                                    .style(style::Button::Icon),
+                            ),
+                    )
+                },
+            );
+        let mut settings = Column::new()
+            .spacing(10)
+            .push(Text::new("Installed OCR languages").size(16))
+            .push(
+                TextInput::new(
+                    ocr_languages_input,
+                    "eng+deu",
+                    &preferences.ocr_languages,
+                    |s| Message::PreferencesMessage(PreferencesMessage::OcrLanguagesEdited(s)),
+                )
+                .padding(10),
+            )
+            .push(
+                Text::new("Tesseract models, the one matching a document's language is used.")
+                    .size(14)
+                    .color([0.5, 0.5, 0.5]),
+            )
+            .push(Text::new("Open the preview").size(16))
+            .push(
+                [
+                    (PreviewLayout::SideBySide, "Next to the list"),
+                    (PreviewLayout::Stacked, "Below the list"),
+                ]
+                .iter()
+                .fold(Row::new().spacing(20), |row, (layout, label)| {
+                    row.push(Radio::new(
+                        *layout,
+                        *label,
+                        Some(preview_layout),
+                        |layout| {
+                            Message::PreferencesMessage(PreferencesMessage::PreviewLayoutChanged(
+                                layout,
+                            ))
+                        },
+                    ))
+                }),
+            )
+            .push(Checkbox::new(
+                preferences.write_pdf_metadata,
+                "Write the normalized fields into the metadata of PDFs",
+                |write| {
+                    Message::PreferencesMessage(PreferencesMessage::WritePdfMetadataToggled(write))
+                },
+            ))
+            .push(Checkbox::new(
+                preferences.tray,
+                "Stay in the system tray, with a menu to add documents",
+                |tray| Message::PreferencesMessage(PreferencesMessage::TrayToggled(tray)),
+            ))
+            .push(
+                Text::new("Clicking the icon hides the window, the cabinet keeps pulling scans and taking shares meanwhile. Hiding needs xdotool, adding zenity or kdialog.")
+                    .size(14)
+                    .color([0.5, 0.5, 0.5]),
+            )
+            .push(Checkbox::new(
+                preferences.event_stream,
+                "Log added, renamed, tagged and deleted documents to .filecabinet/events.jsonl for other programs to follow",
+                |enabled| {
+                    Message::PreferencesMessage(PreferencesMessage::EventStreamToggled(enabled))
+                },
+            ))
+            .push(Text::new("Ask before").size(16))
+            .push(Confirmation::ALL.iter().fold(
+                Column::new().spacing(10),
+                |column, confirmation| {
+                    let confirmation = *confirmation;
+                    column.push(Checkbox::new(
+                        preferences.confirms(confirmation),
+                        confirmation.name(),
+                        move |ask| {
+                            Message::PreferencesMessage(PreferencesMessage::ConfirmationToggled(
+                                confirmation,
+                                ask,
+                            ))
+                        },
+                    ))
+                },
+            ))
+            .push(Checkbox::new(
+                preferences.share_enabled,
+                "Let phones on this network share documents into the cabinet",
+                |enabled| Message::PreferencesMessage(PreferencesMessage::ShareToggled(enabled)),
+            ))
+            .push(if preferences.share_enabled {
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(Text::new(share_link.as_str()).size(16).width(Length::Fill))
+                    .push(
+                        Button::new(renew_share_token_button, Text::new("New link"))
+                            .on_press(Message::PreferencesMessage(
+                                PreferencesMessage::RenewShareToken,
+                            ))
+                            .padding(10)
+                            .style(style::Button::Refresh),
+                    )
+            } else {
+                Row::new()
+            })
+            .push(
+                Text::new("Open the link on the phone. Documents go to the import folder if there is one, otherwise into the cabinet.")
+                    .size(14)
+                    .color([0.5, 0.5, 0.5]),
+            )
+            .push(Text::new("Pull scans from").size(16))
+            .push(
+                TextInput::new(
+                    drop_folder_input,
+                    "ftp://192.168.1.20/scans",
+                    &preferences.drop_folder,
+                    |s| Message::PreferencesMessage(PreferencesMessage::DropFolderEdited(s)),
+                )
+                .padding(10),
+            )
+            .push(
+                Text::new("The ftp://, sftp:// or smb:// folder of a network scanner, checked every minute. FTP and SFTP logins can live in ~/.netrc.")
+                    .size(14)
+                    .color([0.5, 0.5, 0.5]),
+            )
+            .push(Checkbox::new(
+                preferences.delete_pulled_scans,
+                "Delete scans from the scanner once imported",
+                |delete| {
+                    Message::PreferencesMessage(PreferencesMessage::DeletePulledScansToggled(
+                        delete,
+                    ))
+                },
+            ))
+            .push(Checkbox::new(
+                preferences.drop_blank_pages,
+                "Drop blank pages from imported PDFs",
+                |drop| Message::PreferencesMessage(PreferencesMessage::DropBlankPagesToggled(drop)),
+            ))
+            .push(BlankSensitivity::ALL.iter().fold(
+                Row::new().spacing(20).push(Text::new("Sensitivity").size(14)),
+                |row, sensitivity| {
+                    row.push(Radio::new(
+                        *sensitivity,
+                        sensitivity.name(),
+                        Some(preferences.blank_sensitivity),
+                        |sensitivity| {
+                            Message::PreferencesMessage(
+                                PreferencesMessage::BlankSensitivityChanged(sensitivity),
+                            )
+                        },
+                    ))
+                },
+            ))
+            .push(
+                Text::new("The backs of duplex scans, for instance. A higher sensitivity also drops pages with some show-through, the originals are kept as earlier versions.")
+                    .size(14)
+                    .color([0.5, 0.5, 0.5]),
+            )
+            .push(Text::new("Archive to").size(16))
+            .push(
+                TextInput::new(
+                    archive_dir_input,
+                    "/media/external/archive",
+                    &preferences.archive_dir,
+                    |s| Message::PreferencesMessage(PreferencesMessage::ArchiveDirEdited(s)),
+                )
+                .padding(10),
+            )
+            .push(
+                Text::new("A folder on an external drive or a mounted remote. Archived documents stay listed and can be restored.")
+                    .size(14)
+                    .color([0.5, 0.5, 0.5]),
+            )
+            .push(Text::new("Empty the trash of documents deleted").size(16))
+            .push(
+                [
+                    (7, "A week ago"),
+                    (30, "A month ago"),
+                    (365, "A year ago"),
+                    (0, "Never"),
+                ]
+                .iter()
+                .fold(Row::new().spacing(20), |row, (days, label)| {
+                    row.push(Radio::new(
+                        *days,
+                        *label,
+                        Some(preferences.trash_retention_days),
+                        |days| {
+                            Message::PreferencesMessage(PreferencesMessage::TrashRetentionChanged(
+                                days,
+                            ))
+                        },
+                    ))
+                }),
+            )
+            .push(Text::new("Work on").size(16))
+            .push(
+                [(0, "Every core"), (4, "4 threads"), (2, "2 threads"), (1, "1 thread")]
+                    .iter()
+                    .fold(Row::new().spacing(20), |row, (threads, label)| {
+                        row.push(Radio::new(
+                            *threads,
+                            *label,
+                            Some(preferences.worker_threads),
+                            |threads| {
+                                Message::PreferencesMessage(
+                                    PreferencesMessage::WorkerThreadsChanged(threads),
+                                )
+                            },
+                        ))
+                    }),
+            )
+            .push(
+                Text::new("Fewer threads keep the machine cooler while documents are indexed and read. Applies from the next launch.")
+                    .size(14)
+                    .color([0.5, 0.5, 0.5]),
+            )
+            .push(Checkbox::new(
+                preferences.low_power,
+                "Low power mode: pause indexing while on battery",
+                |low_power| {
+                    Message::PreferencesMessage(PreferencesMessage::LowPowerToggled(low_power))
+                },
+            ))
+            .push(Text::new("Lock the cabinet after").size(16))
+            .push(
+                [
+                    (5, "5 minutes"),
+                    (15, "15 minutes"),
+                    (60, "1 hour"),
+                    (0, "Never"),
+                ]
+                .iter()
+                .fold(Row::new().spacing(20), |row, (minutes, label)| {
+                    row.push(Radio::new(
+                        *minutes,
+                        *label,
+                        Some(preferences.auto_lock_minutes),
+                        |minutes| {
+                            Message::PreferencesMessage(PreferencesMessage::AutoLockChanged(
+                                minutes,
+                            ))
+                        },
+                    ))
+                }),
+            )
+            .push(
+                Text::new("without use. Only cabinets with a vault lock, with its passphrase.")
+                    .size(14)
+                    .color([0.5, 0.5, 0.5]),
+            )
+            .push(Text::new("Open with").size(16))
+            .push(tools)
+            .push(
+                Text::new("{} stands for the file, which is added at the end if it's missing.")
+                    .size(14)
+                    .color([0.5, 0.5, 0.5]),
+            )
+            .push(
+                Button::new(add_tool_button, Text::new("Add tool"))
+                    .on_press(Message::PreferencesMessage(PreferencesMessage::AddTool))
+                    .padding(10)
+                    .style(style::Button::Update),
+            )
+            .push(Text::new("Hooks").size(16))
+            .push(hooks)
+            .push(
+                Text::new(
+                    "Run with the file like tools, and get its name, date, institution, title, \
+                     page and catalog entry as JSON on stdin. A failing pre-import hook keeps \
+                     the file out of the cabinet.",
+                )
+                .size(14)
+                .color([0.5, 0.5, 0.5]),
+            )
+            .push(
+                Button::new(add_hook_button, Text::new("Add hook"))
+                    .on_press(Message::PreferencesMessage(PreferencesMessage::AddHook))
+                    .padding(10)
+                    .style(style::Button::Update),
+            )
+            .push(Text::new("Encrypt exports for").size(16))
+            .push(recipients)
+            .push(
+                Text::new("age or SSH public keys, or GPG key IDs or emails from your keyring.")
+                    .size(14)
+                    .color([0.5, 0.5, 0.5]),
+            )
+            .push(
+                Button::new(add_recipient_button, Text::new("Add recipient"))
+                    .on_press(Message::PreferencesMessage(
+                        PreferencesMessage::AddRecipient,
+                    ))
+                    .padding(10)
+                    .style(style::Button::Update),
+            )
+            .push(Text::new("Stamp on bundled copies").size(16))
+            .push(
+                TextInput::new(
+                    stamp_template_input,
+                    "Copy provided to {recipient} on {date}",
+                    &preferences.stamp_template,
+                    |s| Message::PreferencesMessage(PreferencesMessage::StampTemplateEdited(s)),
+                )
+                .padding(10),
+            )
+            .push(
+                Text::new("{recipient} is whom the bundle is for, {date} the day it is exported.")
+                    .size(14)
+                    .color([0.5, 0.5, 0.5]),
+            )
+            .padding(10);
+        if *vault {
+            let checked = check_passphrase(new_passphrase, confirmation);
+            let mut change = Button::new(change_passphrase_button, Text::new("Change passphrase"))
+                .padding(10)
+                .style(style::Button::Update);
+            if !*changing && !old_passphrase.is_empty() && checked.is_ok() {
+                change = change.on_press(Message::ChangePassphrase(
+                    old_passphrase.clone(),
+                    new_passphrase.clone(),
+                ));
+            }
+            let status = match checked {
+                Err(error) if !new_passphrase.is_empty() && !*changing => error,
+                _ => passphrase_status.clone(),
+            };
+            settings = settings
+                .push(Text::new("Vault passphrase").size(16))
+                .push(
+                    Row::new()
+                        .spacing(10)
+                        .push(
+                            TextInput::new(
+                                old_passphrase_input,
+                                "Current passphrase",
+                                old_passphrase,
+                                |s| {
+                                    Message::SettingsPane(SettingsPaneMessage::OldPassphraseEdited(
+                                        s,
+                                    ))
+                                },
+                            )
+                            .password()
+                            .padding(10),
+                        )
+                        .push(
+                            TextInput::new(
+                                new_passphrase_input,
+                                "New passphrase",
+                                new_passphrase,
+                                |s| {
+                                    Message::SettingsPane(SettingsPaneMessage::NewPassphraseEdited(
+                                        s,
+                                    ))
+                                },
+                            )
+                            .password()
+                            .padding(10),
+                        )
+                        .push(
+                            TextInput::new(
+                                confirmation_input,
+                                "Repeat the new passphrase",
+                                confirmation,
+                                |s| {
+                                    Message::SettingsPane(SettingsPaneMessage::ConfirmationEdited(
+                                        s,
+                                    ))
+                                },
+                            )
+                            .password()
+                            .padding(10),
+                        ),
+                )
+                .push(
+                    Text::new(
+                        "Every encrypted document is re-encrypted. If that is interrupted, \
+                         change it again with the same passphrases to resume.",
+                    )
+                    .size(14)
+                    .color([0.5, 0.5, 0.5]),
+                )
+                .push(
+                    Row::new()
+                        .spacing(10)
+                        .align_items(Align::Center)
+                        .push(change)
+                        .push(Text::new(status).size(16)),
+                );
+        }
+        Scrollable::new(scroll_state)
+            .push(settings)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+}
+
+impl PacketPane {
+    fn new(dir: &str, criteria: PacketCriteria, recipients: Vec<Recipient>) -> Self {
+        let mut pane = PacketPane {
+            dir: dir.to_string(),
+            criteria,
+            recipients,
+            ..Default::default()
+        };
+        pane.reload();
+        pane
+    }
+
+    fn reload(&mut self) {
+        self.items = self.criteria.review_list(Path::new(&self.dir));
+    }
+}
+
+impl PaneContent for PacketPane {
+    fn title(&self) -> String {
+        "Tax packet".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::ClosePacketPane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        match message {
+            PaneMessage::Event(Event::RefreshTargetDir(path) | Event::PathChanged(path)) => {
+                self.dir = path;
+                self.reload();
+            }
+            PaneMessage::Event(Event::PacketCriteriaChanged(criteria)) => {
+                self.criteria = criteria;
+                self.reload();
+            }
+            PaneMessage::Event(Event::PreferencesChanged(preferences)) => {
+                self.recipients = preferences.recipients
+            }
+            PaneMessage::Packet(PacketPaneMessage::EncryptToggled(encrypt)) => {
+                self.encrypt = encrypt
+            }
+            PaneMessage::Packet(PacketPaneMessage::ItemToggled(i, included)) => {
+                if let Some(item) = self.items.get_mut(i) {
+                    item.included = included;
+                }
+            }
+            PaneMessage::Packet(PacketPaneMessage::Exported(Ok(path))) => {
+                self.status = format!("Exported {}", path)
+            }
+            PaneMessage::Packet(PacketPaneMessage::Exported(Err(error))) => {
+                self.status = format!("Export failed: {:?}", error)
+            }
+            _ => {}
+        }
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let PacketPane {
+            criteria,
+            items,
+            recipients,
+            encrypt,
+            status,
+            year_input,
+            start_month_input,
+            keywords_input,
+            export_button,
+            scroll_state,
+            ..
+        } = self;
+
+        let included: Vec<String> = items
+            .iter()
+            .filter(|item| item.included)
+            .map(|item| item.path.to_string_lossy().to_string())
+            .collect();
+        let summary = Text::new(format!(
+            "{} of {} documents selected",
+            included.len(),
+            items.len()
+        ))
+        .size(16);
+        let list = items
+            .iter()
+            .enumerate()
+            .fold(Column::new().spacing(5), |column, (i, item)| {
+                let label = item
+                    .path
+                    .file_name()
+                    .map(|f| f.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                column.push(Checkbox::new(item.included, label, move |included| {
+                    Message::PacketPane(PacketPaneMessage::ItemToggled(i, included))
+                }))
+            });
+        let mut export = Button::new(export_button, Text::new("Export packet"))
+            .padding(10)
+            .style(style::Button::Update);
+        // Offered once recipients are set up in the settings.
+        let encrypt_checkbox = (!recipients.is_empty()).then(|| {
+            let names = recipients
+                .iter()
+                .map(|recipient| recipient.name.as_str())
+                .join(", ");
+            Checkbox::new(*encrypt, format!("Encrypt for {}", names), |encrypt| {
+                Message::PacketPane(PacketPaneMessage::EncryptToggled(encrypt))
+            })
+        });
+        if !included.is_empty() {
+            export = export.on_press(Message::ExportPacket(
+                criteria.year.clone(),
+                included,
+                if *encrypt {
+                    recipients.clone()
+                } else {
+                    Vec::new()
+                },
+            ));
+        }
+        let mut actions = Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(export);
+        if let Some(encrypt_checkbox) = encrypt_checkbox {
+            actions = actions.push(encrypt_checkbox);
+        }
+
+        Column::new()
+            .spacing(10)
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(
+                        TextInput::new(year_input, "Fiscal year", &criteria.year, |s| {
+                            Message::PacketMessage(PacketMessage::YearEdited(s))
+                        })
+                        .padding(10),
+                    )
+                    .push(
+                        TextInput::new(
+                            start_month_input,
+                            "Starts in month",
+                            &criteria.start_month,
+                            |s| Message::PacketMessage(PacketMessage::StartMonthEdited(s)),
+                        )
+                        .padding(10),
+                    )
+                    .push(
+                        TextInput::new(
+                            keywords_input,
+                            "Keywords, e.g. tax, 1099",
+                            &criteria.keywords,
+                            |s| Message::PacketMessage(PacketMessage::KeywordsEdited(s)),
+                        )
+                        .padding(10),
+                    ),
+            )
+            .push(summary)
+            .push(
+                Scrollable::new(scroll_state)
+                    .push(list)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .push(actions.push(Text::new(status.as_str()).size(16)))
+            .padding(10)
+            .into()
+    }
+}
+
+impl SyncRow {
+    fn description(&self) -> String {
+        match &self.change {
+            Change::Copy {
+                from: Side::Local,
+                filename,
+            } => format!("Copy {} to the other cabinet", filename),
+            Change::Copy {
+                from: Side::Remote,
+                filename,
+            } => format!("Copy {} from the other cabinet", filename),
+            Change::Rename {
+                on: Side::Local,
+                old,
+                new,
+            } => format!("Rename {} to {} in the other cabinet", old, new),
+            Change::Rename {
+                on: Side::Remote,
+                old,
+                new,
+            } => format!("Rename {} to {} here", old, new),
+            Change::Delete {
+                on: Side::Local,
+                filename,
+            } => format!("Delete {} from the other cabinet", filename),
+            Change::Delete {
+                on: Side::Remote,
+                filename,
+            } => format!("Delete {} here", filename),
+            Change::Conflict { filename, .. } => {
+                format!("{} changed in both cabinets, keep", filename)
+            }
+        }
+    }
+}
+
+impl PaneContent for SyncPane {
+    fn title(&self) -> String {
+        "Sync".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::CloseSyncPane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        match message {
+            PaneMessage::Event(Event::PathChanged(path)) => {
+                // The changes were found for the previous cabinet.
+                self.rows.clear();
+                self.remote = sync::last_remote(&path).unwrap_or_default();
+            }
+            PaneMessage::Sync(SyncPaneMessage::RemoteEdited(remote)) => {
+                self.remote = remote;
+                self.rows.clear();
+            }
+            PaneMessage::Sync(SyncPaneMessage::Planned(Ok(changes))) => {
+                self.status = if changes.is_empty() {
+                    "Both cabinets have the same documents".to_string()
+                } else {
+                    format!("{} changes to review", changes.len())
+                };
+                self.rows = changes
+                    .into_iter()
+                    .map(|change| SyncRow {
+                        change,
+                        apply: true,
+                    })
+                    .collect();
+            }
+            PaneMessage::Sync(SyncPaneMessage::Toggled(i, apply)) => {
+                if let Some(row) = self.rows.get_mut(i) {
+                    row.apply = apply;
+                }
+            }
+            PaneMessage::Sync(SyncPaneMessage::Keep(i, side)) => {
+                if let Some(SyncRow {
+                    change: Change::Conflict { keep, .. },
+                    ..
+                }) = self.rows.get_mut(i)
+                {
+                    *keep = side;
+                }
+            }
+            PaneMessage::Sync(SyncPaneMessage::Synced(Ok(count))) => {
+                self.rows.clear();
+                self.status = format!("Applied {} changes", count);
+            }
+            PaneMessage::Sync(
+                SyncPaneMessage::Planned(Err(error)) | SyncPaneMessage::Synced(Err(error)),
+            ) => self.status = format!("Sync failed: {:?}", error),
+            _ => {}
+        }
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let SyncPane {
+            remote,
+            rows,
+            status,
+            remote_input,
+            compare_button,
+            apply_button,
+            scroll_state,
+        } = self;
+
+        let list = rows
+            .iter()
+            .enumerate()
+            .fold(Column::new().spacing(5), |column, (i, row)| {
+                let mut line =
+                    Row::new()
+                        .spacing(10)
+                        .align_items(Align::Center)
+                        .push(Checkbox::new(row.apply, row.description(), move |apply| {
+                            Message::SyncPane(SyncPaneMessage::Toggled(i, apply))
+                        }));
+                if let Change::Conflict { keep, .. } = row.change {
+                    for (side, label) in [(Side::Local, "this one"), (Side::Remote, "the other")] {
+                        line = line.push(Radio::new(side, label, Some(keep), move |side| {
+                            Message::SyncPane(SyncPaneMessage::Keep(i, side))
+                        }));
+                    }
+                }
+                column.push(line)
+            });
+        let mut compare = Button::new(compare_button, Text::new("Compare"))
+            .padding(10)
+            .style(style::Button::Refresh);
+        if !remote.trim().is_empty() {
+            compare = compare.on_press(Message::PlanSync(remote.trim().to_string()));
+        }
+        let changes: Vec<Change> = rows
+            .iter()
+            .filter(|row| row.apply)
+            .map(|row| row.change.clone())
+            .collect();
+        let mut apply = Button::new(apply_button, Text::new("Apply"))
+            .padding(10)
+            .style(style::Button::Update);
+        if !changes.is_empty() {
+            apply = apply.on_press(Message::ApplySync(remote.trim().to_string(), changes));
+        }
+
+        Column::new()
+            .spacing(10)
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(
+                        TextInput::new(remote_input, "Other cabinet, e.g. on a NAS", remote, |s| {
+                            Message::SyncPane(SyncPaneMessage::RemoteEdited(s))
+                        })
+                        .padding(10),
+                    )
+                    .push(compare),
+            )
+            .push(
+                Scrollable::new(scroll_state)
+                    .push(list)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(apply)
+                    .push(Text::new(status.as_str()).size(16)),
+            )
+            .padding(10)
+            .into()
+    }
+}
+
+impl BackupPane {
+    fn new(backup_dir: &str, cabinet: &str) -> Self {
+        let mut pane = BackupPane {
+            backup_dir: backup_dir.to_string(),
+            restore_dir: cabinet.to_string(),
+            ..Default::default()
+        };
+        pane.reload();
+        pane
+    }
+
+    fn reload(&mut self) {
+        self.snapshots = backup::snapshots(Path::new(&self.backup_dir));
+        self.selected = None;
+    }
+
+    fn passphrase(&self) -> Option<String> {
+        Some(self.passphrase.clone()).filter(|passphrase| !passphrase.is_empty())
+    }
+}
+
+impl PaneContent for BackupPane {
+    fn title(&self) -> String {
+        "Backup".to_string()
+    }
+
+    fn close_message(&self, pane: Pane) -> Option<Message> {
+        Some(Message::CloseBackupPane(pane))
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        match message {
+            PaneMessage::Event(Event::PreferencesChanged(preferences))
+                if preferences.backup_dir != self.backup_dir =>
+            {
+                self.backup_dir = preferences.backup_dir;
+                self.reload();
+            }
+            PaneMessage::Backup(BackupPaneMessage::PassphraseEdited(passphrase)) => {
+                self.passphrase = passphrase
+            }
+            PaneMessage::Event(Event::Locked) => self.passphrase.clear(),
+            PaneMessage::Backup(BackupPaneMessage::SnapshotSelected(i)) => self.selected = Some(i),
+            PaneMessage::Backup(BackupPaneMessage::RestoreDirEdited(dir)) => self.restore_dir = dir,
+            PaneMessage::Backup(BackupPaneMessage::BackedUp(Ok(created))) => {
+                self.reload();
+                self.status = format!(
+                    "Wrote {}, {} new documents",
+                    created.snapshot, created.added
+                );
+            }
+            PaneMessage::Backup(BackupPaneMessage::Restored(Ok(restored))) => {
+                self.status = format!("Restored {} documents", restored)
+            }
+            PaneMessage::Backup(
+                BackupPaneMessage::BackedUp(Err(error)) | BackupPaneMessage::Restored(Err(error)),
+            ) => self.status = format!("Failed: {:?}", error),
+            PaneMessage::Backup(BackupPaneMessage::ChecksumsWritten(written)) => {
+                self.status = match written {
+                    Ok(hashed) => format!("Wrote {} hashes to {}", hashed, checksums::FILENAME),
+                    Err(error) => format!("Couldn't write {}: {:?}", checksums::FILENAME, error),
+                }
+            }
+            PaneMessage::Backup(BackupPaneMessage::ChecksumsVerified(verified)) => {
+                self.status = match verified {
+                    Ok(report) if report.passed() => format!("All documents match: {}", report),
+                    Ok(report) => format!(
+                        "{}: {}",
+                        report,
+                        report
+                            .changed
+                            .iter()
+                            .chain(&report.missing)
+                            .take(5)
+                            .join(", ")
+                    ),
+                    Err(error) => format!("Couldn't check {}: {:?}", checksums::FILENAME, error),
+                }
+            }
+            PaneMessage::Backup(BackupPaneMessage::SiteWritten(written)) => {
+                self.status = match written {
+                    Ok(listed) => format!("Listed {} documents in {}", listed, site::FILENAME),
+                    Err(error) => format!("Couldn't write {}: {:?}", site::FILENAME, error),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn view(&mut self, _pane: Pane) -> Element<'_, Message> {
+        let passphrase = self.passphrase();
+        let BackupPane {
+            backup_dir,
+            passphrase: passphrase_value,
+            snapshots,
+            selected,
+            restore_dir,
+            status,
+            backup_dir_input,
+            passphrase_input,
+            backup_button,
+            restore_dir_input,
+            restore_button,
+            write_checksums_button,
+            verify_checksums_button,
+            write_site_button,
+            scroll_state,
+        } = self;
+
+        let mut backup = Button::new(backup_button, Text::new("Back up now"))
+            .padding(10)
+            .style(style::Button::Update);
+        if !backup_dir.trim().is_empty() {
+            backup = backup.on_press(Message::Backup(
+                backup_dir.trim().to_string(),
+                passphrase.clone(),
+            ));
+        }
+        let list =
+            snapshots
+                .iter()
+                .enumerate()
+                .fold(Column::new().spacing(5), |column, (i, snapshot)| {
+                    column.push(Radio::new(i, snapshot, *selected, |i| {
+                        Message::BackupPane(BackupPaneMessage::SnapshotSelected(i))
+                    }))
+                });
+        let mut restore = Button::new(restore_button, Text::new("Restore"))
+            .padding(10)
+            .style(style::Button::Refresh);
+        if let (Some(snapshot), false) = (
+            selected.and_then(|i| snapshots.get(i)),
+            restore_dir.trim().is_empty(),
+        ) {
+            restore = restore.on_press(Message::Restore(
+                backup_dir.trim().to_string(),
+                snapshot.clone(),
+                passphrase,
+                restore_dir.trim().to_string(),
+            ));
+        }
+
+        Column::new()
+            .spacing(10)
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(
+                        TextInput::new(backup_dir_input, "Backup folder", backup_dir, |s| {
+                            Message::PreferencesMessage(PreferencesMessage::BackupDirEdited(s))
+                        })
+                        .padding(10),
+                    )
+                    .push(
+                        TextInput::new(
+                            passphrase_input,
+                            "Passphrase, to encrypt",
+                            passphrase_value,
+                            |s| Message::BackupPane(BackupPaneMessage::PassphraseEdited(s)),
+                        )
+                        .password()
+                        .padding(10),
+                    )
+                    .push(backup),
+            )
+            .push(Text::new("Snapshots").size(16))
+            .push(
+                Scrollable::new(scroll_state)
+                    .push(list)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(
+                        TextInput::new(
+                            restore_dir_input,
+                            "Restore into folder",
+                            restore_dir,
+                            |s| Message::BackupPane(BackupPaneMessage::RestoreDirEdited(s)),
+                        )
+                        .padding(10),
+                    )
+                    .push(restore),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(Text::new("Checksums").size(16))
+                    .push(
+                        Button::new(write_checksums_button, Text::new("Write SHA256SUMS"))
+                            .on_press(Message::WriteChecksums)
+                            .padding(10)
+                            .style(style::Button::Refresh),
+                    )
+                    .push(
+                        Button::new(verify_checksums_button, Text::new("Verify"))
+                            .on_press(Message::VerifyChecksums)
+                            .padding(10)
+                            .style(style::Button::Refresh),
+                    ),
             )
-            .push(Text::new(&self.preview_image_path))
             .push(
-                Scrollable::new(&mut self.scroll_state)
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(Text::new("Browsable without the app").size(16))
                     .push(
-                        Row::new()
-                            .push(Image::new(&self.preview_image_path))
-                            .align_items(Align::Center)
-                            .width(Length::Fill),
-                    )
-                    .width(Length::Fill),
+                        Button::new(write_site_button, Text::new("Write index.html"))
+                            .on_press(Message::WriteSite)
+                            .padding(10)
+                            .style(style::Button::Refresh),
+                    ),
             )
+            .push(Text::new(status.as_str()).size(16))
             .padding(10)
             .into()
     }
 }
 
+impl DocPane {
+    /// Where the anchor is in the list as shown, if it is on it.
+    fn anchor_row(&self) -> Option<usize> {
+        let anchor = self.docs.get(self.anchor?)?;
+        self.listed().iter().position(|path| *path == anchor.path)
+    }
+
+    /// Changes what the list shows with `change`, scrolling so the anchor stays where it
+    /// was on screen, or comes into view if it wasn't listed before.
+    fn keep_in_view(&mut self, change: impl FnOnce(&mut Self)) {
+        let before = self.anchor_row();
+        change(self);
+        let after = match self.anchor_row() {
+            Some(after) => after as f32,
+            None => return,
+        };
+        let offset = match before {
+            Some(before) => scroll_offset(&self.scroll) + (after - before as f32) * ROW_HEIGHT,
+            None => after * ROW_HEIGHT,
+        };
+        scroll_to_offset(&mut self.scroll, offset.max(0.0));
+    }
+
+    /// Numbers the document being edited after the pages of its group already filed,
+    /// e.g. 3 after `_1` and `_2`, unless it has a page or one was typed.
+    fn suggest_page(&mut self, id: DocumentId) {
+        let doc = match self.docs.get(id) {
+            Some(doc) if doc.page.is_none() && !doc.draft.page_typed => doc,
+            _ => return,
+        };
+        let institution = utils::to_camelcase(&doc.draft.institution);
+        let title = utils::to_camelcase(&doc.draft.title);
+        let date = doc.draft.date.trim();
+        let page = match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            // Only the documents of that day can be of the group.
+            Ok(day) => {
+                let others = self
+                    .docs
+                    .dated(day..=day)
+                    .filter(|other| other.id != id)
+                    .map(|other| other.filename.as_str());
+                utils::next_page(others, date, &institution, &title)
+            }
+            Err(_) => 1,
+        };
+        self.docs.update(id, |doc| {
+            doc.draft.page = page.to_string();
+            doc.draft.page_hint = match page {
+                1 => String::new(),
+                _ => format!("Page {} of the group is filed already", page - 1),
+            };
+        });
+    }
+}
+
 impl PaneContent for DocPane {
-    fn update(&mut self, message: Message) {
+    fn title(&self) -> String {
+        "Documents".to_string()
+    }
+
+    fn update(&mut self, message: PaneMessage) {
+        if let PaneMessage::Doc(DocPaneMessage::Doc(id, _)) = &message {
+            self.anchor = Some(*id);
+        }
         match message {
-            Message::Loaded(_) => {}
-            Message::Saved(_) => {}
-            Message::RefreshTargetDir(path) => self.docs = utils::read_docs(&path),
-            Message::PathChanged(path) => self.docs = utils::read_docs(&path),
-            Message::FilterChanged(filter) => {
-                self.filter = filter;
-            }
-            Message::DocMessage(i, DocMessage::ConfirmDelete) => {
-                if let Some(doc) = self.docs.get_mut(i) {
+            PaneMessage::Event(Event::RefreshTargetDir(path) | Event::PathChanged(path)) => {
+                self.docs = Docs::from(listing::rescan(&path, self.docs.take()));
+                let catalog = Catalog::load(Path::new(&path));
+                self.searches = catalog.searches;
+                self.badges = Badge::load(Path::new(&path), &catalog.institutions);
+                self.owners = catalog.owners;
+                if !self
+                    .owners
+                    .iter()
+                    .any(|owner| Some(&owner.name) == self.owner.as_ref())
+                {
+                    self.owner = None;
+                }
+                self.field_defs = fields::load(Path::new(&path));
+                self.dir = path;
+            }
+            PaneMessage::Doc(DocPaneMessage::SearchSubmitted(query))
+                if !query.trim().is_empty() =>
+            {
+                self.searches = catalog::record_search(Path::new(&self.dir), &query)
+            }
+            PaneMessage::Doc(DocPaneMessage::SuggestionPicked(query)) => {
+                self.searches = catalog::record_search(Path::new(&self.dir), &query);
+                self.query_error = None;
+                self.query = query;
+            }
+            PaneMessage::Doc(DocPaneMessage::PinSearch(query, pinned)) => {
+                self.searches = catalog::record_pin(Path::new(&self.dir), &query, pinned)
+            }
+            PaneMessage::Doc(DocPaneMessage::CancelEditing) => {
+                self.docs.update_all(|doc| {
+                    if matches!(doc.state, DocState::Editing { .. }) {
+                        doc.update(DocMessage::Cancel);
+                    }
+                });
+            }
+            PaneMessage::Doc(DocPaneMessage::FilterChanged(filter)) => {
+                self.keep_in_view(|pane| pane.filter = filter);
+            }
+            PaneMessage::Doc(DocPaneMessage::OwnerFilterChanged(owner)) => {
+                self.keep_in_view(|pane| pane.owner = owner);
+            }
+            PaneMessage::Doc(DocPaneMessage::Restore(session)) => {
+                self.filter = session.filter;
+                self.owner = session.owner;
+                self.query = session.query;
+                let selected = session.selected;
+                self.docs
+                    .update_all(|doc| doc.selected = selected.contains(&doc.filename));
+                scroll_to_offset(&mut self.scroll, session.scroll);
+            }
+            PaneMessage::Doc(DocPaneMessage::QueryEdited(query)) => {
+                if query.trim().is_empty() {
+                    self.keep_in_view(|pane| pane.results = None);
+                    self.query_error = None;
+                }
+                self.query = query;
+            }
+            PaneMessage::Doc(DocPaneMessage::Searched(query, found)) if query == self.query => {
+                match found {
+                    Ok(found) => {
+                        self.keep_in_view(|pane| {
+                            pane.results = Some(SearchResults { query, found })
+                        });
+                        self.query_error = None;
+                    }
+                    Err(error) => self.query_error = Some(error),
+                }
+            }
+            PaneMessage::Event(Event::PreferencesChanged(preferences)) => {
+                self.write_pdf_metadata = preferences.write_pdf_metadata;
+                self.external_tools = preferences.external_tools;
+                self.hooks = preferences.hooks;
+                self.can_archive = !preferences.archive_dir.trim().is_empty();
+            }
+            PaneMessage::Doc(DocPaneMessage::EditDocument(filename)) => {
+                // Whatever the list shows, the document has to be on it.
+                self.filter = Filter::All;
+                self.owner = None;
+                self.query.clear();
+                self.results = None;
+                self.query_error = None;
+                let path = Path::new(&self.dir).join(&filename);
+                if let Some(doc) = self.docs.find(&path.to_string_lossy()) {
+                    let id = doc.id;
+                    self.update(PaneMessage::Doc(DocPaneMessage::Doc(id, DocMessage::Edit)));
+                }
+            }
+            PaneMessage::Doc(DocPaneMessage::Doc(id, DocMessage::Edit)) => {
+                // The fields may have changed since the cabinet was listed.
+                self.field_defs = fields::load(Path::new(&self.dir));
+                self.docs.update(id, |doc| doc.update(DocMessage::Edit));
+                self.suggest_page(id);
+            }
+            PaneMessage::Doc(DocPaneMessage::Doc(
+                id,
+                doc_message @ (DocMessage::DateEdited(_)
+                | DocMessage::InstitutionEdited(_)
+                | DocMessage::TitleEdited(_)),
+            )) => {
+                self.docs.update(id, |doc| doc.update(doc_message));
+                self.suggest_page(id);
+            }
+            PaneMessage::Doc(DocPaneMessage::Doc(id, DocMessage::FinishEdition)) => {
+                let (field_defs, hooks) = (&self.field_defs, &self.hooks);
+                let write_pdf_metadata = self.write_pdf_metadata;
+                self.docs.update(id, |doc| {
+                    let values = match fields::check_all(field_defs, &doc.draft.fields) {
+                        Ok(values) => values,
+                        Err(error) => {
+                            doc.draft.field_error = Some(error.to_string());
+                            return;
+                        }
+                    };
+                    doc.update(DocMessage::FinishEdition);
+                    if matches!(doc.state, DocState::Editing { .. }) {
+                        // The rename failed, the document is still being edited.
+                        return;
+                    }
+                    if values != doc.fields {
+                        catalog::record_fields(Path::new(&doc.path), values.clone());
+                        doc.fields = values;
+                    }
+                    if write_pdf_metadata && doc.extension == "pdf" {
+                        let path = Path::new(&doc.path);
+                        let date = doc.date.map(|date| date.format("%Y-%m-%d").to_string());
+                        match metadata::write(
+                            path,
+                            date.as_deref().unwrap_or_default(),
+                            doc.institution.as_deref().unwrap_or_default(),
+                            doc.name.as_deref().unwrap_or_default(),
+                        ) {
+                            Ok(()) => catalog::record_rewrite(path),
+                            Err(error) => {
+                                warn!(event = "metadata_write_failed", file = %doc.path, ?error)
+                            }
+                        }
+                    }
+                    let payload = Payload::of(HookEvent::PostNormalize, Path::new(&doc.path));
+                    hooks::spawn(hooks, payload);
+                });
+            }
+            PaneMessage::Doc(DocPaneMessage::Viewed(path, viewed)) => {
+                if let Some(id) = self.docs.find(&path).map(|doc| doc.id) {
+                    self.docs.update(id, |doc| doc.viewed = Some(viewed));
+                }
+            }
+            PaneMessage::Doc(DocPaneMessage::Doc(id, DocMessage::ConfirmDelete)) => {
+                let hooks = &self.hooks;
+                let trashed = self.docs.update(id, |doc| {
                     doc.update(DocMessage::ConfirmDelete);
-                    fs::remove_file(doc.clone().path).unwrap();
+                    // Taken before the catalog forgets the document.
+                    let payload = Payload::of(HookEvent::PostDelete, Path::new(&doc.path));
+                    if let Err(error) = trash::trash(Path::new(&doc.path), SystemTime::now()) {
+                        warn!(event = "delete_failed", file = %doc.path, ?error);
+                        return false;
+                    }
+                    catalog::record_delete(Path::new(&doc.path));
+                    locks::unlock_document(Path::new(&doc.path));
+                    hooks::spawn(hooks, payload);
+                    true
+                });
+                if trashed == Some(true) {
+                    self.docs.remove(id);
+                }
+            }
+            PaneMessage::Doc(DocPaneMessage::Doc(id, DocMessage::Locate)) => {
+                let path = match self.docs.get(id) {
+                    Some(doc) => PathBuf::from(&doc.path),
+                    None => return,
+                };
+                match (catalog::record_relink(&path), path.parent()) {
+                    (Some(_), Some(dir)) => {
+                        let dir = dir.to_string_lossy().to_string();
+                        self.docs = Docs::from(listing::rescan(&dir, self.docs.take()))
+                    }
+                    _ => {
+                        self.docs.update(id, |doc| doc.update(DocMessage::Locate));
+                    }
                 }
-                self.docs.remove(i);
             }
-            Message::DocMessage(i, doc_message) => {
-                if let Some(doc) = self.docs.get_mut(i) {
-                    doc.update(doc_message);
+            PaneMessage::Doc(DocPaneMessage::Doc(id, DocMessage::RemoveFromCatalog)) => {
+                if let Some(doc) = self.docs.remove(id) {
+                    catalog::record_delete(Path::new(&doc.path));
                 }
             }
+            PaneMessage::Doc(DocPaneMessage::Doc(id, doc_message)) => {
+                self.docs.update(id, |doc| doc.update(doc_message));
+            }
             _ => {}
         }
     }
 
-    fn view(&mut self, pane: Pane) -> Element<Message> {
+    fn view(&mut self, pane: Pane) -> Element<'_, Message> {
         let DocPane {
             docs,
             filter,
+            owner,
+            owners,
             controls,
+            external_tools,
+            field_defs,
+            can_archive,
+            query,
+            results,
+            query_error,
+            searches,
+            search_input,
+            suggestion_buttons,
+            hit_buttons,
+            badges,
             ..
         } = self;
 
-        let controls = controls.view(&docs, *filter);
-        let filtered_docs = docs.iter().filter(|doc| filter.matches(doc));
-
-        let docs: Element<_> = if filtered_docs.count() > 0 {
-            docs.iter_mut()
-                .enumerate()
-                .filter(|(_, doc)| filter.matches(doc))
-                .fold(Column::new().spacing(0), |column, (i, doc)| {
-                    column.push(
-                        doc.view(&pane)
-                            .map(move |message| Message::DocMessage(i, message)),
+        let mut search = Column::new().spacing(5).push(
+            TextInput::new(
+                search_input,
+                "Search, e.g. institution:Chase AND date:2021 AND (statement OR invoice)",
+                query,
+                |query| Message::DocPane(DocPaneMessage::QueryEdited(query)),
+            )
+            .on_submit(Message::DocPane(DocPaneMessage::SearchSubmitted(
+                query.clone(),
+            )))
+            .padding(10)
+            .size(16),
+        );
+        if let Some(error) = query_error {
+            search = search.push(Text::new(error.to_string()).size(14).color([0.8, 0.2, 0.2]));
+        }
+        let suggestions = catalog::suggestions(searches, query);
+        if suggestion_buttons.len() < suggestions.len() {
+            suggestion_buttons.resize_with(suggestions.len(), Default::default);
+        }
+        for (suggestion, (use_button, pin_button)) in suggestions
+            .into_iter()
+            .take(SUGGESTIONS)
+            .zip(suggestion_buttons.iter_mut())
+        {
+            let uses = match suggestion.uses {
+                1 => "searched once".to_string(),
+                uses => format!("searched {} times", uses),
+            };
+            search = search.push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(
+                        Button::new(use_button, Text::new(&suggestion.query).size(14))
+                            .padding(4)
+                            .style(style::Button::Filter { selected: false })
+                            .on_press(Message::DocPane(DocPaneMessage::SuggestionPicked(
+                                suggestion.query.clone(),
+                            ))),
                     )
-                })
+                    .push(Text::new(uses).size(14).color([0.5, 0.5, 0.5]))
+                    .push(
+                        Button::new(
+                            pin_button,
+                            Text::new(if suggestion.pinned { "unpin" } else { "pin" }).size(12),
+                        )
+                        .padding(4)
+                        .style(style::Button::Icon)
+                        .on_press(Message::DocPane(
+                            DocPaneMessage::PinSearch(suggestion.query.clone(), !suggestion.pinned),
+                        )),
+                    ),
+            );
+        }
+        let controls = controls.view(docs, *filter, owner.as_deref(), owners, *can_archive);
+        let listed = docs.listed_mut(*filter, owner.as_deref(), results.as_ref());
+        if hit_buttons.len() < listed.len() {
+            hit_buttons.resize_with(listed.len(), Default::default);
+        }
+
+        let docs: Element<_> = if !listed.is_empty() {
+            listed
+                .into_iter()
+                .zip(hit_buttons.iter_mut())
+                .fold(
+                    Column::new().spacing(0),
+                    |column, ((_, doc), hit_button)| {
+                        let hit = results.as_ref().and_then(|results| results.hit(doc));
+                        let path = doc.path.clone();
+                        let id = doc.id;
+                        let column = column.push(
+                            doc.view(&pane, external_tools, field_defs, owners, badges)
+                                .map(move |message| {
+                                    Message::DocPane(DocPaneMessage::Doc(id, message))
+                                }),
+                        );
+                        match hit {
+                            Some(hit) => column.push(snippet(hit_button, path, hit)),
+                            None => column,
+                        }
+                    },
+                )
                 .into()
+        } else if let Some(results) = results {
+            empty_message(&format!("Nothing found for \"{}\".", results.query.trim()))
         } else {
             empty_message(match filter {
                 Filter::All => "No files found...",
                 Filter::Normalized => "No files found...",
                 Filter::Unnormalized => "No files found...",
+                Filter::Favorites => "No favorites yet. Star a document to pin it here.",
+                Filter::Recent => "Nothing previewed lately.",
+                Filter::Archived => "Nothing archived. Tick old documents to move them away.",
+                Filter::Label(_) => {
+                    "Nothing has this label. Click the stripe of a row to pick one."
+                }
             })
         };
 
         let content = Column::new()
             .max_width(800)
             .spacing(20)
+            .push(search)
             .push(controls)
             .push(docs);
 
@@ -187,273 +7619,1931 @@ impl PaneContent for DocPane {
             .push(Container::new(content).width(Length::Fill).center_x())
             .into()
     }
+
+    fn neighbours(&self, path: &str) -> (Option<String>, Option<String>) {
+        let listed = self.listed();
+        match listed.iter().position(|listed| listed == path) {
+            Some(i) => (
+                i.checked_sub(1).map(|i| listed[i].clone()),
+                listed.get(i + 1).cloned(),
+            ),
+            None => (None, None),
+        }
+    }
+
+    fn listed(&self) -> Vec<String> {
+        self.docs
+            .listed(self.filter, self.owner.as_deref(), self.results.as_ref())
+            .map(|doc| doc.path.clone())
+            .collect()
+    }
+
+    fn session(&self) -> Option<Session> {
+        Some(Session {
+            filter: self.filter,
+            query: self.query.clone(),
+            owner: self.owner.clone(),
+            scroll: scroll_offset(&self.scroll),
+            selected: self
+                .docs
+                .selected()
+                .map(|doc| doc.filename.clone())
+                .collect(),
+            previewed: None,
+        })
+    }
 }
 
 impl Application for FileCabinet {
     type Executor = iced::executor::Default;
     type Message = Message;
-    type Flags = ();
+    type Flags = Vec<String>;
 
-    fn new(_flags: ()) -> (FileCabinet, Command<Message>) {
+    fn new(documents: Vec<String>) -> (FileCabinet, Command<Message>) {
         (
-            FileCabinet::Loading,
+            FileCabinet::Loading(documents),
             Command::perform(SavedState::load(), Message::Loaded),
         )
     }
 
     fn title(&self) -> String {
         let dirty = match self {
-            FileCabinet::Loading => false,
-            FileCabinet::Loaded(state) => state.dirty,
+            FileCabinet::Loading(_) | FileCabinet::Onboarding(_) => false,
+            FileCabinet::Loaded(state) => state.saves_written < state.saves,
         };
 
         format!("Filecabinet {}", if dirty { "*" } else { "" })
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    fn subscription(&self) -> Subscription<Message> {
+        use iced_native::{keyboard, mouse, window};
+        let events = iced_native::subscription::events_with(|event, _status| match event {
+            iced_native::Event::Window(window::Event::Resized { width, .. }) => {
+                Some(Message::WindowResized(width))
+            }
+            iced_native::Event::Keyboard(keyboard::Event::KeyPressed {
+                key_code: keyboard::KeyCode::L,
+                modifiers,
+            }) if modifiers.is_command_pressed() => Some(Message::LockNow),
+            iced_native::Event::Keyboard(keyboard::Event::KeyPressed {
+                key_code: keyboard::KeyCode::Escape,
+                ..
+            }) => Some(Message::DocPane(DocPaneMessage::CancelEditing)),
+            // Moving the mouse over the window doesn't count as using it.
+            iced_native::Event::Keyboard(keyboard::Event::KeyPressed { .. })
+            | iced_native::Event::Mouse(
+                mouse::Event::ButtonPressed(_) | mouse::Event::WheelScrolled { .. },
+            ) => Some(Message::Activity),
+            _ => None,
+        });
+        let mut subscriptions = vec![events];
+        // Listening from the start, so launching the app again can't start a second one.
+        #[cfg(unix)]
+        subscriptions.push(Subscription::from_recipe(control::Control).map(Message::Control));
+        let state = match self {
+            FileCabinet::Loaded(state) => state,
+            _ => return Subscription::batch(subscriptions),
+        };
+        if state.preferences.auto_lock_minutes > 0 {
+            subscriptions.push(iced::time::every(Duration::from_secs(15)).map(Message::Tick));
+        }
+        if state.on_battery {
+            subscriptions
+                .push(iced::time::every(Duration::from_secs(60)).map(|_| Message::CheckPower));
+        }
+        if state.preview_cache.is_loading() {
+            subscriptions.push(
+                iced::time::every(Duration::from_millis(150))
+                    .map(|_| Message::PreviewPane(PreviewMessage::Spin)),
+            );
+        }
+        if !state.preferences.drop_folder.trim().is_empty() {
+            subscriptions
+                .push(iced::time::every(Duration::from_secs(60)).map(|_| Message::PullScans));
+        }
+        if !state.import_profile.source_dir.trim().is_empty() {
+            subscriptions
+                .push(iced::time::every(Duration::from_secs(30)).map(|_| Message::WatchDownloads));
+        }
+        if state.preferences.share_enabled && !state.preferences.share_token.is_empty() {
+            subscriptions.push(
+                Subscription::from_recipe(share::Share {
+                    port: state.preferences.share_port,
+                    token: state.preferences.share_token.clone(),
+                    dir: state.intake_dir(),
+                })
+                .map(Message::Shared),
+            );
+        }
+        if let Some((format, _, _)) = state.export {
+            subscriptions.push(
+                Subscription::from_recipe(export::Export {
+                    dir: state.target_dir.clone(),
+                    format,
+                })
+                .map(Message::ExportProgress),
+            );
+        }
+        #[cfg(target_os = "linux")]
+        if state.preferences.tray {
+            subscriptions.push(Subscription::from_recipe(tray::Tray).map(Message::Tray));
+        }
+        Subscription::batch(subscriptions)
+    }
+
     fn update(&mut self, message: Message) -> Command<Message> {
         match self {
-            FileCabinet::Loading => {
+            FileCabinet::Loading(documents) => {
                 match message {
                     Message::Loaded(Ok(saved_state)) => {
-                        // Create the panes so that the documents are loaded on launch.
-                        let (mut pane_state, pane) = pane_grid::State::new(Box::new(
-                            DocPane::default(),
-                        )
-                            as Box<dyn PaneContent>);
-                        // Pass the path to each doc_pane doc so it can render.
-                        for (_pane, boxed_content) in pane_state.iter_mut() {
-                            boxed_content
-                                .update(Message::PathChanged(saved_state.target_dir.clone()));
-                        }
-                        *self = FileCabinet::Loaded(State {
-                            target_dir: saved_state.target_dir,
-                            panes: pane_state,
-                            doc_pane: Some(pane),
-                            ..Default::default()
+                        let mut state = State::from_saved(saved_state);
+                        // What was loaded needn't be saved again.
+                        state.saved_json =
+                            serde_json::to_string_pretty(&state.saved_state()).unwrap_or_default();
+                        // Pick the queue up again, a paused one is only listed.
+                        let queue = if state.index_queue.status != index::Status::Idle {
+                            Command::perform(
+                                index::queue(state.target_dir.clone()),
+                                Message::IndexQueued,
+                            )
+                        } else {
+                            Command::none()
+                        };
+                        let restore = state.restore_session();
+                        let health = state.check_health();
+                        let purge = state.purge_trash();
+                        let mut commands = vec![queue, restore, health, purge];
+                        for path in documents.drain(..) {
+                            commands.push(Command::perform(
+                                control::open(path, state.target_dir.clone()),
+                                Message::ControlOpened,
+                            ));
+                        }
+                        *self = FileCabinet::Loaded(state);
+                        return Command::batch(commands);
+                    }
+                    Message::Loaded(Err(_)) => {
+                        // Nothing saved yet, so this is the first run.
+                        *self = FileCabinet::Onboarding(Onboarding::default());
+                    }
+                    _ => {}
+                }
+                Command::none()
+            }
+            FileCabinet::Onboarding(onboarding) => match message {
+                Message::OnboardingMessage(OnboardingMessage::Next)
+                    if onboarding.step == OnboardingStep::Import =>
+                {
+                    if let Err(error) = onboarding.finish() {
+                        onboarding.error = error;
+                        return Command::none();
+                    }
+                    let mut state = State::from_saved(SavedState {
+                        target_dir: onboarding.cabinet_dir.trim().to_string(),
+                        import_profile: Default::default(),
+                        packet_criteria: Default::default(),
+                        preferences: Default::default(),
+                        index_status: Default::default(),
+                        sessions: Default::default(),
+                    });
+                    let mut commands = vec![state.save()];
+                    if !onboarding.import_dir.trim().is_empty() {
+                        commands.push(Command::perform(
+                            rules::import_folder(
+                                onboarding.import_dir.trim().to_string(),
+                                state.target_dir.clone(),
+                            ),
+                            Message::Imported,
+                        ));
+                    }
+                    *self = FileCabinet::Loaded(state);
+                    Command::batch(commands)
+                }
+                Message::OnboardingMessage(onboarding_message) => {
+                    onboarding.update(onboarding_message);
+                    Command::none()
+                }
+                _ => Command::none(),
+            },
+            FileCabinet::Loaded(state) => {
+                // Actions that can't be undone wait for an answer, unless the user asked
+                // not to be asked.
+                let message = match message {
+                    Message::ConfirmationAnswered(true) => match state.pending.take() {
+                        Some(pending) => {
+                            if pending.dont_ask {
+                                state
+                                    .preferences
+                                    .update(PreferencesMessage::ConfirmationToggled(
+                                        pending.confirmation,
+                                        false,
+                                    ));
+                                state.broadcast(Event::PreferencesChanged(
+                                    state.preferences.clone(),
+                                ));
+                            }
+                            pending.message
+                        }
+                        None => return Command::none(),
+                    },
+                    Message::ConfirmationAnswered(false) => {
+                        state.pending = None;
+                        return Command::none();
+                    }
+                    Message::DontAskAgainToggled(dont_ask) => {
+                        if let Some(pending) = &mut state.pending {
+                            pending.dont_ask = dont_ask;
+                        }
+                        return Command::none();
+                    }
+                    message => match message.confirmation() {
+                        Some((confirmation, question))
+                            if state.preferences.confirms(confirmation) =>
+                        {
+                            state.pending = Some(Pending::new(confirmation, question, message));
+                            return Command::none();
+                        }
+                        _ => message,
+                    },
+                };
+                let mut commands = Vec::new();
+                let opening = state.compact().then(|| message.clone());
+
+                match message {
+                    Message::RefreshTargetDir(path) => {
+                        state.broadcast(Event::RefreshTargetDir(path))
+                    }
+                    Message::PathChanged(path) => {
+                        state.sessions = state.sessions();
+                        state.target_dir = path.clone();
+                        state.index_queue = Default::default();
+                        state.broadcast(Event::PathChanged(path));
+                        commands.push(Command::batch(vec![
+                            state.restore_session(),
+                            state.check_health(),
+                            state.purge_trash(),
+                        ]));
+                    }
+                    Message::ClosePreviewPane(pane) => {
+                        state.panes.close(&pane);
+                        state.preview_pane = Default::default();
+                        // Decrypted documents have to be unlocked again to be shown.
+                        state.preview_cache.forget_unlocked();
+                        if state.compare_pane.is_none() {
+                            state.preview_cache.clear();
+                        }
+                    }
+                    Message::DocPane(DocPaneMessage::Doc(
+                        _,
+                        DocMessage::OpenPreviewPane(path, _),
+                    ))
+                    | Message::ShowPreview(path) => {
+                        commands.push(state.show_preview(path));
+                    }
+                    Message::ShowHit(path, page) => {
+                        commands.push(state.show_preview(path.clone()));
+                        // The first page is shown already.
+                        if page > 1 {
+                            commands.push(state.show_page(path, page));
+                        }
+                    }
+                    Message::ShowPage(path, page) => commands.push(state.show_page(path, page)),
+                    Message::Compare(left, right) => {
+                        if let Some(doc_pane) = state.doc_pane {
+                            let catalog = Catalog::load(Path::new(&state.target_dir));
+                            let dhash = |path: &str| {
+                                let filename = Path::new(path).file_name()?.to_str()?;
+                                catalog.get(filename)?.dhash
+                            };
+                            let distance = dhash(&left)
+                                .zip(dhash(&right))
+                                .map(|(a, b)| similarity::distance(a, b));
+                            let compare = ComparePane::new(
+                                [left.clone(), right.clone()],
+                                [
+                                    state.preview_cache.get(&left),
+                                    state.preview_cache.get(&right),
+                                ],
+                                distance,
+                            );
+                            match state
+                                .compare_pane
+                                .and_then(|pane| state.panes.get_mut(&pane))
+                            {
+                                Some(panel) => panel.content = Box::new(compare),
+                                None => {
+                                    state.compare_pane = state
+                                        .panes
+                                        .split(
+                                            state.preferences.preview_axis(),
+                                            &doc_pane,
+                                            Panel::new(compare),
+                                        )
+                                        .map(|(pane, _)| pane);
+                                }
+                            }
+                            commands.push(state.load_previews(vec![left, right]));
+                        }
+                    }
+                    Message::CloseComparePane(pane) => {
+                        state.panes.close(&pane);
+                        state.compare_pane = None;
+                        if state.preview_pane.is_none() {
+                            state.preview_cache.clear();
+                        }
+                    }
+                    Message::OpenTagsPane(paths) => {
+                        if let Some(doc_pane) = state.doc_pane {
+                            let tags = TagsPane::new(&state.target_dir, &paths);
+                            match state.tags_pane.and_then(|pane| state.panes.get_mut(&pane)) {
+                                Some(panel) => panel.content = Box::new(tags),
+                                None => {
+                                    state.tags_pane = state
+                                        .panes
+                                        .split(
+                                            pane_grid::Axis::Horizontal,
+                                            &doc_pane,
+                                            Panel::new(tags),
+                                        )
+                                        .map(|(pane, _)| pane);
+                                }
+                            }
+                        }
+                    }
+                    Message::OpenBundlePane(paths, extra) => {
+                        if let Some(doc_pane) = state.doc_pane {
+                            let bundle = BundlePane::new(paths, extra);
+                            match state
+                                .bundle_pane
+                                .and_then(|pane| state.panes.get_mut(&pane))
+                            {
+                                Some(panel) => panel.content = Box::new(bundle),
+                                None => {
+                                    state.bundle_pane = state
+                                        .panes
+                                        .split(
+                                            pane_grid::Axis::Horizontal,
+                                            &doc_pane,
+                                            Panel::new(bundle),
+                                        )
+                                        .map(|(pane, _)| pane);
+                                }
+                            }
+                        }
+                    }
+                    Message::CloseImportReportPane(pane) => {
+                        state.panes.close(&pane);
+                        state.import_report_pane = None;
+                    }
+                    Message::ImportReportPane(report_message) => {
+                        state.send(PaneMessage::ImportReport(report_message))
+                    }
+                    Message::ExportImportReport(report) => {
+                        state.send(PaneMessage::ImportReport(ImportReportMessage::Export));
+                        commands.push(Command::perform(
+                            report::export(state.target_dir.clone(), report),
+                            |exported| {
+                                Message::ImportReportPane(ImportReportMessage::Exported(exported))
+                            },
+                        ));
+                    }
+                    Message::CheckHealth => commands.push(state.check_health()),
+                    Message::HealthChecked(health) => state.show_health(health),
+                    Message::CloseHealthPane(pane) => {
+                        state.panes.close(&pane);
+                        state.health_pane = None;
+                    }
+                    Message::FixMissing => {
+                        state.send(PaneMessage::Health(HealthMessage::Fixing));
+                        commands.push(Command::perform(
+                            health::fix_missing(state.target_dir.clone()),
+                            |fixed| Message::HealthFixed(fixed.map(|_| Vec::new())),
+                        ));
+                    }
+                    Message::FixChanged(filenames) => {
+                        state.send(PaneMessage::Health(HealthMessage::Fixing));
+                        commands.push(Command::perform(
+                            health::fix_changed(state.target_dir.clone(), filenames),
+                            Message::HealthFixed,
+                        ));
+                    }
+                    Message::IndexUncatalogued(filenames) => {
+                        state.send(PaneMessage::Health(HealthMessage::Indexing));
+                        commands.push(state.index_first(&filenames));
+                    }
+                    Message::HealthFixed(fixed) => {
+                        if let Ok(reindex) = &fixed {
+                            commands.push(state.check_health());
+                            if !reindex.is_empty() {
+                                commands.push(state.index_first(reindex));
+                            }
+                        }
+                        state.send(PaneMessage::Health(HealthMessage::Fixed(fixed)));
+                        state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()));
+                    }
+                    Message::CloseBundlePane(pane) => {
+                        state.panes.close(&pane);
+                        state.bundle_pane = None;
+                    }
+                    Message::BundlePane(bundle_message) => {
+                        state.send(PaneMessage::Bundle(bundle_message))
+                    }
+                    Message::ExportBundle(paths, extra, password, recipient) => {
+                        state.send(PaneMessage::Bundle(BundleMessage::Export));
+                        let stamp = recipient.map(|recipient| {
+                            bundle::stamp_text(
+                                &state.preferences.stamp_template,
+                                &recipient,
+                                chrono::Local::today().naive_local(),
+                            )
                         });
+                        commands.push(Command::perform(
+                            bundle::export(state.target_dir.clone(), paths, extra, password, stamp),
+                            |exported| Message::BundlePane(BundleMessage::Exported(exported)),
+                        ));
+                    }
+                    Message::OpenTagManagerPane => {
+                        if let (Some(doc_pane), None) = (&state.doc_pane, &state.tag_manager_pane) {
+                            if let Some((tag_manager_pane, _split)) = state.panes.split(
+                                pane_grid::Axis::Vertical,
+                                doc_pane,
+                                Panel::new(TagManagerPane::new(&state.target_dir)),
+                            ) {
+                                state.tag_manager_pane = Some(tag_manager_pane);
+                            }
+                        }
+                    }
+                    Message::CloseTagManagerPane(pane) => {
+                        state.panes.close(&pane);
+                        state.tag_manager_pane = None;
+                    }
+                    Message::TagManagerPane(tag_manager_message) => {
+                        let changed = matches!(
+                            tag_manager_message,
+                            TagManagerMessage::Rename | TagManagerMessage::ConfirmDelete
+                        );
+                        state.send(PaneMessage::TagManager(tag_manager_message));
+                        if changed {
+                            state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()));
+                        }
+                    }
+                    Message::CloseTagsPane(pane) => {
+                        state.panes.close(&pane);
+                        state.tags_pane = None;
+                    }
+                    Message::TagsPane(tags_message) => {
+                        let changed =
+                            matches!(tags_message, TagsPaneMessage::Apply | TagsPaneMessage::Undo);
+                        state.send(PaneMessage::Tags(tags_message));
+                        if changed {
+                            state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()));
+                        }
+                    }
+                    Message::ComparePane(compare_message) => {
+                        let show_diff =
+                            matches!(compare_message, CompareMessage::DiffToggled(true));
+                        state.send(PaneMessage::Compare(compare_message));
+                        let compared = state
+                            .compare_pane
+                            .and_then(|pane| state.panes.get(&pane))
+                            .and_then(|panel| panel.content.compared());
+                        if let (true, Some([a, b])) = (show_diff, compared) {
+                            commands.push(Command::perform(
+                                preview::diff(a, b),
+                                |(a, b, handle)| {
+                                    Message::ComparePane(CompareMessage::DiffLoaded(a, b, handle))
+                                },
+                            ));
+                        }
+                    }
+                    Message::OpenDuplicatesPane => {
+                        if let (Some(doc_pane), None) = (&state.doc_pane, &state.duplicates_pane) {
+                            if let Some((duplicates_pane, _split)) = state.panes.split(
+                                pane_grid::Axis::Horizontal,
+                                doc_pane,
+                                Panel::new(DuplicatesPane::new(&state.target_dir)),
+                            ) {
+                                state.duplicates_pane = Some(duplicates_pane);
+                            }
+                        }
+                    }
+                    Message::CloseDuplicatesPane(pane) => {
+                        state.panes.close(&pane);
+                        state.duplicates_pane = None;
+                    }
+                    Message::PreviewLoaded(path, handle) => {
+                        match &handle {
+                            Some(handle) => {
+                                state.preview_cache.insert(path.clone(), handle.clone())
+                            }
+                            None => state.preview_cache.failed(&path),
+                        }
+                        let failed = handle.is_none();
+                        state.broadcast(Event::PreviewLoaded(path.clone(), handle));
+                        if failed
+                            && utils::extension(&path) == "pdf"
+                            && pdf::is_encrypted(Path::new(&path))
+                        {
+                            state.send(PaneMessage::Preview(PreviewMessage::PasswordRequired(
+                                path.clone(),
+                            )));
+                            // Try the password stored for the institution first.
+                            if let Some(password) = OptDoc::new(&path)
+                                .institution
+                                .and_then(|institution| passwords::get(&institution))
+                            {
+                                commands.push(Command::perform(
+                                    preview::unlock(path, password, false),
+                                    |(path, unlocked)| {
+                                        Message::PreviewPane(PreviewMessage::Unlocked(
+                                            path, unlocked,
+                                        ))
+                                    },
+                                ));
+                            }
+                        } else if failed && utils::extension(&path) == "cocoon" {
+                            let own_passphrase = Path::new(&path)
+                                .parent()
+                                .zip(Path::new(&path).file_name().and_then(|f| f.to_str()))
+                                .and_then(|(dir, filename)| {
+                                    Some(Catalog::load(dir).get(filename)?.own_passphrase)
+                                })
+                                .unwrap_or_default();
+                            state.send(PaneMessage::Preview(PreviewMessage::PassphraseRequired(
+                                path,
+                                own_passphrase,
+                            )));
+                        } else if failed && office::is_office(&path) {
+                            commands.push(Command::perform(
+                                office::extract(path),
+                                |(path, text)| {
+                                    Message::PreviewPane(PreviewMessage::TextLoaded(path, text))
+                                },
+                            ));
+                        }
+                    }
+                    Message::OpenExternally(path) => match tools::open_default(&path) {
+                        Ok(()) => info!(event = "OpenExternally", file = %path),
+                        Err(error) => warn!(event = "open_externally_failed", file = %path, ?error),
+                    },
+                    Message::OpenSealed(path, passphrase) => {
+                        commands.push(Command::perform(
+                            preview::open_sealed(path, passphrase),
+                            |(path, opened)| {
+                                Message::PreviewPane(PreviewMessage::Unlocked(path, opened))
+                            },
+                        ));
+                    }
+                    Message::Seal(path, passphrase) => {
+                        commands.push(Command::perform(
+                            vault::seal_with_own(path.clone(), passphrase),
+                            move |sealed| {
+                                Message::PreviewPane(PreviewMessage::Sealed(path.clone(), sealed))
+                            },
+                        ));
+                    }
+                    Message::PreviewPane(PreviewMessage::Sealed(path, sealed)) => {
+                        match &sealed {
+                            Ok(_) => state.preview_cache.remove(&path),
+                            Err(error) => warn!(event = "seal_failed", file = %path, ?error),
+                        }
+                        let refresh = sealed.is_ok();
+                        state.send(PaneMessage::Preview(PreviewMessage::Sealed(path, sealed)));
+                        if refresh {
+                            state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()));
+                        }
+                    }
+                    Message::Unlock(path, password, remember, keep_copy) => {
+                        if let (true, Some(institution)) =
+                            (remember, OptDoc::new(&path).institution)
+                        {
+                            if let Err(error) = passwords::store(&institution, &password) {
+                                warn!(event = "password_store_failed", ?error);
+                            }
+                        }
+                        commands.push(Command::perform(
+                            preview::unlock(path, password, keep_copy),
+                            |(path, unlocked)| {
+                                Message::PreviewPane(PreviewMessage::Unlocked(path, unlocked))
+                            },
+                        ));
+                    }
+                    Message::PreviewPane(PreviewMessage::Unlocked(path, unlocked)) => {
+                        match &unlocked {
+                            Ok(unlocked) => {
+                                info!(event = "Unlock", file = %path);
+                                if let Some(handle) = &unlocked.handle {
+                                    state
+                                        .preview_cache
+                                        .insert_unlocked(path.clone(), handle.clone());
+                                }
+                            }
+                            Err(error) => {
+                                warn!(event = "unlock_failed", file = %path, ?error)
+                            }
+                        }
+                        let copied = unlocked
+                            .as_ref()
+                            .is_ok_and(|unlocked| unlocked.copy.is_some());
+                        state.send(PaneMessage::Preview(PreviewMessage::Unlocked(
+                            path, unlocked,
+                        )));
+                        if copied {
+                            state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()));
+                        }
+                    }
+                    Message::PreviewPane(PreviewMessage::Rotate(path, turns)) => {
+                        state.send(PaneMessage::Preview(PreviewMessage::Rotate(
+                            path.clone(),
+                            turns,
+                        )));
+                        commands.push(Command::perform(
+                            preview::rotated(path, turns),
+                            |(path, turns, handle)| {
+                                Message::PreviewPane(PreviewMessage::Rotated(path, turns, handle))
+                            },
+                        ));
+                    }
+                    Message::SaveRotation(path, turns) => {
+                        commands.push(Command::perform(
+                            orientation::save(path, turns),
+                            |(path, saved)| {
+                                Message::PreviewPane(PreviewMessage::RotationSaved(path, saved))
+                            },
+                        ));
+                    }
+                    Message::PreviewPane(PreviewMessage::RotationSaved(path, saved)) => {
+                        match &saved {
+                            Ok(()) => {
+                                info!(event = "Rotate", file = %path);
+                                catalog::record_rewrite(Path::new(&path));
+                                state.preview_cache.remove(&path);
+                            }
+                            Err(error) => {
+                                warn!(event = "rotate_failed", file = %path, ?error)
+                            }
+                        }
+                        let reload = saved.is_ok().then(|| path.clone());
+                        state.send(PaneMessage::Preview(PreviewMessage::RotationSaved(
+                            path, saved,
+                        )));
+                        if let Some(path) = reload {
+                            commands.push(state.load_previews(vec![path]));
+                        }
+                    }
+                    Message::PreviewPane(PreviewMessage::EditPages(path)) => {
+                        state.send(PaneMessage::Preview(PreviewMessage::EditPages(
+                            path.clone(),
+                        )));
+                        commands.push(Command::perform(
+                            preview::thumbnails(
+                                path,
+                                state.preferences.blank_sensitivity.max_ink(),
+                            ),
+                            |(path, loaded)| {
+                                Message::PreviewPane(PreviewMessage::PagesLoaded(path, loaded))
+                            },
+                        ));
+                    }
+                    Message::PreviewPane(PreviewMessage::Redact(path)) => {
+                        state.send(PaneMessage::Preview(PreviewMessage::Redact(path.clone())));
+                        commands.push(Command::perform(redact::page(path, 1), |(path, page)| {
+                            Message::PreviewPane(PreviewMessage::RedactPageLoaded(path, page))
+                        }));
+                    }
+                    Message::PreviewPane(PreviewMessage::RedactPage(path, number)) => {
+                        commands.push(Command::perform(
+                            redact::page(path, number),
+                            |(path, page)| {
+                                Message::PreviewPane(PreviewMessage::RedactPageLoaded(path, page))
+                            },
+                        ));
+                    }
+                    Message::ExportRedacted(path, regions) => {
+                        commands.push(Command::perform(
+                            redact::export(state.target_dir.clone(), path.clone(), regions),
+                            move |exported| {
+                                Message::PreviewPane(PreviewMessage::RedactedExported(
+                                    path.clone(),
+                                    exported,
+                                ))
+                            },
+                        ));
+                    }
+                    Message::SavePages(path, pages) => {
+                        commands.push(Command::perform(
+                            pdf::edit_pages(path, pages),
+                            |(path, saved)| {
+                                Message::PreviewPane(PreviewMessage::PagesSaved(path, saved))
+                            },
+                        ));
+                    }
+                    Message::PreviewPane(PreviewMessage::PagesSaved(path, saved)) => {
+                        match &saved {
+                            Ok(()) => {
+                                info!(event = "EditPages", file = %path);
+                                catalog::record_rewrite(Path::new(&path));
+                                // Pages may have moved or gone, the text is read again.
+                                search::forget_text(Path::new(&path));
+                                state.preview_cache.remove(&path);
+                            }
+                            Err(error) => {
+                                warn!(event = "edit_pages_failed", file = %path, ?error)
+                            }
+                        }
+                        let reload = saved.is_ok().then(|| path.clone());
+                        state.send(PaneMessage::Preview(PreviewMessage::PagesSaved(
+                            path, saved,
+                        )));
+                        if let Some(path) = reload {
+                            commands.push(state.load_previews(vec![path]));
+                            state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()));
+                        }
+                    }
+                    Message::PreviewPane(PreviewMessage::PageLoaded(path, page, handle)) => {
+                        // The first page is cached with the previews.
+                        if let Some(handle) = handle.as_ref().filter(|_| page > 1) {
+                            state
+                                .preview_cache
+                                .insert_page(path.clone(), page, handle.clone());
+                        }
+                        state.send(PaneMessage::Preview(PreviewMessage::PageLoaded(
+                            path, page, handle,
+                        )))
+                    }
+                    Message::PreviewPane(preview_message) => {
+                        state.send(PaneMessage::Preview(preview_message))
+                    }
+                    Message::DocPane(DocPaneMessage::Doc(_, DocMessage::MakeSearchable(path))) => {
+                        commands.push(Command::perform(
+                            ocr::make_searchable(path, state.preferences.ocr_languages()),
+                            Message::MadeSearchable,
+                        ));
+                    }
+                    Message::DocPane(DocPaneMessage::Doc(_, DocMessage::Unarchive(path))) => {
+                        commands.push(Command::perform(
+                            archive::restore(path),
+                            Message::Unarchived,
+                        ));
+                    }
+                    Message::MadeSearchable(Ok(_)) => {
+                        state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()))
+                    }
+                    Message::MadeSearchable(Err(error)) => {
+                        warn!(event = "make_searchable_failed", ?error);
+                    }
+                    Message::DocPane(doc_pane_message) => {
+                        // Deleting and renaming change the folder.
+                        let refresh = matches!(
+                            doc_pane_message,
+                            DocPaneMessage::Doc(
+                                _,
+                                DocMessage::ConfirmDelete | DocMessage::FinishEdition
+                            )
+                        );
+                        let filtered = matches!(
+                            doc_pane_message,
+                            DocPaneMessage::FilterChanged(_) | DocPaneMessage::Searched(_, _)
+                        );
+                        if let DocPaneMessage::QueryEdited(query)
+                        | DocPaneMessage::SuggestionPicked(query) = &doc_pane_message
+                        {
+                            if !query.trim().is_empty() {
+                                commands.push(Command::perform(
+                                    search::search(state.target_dir.clone(), query.clone()),
+                                    |(query, found)| {
+                                        Message::DocPane(DocPaneMessage::Searched(query, found))
+                                    },
+                                ));
+                            }
+                        }
+                        state.send(PaneMessage::Doc(doc_pane_message));
+                        if refresh {
+                            state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()));
+                        }
+                        if filtered {
+                            state.index_queue.prioritize(&state.visible());
+                        }
+                    }
+                    Message::WindowResized(width) => state.window_width = Some(width),
+                    Message::MaximizePane(pane) => state.maximized = Some(pane),
+                    Message::RestorePanes => state.maximized = None,
+                    Message::Resized(pane_grid::ResizeEvent { split, ratio }) => {
+                        state.panes.resize(&split, ratio);
+                    }
+                    Message::Dragged(pane_grid::DragEvent::Dropped { pane, target }) => {
+                        state.panes.swap(&pane, &target);
+                    }
+                    Message::OpenRulesPane => {
+                        if let (Some(doc_pane), None) = (&state.doc_pane, &state.rules_pane) {
+                            if let Some((rules_pane, _split)) = state.panes.split(
+                                pane_grid::Axis::Horizontal,
+                                doc_pane,
+                                Panel::new(RulesPane::new(state.import_profile.clone())),
+                            ) {
+                                state.rules_pane = Some(rules_pane);
+                            }
+                        }
+                    }
+                    Message::OpenTagRulesPane => {
+                        if let (Some(doc_pane), None) = (&state.doc_pane, &state.tag_rules_pane) {
+                            if let Some((tag_rules_pane, _split)) = state.panes.split(
+                                pane_grid::Axis::Horizontal,
+                                doc_pane,
+                                Panel::new(TagRulesPane::new(&state.target_dir)),
+                            ) {
+                                state.tag_rules_pane = Some(tag_rules_pane);
+                            }
+                        }
                     }
-                    Message::Loaded(Err(_)) => {
-                        *self = FileCabinet::Loaded(State::default());
+                    Message::CloseTagRulesPane(pane) => {
+                        state.panes.close(&pane);
+                        state.tag_rules_pane = None;
                     }
-                    _ => {}
-                }
-                Command::none()
-            }
-            FileCabinet::Loaded(state) => {
-                let mut saved = false;
-
-                match message {
-                    Message::RefreshTargetDir(_) => {
-                        for (_pane, boxed_content) in state.panes.iter_mut() {
-                            boxed_content.update(message.clone());
+                    Message::TagRulesPane(tag_rules_message) => {
+                        match &tag_rules_message {
+                            TagRulesMessage::Rerun => {
+                                commands.push(Command::perform(
+                                    rules::rerun_tag_rules(state.target_dir.clone()),
+                                    |reran| Message::TagRulesPane(TagRulesMessage::Reran(reran)),
+                                ));
+                            }
+                            TagRulesMessage::Reran(Ok(_)) => {
+                                state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()))
+                            }
+                            _ => {}
                         }
+                        state.send(PaneMessage::TagRules(tag_rules_message));
+                    }
+                    Message::OpenFieldsPane => {
+                        if let (Some(doc_pane), None) = (&state.doc_pane, &state.fields_pane) {
+                            if let Some((fields_pane, _split)) = state.panes.split(
+                                pane_grid::Axis::Horizontal,
+                                doc_pane,
+                                Panel::new(FieldsPane::new(&state.target_dir)),
+                            ) {
+                                state.fields_pane = Some(fields_pane);
+                            }
+                        }
+                    }
+                    Message::CloseFieldsPane(pane) => {
+                        state.panes.close(&pane);
+                        state.fields_pane = None;
+                    }
+                    Message::FieldsPane(fields_message) => {
+                        state.send(PaneMessage::Fields(fields_message));
                     }
-                    Message::PathChanged(ref value) => {
-                        state.target_dir = value.clone();
-                        for (_pane, boxed_content) in state.panes.iter_mut() {
-                            boxed_content.update(message.clone());
+                    Message::OpenPeoplePane => {
+                        if let (Some(doc_pane), None) = (&state.doc_pane, &state.people_pane) {
+                            if let Some((people_pane, _split)) = state.panes.split(
+                                pane_grid::Axis::Horizontal,
+                                doc_pane,
+                                Panel::new(PeoplePane::new(&state.target_dir)),
+                            ) {
+                                state.people_pane = Some(people_pane);
+                            }
                         }
                     }
-                    Message::FilterChanged(_filter) => {
-                        for (_pane, boxed_content) in state.panes.iter_mut() {
-                            boxed_content.update(message.clone());
+                    Message::ClosePeoplePane(pane) => {
+                        state.panes.close(&pane);
+                        state.people_pane = None;
+                    }
+                    Message::PeoplePane(people_message) => {
+                        state.send(PaneMessage::People(people_message));
+                        // The list shows the documents of each by name and color.
+                        state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()));
+                    }
+                    Message::OpenChecklistsPane => {
+                        if let (Some(doc_pane), None) = (&state.doc_pane, &state.checklists_pane) {
+                            if let Some((checklists_pane, _split)) = state.panes.split(
+                                pane_grid::Axis::Vertical,
+                                doc_pane,
+                                Panel::new(ChecklistsPane::new(&state.target_dir)),
+                            ) {
+                                state.checklists_pane = Some(checklists_pane);
+                            }
                         }
                     }
-                    Message::ClosePreviewPane(pane) => {
+                    Message::CloseChecklistsPane(pane) => {
                         state.panes.close(&pane);
-                        state.preview_pane = Default::default();
+                        state.checklists_pane = None;
                     }
-                    Message::DocMessage(_, DocMessage::OpenPreviewPane(path, _)) => {
-                        if let Some(doc_pane) = &state.doc_pane {
-                            match state.preview_pane {
-                                None => {
-                                    println!("Preview pane closed, opening for the first time");
-                                    // If the preview pane isn't open, open it,
-                                    if let Some((preview_pane, _split)) = state.panes.split(
-                                        pane_grid::Axis::Vertical,
-                                        doc_pane,
-                                        Box::new(PreviewPane {
-                                            preview_image_path: path.clone(),
-                                            ..Default::default()
-                                        }),
-                                    ) {
-                                        // then save the preview pane.
-                                        state.preview_pane = Some(preview_pane);
-                                        state.preview_image = path;
-                                    }
-                                }
-                                Some(preview_pane) => {
-                                    println!("Preview pane open, closing and reopening new one...");
-                                    if state.preview_image != path {
-                                        println!("Preview pane image is the same path, refusing to open.");
-                                        // If the preview pane is open, close it,
-                                        state.panes.close(&preview_pane);
-                                        // then open the new one.
-                                        if let Some((pane, _)) = state.panes.split(
-                                            pane_grid::Axis::Vertical,
-                                            doc_pane,
-                                            Box::new(PreviewPane {
-                                                preview_image_path: path.clone(),
-                                                ..Default::default()
-                                            }),
-                                        ) {
-                                            // Update the preview pane with state.
-                                            state.preview_pane = Some(pane);
-                                            state.preview_image = path;
-                                        } else {
-                                            // If fails, unset the preview pane.
-                                            state.preview_pane = None;
-                                            state.preview_image = String::new();
-                                        }
-                                    }
+                    Message::ChecklistsPane(ChecklistsMessage::LinkSelected(item)) => {
+                        let selected = state
+                            .doc_pane
+                            .and_then(|pane| state.panes.get(&pane))
+                            .and_then(|panel| panel.content.session())
+                            .map(|session| session.selected)
+                            .unwrap_or_default();
+                        state.send(PaneMessage::Checklists(ChecklistsMessage::Link(
+                            item, selected,
+                        )));
+                    }
+                    Message::ChecklistsPane(checklists_message) => {
+                        state.send(PaneMessage::Checklists(checklists_message));
+                    }
+                    Message::OpenInstitutionsPane => {
+                        if let (Some(doc_pane), None) = (&state.doc_pane, &state.institutions_pane)
+                        {
+                            if let Some((institutions_pane, _split)) = state.panes.split(
+                                pane_grid::Axis::Vertical,
+                                doc_pane,
+                                Panel::new(InstitutionsPane::new(&state.target_dir)),
+                            ) {
+                                state.institutions_pane = Some(institutions_pane);
+                            }
+                        }
+                    }
+                    Message::CloseInstitutionsPane(pane) => {
+                        state.panes.close(&pane);
+                        state.institutions_pane = None;
+                    }
+                    Message::InstitutionsPane(InstitutionsMessage::Rename(from, to)) => {
+                        commands.push(Command::perform(
+                            institutions::rename_all(state.target_dir.clone(), from, to.clone()),
+                            move |renamed| {
+                                Message::InstitutionsPane(InstitutionsMessage::Renamed(
+                                    to.clone(),
+                                    renamed,
+                                ))
+                            },
+                        ));
+                    }
+                    Message::InstitutionsPane(InstitutionsMessage::Renamed(to, renamed)) => {
+                        let done = renamed.is_ok();
+                        state.send(PaneMessage::Institutions(InstitutionsMessage::Renamed(
+                            to, renamed,
+                        )));
+                        if done {
+                            state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()));
+                        }
+                    }
+                    Message::InstitutionsPane(institutions_message) => {
+                        state.send(PaneMessage::Institutions(institutions_message))
+                    }
+                    Message::CloseRulesPane(pane) => {
+                        state.panes.close(&pane);
+                        state.rules_pane = None;
+                    }
+                    Message::OpenCalendarPane => {
+                        if let (Some(doc_pane), None) = (&state.doc_pane, &state.calendar_pane) {
+                            if let Some((calendar_pane, _split)) = state.panes.split(
+                                pane_grid::Axis::Horizontal,
+                                doc_pane,
+                                Panel::new(CalendarPane::new(&state.target_dir)),
+                            ) {
+                                state.calendar_pane = Some(calendar_pane);
+                            }
+                        }
+                    }
+                    Message::CloseCalendarPane(pane) => {
+                        state.panes.close(&pane);
+                        state.calendar_pane = None;
+                    }
+                    Message::ExportCalendar => {
+                        commands.push(Command::perform(
+                            ics::export(state.target_dir.clone()),
+                            |exported| Message::CalendarPane(CalendarMessage::Exported(exported)),
+                        ));
+                    }
+                    Message::CalendarPane(calendar_message) => {
+                        state.send(PaneMessage::Calendar(calendar_message))
+                    }
+                    Message::OpenPacketPane => {
+                        if let (Some(doc_pane), None) = (&state.doc_pane, &state.packet_pane) {
+                            if let Some((packet_pane, _split)) = state.panes.split(
+                                pane_grid::Axis::Vertical,
+                                doc_pane,
+                                Panel::new(PacketPane::new(
+                                    &state.target_dir,
+                                    state.packet_criteria.clone(),
+                                    state.preferences.recipients.clone(),
+                                )),
+                            ) {
+                                state.packet_pane = Some(packet_pane);
+                            }
+                        }
+                    }
+                    Message::ClosePacketPane(pane) => {
+                        state.panes.close(&pane);
+                        state.packet_pane = None;
+                    }
+                    Message::OpenSyncPane => {
+                        if let (Some(doc_pane), None) = (&state.doc_pane, &state.sync_pane) {
+                            let sync = SyncPane {
+                                remote: sync::last_remote(&state.target_dir).unwrap_or_default(),
+                                ..Default::default()
+                            };
+                            if let Some((sync_pane, _split)) = state.panes.split(
+                                pane_grid::Axis::Vertical,
+                                doc_pane,
+                                Panel::new(sync),
+                            ) {
+                                state.sync_pane = Some(sync_pane);
+                            }
+                        }
+                    }
+                    Message::CloseSyncPane(pane) => {
+                        state.panes.close(&pane);
+                        state.sync_pane = None;
+                    }
+                    Message::OpenBackupPane => {
+                        if let (Some(doc_pane), None) = (&state.doc_pane, &state.backup_pane) {
+                            if let Some((backup_pane, _split)) = state.panes.split(
+                                pane_grid::Axis::Vertical,
+                                doc_pane,
+                                Panel::new(BackupPane::new(
+                                    &state.preferences.backup_dir,
+                                    &state.target_dir,
+                                )),
+                            ) {
+                                state.backup_pane = Some(backup_pane);
+                            }
+                        }
+                    }
+                    Message::CloseBackupPane(pane) => {
+                        state.panes.close(&pane);
+                        state.backup_pane = None;
+                    }
+                    Message::Backup(target, passphrase) => {
+                        commands.push(Command::perform(
+                            backup::backup(state.target_dir.clone(), target, passphrase),
+                            |created| Message::BackupPane(BackupPaneMessage::BackedUp(created)),
+                        ));
+                    }
+                    Message::Restore(target, snapshot, passphrase, dir) => {
+                        commands.push(Command::perform(
+                            backup::restore(target, snapshot, passphrase, dir),
+                            |restored| Message::BackupPane(BackupPaneMessage::Restored(restored)),
+                        ));
+                    }
+                    Message::OpenStatsPane => {
+                        if let (Some(doc_pane), None) = (&state.doc_pane, &state.stats_pane) {
+                            let stats = StatsPane {
+                                dir: state.target_dir.clone(),
+                                ..Default::default()
+                            };
+                            if let Some((stats_pane, _split)) = state.panes.split(
+                                pane_grid::Axis::Vertical,
+                                doc_pane,
+                                Panel::new(stats),
+                            ) {
+                                state.stats_pane = Some(stats_pane);
+                                commands.push(Command::perform(
+                                    stats::collect(state.target_dir.clone()),
+                                    |(dir, stats)| {
+                                        Message::StatsPane(StatsPaneMessage::Computed(dir, stats))
+                                    },
+                                ));
+                            }
+                        }
+                    }
+                    Message::CloseStatsPane(pane) => {
+                        state.panes.close(&pane);
+                        state.stats_pane = None;
+                    }
+                    Message::ComputeStats => {
+                        commands.push(Command::perform(
+                            stats::collect(state.target_dir.clone()),
+                            |(dir, stats)| {
+                                Message::StatsPane(StatsPaneMessage::Computed(dir, stats))
+                            },
+                        ));
+                    }
+                    Message::EmptyTrash => {
+                        state.send(PaneMessage::Stats(StatsPaneMessage::EmptyingTrash));
+                        commands.push(Command::perform(
+                            trash::empty(state.target_dir.clone()),
+                            |e| Message::StatsPane(StatsPaneMessage::TrashEmptied(e)),
+                        ));
+                    }
+                    Message::StatsPane(StatsPaneMessage::TrashEmptied(emptied)) => {
+                        state.send(PaneMessage::Stats(StatsPaneMessage::TrashEmptied(emptied)));
+                        commands.push(Command::perform(
+                            stats::collect(state.target_dir.clone()),
+                            |(dir, stats)| {
+                                Message::StatsPane(StatsPaneMessage::Computed(dir, stats))
+                            },
+                        ));
+                    }
+                    Message::TrashPurged(Ok(_)) => {}
+                    Message::TrashPurged(Err(error)) => {
+                        warn!(event = "trash_purge_failed", ?error)
+                    }
+                    Message::CompactMetadata => {
+                        state.send(PaneMessage::Stats(StatsPaneMessage::Compacting));
+                        commands.push(Command::perform(
+                            compact::run(state.target_dir.clone()),
+                            |c| Message::StatsPane(StatsPaneMessage::Compacted(c)),
+                        ));
+                    }
+                    Message::StatsPane(StatsPaneMessage::Compacted(compacted)) => {
+                        state.send(PaneMessage::Stats(StatsPaneMessage::Compacted(compacted)));
+                        state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()));
+                        commands.push(Command::perform(
+                            stats::collect(state.target_dir.clone()),
+                            |(dir, stats)| {
+                                Message::StatsPane(StatsPaneMessage::Computed(dir, stats))
+                            },
+                        ));
+                    }
+                    Message::ExportCabinet(format) if state.export.is_none() => {
+                        state.export = Some((format, 0, 0))
+                    }
+                    Message::ExportProgress(export::Progress::Wrote(done, total)) => {
+                        if let Some((_, written, count)) = &mut state.export {
+                            *written = done;
+                            *count = total;
+                        }
+                    }
+                    Message::ExportProgress(export::Progress::Finished(exported)) => {
+                        state.export = None;
+                        state.send(PaneMessage::Stats(StatsPaneMessage::Exported(exported)));
+                    }
+                    // Dropping the subscription stops the export.
+                    Message::CancelExport => {
+                        state.export = None;
+                        state.send(PaneMessage::Stats(StatsPaneMessage::Exported(Err(
+                            export::ExportError::CancelledError,
+                        ))));
+                    }
+                    Message::StatsPane(stats_pane_message) => {
+                        state.send(PaneMessage::Stats(stats_pane_message))
+                    }
+                    Message::WriteChecksums => {
+                        commands.push(Command::perform(
+                            checksums::export(state.target_dir.clone()),
+                            |written| {
+                                Message::BackupPane(BackupPaneMessage::ChecksumsWritten(written))
+                            },
+                        ));
+                    }
+                    Message::WriteSite => {
+                        commands.push(Command::perform(
+                            site::export(state.target_dir.clone()),
+                            |written| Message::BackupPane(BackupPaneMessage::SiteWritten(written)),
+                        ));
+                    }
+                    Message::VerifyChecksums => {
+                        commands.push(Command::perform(
+                            checksums::check(state.target_dir.clone()),
+                            |verified| {
+                                Message::BackupPane(BackupPaneMessage::ChecksumsVerified(verified))
+                            },
+                        ));
+                    }
+                    Message::BackupPane(backup_pane_message) => {
+                        let restored =
+                            matches!(backup_pane_message, BackupPaneMessage::Restored(_));
+                        state.send(PaneMessage::Backup(backup_pane_message));
+                        if restored {
+                            state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()));
+                        }
+                    }
+                    Message::PlanSync(remote) => {
+                        commands.push(Command::perform(
+                            sync::plan(state.target_dir.clone(), remote),
+                            |planned| Message::SyncPane(SyncPaneMessage::Planned(planned)),
+                        ));
+                    }
+                    Message::ApplySync(remote, changes) => {
+                        commands.push(Command::perform(
+                            sync::apply(state.target_dir.clone(), remote, changes),
+                            |synced| Message::SyncPane(SyncPaneMessage::Synced(synced)),
+                        ));
+                    }
+                    Message::SyncPane(sync_pane_message) => {
+                        let synced = matches!(sync_pane_message, SyncPaneMessage::Synced(_));
+                        state.send(PaneMessage::Sync(sync_pane_message));
+                        // Even a sync that failed halfway changed some files.
+                        if synced {
+                            state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()));
+                        }
+                    }
+                    Message::PacketMessage(packet_message) => {
+                        state.packet_criteria.update(packet_message);
+                        state
+                            .broadcast(Event::PacketCriteriaChanged(state.packet_criteria.clone()));
+                    }
+                    Message::OpenSettingsPane => {
+                        if let (Some(doc_pane), None) = (&state.doc_pane, &state.settings_pane) {
+                            if let Some((settings_pane, _split)) = state.panes.split(
+                                pane_grid::Axis::Vertical,
+                                doc_pane,
+                                Panel::new(SettingsPane::new(
+                                    state.preferences.clone(),
+                                    &state.target_dir,
+                                )),
+                            ) {
+                                state.settings_pane = Some(settings_pane);
+                            }
+                        }
+                    }
+                    Message::CloseSettingsPane(pane) => {
+                        state.panes.close(&pane);
+                        state.settings_pane = None;
+                    }
+                    Message::Activity => state.last_activity = Some(Instant::now()),
+                    Message::Tick(now) => {
+                        let last_activity = *state.last_activity.get_or_insert(now);
+                        let idle = Duration::from_secs(
+                            60 * u64::from(state.preferences.auto_lock_minutes),
+                        );
+                        // Cabinets without a vault have no passphrase to unlock them with.
+                        if !state.locked
+                            && state.preferences.auto_lock_minutes > 0
+                            && now.duration_since(last_activity) >= idle
+                            && vault::exists(Path::new(&state.target_dir))
+                        {
+                            state.lock(false);
+                        }
+                    }
+                    // Cabinets without a vault have no passphrase to unlock them with.
+                    Message::LockNow
+                        if !state.locked && vault::exists(Path::new(&state.target_dir)) =>
+                    {
+                        state.lock(true)
+                    }
+                    Message::LockNow => {}
+                    Message::UnlockPassphraseEdited(passphrase) => {
+                        state.unlock_passphrase = passphrase
+                    }
+                    Message::UnlockCabinet => {
+                        if vault::check(Path::new(&state.target_dir), &state.unlock_passphrase) {
+                            state.locked = false;
+                            state.unlock_error.clear();
+                            state.last_activity = Some(Instant::now());
+                            info!(event = "Unlock", dir = %state.target_dir);
+                        } else {
+                            state.unlock_error = "Wrong passphrase.".to_string();
+                        }
+                        state.unlock_passphrase.clear();
+                    }
+                    Message::ChangePassphrase(old, new) => {
+                        state.send(PaneMessage::Settings(
+                            SettingsPaneMessage::ChangingPassphrase,
+                        ));
+                        commands.push(Command::perform(
+                            vault::rotate(state.target_dir.clone(), old, new),
+                            |changed| {
+                                Message::SettingsPane(SettingsPaneMessage::PassphraseChanged(
+                                    changed,
+                                ))
+                            },
+                        ));
+                    }
+                    Message::SettingsPane(settings_pane_message) => {
+                        state.send(PaneMessage::Settings(settings_pane_message));
+                    }
+                    Message::OpenLogPane => {
+                        if let (Some(doc_pane), None) = (&state.doc_pane, &state.log_pane) {
+                            if let Some((log_pane, _split)) = state.panes.split(
+                                pane_grid::Axis::Horizontal,
+                                doc_pane,
+                                Panel::new(LogPane::new()),
+                            ) {
+                                state.log_pane = Some(log_pane);
+                            }
+                        }
+                    }
+                    Message::CloseLogPane(pane) => {
+                        state.panes.close(&pane);
+                        state.log_pane = None;
+                    }
+                    Message::LogPane(log_message) => state.send(PaneMessage::Log(log_message)),
+                    Message::PreferencesMessage(preferences_message) => {
+                        let layout = state.preferences.preview_layout;
+                        state.preferences.update(preferences_message);
+                        events::enable(state.preferences.event_stream);
+                        // Move an open preview right away, other panes aren't affected.
+                        if let (Some(doc_pane), Some(preview_pane)) =
+                            (state.doc_pane, state.preview_pane)
+                        {
+                            if layout != state.preferences.preview_layout {
+                                if let Some((panel, _)) = state.panes.close(&preview_pane) {
+                                    state.preview_pane = state
+                                        .panes
+                                        .split(state.preferences.preview_axis(), &doc_pane, panel)
+                                        .map(|(pane, _)| pane);
                                 }
                             }
                         }
+                        state.broadcast(Event::PreferencesChanged(state.preferences.clone()));
+                    }
+                    Message::PacketPane(packet_pane_message) => {
+                        state.send(PaneMessage::Packet(packet_pane_message))
+                    }
+                    Message::Index | Message::ResumeIndex
+                        if state.index_queue.status != index::Status::Running =>
+                    {
+                        let paused = state.index_queue.status == index::Status::Paused
+                            && state.index_queue.len() > 0;
+                        state.index_queue.status = index::Status::Running;
+                        commands.push(if paused {
+                            state.index_next()
+                        } else {
+                            Command::perform(
+                                index::queue(state.target_dir.clone()),
+                                Message::IndexQueued,
+                            )
+                        });
+                    }
+                    Message::PauseIndex if state.index_queue.status == index::Status::Running => {
+                        state.index_queue.status = index::Status::Paused
+                    }
+                    Message::CheckPower if !state.preferences.low_power || !power::on_battery() => {
+                        state.on_battery = false;
+                        commands.push(state.index_next());
+                    }
+                    Message::IndexQueued(Ok(pending)) => {
+                        state.index_queue.start(pending);
+                        state.index_queue.prioritize(&state.visible());
+                        commands.push(state.index_next());
                     }
-                    Message::DocMessage(_, DocMessage::Delete) => {
-                        for (_pane, boxed_content) in state.panes.iter_mut() {
-                            boxed_content.update(message.clone());
+                    Message::IndexQueued(Err(error)) => {
+                        warn!(event = "index_failed", ?error);
+                        state.index_queue.status = index::Status::Idle;
+                    }
+                    Message::IndexedDocument(result) => {
+                        if let Err(error) = result {
+                            warn!(event = "index_failed", ?error);
+                        }
+                        state.index_queue.finished();
+                        // Refreshing reads the whole folder, so not after every document.
+                        if state.index_queue.done % REFRESH_INDEXED == 0 {
+                            state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()))
                         }
-                        // Refresh after deleting
-                        for (_pane, boxed_content) in state.panes.iter_mut() {
-                            boxed_content.update(Message::RefreshTargetDir(state.target_dir.clone()));
+                        commands.push(state.index_next());
+                    }
+                    // Shares into the import folder show up once imported.
+                    #[cfg(not(target_arch = "wasm32"))]
+                    Message::Shared(path)
+                        if Path::new(&path).parent() == Some(Path::new(&state.target_dir)) =>
+                    {
+                        state.queue_added(&[path]);
+                        state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()))
+                    }
+                    #[cfg(target_os = "linux")]
+                    Message::Tray(tray::TrayAction::QuickAdd) => {
+                        commands.push(Command::perform(
+                            tray::quick_add(state.intake_dir()),
+                            Message::QuickAdded,
+                        ));
+                    }
+                    #[cfg(target_os = "linux")]
+                    Message::Tray(tray::TrayAction::ToggleWindow) => tray::toggle_window(),
+                    #[cfg(target_os = "linux")]
+                    Message::QuickAdded(Ok(paths)) if !paths.is_empty() => {
+                        if state.intake_dir() == state.target_dir {
+                            state.queue_added(&paths);
+                            state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()));
+                        } else {
+                            commands.push(state.import());
                         }
                     }
-                    Message::DocMessage(_, DocMessage::FinishEdition) => {
-                        for (_pane, boxed_content) in state.panes.iter_mut() {
-                            boxed_content.update(message.clone());
+                    #[cfg(target_os = "linux")]
+                    Message::QuickAdded(Ok(_)) => {}
+                    #[cfg(target_os = "linux")]
+                    Message::QuickAdded(Err(error)) => warn!(event = "quick_add_failed", ?error),
+                    Message::Control(control::Request::Add(path)) => {
+                        commands.push(Command::perform(
+                            control::add(path, state.intake_dir()),
+                            Message::ControlAdded,
+                        ));
+                    }
+                    Message::Control(control::Request::Search(query)) => {
+                        if !query.trim().is_empty() {
+                            commands.push(Command::perform(
+                                search::search(state.target_dir.clone(), query.clone()),
+                                |(query, found)| {
+                                    Message::DocPane(DocPaneMessage::Searched(query, found))
+                                },
+                            ));
                         }
-                        // Refresh after editing
-                        for (_pane, boxed_content) in state.panes.iter_mut() {
-                            boxed_content.update(Message::RefreshTargetDir(state.target_dir.clone()));
+                        state.send(PaneMessage::Doc(DocPaneMessage::QueryEdited(query.clone())));
+                        state.send(PaneMessage::Doc(DocPaneMessage::SearchSubmitted(query)));
+                        #[cfg(target_os = "linux")]
+                        tray::show_window();
+                    }
+                    Message::Control(control::Request::Focus(filename)) => {
+                        let path = Path::new(&state.target_dir).join(&filename);
+                        if path.is_file() {
+                            commands.push(state.show_preview(path.to_string_lossy().to_string()));
+                            #[cfg(target_os = "linux")]
+                            tray::show_window();
+                        } else {
+                            warn!(event = "control_focus_failed", file = %filename);
                         }
                     }
-                    Message::DocMessage(_, ref _doc_message) => {
-                        for (_pane, boxed_content) in state.panes.iter_mut() {
-                            boxed_content.update(message.clone());
+                    Message::Control(control::Request::Raise) => {
+                        #[cfg(target_os = "linux")]
+                        tray::show_window();
+                    }
+                    Message::ControlAdded(Ok(path)) => {
+                        if state.intake_dir() == state.target_dir {
+                            state.queue_added(&[path]);
+                            state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()));
+                        } else {
+                            commands.push(state.import());
                         }
                     }
-                    Message::Resized(pane_grid::ResizeEvent { split, ratio }) => {
-                        state.panes.resize(&split, ratio);
+                    Message::ControlAdded(Err(error)) => {
+                        warn!(event = "control_add_failed", ?error)
                     }
-                    Message::Dragged(pane_grid::DragEvent::Dropped { pane, target }) => {
-                        state.panes.swap(&pane, &target);
+                    Message::Control(control::Request::Open(path)) => {
+                        commands.push(Command::perform(
+                            control::open(path, state.target_dir.clone()),
+                            Message::ControlOpened,
+                        ));
                     }
-                    Message::Saved(_) => {
-                        state.saving = false;
-                        saved = true;
+                    Message::ControlOpened(Ok((path, copied))) => {
+                        if copied {
+                            state.queue_added(std::slice::from_ref(&path));
+                            state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()));
+                        }
+                        if let Some(filename) = Path::new(&path).file_name() {
+                            let filename = filename.to_string_lossy().to_string();
+                            state.send(PaneMessage::Doc(DocPaneMessage::EditDocument(filename)));
+                        }
+                        commands.push(state.show_preview(path));
+                        #[cfg(target_os = "linux")]
+                        tray::show_window();
+                    }
+                    Message::ControlOpened(Err(error)) => {
+                        warn!(event = "control_open_failed", ?error)
+                    }
+                    Message::PullScans if !state.pulling => {
+                        state.pulling = true;
+                        commands.push(Command::perform(
+                            dropfolder::pull(
+                                state.preferences.drop_folder.trim().to_string(),
+                                state.target_dir.clone(),
+                                state.preferences.delete_pulled_scans,
+                                state.preferences.blank_ink(),
+                            ),
+                            Message::PulledScans,
+                        ));
+                    }
+                    Message::PulledScans(result) => {
+                        state.pulling = false;
+                        match result {
+                            Ok(report) if report.is_empty() => {}
+                            Ok(report) => {
+                                state.index_queue.add(&report.imported);
+                                state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()));
+                                state.show_import_report(report);
+                            }
+                            Err(error) => warn!(event = "pull_scans_failed", ?error),
+                        }
+                    }
+                    Message::Archive(paths) => {
+                        commands.push(Command::perform(
+                            archive::archive(
+                                paths,
+                                state.preferences.archive_dir.trim().to_string(),
+                            ),
+                            Message::Archived,
+                        ));
+                    }
+                    Message::Archived(Ok(_)) | Message::Unarchived(Ok(_)) => {
+                        state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()))
+                    }
+                    Message::Archived(Err(error)) => {
+                        warn!(event = "archive_failed", ?error);
+                        // Documents archived before the failure are gone from the folder.
+                        state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()))
+                    }
+                    Message::Unarchived(Err(error)) => {
+                        warn!(event = "unarchive_failed", ?error);
+                    }
+                    Message::ExportPacket(year, paths, recipients) => {
+                        commands.push(Command::perform(
+                            packet::export(state.target_dir.clone(), year, paths, recipients),
+                            |exported| Message::PacketPane(PacketPaneMessage::Exported(exported)),
+                        ));
+                    }
+                    Message::RuleMessage(rule_message) => {
+                        state.import_profile.update(rule_message);
+                        state.broadcast(Event::ImportProfileChanged(state.import_profile.clone()));
+                    }
+                    Message::Import => commands.push(state.import()),
+                    Message::WatchDownloads => {
+                        let downloads = state.import_profile.downloads();
+                        if !downloads.is_empty() && downloads != state.downloads {
+                            commands.push(state.import());
+                        }
+                        state.downloads = downloads;
+                    }
+                    Message::Imported(imported) => {
+                        match &imported {
+                            Ok(report) => {
+                                state.index_queue.add(&report.imported);
+                                state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()));
+                                state.show_import_report(report.clone());
+                            }
+                            Err(error) => warn!(event = "import_failed", ?error),
+                        }
+                        state.broadcast(Event::Imported(imported));
+                    }
+                    #[cfg(target_arch = "wasm32")]
+                    Message::PickFiles => {
+                        commands.push(Command::perform(
+                            storage::browser::pick(state.target_dir.clone()),
+                            Message::Picked,
+                        ));
+                    }
+                    #[cfg(target_arch = "wasm32")]
+                    Message::Picked(Ok(_)) => {
+                        state.broadcast(Event::RefreshTargetDir(state.target_dir.clone()))
+                    }
+                    #[cfg(target_arch = "wasm32")]
+                    Message::Picked(Err(error)) => warn!(event = "pick_failed", ?error),
+                    Message::Saved(save, Ok(())) => {
+                        state.saves_written = state.saves_written.max(save);
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if state.saves_written == state.saves {
+                            crash::saved();
+                        }
+                    }
+                    Message::Saved(save, Err(error)) => {
+                        warn!(event = "save_failed", ?error);
+                        // Saved again by the next update, not this one, so a full disk isn't
+                        // written to in a loop. Newer saves still running write it anyway.
+                        if save == state.saves {
+                            state.saved_json.clear();
+                            return Command::batch(commands);
+                        }
                     }
                     _ => {}
                 }
 
-                if !saved {
-                    state.dirty = true;
+                if let Some(pane) = opening.and_then(|message| state.opened_by(&message)) {
+                    state.maximized = Some(pane);
                 }
 
-                if state.dirty && !state.saving {
-                    state.dirty = false;
-                    state.saving = true;
-
-                    Command::perform(
-                        SavedState {
-                            target_dir: state.target_dir.clone(),
-                        }
-                        .save(),
-                        Message::Saved,
-                    )
-                } else {
-                    Command::none()
+                // A closed pane can't stay maximized.
+                if let Some(pane) = state.maximized {
+                    if state.panes.get(&pane).is_none() {
+                        state.maximized = None;
+                    }
                 }
+
+                commands.push(state.save());
+                Command::batch(commands)
             }
         }
     }
 
-    fn view(&mut self) -> Element<Message> {
+    fn view(&mut self) -> Element<'_, Message> {
         match self {
-            FileCabinet::Loading => loading_message(),
-            FileCabinet::Loaded(state) => Container::new(
-                Column::new()
+            FileCabinet::Loading(_) => loading_message(),
+            FileCabinet::Onboarding(onboarding) => onboarding.view(),
+            FileCabinet::Loaded(state) => {
+                if state.locked {
+                    return lock_screen(state);
+                }
+                if state.pending.is_some() {
+                    return confirmation_dialog(state);
+                }
+                let compact = state.compact();
+                let (size, padding) = if compact { (14, 5) } else { (16, 10) };
+                let path_row = Row::new()
+                    .spacing(10)
+                    .push(
+                        TextInput::new(
+                            &mut state.target_dir_state,
+                            "Specify path to documents",
+                            &state.target_dir,
+                            Message::PathChanged,
+                        )
+                        .padding(padding)
+                        .size(size),
+                    )
                     .push(
+                        refresh_button(&mut state.refresh_state, &state.target_dir, size)
+                            .style(style::Button::Refresh)
+                            .padding(padding),
+                    );
+                let pane_buttons = vec![
+                    (&mut state.rules_state, "rules", Message::OpenRulesPane),
+                    (
+                        &mut state.institutions_state,
+                        "institutions",
+                        Message::OpenInstitutionsPane,
+                    ),
+                    (
+                        &mut state.calendar_state,
+                        "calendar",
+                        Message::OpenCalendarPane,
+                    ),
+                    (
+                        &mut state.duplicates_state,
+                        "duplicates",
+                        Message::OpenDuplicatesPane,
+                    ),
+                    (&mut state.packet_state, "packet", Message::OpenPacketPane),
+                    (&mut state.sync_state, "sync", Message::OpenSyncPane),
+                    (&mut state.backup_state, "backup", Message::OpenBackupPane),
+                    (&mut state.stats_state, "stats", Message::OpenStatsPane),
+                    (
+                        &mut state.tag_manager_state,
+                        "tag tree",
+                        Message::OpenTagManagerPane,
+                    ),
+                    (
+                        &mut state.tag_rules_state,
+                        "auto-tag",
+                        Message::OpenTagRulesPane,
+                    ),
+                    (&mut state.fields_state, "fields", Message::OpenFieldsPane),
+                    (&mut state.people_state, "people", Message::OpenPeoplePane),
+                    (
+                        &mut state.checklists_state,
+                        "checklists",
+                        Message::OpenChecklistsPane,
+                    ),
+                    (
+                        &mut state.settings_state,
+                        "settings",
+                        Message::OpenSettingsPane,
+                    ),
+                    (&mut state.log_state, "log", Message::OpenLogPane),
+                ]
+                .into_iter()
+                .fold(
+                    Row::new().spacing(padding),
+                    |row, (button_state, label, message)| {
+                        row.push(
+                            Button::new(button_state, Text::new(label).size(size))
+                                .style(style::Button::Refresh)
+                                .padding(padding)
+                                .on_press(message),
+                        )
+                    },
+                );
+                // The buttons don't fit next to the path on small windows.
+                let header: Element<_> = if compact {
+                    Column::new()
+                        .spacing(5)
+                        .push(path_row)
+                        .push(pane_buttons)
+                        .into()
+                } else {
+                    path_row.push(pane_buttons).into()
+                };
+                let header: Element<_> = if vault::exists(Path::new(&state.target_dir)) {
+                    Row::new()
+                        .spacing(padding)
+                        .align_items(Align::Center)
+                        .push(Container::new(header).width(Length::Fill))
+                        .push(
+                            Button::new(&mut state.lock_button, Text::new("lock").size(size))
+                                .style(style::Button::Refresh)
+                                .padding(padding)
+                                .on_press(Message::LockNow),
+                        )
+                        .into()
+                } else {
+                    header
+                };
+                // Small windows show one pane at a time, the document list by default.
+                let shown = if compact {
+                    state.maximized.or(state.doc_pane)
+                } else {
+                    state.maximized
+                };
+                let body = match shown {
+                    Some(pane) => {
+                        let restore = match (compact, Some(pane) == state.doc_pane) {
+                            (false, _) => Some((Message::RestorePanes, "Restore")),
+                            (true, false) => Some((Message::RestorePanes, "Back")),
+                            (true, true) => None,
+                        };
+                        match state.panes.get_mut(&pane) {
+                            Some(panel) => panel.view_maximized(pane, restore),
+                            None => Column::new().into(),
+                        }
+                    }
+                    None => PaneGrid::new(&mut state.panes, |pane, panel| panel.view(pane))
+                        .on_drag(Message::Dragged)
+                        .on_resize(10, Message::Resized)
+                        .spacing(10)
+                        .into(),
+                };
+                let status_bar = match state.index_queue.status {
+                    index::Status::Idle => None,
+                    status => {
+                        let (text, label, message) =
+                            if status == index::Status::Running && state.on_battery {
+                                (
+                                    format!(
+                                        "Indexing waits for the charger, {} left",
+                                        state.index_queue.len()
+                                    ),
+                                    "pause",
+                                    Message::PauseIndex,
+                                )
+                            } else if status == index::Status::Running {
+                                (
+                                    format!(
+                                        "Indexing: {} done, {} left",
+                                        state.index_queue.done,
+                                        state.index_queue.len()
+                                    ),
+                                    "pause",
+                                    Message::PauseIndex,
+                                )
+                            } else {
+                                (
+                                    format!("Indexing paused, {} left", state.index_queue.len()),
+                                    "resume",
+                                    Message::ResumeIndex,
+                                )
+                            };
+                        Some(
+                            Row::new()
+                                .spacing(padding)
+                                .align_items(Align::Center)
+                                .push(Text::new(text).size(size).width(Length::Fill))
+                                .push(
+                                    Button::new(
+                                        &mut state.index_queue_button,
+                                        Text::new(label).size(size),
+                                    )
+                                    .style(style::Button::Refresh)
+                                    .padding(padding)
+                                    .on_press(message),
+                                ),
+                        )
+                    }
+                };
+                let mut content = Column::new().spacing(padding);
+                if !compact {
+                    content = content.push(
                         Text::new("filecabinet")
                             .width(Length::Fill)
                             .size(80)
                             .color([0.5, 0.5, 0.5])
                             .horizontal_alignment(HorizontalAlignment::Center),
-                    )
-                    .push(
+                    );
+                }
+                content = content.push(header).push(body);
+                if let Some(status_bar) = status_bar {
+                    content = content.push(status_bar);
+                }
+                if let Some((_, done, total)) = state.export {
+                    content = content.push(
                         Row::new()
-                            .spacing(10)
+                            .spacing(padding)
+                            .align_items(Align::Center)
                             .push(
-                                TextInput::new(
-                                    &mut state.target_dir_state,
-                                    "Specify path to documents",
-                                    &*state.target_dir,
-                                    Message::PathChanged,
-                                )
-                                .padding(10)
-                                .size(16),
+                                Text::new(format!("Exporting: {} of {} documents", done, total))
+                                    .size(size)
+                                    .width(Length::Fill),
                             )
                             .push(
                                 Button::new(
-                                    &mut state.refresh_state,
-                                    Text::new("refresh").size(16),
+                                    &mut state.export_button,
+                                    Text::new("cancel").size(size),
                                 )
                                 .style(style::Button::Refresh)
-                                .padding(10)
-                                .on_press(Message::RefreshTargetDir(state.target_dir.clone())),
+                                .padding(padding)
+                                .on_press(Message::CancelExport),
                             ),
-                    )
-                    .push(
-                        PaneGrid::new(&mut state.panes, |pane, content| {
-                            pane_grid::Content::new(content.view(pane)).style(style::Pane {})
-                        })
-                        .on_drag(Message::Dragged)
-                        .on_resize(10, Message::Resized)
-                        .spacing(10),
-                    )
-                    .spacing(10),
-            )
-            .width(Length::Fill)
-            .height(Length::Fill)
-            .padding(10)
-            .into(),
+                    );
+                }
+                Container::new(content)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .padding(padding)
+                    .into()
+            }
         }
     }
 }
 
+/// Names a document for as long as the app runs, whatever its place in the list, which
+/// changes with every filter, search and refresh.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DocumentId(u64);
+
+impl Default for DocumentId {
+    /// A new id every time.
+    fn default() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        DocumentId(NEXT.fetch_add(1, atomic::Ordering::Relaxed))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Document {
+    #[serde(skip)]
+    id: DocumentId,
     path: String,
     filename: String,
-    date: String,
-    institution: String,
-    title: String,
-    page: String,
+    /// The fields of a normalized filename, parsed once when the document is listed.
+    #[serde(skip)]
+    date: Option<NaiveDate>,
+    institution: Option<String>,
+    name: Option<String>,
+    page: Option<u32>,
+    /// Whether the filename is the normalized one built from those fields.
+    normalized: bool,
     extension: String,
+    /// Bytes on disk, nothing for missing and archived documents.
+    size: u64,
+    modified: Option<SystemTime>,
     selected: bool,
     encrypted: bool,
-    show_delete_confirmation: bool,
+    amount: Option<Amount>,
+    language: Option<String>,
+    codes: Vec<Barcode>,
+    favorite: bool,
+    viewed: Option<String>,
+    metadata: Option<Metadata>,
+    /// Moved to cold storage, only its catalog entry is left.
+    archived: bool,
+    tags: Vec<String>,
+    label: Option<Label>,
+    /// Values of the custom fields of the cabinet, see `fields`.
+    fields: BTreeMap<String, String>,
+    /// Who in the household the document belongs to, see `catalog::Owner`.
+    owner: Option<String>,
+    /// Why the document couldn't be edited, e.g. someone else sharing the cabinet is.
+    #[serde(skip)]
+    lock_notice: Option<String>,
+    #[serde(skip)]
+    draft: Draft,
     #[serde(skip)]
     state: DocState,
 }
 
+/// What the rename form of a document holds while it is edited.
+#[derive(Debug, Clone, Default)]
+pub struct Draft {
+    date: String,
+    institution: String,
+    title: String,
+    page: String,
+    /// Where the suggested date comes from, when the filename has none.
+    date_hint: String,
+    /// Whether the page was typed, otherwise it follows the pages of the group filed.
+    page_typed: bool,
+    page_hint: String,
+    /// Custom fields by name, as typed.
+    fields: BTreeMap<String, String>,
+    /// Why the custom fields can't be saved as typed.
+    field_error: Option<String>,
+    /// Why the file couldn't be renamed, e.g. another document has the name.
+    rename_error: Option<String>,
+}
+
 #[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum DocState {
     Idle {
         edit_button: button::State,
         preview_button: button::State,
+        favorite_button: button::State,
+        tools_button: button::State,
+        tool_buttons: Vec<button::State>,
+        /// Whether the "Open with" menu is unfolded.
+        show_tools: bool,
+        label_button: button::State,
+        /// One per `Label::ALL` and one to clear the label.
+        label_buttons: Vec<button::State>,
+        /// Whether the label palette is unfolded.
+        show_labels: bool,
+        owner_button: button::State,
     },
     Editing {
         date_input: text_input::State,
+        calendar_button: button::State,
+        /// A day of the month the date picker shows, `None` while it is folded.
+        picker: Option<NaiveDate>,
+        previous_month_button: button::State,
+        next_month_button: button::State,
+        /// One per day of the six weeks the date picker can show.
+        day_buttons: Vec<button::State>,
         institution_input: text_input::State,
         title_input: text_input::State,
         page_input: text_input::State,
         delete_button: button::State,
         cancel_button: button::State,
         submit_button: button::State,
-        confirm_yes_button: button::State,
-        confirm_no_button: button::State,
+        searchable_button: button::State,
+        /// One per custom field of the cabinet.
+        field_inputs: Vec<text_input::State>,
+    },
+    /// In the catalog but no longer on disk.
+    Missing {
+        locate_button: button::State,
+        remove_button: button::State,
+        not_found: bool,
     },
+    /// In cold storage, see `archive::archive_document`.
+    Archived { restore_button: button::State },
 }
 
 impl Default for DocState {
@@ -461,6 +9551,14 @@ impl Default for DocState {
         DocState::Idle {
             edit_button: button::State::new(),
             preview_button: button::State::new(),
+            favorite_button: button::State::new(),
+            tools_button: button::State::new(),
+            tool_buttons: Vec::new(),
+            show_tools: false,
+            label_button: button::State::new(),
+            label_buttons: Vec::new(),
+            show_labels: false,
+            owner_button: button::State::new(),
         }
     }
 }
@@ -470,37 +9568,158 @@ pub enum DocMessage {
     Selected(bool),
     Edit,
     DateEdited(String),
+    /// Unfolds the date picker on the month of the date typed, or folds it.
+    ToggleCalendar,
+    /// Shows the month of this day in the date picker.
+    ShowMonth(NaiveDate),
+    DatePicked(NaiveDate),
     InstitutionEdited(String),
     TitleEdited(String),
     PageEdited(String),
+    FieldEdited(String, String),
     FinishEdition,
-    Delete,
+    /// Deletes the file, once confirmed, see `Confirmation::Delete`.
     ConfirmDelete,
-    ConfirmNo,
     Cancel,
     OpenPreviewPane(String, Pane),
+    MakeSearchable(String),
+    Locate,
+    RemoveFromCatalog,
+    ToggleFavorite,
+    ToggleTools,
+    OpenWith(ExternalTool),
+    ToggleLabels,
+    SetLabel(Option<Label>),
+    SetOwner(Option<String>),
+    Unarchive(String),
 }
 
 impl Document {
     fn new(path: String) -> Self {
-        let options = OptDoc::new(&path);
-        let now: DateTime<Utc> = Utc::now();
-        let tmp = &path.clone();
-        let _path = Path::new(tmp);
-        let file_stem = _path.file_stem().unwrap().to_str().unwrap();
-        let extension = utils::extension(_path);
-        Document {
+        let file_stem = Path::new(&path).file_stem().unwrap().to_str().unwrap();
+        let extension = utils::extension(&path);
+        let filename = format!("{}.{}", file_stem, extension);
+        let mut doc = Document {
+            id: DocumentId::default(),
             path,
-            filename: format!("{}.{}", file_stem, extension),
-            date: options.date.unwrap_or(now.format("%Y-%m-%d").to_string()),
-            institution: options.institution.unwrap_or(String::new()),
-            title: options.name.unwrap_or(String::new()),
-            page: options.page.unwrap_or(String::from("1")).parse().unwrap(),
-            extension: extension.to_string(),
+            filename,
+            date: None,
+            institution: None,
+            name: None,
+            page: None,
+            normalized: false,
+            extension,
+            size: 0,
+            modified: None,
             selected: false,
             encrypted: false,
-            show_delete_confirmation: false,
+            amount: None,
+            language: None,
+            codes: Vec::new(),
+            favorite: false,
+            viewed: None,
+            metadata: None,
+            archived: false,
+            tags: Vec::new(),
+            label: None,
+            fields: BTreeMap::new(),
+            owner: None,
+            lock_notice: None,
+            draft: Draft::default(),
             state: DocState::default(),
+        };
+        doc.parse();
+        doc
+    }
+
+    /// Reads the fields of the filename and the size and modification time of the file.
+    fn parse(&mut self) {
+        let options = OptDoc::new(&self.path);
+        self.normalized = options.is_normalized(&self.path);
+        self.date = options.day();
+        self.page = options.page_number();
+        self.institution = options.institution;
+        self.name = options.name;
+        let metadata = std::fs::metadata(&self.path).ok();
+        self.size = metadata.as_ref().map_or(0, |metadata| metadata.len());
+        self.modified = metadata.and_then(|metadata| metadata.modified().ok());
+    }
+
+    /// Whether the file is still the one that was read, by its size and modification time.
+    /// Missing and archived documents never are.
+    fn is_current(&self) -> bool {
+        matches!(self.state, DocState::Idle { .. } | DocState::Editing { .. })
+            && !self.archived
+            && std::fs::metadata(&self.path).is_ok_and(|metadata| {
+                metadata.len() == self.size && metadata.modified().ok() == self.modified
+            })
+    }
+
+    /// The rename form filled with the fields of the filename, today and page 1 when it
+    /// has none.
+    fn draft(&self) -> Draft {
+        Draft {
+            date: self
+                .date
+                .unwrap_or_else(|| Utc::now().naive_utc().date())
+                .format("%Y-%m-%d")
+                .to_string(),
+            institution: self.institution.clone().unwrap_or_default(),
+            title: self.name.clone().unwrap_or_default(),
+            page: self.page.unwrap_or(1).to_string(),
+            date_hint: String::new(),
+            page_typed: false,
+            page_hint: String::new(),
+            fields: self.fields.clone(),
+            field_error: None,
+            rename_error: None,
+        }
+    }
+
+    /// Fills the fields the filename doesn't have from the filename parser plugins, or
+    /// else from the PDF's own metadata.
+    fn prefill(&mut self) {
+        self.draft = self.draft();
+        let mut options = OptDoc::new(&self.path);
+        if !options.is_parseable() {
+            if let Some(parsed) = plugins::registry().parse(&self.filename) {
+                if let Some(date) = parsed.date {
+                    self.draft.date = date.clone();
+                    self.draft.date_hint = "Date from the filename".to_string();
+                    options.date = Some(date);
+                }
+                if let Some(institution) = parsed.institution {
+                    self.draft.institution = institution.clone();
+                    options.institution = Some(institution);
+                }
+                if let Some(name) = parsed.name {
+                    self.draft.title = name.clone();
+                    options.name = Some(name);
+                }
+            }
+        }
+        // Documents that weren't indexed yet are read on the spot.
+        let metadata = match self
+            .metadata
+            .clone()
+            .or_else(|| metadata::read(Path::new(&self.path)))
+        {
+            Some(metadata) => metadata,
+            None => return,
+        };
+        if let (None, Some(created)) = (&options.date, &metadata.created) {
+            self.draft.date = created.clone();
+            self.draft.date_hint = if self.extension == "pdf" {
+                "Date from the PDF's metadata".to_string()
+            } else {
+                "Date the photo was taken".to_string()
+            };
+        }
+        if let (None, Some(author)) = (&options.institution, &metadata.author) {
+            self.draft.institution = author.clone();
+        }
+        if let (None, Some(title)) = (&options.name, &metadata.title) {
+            self.draft.title = title.clone();
         }
     }
 
@@ -510,31 +9729,55 @@ impl Document {
                 self.selected = selected;
             }
             DocMessage::Edit => {
+                // Two people renaming it at once would overwrite each other's changes.
+                self.lock_notice = match locks::lock_document(Path::new(&self.path)) {
+                    Ok(()) => None,
+                    Err(LockError::HeldError(holder)) => {
+                        Some(format!("Being edited by {}", holder))
+                    }
+                    Err(LockError::WriteError) => Some("The cabinet is read-only".to_string()),
+                };
+                if self.lock_notice.is_some() {
+                    return;
+                }
+                self.prefill();
                 self.state = DocState::Editing {
                     date_input: Default::default(),
+                    calendar_button: Default::default(),
+                    picker: None,
+                    previous_month_button: Default::default(),
+                    next_month_button: Default::default(),
+                    day_buttons: Vec::new(),
                     institution_input: Default::default(),
                     title_input: Default::default(),
                     page_input: Default::default(),
                     delete_button: Default::default(),
                     cancel_button: Default::default(),
                     submit_button: Default::default(),
-                    confirm_yes_button: Default::default(),
-                    confirm_no_button: Default::default(),
+                    searchable_button: Default::default(),
+                    field_inputs: Vec::new(),
                 };
             }
             DocMessage::Cancel => {
-                self.state = DocState::Idle {
-                    edit_button: button::State::new(),
-                    preview_button: button::State::new(),
-                }
+                // Nothing typed is kept, the file keeps its name.
+                locks::unlock_document(Path::new(&self.path));
+                self.draft = Draft::default();
+                self.state = DocState::default()
             }
             DocMessage::FinishEdition => {
-                self.institution = utils::to_camelcase(&*self.institution);
-                self.title = utils::to_camelcase(&*self.title);
+                // Only a date that parses back goes into the filename.
+                if let Err(error) = utils::check_date(&self.draft.date) {
+                    self.draft.rename_error = Some(format!("Date: {}", error));
+                    return;
+                }
+                let draft = &self.draft;
                 let basename = Path::new(&self.path).parent();
-                let filename = format!(
-                    "{}_{}_{}_{}.{}",
-                    &self.date, &self.institution, &self.title, &self.page, &self.extension
+                let filename = utils::normalized_filename(
+                    &draft.date,
+                    &utils::to_camelcase(&draft.institution),
+                    &utils::to_camelcase(&draft.title),
+                    &draft.page,
+                    &self.extension,
                 );
                 let new_path: String = basename
                     .and_then(|p| {
@@ -543,107 +9786,493 @@ impl Document {
                         pb.push(&filename);
                         pb.to_str().map(|s| s.to_string())
                     })
-                    .unwrap_or(filename);
-                fs::rename(&self.path, &new_path).unwrap(); // Rename file
-                println!(
-                    "event=\"Rename\" old=\"{}\" new=\"{}\"",
-                    &self.path, &new_path
-                );
-                self.path = new_path.to_string(); // Update UI doc path.
-                self.state = DocState::Idle {
-                    edit_button: button::State::new(),
-                    preview_button: button::State::new(),
+                    .unwrap_or_else(|| filename.clone());
+                if let Err(error) =
+                    storage::backend().rename(Path::new(&self.path), Path::new(&new_path))
+                {
+                    warn!(event = "rename_failed", old = %self.path, new = %new_path, ?error);
+                    self.draft.rename_error = Some(match error {
+                        storage::StorageError::ExistsError => {
+                            format!("{} is taken, change the title or page.", filename)
+                        }
+                        _ => "The file couldn't be renamed.".to_string(),
+                    });
+                    return;
                 }
+                catalog::record_rename(Path::new(&self.path), Path::new(&new_path));
+                locks::unlock_document(Path::new(&self.path));
+                info!(event = "Rename", old = %self.path, new = %new_path);
+                self.path = new_path.to_string(); // Update UI doc path.
+                self.filename = filename; // The catalog knows it by the new name.
+                self.parse();
+                self.state = DocState::default()
             }
-            DocMessage::Delete => {
-                if self.show_delete_confirmation {
-                    self.show_delete_confirmation = false;
-                } else {
-                    self.show_delete_confirmation = true;
+            DocMessage::DateEdited(s) => {
+                self.draft.date = s;
+                self.draft.rename_error = None;
+            }
+            DocMessage::ToggleCalendar => {
+                let typed = NaiveDate::parse_from_str(&self.draft.date, "%Y-%m-%d").ok();
+                if let DocState::Editing { picker, .. } = &mut self.state {
+                    *picker = match picker {
+                        Some(_) => None,
+                        None => Some(typed.unwrap_or_else(|| Utc::now().naive_utc().date())),
+                    };
                 }
             }
-            DocMessage::ConfirmNo => {
-                self.show_delete_confirmation = false;
+            DocMessage::ShowMonth(day) => {
+                if let DocState::Editing { picker, .. } = &mut self.state {
+                    *picker = Some(day);
+                }
             }
-            DocMessage::DateEdited(s) => {
-                self.date = s;
+            DocMessage::DatePicked(day) => {
+                self.draft.date = day.format("%Y-%m-%d").to_string();
+                self.draft.rename_error = None;
+                if let DocState::Editing { picker, .. } = &mut self.state {
+                    *picker = None;
+                }
             }
             DocMessage::InstitutionEdited(s) => {
-                self.institution = s;
+                self.draft.institution = s;
+                self.draft.rename_error = None;
             }
             DocMessage::PageEdited(s) => {
-                self.page = s;
+                self.draft.page = s;
+                self.draft.page_typed = true;
+                self.draft.page_hint.clear();
+                self.draft.rename_error = None;
             }
             DocMessage::TitleEdited(s) => {
-                self.title = s;
+                self.draft.title = s;
+                self.draft.rename_error = None;
+            }
+            DocMessage::FieldEdited(name, s) => {
+                self.draft.fields.insert(name, s);
+            }
+            DocMessage::ToggleFavorite => {
+                self.favorite = !self.favorite;
+                catalog::record_favorite(Path::new(&self.path), self.favorite);
+            }
+            DocMessage::ToggleLabels => {
+                if let DocState::Idle { show_labels, .. } = &mut self.state {
+                    *show_labels = !*show_labels;
+                }
+            }
+            DocMessage::SetLabel(label) => {
+                if let DocState::Idle { show_labels, .. } = &mut self.state {
+                    *show_labels = false;
+                }
+                self.label = label;
+                catalog::record_label(Path::new(&self.path), label);
+            }
+            DocMessage::SetOwner(owner) => {
+                catalog::record_owner(Path::new(&self.path), owner.clone());
+                self.owner = owner;
+            }
+            DocMessage::ToggleTools => {
+                if let DocState::Idle { show_tools, .. } = &mut self.state {
+                    *show_tools = !*show_tools;
+                }
+            }
+            DocMessage::OpenWith(tool) => {
+                if let DocState::Idle { show_tools, .. } = &mut self.state {
+                    *show_tools = false;
+                }
+                match tool.launch(&self.path) {
+                    Ok(()) => info!(event = "OpenWith", tool = %tool.name, file = %self.path),
+                    Err(error) => warn!(event = "tool_failed", tool = %tool.name, ?error),
+                }
+            }
+            DocMessage::Locate => {
+                if let DocState::Missing { not_found, .. } = &mut self.state {
+                    *not_found = true;
+                }
             }
             _ => {}
         }
     }
 
-    fn view(&mut self, pane: &Pane) -> Element<DocMessage> {
+    /// `tools` are the external tools offered in the "Open with" menu, for any extension.
+    /// The owner button cycles through `owners`, it is left out if there are none. The row
+    /// starts with the badge of the institution, if any institution of the list has one.
+    fn view(
+        &mut self,
+        pane: &Pane,
+        tools: &[ExternalTool],
+        field_defs: &[FieldDef],
+        owners: &[Owner],
+        badges: &BTreeMap<String, Badge>,
+    ) -> Element<'_, DocMessage> {
         match &mut self.state {
+            DocState::Missing {
+                locate_button,
+                remove_button,
+                not_found,
+            } => Row::new()
+                .spacing(20)
+                .align_items(Align::Center)
+                .push(
+                    Text::new(format!("{} is missing", self.filename))
+                        .color([0.8, 0.4, 0.1])
+                        .width(Length::Fill),
+                )
+                .push(
+                    Text::new(if *not_found {
+                        "No file with the same content"
+                    } else {
+                        ""
+                    })
+                    .size(14)
+                    .color([0.5, 0.5, 0.5]),
+                )
+                .push(
+                    Button::new(locate_button, Text::new("Locate..."))
+                        .on_press(DocMessage::Locate)
+                        .padding(10)
+                        .style(style::Button::Refresh),
+                )
+                .push(
+                    Button::new(remove_button, Text::new("Remove from catalog"))
+                        .on_press(DocMessage::RemoveFromCatalog)
+                        .padding(10)
+                        .style(style::Button::Destructive),
+                )
+                .into(),
+            DocState::Archived { restore_button } => Row::new()
+                .spacing(20)
+                .align_items(Align::Center)
+                .push(
+                    Text::new(&self.filename)
+                        .color([0.5, 0.5, 0.5])
+                        .width(Length::Fill),
+                )
+                .push(Text::new("Archived").size(14).color([0.5, 0.5, 0.5]))
+                .push(
+                    Button::new(restore_button, Text::new("Restore"))
+                        .on_press(DocMessage::Unarchive(self.path.clone()))
+                        .padding(10)
+                        .style(style::Button::Refresh),
+                )
+                .into(),
             DocState::Idle {
                 preview_button,
                 edit_button,
+                favorite_button,
+                tools_button,
+                tool_buttons,
+                show_tools,
+                label_button,
+                label_buttons,
+                show_labels,
+                owner_button,
             } => {
                 let checkbox = Checkbox::new(self.selected, "", DocMessage::Selected);
+                let stripe = Button::new(label_button, Text::new(""))
+                    .on_press(DocMessage::ToggleLabels)
+                    .width(Length::Units(8))
+                    .height(Length::Units(36))
+                    .style(style::Button::Label {
+                        color: self.label.map(Label::color),
+                        selected: false,
+                    });
+                let favorite = Button::new(
+                    favorite_button,
+                    Text::new(if self.favorite { "★" } else { "☆" }).size(20),
+                )
+                .on_press(DocMessage::ToggleFavorite)
+                .padding(10)
+                .style(style::Button::Icon);
                 let preview = Button::new(preview_button, Text::new(&self.filename))
                     .on_press(DocMessage::OpenPreviewPane(self.path.clone(), *pane))
                     .style(style::Button::Doc)
                     .width(Length::Fill);
-                Row::new()
+                let amount = Text::new(
+                    self.amount
+                        .as_ref()
+                        .map(|a| a.to_string())
+                        .unwrap_or_default(),
+                )
+                .size(16)
+                .color([0.3, 0.3, 0.3]);
+                // Badge for documents carrying a QR code or barcode.
+                let codes = Text::new(if self.codes.is_empty() {
+                    ""
+                } else if self.codes.iter().any(|c| c.symbology.starts_with("QR")) {
+                    "QR"
+                } else {
+                    "BC"
+                })
+                .size(14)
+                .color([0.2, 0.2, 0.7]);
+                let tags = Text::new(self.tags.iter().map(|tag| format!("#{}", tag)).join(" "))
+                    .size(14)
+                    .color([0.5, 0.5, 0.5]);
+                let lock_notice = Text::new(self.lock_notice.as_deref().unwrap_or_default())
+                    .size(14)
+                    .color([0.8, 0.4, 0.1]);
+                let extension = &self.extension;
+                let tools: Vec<_> = tools.iter().filter(|t| t.handles(extension)).collect();
+                let mut row = Row::new()
                     .spacing(20)
                     .align_items(Align::Center)
+                    .push(stripe);
+                if !badges.is_empty() {
+                    let institution = self.institution.as_ref();
+                    row = row.push(Badge::view(institution.and_then(|i| badges.get(i))));
+                }
+                row = row
                     .push(checkbox)
+                    .push(favorite)
                     .push(preview)
-                    .push(
-                        Button::new(edit_button, edit_icon())
-                            .on_press(DocMessage::Edit)
+                    .push(lock_notice)
+                    .push(tags)
+                    .push(codes)
+                    .push(amount);
+                if !owners.is_empty() {
+                    let owner = self.owner.as_ref();
+                    let current = owners.iter().position(|o| Some(&o.name) == owner);
+                    // Past the last owner comes nobody.
+                    let next = match current {
+                        Some(i) => owners.get(i + 1),
+                        None => owners.first(),
+                    };
+                    row = row.push(
+                        Button::new(
+                            owner_button,
+                            Text::new(self.owner.as_deref().unwrap_or("nobody")).size(14),
+                        )
+                        .on_press(DocMessage::SetOwner(next.map(|owner| owner.name.clone())))
+                        .padding(6)
+                        .style(style::Button::Label {
+                            color: current.map(|i| owners[i].color.color()),
+                            selected: false,
+                        }),
+                    );
+                }
+                if !tools.is_empty() {
+                    row = row.push(
+                        Button::new(tools_button, Text::new("Open with").size(14))
+                            .on_press(DocMessage::ToggleTools)
                             .padding(10)
-                            .style(style::Button::Icon),
-                    )
+                            .style(style::Button::Refresh),
+                    );
+                }
+                row = row.push(
+                    Button::new(edit_button, edit_icon())
+                        .on_press(DocMessage::Edit)
+                        .padding(10)
+                        .style(style::Button::Icon),
+                );
+                if *show_labels {
+                    label_buttons.resize_with(Label::ALL.len() + 1, Default::default);
+                    let current = self.label;
+                    let choices = Label::ALL.iter().map(|label| Some(*label)).chain([None]);
+                    let palette = choices.zip(label_buttons.iter_mut()).fold(
+                        Row::new().spacing(10),
+                        |palette, (label, state)| {
+                            palette.push(
+                                Button::new(
+                                    state,
+                                    Text::new(if label.is_none() { "×" } else { "" }),
+                                )
+                                .on_press(DocMessage::SetLabel(label))
+                                .width(Length::Units(24))
+                                .height(Length::Units(24))
+                                .style(style::Button::Label {
+                                    color: label.map(Label::color),
+                                    selected: label == current,
+                                }),
+                            )
+                        },
+                    );
+                    return Column::new().spacing(5).push(row).push(palette).into();
+                }
+                if !*show_tools || tools.is_empty() {
+                    return row.into();
+                }
+                tool_buttons.resize_with(tools.len(), Default::default);
+                let menu = tools.into_iter().zip(tool_buttons.iter_mut()).fold(
+                    Row::new().spacing(10),
+                    |menu, (tool, state)| {
+                        menu.push(
+                            Button::new(state, Text::new(&tool.name).size(14))
+                                .on_press(DocMessage::OpenWith(tool.clone()))
+                                .padding(10)
+                                .style(style::Button::Refresh),
+                        )
+                    },
+                );
+                Column::new()
+                    .spacing(5)
+                    .align_items(Align::End)
+                    .push(row)
+                    .push(menu)
                     .into()
             }
             DocState::Editing {
                 date_input,
+                calendar_button,
+                picker,
+                previous_month_button,
+                next_month_button,
+                day_buttons,
                 institution_input,
                 title_input,
                 page_input,
                 delete_button,
                 cancel_button,
                 submit_button,
-                confirm_no_button,
-                confirm_yes_button,
+                searchable_button,
+                field_inputs,
             } => {
+                let codes = self
+                    .codes
+                    .iter()
+                    .fold(Column::new().spacing(5), |column, code| {
+                        column.push(
+                            Text::new(format!("{}: {}", code.symbology, code.data))
+                                .size(14)
+                                .color([0.3, 0.3, 0.3]),
+                        )
+                    });
+                let language = Text::new(match &self.language {
+                    Some(language) => format!("Language: {}", language),
+                    None => String::new(),
+                })
+                .size(14)
+                .color([0.3, 0.3, 0.3]);
+                field_inputs.resize_with(field_defs.len(), Default::default);
+                let draft = &self.draft;
+                let mut custom_fields = field_defs.iter().zip(field_inputs.iter_mut()).fold(
+                    Column::new().spacing(10),
+                    |column, (def, input)| {
+                        let name = def.name.clone();
+                        let value = draft.fields.get(&def.name).map_or("", String::as_str);
+                        let column = column.push(
+                            TextInput::new(input, &def.placeholder(), value, move |s| {
+                                DocMessage::FieldEdited(name.clone(), s)
+                            })
+                            .on_submit(DocMessage::FinishEdition)
+                            .padding(10),
+                        );
+                        match def.check(value) {
+                            _ if value.trim().is_empty() => column,
+                            Ok(value) => {
+                                column.push(check_text(format!("{}: {}", def.name, value), true))
+                            }
+                            Err(error) => column.push(check_text(error.to_string(), false)),
+                        }
+                    },
+                );
+                // Checked as they are typed, rather than after the rename.
+                let date = utils::check_date(&draft.date);
+                let institution = utils::check_word(&draft.institution);
+                let title = utils::check_word(&draft.title);
+                let page = utils::check_page(&draft.page);
+                let filename = match (&date, &institution, &title, &page) {
+                    (Ok(date), Ok(institution), Ok(title), Ok(page)) => {
+                        let filename = utils::normalized_filename(
+                            date,
+                            institution,
+                            title,
+                            page,
+                            &self.extension,
+                        );
+                        check_text(format!("Filename: {}", filename), true)
+                    }
+                    _ => Text::new(""),
+                };
+                if let Some(error) = &draft.field_error {
+                    custom_fields =
+                        custom_fields.push(Text::new(error).size(14).color([0.8, 0.2, 0.2]));
+                }
+                // Typing the date still works, the picker only fills it in.
+                let date_picker = match picker {
+                    Some(month) => date_picker(
+                        *month,
+                        &draft.date,
+                        previous_month_button,
+                        next_month_button,
+                        day_buttons,
+                    ),
+                    None => Column::new(),
+                };
                 Column::new()
                     .spacing(10)
                     .push(Text::new(&self.filename))
+                    .push(language)
+                    .push(codes)
                     .push(
-                        TextInput::new(date_input, "Date", &self.date, DocMessage::DateEdited)
-                            .on_submit(DocMessage::FinishEdition)
-                            .padding(10),
+                        Row::new()
+                            .spacing(10)
+                            .push(
+                                TextInput::new(
+                                    date_input,
+                                    "Date",
+                                    &self.draft.date,
+                                    DocMessage::DateEdited,
+                                )
+                                .on_submit(DocMessage::FinishEdition)
+                                .padding(10),
+                            )
+                            .push(
+                                Button::new(calendar_button, Text::new("Calendar"))
+                                    .on_press(DocMessage::ToggleCalendar)
+                                    .padding(10)
+                                    .style(style::Button::Refresh),
+                            ),
+                    )
+                    .push(date_picker)
+                    .push(part_text("Date", &date))
+                    .push(
+                        Text::new(&self.draft.date_hint)
+                            .size(14)
+                            .color([0.5, 0.5, 0.5]),
                     )
                     .push(
                         TextInput::new(
                             institution_input,
                             "Institution",
-                            &self.institution,
+                            &self.draft.institution,
                             DocMessage::InstitutionEdited,
                         )
                         .on_submit(DocMessage::FinishEdition)
                         .padding(10),
                     )
+                    .push(part_text("Institution", &institution))
                     .push(
-                        TextInput::new(title_input, "Title", &self.title, DocMessage::TitleEdited)
-                            .on_submit(DocMessage::FinishEdition)
-                            .padding(10),
+                        TextInput::new(
+                            title_input,
+                            "Title",
+                            &self.draft.title,
+                            DocMessage::TitleEdited,
+                        )
+                        .on_submit(DocMessage::FinishEdition)
+                        .padding(10),
                     )
+                    .push(part_text("Title", &title))
                     .push(
-                        TextInput::new(page_input, "Page", &self.page, DocMessage::PageEdited)
-                            .on_submit(DocMessage::FinishEdition)
-                            .padding(10),
+                        TextInput::new(
+                            page_input,
+                            "Page",
+                            &self.draft.page,
+                            DocMessage::PageEdited,
+                        )
+                        .on_submit(DocMessage::FinishEdition)
+                        .padding(10),
+                    )
+                    .push(part_text("Page", &page))
+                    .push(
+                        Text::new(&self.draft.page_hint)
+                            .size(14)
+                            .color([0.5, 0.5, 0.5]),
                     )
+                    .push(filename)
+                    .push(match &self.draft.rename_error {
+                        Some(error) => check_text(error.clone(), false),
+                        None => Text::new(""),
+                    })
+                    .push(custom_fields)
                     .push(
                         Row::new()
                             .spacing(10)
@@ -665,25 +10294,17 @@ impl Document {
                                         .push(delete_icon())
                                         .push(Text::new("Delete")),
                                 )
-                                .on_press(DocMessage::Delete)
+                                .on_press(DocMessage::ConfirmDelete)
                                 .padding(10)
                                 .style(style::Button::Destructive),
                             )
-                            .push(if self.show_delete_confirmation {
-                                Row::new()
-                                    .push(
-                                        Button::new(confirm_no_button, Text::new("No!"))
-                                            .on_press(DocMessage::ConfirmNo)
-                                            .style(style::Button::Cancel),
-                                    )
-                                    .push(
-                                        Button::new(confirm_yes_button, Text::new("Yes?"))
-                                            .on_press(DocMessage::ConfirmDelete)
-                                            .style(style::Button::Destructive),
-                                    )
-                                    .padding(10)
-                                    .spacing(10)
-                                    .align_items(Align::Center)
+                            .push(if self.extension == "pdf" {
+                                Row::new().push(
+                                    Button::new(searchable_button, Text::new("Make searchable"))
+                                        .on_press(DocMessage::MakeSearchable(self.path.clone()))
+                                        .padding(10)
+                                        .style(style::Button::Refresh),
+                                )
                             } else {
                                 Row::new()
                             })
@@ -709,77 +10330,318 @@ pub struct Controls {
     all_button: button::State,
     active_button: button::State,
     completed_button: button::State,
+    favorites_button: button::State,
+    recent_button: button::State,
+    index_button: button::State,
+    compare_button: button::State,
+    archived_button: button::State,
+    archive_button: button::State,
+    tags_button: button::State,
+    bundle_button: button::State,
+    /// One per `Label::ALL`.
+    label_buttons: Vec<button::State>,
+    /// One per owner of the cabinet.
+    owner_buttons: Vec<button::State>,
 }
 
 impl Controls {
-    fn view(&mut self, docs: &[Document], current_filter: Filter) -> Row<Message> {
+    /// `can_archive` tells whether an archive folder is configured. `current_owner` is the
+    /// one of `owners` the list is narrowed to.
+    fn view(
+        &mut self,
+        docs: &Docs,
+        current_filter: Filter,
+        current_owner: Option<&str>,
+        owners: &[Owner],
+        can_archive: bool,
+    ) -> Row<'_, Message> {
         let Controls {
             all_button,
             active_button,
             completed_button,
+            favorites_button,
+            recent_button,
+            index_button,
+            compare_button,
+            archived_button,
+            archive_button,
+            tags_button,
+            bundle_button,
+            label_buttons,
+            owner_buttons,
         } = self;
 
         let filter_button = |state, label, filter: Filter, current_filter: Filter| {
-            let label = Text::new(format!(
-                "{}: {}",
-                label,
-                docs.iter().filter(|d| filter.matches(d)).count()
-            ))
-            .size(16);
+            let count = docs.matching(filter, None, None).count();
+            // Documents left to name are the cleanup backlog, they stand out.
+            let label: Element<_> = if filter == Filter::Unnormalized && count > 0 {
+                Row::new()
+                    .spacing(6)
+                    .align_items(Align::Center)
+                    .push(Text::new(label).size(16))
+                    .push(
+                        Container::new(Text::new(count.to_string()).size(13))
+                            .padding(3)
+                            .style(style::Badge),
+                    )
+                    .into()
+            } else {
+                Text::new(format!("{}: {}", label, count)).size(16).into()
+            };
             let button = Button::new(state, label).style(style::Button::Filter {
                 selected: filter == current_filter,
             });
 
-            button.on_press(Message::FilterChanged(filter)).padding(8)
+            button
+                .on_press(Message::DocPane(DocPaneMessage::FilterChanged(filter)))
+                .padding(8)
         };
 
-        Row::new().spacing(20).align_items(Align::Center).push(
-            Row::new()
-                .width(Length::Shrink)
-                .spacing(10)
-                .push(filter_button(
-                    all_button,
-                    "All",
-                    Filter::All,
-                    current_filter,
-                ))
-                .push(filter_button(
-                    active_button,
-                    "Normalized",
-                    Filter::Normalized,
-                    current_filter,
-                ))
-                .push(filter_button(
-                    completed_button,
-                    "Unnormalized",
-                    Filter::Unnormalized,
-                    current_filter,
-                )),
-        )
+        // Comparing needs exactly two documents ticked.
+        let selected: Vec<&Document> = docs.selected().collect();
+        let mut compare = Button::new(compare_button, Text::new("compare").size(16))
+            .padding(8)
+            .style(style::Button::Filter { selected: false });
+        if let [left, right] = selected.as_slice() {
+            compare = compare.on_press(Message::Compare(left.path.clone(), right.path.clone()));
+        }
+        let archivable: Vec<String> = selected
+            .iter()
+            .filter(|d| !d.archived && matches!(d.state, DocState::Idle { .. }))
+            .map(|d| d.path.clone())
+            .collect();
+        let mut archive = Button::new(archive_button, Text::new("archive").size(16))
+            .padding(8)
+            .style(style::Button::Filter { selected: false });
+        if can_archive && !archivable.is_empty() {
+            archive = archive.on_press(Message::Archive(archivable));
+        }
+        let taggable: Vec<String> = selected
+            .iter()
+            .filter(|d| !d.archived && matches!(d.state, DocState::Idle { .. }))
+            .map(|d| d.path.clone())
+            .collect();
+        let mut tags = Button::new(tags_button, Text::new("tags").size(16))
+            .padding(8)
+            .style(style::Button::Filter { selected: false });
+        if !taggable.is_empty() {
+            tags = tags.on_press(Message::OpenTagsPane(taggable.clone()));
+        }
+        let mut bundle = Button::new(bundle_button, Text::new("bundle").size(16))
+            .padding(8)
+            .style(style::Button::Filter { selected: false });
+        if !taggable.is_empty() {
+            bundle = bundle.on_press(Message::OpenBundlePane(taggable, Vec::new()));
+        }
+
+        label_buttons.resize_with(Label::ALL.len(), Default::default);
+        let labels = Label::ALL.iter().zip(label_buttons.iter_mut()).fold(
+            Row::new().spacing(5).align_items(Align::Center),
+            |labels, (label, state)| {
+                let filter = Filter::Label(*label);
+                let count = docs.matching(filter, None, None).count();
+                labels.push(
+                    Button::new(state, Text::new(count.to_string()).size(14))
+                        .on_press(Message::DocPane(DocPaneMessage::FilterChanged(filter)))
+                        .padding(4)
+                        .style(style::Button::Label {
+                            color: Some(label.color()),
+                            selected: filter == current_filter,
+                        }),
+                )
+            },
+        );
+
+        owner_buttons.resize_with(owners.len(), Default::default);
+        let owners = owners.iter().zip(owner_buttons.iter_mut()).fold(
+            Row::new().spacing(5).align_items(Align::Center),
+            |owners, (owner, state)| {
+                let selected = current_owner == Some(owner.name.as_str());
+                let count = docs
+                    .matching(current_filter, Some(owner.name.as_str()), None)
+                    .count();
+                owners.push(
+                    Button::new(
+                        state,
+                        Text::new(format!("{}: {}", owner.name, count)).size(14),
+                    )
+                    .on_press(Message::DocPane(DocPaneMessage::OwnerFilterChanged(
+                        (!selected).then(|| owner.name.clone()),
+                    )))
+                    .padding(4)
+                    .style(style::Button::Label {
+                        color: Some(owner.color.color()),
+                        selected,
+                    }),
+                )
+            },
+        );
+
+        let totals = amount::totals(
+            docs.matching(current_filter, None, None)
+                .filter_map(|d| d.amount.as_ref()),
+        );
+        let totals = Text::new(if totals.is_empty() {
+            String::new()
+        } else {
+            format!("Total: {}", totals.iter().map(|t| t.to_string()).join(", "))
+        })
+        .size(16);
+
+        Row::new()
+            .spacing(20)
+            .align_items(Align::Center)
+            .push(
+                Row::new()
+                    .width(Length::Shrink)
+                    .spacing(10)
+                    .push(filter_button(
+                        all_button,
+                        "All",
+                        Filter::All,
+                        current_filter,
+                    ))
+                    .push(filter_button(
+                        active_button,
+                        "Normalized",
+                        Filter::Normalized,
+                        current_filter,
+                    ))
+                    .push(filter_button(
+                        completed_button,
+                        "Unnormalized",
+                        Filter::Unnormalized,
+                        current_filter,
+                    ))
+                    .push(filter_button(
+                        favorites_button,
+                        "Favorites",
+                        Filter::Favorites,
+                        current_filter,
+                    ))
+                    .push(filter_button(
+                        recent_button,
+                        "Recent",
+                        Filter::Recent,
+                        current_filter,
+                    ))
+                    .push(filter_button(
+                        archived_button,
+                        "Archived",
+                        Filter::Archived,
+                        current_filter,
+                    )),
+            )
+            .push(labels)
+            .push(owners)
+            .push(totals)
+            .push(
+                Button::new(index_button, Text::new("index").size(16))
+                    .on_press(Message::Index)
+                    .padding(8)
+                    .style(style::Button::Filter { selected: false }),
+            )
+            .push(compare)
+            .push(tags)
+            .push(bundle)
+            .push(archive)
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum Filter {
+    #[default]
     All,
     Normalized,
     Unnormalized,
-}
-
-impl Default for Filter {
-    fn default() -> Self {
-        Filter::All
-    }
+    Favorites,
+    Recent,
+    Archived,
+    Label(Label),
 }
 
 impl Filter {
     fn matches(&self, doc: &Document) -> bool {
         match self {
             Filter::All => true,
-            Filter::Normalized => utils::is_normalized(&doc.path),
-            Filter::Unnormalized => !utils::is_normalized(&doc.path),
+            Filter::Archived => doc.archived,
+            // Archived stubs only show up in their own list and the full one.
+            _ if doc.archived => false,
+            Filter::Normalized => doc.normalized,
+            Filter::Unnormalized => !doc.normalized,
+            Filter::Favorites => doc.favorite,
+            Filter::Label(label) => doc.label == Some(*label),
+            Filter::Recent => {
+                let since = Utc::now() - chrono::Duration::days(RECENT_DAYS);
+                doc.viewed.as_deref() >= Some(&since.format(catalog::VIEWED_FORMAT).to_string())
+            }
         }
     }
+
+    /// Recent lists the most recently viewed documents first, the others keep the
+    /// directory order.
+    fn order(&self, a: &Document, b: &Document) -> std::cmp::Ordering {
+        match self {
+            Filter::Recent => b.viewed.cmp(&a.viewed),
+            _ => std::cmp::Ordering::Equal,
+        }
+    }
+}
+
+/// How many documents of the list count as on screen when the indexing queue is
+/// reordered.
+const VISIBLE: usize = 20;
+
+/// Height of a document of the list that isn't edited, in pixels, to scroll to one.
+const ROW_HEIGHT: f32 = 44.0;
+
+/// Indexed documents after which the list is refreshed while the queue runs.
+const REFRESH_INDEXED: usize = 25;
+
+/// How many past searches are suggested under the search box.
+const SUGGESTIONS: usize = 8;
+
+/// How far back the Recent filter goes.
+const RECENT_DAYS: i64 = 30;
+
+/// How far `scroll` is scrolled down, in pixels. iced only tells it given the bounds of
+/// the scrollable and its content, these ones leave it as is.
+fn scroll_offset(scroll: &scrollable::State) -> f32 {
+    let content = Rectangle {
+        height: f32::MAX,
+        ..Default::default()
+    };
+    scroll.offset(Rectangle::default(), content) as f32
+}
+
+/// Scrolls `scroll` down `offset` pixels, as far as the content goes once laid out.
+fn scroll_to_offset(scroll: &mut scrollable::State, offset: f32) {
+    let content = Rectangle {
+        height: offset,
+        ..Default::default()
+    };
+    scroll.scroll_to(1.0, Rectangle::default(), content);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn refresh_button<'a>(
+    state: &'a mut button::State,
+    target_dir: &str,
+    size: u16,
+) -> Button<'a, Message> {
+    Button::new(state, Text::new("refresh").size(size))
+        .on_press(Message::RefreshTargetDir(target_dir.to_string()))
+}
+
+/// Nothing changes the documents behind the web build's back, its refresh button adds
+/// documents from the browser instead.
+#[cfg(target_arch = "wasm32")]
+fn refresh_button<'a>(
+    state: &'a mut button::State,
+    _target_dir: &str,
+    size: u16,
+) -> Button<'a, Message> {
+    Button::new(state, Text::new("add files").size(size)).on_press(Message::PickFiles)
 }
 
 fn loading_message<'a>() -> Element<'a, Message> {
@@ -794,6 +10656,117 @@ fn loading_message<'a>() -> Element<'a, Message> {
     .into()
 }
 
+/// Shown instead of the cabinet once it is locked, until the vault passphrase is entered.
+fn lock_screen(state: &mut State) -> Element<'_, Message> {
+    let mut unlock = Button::new(&mut state.unlock_button, Text::new("Unlock"))
+        .padding(10)
+        .style(style::Button::Update);
+    if !state.unlock_passphrase.is_empty() {
+        unlock = unlock.on_press(Message::UnlockCabinet);
+    }
+    Container::new(
+        Column::new()
+            .max_width(400)
+            .spacing(20)
+            .push(Text::new("Locked").size(40).color([0.5, 0.5, 0.5]))
+            .push(
+                Text::new(if state.locked_by_hand {
+                    "The cabinet was locked.".to_string()
+                } else {
+                    format!(
+                        "The cabinet locked itself after {} minutes without use.",
+                        state.preferences.auto_lock_minutes
+                    )
+                })
+                .size(16),
+            )
+            .push(
+                TextInput::new(
+                    &mut state.unlock_input,
+                    "Vault passphrase",
+                    &state.unlock_passphrase,
+                    Message::UnlockPassphraseEdited,
+                )
+                .password()
+                .on_submit(Message::UnlockCabinet)
+                .padding(10),
+            )
+            .push(
+                Text::new(&state.unlock_error)
+                    .size(16)
+                    .color([0.8, 0.2, 0.2]),
+            )
+            .push(unlock),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .padding(40)
+    .center_x()
+    .center_y()
+    .into()
+}
+
+/// Shown instead of the cabinet while a destructive action waits to be confirmed.
+fn confirmation_dialog(state: &mut State) -> Element<'_, Message> {
+    let pending = match &mut state.pending {
+        Some(pending) => pending,
+        None => return loading_message(),
+    };
+    Container::new(
+        Column::new()
+            .max_width(400)
+            .spacing(20)
+            .push(Text::new(&pending.question).size(20))
+            .push(Checkbox::new(
+                pending.dont_ask,
+                "Don't ask again",
+                Message::DontAskAgainToggled,
+            ))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(
+                        Button::new(&mut pending.no_button, Text::new("Cancel"))
+                            .on_press(Message::ConfirmationAnswered(false))
+                            .padding(10)
+                            .style(style::Button::Cancel),
+                    )
+                    .push(
+                        Button::new(&mut pending.yes_button, Text::new("Go ahead"))
+                            .on_press(Message::ConfirmationAnswered(true))
+                            .padding(10)
+                            .style(style::Button::Destructive),
+                    ),
+            )
+            .push(
+                Text::new("Which actions ask first can be changed in the settings.")
+                    .size(14)
+                    .color([0.5, 0.5, 0.5]),
+            ),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .padding(40)
+    .center_x()
+    .center_y()
+    .into()
+}
+
+/// A search hit under its document, the matched text highlighted. Pressing it shows the
+/// page the hit is on.
+fn snippet(state: &mut button::State, path: String, hit: Hit) -> Element<'_, Message> {
+    let text = Row::new()
+        .push(Text::new(hit.before).size(14).color([0.5, 0.5, 0.5]))
+        .push(Text::new(hit.matched).size(14).color([0.9, 0.42, 0.44]))
+        .push(Text::new(hit.after).size(14).color([0.5, 0.5, 0.5]))
+        .push(Text::new(format!("  p. {}", hit.page)).size(14));
+    Button::new(state, text)
+        .padding(4)
+        .style(style::Button::Filter { selected: false })
+        .on_press(Message::ShowHit(path, hit.page))
+        .into()
+}
+
 fn empty_message<'a>(message: &str) -> Element<'a, Message> {
     Container::new(
         Text::new(message)
@@ -815,7 +10788,7 @@ const ICONS: Font = Font::External {
 };
 
 fn icon(unicode: char) -> Text {
-    Text::new(&unicode.to_string())
+    Text::new(unicode.to_string())
         .font(ICONS)
         .width(Length::Units(20))
         .horizontal_alignment(HorizontalAlignment::Center)
@@ -830,10 +10803,137 @@ fn delete_icon() -> Text {
     icon('\u{F1F8}')
 }
 
+/// A line under an input of the rename form, green if what was typed is fine.
+fn check_text(line: String, passed: bool) -> Text {
+    let color = if passed {
+        [0.2, 0.6, 0.2]
+    } else {
+        [0.8, 0.2, 0.2]
+    };
+    Text::new(line).size(14).color(color)
+}
+
+/// What a part of the filename comes out as, or why it can't be one.
+fn part_text(label: &str, check: &Result<String, PartError>) -> Text {
+    match check {
+        Ok(part) => check_text(format!("{}: {}", label, part), true),
+        Err(error) => check_text(format!("{}: {}", label, error), false),
+    }
+}
+
+/// A calendar of the month of `month` to pick the date of a document from, with the day
+/// of `typed` picked if it is a valid date.
+fn date_picker<'a>(
+    month: NaiveDate,
+    typed: &str,
+    previous_month_button: &'a mut button::State,
+    next_month_button: &'a mut button::State,
+    day_buttons: &'a mut Vec<button::State>,
+) -> Column<'a, DocMessage> {
+    let typed = NaiveDate::parse_from_str(typed, "%Y-%m-%d").ok();
+    let cell = Length::Units(40);
+    let navigation = Row::new()
+        .spacing(10)
+        .align_items(Align::Center)
+        .push(
+            Button::new(previous_month_button, Text::new("<").size(14))
+                .on_press(DocMessage::ShowMonth(calendar::add_months(month, -1)))
+                .padding(5)
+                .style(style::Button::Refresh),
+        )
+        .push(
+            Text::new(month.format("%B %Y").to_string())
+                .size(16)
+                .width(Length::Units(180))
+                .horizontal_alignment(HorizontalAlignment::Center),
+        )
+        .push(
+            Button::new(next_month_button, Text::new(">").size(14))
+                .on_press(DocMessage::ShowMonth(calendar::add_months(month, 1)))
+                .padding(5)
+                .style(style::Button::Refresh),
+        );
+    let header = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"].iter().fold(
+        Row::new().spacing(5),
+        |row, weekday| {
+            row.push(
+                Text::new(*weekday)
+                    .size(14)
+                    .width(cell)
+                    .horizontal_alignment(HorizontalAlignment::Center)
+                    .color([0.5, 0.5, 0.5]),
+            )
+        },
+    );
+    let day = |state, day: NaiveDate| {
+        Button::new(
+            state,
+            Text::new(day.day().to_string())
+                .size(14)
+                .horizontal_alignment(HorizontalAlignment::Center),
+        )
+        .width(cell)
+        .on_press(DocMessage::DatePicked(day))
+        .style(style::Button::Filter {
+            selected: typed == Some(day),
+        })
+    };
+    // A month spans six weeks at most.
+    day_buttons.resize_with(6 * 7, Default::default);
+    calendar::weeks(month)
+        .iter()
+        .zip(day_buttons.chunks_mut(7))
+        .fold(
+            Column::new().spacing(5).push(navigation).push(header),
+            |column, (week, buttons)| {
+                column.push(week.iter().zip(buttons.iter_mut()).fold(
+                    Row::new().spacing(5),
+                    |row, (date, state)| match date {
+                        Some(date) => row.push(day(state, *date)),
+                        None => row.push(Space::with_width(cell)),
+                    },
+                ))
+            },
+        )
+}
+
 // Persistence
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct SavedState {
     target_dir: String,
+    #[serde(default)]
+    import_profile: ImportProfile,
+    #[serde(default)]
+    packet_criteria: PacketCriteria,
+    #[serde(default)]
+    preferences: Preferences,
+    /// Whether indexing was running or paused when the app was closed.
+    #[serde(default)]
+    index_status: index::Status,
+    /// Where the document list of each cabinet was left, by cabinet folder.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    sessions: BTreeMap<String, Session>,
+}
+
+/// Where the document list of a cabinet was left, restored when it is opened again.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Session {
+    #[serde(default)]
+    filter: Filter,
+    #[serde(default)]
+    query: String,
+    /// Name of the owner the list was narrowed to.
+    #[serde(default)]
+    owner: Option<String>,
+    /// Pixels the list was scrolled down.
+    #[serde(default)]
+    scroll: f32,
+    /// Filenames of the checked documents.
+    #[serde(default)]
+    selected: Vec<String>,
+    /// Filename of the previewed document.
+    #[serde(default)]
+    previewed: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -843,11 +10943,17 @@ enum LoadError {
 }
 
 #[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
 enum SaveError {
     DirectoryError,
     FileError,
     WriteError,
-    FormatError,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+lazy_static! {
+    /// The number of the save last written, see `SavedState::save`.
+    static ref WRITTEN: async_std::sync::Mutex<u64> = async_std::sync::Mutex::new(0);
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -858,7 +10964,7 @@ impl SavedState {
         {
             project_dirs.data_dir().into()
         } else {
-            std::env::current_dir().unwrap_or(std::path::PathBuf::new())
+            std::env::current_dir().unwrap_or_default()
         };
 
         path.push("filecabinet.json");
@@ -866,6 +10972,14 @@ impl SavedState {
         path
     }
 
+    /// The saved preferences, for what has to be set up before the app runs.
+    fn preferences() -> Option<Preferences> {
+        let contents = fs::read_to_string(Self::path()).ok()?;
+        serde_json::from_str::<SavedState>(&contents)
+            .ok()
+            .map(|saved| saved.preferences)
+    }
+
     async fn load() -> Result<SavedState, LoadError> {
         let mut contents = String::new();
 
@@ -880,8 +10994,22 @@ impl SavedState {
         serde_json::from_str(&contents).map_err(|_| LoadError::FormatError)
     }
 
-    async fn save(self) -> Result<(), SaveError> {
-        let json = serde_json::to_string_pretty(&self).map_err(|_| SaveError::FormatError)?;
+    /// Writes the state right away, for the panic hook.
+    fn flush(json: &str) -> Result<(), String> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    /// Writes `json` as the `save`th save. Saves may finish out of order, one older than
+    /// the save last written is dropped.
+    async fn save(json: String, save: u64) -> Result<(), SaveError> {
+        let mut written = WRITTEN.lock().await;
+        if *written > save {
+            return Ok(());
+        }
 
         let path = Self::path();
 
@@ -900,9 +11028,7 @@ impl SavedState {
                 .await
                 .map_err(|_| SaveError::WriteError)?;
         }
-
-        // This is a simple way to save at most once every couple seconds
-        async_std::task::sleep(std::time::Duration::from_secs(2)).await;
+        *written = save;
 
         Ok(())
     }
@@ -927,17 +11053,15 @@ impl SavedState {
         serde_json::from_str(&contents).map_err(|_| LoadError::FormatError)
     }
 
-    async fn save(self) -> Result<(), SaveError> {
+    /// Writes `json`. Browsers run each save to its end before the next, so they are
+    /// written in order.
+    async fn save(json: String, _save: u64) -> Result<(), SaveError> {
         let storage = Self::storage().ok_or(SaveError::FileError)?;
 
-        let json = serde_json::to_string_pretty(&self).map_err(|_| SaveError::FormatError)?;
-
         storage
             .set_item("state", &json)
             .map_err(|_| SaveError::WriteError)?;
 
-        let _ = wasm_timer::Delay::new(std::time::Duration::from_secs(2)).await;
-
         Ok(())
     }
 }
@@ -948,6 +11072,40 @@ mod style {
 
     pub struct Pane {}
 
+    pub struct TitleBar {}
+
+    /// A count asking for attention, e.g. of the documents left to name.
+    pub struct Badge;
+
+    impl container::StyleSheet for Badge {
+        fn style(&self) -> container::Style {
+            container::Style {
+                text_color: Some(Color::WHITE),
+                background: Some(Background::Color(Color::from_rgb(
+                    0xef as f32 / 255.0,
+                    0x47 as f32 / 255.0,
+                    0x6f as f32 / 255.0,
+                ))),
+                border_radius: 8.0,
+                ..Default::default()
+            }
+        }
+    }
+
+    impl container::StyleSheet for TitleBar {
+        fn style(&self) -> container::Style {
+            container::Style {
+                background: Some(Background::Color(Color::from_rgb(
+                    0xe8 as f32 / 255.0,
+                    0xd8 as f32 / 255.0,
+                    0xd5 as f32 / 255.0,
+                ))),
+                border_radius: 5.0,
+                ..Default::default()
+            }
+        }
+    }
+
     impl container::StyleSheet for Pane {
         fn style(&self) -> container::Style {
             container::Style {
@@ -965,13 +11123,20 @@ mod style {
     }
 
     pub enum Button {
-        Filter { selected: bool },
+        Filter {
+            selected: bool,
+        },
         Icon,
         Destructive,
         Update,
         Cancel,
         Doc,
         Refresh,
+        /// A swatch of a color label, an outline without one.
+        Label {
+            color: Option<[f32; 3]>,
+            selected: bool,
+        },
     }
 
     impl button::StyleSheet for Button {
@@ -1041,6 +11206,26 @@ mod style {
                     shadow_offset: Vector::new(1.0, 1.0),
                     ..button::Style::default()
                 },
+                Button::Label { color, selected } => button::Style {
+                    background: color.map(|color| Background::Color(Color::from(color))),
+                    border_radius: 4.0,
+                    border_width: if *selected || color.is_none() {
+                        2.0
+                    } else {
+                        0.0
+                    },
+                    border_color: if *selected {
+                        Color::from_rgb(0.2, 0.2, 0.7)
+                    } else {
+                        Color::from_rgb(0.8, 0.8, 0.8)
+                    },
+                    text_color: if color.is_some() {
+                        Color::WHITE
+                    } else {
+                        Color::from_rgb(0.5, 0.5, 0.5)
+                    },
+                    ..button::Style::default()
+                },
                 Button::Cancel => button::Style {
                     background: Some(Background::Color(Color::from_rgb(
                         0xff as f32 / 255.0,
@@ -0,0 +1,174 @@
+use crate::catalog::Catalog;
+use crate::scratch::WorkDir;
+use crate::{office, pdf, search, utils};
+use std::path::Path;
+use std::process::Command;
+use tracing::{info, warn};
+use whatlang::Lang;
+
+/// Text read from a document and the language it is written in, if it could be told.
+#[derive(Debug, Clone)]
+pub struct Recognized {
+    pub text: String,
+    /// The text page by page, a single one for images.
+    pub pages: Vec<String>,
+    /// ISO 639-3 code, e.g. `eng`.
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum OcrError {
+    TextLayerError,
+    ImageError,
+    TesseractError,
+    PdfError,
+    WriteError,
+}
+
+/// Returns the text of a document: the text layer for PDFs, and for scans whatever
+/// `tesseract` recognizes if it is installed. `None` if nothing could be read.
+///
+/// Scans are first read with all of the installed `languages`, then read again with
+/// only the model of the language that was detected, which is more accurate.
+pub fn document_text(path: &Path, languages: &[String]) -> Option<Recognized> {
+    let pages = match utils::extension(path).as_str() {
+        "pdf" => pdf_pages(path),
+        "jpg" | "jpeg" | "png" => {
+            let text = tesseract(path, &languages.join("+"))?;
+            match detect_language(&text) {
+                Some(language) if languages.len() > 1 && languages.contains(&language) => {
+                    tesseract(path, &language).or(Some(text))
+                }
+                _ => Some(text),
+            }
+            .map(|text| vec![text])
+        }
+        "docx" | "odt" => office::text(path).map(|text| vec![text]),
+        _ => None,
+    }?;
+    let text = pages.join("\n");
+    if text.trim().is_empty() {
+        None
+    } else {
+        Some(Recognized {
+            language: detect_language(&text),
+            text,
+            pages,
+        })
+    }
+}
+
+/// Detects the language of `text` as the name of its Tesseract model.
+pub fn detect_language(text: &str) -> Option<String> {
+    let info = whatlang::detect(text)?;
+    if !info.is_reliable() {
+        return None;
+    }
+    Some(tesseract_model(info.lang()))
+}
+
+/// Tesseract names its models by ISO 639-3 code except for a few languages.
+fn tesseract_model(lang: Lang) -> String {
+    match lang {
+        Lang::Cmn => "chi_sim".to_string(),
+        lang => lang.code().to_string(),
+    }
+}
+
+/// Re-exports a scanned, image-only PDF as PDF/A with an invisible text layer from
+/// `tesseract`, replacing the file in the cabinet. Returns the path of the file.
+pub async fn make_searchable(path: String, languages: Vec<String>) -> Result<String, OcrError> {
+    let source = Path::new(&path);
+    if pdf_pages(source).is_some_and(|pages| pages.iter().any(|page| !page.trim().is_empty())) {
+        return Err(OcrError::TextLayerError);
+    }
+    // Use the model of the language found while indexing when it is installed.
+    let language = source
+        .parent()
+        .zip(source.file_name().and_then(|f| f.to_str()))
+        .and_then(|(dir, filename)| Catalog::load(dir).get(filename)?.language.clone())
+        .filter(|language| languages.contains(language))
+        .unwrap_or_else(|| languages.join("+"));
+
+    let work_dir = WorkDir::new("ocr").map_err(|_| OcrError::WriteError)?;
+    let images =
+        pdf::extract_page_images(source, work_dir.path()).map_err(|_| OcrError::ImageError)?;
+    let mut pages = Vec::new();
+    for image in images {
+        // tesseract appends the extension of the output format itself.
+        let base = image.path.with_extension("");
+        let mut command = Command::new("tesseract");
+        command
+            .arg(&image.path)
+            .arg(&base)
+            .arg("--dpi")
+            .arg(image.dpi.to_string());
+        if !language.is_empty() {
+            command.arg("-l").arg(&language);
+        }
+        let status = command
+            .arg("pdf")
+            .status()
+            .map_err(|_| OcrError::TesseractError)?;
+        if !status.success() {
+            return Err(OcrError::TesseractError);
+        }
+        pages.push(base.with_extension("pdf"));
+    }
+
+    let mut document = pdf::concat(&pages).map_err(|_| OcrError::PdfError)?;
+    let title = source
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    pdf::mark_pdfa(&mut document, &title).map_err(|_| OcrError::PdfError)?;
+    pdf::save(&mut document, source).map_err(|_| OcrError::WriteError)?;
+
+    // The text layer is new, so let the next index pass read it.
+    if let (Some(dir), Some(filename)) = (source.parent(), source.file_name()) {
+        let mut catalog = Catalog::load(dir);
+        let entry = catalog.entry(&filename.to_string_lossy());
+        entry.indexed = false;
+        entry.sha256 = None;
+        if let Err(error) = catalog.save(dir) {
+            warn!(event = "catalog_save_failed", ?error);
+        }
+        search::forget_text(source);
+    }
+    info!(event = "MakeSearchable", file = %path, language = %language);
+    Ok(path)
+}
+
+fn pdf_pages(path: &Path) -> Option<Vec<String>> {
+    let document = lopdf::Document::load(path).ok()?;
+    document
+        .get_pages()
+        .keys()
+        .map(|&page| document.extract_text(&[page]).ok())
+        .collect()
+}
+
+fn tesseract(path: &Path, languages: &str) -> Option<String> {
+    let mut command = Command::new("tesseract");
+    command.arg(path).arg("stdout");
+    if !languages.is_empty() {
+        command.arg("-l").arg(languages);
+    }
+    let output = command.output().ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_detect_language() {
+    let english = "The quarterly statement lists every transaction made on the account \
+                   together with the remaining balance and the interest that was paid.";
+    let german = "Die Rechnung enthält alle Leistungen des vergangenen Monats sowie den \
+                  Betrag, der bis zum Ende des Monats zu überweisen ist.";
+    assert_eq!(detect_language(english).as_deref(), Some("eng"));
+    assert_eq!(detect_language(german).as_deref(), Some("deu"));
+}
@@ -0,0 +1,197 @@
+use crate::catalog::{Catalog, CatalogError};
+use crate::{preview, search, versions};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Cached previews older than this are dropped by a cleanup unless a document of the
+/// cabinet shows them, see `preview::stale_previews`.
+pub const PREVIEW_MAX_AGE: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+
+/// Metadata left behind by documents that are gone from a cabinet.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Orphans {
+    /// Entries moved to a copy of their document found under another name, `(old, new)`.
+    pub relinked: Vec<(String, String)>,
+    /// Catalog entries of documents that are gone, archived ones aside.
+    pub entries: Vec<String>,
+    /// Texts read from documents that are gone.
+    pub texts: Vec<PathBuf>,
+    /// Folders of earlier versions of documents that are gone.
+    pub versions: Vec<PathBuf>,
+    pub previews: Vec<PathBuf>,
+    /// Bytes the removed files take, and the entries in the catalog.
+    pub bytes: u64,
+}
+
+impl Orphans {
+    pub fn is_empty(&self) -> bool {
+        self.relinked.is_empty()
+            && self.entries.is_empty()
+            && self.texts.is_empty()
+            && self.versions.is_empty()
+            && self.previews.is_empty()
+    }
+}
+
+impl std::fmt::Display for Orphans {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} relinked, {} entries, {} texts, {} version folders and {} previews, {}",
+            self.relinked.len(),
+            self.entries.len(),
+            self.texts.len(),
+            self.versions.len(),
+            self.previews.len(),
+            crate::stats::size(self.bytes)
+        )
+    }
+}
+
+/// Bytes taken by the file or folder at `path`.
+pub fn size(path: &Path) -> u64 {
+    match fs::read_dir(path) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| size(&entry.path()))
+            .sum(),
+        Err(_) => fs::metadata(path).map_or(0, |metadata| metadata.len()),
+    }
+}
+
+/// Files in `folder` belonging to a document that isn't `kept`, by their name without
+/// `suffix`.
+fn orphaned(folder: &Path, suffix: &str, kept: &HashSet<String>) -> Vec<PathBuf> {
+    let mut orphaned: Vec<PathBuf> = fs::read_dir(folder)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    orphaned.retain(|path| {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        name.strip_suffix(suffix)
+            .is_some_and(|filename| !kept.contains(filename))
+    });
+    orphaned.sort();
+    orphaned
+}
+
+/// Relinks the entries of documents that were moved within the cabinet at `dir`, with
+/// their texts and versions, then removes the metadata of the documents that are gone,
+/// and cached previews over `preview_max_age` if given. Only reports what it would do
+/// when `dry_run`.
+pub fn clean(
+    dir: &Path,
+    preview_max_age: Option<Duration>,
+    dry_run: bool,
+) -> Result<Orphans, CatalogError> {
+    let mut catalog = Catalog::load(dir);
+    let relinked = catalog.relink_missing(dir);
+    if !dry_run {
+        for (old, new) in &relinked {
+            search::rename_text(&dir.join(old), &dir.join(new));
+            versions::rename_versions(&dir.join(old), &dir.join(new));
+        }
+    }
+    let entries = catalog.missing(dir);
+    let mut bytes: u64 = entries
+        .iter()
+        .filter_map(|filename| serde_json::to_string(catalog.get(filename)?).ok())
+        .map(|json| json.len() as u64)
+        .sum();
+    for filename in &entries {
+        catalog.remove(filename);
+    }
+
+    // Archived documents keep their text for the search.
+    let mut kept: HashSet<String> = catalog.entries.keys().cloned().collect();
+    kept.extend(crate::utils::list_files(dir));
+    kept.extend(relinked.iter().map(|(old, _)| old.clone()));
+    let texts = orphaned(&search::text_dir(dir), ".json", &kept);
+    let versions = orphaned(&versions::root(dir), "", &kept);
+    let previews = preview_max_age
+        .map(|max_age| preview::stale_previews(dir, max_age))
+        .unwrap_or_default();
+    bytes += texts
+        .iter()
+        .chain(&versions)
+        .chain(&previews)
+        .map(|path| size(path))
+        .sum::<u64>();
+
+    let orphans = Orphans {
+        relinked,
+        entries,
+        texts,
+        versions,
+        previews,
+        bytes,
+    };
+    if dry_run {
+        return Ok(orphans);
+    }
+    catalog.save(dir)?;
+    for path in orphans.texts.iter().chain(&orphans.previews) {
+        if let Err(error) = fs::remove_file(path) {
+            warn!(event = "orphan_remove_failed", file = %path.display(), ?error);
+        }
+    }
+    for path in &orphans.versions {
+        if let Err(error) = fs::remove_dir_all(path) {
+            warn!(event = "orphan_remove_failed", file = %path.display(), ?error);
+        }
+    }
+    info!(
+        event = "CleanOrphans",
+        dir = %dir.display(),
+        relinked = orphans.relinked.len(),
+        entries = orphans.entries.len(),
+        texts = orphans.texts.len(),
+        versions = orphans.versions.len(),
+        previews = orphans.previews.len(),
+        bytes = orphans.bytes
+    );
+    Ok(orphans)
+}
+
+#[test]
+fn test_clean() {
+    let dir = tempdir::TempDir::new("orphans").unwrap();
+    let dir = dir.path();
+    fs::write(dir.join("kept.pdf"), b"kept").unwrap();
+    fs::write(dir.join("moved.pdf"), b"moved").unwrap();
+    let mut catalog = Catalog::default();
+    catalog.entry("kept.pdf").indexed = true;
+    catalog.entry("old.pdf").sha256 = crate::utils::sha256(&dir.join("moved.pdf"));
+    catalog.entry("gone.pdf").indexed = true;
+    catalog.entry("archived.pdf").archived = Some("/archive/archived.pdf".to_string());
+    catalog.save(dir).unwrap();
+    for filename in ["kept.pdf", "old.pdf", "gone.pdf", "archived.pdf"] {
+        search::save_text(&dir.join(filename), &[]).unwrap();
+    }
+    fs::create_dir_all(versions::root(dir).join("gone.pdf")).unwrap();
+    fs::write(versions::root(dir).join("gone.pdf/2021.pdf"), b"version").unwrap();
+
+    let planned = clean(dir, None, true).unwrap();
+    assert_eq!(
+        planned.relinked,
+        [("old.pdf".to_string(), "moved.pdf".to_string())]
+    );
+    assert_eq!(planned.entries, ["gone.pdf"]);
+    assert_eq!(planned.texts, [search::text_dir(dir).join("gone.pdf.json")]);
+    assert_eq!(planned.versions, [versions::root(dir).join("gone.pdf")]);
+    assert!(planned.bytes >= 7);
+    assert!(Catalog::load(dir).get("gone.pdf").is_some());
+
+    let cleaned = clean(dir, None, false).unwrap();
+    assert_eq!(cleaned, planned);
+    let catalog = Catalog::load(dir);
+    assert!(catalog.get("gone.pdf").is_none());
+    assert!(catalog.get("moved.pdf").is_some());
+    assert!(search::has_text(&dir.join("moved.pdf")));
+    assert!(search::has_text(&dir.join("archived.pdf")));
+    assert!(!search::has_text(&dir.join("gone.pdf")));
+    assert!(!versions::root(dir).join("gone.pdf").exists());
+    assert!(clean(dir, None, false).unwrap().entries.is_empty());
+}
@@ -0,0 +1,243 @@
+use crate::catalog::{self, Catalog};
+use crate::recipients::{self, Recipient};
+use crate::utils::{self, OptDoc};
+use crate::{fields, pdf};
+use chrono::{Datelike, NaiveDate};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Which documents are proposed for the year-end packet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PacketCriteria {
+    pub year: String,
+    /// Month (1-12) the fiscal year starts in.
+    pub start_month: String,
+    /// Comma separated words, a document is proposed if its institution or title contains one.
+    pub keywords: String,
+}
+
+impl Default for PacketCriteria {
+    fn default() -> Self {
+        PacketCriteria {
+            year: String::new(),
+            start_month: "1".to_string(),
+            keywords: "tax".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum PacketMessage {
+    YearEdited(String),
+    StartMonthEdited(String),
+    KeywordsEdited(String),
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum PacketError {
+    CriteriaError,
+    DirectoryError,
+    PdfError,
+    WriteError,
+    EncryptError,
+}
+
+/// A document in the fiscal year and whether it goes into the packet.
+#[derive(Debug, Clone)]
+pub struct PacketItem {
+    pub path: PathBuf,
+    pub included: bool,
+}
+
+impl PacketCriteria {
+    pub fn update(&mut self, message: PacketMessage) {
+        match message {
+            PacketMessage::YearEdited(s) => self.year = s,
+            PacketMessage::StartMonthEdited(s) => self.start_month = s,
+            PacketMessage::KeywordsEdited(s) => self.keywords = s,
+        }
+    }
+
+    fn fiscal_year(&self) -> Option<(i32, u32)> {
+        let year = self.year.trim().parse().ok()?;
+        let start_month = self.start_month.trim().parse().ok()?;
+        if (1..=12).contains(&start_month) {
+            Some((year, start_month))
+        } else {
+            None
+        }
+    }
+
+    fn matches_keywords(&self, doc: &OptDoc) -> bool {
+        let keywords: Vec<String> = self
+            .keywords
+            .split(',')
+            .map(|k| k.trim().to_lowercase())
+            .filter(|k| !k.is_empty())
+            .collect();
+        if keywords.is_empty() {
+            return true;
+        }
+        let haystack = format!(
+            "{} {}",
+            doc.institution.as_deref().unwrap_or(""),
+            doc.name.as_deref().unwrap_or("")
+        )
+        .to_lowercase();
+        keywords.iter().any(|k| haystack.contains(k.as_str()))
+    }
+
+    /// Lists every document dated within the fiscal year, pre-selecting the ones matching the keywords.
+    pub fn review_list(&self, dir: &Path) -> Vec<PacketItem> {
+        let (year, start_month) = match self.fiscal_year() {
+            Some(fiscal_year) => fiscal_year,
+            None => return Vec::new(),
+        };
+        let mut items: Vec<(NaiveDate, PacketItem)> = utils::list_files(dir)
+            .into_iter()
+            .filter_map(|filename| {
+                let path = dir.join(filename);
+                let doc = OptDoc::new(&path);
+                let date = doc.day()?;
+                if !in_fiscal_year(date, year, start_month) {
+                    return None;
+                }
+                let included = self.matches_keywords(&doc);
+                Some((date, PacketItem { path, included }))
+            })
+            .collect();
+        items.sort_by(|(a, a_item), (b, b_item)| a.cmp(b).then(a_item.path.cmp(&b_item.path)));
+        items.into_iter().map(|(_, item)| item).collect()
+    }
+}
+
+/// Fiscal year `year` runs from the first of `start_month` in `year` up to the same day a year later.
+pub fn in_fiscal_year(date: NaiveDate, year: i32, start_month: u32) -> bool {
+    if date.month() >= start_month {
+        date.year() == year
+    } else {
+        date.year() == year + 1
+    }
+}
+
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Writes `TaxPacket_<year>.pdf` (merged, one bookmark per document) and a CSV index
+/// next to it in `<dir>/packets`, returning the path of the PDF. Both are also encrypted
+/// to the `recipients` if there are any, the path of the encrypted PDFs is returned then.
+pub async fn export(
+    dir: String,
+    year: String,
+    paths: Vec<String>,
+    recipients: Vec<Recipient>,
+) -> Result<String, PacketError> {
+    // Named after the year, so it mustn't take the packets out of their folder.
+    let year: u32 = year
+        .trim()
+        .parse()
+        .map_err(|_| PacketError::CriteriaError)?;
+    let out_dir = Path::new(&dir).join("packets");
+    fs::create_dir_all(&out_dir).map_err(|_| PacketError::DirectoryError)?;
+
+    let parts: Vec<(PathBuf, String)> = paths
+        .iter()
+        .map(|path| {
+            let doc = OptDoc::new(path);
+            let title = format!(
+                "{} {} {}",
+                doc.date.unwrap_or_default(),
+                doc.institution.unwrap_or_default(),
+                doc.name.unwrap_or_default()
+            );
+            (PathBuf::from(path), title)
+        })
+        .collect();
+    let (mut document, starts) = pdf::merge(&parts).map_err(|_| PacketError::PdfError)?;
+    let pdf_path = out_dir.join(format!("TaxPacket_{}.pdf", year));
+    pdf::save(&mut document, &pdf_path).map_err(|_| PacketError::WriteError)?;
+
+    let catalog = Catalog::load(Path::new(&dir));
+    let defs = fields::load(Path::new(&dir));
+    let header: Vec<String> = [
+        "No",
+        "Date",
+        "Institution",
+        "Title",
+        "Page",
+        "PacketPage",
+        "File",
+    ]
+    .iter()
+    .map(|f| f.to_string())
+    .chain(fields::csv_header(&defs))
+    .map(|f| csv_field(&f))
+    .collect();
+    let mut csv = format!("{}\n", header.join(","));
+    for (i, (path, start)) in paths.iter().zip(starts.iter()).enumerate() {
+        let doc = OptDoc::new(path);
+        let filename = Path::new(path)
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let custom = fields::csv_row(&defs, catalog.get(&filename));
+        let row = [
+            (i + 1).to_string(),
+            doc.date.unwrap_or_default(),
+            doc.institution.unwrap_or_default(),
+            doc.name.unwrap_or_default(),
+            doc.page.unwrap_or_default(),
+            start.map(|p| p.to_string()).unwrap_or_default(),
+            filename,
+        ];
+        let row: Vec<String> = row.iter().chain(&custom).map(|f| csv_field(f)).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    let csv_path = out_dir.join(format!("TaxPacket_{}.csv", year));
+    fs::write(&csv_path, csv).map_err(|_| PacketError::WriteError)?;
+    catalog::record_exports(Path::new(&dir), &paths);
+
+    info!(
+        event = "ExportPacket",
+        pdf = %pdf_path.display(),
+        csv = %csv_path.display(),
+        documents = paths.len()
+    );
+    if recipients.is_empty() {
+        return Ok(pdf_path.to_string_lossy().to_string());
+    }
+    recipients::encrypt(&csv_path, &recipients).map_err(|_| PacketError::EncryptError)?;
+    let encrypted =
+        recipients::encrypt(&pdf_path, &recipients).map_err(|_| PacketError::EncryptError)?;
+    info!(
+        event = "EncryptPacket",
+        pdf = %pdf_path.display(),
+        recipients = recipients.len()
+    );
+    Ok(encrypted
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .join(", "))
+}
+
+#[test]
+fn test_in_fiscal_year() {
+    let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+    assert!(in_fiscal_year(date("2021-01-01"), 2021, 1));
+    assert!(in_fiscal_year(date("2021-12-31"), 2021, 1));
+    assert!(!in_fiscal_year(date("2022-01-01"), 2021, 1));
+    assert!(in_fiscal_year(date("2021-04-06"), 2021, 4));
+    assert!(in_fiscal_year(date("2022-03-31"), 2021, 4));
+    assert!(!in_fiscal_year(date("2021-03-31"), 2021, 4));
+}
@@ -0,0 +1,21 @@
+/// Keyring service the passwords of protected PDFs are stored under, one entry per
+/// institution.
+const SERVICE: &str = "filecabinet";
+
+#[derive(Debug, Clone)]
+pub enum PasswordError {
+    KeyringError,
+}
+
+pub fn get(institution: &str) -> Option<String> {
+    keyring::Entry::new(SERVICE, institution)
+        .ok()?
+        .get_password()
+        .ok()
+}
+
+pub fn store(institution: &str, password: &str) -> Result<(), PasswordError> {
+    keyring::Entry::new(SERVICE, institution)
+        .and_then(|entry| entry.set_password(password))
+        .map_err(|_| PasswordError::KeyringError)
+}
@@ -0,0 +1,183 @@
+use crate::catalog::Entry;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// Fields a parser could read from a filename, missing ones are left to the user.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Parsed {
+    pub date: Option<String>,
+    pub institution: Option<String>,
+    pub name: Option<String>,
+}
+
+/// Reads document fields from filenames that don't follow the cabinet's scheme, e.g. the
+/// names a scanner or a bank gives its files.
+pub trait FilenameParser: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// `None` if the filename isn't one this parser knows.
+    fn parse(&self, filename: &str) -> Option<Parsed>;
+}
+
+/// Pulls documents into the cabinet from somewhere else than the import profile's folder.
+pub trait Importer: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// Pairs every file to import with its normalized path in `target_dir`.
+    fn plan(&self, target_dir: &Path) -> Vec<(PathBuf, PathBuf)>;
+}
+
+/// Runs on every newly indexed document, after the built-in analysis.
+pub trait PostProcessor: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn process(&self, path: &Path, entry: &mut Entry) -> Result<(), PluginError>;
+}
+
+// Only returned by custom post-processors, the built-in plugins don't fail.
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names, dead_code)]
+pub enum PluginError {
+    ReadError,
+    FormatError,
+    WriteError,
+}
+
+/// The parsers, importers and post-processing steps the app was built with. They are tried
+/// in the order they were added.
+#[derive(Default)]
+pub struct Registry {
+    parsers: Vec<Box<dyn FilenameParser>>,
+    importers: Vec<Box<dyn Importer>>,
+    post_processors: Vec<Box<dyn PostProcessor>>,
+}
+
+impl Registry {
+    pub fn with_parser(mut self, parser: impl FilenameParser + 'static) -> Self {
+        self.parsers.push(Box::new(parser));
+        self
+    }
+
+    // The next two are for custom plugins, none of the built-in ones is an importer or a
+    // post-processor.
+    #[allow(dead_code)]
+    pub fn with_importer(mut self, importer: impl Importer + 'static) -> Self {
+        self.importers.push(Box::new(importer));
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_post_processor(mut self, post_processor: impl PostProcessor + 'static) -> Self {
+        self.post_processors.push(Box::new(post_processor));
+        self
+    }
+
+    /// What the first parser that knows `filename` reads from it.
+    pub fn parse(&self, filename: &str) -> Option<Parsed> {
+        self.parsers.iter().find_map(|parser| {
+            let parsed = parser.parse(filename)?;
+            debug!(
+                event = "Parse",
+                plugin = parser.name(),
+                file = filename,
+                ?parsed
+            );
+            Some(parsed)
+        })
+    }
+
+    pub fn importers(&self) -> &[Box<dyn Importer>] {
+        &self.importers
+    }
+
+    /// Runs every post-processor on the document, a failing one doesn't stop the others.
+    pub fn post_process(&self, path: &Path, entry: &mut Entry) {
+        for post_processor in &self.post_processors {
+            match post_processor.process(path, entry) {
+                Ok(()) => info!(
+                    event = "PostProcess",
+                    plugin = post_processor.name(),
+                    file = %path.display()
+                ),
+                Err(error) => warn!(
+                    event = "post_process_failed",
+                    plugin = post_processor.name(),
+                    file = %path.display(),
+                    ?error
+                ),
+            }
+        }
+    }
+}
+
+/// The compiled-in plugins. Custom ones are registered here until plugins can be loaded
+/// at runtime.
+fn builtin() -> Registry {
+    Registry::default().with_parser(TimestampParser)
+}
+
+lazy_static! {
+    static ref REGISTRY: Registry = builtin();
+    static ref RE_TIMESTAMP: Regex =
+        Regex::new(r"(?:^|\D)(?P<year>(?:19|20)\d{2})-?(?P<month>\d{2})-?(?P<day>\d{2})(?:\D|$)")
+            .unwrap();
+}
+
+pub fn registry() -> &'static Registry {
+    &REGISTRY
+}
+
+/// Dates scanners and phones put in their filenames, e.g. `Scan 2021-03-04 10.22.31.pdf`
+/// or `IMG_20210304_102231.jpg`.
+struct TimestampParser;
+
+impl FilenameParser for TimestampParser {
+    fn name(&self) -> &'static str {
+        "timestamp"
+    }
+
+    fn parse(&self, filename: &str) -> Option<Parsed> {
+        let captures = RE_TIMESTAMP.captures(filename)?;
+        let date = format!(
+            "{}-{}-{}",
+            &captures["year"], &captures["month"], &captures["day"]
+        );
+        chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok()?;
+        Some(Parsed {
+            date: Some(date),
+            ..Parsed::default()
+        })
+    }
+}
+
+#[test]
+fn test_parse() {
+    struct BankParser;
+    impl FilenameParser for BankParser {
+        fn name(&self) -> &'static str {
+            "bank"
+        }
+        fn parse(&self, filename: &str) -> Option<Parsed> {
+            filename.strip_prefix("eStmt_").map(|_| Parsed {
+                institution: Some("Chase".to_string()),
+                name: Some("Statement".to_string()),
+                ..Parsed::default()
+            })
+        }
+    }
+    let registry = Registry::default()
+        .with_parser(BankParser)
+        .with_parser(TimestampParser);
+    assert_eq!(
+        registry.parse("eStmt_2021-03-04.pdf").unwrap().institution,
+        Some("Chase".to_string())
+    );
+    assert_eq!(
+        registry.parse("IMG_20210304_102231.jpg").unwrap().date,
+        Some("2021-03-04".to_string())
+    );
+    assert_eq!(
+        registry.parse("Scan 2021-03-04 10.22.31.pdf").unwrap().date,
+        Some("2021-03-04".to_string())
+    );
+    assert!(registry.parse("IMG_20211304_102231.jpg").is_none());
+    assert!(registry.parse("invoice.pdf").is_none());
+}
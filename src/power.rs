@@ -0,0 +1,83 @@
+use std::env;
+#[cfg(target_os = "linux")]
+use std::fs;
+#[cfg(target_os = "linux")]
+use std::path::Path;
+use tracing::info;
+
+/// Where Linux lists the chargers and batteries.
+#[cfg(target_os = "linux")]
+const POWER_SUPPLIES: &str = "/sys/class/power_supply";
+
+/// Whether the machine runs on its battery. Machines without one, and systems this can't
+/// be told on, count as plugged in.
+#[cfg(target_os = "linux")]
+pub fn on_battery() -> bool {
+    discharging(Path::new(POWER_SUPPLIES))
+}
+
+#[cfg(target_os = "macos")]
+pub fn on_battery() -> bool {
+    std::process::Command::new("pmset")
+        .args(["-g", "batt"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains("'Battery Power'"))
+        .unwrap_or(false)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn on_battery() -> bool {
+    false
+}
+
+/// Whether the power supplies listed in `dir`, laid out like `/sys/class/power_supply`,
+/// run on battery: none of the chargers is online and a battery discharges.
+#[cfg(target_os = "linux")]
+fn discharging(dir: &Path) -> bool {
+    let read = |supply: &Path, name: &str| {
+        fs::read_to_string(supply.join(name))
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+    };
+    let mut discharging = false;
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let supply = entry.path();
+        match read(&supply, "type").as_str() {
+            "Mains" | "USB" if read(&supply, "online") == "1" => return false,
+            "Battery" => discharging |= read(&supply, "status") == "Discharging",
+            _ => {}
+        }
+    }
+    discharging
+}
+
+/// Limits the threads the app runs its work on to `threads`, unless the environment
+/// says otherwise. The executor reads it when it starts, so this is called before the
+/// app runs and a change applies from its next launch. Tesseract gets the same limit.
+pub fn limit_threads(threads: usize) {
+    for variable in ["ASYNC_STD_THREAD_COUNT", "OMP_THREAD_LIMIT"] {
+        if env::var_os(variable).is_none() {
+            env::set_var(variable, threads.to_string());
+        }
+    }
+    info!(event = "LimitThreads", threads);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_discharging() {
+    let dir = tempdir::TempDir::new("power").unwrap();
+    let supply = |name: &str, values: &[(&str, &str)]| {
+        let supply = dir.path().join(name);
+        fs::create_dir_all(&supply).unwrap();
+        for (file, value) in values {
+            fs::write(supply.join(file), format!("{}\n", value)).unwrap();
+        }
+    };
+    assert!(!discharging(dir.path()));
+    supply("BAT0", &[("type", "Battery"), ("status", "Discharging")]);
+    supply("AC", &[("type", "Mains"), ("online", "0")]);
+    assert!(discharging(dir.path()));
+    supply("AC", &[("type", "Mains"), ("online", "1")]);
+    assert!(!discharging(dir.path()));
+}
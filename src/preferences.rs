@@ -0,0 +1,380 @@
+use crate::hooks::Hook;
+use crate::recipients::Recipient;
+use crate::similarity;
+use crate::tools::ExternalTool;
+use iced::pane_grid::Axis;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Application wide settings edited in the settings pane.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preferences {
+    /// Plus separated Tesseract models that are installed, e.g. `eng+deu`.
+    pub ocr_languages: String,
+    #[serde(default)]
+    pub preview_layout: PreviewLayout,
+    /// Store the normalized date, institution and title in the XMP metadata of PDFs.
+    #[serde(default)]
+    pub write_pdf_metadata: bool,
+    /// Programs offered in the "Open with" menu of documents.
+    #[serde(default)]
+    pub external_tools: Vec<ExternalTool>,
+    /// Where the backup pane writes snapshots of the cabinet.
+    #[serde(default)]
+    pub backup_dir: String,
+    /// Where archived documents are moved, e.g. a folder on an external drive.
+    #[serde(default)]
+    pub archive_dir: String,
+    /// `ftp://`, `sftp://` or `smb://` folder a network scanner drops its scans in.
+    #[serde(default)]
+    pub drop_folder: String,
+    /// Delete scans from the drop folder once they are in the cabinet.
+    #[serde(default)]
+    pub delete_pulled_scans: bool,
+    /// Serve the page phones share documents into the intake folder with, see `share`.
+    #[serde(default)]
+    pub share_enabled: bool,
+    #[serde(default = "default_share_port")]
+    pub share_port: u16,
+    /// Secret part of the link phones open.
+    #[serde(default)]
+    pub share_token: String,
+    /// Public keys exports can be encrypted to.
+    #[serde(default)]
+    pub recipients: Vec<Recipient>,
+    /// Minutes without input after which a cabinet with a vault is locked, 0 for never.
+    #[serde(default = "default_auto_lock_minutes")]
+    pub auto_lock_minutes: u32,
+    /// Drop the blank pages of imported PDFs, e.g. the backs of a duplex scan.
+    #[serde(default)]
+    pub drop_blank_pages: bool,
+    #[serde(default)]
+    pub blank_sensitivity: BlankSensitivity,
+    /// Show an icon in the system tray to add documents and hide the window with.
+    #[serde(default)]
+    pub tray: bool,
+    /// Commands run before imports and after renames and deletes, see `hooks`.
+    #[serde(default)]
+    pub hooks: Vec<Hook>,
+    /// Append changes to documents to `.filecabinet/events.jsonl`, see `events`.
+    #[serde(default)]
+    pub event_stream: bool,
+    /// Text stamped on the pages of bundled copies, see `bundle::stamp_text`.
+    #[serde(default = "default_stamp_template")]
+    pub stamp_template: String,
+    /// Actions done without asking, the user told them not to ask again.
+    #[serde(default)]
+    pub unconfirmed: BTreeSet<Confirmation>,
+    /// Days deleted documents stay in the trash of a cabinet, 0 until it is emptied.
+    #[serde(default = "default_trash_retention_days")]
+    pub trash_retention_days: u32,
+    /// Threads the app works on, 0 for one per core. Read when the app starts.
+    #[serde(default)]
+    pub worker_threads: u32,
+    /// Pause indexing while the machine runs on its battery, see `power::on_battery`.
+    #[serde(default)]
+    pub low_power: bool,
+}
+
+fn default_auto_lock_minutes() -> u32 {
+    15
+}
+
+fn default_trash_retention_days() -> u32 {
+    30
+}
+
+fn default_stamp_template() -> String {
+    "Copy provided to {recipient} on {date}".to_string()
+}
+
+fn default_share_port() -> u16 {
+    8737
+}
+
+fn new_share_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(20)
+        .collect()
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Preferences {
+            ocr_languages: "eng".to_string(),
+            preview_layout: Default::default(),
+            write_pdf_metadata: false,
+            external_tools: Vec::new(),
+            backup_dir: String::new(),
+            archive_dir: String::new(),
+            drop_folder: String::new(),
+            delete_pulled_scans: false,
+            share_enabled: false,
+            share_port: default_share_port(),
+            share_token: String::new(),
+            recipients: Vec::new(),
+            auto_lock_minutes: default_auto_lock_minutes(),
+            drop_blank_pages: false,
+            blank_sensitivity: Default::default(),
+            tray: false,
+            hooks: Vec::new(),
+            event_stream: false,
+            stamp_template: default_stamp_template(),
+            unconfirmed: BTreeSet::new(),
+            trash_retention_days: default_trash_retention_days(),
+            worker_threads: 0,
+            low_power: false,
+        }
+    }
+}
+
+/// Where the preview opens relative to the document list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PreviewLayout {
+    /// Next to the list, for wide screens.
+    #[default]
+    SideBySide,
+    /// Below the list, for laptops.
+    Stacked,
+}
+
+/// How readily scanned pages are taken for blank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BlankSensitivity {
+    /// Only pages with hardly a speck on them, for documents with sparse pages.
+    Low,
+    #[default]
+    Medium,
+    /// Also pages with some show-through or a stamp, for thin paper.
+    High,
+}
+
+impl BlankSensitivity {
+    pub const ALL: [BlankSensitivity; 3] = [
+        BlankSensitivity::Low,
+        BlankSensitivity::Medium,
+        BlankSensitivity::High,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BlankSensitivity::Low => "Low",
+            BlankSensitivity::Medium => "Medium",
+            BlankSensitivity::High => "High",
+        }
+    }
+
+    /// The share of a scan that may be inked for it to count as blank.
+    pub fn max_ink(self) -> f32 {
+        match self {
+            BlankSensitivity::Low => similarity::BLANK_INK / 4.0,
+            BlankSensitivity::Medium => similarity::BLANK_INK,
+            BlankSensitivity::High => similarity::BLANK_INK * 4.0,
+        }
+    }
+}
+
+/// Actions that can't be undone, which ask before they are done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Confirmation {
+    Delete,
+    /// Renames of several documents at once, e.g. by a sync.
+    BulkRename,
+    /// Writes over a document, e.g. when its pages are saved.
+    Overwrite,
+    /// Keeps a copy of an encrypted PDF without its password.
+    Decrypt,
+}
+
+impl Confirmation {
+    pub const ALL: [Confirmation; 4] = [
+        Confirmation::Delete,
+        Confirmation::BulkRename,
+        Confirmation::Overwrite,
+        Confirmation::Decrypt,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Confirmation::Delete => "Deleting a document",
+            Confirmation::BulkRename => "Renaming documents in bulk",
+            Confirmation::Overwrite => "Overwriting a document",
+            Confirmation::Decrypt => "Keeping a decrypted copy",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PreferencesMessage {
+    OcrLanguagesEdited(String),
+    PreviewLayoutChanged(PreviewLayout),
+    WritePdfMetadataToggled(bool),
+    AddTool,
+    RemoveTool(usize),
+    ToolNameEdited(usize, String),
+    ToolExtensionsEdited(usize, String),
+    ToolCommandEdited(usize, String),
+    BackupDirEdited(String),
+    ArchiveDirEdited(String),
+    DropFolderEdited(String),
+    DeletePulledScansToggled(bool),
+    ShareToggled(bool),
+    /// Replaces the link, phones with the old one can't upload anymore.
+    RenewShareToken,
+    AddRecipient,
+    RemoveRecipient(usize),
+    RecipientNameEdited(usize, String),
+    RecipientKeyEdited(usize, String),
+    AutoLockChanged(u32),
+    DropBlankPagesToggled(bool),
+    BlankSensitivityChanged(BlankSensitivity),
+    TrayToggled(bool),
+    AddHook,
+    RemoveHook(usize),
+    /// Moves the hook to the next event.
+    HookEventToggled(usize),
+    HookCommandEdited(usize, String),
+    EventStreamToggled(bool),
+    StampTemplateEdited(String),
+    /// Whether to ask before the action.
+    ConfirmationToggled(Confirmation, bool),
+    TrashRetentionChanged(u32),
+    WorkerThreadsChanged(u32),
+    LowPowerToggled(bool),
+}
+
+impl Preferences {
+    pub fn update(&mut self, message: PreferencesMessage) {
+        match message {
+            PreferencesMessage::OcrLanguagesEdited(s) => self.ocr_languages = s,
+            PreferencesMessage::PreviewLayoutChanged(layout) => self.preview_layout = layout,
+            PreferencesMessage::WritePdfMetadataToggled(write) => self.write_pdf_metadata = write,
+            PreferencesMessage::AddTool => self.external_tools.push(Default::default()),
+            PreferencesMessage::RemoveTool(i) => {
+                if i < self.external_tools.len() {
+                    self.external_tools.remove(i);
+                }
+            }
+            PreferencesMessage::ToolNameEdited(i, s) => {
+                if let Some(tool) = self.external_tools.get_mut(i) {
+                    tool.name = s
+                }
+            }
+            PreferencesMessage::ToolExtensionsEdited(i, s) => {
+                if let Some(tool) = self.external_tools.get_mut(i) {
+                    tool.extensions = s
+                }
+            }
+            PreferencesMessage::ToolCommandEdited(i, s) => {
+                if let Some(tool) = self.external_tools.get_mut(i) {
+                    tool.command = s
+                }
+            }
+            PreferencesMessage::BackupDirEdited(s) => self.backup_dir = s,
+            PreferencesMessage::ArchiveDirEdited(s) => self.archive_dir = s,
+            PreferencesMessage::DropFolderEdited(s) => self.drop_folder = s,
+            PreferencesMessage::DeletePulledScansToggled(delete) => {
+                self.delete_pulled_scans = delete
+            }
+            PreferencesMessage::ShareToggled(enabled) => {
+                self.share_enabled = enabled;
+                if enabled && self.share_token.is_empty() {
+                    self.share_token = new_share_token();
+                }
+            }
+            PreferencesMessage::RenewShareToken => self.share_token = new_share_token(),
+            PreferencesMessage::AddRecipient => self.recipients.push(Default::default()),
+            PreferencesMessage::RemoveRecipient(i) => {
+                if i < self.recipients.len() {
+                    self.recipients.remove(i);
+                }
+            }
+            PreferencesMessage::RecipientNameEdited(i, s) => {
+                if let Some(recipient) = self.recipients.get_mut(i) {
+                    recipient.name = s
+                }
+            }
+            PreferencesMessage::RecipientKeyEdited(i, s) => {
+                if let Some(recipient) = self.recipients.get_mut(i) {
+                    recipient.key = s
+                }
+            }
+            PreferencesMessage::AutoLockChanged(minutes) => self.auto_lock_minutes = minutes,
+            PreferencesMessage::DropBlankPagesToggled(drop) => self.drop_blank_pages = drop,
+            PreferencesMessage::BlankSensitivityChanged(sensitivity) => {
+                self.blank_sensitivity = sensitivity
+            }
+            PreferencesMessage::TrayToggled(tray) => self.tray = tray,
+            PreferencesMessage::EventStreamToggled(enabled) => self.event_stream = enabled,
+            PreferencesMessage::StampTemplateEdited(s) => self.stamp_template = s,
+            PreferencesMessage::TrashRetentionChanged(days) => self.trash_retention_days = days,
+            PreferencesMessage::WorkerThreadsChanged(threads) => self.worker_threads = threads,
+            PreferencesMessage::LowPowerToggled(low_power) => self.low_power = low_power,
+            PreferencesMessage::ConfirmationToggled(confirmation, true) => {
+                self.unconfirmed.remove(&confirmation);
+            }
+            PreferencesMessage::ConfirmationToggled(confirmation, false) => {
+                self.unconfirmed.insert(confirmation);
+            }
+            PreferencesMessage::AddHook => self.hooks.push(Default::default()),
+            PreferencesMessage::RemoveHook(i) => {
+                if i < self.hooks.len() {
+                    self.hooks.remove(i);
+                }
+            }
+            PreferencesMessage::HookEventToggled(i) => {
+                if let Some(hook) = self.hooks.get_mut(i) {
+                    hook.event = hook.event.next()
+                }
+            }
+            PreferencesMessage::HookCommandEdited(i, s) => {
+                if let Some(hook) = self.hooks.get_mut(i) {
+                    hook.command = s
+                }
+            }
+        }
+    }
+
+    /// Whether to ask before `confirmation`'s action.
+    pub fn confirms(&self, confirmation: Confirmation) -> bool {
+        !self.unconfirmed.contains(&confirmation)
+    }
+
+    pub fn preview_axis(&self) -> Axis {
+        match self.preview_layout {
+            PreviewLayout::SideBySide => Axis::Vertical,
+            PreviewLayout::Stacked => Axis::Horizontal,
+        }
+    }
+
+    /// The most ink a page of an imported PDF may have to be dropped, `None` to keep them.
+    pub fn blank_ink(&self) -> Option<f32> {
+        self.drop_blank_pages
+            .then(|| self.blank_sensitivity.max_ink())
+    }
+
+    /// How many threads to limit the app to, `None` for one per core.
+    pub fn worker_threads(&self) -> Option<usize> {
+        (self.worker_threads > 0).then(|| self.worker_threads as usize)
+    }
+
+    pub fn ocr_languages(&self) -> Vec<String> {
+        self.ocr_languages
+            .split(|c: char| c == '+' || c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+#[test]
+fn test_ocr_languages() {
+    let preferences = Preferences {
+        ocr_languages: "eng+deu, fra".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(preferences.ocr_languages(), vec!["eng", "deu", "fra"]);
+}
@@ -0,0 +1,427 @@
+use crate::mapped::MappedPdf;
+use crate::orientation::Orientation;
+use crate::pdf::{self, PdfError};
+use crate::similarity;
+use crate::storage;
+use crate::utils::{self, OptDoc};
+use crate::vault;
+use iced::image::Handle;
+#[cfg(not(target_arch = "wasm32"))]
+use iced_native::image::Data;
+use image::{DynamicImage, GenericImageView};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tracing::warn;
+
+/// Previews are scaled down to fit in this many pixels, larger scans gain nothing on screen.
+pub const MAX_SIDE: u32 = 2000;
+
+/// Side of the page thumbnails of the page editor.
+const THUMBNAIL_SIDE: u32 = 160;
+
+/// Bytes of decoded pixels the preview cache holds at most, a handful of full size scans.
+/// Every handle also takes its texture on the GPU while it is shown.
+const CACHE_BYTES: usize = 128 * 1024 * 1024;
+
+/// Bytes of decoded pixels the pages other than the first hold at most, a few dozen
+/// pages of a long scan.
+const PAGE_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+lazy_static! {
+    /// The PDF last paged through, with its modification time, kept mapped for its next
+    /// page.
+    static ref MAPPED: Mutex<Option<(PathBuf, SystemTime, Arc<MappedPdf>)>> = Mutex::new(None);
+}
+
+/// Decoded previews of the most recently shown documents, most recent first. The panes
+/// show clones of these handles, so a preview is uploaded to the GPU once however often
+/// it is drawn.
+#[derive(Debug)]
+pub struct PreviewCache {
+    capacity: usize,
+    /// Bytes of pixels the previews may take together.
+    budget: usize,
+    entries: VecDeque<(String, Handle)>,
+    /// Pages other than the first by path and number, most recently shown first.
+    pages: VecDeque<((String, usize), Handle)>,
+    /// Paths being decoded, so a document isn't decoded twice at once.
+    loading: HashSet<String>,
+    /// Paths whose preview was decrypted with a password or passphrase.
+    unlocked: HashSet<String>,
+}
+
+impl Default for PreviewCache {
+    fn default() -> Self {
+        PreviewCache {
+            capacity: 16,
+            budget: CACHE_BYTES,
+            entries: VecDeque::new(),
+            pages: VecDeque::new(),
+            loading: HashSet::new(),
+            unlocked: HashSet::new(),
+        }
+    }
+}
+
+impl PreviewCache {
+    pub fn get(&mut self, path: &str) -> Option<Handle> {
+        let i = self.entries.iter().position(|(p, _)| p == path)?;
+        let entry = self.entries.remove(i)?;
+        let handle = entry.1.clone();
+        self.entries.push_front(entry);
+        Some(handle)
+    }
+
+    pub fn insert(&mut self, path: String, handle: Handle) {
+        self.loading.remove(&path);
+        self.entries.retain(|(p, _)| *p != path);
+        self.entries.push_front((path, handle));
+        self.entries.truncate(self.capacity);
+        // The most recent preview stays, however large.
+        while self.entries.len() > 1 && bytes(self.entries.iter().map(|(_, h)| h)) > self.budget {
+            self.entries.pop_back();
+        }
+    }
+
+    /// The preview of page `page` of `path`, if it was shown lately, see `page`.
+    pub fn get_page(&mut self, path: &str, page: usize) -> Option<Handle> {
+        let i = self
+            .pages
+            .iter()
+            .position(|((p, n), _)| p == path && *n == page)?;
+        let entry = self.pages.remove(i)?;
+        let handle = entry.1.clone();
+        self.pages.push_front(entry);
+        Some(handle)
+    }
+
+    /// Caches a page, dropping the least recently shown ones past `PAGE_CACHE_BYTES`.
+    pub fn insert_page(&mut self, path: String, page: usize, handle: Handle) {
+        self.pages.retain(|((p, n), _)| *p != path || *n != page);
+        self.pages.push_front(((path, page), handle));
+        while self.pages.len() > 1 && bytes(self.pages.iter().map(|(_, h)| h)) > PAGE_CACHE_BYTES {
+            self.pages.pop_back();
+        }
+    }
+
+    /// Drops every preview, once no pane shows them anymore.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.pages.clear();
+        self.unlocked.clear();
+    }
+
+    /// Caches the preview of a document that was decrypted to decode it.
+    pub fn insert_unlocked(&mut self, path: String, handle: Handle) {
+        self.unlocked.insert(path.clone());
+        self.insert(path, handle);
+    }
+
+    /// Forgets the previews of decrypted documents, when the app locks.
+    pub fn forget_unlocked(&mut self) {
+        let unlocked = std::mem::take(&mut self.unlocked);
+        self.entries.retain(|(p, _)| !unlocked.contains(p));
+        self.pages.retain(|((p, _), _)| !unlocked.contains(p));
+    }
+
+    /// Marks `path` as being decoded, false if it is cached or already being decoded.
+    pub fn start_loading(&mut self, path: &str) -> bool {
+        !self.entries.iter().any(|(p, _)| p == path) && self.loading.insert(path.to_string())
+    }
+
+    /// Whether a preview is being decoded.
+    pub fn is_loading(&self) -> bool {
+        !self.loading.is_empty()
+    }
+
+    pub fn failed(&mut self, path: &str) {
+        self.loading.remove(path);
+    }
+
+    /// Forgets the preview of a file that changed on disk.
+    pub fn remove(&mut self, path: &str) {
+        self.entries.retain(|(p, _)| p != path);
+        self.pages.retain(|((p, _), _)| p != path);
+    }
+}
+
+/// Bytes of pixels `handles` take.
+#[cfg(not(target_arch = "wasm32"))]
+fn bytes<'a>(handles: impl Iterator<Item = &'a Handle>) -> usize {
+    handles
+        .map(|handle| match handle.data() {
+            Data::Pixels { pixels, .. } => pixels.len(),
+            Data::Bytes(bytes) => bytes.len(),
+            Data::Path(_) => 0,
+        })
+        .sum()
+}
+
+/// The web build hands the encoded images to the browser, which keeps the pixels out of
+/// reach. Only the number of previews limits the cache there.
+#[cfg(target_arch = "wasm32")]
+fn bytes<'a>(_handles: impl Iterator<Item = &'a Handle>) -> usize {
+    0
+}
+
+/// The PDF at `path` mapped into memory, the one mapped before if it didn't change since.
+fn mapped(path: &Path) -> Option<Arc<MappedPdf>> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    let mut last = MAPPED.lock().ok()?;
+    match &*last {
+        Some((p, m, pdf)) if p == path && *m == modified => return Some(pdf.clone()),
+        _ => {}
+    }
+    let pdf = Arc::new(MappedPdf::open(path).ok()?);
+    *last = Some((path.to_path_buf(), modified, pdf.clone()));
+    Some(pdf)
+}
+
+/// Decodes the preview of the image or scanned PDF at `path`, from the disk cache if it
+/// was scaled down before. `None` if the file can't be decoded.
+pub async fn load(path: String) -> (String, Option<Handle>) {
+    let cached = cache_path(Path::new(&path));
+    let image = match cached.as_ref().and_then(|c| image::open(c).ok()) {
+        Some(image) => image,
+        None => match similarity::first_page_within(Path::new(&path), MAX_SIDE) {
+            // Scaled down, the next decode starts from the cached copy.
+            Some(image) if image.width().max(image.height()) >= MAX_SIDE => {
+                if let Some(cached) = &cached {
+                    if let Some(parent) = cached.parent() {
+                        let _ = fs::create_dir_all(parent);
+                    }
+                    if image.save(cached).is_err() {
+                        warn!(event = "preview_cache_failed", file = %path);
+                    }
+                }
+                image
+            }
+            Some(image) => image,
+            None => return (path, None),
+        },
+    };
+    (path, Some(handle(image)))
+}
+
+/// The preview of `path` turned by `turns` clockwise quarter turns, before the rotation
+/// is saved.
+pub async fn rotated(path: String, turns: u8) -> (String, u8, Option<Handle>) {
+    let handle = similarity::first_page_within(Path::new(&path), MAX_SIDE)
+        .map(|image| handle(Orientation::turned(turns).apply(image)));
+    (path, turns, handle)
+}
+
+/// The preview of page `page` of the scanned PDF at `path`, e.g. the one a search hit is
+/// on. Only the objects of the page are read from the mapped file, PDFs it can't be
+/// mapped for are loaded whole. Pages other than the first aren't cached on disk, see
+/// `PreviewCache::insert_page`.
+pub async fn page(path: String, page: usize) -> (String, usize, Option<Handle>) {
+    let image = match mapped(Path::new(&path)) {
+        Some(pdf) => pdf.page_within(page as u32, MAX_SIDE),
+        None => storage::backend()
+            .read(Path::new(&path))
+            .ok()
+            .and_then(|content| lopdf::Document::load_mem(&content).ok())
+            .and_then(|document| pdf::page_within(&document, page as u32, MAX_SIDE)),
+    };
+    (path, page, image.map(handle))
+}
+
+/// How many pages the PDF at `path` has, `None` if it can't be mapped.
+pub async fn page_count(path: String) -> (String, Option<usize>) {
+    let count = mapped(Path::new(&path)).map(|pdf| pdf.page_count());
+    (path, count)
+}
+
+/// A page of a PDF in the page editor.
+#[derive(Debug, Clone)]
+pub struct Thumbnail {
+    /// Only scanned pages have one.
+    pub handle: Option<Handle>,
+    /// Whether the page looks blank.
+    pub blank: bool,
+}
+
+/// Thumbnails of the pages of the PDF at `path`, in order. Scans with at most `max_ink`
+/// of them inked are taken for blank.
+pub async fn thumbnails(path: String, max_ink: f32) -> (String, Result<Vec<Thumbnail>, PdfError>) {
+    let thumbnails = lopdf::Document::load(&path)
+        .map_err(|_| PdfError::ReadError)
+        .map(|document| {
+            let blank = pdf::blank_pages(&document, max_ink);
+            (1..=document.get_pages().len() as u32)
+                .map(|number| {
+                    let image = pdf::page_within(&document, number, THUMBNAIL_SIDE);
+                    Thumbnail {
+                        handle: image.map(handle),
+                        blank: blank.contains(&number),
+                    }
+                })
+                .collect()
+        });
+    (path, thumbnails)
+}
+
+/// A password protected PDF opened with its password.
+#[derive(Debug, Clone)]
+pub struct Unlocked {
+    /// Preview of the first page, for scanned PDFs.
+    pub handle: Option<Handle>,
+    /// Path of the decrypted copy, if one was saved.
+    pub copy: Option<String>,
+}
+
+/// Decrypts the PDF at `path` for its preview. With `keep_copy`, the decrypted document
+/// is also saved next to it.
+pub async fn unlock(
+    path: String,
+    password: String,
+    keep_copy: bool,
+) -> (String, Result<Unlocked, PdfError>) {
+    let unlocked = pdf::unlock(Path::new(&path), &password).and_then(|mut document| {
+        let copy = if keep_copy {
+            let copy = decrypted_copy_path(Path::new(&path));
+            pdf::save(&mut document, &copy)?;
+            Some(copy.to_string_lossy().to_string())
+        } else {
+            None
+        };
+        let handle = pdf::page_within(&document, 1, MAX_SIDE).map(handle);
+        Ok(Unlocked { handle, copy })
+    });
+    (path, unlocked)
+}
+
+/// Opens the `.cocoon` document at `path` for its preview. The content is decoded in
+/// memory, a wrong passphrase is a `PasswordError`.
+pub async fn open_sealed(path: String, passphrase: String) -> (String, Result<Unlocked, PdfError>) {
+    let opened = vault::open_document(Path::new(&path), &passphrase)
+        .map_err(|_| PdfError::PasswordError)
+        .map(|content| {
+            // `statement.pdf.cocoon` holds `statement.pdf`.
+            let extension = utils::extension(Path::new(&path).with_extension(""));
+            let handle = similarity::decode_within(&extension, &content, MAX_SIDE).map(handle);
+            Unlocked { handle, copy: None }
+        });
+    (path, opened)
+}
+
+/// `date_institution_NameDecrypted_page.pdf` for normalized filenames, so the copy
+/// stays normalized, `name_decrypted.pdf` otherwise.
+fn decrypted_copy_path(path: &Path) -> PathBuf {
+    let doc = OptDoc::new(path);
+    let filename = match (doc.date, doc.institution, doc.name, doc.page) {
+        (Some(date), Some(institution), Some(name), Some(page)) => utils::normalized_filename(
+            &date,
+            &institution,
+            &format!("{}Decrypted", name),
+            &page,
+            "pdf",
+        ),
+        _ => format!(
+            "{}_decrypted.pdf",
+            path.file_stem().unwrap_or_default().to_string_lossy()
+        ),
+    };
+    path.with_file_name(filename)
+}
+
+pub fn handle(image: DynamicImage) -> Handle {
+    let (width, height) = image.dimensions();
+    Handle::from_pixels(width, height, image.into_bgra8().into_raw())
+}
+
+/// Overlay of the first pages of two documents highlighting where they differ, see
+/// `similarity::diff`.
+pub async fn diff(a: String, b: String) -> (String, String, Option<Handle>) {
+    let handle = similarity::first_page_within(Path::new(&a), MAX_SIDE)
+        .zip(similarity::first_page_within(Path::new(&b), MAX_SIDE))
+        .map(|(first, second)| handle(DynamicImage::ImageRgba8(similarity::diff(&first, &second))));
+    (a, b, handle)
+}
+
+/// Where the scaled down preview of `path` is kept. The key changes with the file's
+/// size and modification time, so edited files never show a stale preview.
+fn cache_path(path: &Path) -> Option<PathBuf> {
+    let metadata = fs::metadata(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    metadata.len().hash(&mut hasher);
+    metadata.modified().ok()?.hash(&mut hasher);
+    Some(cache_dir()?.join(format!("{:016x}.png", hasher.finish())))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn cache_dir() -> Option<PathBuf> {
+    let project_dirs = directories_next::ProjectDirs::from("rs", "d6e", "filecabinet")?;
+    Some(project_dirs.cache_dir().join("previews"))
+}
+
+/// Browsers have no cache folder, the web build scales previews down every time.
+#[cfg(target_arch = "wasm32")]
+fn cache_dir() -> Option<PathBuf> {
+    None
+}
+
+/// Cached previews written over `max_age` ago that no document of the cabinet at `dir`
+/// shows. The cache is shared by every cabinet, so the previews of deleted documents can
+/// only be told apart by their age.
+pub fn stale_previews(dir: &Path, max_age: Duration) -> Vec<PathBuf> {
+    let used: HashSet<PathBuf> = utils::list_files(dir)
+        .iter()
+        .filter_map(|filename| cache_path(&dir.join(filename)))
+        .collect();
+    let entries = match cache_dir().and_then(|cache| fs::read_dir(cache).ok()) {
+        Some(entries) => entries,
+        None => return Vec::new(),
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| !used.contains(path))
+        .filter(|path| {
+            fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > max_age)
+        })
+        .collect()
+}
+
+#[test]
+fn test_preview_cache() {
+    let handle = || Handle::from_pixels(1, 1, vec![0; 4]);
+    let mut cache = PreviewCache {
+        capacity: 2,
+        ..Default::default()
+    };
+    assert!(cache.start_loading("a"));
+    assert!(!cache.start_loading("a"));
+    cache.insert("a".to_string(), handle());
+    cache.insert("b".to_string(), handle());
+    assert!(!cache.start_loading("a"));
+    // Reading "a" makes "b" the least recently used, so it goes first.
+    assert!(cache.get("a").is_some());
+    cache.insert("c".to_string(), handle());
+    assert!(cache.get("b").is_none());
+    assert!(cache.get("a").is_some());
+    assert!(cache.get("c").is_some());
+    cache.insert_unlocked("c".to_string(), handle());
+    cache.forget_unlocked();
+    assert!(cache.get("c").is_none());
+    assert!(cache.get("a").is_some());
+
+    cache.budget = 8;
+    cache.insert("d".to_string(), handle());
+    cache.insert("e".to_string(), Handle::from_pixels(2, 2, vec![0; 16]));
+    assert!(cache.get("d").is_none());
+    assert!(cache.get("e").is_some());
+    cache.clear();
+    assert!(cache.get("e").is_none());
+}
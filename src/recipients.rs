@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Someone exports can be encrypted to with their public key, e.g. an accountant.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Recipient {
+    pub name: String,
+    /// An age (`age1...`) or SSH public key, otherwise a GPG key ID, fingerprint or email
+    /// of a key in the keyring.
+    pub key: String,
+}
+
+/// The program a key is used with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Age,
+    Gpg,
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum RecipientError {
+    KeyError,
+    LaunchError,
+    EncryptError,
+}
+
+impl Recipient {
+    pub fn tool(&self) -> Tool {
+        let key = self.key.trim();
+        if key.starts_with("age1") || key.starts_with("ssh-") {
+            Tool::Age
+        } else {
+            Tool::Gpg
+        }
+    }
+}
+
+impl Tool {
+    fn extension(self) -> &'static str {
+        match self {
+            Tool::Age => "age",
+            Tool::Gpg => "gpg",
+        }
+    }
+
+    /// The program and its arguments encrypting `input` to `keys` into `output`.
+    fn args(self, keys: &[&str], input: &Path, output: &Path) -> Vec<String> {
+        let mut args: Vec<String> = match self {
+            Tool::Age => vec!["age".into()],
+            // The keys were picked on purpose, don't ask whether they are trusted.
+            Tool::Gpg => vec![
+                "gpg".into(),
+                "--batch".into(),
+                "--yes".into(),
+                "--trust-model".into(),
+                "always".into(),
+                "--encrypt".into(),
+            ],
+        };
+        for key in keys {
+            args.push("--recipient".into());
+            args.push(key.to_string());
+        }
+        args.push("--output".into());
+        args.push(output.to_string_lossy().to_string());
+        args.push(input.to_string_lossy().to_string());
+        args
+    }
+}
+
+/// Encrypts `path` to every recipient, as `<path>.age` for age keys and `<path>.gpg`
+/// for GPG ones. Returns the encrypted files.
+pub fn encrypt(path: &Path, recipients: &[Recipient]) -> Result<Vec<PathBuf>, RecipientError> {
+    let mut encrypted = Vec::new();
+    for tool in [Tool::Age, Tool::Gpg] {
+        let keys: Vec<&str> = recipients
+            .iter()
+            .filter(|recipient| recipient.tool() == tool && !recipient.key.trim().is_empty())
+            .map(|recipient| recipient.key.trim())
+            .collect();
+        if keys.is_empty() {
+            continue;
+        }
+        let mut output = path.as_os_str().to_owned();
+        output.push(".");
+        output.push(tool.extension());
+        let output = PathBuf::from(output);
+        // Left over from an earlier export, which age refuses to overwrite.
+        let _ = fs::remove_file(&output);
+        let args = tool.args(&keys, path, &output);
+        let status = Command::new(&args[0])
+            .args(&args[1..])
+            .status()
+            .map_err(|_| RecipientError::LaunchError)?;
+        if !status.success() {
+            return Err(RecipientError::EncryptError);
+        }
+        encrypted.push(output);
+    }
+    if encrypted.is_empty() {
+        return Err(RecipientError::KeyError);
+    }
+    Ok(encrypted)
+}
+
+#[test]
+fn test_args() {
+    let recipient = |key: &str| Recipient {
+        name: "Accountant".to_string(),
+        key: key.to_string(),
+    };
+    assert_eq!(
+        recipient(" age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p").tool(),
+        Tool::Age
+    );
+    assert_eq!(recipient("ssh-ed25519 AAAAC3Nza").tool(), Tool::Age);
+    assert_eq!(recipient("accountant@example.com").tool(), Tool::Gpg);
+    assert_eq!(
+        Tool::Gpg.args(
+            &["accountant@example.com"],
+            Path::new("/c/TaxPacket_2020.pdf"),
+            Path::new("/c/TaxPacket_2020.pdf.gpg")
+        ),
+        vec![
+            "gpg",
+            "--batch",
+            "--yes",
+            "--trust-model",
+            "always",
+            "--encrypt",
+            "--recipient",
+            "accountant@example.com",
+            "--output",
+            "/c/TaxPacket_2020.pdf.gpg",
+            "/c/TaxPacket_2020.pdf"
+        ]
+    );
+    assert!(encrypt(Path::new("/c/TaxPacket_2020.pdf"), &[recipient(" ")]).is_err());
+}
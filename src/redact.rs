@@ -0,0 +1,369 @@
+use crate::{pdf, preview, similarity, storage, utils};
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use iced::image::Handle;
+#[cfg(not(target_arch = "wasm32"))]
+use iced_native::{
+    event, layout, mouse, Clipboard, Element, Event, Hasher, Image, Layout, Length, Point,
+    Rectangle, Widget,
+};
+use image::{DynamicImage, GenericImageView, ImageFormat, Rgba};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::info;
+
+/// Boxes smaller than this share of the page are taken for a click, not a drag.
+const MIN_SIDE: f32 = 0.005;
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum RedactError {
+    ReadError,
+    /// A PDF page isn't a scan, its text can't be flattened safely.
+    UnsupportedError,
+    WriteError,
+}
+
+/// A box blacked out on a page, in shares of its width and height from the top left so
+/// it covers the same place at any resolution.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl Region {
+    /// The box between two corners, in any order. `None` if it is too small to be meant.
+    pub fn new(from: (f32, f32), to: (f32, f32)) -> Option<Region> {
+        let clamp = |value: f32| value.clamp(0.0, 1.0);
+        let region = Region {
+            left: clamp(from.0.min(to.0)),
+            top: clamp(from.1.min(to.1)),
+            right: clamp(from.0.max(to.0)),
+            bottom: clamp(from.1.max(to.1)),
+        };
+        (region.right - region.left >= MIN_SIDE && region.bottom - region.top >= MIN_SIDE)
+            .then_some(region)
+    }
+}
+
+/// `image` with `regions` painted black into its pixels, so nothing of what they cover
+/// is left in it.
+pub fn flatten(image: &DynamicImage, regions: &[Region]) -> DynamicImage {
+    let mut pixels = image.to_rgba8();
+    let (width, height) = pixels.dimensions();
+    for region in regions {
+        // Rounded outwards, a pixel partly covered is covered.
+        let left = (region.left * width as f32).floor() as u32;
+        let top = (region.top * height as f32).floor() as u32;
+        let right = ((region.right * width as f32).ceil() as u32).min(width);
+        let bottom = ((region.bottom * height as f32).ceil() as u32).min(height);
+        for y in top..bottom {
+            for x in left..right {
+                pixels.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+            }
+        }
+    }
+    DynamicImage::ImageRgba8(pixels)
+}
+
+/// A page of a document being redacted, scaled down for the screen and kept decoded so
+/// the boxes are drawn on it without reading the document again.
+#[derive(Clone)]
+pub struct Page {
+    /// Counted from 1.
+    pub number: usize,
+    /// Pages of the document.
+    pub count: usize,
+    image: Arc<DynamicImage>,
+}
+
+impl fmt::Debug for Page {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Page")
+            .field("number", &self.number)
+            .field("count", &self.count)
+            .field("dimensions", &self.image.dimensions())
+            .finish()
+    }
+}
+
+impl Page {
+    /// The page with `regions` blacked out, as it will be exported.
+    pub fn handle(&self, regions: &[Region]) -> Handle {
+        preview::handle(flatten(&self.image, regions))
+    }
+}
+
+/// Page `number`, counted from 1, of the document at `path` to draw boxes on. Images
+/// have one page, PDFs have to be scans.
+pub async fn page(path: String, number: usize) -> (String, Result<Page, RedactError>) {
+    let page = read_page(Path::new(&path), number);
+    (path, page)
+}
+
+fn read_page(path: &Path, number: usize) -> Result<Page, RedactError> {
+    if utils::extension(path) != "pdf" {
+        let image =
+            similarity::first_page_within(path, preview::MAX_SIDE).ok_or(RedactError::ReadError)?;
+        return Ok(Page {
+            number: 1,
+            count: 1,
+            image: Arc::new(image),
+        });
+    }
+    let document = read_pdf(path)?;
+    let image = pdf::page_within(&document, number as u32, preview::MAX_SIDE)
+        .ok_or(RedactError::UnsupportedError)?;
+    Ok(Page {
+        number,
+        count: document.get_pages().len(),
+        image: Arc::new(image),
+    })
+}
+
+fn read_pdf(path: &Path) -> Result<lopdf::Document, RedactError> {
+    let content = storage::backend()
+        .read(path)
+        .map_err(|_| RedactError::ReadError)?;
+    lopdf::Document::load_mem(&content).map_err(|_| RedactError::ReadError)
+}
+
+/// Where the redacted copy of `path` goes in the cabinet at `dir`, `<dir>/redacted`. The
+/// copy of a PDF is a PDF, of an image a PNG, which carries none of its metadata.
+fn export_path(dir: &Path, path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = match utils::extension(path).as_str() {
+        "pdf" => "pdf",
+        _ => "png",
+    };
+    dir.join("redacted")
+        .join(format!("{}_redacted.{}", stem, extension))
+}
+
+/// Writes a copy of the document at `path` with `regions` blacked out, by page number,
+/// into the cabinet at `dir`. Every page is flattened to pixels at full resolution, so
+/// neither text nor the covered scan survive under the boxes. The document itself is
+/// never changed. Returns where the copy went.
+pub fn write(
+    dir: &Path,
+    path: &Path,
+    regions: &BTreeMap<usize, Vec<Region>>,
+) -> Result<PathBuf, RedactError> {
+    let destination = export_path(dir, path);
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).map_err(|_| RedactError::WriteError)?;
+    }
+    let boxes = |number: usize| regions.get(&number).map(Vec::as_slice).unwrap_or_default();
+    if utils::extension(path) != "pdf" {
+        let image = similarity::first_page(path).ok_or(RedactError::ReadError)?;
+        let redacted = flatten(&image, boxes(1));
+        AtomicFile::new(&destination, OverwriteBehavior::AllowOverwrite)
+            .write(|f| {
+                redacted
+                    .write_to(f, ImageFormat::Png)
+                    .map_err(std::io::Error::other)
+            })
+            .map_err(|_| RedactError::WriteError)?;
+        return Ok(destination);
+    }
+    let document = read_pdf(path)?;
+    let mut pages = Vec::new();
+    for number in 1..=document.get_pages().len() {
+        let image = pdf::page(&document, number as u32).ok_or(RedactError::UnsupportedError)?;
+        pages.push(flatten(&image, boxes(number)));
+    }
+    let mut redacted = pdf::from_images(pages).map_err(|_| RedactError::WriteError)?;
+    pdf::save(&mut redacted, &destination).map_err(|_| RedactError::WriteError)?;
+    Ok(destination)
+}
+
+pub async fn export(
+    dir: String,
+    path: String,
+    regions: BTreeMap<usize, Vec<Region>>,
+) -> Result<String, RedactError> {
+    let destination = write(Path::new(&dir), Path::new(&path), &regions)?;
+    let boxes: usize = regions.values().map(Vec::len).sum();
+    info!(event = "ExportRedacted", file = %path, to = %destination.display(), boxes);
+    Ok(destination.to_string_lossy().to_string())
+}
+
+/// Where a box being drawn on a `RegionPicker` started, kept between frames.
+#[derive(Debug, Default)]
+pub struct PickerState {
+    start: Option<(f32, f32)>,
+}
+
+/// An image boxes are drawn on by dragging over it, each one produces a `Region`.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct RegionPicker<'a, Message> {
+    state: &'a mut PickerState,
+    image: Image,
+    on_region: Box<dyn Fn(Region) -> Message>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<'a, Message> RegionPicker<'a, Message> {
+    pub fn new(
+        state: &'a mut PickerState,
+        handle: Handle,
+        on_region: impl Fn(Region) -> Message + 'static,
+    ) -> Self {
+        RegionPicker {
+            state,
+            image: Image::new(handle),
+            on_region: Box::new(on_region),
+        }
+    }
+}
+
+/// Where `cursor` is on the image at `bounds`, in shares of its width and height.
+#[cfg(not(target_arch = "wasm32"))]
+fn position(bounds: Rectangle, cursor: Point) -> (f32, f32) {
+    (
+        ((cursor.x - bounds.x) / bounds.width).clamp(0.0, 1.0),
+        ((cursor.y - bounds.y) / bounds.height).clamp(0.0, 1.0),
+    )
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<'a, Message, Renderer> Widget<Message, Renderer> for RegionPicker<'a, Message>
+where
+    Renderer: iced_native::image::Renderer,
+{
+    fn width(&self) -> Length {
+        Widget::<Message, Renderer>::width(&self.image)
+    }
+
+    fn height(&self) -> Length {
+        Widget::<Message, Renderer>::height(&self.image)
+    }
+
+    fn layout(&self, renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        Widget::<Message, Renderer>::layout(&self.image, renderer, limits)
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut Renderer,
+        defaults: &Renderer::Defaults,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+    ) -> Renderer::Output {
+        Widget::<Message, Renderer>::draw(
+            &self.image,
+            renderer,
+            defaults,
+            layout,
+            cursor_position,
+            viewport,
+        )
+    }
+
+    fn hash_layout(&self, state: &mut Hasher) {
+        Widget::<Message, Renderer>::hash_layout(&self.image, state)
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        messages: &mut Vec<Message>,
+        _renderer: &Renderer,
+        _clipboard: Option<&dyn Clipboard>,
+    ) -> event::Status {
+        let bounds = layout.bounds();
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+                if bounds.contains(cursor_position) =>
+            {
+                self.state.start = Some(position(bounds, cursor_position));
+                event::Status::Captured
+            }
+            // Released anywhere, a drag past the edge of the page ends on it.
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                match self.state.start.take() {
+                    Some(start) => {
+                        if let Some(region) = Region::new(start, position(bounds, cursor_position))
+                        {
+                            messages.push((self.on_region)(region));
+                        }
+                        event::Status::Captured
+                    }
+                    None => event::Status::Ignored,
+                }
+            }
+            _ => event::Status::Ignored,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<'a, Message, Renderer> From<RegionPicker<'a, Message>> for Element<'a, Message, Renderer>
+where
+    Message: 'a,
+    Renderer: iced_native::image::Renderer,
+{
+    fn from(picker: RegionPicker<'a, Message>) -> Self {
+        Element::new(picker)
+    }
+}
+
+#[test]
+fn test_redact() {
+    assert_eq!(
+        Region::new((0.8, 0.6), (0.2, 0.4)),
+        Some(Region {
+            left: 0.2,
+            top: 0.4,
+            right: 0.8,
+            bottom: 0.6,
+        })
+    );
+    assert_eq!(Region::new((-0.5, 0.0), (0.5, 1.5)).unwrap().left, 0.0);
+    assert_eq!(Region::new((0.5, 0.5), (0.501, 0.9)), None);
+
+    let white = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+        100,
+        50,
+        Rgba([255, 255, 255, 255]),
+    ));
+    // Partly covered pixels are blacked out too.
+    let region = Region::new((0.125, 0.25), (0.375, 0.75)).unwrap();
+    let flattened = flatten(&white, &[region]).to_rgba8();
+    assert_eq!(flattened.get_pixel(12, 12), &Rgba([0, 0, 0, 255]));
+    assert_eq!(flattened.get_pixel(37, 37), &Rgba([0, 0, 0, 255]));
+    assert_eq!(flattened.get_pixel(11, 20), &Rgba([255, 255, 255, 255]));
+    assert_eq!(flattened.get_pixel(38, 20), &Rgba([255, 255, 255, 255]));
+    assert_eq!(flattened.get_pixel(20, 11), &Rgba([255, 255, 255, 255]));
+    assert_eq!(flattened.get_pixel(20, 38), &Rgba([255, 255, 255, 255]));
+
+    let dir = tempdir::TempDir::new("redact").unwrap();
+    let scan = dir.path().join("2021-03-04_Bank_Statement_1.png");
+    white.save(&scan).unwrap();
+    let mut regions = BTreeMap::new();
+    regions.insert(1, vec![region]);
+    let copy = write(dir.path(), &scan, &regions).unwrap();
+    assert_eq!(
+        copy,
+        dir.path()
+            .join("redacted")
+            .join("2021-03-04_Bank_Statement_1_redacted.png")
+    );
+    assert_eq!(
+        image::open(&copy).unwrap().to_rgba8().get_pixel(20, 20),
+        &Rgba([0, 0, 0, 255])
+    );
+    // The original keeps its pixels.
+    assert_eq!(
+        image::open(&scan).unwrap().to_rgba8().get_pixel(20, 20),
+        &Rgba([255, 255, 255, 255])
+    );
+}
@@ -0,0 +1,487 @@
+use crate::catalog::{Catalog, CatalogError};
+use crate::events::{self, Event};
+use crate::hooks::{self, Hook, HookEvent, Payload};
+use crate::report::{self, ImportReport};
+use crate::search::{self, Candidate, Field, Query};
+use crate::{pdf, plugins, tags, utils};
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use chrono::{DateTime, Utc};
+use glob::{MatchOptions, Pattern};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// A folder to pull recurring downloads from, and the rules used to name them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportProfile {
+    pub source_dir: String,
+    pub rules: Vec<ImportRule>,
+}
+
+/// Files whose name matches `pattern` are filed under `institution` and `name`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportRule {
+    pub pattern: String,
+    pub institution: String,
+    pub name: String,
+}
+
+/// What of a document a `TagRule` looks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleField {
+    /// A glob pattern like `eStmt_*.pdf`.
+    #[default]
+    Filename,
+    Institution,
+    /// The file extension, `pdf` or `jpg`.
+    Extension,
+    /// A word or phrase of the text read while indexing.
+    Keyword,
+}
+
+impl RuleField {
+    pub fn name(self) -> &'static str {
+        match self {
+            RuleField::Filename => "Filename",
+            RuleField::Institution => "Institution",
+            RuleField::Extension => "Type",
+            RuleField::Keyword => "Text",
+        }
+    }
+
+    pub fn placeholder(self) -> &'static str {
+        match self {
+            RuleField::Filename => "eStmt_*.pdf",
+            RuleField::Institution => "Chase",
+            RuleField::Extension => "pdf",
+            RuleField::Keyword => "tax return",
+        }
+    }
+
+    /// The field after this one, to cycle through them with a single button.
+    pub fn next(self) -> RuleField {
+        match self {
+            RuleField::Filename => RuleField::Institution,
+            RuleField::Institution => RuleField::Extension,
+            RuleField::Extension => RuleField::Keyword,
+            RuleField::Keyword => RuleField::Filename,
+        }
+    }
+}
+
+/// Documents entering the cabinet whose `field` matches `pattern` are tagged with `tags`,
+/// a comma separated list.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TagRule {
+    pub field: RuleField,
+    pub pattern: String,
+    pub tags: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum RuleMessage {
+    SourceDirChanged(String),
+    AddRule,
+    RemoveRule(usize),
+    PatternEdited(usize, String),
+    InstitutionEdited(usize, String),
+    NameEdited(usize, String),
+}
+
+/// Drops the blank pages of the imported document at `path` if it is a PDF, see
+/// `pdf::drop_blank_pages`. Returns how many were dropped.
+pub fn drop_blank_pages(path: &Path, max_ink: f32) -> usize {
+    if utils::extension(path) != "pdf" {
+        return 0;
+    }
+    match pdf::drop_blank_pages(path, max_ink) {
+        Ok(dropped) => {
+            if !dropped.is_empty() {
+                info!(event = "DropBlankPages", file = %path.display(), pages = ?dropped);
+            }
+            dropped.len()
+        }
+        Err(error) => {
+            warn!(event = "drop_blank_pages_failed", file = %path.display(), ?error);
+            0
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ImportError {
+    SourceError,
+}
+
+impl ImportRule {
+    pub fn matches(&self, filename: &str) -> bool {
+        !self.institution.is_empty()
+            && !self.name.is_empty()
+            && Pattern::new(&self.pattern)
+                .map(|pattern| pattern.matches(filename))
+                .unwrap_or(false)
+    }
+}
+
+impl TagRule {
+    pub fn matches(&self, candidate: &Candidate) -> bool {
+        let pattern = self.pattern.trim();
+        if pattern.is_empty() || tags::parse(&self.tags).is_empty() {
+            return false;
+        }
+        match self.field {
+            RuleField::Filename => Pattern::new(pattern).is_ok_and(|glob| {
+                glob.matches_with(
+                    candidate.filename(),
+                    MatchOptions {
+                        case_sensitive: false,
+                        ..Default::default()
+                    },
+                )
+            }),
+            RuleField::Institution => {
+                Query::Field(Field::Institution, pattern.to_string()).matches(candidate)
+            }
+            RuleField::Extension => {
+                Query::Field(Field::Extension, pattern.to_string()).matches(candidate)
+            }
+            RuleField::Keyword => candidate.find(pattern).is_some(),
+        }
+    }
+}
+
+/// The tag rules of a cabinet, in `<cabinet>/.filecabinet/tag_rules.json`.
+fn tag_rules_path(dir: &Path) -> PathBuf {
+    Catalog::path(dir).with_file_name("tag_rules.json")
+}
+
+pub fn load_tag_rules(dir: &Path) -> Vec<TagRule> {
+    fs::read_to_string(tag_rules_path(dir))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_tag_rules(dir: &Path, rules: &[TagRule]) -> Result<(), CatalogError> {
+    let path = tag_rules_path(dir);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|_| CatalogError::DirectoryError)?;
+    }
+    let json = serde_json::to_string_pretty(rules).map_err(|_| CatalogError::FormatError)?;
+    AtomicFile::new(&path, OverwriteBehavior::AllowOverwrite)
+        .write(|f| f.write_all(json.as_bytes()))
+        .map_err(|_| CatalogError::WriteError)
+}
+
+/// Tags `rules` give to `filename` of the cabinet at `dir`.
+pub fn rule_tags(rules: &[TagRule], dir: &Path, filename: &str, catalog: &Catalog) -> Vec<String> {
+    if rules.is_empty() {
+        return Vec::new();
+    }
+    let candidate = Candidate::load(dir, filename, catalog);
+    let mut tags = Vec::new();
+    for rule in rules.iter().filter(|rule| rule.matches(&candidate)) {
+        tags::merge(&mut tags, &tags::parse(&rule.tags));
+    }
+    tags
+}
+
+/// Runs the tag rules of the cabinet at `dir` over all of its documents. Rules only ever
+/// add tags. Returns how many documents got new ones.
+pub fn apply_tag_rules(dir: &Path) -> Result<usize, CatalogError> {
+    let rules = load_tag_rules(dir);
+    let mut catalog = Catalog::load(dir);
+    let mut tagged = Vec::new();
+    for filename in utils::list_files(dir) {
+        let tags = rule_tags(&rules, dir, &filename, &catalog);
+        let entry = catalog.entry(&filename);
+        if !tags.is_empty() && tags::merge(&mut entry.tags, &tags) {
+            let tags = entry.tags.clone();
+            tagged.push(Event::Tagged { filename, tags });
+        }
+    }
+    if !tagged.is_empty() {
+        catalog.save(dir)?;
+        events::emit(dir, &tagged);
+    }
+    info!(
+        event = "ApplyTagRules",
+        rules = rules.len(),
+        documents = tagged.len()
+    );
+    Ok(tagged.len())
+}
+
+pub async fn rerun_tag_rules(dir: String) -> Result<usize, CatalogError> {
+    apply_tag_rules(Path::new(&dir))
+}
+
+impl ImportProfile {
+    pub fn update(&mut self, message: RuleMessage) {
+        match message {
+            RuleMessage::SourceDirChanged(dir) => self.source_dir = dir,
+            RuleMessage::AddRule => self.rules.push(ImportRule::default()),
+            RuleMessage::RemoveRule(i) => {
+                if i < self.rules.len() {
+                    self.rules.remove(i);
+                }
+            }
+            RuleMessage::PatternEdited(i, s) => {
+                if let Some(rule) = self.rules.get_mut(i) {
+                    rule.pattern = s;
+                }
+            }
+            RuleMessage::InstitutionEdited(i, s) => {
+                if let Some(rule) = self.rules.get_mut(i) {
+                    rule.institution = s;
+                }
+            }
+            RuleMessage::NameEdited(i, s) => {
+                if let Some(rule) = self.rules.get_mut(i) {
+                    rule.name = s;
+                }
+            }
+        }
+    }
+
+    /// Returns the first rule matching the filename, rules are tried in order.
+    pub fn matching_rule(&self, filename: &str) -> Option<&ImportRule> {
+        self.rules.iter().find(|rule| rule.matches(filename))
+    }
+
+    /// The files in the source dir a rule matches, sorted.
+    pub fn downloads(&self) -> Vec<String> {
+        let mut downloads: Vec<String> = utils::list_files(Path::new(&self.source_dir))
+            .into_iter()
+            .filter(|filename| self.matching_rule(filename).is_some())
+            .collect();
+        downloads.sort();
+        downloads
+    }
+
+    /// Pairs every matching file in the source dir with its normalized path in `target_dir`.
+    pub fn plan(&self, target_dir: &Path) -> Vec<(PathBuf, PathBuf)> {
+        let source_dir = Path::new(&self.source_dir);
+        let mut planned: Vec<(PathBuf, PathBuf)> = Vec::new();
+        for filename in utils::list_files(source_dir) {
+            let rule = match self.matching_rule(&filename) {
+                Some(rule) => rule,
+                None => continue,
+            };
+            let source = source_dir.join(&filename);
+            let date = source
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| utils::parse_date(&stem))
+                .or_else(|| modified_date(&source))
+                .unwrap_or_else(|| Utc::now().format("%Y-%m-%d").to_string());
+            let institution = utils::to_camelcase(&rule.institution);
+            let name = utils::to_camelcase(&rule.name);
+            let extension = utils::extension(&source);
+            // Bump the page until the name is free, both on disk and within this plan.
+            let mut page = 1;
+            let target = loop {
+                let candidate = target_dir.join(utils::normalized_filename(
+                    &date,
+                    &institution,
+                    &name,
+                    &page.to_string(),
+                    &extension,
+                ));
+                if !candidate.exists() && !planned.iter().any(|(_, t)| t == &candidate) {
+                    break candidate;
+                }
+                page += 1;
+            };
+            planned.push((source, target));
+        }
+        planned
+    }
+}
+
+fn modified_date(path: &Path) -> Option<String> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    let modified: DateTime<Utc> = modified.into();
+    Some(modified.format("%Y-%m-%d").to_string())
+}
+
+/// Moves every file matched by the profile, and the files of the importer plugins, into the
+/// cabinet, leaving out the ones it has already. With `blank_ink`, PDF pages with no more
+/// ink than that are dropped. Files a pre-import hook fails on are left where they are.
+pub async fn import(
+    profile: ImportProfile,
+    target_dir: String,
+    blank_ink: Option<f32>,
+    hooks: Vec<Hook>,
+) -> Result<ImportReport, ImportError> {
+    let target_dir = Path::new(&target_dir);
+    let importers = plugins::registry().importers();
+    let has_source = !profile.source_dir.is_empty() && Path::new(&profile.source_dir).is_dir();
+    if !has_source && importers.is_empty() {
+        return Err(ImportError::SourceError);
+    }
+    let mut report = ImportReport::new("Import rules");
+    let mut planned = if has_source {
+        profile.plan(target_dir)
+    } else {
+        Vec::new()
+    };
+    for importer in importers {
+        for (source, target) in importer.plan(target_dir) {
+            // Two sources can't be filed under the same name.
+            if target.exists() || planned.iter().any(|(_, t)| t == &target) {
+                warn!(event = "import_skipped", plugin = importer.name(), file = %source.display());
+                report.error(&source, "its name is taken in the cabinet");
+                continue;
+            }
+            planned.push((source, target));
+        }
+    }
+    let mut known = report::known_hashes(target_dir);
+    for (source, target) in planned {
+        if utils::sha256(&source).is_some_and(|hash| !known.insert(hash)) {
+            report.duplicate(&source);
+            continue;
+        }
+        let filename = target.file_name().unwrap_or_default().to_string_lossy();
+        let payload = Payload::new(HookEvent::PreImport, &source, target_dir, &filename);
+        if hooks::run(&hooks, &payload).is_err() {
+            report.error(&source, "a pre-import hook refused it");
+            continue;
+        }
+        // Downloads often live on another filesystem than the cabinet, so fall back to copying.
+        let moved = fs::rename(&source, &target).is_ok()
+            || (fs::copy(&source, &target).is_ok() && fs::remove_file(&source).is_ok());
+        if !moved {
+            warn!(event = "import_failed", file = %source.display());
+            report.error(&source, "couldn't move it into the cabinet");
+            continue;
+        }
+        info!(event = "Import", old = %source.display(), new = %target.display());
+        let dropped = blank_ink.map_or(0, |max_ink| drop_blank_pages(&target, max_ink));
+        report.add(&target, dropped);
+    }
+    report.finish(target_dir);
+    Ok(report)
+}
+
+/// Copies every document of `source_dir` into the cabinet as is, skipping names that are
+/// taken.
+pub async fn import_folder(
+    source_dir: String,
+    target_dir: String,
+) -> Result<ImportReport, ImportError> {
+    let source_dir = Path::new(&source_dir);
+    if !source_dir.is_dir() {
+        return Err(ImportError::SourceError);
+    }
+    let mut report = ImportReport::new(&source_dir.display().to_string());
+    let mut known = report::known_hashes(Path::new(&target_dir));
+    for filename in utils::list_files(source_dir) {
+        let source = source_dir.join(&filename);
+        let target = Path::new(&target_dir).join(&filename);
+        if target.exists() {
+            report.error(&source, "its name is taken in the cabinet");
+            continue;
+        }
+        if utils::sha256(&source).is_some_and(|hash| !known.insert(hash)) {
+            report.duplicate(&source);
+            continue;
+        }
+        if fs::copy(&source, &target).is_err() {
+            report.error(&source, "couldn't copy it into the cabinet");
+            continue;
+        }
+        info!(event = "Import", old = %source.display(), new = %target.display());
+        report.add(&target, 0);
+    }
+    report.finish(Path::new(&target_dir));
+    Ok(report)
+}
+
+#[test]
+fn test_rule_matches() {
+    let rule = ImportRule {
+        pattern: "eStmt_*.pdf".to_string(),
+        institution: "Chase".to_string(),
+        name: "Statement".to_string(),
+    };
+    assert!(rule.matches("eStmt_2021-03-04.pdf"));
+    assert!(!rule.matches("invoice.pdf"));
+    let incomplete = ImportRule {
+        name: String::new(),
+        ..rule
+    };
+    assert!(!incomplete.matches("eStmt_2021-03-04.pdf"));
+}
+
+#[test]
+fn test_downloads() {
+    let dir = tempdir::TempDir::new("downloads").unwrap();
+    for filename in &[
+        "eStmt_2021-04-04.pdf",
+        "invoice.pdf",
+        "eStmt_2021-03-04.pdf",
+    ] {
+        fs::write(dir.path().join(filename), b"statement").unwrap();
+    }
+    let profile = ImportProfile {
+        source_dir: dir.path().to_string_lossy().to_string(),
+        rules: vec![ImportRule {
+            pattern: "eStmt_*.pdf".to_string(),
+            institution: "Chase".to_string(),
+            name: "Statement".to_string(),
+        }],
+    };
+    assert_eq!(
+        profile.downloads(),
+        vec!["eStmt_2021-03-04.pdf", "eStmt_2021-04-04.pdf"]
+    );
+}
+
+#[test]
+fn test_tag_rules() {
+    let dir = tempdir::TempDir::new("rules").unwrap();
+    let dir = dir.path();
+    let statement = "2021-03-04_Chase_Statement_1.pdf";
+    let receipt = "2021-05-06_HomeDepot_Receipt_1.jpg";
+    fs::write(dir.join(statement), b"statement").unwrap();
+    fs::write(dir.join(receipt), b"receipt").unwrap();
+    search::save_text(
+        &dir.join(receipt),
+        &["Thank you. Deductible for TAX purposes.".to_string()],
+    )
+    .unwrap();
+    let rule = |field, pattern: &str, tags: &str| TagRule {
+        field,
+        pattern: pattern.to_string(),
+        tags: tags.to_string(),
+    };
+    save_tag_rules(
+        dir,
+        &[
+            rule(RuleField::Filename, "*_statement_*", "finance"),
+            rule(RuleField::Institution, "chase", "finance/bank, Finance"),
+            rule(RuleField::Extension, "jpg", "scans"),
+            rule(RuleField::Keyword, "tax purposes", "finance/tax"),
+            rule(RuleField::Keyword, "", "never"),
+        ],
+    )
+    .unwrap();
+
+    assert_eq!(apply_tag_rules(dir).unwrap(), 2);
+    let catalog = Catalog::load(dir);
+    assert_eq!(
+        catalog.get(statement).unwrap().tags,
+        vec!["finance", "finance/bank"]
+    );
+    assert_eq!(
+        catalog.get(receipt).unwrap().tags,
+        vec!["finance/tax", "scans"]
+    );
+    // Nothing new the second time.
+    assert_eq!(apply_tag_rules(dir).unwrap(), 0);
+}
@@ -0,0 +1,288 @@
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+/// Prefix of the workspace folders, one per running app.
+const PREFIX: &str = "filecabinet-";
+
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static! {
+    /// The workspace of this run, once created.
+    static ref ROOT: Mutex<Option<Root>> = Mutex::new(None);
+}
+
+/// Where files holding document content are written while an external program needs
+/// them, e.g. the pages Tesseract reads: `<temp>/filecabinet-<pid>-<random>`.
+#[derive(Debug, Clone)]
+struct Root {
+    path: PathBuf,
+    owner: u32,
+}
+
+/// Creates the workspace on first use. The random part of its name keeps anyone from
+/// creating it beforehand, so it is known to belong to this user only.
+fn root() -> io::Result<Root> {
+    let mut root = ROOT
+        .lock()
+        .map_err(|_| io::Error::other("workspace lock poisoned"))?;
+    if let Some(root) = &*root {
+        if !private(&root.path, root.owner) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{} is no longer private", root.path.display()),
+            ));
+        }
+        return Ok(root.clone());
+    }
+    loop {
+        let suffix: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(10)
+            .collect();
+        let path =
+            std::env::temp_dir().join(format!("{}{}-{}", PREFIX, std::process::id(), suffix));
+        match create_root(&path) {
+            Ok(()) => {
+                let created = Root {
+                    owner: owner(&fs::symlink_metadata(&path)?),
+                    path,
+                };
+                *root = Some(created.clone());
+                return Ok(created);
+            }
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// Creates `root`, failing if anything is there already.
+#[cfg(unix)]
+fn create_root(root: &Path) -> io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    fs::DirBuilder::new().mode(0o700).create(root)
+}
+
+#[cfg(not(unix))]
+fn create_root(root: &Path) -> io::Result<()> {
+    fs::create_dir(root)
+}
+
+#[cfg(unix)]
+fn owner(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.uid()
+}
+
+#[cfg(not(unix))]
+fn owner(_metadata: &fs::Metadata) -> u32 {
+    0
+}
+
+/// Whether `path` is a folder, not a link to one, that only `owner` can enter.
+#[cfg(unix)]
+fn private(path: &Path, owner: u32) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    fs::symlink_metadata(path)
+        .map(|metadata| {
+            metadata.file_type().is_dir()
+                && metadata.uid() == owner
+                && metadata.mode() & 0o777 == 0o700
+        })
+        .unwrap_or(false)
+}
+
+// The temp folder is the user's own on other systems.
+#[cfg(not(unix))]
+fn private(path: &Path, _owner: u32) -> bool {
+    fs::symlink_metadata(path)
+        .map(|metadata| metadata.file_type().is_dir())
+        .unwrap_or(false)
+}
+
+/// A folder in the workspace, shredded with everything in it once dropped.
+#[derive(Debug)]
+pub struct WorkDir {
+    path: PathBuf,
+}
+
+impl WorkDir {
+    pub fn new(name: &str) -> io::Result<WorkDir> {
+        let root = root()?;
+        let path = root
+            .path
+            .join(format!("{}-{}", name, NEXT.fetch_add(1, Ordering::Relaxed)));
+        fs::create_dir(&path)?;
+        Ok(WorkDir { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for WorkDir {
+    fn drop(&mut self) {
+        shred_dir(&self.path);
+    }
+}
+
+#[cfg(unix)]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+fn same_file(_a: &fs::Metadata, _b: &fs::Metadata) -> bool {
+    true
+}
+
+/// Overwrites the regular file at `path` with zeros before removing it, so its content
+/// can't be read back from the freed blocks. Copy-on-write and flash storage may still
+/// keep old copies. Nothing is written if `path` was swapped for a link meanwhile.
+fn shred(path: &Path) -> io::Result<()> {
+    let before = fs::symlink_metadata(path)?;
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    if !before.file_type().is_file() || !same_file(&before, &file.metadata()?) {
+        return Err(io::Error::other("replaced before it was shredded"));
+    }
+    let zeros = [0u8; 8192];
+    let mut left = before.len();
+    while left > 0 {
+        let n = left.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..n])?;
+        left -= n as u64;
+    }
+    file.sync_all()?;
+    drop(file);
+    fs::remove_file(path)
+}
+
+/// Shreds every file below `dir` and removes it. Links are removed, never followed.
+fn shred_dir(dir: &Path) {
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let shredded = match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => {
+                shred_dir(&path);
+                Ok(())
+            }
+            Ok(file_type) if file_type.is_file() => shred(&path),
+            _ => fs::remove_file(&path),
+        };
+        if let Err(error) = shredded {
+            warn!(event = "shred_failed", file = %path.display(), ?error);
+        }
+    }
+    if let Err(error) = fs::remove_dir(dir) {
+        if fs::symlink_metadata(dir).is_ok() {
+            warn!(event = "shred_failed", file = %dir.display(), ?error);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn running(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+// Without a cheap way to tell, workspaces of other runs are left alone.
+#[cfg(not(target_os = "linux"))]
+fn running(_pid: u32) -> bool {
+    true
+}
+
+/// Shreds the workspaces of earlier runs that crashed or were killed before they could
+/// clean up. Folders that aren't private to this user are left alone, whoever made them.
+pub fn clean_up_stale() {
+    let own = match root() {
+        Ok(root) => root,
+        Err(error) => {
+            warn!(event = "shred_failed", ?error);
+            return;
+        }
+    };
+    for entry in fs::read_dir(std::env::temp_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+    {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let pid = match name
+            .strip_prefix(PREFIX)
+            .and_then(|rest| rest.split('-').next())
+            .and_then(|pid| pid.parse().ok())
+        {
+            Some(pid) => pid,
+            None => continue,
+        };
+        let path = entry.path();
+        if pid != std::process::id() && !running(pid) && private(&path, own.owner) {
+            shred_dir(&path);
+            info!(event = "ShredStale", dir = %path.display());
+        }
+    }
+}
+
+/// Shreds the workspace of this run when dropped. The app exits by dropping its state,
+/// which holds one.
+#[derive(Debug, Default)]
+pub struct Cleanup;
+
+impl Drop for Cleanup {
+    fn drop(&mut self) {
+        let root = ROOT.lock().ok().and_then(|mut root| root.take());
+        if let Some(root) = root {
+            if private(&root.path, root.owner) {
+                shred_dir(&root.path);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_work_dir() {
+    let work_dir = WorkDir::new("test").unwrap();
+    let page = work_dir.path().join("page0001.png");
+    fs::write(&page, b"statement").unwrap();
+    fs::create_dir(work_dir.path().join("pages")).unwrap();
+    fs::write(work_dir.path().join("pages").join("page0002.png"), b"will").unwrap();
+    let path = work_dir.path().to_path_buf();
+    drop(work_dir);
+    assert!(!path.exists());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_links_are_not_followed() {
+    use std::os::unix::fs::{symlink, PermissionsExt};
+    let outside = tempdir::TempDir::new("filecabinet").unwrap();
+    let important = outside.path().join("important.pdf");
+    fs::write(&important, b"deed").unwrap();
+
+    let work_dir = WorkDir::new("test").unwrap();
+    symlink(&important, work_dir.path().join("page0001.png")).unwrap();
+    symlink(outside.path(), work_dir.path().join("pages")).unwrap();
+    drop(work_dir);
+    assert_eq!(fs::read(&important).unwrap(), b"deed");
+
+    // Planted by someone else, or at least not private: left alone.
+    let stale = std::env::temp_dir().join(format!("{}4000000000-test", PREFIX));
+    fs::create_dir(&stale).unwrap();
+    fs::set_permissions(&stale, fs::Permissions::from_mode(0o755)).unwrap();
+    symlink(&important, stale.join("x")).unwrap();
+    clean_up_stale();
+    assert!(stale.join("x").exists());
+
+    fs::set_permissions(&stale, fs::Permissions::from_mode(0o700)).unwrap();
+    clean_up_stale();
+    assert!(!stale.exists());
+    assert_eq!(fs::read(&important).unwrap(), b"deed");
+}
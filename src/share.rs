@@ -0,0 +1,416 @@
+use crate::utils;
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use iced_native::futures::channel::mpsc;
+use iced_native::futures::stream::{BoxStream, StreamExt};
+use iced_native::subscription::Recipe;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Largest document a phone may upload.
+const MAX_UPLOAD: usize = 100 * 1024 * 1024;
+
+/// Longest request line or header line read.
+const MAX_LINE: u64 = 8 * 1024;
+
+/// Connections handled at once, more are turned away.
+const MAX_CONNECTIONS: usize = 8;
+
+/// The page phones open. It uploads the picked files one by one, with the token of its
+/// own address.
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Share to cabinet</title>
+<style>
+body { font-family: sans-serif; margin: 2em; }
+label { display: block; padding: 1.5em; border: 2px dashed #888; text-align: center; }
+</style>
+</head>
+<body>
+<h1>Share to cabinet</h1>
+<label>Take a photo or pick documents
+<input id="files" type="file" accept="image/*,application/pdf" multiple hidden>
+</label>
+<p id="status"></p>
+<script>
+const token = new URLSearchParams(location.search).get("token") || "";
+const status = document.getElementById("status");
+document.getElementById("files").addEventListener("change", async (event) => {
+  for (const file of event.target.files) {
+    status.textContent = "Sending " + file.name + "...";
+    const response = await fetch("/upload?token=" + encodeURIComponent(token), {
+      method: "POST",
+      headers: { "X-Filename": encodeURIComponent(file.name) },
+      body: file,
+    });
+    status.textContent = await response.text();
+    if (!response.ok) return;
+  }
+  event.target.value = "";
+});
+</script>
+</body>
+</html>
+"#;
+
+/// A request as far as the share endpoint cares.
+#[derive(Debug, Default)]
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    /// Names in lower case.
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn parameter(&self, name: &str) -> Option<String> {
+        self.query.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            (key == name).then(|| utils::percent_decode(value))
+        })
+    }
+
+    /// The token of the query string, or of an `Authorization: Bearer` header.
+    fn token(&self) -> Option<String> {
+        self.parameter("token").or_else(|| {
+            self.header("authorization")?
+                .strip_prefix("Bearer ")
+                .map(str::to_string)
+        })
+    }
+
+    fn authorized(&self, token: &str) -> bool {
+        self.token().is_some_and(|given| {
+            ring::constant_time::verify_slices_are_equal(given.as_bytes(), token.as_bytes()).is_ok()
+        })
+    }
+}
+
+impl Response {
+    fn text(status: u16, text: &str) -> Response {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: text.as_bytes().to_vec(),
+        }
+    }
+
+    fn write(&self, stream: &mut impl Write) -> io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            201 => "Created",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            404 => "Not Found",
+            413 => "Payload Too Large",
+            415 => "Unsupported Media Type",
+            _ => "Internal Server Error",
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status,
+            reason,
+            self.content_type,
+            self.body.len()
+        )?;
+        stream.write_all(&self.body)?;
+        stream.flush()
+    }
+}
+
+/// Reads a line of at most `MAX_LINE` bytes into `line`.
+fn read_line(stream: &mut impl BufRead, line: &mut String) -> Result<(), u16> {
+    line.clear();
+    let read = stream.take(MAX_LINE).read_line(line).map_err(|_| 400u16)?;
+    if read as u64 == MAX_LINE && !line.ends_with('\n') {
+        return Err(400);
+    }
+    Ok(())
+}
+
+/// Reads a request, or returns the status to answer a malformed or oversized one with.
+/// The body is only read for requests carrying `token`, anyone else doesn't get to make
+/// the app hold an upload.
+fn read_request(stream: &mut impl BufRead, token: &str) -> Result<Request, u16> {
+    let mut line = String::new();
+    read_line(stream, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method, target),
+        _ => return Err(400),
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: query.to_string(),
+        ..Default::default()
+    };
+    loop {
+        read_line(stream, &mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if request.headers.len() >= 100 {
+            return Err(400);
+        }
+        let (name, value) = header.split_once(':').ok_or(400u16)?;
+        request
+            .headers
+            .push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+    if !request.authorized(token) {
+        return Ok(request);
+    }
+    let length: usize = match request.header("content-length") {
+        Some(length) => length.parse().map_err(|_| 400u16)?,
+        None => 0,
+    };
+    if length > MAX_UPLOAD {
+        return Err(413);
+    }
+    request.body = vec![0; length];
+    stream.read_exact(&mut request.body).map_err(|_| 400u16)?;
+    Ok(request)
+}
+
+/// Answers `request`, saving uploads into `dir`. Returns where an upload was saved.
+fn route(request: &Request, token: &str, dir: &Path) -> (Response, Option<PathBuf>) {
+    let authorized = request.authorized(token);
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") if authorized => (
+            Response {
+                status: 200,
+                content_type: "text/html; charset=utf-8",
+                body: PAGE.as_bytes().to_vec(),
+            },
+            None,
+        ),
+        ("GET", "/") | ("POST", "/upload") if !authorized => (
+            Response::text(401, "Open the link shown in the settings of filecabinet."),
+            None,
+        ),
+        ("POST", "/upload") => {
+            let filename = request
+                .header("x-filename")
+                .map(utils::percent_decode)
+                .unwrap_or_default();
+            // Only the name is kept, a phone doesn't get to pick the folder.
+            let filename = match Path::new(&filename).file_name() {
+                Some(filename) if utils::is_document(&filename.to_string_lossy()) => {
+                    filename.to_string_lossy().to_string()
+                }
+                _ => {
+                    return (
+                        Response::text(415, "Only PDFs and photos can be shared."),
+                        None,
+                    )
+                }
+            };
+            let path = utils::free_path(dir, &filename);
+            let saved = AtomicFile::new(&path, OverwriteBehavior::DisallowOverwrite)
+                .write(|f| f.write_all(&request.body));
+            match saved {
+                Ok(()) => {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    (
+                        Response::text(201, &format!("Saved as {}", name)),
+                        Some(path),
+                    )
+                }
+                Err(error) => {
+                    warn!(event = "share_save_failed", file = %path.display(), ?error);
+                    (Response::text(500, "Couldn't save the document."), None)
+                }
+            }
+        }
+        _ => (Response::text(404, "Not found"), None),
+    }
+}
+
+fn handle(stream: TcpStream, token: &str, dir: &Path) -> Option<PathBuf> {
+    stream.set_nonblocking(false).ok()?;
+    stream
+        .set_read_timeout(Some(Duration::from_secs(30)))
+        .ok()?;
+    let mut writer = stream.try_clone().ok()?;
+    let (response, saved) = match read_request(&mut BufReader::new(stream), token) {
+        Ok(request) => route(&request, token, dir),
+        Err(status) => (Response::text(status, "Bad request"), None),
+    };
+    if let Err(error) = response.write(&mut writer) {
+        warn!(event = "share_response_failed", ?error);
+    }
+    saved
+}
+
+/// Accepts connections until the subscription is dropped, which closes `sender`. Each
+/// connection is handled on a thread of its own, so a slow or idle client doesn't hold
+/// up the others.
+fn serve(
+    listener: TcpListener,
+    token: String,
+    dir: PathBuf,
+    sender: mpsc::UnboundedSender<String>,
+) {
+    let (token, dir) = (Arc::new(token), Arc::new(dir));
+    let connections = Arc::new(AtomicUsize::new(0));
+    while !sender.is_closed() {
+        match listener.accept() {
+            Ok((stream, address)) => {
+                if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                    warn!(event = "share_busy", from = %address);
+                    continue;
+                }
+                let (token, dir, sender) = (token.clone(), dir.clone(), sender.clone());
+                let connections = connections.clone();
+                thread::spawn(move || {
+                    if let Some(path) = handle(stream, &token, &dir) {
+                        info!(event = "ShareUpload", from = %address, file = %path.display());
+                        let _ = sender.unbounded_send(path.to_string_lossy().to_string());
+                    }
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(250))
+            }
+            Err(error) => warn!(event = "share_accept_failed", ?error),
+        }
+    }
+}
+
+/// Serves the upload page on every interface at `port`, saving what phones share into
+/// `dir`. Produces the path of every saved document.
+pub struct Share {
+    pub port: u16,
+    pub token: String,
+    pub dir: String,
+}
+
+impl<H, E> Recipe<H, E> for Share
+where
+    H: std::hash::Hasher,
+{
+    type Output = String;
+
+    fn hash(&self, state: &mut H) {
+        use std::hash::Hash;
+        struct Marker;
+        std::any::TypeId::of::<Marker>().hash(state);
+        self.port.hash(state);
+        self.token.hash(state);
+        self.dir.hash(state);
+    }
+
+    fn stream(self: Box<Self>, _input: BoxStream<'static, E>) -> BoxStream<'static, String> {
+        let (sender, receiver) = mpsc::unbounded();
+        // Polling for connections lets the thread notice the subscription ended.
+        let listener = TcpListener::bind(("0.0.0.0", self.port))
+            .and_then(|listener| listener.set_nonblocking(true).map(|()| listener));
+        match listener {
+            Ok(listener) => {
+                info!(event = "ShareStart", port = self.port, dir = %self.dir);
+                let Share { token, dir, .. } = *self;
+                thread::spawn(move || serve(listener, token, PathBuf::from(dir), sender));
+            }
+            Err(error) => warn!(event = "share_start_failed", port = self.port, ?error),
+        }
+        receiver.boxed()
+    }
+}
+
+/// The address of this machine on the local network, the one phones connect to.
+pub fn lan_address() -> Option<IpAddr> {
+    // Connecting a UDP socket sends nothing, it only picks the outgoing interface.
+    let socket = UdpSocket::bind(("0.0.0.0", 0)).ok()?;
+    socket.connect(("192.0.2.1", 80)).ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
+
+/// The link to open on a phone.
+pub fn link(port: u16, token: &str) -> String {
+    let host = lan_address().map_or("localhost".to_string(), |ip| ip.to_string());
+    format!("http://{}:{}/?token={}", host, port, token)
+}
+
+#[test]
+fn test_route() {
+    let dir = tempdir::TempDir::new("intake").unwrap();
+    let request = |raw: &[u8]| read_request(&mut BufReader::new(raw), "secret").unwrap();
+    let (page, _) = route(
+        &request(b"GET /?token=secret HTTP/1.1\r\n\r\n"),
+        "secret",
+        dir.path(),
+    );
+    assert_eq!(page.status, 200);
+    let (denied, _) = route(
+        &request(b"GET /?token=guess HTTP/1.1\r\n\r\n"),
+        "secret",
+        dir.path(),
+    );
+    assert_eq!(denied.status, 401);
+
+    let upload = request(
+        b"POST /upload HTTP/1.1\r\nAuthorization: Bearer secret\r\n\
+          X-Filename: ..%2F..%2Freceipt%20photo.jpg\r\nContent-Length: 5\r\n\r\nphoto",
+    );
+    let (created, saved) = route(&upload, "secret", dir.path());
+    assert_eq!(created.status, 201);
+    assert_eq!(saved, Some(dir.path().join("receipt photo.jpg")));
+    assert_eq!(
+        std::fs::read(dir.path().join("receipt photo.jpg")).unwrap(),
+        b"photo"
+    );
+    let (_, saved) = route(&upload, "secret", dir.path());
+    assert_eq!(saved, Some(dir.path().join("receipt photo_2.jpg")));
+
+    let script = request(
+        b"POST /upload?token=secret HTTP/1.1\r\nX-Filename: run.sh\r\nContent-Length: 2\r\n\r\nls",
+    );
+    assert_eq!(route(&script, "secret", dir.path()).0.status, 415);
+    assert!(read_request(
+        &mut BufReader::new(
+            &b"POST /upload?token=secret HTTP/1.1\r\nContent-Length: 999999999999\r\n\r\n"[..]
+        ),
+        "secret"
+    )
+    .is_err());
+
+    // Without the token the body isn't read, however long it claims to be.
+    let guess = request(b"POST /upload HTTP/1.1\r\nContent-Length: 99999999\r\n\r\nph");
+    assert!(guess.body.is_empty());
+    assert_eq!(route(&guess, "secret", dir.path()).0.status, 401);
+    let long = [&b"GET /"[..], &[b'a'; 10_000], b" HTTP/1.1\r\n\r\n"].concat();
+    assert_eq!(
+        read_request(&mut BufReader::new(&long[..]), "secret").unwrap_err(),
+        400
+    );
+}
@@ -0,0 +1,189 @@
+use crate::catalog::Catalog;
+use crate::{stats, utils};
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use chrono::Local;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Name of the page, the one a browser opens for a folder.
+pub const FILENAME: &str = "index.html";
+
+/// Where documents without a date or an institution in their name are listed.
+const UNDATED: &str = "Undated";
+const UNSORTED: &str = "Unsorted";
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum SiteError {
+    WriteError,
+}
+
+pub fn path(dir: &Path) -> PathBuf {
+    dir.join(FILENAME)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// One document of the page.
+struct Item {
+    filename: String,
+    date: Option<String>,
+    title: String,
+    page: Option<u32>,
+    size: u64,
+    tags: Vec<String>,
+}
+
+impl Item {
+    fn html(&self) -> String {
+        let mut details = Vec::new();
+        if let Some(date) = &self.date {
+            details.push(date.clone());
+        }
+        if let Some(page) = self.page {
+            details.push(format!("page {}", page));
+        }
+        details.push(stats::size(self.size));
+        details.extend(self.tags.iter().map(|tag| format!("#{}", tag)));
+        format!(
+            "<li><a href=\"{}\">{}</a> <span>{}</span></li>\n",
+            utils::percent_encode(&self.filename),
+            escape(&self.title),
+            escape(&details.join(" · "))
+        )
+    }
+}
+
+/// The page listing the documents of the cabinet at `dir` by year then institution, with
+/// links relative to the cabinet. Returns it with how many documents it lists.
+pub fn render(dir: &Path) -> (String, usize) {
+    let catalog = Catalog::load(dir);
+    let mut years: BTreeMap<String, BTreeMap<String, Vec<Item>>> = BTreeMap::new();
+    let filenames = utils::list_files(dir);
+    for filename in &filenames {
+        let fields = utils::OptDoc::new(filename);
+        let normalized = utils::is_normalized(filename);
+        let year = match (&fields.date, normalized) {
+            (Some(date), true) => date.get(..4).unwrap_or(UNDATED).to_string(),
+            _ => UNDATED.to_string(),
+        };
+        let institution = match (&fields.institution, normalized) {
+            (Some(institution), true) => institution.clone(),
+            _ => UNSORTED.to_string(),
+        };
+        let title = match (&fields.name, normalized) {
+            (Some(name), true) => name.clone(),
+            _ => filename.clone(),
+        };
+        let page = fields.page_number();
+        let item = Item {
+            filename: filename.clone(),
+            date: fields.date.filter(|_| normalized),
+            title,
+            page: page.filter(|_| normalized),
+            size: std::fs::metadata(dir.join(filename))
+                .map(|m| m.len())
+                .unwrap_or_default(),
+            tags: catalog
+                .get(filename)
+                .map(|entry| entry.tags.clone())
+                .unwrap_or_default(),
+        };
+        years
+            .entry(year)
+            .or_default()
+            .entry(institution)
+            .or_default()
+            .push(item);
+    }
+
+    // Latest years first, the undated ones last.
+    let mut order: Vec<&String> = years.keys().filter(|year| *year != UNDATED).collect();
+    order.reverse();
+    order.extend(years.keys().filter(|year| *year == UNDATED));
+
+    let name = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Cabinet".to_string());
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{name}</title>\n\
+         <style>\nbody {{ font-family: sans-serif; max-width: 800px; margin: 40px auto; \
+         color: #333; }}\nh2 {{ border-bottom: 1px solid #ccc; }}\nh3 {{ color: #666; }}\n\
+         li span {{ color: #999; font-size: small; }}\nnav a {{ margin-right: 10px; }}\n\
+         </style>\n</head>\n<body>\n<h1>{name}</h1>\n<p>{count} documents, listed on {now}.</p>\n",
+        name = escape(&name),
+        count = filenames.len(),
+        now = Local::now().format("%Y-%m-%d"),
+    );
+    html.push_str("<nav>");
+    for year in &order {
+        html.push_str(&format!("<a href=\"#{0}\">{0}</a>", escape(year)));
+    }
+    html.push_str("</nav>\n");
+    for year in order {
+        html.push_str(&format!("<h2 id=\"{0}\">{0}</h2>\n", escape(year)));
+        for (institution, items) in &years[year] {
+            html.push_str(&format!("<h3>{}</h3>\n<ul>\n", escape(institution)));
+            for item in items {
+                html.push_str(&item.html());
+            }
+            html.push_str("</ul>\n");
+        }
+    }
+    html.push_str("</body>\n</html>\n");
+    (html, filenames.len())
+}
+
+/// Writes the `index.html` of the cabinet at `dir`, which any browser opens without the
+/// app, e.g. from a backup drive. Returns how many documents it lists.
+pub fn write(dir: &Path) -> Result<usize, SiteError> {
+    let (html, listed) = render(dir);
+    AtomicFile::new(path(dir), OverwriteBehavior::AllowOverwrite)
+        .write(|f| f.write_all(html.as_bytes()))
+        .map_err(|_| SiteError::WriteError)?;
+    Ok(listed)
+}
+
+pub async fn export(dir: String) -> Result<usize, SiteError> {
+    let listed = write(Path::new(&dir))?;
+    info!(event = "WriteSite", dir = %dir, listed);
+    Ok(listed)
+}
+
+#[test]
+fn test_site() {
+    let dir = tempdir::TempDir::new("site").unwrap();
+    let dir = dir.path();
+    for filename in [
+        "2020-05-01_Bank_Statement_1.pdf",
+        "2021-03-04_Bank_Statement_1.pdf",
+        "2021-03-10_Power & Light_Bill_2.pdf",
+        "scan 0001.jpg",
+    ] {
+        std::fs::write(dir.join(filename), b"document").unwrap();
+    }
+    let mut catalog = Catalog::default();
+    catalog.entry("2021-03-04_Bank_Statement_1.pdf").tags = vec!["taxes".to_string()];
+    catalog.save(dir).unwrap();
+
+    assert_eq!(write(dir).unwrap(), 4);
+    let html = std::fs::read_to_string(path(dir)).unwrap();
+    let position = |text: &str| html.find(text).unwrap_or_else(|| panic!("{}", text));
+    assert!(position("<h2 id=\"2021\">") < position("<h2 id=\"2020\">"));
+    assert!(position("<h2 id=\"2020\">") < position("<h2 id=\"Undated\">"));
+    assert!(html.contains("<h3>Power &amp; Light</h3>"));
+    assert!(html.contains(
+        "<a href=\"2021-03-04_Bank_Statement_1.pdf\">Statement</a> \
+         <span>2021-03-04 · page 1 · 8 B · #taxes</span>"
+    ));
+    assert!(html.contains("<a href=\"scan%200001.jpg\">scan 0001.jpg</a>"));
+    assert!(!html.contains(FILENAME));
+}
@@ -0,0 +1,185 @@
+use crate::catalog::{self, Catalog};
+use crate::trash;
+use crate::utils::{self, OptDoc};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Utc};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// How many of the largest files are listed.
+const LARGEST: usize = 10;
+
+/// Documents not previewed nor exported for this long are listed as unused.
+pub const IDLE_YEARS: i64 = 5;
+
+/// Files and bytes of a folder or a year, subfolders not included.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Totals {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Where the space of a cabinet goes, to decide what to archive.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    /// Folders by their path relative to the cabinet, `.` for the cabinet itself.
+    pub folders: BTreeMap<String, Totals>,
+    /// Documents of the cabinet by the year in their filename, `None` for undated ones.
+    pub years: BTreeMap<Option<i32>, Totals>,
+    /// Paths relative to the cabinet and sizes, largest first.
+    pub largest: Vec<(String, u64)>,
+    /// Documents not used for `IDLE_YEARS`, with the day they last were and their size,
+    /// longest unused first.
+    pub idle: Vec<(String, NaiveDate, u64)>,
+    /// Deleted documents waiting in the trash, see `trash`.
+    pub trash: Totals,
+}
+
+impl Totals {
+    fn add(&mut self, bytes: u64) {
+        self.files += 1;
+        self.bytes += bytes;
+    }
+}
+
+/// Walks the cabinet at `dir` and its subfolders. Symlinks aren't followed.
+pub fn compute(dir: &Path) -> Stats {
+    let mut stats = Stats::default();
+    let mut folders = vec![dir.to_path_buf()];
+    while let Some(folder) = folders.pop() {
+        let relative = match folder.strip_prefix(dir) {
+            Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+            Ok(relative) => relative.to_string_lossy().to_string(),
+            Err(_) => continue,
+        };
+        let totals = stats.folders.entry(relative).or_default();
+        for entry in fs::read_dir(&folder).into_iter().flatten().flatten() {
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            if metadata.is_dir() {
+                folders.push(entry.path());
+            } else if metadata.is_file() {
+                totals.add(metadata.len());
+                let path = entry.path();
+                let relative = path.strip_prefix(dir).unwrap_or(&path);
+                stats
+                    .largest
+                    .push((relative.to_string_lossy().to_string(), metadata.len()));
+            }
+        }
+    }
+    stats
+        .largest
+        .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    stats.largest.truncate(LARGEST);
+    for filename in utils::list_files(dir) {
+        let path = dir.join(&filename);
+        let year = OptDoc::new(&path).day().map(|date| date.year());
+        let bytes = fs::metadata(&path).map(|m| m.len()).unwrap_or_default();
+        stats.years.entry(year).or_default().add(bytes);
+    }
+    let cutoff = Utc::now().naive_utc() - Duration::days(IDLE_YEARS * 365);
+    stats.idle = idle(dir, &Catalog::load(dir), cutoff);
+    stats.trash = trash::totals(dir);
+    stats
+}
+
+/// The documents of the cabinet at `dir` last previewed, exported or changed before
+/// `cutoff`. Documents never previewed count from when their file last changed, so new
+/// ones aren't taken for forgotten.
+pub fn idle(dir: &Path, catalog: &Catalog, cutoff: NaiveDateTime) -> Vec<(String, NaiveDate, u64)> {
+    let parse = |time: &Option<String>| {
+        NaiveDateTime::parse_from_str(time.as_deref()?, catalog::VIEWED_FORMAT).ok()
+    };
+    let mut idle: Vec<(String, NaiveDate, u64)> = utils::list_files(dir)
+        .into_iter()
+        .filter_map(|filename| {
+            let metadata = fs::metadata(dir.join(&filename)).ok()?;
+            let modified = metadata
+                .modified()
+                .ok()
+                .map(|modified| DateTime::<Utc>::from(modified).naive_utc());
+            let entry = catalog.get(&filename);
+            let last_used = [
+                modified,
+                entry.and_then(|entry| parse(&entry.viewed)),
+                entry.and_then(|entry| parse(&entry.exported)),
+            ]
+            .iter()
+            .flatten()
+            .max()
+            .copied()?;
+            (last_used < cutoff).then(|| (filename, last_used.date(), metadata.len()))
+        })
+        .collect();
+    idle.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+    idle
+}
+
+pub async fn collect(dir: String) -> (String, Stats) {
+    let stats = compute(Path::new(&dir));
+    (dir, stats)
+}
+
+/// `bytes` in the largest unit that keeps it above 1, e.g. `1.5 MB`.
+pub fn size(bytes: u64) -> String {
+    let units = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1000.0;
+    for unit in &units[..units.len() - 1] {
+        if value < 1000.0 {
+            return format!("{:.1} {}", value, unit);
+        }
+        value /= 1000.0;
+    }
+    format!("{:.1} {}", value, units[units.len() - 1])
+}
+
+#[test]
+fn test_compute() {
+    let dir = tempdir::TempDir::new("stats").unwrap();
+    let dir = dir.path();
+    fs::write(dir.join("2020-03-04_Bank_Statement_1.pdf"), vec![0; 300]).unwrap();
+    fs::write(dir.join("2021-03-04_Bank_Statement_1.pdf"), vec![0; 100]).unwrap();
+    fs::write(dir.join("scan.pdf"), vec![0; 50]).unwrap();
+    fs::create_dir(dir.join("packets")).unwrap();
+    fs::write(dir.join("packets").join("TaxPacket_2020.pdf"), vec![0; 400]).unwrap();
+    let stats = compute(dir);
+    assert_eq!(
+        stats.folders["."],
+        Totals {
+            files: 3,
+            bytes: 450
+        }
+    );
+    assert_eq!(stats.folders["packets"].bytes, 400);
+    assert_eq!(stats.years[&Some(2020)].bytes, 300);
+    assert_eq!(stats.years[&None].files, 1);
+    assert_eq!(
+        stats.largest[0],
+        (
+            Path::new("packets")
+                .join("TaxPacket_2020.pdf")
+                .to_string_lossy()
+                .to_string(),
+            400
+        )
+    );
+    assert!(stats.idle.is_empty());
+
+    // Against a cutoff to come, only what is used after it isn't idle.
+    let mut catalog = Catalog::default();
+    catalog.entry("scan.pdf").viewed = Some("2999-01-01 00:00:00".to_string());
+    catalog.entry("2020-03-04_Bank_Statement_1.pdf").exported =
+        Some("2000-01-01 00:00:00".to_string());
+    let cutoff = Utc::now().naive_utc() + Duration::days(1);
+    let idle = idle(dir, &catalog, cutoff);
+    assert_eq!(idle.len(), 2);
+    assert!(idle.iter().all(|(filename, _, _)| filename != "scan.pdf"));
+    assert_eq!(size(999), "999 B");
+    assert_eq!(size(1_500_000), "1.5 MB");
+}
@@ -0,0 +1,293 @@
+use crate::{Document, DocumentId, Filter, SearchResults};
+use chrono::NaiveDate;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::{Bound, RangeBounds};
+
+/// The documents of the cabinet in the order they were listed, indexed so that finding one
+/// by id, path or filename, the ticked ones or the ones of some days doesn't go through
+/// all of them. Changes go through [`Docs::update`] so the indexes follow renames and
+/// ticks.
+#[derive(Debug, Default)]
+pub struct Docs {
+    docs: Vec<Document>,
+    by_id: HashMap<DocumentId, usize>,
+    by_path: HashMap<String, usize>,
+    /// The documents all come from the cabinet's folder, so their filenames are unique.
+    by_filename: HashMap<String, usize>,
+    /// By the date of the filename, the undated ones first.
+    by_date: BTreeSet<(Option<NaiveDate>, usize)>,
+    selected: BTreeSet<usize>,
+}
+
+impl From<Vec<Document>> for Docs {
+    fn from(docs: Vec<Document>) -> Docs {
+        let mut store = Docs {
+            docs,
+            ..Docs::default()
+        };
+        store.reindex();
+        store
+    }
+}
+
+impl Docs {
+    fn index(&mut self, i: usize) {
+        let doc = &self.docs[i];
+        self.by_id.insert(doc.id, i);
+        self.by_path.insert(doc.path.clone(), i);
+        self.by_filename.insert(doc.filename.clone(), i);
+        self.by_date.insert((doc.date, i));
+        if doc.selected {
+            self.selected.insert(i);
+        }
+    }
+
+    fn unindex(&mut self, i: usize) {
+        let doc = &self.docs[i];
+        self.by_id.remove(&doc.id);
+        // Another document may have taken the path over since.
+        if self.by_path.get(&doc.path) == Some(&i) {
+            self.by_path.remove(&doc.path);
+        }
+        if self.by_filename.get(&doc.filename) == Some(&i) {
+            self.by_filename.remove(&doc.filename);
+        }
+        self.by_date.remove(&(doc.date, i));
+        self.selected.remove(&i);
+    }
+
+    fn reindex(&mut self) {
+        self.by_id.clear();
+        self.by_path.clear();
+        self.by_filename.clear();
+        self.by_date.clear();
+        self.selected.clear();
+        for i in 0..self.docs.len() {
+            self.index(i);
+        }
+    }
+
+    /// Empties the store, giving back the documents in order, e.g. to list them again.
+    pub fn take(&mut self) -> Vec<Document> {
+        std::mem::take(self).docs
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Document> {
+        self.docs.iter()
+    }
+
+    pub fn get(&self, id: DocumentId) -> Option<&Document> {
+        Some(&self.docs[*self.by_id.get(&id)?])
+    }
+
+    pub fn find(&self, path: &str) -> Option<&Document> {
+        Some(&self.docs[*self.by_path.get(path)?])
+    }
+
+    /// The ticked documents, in order.
+    pub fn selected(&self) -> impl Iterator<Item = &Document> {
+        self.selected.iter().map(move |&i| &self.docs[i])
+    }
+
+    /// The documents whose filename has a date in `days`, by date.
+    pub fn dated(&self, days: impl RangeBounds<NaiveDate>) -> impl Iterator<Item = &Document> {
+        // The undated documents come first, they are left out by starting after them.
+        let start = match days.start_bound() {
+            Bound::Included(day) => Bound::Included((Some(*day), 0)),
+            Bound::Excluded(day) => Bound::Excluded((Some(*day), usize::MAX)),
+            Bound::Unbounded => Bound::Excluded((None, usize::MAX)),
+        };
+        let end = match days.end_bound() {
+            Bound::Included(day) => Bound::Included((Some(*day), usize::MAX)),
+            Bound::Excluded(day) => Bound::Excluded((Some(*day), 0)),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        // BTreeSet::range panics on a range ending before it starts.
+        let empty = match (&start, &end) {
+            (Bound::Excluded(start), Bound::Excluded(end)) => start >= end,
+            (Bound::Included(start) | Bound::Excluded(start), Bound::Included(end))
+            | (Bound::Included(start), Bound::Excluded(end)) => start > end,
+            _ => false,
+        };
+        (!empty)
+            .then(|| self.by_date.range((start, end)))
+            .into_iter()
+            .flatten()
+            .map(move |&(_, i)| &self.docs[i])
+    }
+
+    /// Where the documents listed under `filter` are, in the order they were listed, only
+    /// the ones of `owner` if given. Only the ones found are listed while searching, they
+    /// are looked up by filename rather than going through all the documents.
+    fn matching_indexes(
+        &self,
+        filter: Filter,
+        owner: Option<&str>,
+        results: Option<&SearchResults>,
+    ) -> Vec<usize> {
+        let candidates: Vec<usize> = match results {
+            Some(results) => {
+                let mut found: Vec<usize> = results
+                    .found
+                    .keys()
+                    .filter_map(|filename| self.by_filename.get(filename).copied())
+                    .collect();
+                found.sort_unstable();
+                found
+            }
+            None => (0..self.docs.len()).collect(),
+        };
+        candidates
+            .into_iter()
+            .filter(|&i| filter.matches(&self.docs[i]))
+            .filter(|&i| owner.is_none() || self.docs[i].owner.as_deref() == owner)
+            .collect()
+    }
+
+    /// As [`Docs::matching_indexes`], in the filter's order.
+    fn listed_indexes(
+        &self,
+        filter: Filter,
+        owner: Option<&str>,
+        results: Option<&SearchResults>,
+    ) -> Vec<usize> {
+        let mut listed = self.matching_indexes(filter, owner, results);
+        listed.sort_by(|&a, &b| filter.order(&self.docs[a], &self.docs[b]));
+        listed
+    }
+
+    /// The documents listed under `filter` in the order they were listed, e.g. to count
+    /// them.
+    pub fn matching(
+        &self,
+        filter: Filter,
+        owner: Option<&str>,
+        results: Option<&SearchResults>,
+    ) -> impl Iterator<Item = &Document> {
+        self.matching_indexes(filter, owner, results)
+            .into_iter()
+            .map(move |i| &self.docs[i])
+    }
+
+    /// The documents listed under `filter`, see [`Docs::listed_mut`].
+    pub fn listed(
+        &self,
+        filter: Filter,
+        owner: Option<&str>,
+        results: Option<&SearchResults>,
+    ) -> impl Iterator<Item = &Document> {
+        self.listed_indexes(filter, owner, results)
+            .into_iter()
+            .map(move |i| &self.docs[i])
+    }
+
+    /// The documents listed under `filter` with their index, in the filter's order, only
+    /// the ones of `owner` if given. Only the ones found are listed while searching. They
+    /// are handed out to draw them, which only changes the state of their widgets. What
+    /// the indexes are built from changes through [`Docs::update`].
+    pub fn listed_mut(
+        &mut self,
+        filter: Filter,
+        owner: Option<&str>,
+        results: Option<&SearchResults>,
+    ) -> Vec<(usize, &mut Document)> {
+        let listed = self.listed_indexes(filter, owner, results);
+        let mut docs: Vec<Option<&mut Document>> = self.docs.iter_mut().map(Some).collect();
+        listed
+            .into_iter()
+            .filter_map(|i| docs[i].take().map(|doc| (i, doc)))
+            .collect()
+    }
+
+    /// Changes the document `id` with `change`, `None` if it isn't listed.
+    pub fn update<T>(
+        &mut self,
+        id: DocumentId,
+        change: impl FnOnce(&mut Document) -> T,
+    ) -> Option<T> {
+        let i = *self.by_id.get(&id)?;
+        self.unindex(i);
+        let changed = change(&mut self.docs[i]);
+        self.index(i);
+        Some(changed)
+    }
+
+    /// Changes every document with `change`.
+    pub fn update_all(&mut self, change: impl FnMut(&mut Document)) {
+        self.docs.iter_mut().for_each(change);
+        self.reindex();
+    }
+
+    pub fn remove(&mut self, id: DocumentId) -> Option<Document> {
+        let i = *self.by_id.get(&id)?;
+        let doc = self.docs.remove(i);
+        self.reindex();
+        Some(doc)
+    }
+}
+
+#[test]
+fn test_docs() {
+    let doc = |path: &str| Document::new(path.to_string());
+    let mut docs = Docs::from(vec![
+        doc("/cabinet/2021-03-04_Chase_Statement_1.pdf"),
+        doc("/cabinet/scan0001.pdf"),
+        doc("/cabinet/2020-01-02_Chase_Statement_1.pdf"),
+    ]);
+    let scan = docs.find("/cabinet/scan0001.pdf").unwrap().id;
+    assert_eq!(docs.get(scan).unwrap().filename, "scan0001.pdf");
+
+    // Renaming moves the document in the path and date indexes.
+    docs.update(scan, |doc| {
+        doc.path = "/cabinet/2021-03-04_Chase_Statement_2.pdf".to_string();
+        doc.date = NaiveDate::from_ymd_opt(2021, 3, 4);
+        doc.selected = true;
+    });
+    assert!(docs.find("/cabinet/scan0001.pdf").is_none());
+    assert_eq!(
+        docs.find("/cabinet/2021-03-04_Chase_Statement_2.pdf")
+            .unwrap()
+            .id,
+        scan
+    );
+    let march = NaiveDate::from_ymd_opt(2021, 3, 1).unwrap()..;
+    assert_eq!(docs.dated(march).count(), 2);
+    let (first_day, fourth) = (
+        NaiveDate::from_ymd_opt(2020, 1, 2).unwrap(),
+        NaiveDate::from_ymd_opt(2021, 3, 4).unwrap(),
+    );
+    assert_eq!(docs.dated(..).count(), 3);
+    assert_eq!(docs.dated(first_day..fourth).count(), 1);
+    assert_eq!(docs.dated(first_day..=fourth).count(), 3);
+    assert_eq!(docs.dated(fourth..first_day).count(), 0);
+    assert_eq!(
+        docs.dated((Bound::Excluded(fourth), Bound::Excluded(fourth)))
+            .count(),
+        0
+    );
+    let favorite = docs.iter().next().unwrap().id;
+    docs.update(favorite, |doc| doc.favorite = true);
+    assert_eq!(
+        docs.listed(Filter::Favorites, None, None)
+            .map(|doc| doc.id)
+            .collect::<Vec<_>>(),
+        [favorite]
+    );
+    let results = SearchResults {
+        query: "chase".to_string(),
+        found: std::iter::once(("2020-01-02_Chase_Statement_1.pdf".to_string(), None)).collect(),
+    };
+    assert_eq!(docs.listed_mut(Filter::All, None, Some(&results)).len(), 1);
+    assert_eq!(
+        docs.selected().map(|doc| doc.id).collect::<Vec<_>>(),
+        [scan]
+    );
+
+    let first = docs.iter().next().unwrap().id;
+    assert!(docs.remove(first).is_some());
+    assert_eq!(docs.iter().count(), 2);
+    assert_eq!(docs.selected().next().unwrap().id, scan);
+    assert!(docs.update(first, |_| ()).is_none());
+    docs.update_all(|doc| doc.selected = false);
+    assert_eq!(docs.selected().count(), 0);
+}
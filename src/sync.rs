@@ -0,0 +1,344 @@
+use crate::catalog::Catalog;
+use crate::utils;
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::time::SystemTime;
+use tracing::info;
+
+/// Filenames and the SHA-256 of their content.
+pub type Files = BTreeMap<String, String>;
+
+/// The open cabinet, or the one it is synced with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Local,
+    Remote,
+}
+
+/// What syncing does to bring both cabinets to the same files.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// Added or edited on one side, copied over to the other.
+    Copy { from: Side, filename: String },
+    /// Renamed on one side, renamed the same way on the other.
+    Rename { on: Side, old: String, new: String },
+    /// Deleted on one side, deleted on the other as well.
+    Delete { on: Side, filename: String },
+    /// Changed differently on both sides, the version of `keep` wins.
+    Conflict { filename: String, keep: Side },
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum SyncError {
+    DirectoryError,
+    WriteError,
+}
+
+/// The files both cabinets had after each sync, to tell deletions from additions.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncState {
+    /// The cabinet synced with last.
+    last: Option<String>,
+    /// Files in common after the last sync, per cabinet synced with.
+    synced: BTreeMap<String, Files>,
+}
+
+impl Side {
+    fn other(self) -> Side {
+        match self {
+            Side::Local => Side::Remote,
+            Side::Remote => Side::Local,
+        }
+    }
+}
+
+impl SyncState {
+    fn path(dir: &Path) -> std::path::PathBuf {
+        dir.join(".filecabinet").join("sync.json")
+    }
+
+    fn load(dir: &Path) -> SyncState {
+        fs::read_to_string(Self::path(dir))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, dir: &Path) -> Result<(), SyncError> {
+        let path = Self::path(dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|_| SyncError::WriteError)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|_| SyncError::WriteError)?;
+        AtomicFile::new(&path, OverwriteBehavior::AllowOverwrite)
+            .write(|f| f.write_all(json.as_bytes()))
+            .map_err(|_| SyncError::WriteError)
+    }
+}
+
+/// The cabinet `dir` was last synced with.
+pub fn last_remote(dir: &str) -> Option<String> {
+    SyncState::load(Path::new(dir)).last
+}
+
+fn files(dir: &Path) -> Files {
+    utils::list_files(dir)
+        .into_iter()
+        .filter_map(|filename| {
+            let sha256 = utils::sha256(&dir.join(&filename))?;
+            Some((filename, sha256))
+        })
+        .collect()
+}
+
+/// Reconciles two listings with the one of the last sync, `base`. A file missing from
+/// one side whose content shows up there under a new name was renamed, one that only
+/// changed on one side is copied or deleted, and one that changed on both is a conflict.
+pub fn changes(local: &Files, remote: &Files, base: &Files) -> Vec<Change> {
+    let side = |side: Side| match side {
+        Side::Local => local,
+        Side::Remote => remote,
+    };
+    let mut changes = Vec::new();
+    let mut handled = BTreeSet::new();
+    for on in [Side::Local, Side::Remote] {
+        let (files, other) = (side(on), side(on.other()));
+        for (old, sha256) in base {
+            if files.contains_key(old) || handled.contains(old) {
+                continue;
+            }
+            let new = files
+                .iter()
+                .find(|(new, content)| *content == sha256 && !base.contains_key(*new))
+                .map(|(new, _)| new.clone());
+            let new = match new {
+                Some(new) if !handled.contains(&new) => new,
+                _ => continue,
+            };
+            handled.insert(old.clone());
+            handled.insert(new.clone());
+            if other.get(old) == Some(sha256) && !other.contains_key(&new) {
+                changes.push(Change::Rename {
+                    on,
+                    old: old.clone(),
+                    new,
+                });
+            } else if other.get(&new) != Some(sha256) {
+                changes.push(Change::Conflict {
+                    filename: new,
+                    keep: on,
+                });
+            }
+        }
+    }
+    let filenames: BTreeSet<&String> = local
+        .keys()
+        .chain(remote.keys())
+        .chain(base.keys())
+        .collect();
+    for filename in filenames {
+        if handled.contains(filename) {
+            continue;
+        }
+        let (l, r, b) = (
+            local.get(filename),
+            remote.get(filename),
+            base.get(filename),
+        );
+        let filename = filename.clone();
+        let change = if l == r {
+            continue;
+        } else if l == b {
+            match r {
+                Some(_) => Change::Copy {
+                    from: Side::Remote,
+                    filename,
+                },
+                None => Change::Delete {
+                    on: Side::Remote,
+                    filename,
+                },
+            }
+        } else if r == b {
+            match l {
+                Some(_) => Change::Copy {
+                    from: Side::Local,
+                    filename,
+                },
+                None => Change::Delete {
+                    on: Side::Local,
+                    filename,
+                },
+            }
+        } else {
+            Change::Conflict {
+                filename,
+                keep: Side::Local,
+            }
+        };
+        changes.push(change);
+    }
+    changes
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// What syncing `local` with `remote` would change. Conflicts keep the most recently
+/// modified version by default.
+pub async fn plan(local: String, remote: String) -> Result<Vec<Change>, SyncError> {
+    let (local, remote) = (Path::new(&local), Path::new(&remote));
+    if !local.is_dir() || !remote.is_dir() || local == remote {
+        return Err(SyncError::DirectoryError);
+    }
+    let state = SyncState::load(local);
+    let base = state
+        .synced
+        .get(&remote.to_string_lossy().to_string())
+        .cloned()
+        .unwrap_or_default();
+    let mut changes = changes(&files(local), &files(remote), &base);
+    for change in changes.iter_mut() {
+        if let Change::Conflict { filename, keep } = change {
+            if modified(&remote.join(&*filename)) > modified(&local.join(&*filename)) {
+                *keep = Side::Remote;
+            }
+        }
+    }
+    Ok(changes)
+}
+
+/// Copies `filename` from `from_dir` over the one in `to_dir`, catalog entry included.
+fn copy(from_dir: &Path, to_dir: &Path, filename: &str) -> Result<(), SyncError> {
+    fs::copy(from_dir.join(filename), to_dir.join(filename)).map_err(|_| SyncError::WriteError)?;
+    let entry = Catalog::load(from_dir).get(filename).cloned();
+    let mut catalog = Catalog::load(to_dir);
+    match entry {
+        Some(entry) => *catalog.entry(filename) = entry,
+        None => {
+            catalog.remove(filename);
+        }
+    }
+    catalog.save(to_dir).map_err(|_| SyncError::WriteError)
+}
+
+fn delete(dir: &Path, filename: &str) -> Result<(), SyncError> {
+    fs::remove_file(dir.join(filename)).map_err(|_| SyncError::WriteError)?;
+    let mut catalog = Catalog::load(dir);
+    catalog.remove(filename);
+    catalog.save(dir).map_err(|_| SyncError::WriteError)
+}
+
+fn rename(dir: &Path, old: &str, new: &str) -> Result<(), SyncError> {
+    if dir.join(new).exists() {
+        return Err(SyncError::WriteError);
+    }
+    fs::rename(dir.join(old), dir.join(new)).map_err(|_| SyncError::WriteError)?;
+    let mut catalog = Catalog::load(dir);
+    catalog.rename(old, new);
+    catalog.save(dir).map_err(|_| SyncError::WriteError)
+}
+
+/// Applies the reviewed `changes` and remembers the files both cabinets now have in
+/// common. Returns how many changes were applied.
+pub async fn apply(
+    local: String,
+    remote: String,
+    changes: Vec<Change>,
+) -> Result<usize, SyncError> {
+    let (local, remote) = (Path::new(&local), Path::new(&remote));
+    let dir = |side: Side| match side {
+        Side::Local => local,
+        Side::Remote => remote,
+    };
+    for change in &changes {
+        match change {
+            Change::Copy { from, filename } => copy(dir(*from), dir(from.other()), filename)?,
+            Change::Rename { on, old, new } => rename(dir(on.other()), old, new)?,
+            Change::Delete { on, filename } => delete(dir(on.other()), filename)?,
+            Change::Conflict { filename, keep } => {
+                if dir(*keep).join(filename).exists() {
+                    copy(dir(*keep), dir(keep.other()), filename)?
+                } else {
+                    delete(dir(keep.other()), filename)?
+                }
+            }
+        }
+        info!(event = "Sync", ?change);
+    }
+    // Files left out still differ, their previous state is kept to compare with.
+    let remote_key = remote.to_string_lossy().to_string();
+    let mut state = SyncState::load(local);
+    let base = state.synced.remove(&remote_key).unwrap_or_default();
+    let (local_files, remote_files) = (files(local), files(remote));
+    let synced = local_files
+        .keys()
+        .chain(remote_files.keys())
+        .chain(base.keys())
+        .filter_map(|filename| {
+            let sha256 = match (local_files.get(filename), remote_files.get(filename)) {
+                (Some(l), Some(r)) if l == r => l,
+                (None, None) => return None,
+                _ => base.get(filename)?,
+            };
+            Some((filename.clone(), sha256.clone()))
+        })
+        .collect();
+    state.synced.insert(remote_key.clone(), synced);
+    state.last = Some(remote_key);
+    state.save(local)?;
+    Ok(changes.len())
+}
+
+#[test]
+fn test_changes() {
+    let files = |files: &[(&str, &str)]| -> Files {
+        files
+            .iter()
+            .map(|(f, h)| (f.to_string(), h.to_string()))
+            .collect()
+    };
+    let base = files(&[
+        ("a.pdf", "1"),
+        ("b.pdf", "2"),
+        ("c.pdf", "3"),
+        ("d.pdf", "4"),
+    ]);
+    let local = files(&[
+        ("a.pdf", "1"),
+        ("b2.pdf", "2"),
+        ("c.pdf", "3"),
+        ("d.pdf", "5"),
+        ("e.pdf", "6"),
+    ]);
+    let remote = files(&[("a.pdf", "1"), ("b.pdf", "2"), ("d.pdf", "7")]);
+    let changes = changes(&local, &remote, &base);
+    assert_eq!(
+        changes,
+        vec![
+            Change::Rename {
+                on: Side::Local,
+                old: "b.pdf".to_string(),
+                new: "b2.pdf".to_string()
+            },
+            Change::Delete {
+                on: Side::Remote,
+                filename: "c.pdf".to_string()
+            },
+            Change::Conflict {
+                filename: "d.pdf".to_string(),
+                keep: Side::Local
+            },
+            Change::Copy {
+                from: Side::Local,
+                filename: "e.pdf".to_string()
+            },
+        ]
+    );
+}
@@ -0,0 +1,273 @@
+use crate::catalog::{Catalog, CatalogError};
+use crate::events::{self, Event};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use tracing::info;
+
+/// The tags of documents before a batch edit, so the edit can be undone as a whole.
+#[derive(Debug, Clone, Default)]
+pub struct TagEdit {
+    before: BTreeMap<String, Vec<String>>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl TagEdit {
+    pub fn len(&self) -> usize {
+        self.before.len()
+    }
+}
+
+/// `tag` without extra whitespace or empty levels, e.g. `finance/tax` for
+/// ` finance / /tax `. Tags nest with slashes.
+pub fn normalize(tag: &str) -> String {
+    tag.split('/')
+        .map(|level| level.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|level| !level.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Tags typed as a comma separated list, normalized and without duplicates.
+pub fn parse(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input.split(',') {
+        let tag = normalize(tag);
+        if !tag.is_empty() && !tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// Adds the ones of `add` that `tags` doesn't have yet, ignoring case, and keeps
+/// them sorted. Returns whether any was added.
+pub fn merge(tags: &mut Vec<String>, add: &[String]) -> bool {
+    let before = tags.len();
+    for tag in add {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.clone());
+        }
+    }
+    tags.sort();
+    tags.len() > before
+}
+
+/// Whether `tag` is `ancestor` or nested below it.
+fn is_within(tag: &str, ancestor: &str) -> bool {
+    tag.strip_prefix(ancestor)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Every tag of the cabinet with the levels above it, in tree order, with how many
+/// documents are tagged with it or a tag nested below it.
+pub fn tree(catalog: &Catalog) -> Vec<(String, usize)> {
+    let mut counts: BTreeMap<Vec<String>, usize> = BTreeMap::new();
+    for entry in catalog.entries.values() {
+        let mut levels: BTreeSet<Vec<String>> = BTreeSet::new();
+        for tag in &entry.tags {
+            let path: Vec<String> = tag.split('/').map(str::to_string).collect();
+            for depth in 1..=path.len() {
+                levels.insert(path[..depth].to_vec());
+            }
+        }
+        for level in levels {
+            *counts.entry(level).or_default() += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .map(|(path, count)| (path.join("/"), count))
+        .collect()
+}
+
+/// Applies `change` to the tags of every document of the cabinet at `dir`, keeping them
+/// sorted and without duplicates. Returns how many documents changed.
+fn retag(dir: &Path, change: impl Fn(&str) -> Option<String>) -> Result<usize, CatalogError> {
+    let mut catalog = Catalog::load(dir);
+    let mut tagged = Vec::new();
+    for (filename, entry) in catalog.entries.iter_mut() {
+        if !entry
+            .tags
+            .iter()
+            .any(|tag| change(tag).as_ref() != Some(tag))
+        {
+            continue;
+        }
+        let mut tags: Vec<String> = Vec::new();
+        for tag in entry.tags.iter().filter_map(|tag| change(tag)) {
+            if !tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+                tags.push(tag);
+            }
+        }
+        tags.sort();
+        entry.tags = tags.clone();
+        tagged.push(Event::Tagged {
+            filename: filename.clone(),
+            tags,
+        });
+    }
+    catalog.save(dir)?;
+    events::emit(dir, &tagged);
+    Ok(tagged.len())
+}
+
+/// Renames `from` and the tags nested below it to `to` on every document of the cabinet
+/// at `dir`. Renaming to a tag that is in use already merges them. Returns how many
+/// documents changed.
+pub fn rename(dir: &Path, from: &str, to: &str) -> Result<usize, CatalogError> {
+    let to = normalize(to);
+    let changed = retag(dir, |tag| {
+        Some(match tag.strip_prefix(from) {
+            Some(rest) if is_within(tag, from) => format!("{}{}", to, rest),
+            _ => tag.to_string(),
+        })
+    })?;
+    info!(event = "RenameTag", from = %from, to = %to, documents = changed);
+    Ok(changed)
+}
+
+/// Removes `tag` and the tags nested below it from every document of the cabinet at
+/// `dir`. Returns how many documents changed.
+pub fn delete(dir: &Path, tag: &str) -> Result<usize, CatalogError> {
+    let changed = retag(dir, |t| (!is_within(t, tag)).then(|| t.to_string()))?;
+    info!(event = "DeleteTag", tag = %tag, documents = changed);
+    Ok(changed)
+}
+
+/// Tags every one of `filenames` has, sorted.
+pub fn common(catalog: &Catalog, filenames: &[String]) -> Vec<String> {
+    let mut tagged = filenames.iter().map(|filename| {
+        catalog
+            .get(filename)
+            .map(|e| e.tags.clone())
+            .unwrap_or_default()
+    });
+    let mut common = tagged.next().unwrap_or_default();
+    for tags in tagged {
+        common.retain(|tag| tags.contains(tag));
+    }
+    common.sort();
+    common
+}
+
+/// Adds `add` to and removes `remove` from the tags of `filenames` of the cabinet at `dir`,
+/// ignoring case. Returns the edit for `undo`.
+pub fn edit(
+    dir: &Path,
+    filenames: &[String],
+    add: &[String],
+    remove: &[String],
+) -> Result<TagEdit, CatalogError> {
+    let mut catalog = Catalog::load(dir);
+    let mut before = BTreeMap::new();
+    for filename in filenames {
+        let entry = catalog.entry(filename);
+        before.insert(filename.clone(), entry.tags.clone());
+        entry
+            .tags
+            .retain(|tag| !remove.iter().any(|r| r.eq_ignore_ascii_case(tag)));
+        merge(&mut entry.tags, add);
+    }
+    catalog.save(dir)?;
+    events::emit(dir, &tagged(&catalog, filenames));
+    info!(event = "TagEdit", documents = filenames.len(), added = ?add, removed = ?remove);
+    Ok(TagEdit {
+        before,
+        added: add.to_vec(),
+        removed: remove.to_vec(),
+    })
+}
+
+/// The tags `filenames` have now, for the event stream.
+fn tagged(catalog: &Catalog, filenames: &[String]) -> Vec<Event> {
+    filenames
+        .iter()
+        .map(|filename| Event::Tagged {
+            filename: filename.clone(),
+            tags: catalog
+                .get(filename)
+                .map(|entry| entry.tags.clone())
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Puts back the tags the documents of `edit` had before it.
+pub fn undo(dir: &Path, edit: &TagEdit) -> Result<(), CatalogError> {
+    let mut catalog = Catalog::load(dir);
+    for (filename, tags) in &edit.before {
+        catalog.entry(filename).tags = tags.clone();
+    }
+    catalog.save(dir)?;
+    let filenames: Vec<String> = edit.before.keys().cloned().collect();
+    events::emit(dir, &tagged(&catalog, &filenames));
+    info!(event = "UndoTagEdit", documents = edit.len());
+    Ok(())
+}
+
+#[test]
+fn test_edit_and_undo() {
+    let dir = tempdir::TempDir::new("tags").unwrap();
+    let dir = dir.path();
+    let files = vec!["a.pdf".to_string(), "b.pdf".to_string()];
+    let mut catalog = Catalog::default();
+    catalog.entry("a.pdf").tags = vec!["tax".to_string(), "urgent".to_string()];
+    catalog.entry("b.pdf").tags = vec!["tax".to_string()];
+    catalog.save(dir).unwrap();
+    assert_eq!(common(&catalog, &files), vec!["tax"]);
+    assert_eq!(
+        parse(" Tax,  home  office ,tax,,"),
+        vec!["Tax", "home office"]
+    );
+
+    let edit = edit(dir, &files, &parse("Tax, 2021"), &["urgent".to_string()]).unwrap();
+    let catalog = Catalog::load(dir);
+    assert_eq!(catalog.get("a.pdf").unwrap().tags, vec!["2021", "tax"]);
+    assert_eq!(catalog.get("b.pdf").unwrap().tags, vec!["2021", "tax"]);
+
+    undo(dir, &edit).unwrap();
+    let catalog = Catalog::load(dir);
+    assert_eq!(catalog.get("a.pdf").unwrap().tags, vec!["tax", "urgent"]);
+    assert_eq!(catalog.get("b.pdf").unwrap().tags, vec!["tax"]);
+}
+
+#[test]
+fn test_tree() {
+    let dir = tempdir::TempDir::new("tags").unwrap();
+    let dir = dir.path();
+    let mut catalog = Catalog::default();
+    catalog.entry("a.pdf").tags = parse("finance/tax/2021, finance/tax/2022");
+    catalog.entry("b.pdf").tags = parse("finance / bank, finance-old");
+    catalog.entry("c.pdf").tags = parse("taxes/2021");
+    catalog.save(dir).unwrap();
+    assert_eq!(
+        tree(&catalog),
+        vec![
+            ("finance".to_string(), 2),
+            ("finance/bank".to_string(), 1),
+            ("finance/tax".to_string(), 1),
+            ("finance/tax/2021".to_string(), 1),
+            ("finance/tax/2022".to_string(), 1),
+            ("finance-old".to_string(), 1),
+            ("taxes".to_string(), 1),
+            ("taxes/2021".to_string(), 1),
+        ]
+    );
+
+    // Merges into the tax folder, `finance-old` isn't below `finance`.
+    assert_eq!(rename(dir, "taxes", "finance/tax").unwrap(), 1);
+    assert_eq!(
+        rename(dir, "finance/tax/2022", "finance/tax/2021").unwrap(),
+        1
+    );
+    let catalog = Catalog::load(dir);
+    assert_eq!(catalog.get("a.pdf").unwrap().tags, vec!["finance/tax/2021"]);
+    assert_eq!(catalog.get("c.pdf").unwrap().tags, vec!["finance/tax/2021"]);
+
+    assert_eq!(delete(dir, "finance").unwrap(), 3);
+    let catalog = Catalog::load(dir);
+    assert!(catalog.get("a.pdf").unwrap().tags.is_empty());
+    assert!(catalog.get("c.pdf").unwrap().tags.is_empty());
+    assert_eq!(catalog.get("b.pdf").unwrap().tags, vec!["finance-old"]);
+}
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::thread;
+
+/// An external program documents can be opened with, e.g. GIMP for PNGs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExternalTool {
+    pub name: String,
+    /// Comma separated extensions it handles, e.g. `png, jpg`.
+    pub extensions: String,
+    /// Program and arguments, with `{}` standing for the file. Quotes group words as in
+    /// a shell, but no shell is involved, so the path is always a single argument.
+    pub command: String,
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum ToolError {
+    ParseError,
+    LaunchError,
+}
+
+impl ExternalTool {
+    pub fn handles(&self, extension: &str) -> bool {
+        !self.name.trim().is_empty()
+            && self
+                .extensions
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(extension))
+    }
+
+    /// The program and its arguments for `path`, which is appended if the command has
+    /// no `{}`.
+    fn args(&self, path: &str) -> Result<Vec<String>, ToolError> {
+        command_args(&self.command, path).ok_or(ToolError::ParseError)
+    }
+
+    /// Starts the tool on `path` without waiting for it to exit.
+    pub fn launch(&self, path: &str) -> Result<(), ToolError> {
+        let args = self.args(path)?;
+        spawn(Command::new(&args[0]).args(&args[1..])).map(|_| ())
+    }
+}
+
+/// Starts `command` without waiting for it to exit, returns its process id. A thread
+/// waits for it instead, or once done it would linger as a zombie process until the app
+/// exits.
+fn spawn(command: &mut Command) -> Result<u32, ToolError> {
+    let mut child = command.spawn().map_err(|_| ToolError::LaunchError)?;
+    let id = child.id();
+    thread::spawn(move || child.wait());
+    Ok(id)
+}
+
+/// Opens `path` with the program the desktop opens its kind of file with.
+pub fn open_default(path: &str) -> Result<(), ToolError> {
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    // Not `cmd /C start`, cmd would run what follows an `&` or `|` in the filename.
+    #[cfg(target_os = "windows")]
+    let mut command = Command::new("explorer");
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut command = Command::new("xdg-open");
+    spawn(command.arg(path)).map(|_| ())
+}
+
+/// The program and arguments of `command` run on `path`, with `{}` standing for it, or
+/// else `path` appended. `None` if there is no program.
+pub fn command_args(command: &str, path: &str) -> Option<Vec<String>> {
+    let words = split(command)?;
+    if words.is_empty() {
+        return None;
+    }
+    let mut args: Vec<String> = words.iter().map(|w| w.replace("{}", path)).collect();
+    if !words.iter().any(|w| w.contains("{}")) {
+        args.push(path.to_string());
+    }
+    Some(args)
+}
+
+/// Splits a command line into words: single quotes keep everything literally, double
+/// quotes and backslashes escape as in a POSIX shell. `None` for unterminated quotes.
+fn split(command: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => word.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            c @ ('"' | '\\' | '$' | '`') => word.push(c),
+                            c => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                        },
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => word.get_or_insert_with(String::new).push(chars.next()?),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Some(words)
+}
+
+#[test]
+fn test_args() {
+    let tool = |command: &str| ExternalTool {
+        name: "Tool".to_string(),
+        extensions: "pdf".to_string(),
+        command: command.to_string(),
+    };
+    let path = "/docs/2021-01-01_Bank_Statement \"$(rm -rf ~)\"_1.pdf";
+    assert_eq!(
+        tool("okular").args(path).unwrap(),
+        vec!["okular".to_string(), path.to_string()]
+    );
+    assert_eq!(
+        tool(r#"'/opt/My Viewer/view' --title "A \"B\"" --file={} ''"#)
+            .args(path)
+            .unwrap(),
+        vec![
+            "/opt/My Viewer/view".to_string(),
+            "--title".to_string(),
+            "A \"B\"".to_string(),
+            format!("--file={}", path),
+            String::new(),
+        ]
+    );
+    assert!(tool("gimp 'unterminated").args(path).is_err());
+    assert!(tool("  ").args(path).is_err());
+    assert!(tool("okular").handles("pdf"));
+    assert!(!tool("okular").handles("png"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_spawn_reaps() {
+    let id = spawn(&mut Command::new("true")).unwrap();
+    // Until it is waited for, a child that exited stays listed as a zombie.
+    let listed = std::path::Path::new("/proc").join(id.to_string());
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while listed.exists() && std::time::Instant::now() < deadline {
+        thread::sleep(std::time::Duration::from_millis(20));
+    }
+    assert!(!listed.exists());
+}
@@ -0,0 +1,148 @@
+use crate::catalog::Catalog;
+use crate::stats::Totals;
+use crate::storage;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum TrashError {
+    NotFoundError,
+    WriteError,
+}
+
+/// Where deleted documents of the cabinet at `dir` wait to be purged, in
+/// `<cabinet>/.filecabinet/trash`.
+pub fn dir(dir: &Path) -> PathBuf {
+    Catalog::path(dir).with_file_name("trash")
+}
+
+/// Seconds since the epoch, which prefix the documents in the trash.
+fn seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+/// When the document of the trash named `filename` was deleted.
+fn deleted(filename: &str) -> Option<SystemTime> {
+    let (seconds, _) = filename.split_once('_')?;
+    Some(UNIX_EPOCH + Duration::from_secs(seconds.parse().ok()?))
+}
+
+/// Moves the document at `path` into the trash of its cabinet, its name prefixed with
+/// when it was deleted so it can be purged after the retention. Returns where it went.
+pub fn trash(path: &Path, now: SystemTime) -> Result<PathBuf, TrashError> {
+    let cabinet = path.parent().ok_or(TrashError::NotFoundError)?;
+    let filename = path.file_name().ok_or(TrashError::NotFoundError)?;
+    let trash = self::dir(cabinet);
+    // The browser build keeps files in memory, there is no folder to create there.
+    let _ = fs::create_dir_all(&trash);
+    let target = trash.join(format!("{}_{}", seconds(now), filename.to_string_lossy()));
+    storage::backend()
+        .rename(path, &target)
+        .map_err(|_| TrashError::WriteError)?;
+    Ok(target)
+}
+
+/// The documents in the trash of the cabinet at `dir`, with when they were deleted.
+fn list(dir: &Path) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let trash = self::dir(dir);
+    storage::backend()
+        .list(&trash)
+        .into_iter()
+        .map(|filename| (trash.join(&filename), deleted(&filename)))
+        .collect()
+}
+
+fn size(path: &Path) -> u64 {
+    fs::metadata(path).map_or(0, |metadata| metadata.len())
+}
+
+/// How many documents the trash of the cabinet at `dir` holds and their size.
+pub fn totals(dir: &Path) -> Totals {
+    list(dir)
+        .iter()
+        .fold(Totals::default(), |totals, (path, _)| Totals {
+            files: totals.files + 1,
+            bytes: totals.bytes + size(path),
+        })
+}
+
+/// Removes the documents of the trash of the cabinet at `dir` deleted before `cutoff`,
+/// all of them without one. Returns what was removed.
+fn remove(dir: &Path, cutoff: Option<SystemTime>) -> Totals {
+    let mut removed = Totals::default();
+    for (path, deleted) in list(dir) {
+        // Files without a time weren't put there by the app, they are left alone.
+        let expired = match (cutoff, deleted) {
+            (None, _) => true,
+            (Some(cutoff), Some(deleted)) => deleted < cutoff,
+            (Some(_), None) => false,
+        };
+        let bytes = size(&path);
+        if expired && storage::backend().remove(&path).is_ok() {
+            removed.files += 1;
+            removed.bytes += bytes;
+        }
+    }
+    removed
+}
+
+/// Removes the documents deleted more than `days` ago from the trash of the cabinet at
+/// `dir`, none for 0 days.
+pub fn purge(dir: &Path, days: u32, now: SystemTime) -> Totals {
+    if days == 0 {
+        return Totals::default();
+    }
+    let retention = Duration::from_secs(24 * 60 * 60 * u64::from(days));
+    remove(dir, now.checked_sub(retention))
+}
+
+pub async fn purge_expired(dir: String, days: u32) -> Result<Totals, TrashError> {
+    let purged = purge(Path::new(&dir), days, SystemTime::now());
+    if purged.files > 0 {
+        info!(event = "PurgeTrash", dir = %dir, files = purged.files, bytes = purged.bytes);
+    }
+    Ok(purged)
+}
+
+/// Removes every document of the trash of the cabinet at `dir`.
+pub async fn empty(dir: String) -> Result<Totals, TrashError> {
+    let emptied = remove(Path::new(&dir), None);
+    info!(event = "EmptyTrash", dir = %dir, files = emptied.files, bytes = emptied.bytes);
+    match totals(Path::new(&dir)).files {
+        0 => Ok(emptied),
+        _ => Err(TrashError::WriteError),
+    }
+}
+
+#[test]
+fn test_trash() {
+    let cabinet = tempdir::TempDir::new("trash").unwrap();
+    let cabinet = cabinet.path();
+    let day = Duration::from_secs(24 * 60 * 60);
+    let now = UNIX_EPOCH + 100 * day;
+    for filename in ["old.pdf", "new.pdf"] {
+        fs::write(cabinet.join(filename), b"document").unwrap();
+    }
+    let old = trash(&cabinet.join("old.pdf"), now - 40 * day).unwrap();
+    trash(&cabinet.join("new.pdf"), now - day).unwrap();
+    assert!(old.starts_with(dir(cabinet)));
+    assert!(!cabinet.join("old.pdf").exists());
+    fs::write(dir(cabinet).join("notes.txt"), b"mine").unwrap();
+    assert_eq!(
+        totals(cabinet),
+        Totals {
+            files: 3,
+            bytes: 20
+        }
+    );
+
+    assert_eq!(purge(cabinet, 0, now), Totals::default());
+    assert_eq!(purge(cabinet, 30, now), Totals { files: 1, bytes: 8 });
+    assert!(!old.exists());
+    assert_eq!(remove(cabinet, None).files, 2);
+    assert_eq!(totals(cabinet), Totals::default());
+}
@@ -0,0 +1,402 @@
+use crate::utils;
+use iced_native::futures::channel::mpsc::{self, UnboundedSender};
+use iced_native::futures::stream::{BoxStream, StreamExt};
+use iced_native::subscription::Recipe;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+use tracing::{info, warn};
+use zbus::dbus_interface;
+use zbus::zvariant::{ObjectPath, OwnedValue, Structure, Value};
+
+const ITEM_PATH: &str = "/StatusNotifierItem";
+const MENU_PATH: &str = "/MenuBar";
+
+/// Ids of the entries of the tray menu, 0 is the menu itself.
+const QUICK_ADD: i32 = 1;
+const TOGGLE_WINDOW: i32 = 2;
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum TrayError {
+    LaunchError,
+    WriteError,
+}
+
+/// What was picked from the tray icon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayAction {
+    QuickAdd,
+    ToggleWindow,
+}
+
+/// An icon in the system tray of desktops implementing the StatusNotifierItem protocol,
+/// KDE and most others, GNOME with the AppIndicator extension. A click shows or hides the
+/// window, its menu also offers to add a document.
+pub struct Tray;
+
+impl<H, E> Recipe<H, E> for Tray
+where
+    H: std::hash::Hasher,
+{
+    type Output = TrayAction;
+
+    fn hash(&self, state: &mut H) {
+        use std::hash::Hash;
+        struct Marker;
+        std::any::TypeId::of::<Marker>().hash(state);
+    }
+
+    fn stream(self: Box<Self>, _input: BoxStream<'static, E>) -> BoxStream<'static, TrayAction> {
+        let (sender, receiver) = mpsc::unbounded();
+        thread::spawn(move || {
+            let connection = match register(sender.clone()) {
+                Ok(connection) => connection,
+                Err(error) => {
+                    warn!(event = "tray_start_failed", ?error);
+                    return;
+                }
+            };
+            info!(event = "TrayStart");
+            // The icon goes away with the connection, once the subscription ended.
+            while !sender.is_closed() {
+                thread::sleep(Duration::from_secs(1));
+            }
+            drop(connection);
+        });
+        receiver.boxed()
+    }
+}
+
+/// Serves the icon and its menu on the session bus and announces them to the tray.
+fn register(sender: UnboundedSender<TrayAction>) -> zbus::Result<zbus::blocking::Connection> {
+    let name = format!("org.kde.StatusNotifierItem-{}-1", std::process::id());
+    let connection = zbus::blocking::ConnectionBuilder::session()?
+        .name(name.as_str())?
+        .serve_at(
+            ITEM_PATH,
+            Item {
+                sender: sender.clone(),
+            },
+        )?
+        .serve_at(MENU_PATH, Menu { sender })?
+        .build()?;
+    connection.call_method(
+        Some("org.kde.StatusNotifierWatcher"),
+        "/StatusNotifierWatcher",
+        Some("org.kde.StatusNotifierWatcher"),
+        "RegisterStatusNotifierItem",
+        &(name.as_str(),),
+    )?;
+    Ok(connection)
+}
+
+struct Item {
+    sender: UnboundedSender<TrayAction>,
+}
+
+#[dbus_interface(name = "org.kde.StatusNotifierItem")]
+impl Item {
+    fn activate(&self, _x: i32, _y: i32) {
+        let _ = self.sender.unbounded_send(TrayAction::ToggleWindow);
+    }
+
+    fn secondary_activate(&self, _x: i32, _y: i32) {
+        let _ = self.sender.unbounded_send(TrayAction::QuickAdd);
+    }
+
+    /// The tray shows the menu at `Menu` itself.
+    fn context_menu(&self, _x: i32, _y: i32) {}
+
+    fn scroll(&self, _delta: i32, _orientation: &str) {}
+
+    #[dbus_interface(property, name = "Category")]
+    fn category(&self) -> &str {
+        "ApplicationStatus"
+    }
+
+    #[dbus_interface(property, name = "Id")]
+    fn id(&self) -> &str {
+        "filecabinet"
+    }
+
+    #[dbus_interface(property, name = "Title")]
+    fn title(&self) -> &str {
+        "Filecabinet"
+    }
+
+    #[dbus_interface(property, name = "Status")]
+    fn status(&self) -> &str {
+        "Active"
+    }
+
+    #[dbus_interface(property, name = "IconName")]
+    fn icon_name(&self) -> &str {
+        "folder-documents"
+    }
+
+    #[dbus_interface(property, name = "ItemIsMenu")]
+    fn item_is_menu(&self) -> bool {
+        false
+    }
+
+    #[dbus_interface(property, name = "Menu")]
+    fn menu(&self) -> ObjectPath<'_> {
+        ObjectPath::from_static_str_unchecked(MENU_PATH)
+    }
+}
+
+/// A menu entry: its id, properties and entries.
+type Layout = (i32, HashMap<String, OwnedValue>, Vec<OwnedValue>);
+
+fn entry_properties(id: i32) -> HashMap<String, OwnedValue> {
+    let mut properties = HashMap::new();
+    let label = match id {
+        QUICK_ADD => "Quick add document…",
+        TOGGLE_WINDOW => "Show or hide the window",
+        _ => {
+            properties.insert(
+                "children-display".to_string(),
+                Value::from("submenu").into(),
+            );
+            return properties;
+        }
+    };
+    properties.insert("label".to_string(), Value::from(label).into());
+    properties
+}
+
+fn layout() -> Layout {
+    let entries = [QUICK_ADD, TOGGLE_WINDOW]
+        .iter()
+        .map(|id| {
+            let entry = Structure::from((*id, entry_properties(*id), Vec::<OwnedValue>::new()));
+            Value::from(entry).into()
+        })
+        .collect();
+    (0, entry_properties(0), entries)
+}
+
+/// The menu of the icon, in the `com.canonical.dbusmenu` protocol trays use. It never
+/// changes, so the revision stays at 1.
+struct Menu {
+    sender: UnboundedSender<TrayAction>,
+}
+
+#[dbus_interface(name = "com.canonical.dbusmenu")]
+impl Menu {
+    fn get_layout(
+        &self,
+        _parent_id: i32,
+        _recursion_depth: i32,
+        _property_names: Vec<String>,
+    ) -> (u32, Layout) {
+        (1, layout())
+    }
+
+    fn get_group_properties(
+        &self,
+        ids: Vec<i32>,
+        _property_names: Vec<String>,
+    ) -> Vec<(i32, HashMap<String, OwnedValue>)> {
+        ids.into_iter()
+            .map(|id| (id, entry_properties(id)))
+            .collect()
+    }
+
+    fn get_property(&self, id: i32, name: &str) -> OwnedValue {
+        entry_properties(id)
+            .remove(name)
+            .unwrap_or_else(|| Value::from("").into())
+    }
+
+    fn event(&self, id: i32, event_id: &str, _data: OwnedValue, _timestamp: u32) {
+        if event_id != "clicked" {
+            return;
+        }
+        let action = match id {
+            QUICK_ADD => TrayAction::QuickAdd,
+            TOGGLE_WINDOW => TrayAction::ToggleWindow,
+            _ => return,
+        };
+        let _ = self.sender.unbounded_send(action);
+    }
+
+    fn event_group(&self, events: Vec<(i32, String, OwnedValue, u32)>) -> Vec<i32> {
+        for (id, event_id, data, timestamp) in events {
+            self.event(id, &event_id, data, timestamp);
+        }
+        Vec::new()
+    }
+
+    fn about_to_show(&self, _id: i32) -> bool {
+        false
+    }
+
+    fn about_to_show_group(&self, _ids: Vec<i32>) -> (Vec<i32>, Vec<i32>) {
+        (Vec::new(), Vec::new())
+    }
+
+    #[dbus_interface(property, name = "Version")]
+    fn version(&self) -> u32 {
+        3
+    }
+
+    #[dbus_interface(property, name = "TextDirection")]
+    fn text_direction(&self) -> &str {
+        "ltr"
+    }
+
+    #[dbus_interface(property, name = "Status")]
+    fn status(&self) -> &str {
+        "normal"
+    }
+
+    #[dbus_interface(property, name = "IconThemePath")]
+    fn icon_theme_path(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Hides the window of the app, or brings it back. The window has to be unmapped for it
+/// to leave the taskbar, which iced can't do, so this goes through `xdotool` and only
+/// works on X11.
+pub fn toggle_window() {
+    let visible = windows(true);
+    let (windows, actions): (Vec<String>, &[&str]) = if visible.is_empty() {
+        (windows(false), &["windowmap", "windowactivate"])
+    } else {
+        (visible, &["windowunmap"])
+    };
+    act(&windows, actions);
+    info!(event = "ToggleWindow", windows = windows.len());
+}
+
+/// Brings the window of the app to the front, hidden or not, e.g. when a script asked it
+/// for a document.
+pub fn show_window() {
+    let windows = windows(false);
+    act(&windows, &["windowmap", "windowactivate"]);
+    info!(event = "ShowWindow", windows = windows.len());
+}
+
+/// The X11 windows of the app, only the visible ones if `visible`.
+fn windows(visible: bool) -> Vec<String> {
+    let pid = std::process::id().to_string();
+    let mut command = Command::new("xdotool");
+    command.arg("search");
+    if visible {
+        command.arg("--onlyvisible");
+    }
+    command
+        .args(["--pid", &pid])
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .split_whitespace()
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn act(windows: &[String], actions: &[&str]) {
+    for window in windows {
+        for action in actions {
+            if let Err(error) = Command::new("xdotool")
+                .args([*action, window.as_str()])
+                .status()
+            {
+                warn!(event = "toggle_window_failed", ?error);
+                return;
+            }
+        }
+    }
+}
+
+/// Asks for documents with the file picker of the desktop, `zenity` or `kdialog`.
+/// Returns nothing when the picker was closed.
+fn pick() -> Result<Vec<String>, TrayError> {
+    let pickers: [(&str, &[&str]); 2] = [
+        (
+            "zenity",
+            &[
+                "--file-selection",
+                "--multiple",
+                "--separator=\n",
+                "--title=Add to the cabinet",
+            ],
+        ),
+        (
+            "kdialog",
+            &[
+                "--getopenfilename",
+                ".",
+                "--multiple",
+                "--separate-output",
+                "--title",
+                "Add to the cabinet",
+            ],
+        ),
+    ];
+    for (program, args) in pickers.iter() {
+        if let Ok(output) = Command::new(program).args(args.iter()).output() {
+            return Ok(String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(str::to_string)
+                .collect());
+        }
+    }
+    Err(TrayError::LaunchError)
+}
+
+/// Copies the documents at `paths` into `dir`, keeping their names unless taken. Returns
+/// where they went.
+fn copy_into(paths: &[String], dir: &Path) -> Result<Vec<String>, TrayError> {
+    let mut added = Vec::with_capacity(paths.len());
+    for path in paths {
+        let filename = Path::new(path)
+            .file_name()
+            .ok_or(TrayError::WriteError)?
+            .to_string_lossy();
+        let destination = utils::free_path(dir, &filename);
+        fs::copy(path, &destination).map_err(|_| TrayError::WriteError)?;
+        added.push(destination.to_string_lossy().to_string());
+    }
+    Ok(added)
+}
+
+/// Lets the user pick documents and copies them into `dir`, the intake folder.
+pub async fn quick_add(dir: String) -> Result<Vec<String>, TrayError> {
+    let picked = pick()?;
+    let added = copy_into(&picked, Path::new(&dir))?;
+    info!(event = "QuickAdd", dir = %dir, documents = added.len());
+    Ok(added)
+}
+
+#[test]
+fn test_tray() {
+    let (id, properties, entries) = layout();
+    assert_eq!(id, 0);
+    assert!(properties.contains_key("children-display"));
+    assert_eq!(entries.len(), 2);
+    assert_eq!(
+        entry_properties(QUICK_ADD).get("label"),
+        Some(&Value::from("Quick add document…").into())
+    );
+
+    let dir = tempdir::TempDir::new("tray").unwrap();
+    let source = dir.path().join("scan.pdf");
+    fs::write(&source, b"scan").unwrap();
+    let inbox = dir.path().join("inbox");
+    fs::create_dir(&inbox).unwrap();
+    let source = source.to_string_lossy().to_string();
+    let added = copy_into(&[source.clone(), source], &inbox).unwrap();
+    assert_eq!(added.len(), 2);
+    assert_ne!(added[0], added[1]);
+    assert!(added.iter().all(|path| Path::new(path).is_file()));
+}
@@ -0,0 +1,217 @@
+use crate::catalog::Catalog;
+use crate::utils;
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use cocoon::Cocoon;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Known plaintext sealed with the vault passphrase, to tell whether a passphrase is right.
+const MARKER: &[u8] = b"filecabinet vault";
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum VaultError {
+    DirectoryError,
+    EncryptError,
+    WriteError,
+    ReadError,
+    /// The old passphrase doesn't open the vault, or the new one isn't the one an
+    /// unfinished change was started with.
+    PassphraseError,
+    DecryptError,
+    /// A re-encrypted container didn't read back as the original.
+    VerifyError,
+}
+
+/// The vault of a cabinet is set up in `<cabinet>/.filecabinet/vault.cocoon`.
+pub fn path(dir: &Path) -> PathBuf {
+    dir.join(".filecabinet").join("vault.cocoon")
+}
+
+/// The new passphrase is sealed here while the containers are re-encrypted, so that an
+/// interrupted change can only be resumed with the same one.
+fn next_path(dir: &Path) -> PathBuf {
+    dir.join(".filecabinet").join("vault.next.cocoon")
+}
+
+pub fn exists(dir: &Path) -> bool {
+    path(dir).exists()
+}
+
+fn seal(content: &[u8], passphrase: &str) -> Result<Vec<u8>, VaultError> {
+    Cocoon::new(passphrase.as_bytes())
+        .wrap(content)
+        .map_err(|_| VaultError::EncryptError)
+}
+
+fn open(sealed: &[u8], passphrase: &str) -> Option<Vec<u8>> {
+    Cocoon::new(passphrase.as_bytes()).unwrap(sealed).ok()
+}
+
+fn write(path: &Path, content: &[u8]) -> Result<(), VaultError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|_| VaultError::DirectoryError)?;
+    }
+    AtomicFile::new(path, OverwriteBehavior::AllowOverwrite)
+        .write(|f| f.write_all(content))
+        .map_err(|_| VaultError::WriteError)
+}
+
+fn unlocks(path: &Path, passphrase: &str) -> bool {
+    fs::read(path)
+        .ok()
+        .and_then(|sealed| open(&sealed, passphrase))
+        .is_some_and(|marker| marker == MARKER)
+}
+
+/// Whether `passphrase` opens the vault of the cabinet at `dir`.
+pub fn check(dir: &Path, passphrase: &str) -> bool {
+    unlocks(&path(dir), passphrase)
+}
+
+/// Sets up the vault of the cabinet at `dir` with `passphrase`.
+pub fn create(dir: &Path, passphrase: &str) -> Result<(), VaultError> {
+    write(&path(dir), &seal(MARKER, passphrase)?)
+}
+
+/// Re-encrypts the `.cocoon` containers of the cabinet at `dir` from `old` to `new`, one
+/// at a time, and switches the vault over once all of them are done. Running it again
+/// after an interruption skips the containers `new` already opens. Returns how many
+/// containers were re-encrypted.
+pub fn change_passphrase(dir: &Path, old: &str, new: &str) -> Result<usize, VaultError> {
+    let next = next_path(dir);
+    let resuming = next.exists();
+    if resuming && !unlocks(&next, new) {
+        return Err(VaultError::PassphraseError);
+    }
+    // The vault already opens with the new passphrase if only removing the marker of the
+    // change was left to do.
+    let unlocked = unlocks(&path(dir), old) || resuming && unlocks(&path(dir), new);
+    if !unlocked {
+        return Err(VaultError::PassphraseError);
+    }
+    if !resuming {
+        write(&next, &seal(MARKER, new)?)?;
+    }
+    let catalog = Catalog::load(dir);
+    let mut changed = 0;
+    for filename in utils::list_files(dir) {
+        let own_passphrase = catalog
+            .get(&filename)
+            .is_some_and(|entry| entry.own_passphrase);
+        if !filename.ends_with(".cocoon") || own_passphrase {
+            continue;
+        }
+        let path = dir.join(&filename);
+        let sealed = fs::read(&path).map_err(|_| VaultError::ReadError)?;
+        if open(&sealed, new).is_some() {
+            continue;
+        }
+        let content = open(&sealed, old).ok_or(VaultError::DecryptError)?;
+        let resealed = seal(&content, new)?;
+        // Only replace the container with one that opens to the same content, and check
+        // that it made it to the disk.
+        if open(&resealed, new).as_deref() != Some(&content[..]) {
+            return Err(VaultError::VerifyError);
+        }
+        write(&path, &resealed)?;
+        if fs::read(&path).map_err(|_| VaultError::ReadError)? != resealed {
+            return Err(VaultError::VerifyError);
+        }
+        changed += 1;
+        info!(event = "ChangePassphrase", file = %path.display());
+    }
+    write(&self::path(dir), &seal(MARKER, new)?)?;
+    fs::remove_file(&next).map_err(|_| VaultError::WriteError)?;
+    Ok(changed)
+}
+
+/// The content of the `.cocoon` document at `path`.
+pub fn open_document(path: &Path, passphrase: &str) -> Result<Vec<u8>, VaultError> {
+    let sealed = fs::read(path).map_err(|_| VaultError::ReadError)?;
+    open(&sealed, passphrase).ok_or(VaultError::DecryptError)
+}
+
+/// Seals the document `filename` of the cabinet at `dir` into `<filename>.cocoon` with a
+/// passphrase of its own, which changing the vault passphrase leaves alone. The plain
+/// file is only removed once the container reads back. Returns the new filename.
+pub fn seal_document(dir: &Path, filename: &str, passphrase: &str) -> Result<String, VaultError> {
+    let sealed_filename = format!("{}.cocoon", filename);
+    let (path, sealed_path) = (dir.join(filename), dir.join(&sealed_filename));
+    if filename.ends_with(".cocoon") || sealed_path.exists() {
+        return Err(VaultError::WriteError);
+    }
+    let content = fs::read(&path).map_err(|_| VaultError::ReadError)?;
+    let sealed = seal(&content, passphrase)?;
+    if open(&sealed, passphrase).as_deref() != Some(&content[..]) {
+        return Err(VaultError::VerifyError);
+    }
+    write(&sealed_path, &sealed)?;
+    if fs::read(&sealed_path).map_err(|_| VaultError::ReadError)? != sealed {
+        return Err(VaultError::VerifyError);
+    }
+    let mut catalog = Catalog::load(dir);
+    catalog.rename(filename, &sealed_filename);
+    catalog.entry(&sealed_filename).own_passphrase = true;
+    catalog.save(dir).map_err(|_| VaultError::WriteError)?;
+    fs::remove_file(&path).map_err(|_| VaultError::WriteError)?;
+    Ok(sealed_filename)
+}
+
+pub async fn seal_with_own(path: String, passphrase: String) -> Result<String, VaultError> {
+    let path = Path::new(&path);
+    let (dir, filename) = path
+        .parent()
+        .zip(path.file_name().and_then(|f| f.to_str()))
+        .ok_or(VaultError::ReadError)?;
+    let sealed = seal_document(dir, filename, &passphrase)?;
+    info!(event = "Seal", file = %path.display(), sealed = %sealed);
+    Ok(dir.join(sealed).to_string_lossy().to_string())
+}
+
+pub async fn rotate(dir: String, old: String, new: String) -> Result<usize, VaultError> {
+    let changed = change_passphrase(Path::new(&dir), &old, &new)?;
+    info!(event = "PassphraseChanged", dir = %dir, changed);
+    Ok(changed)
+}
+
+#[test]
+fn test_create() {
+    let dir = tempdir::TempDir::new("vault").unwrap();
+    create(dir.path(), "correct horse").unwrap();
+    let sealed = fs::read(path(dir.path())).unwrap();
+    assert_eq!(
+        Cocoon::new(b"correct horse").unwrap(&sealed).unwrap(),
+        MARKER
+    );
+    assert!(Cocoon::new(b"wrong horse").unwrap(&sealed).is_err());
+}
+
+#[test]
+fn test_change_passphrase() {
+    let dir = tempdir::TempDir::new("vault").unwrap();
+    let dir = dir.path();
+    create(dir, "old horse").unwrap();
+    let (a, b) = (dir.join("a.pdf.cocoon"), dir.join("b.pdf.cocoon"));
+    fs::write(&a, seal(b"a", "old horse").unwrap()).unwrap();
+    // Left off after this one when the change was interrupted.
+    fs::write(&b, seal(b"b", "new horse").unwrap()).unwrap();
+    write(&next_path(dir), &seal(MARKER, "new horse").unwrap()).unwrap();
+    fs::write(dir.join("will.pdf"), b"will").unwrap();
+    let will = seal_document(dir, "will.pdf", "own horse").unwrap();
+    assert!(!dir.join("will.pdf").exists());
+    assert!(Catalog::load(dir).get(&will).unwrap().own_passphrase);
+
+    assert!(change_passphrase(dir, "old horse", "other horse").is_err());
+    assert!(change_passphrase(dir, "wrong horse", "new horse").is_err());
+    assert_eq!(change_passphrase(dir, "old horse", "new horse").unwrap(), 1);
+    assert_eq!(open(&fs::read(&a).unwrap(), "new horse").unwrap(), b"a");
+    assert!(unlocks(&path(dir), "new horse"));
+    assert_eq!(
+        open_document(&dir.join(will), "own horse").unwrap(),
+        b"will"
+    );
+    assert!(!next_path(dir).exists());
+}